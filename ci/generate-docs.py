@@ -465,8 +465,10 @@ TOC = [
             Gen("shelldone cli", "cli/cli"),
             Page("shelldone connect", "cli/connect.md"),
             Page("shelldone imgcat", "cli/imgcat.md"),
+            Page("shelldone layout", "cli/layout.md"),
             Page("shelldone ls-fonts", "cli/ls-fonts.md"),
             Page("shelldone record", "cli/record.md"),
+            Page("shelldone render", "cli/render.md"),
             Page("shelldone replay", "cli/replay.md"),
             Page("shelldone serial", "cli/serial.md"),
             Page("shelldone set-working-directory", "cli/set-working-directory.md"),
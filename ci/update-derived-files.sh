@@ -27,7 +27,7 @@ trim_file() {
 
 cargo run --example narrow $PWD/target/debug/shelldone --help | ./target/debug/strip-ansi-escapes | trim_file > docs/examples/cmd-synopsis-shelldone--help.txt
 
-for cmd in start ssh serial connect ls-fonts show-keys imgcat set-working-directory record replay  ; do
+for cmd in start ssh serial connect ls-fonts show-keys render imgcat set-working-directory record replay layout  ; do
   fname="docs/examples/cmd-synopsis-shelldone-${cmd}--help.txt"
   cargo run --example narrow $PWD/target/debug/shelldone $cmd --help | ./target/debug/strip-ansi-escapes | trim_file > $fname
 done
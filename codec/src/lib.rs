@@ -13,8 +13,10 @@
 
 use anyhow::{bail, Context as _, Error};
 use config::keyassignment::{PaneDirection, ScrollbackEraseMode};
-use mux::client::{ClientId, ClientInfo};
+use mux::client::{ClientId, ClientInfo, ClientViewport, GuestPresence};
+use mux::monitor::PaneMonitors;
 use mux::pane::PaneId;
+use mux::recording::{RecordingEvent, RecordingOptions};
 use mux::renderable::{RenderableDimensions, StableCursorPosition};
 use mux::tab::{PaneNode, SerdeUrl, SplitRequest, TabId};
 use mux::window::WindowId;
@@ -22,7 +24,7 @@ use portable_pty::CommandBuilder;
 use rangeset::*;
 use serde::{Deserialize, Serialize};
 use shelldone_term::color::ColorPalette;
-use shelldone_term::{Alert, ClipboardSelection, StableRowIndex, TerminalSize};
+use shelldone_term::{Alert, ClipboardSelection, CommandBlock, StableRowIndex, TerminalSize};
 use smol::io::AsyncWriteExt;
 use smol::prelude::*;
 use std::collections::HashMap;
@@ -465,7 +467,7 @@ macro_rules! pdu {
 /// The overall version of the codec.
 /// This must be bumped when backwards incompatible changes
 /// are made to the types and protocol.
-pub const CODEC_VERSION: usize = 45;
+pub const CODEC_VERSION: usize = 58;
 
 // Defines the Pdu enum.
 // Each struct has an explicit identifying number.
@@ -526,6 +528,20 @@ pdu! {
     GetPaneDirection: 60,
     GetPaneDirectionResponse: 61,
     AdjustPaneSize: 62,
+    SwapPanes: 63,
+    MoveTabToWindow: 64,
+    MoveTabToWindowResponse: 65,
+    SshPortForwards: 66,
+    SshPortForwardsResponse: 67,
+    SetPaneMonitors: 68,
+    SetPaneRecording: 69,
+    PaneRecordingOutput: 70,
+    CreateShare: 71,
+    CreateShareResponse: 72,
+    ListShares: 73,
+    ListSharesResponse: 74,
+    UpdateShare: 75,
+    SetClientViewport: 76,
 }
 
 impl Pdu {
@@ -618,6 +634,7 @@ impl Pdu {
             Pdu::SetPalette(payload) => Some(payload.pane_id),
             Pdu::NotifyAlert(NotifyAlert { pane_id, .. })
             | Pdu::SetClipboard(SetClipboard { pane_id, .. })
+            | Pdu::PaneRecordingOutput(PaneRecordingOutput { pane_id, .. })
             | Pdu::PaneFocused(PaneFocused { pane_id })
             | Pdu::PaneRemoved(PaneRemoved { pane_id }) => Some(*pane_id),
             _ => None,
@@ -698,6 +715,61 @@ pub struct MovePaneToNewTabResponse {
     pub window_id: WindowId,
 }
 
+#[derive(Deserialize, Serialize, PartialEq, Debug)]
+pub struct SwapPanes {
+    pub pane_id: PaneId,
+    pub other_pane_id: PaneId,
+}
+
+#[derive(Deserialize, Serialize, PartialEq, Debug)]
+pub struct MoveTabToWindow {
+    pub tab_id: TabId,
+    /// If None, move the tab into a new window
+    pub window_id: Option<WindowId>,
+    pub workspace_for_new_window: Option<String>,
+}
+
+#[derive(Deserialize, Serialize, PartialEq, Debug)]
+pub struct MoveTabToWindowResponse {
+    pub window_id: WindowId,
+}
+
+#[derive(Deserialize, Serialize, PartialEq, Debug)]
+pub enum SshPortForwardAction {
+    List,
+    /// `kind` is one of Local, Remote or Dynamic and `spec`
+    /// uses the same syntax as `ssh -L`, `-R` or `-D`
+    Add {
+        kind: String,
+        spec: String,
+    },
+    Remove {
+        id: usize,
+    },
+}
+
+/// Manages the port forwards of the named ssh domain.
+/// The response lists the forwards that are active once
+/// the action has been performed.
+#[derive(Deserialize, Serialize, PartialEq, Debug)]
+pub struct SshPortForwards {
+    pub domain: String,
+    pub action: SshPortForwardAction,
+}
+
+#[derive(Deserialize, Serialize, PartialEq, Debug)]
+pub struct SshPortForwardEntry {
+    pub id: usize,
+    pub description: String,
+    pub bound_port: u16,
+    pub connections: usize,
+}
+
+#[derive(Deserialize, Serialize, PartialEq, Debug)]
+pub struct SshPortForwardsResponse {
+    pub forwards: Vec<SshPortForwardEntry>,
+}
+
 #[derive(Deserialize, Serialize, PartialEq, Debug)]
 pub struct SpawnV2 {
     pub domain: config::keyassignment::SpawnTabDomain,
@@ -737,6 +809,9 @@ pub struct WriteToPane {
 pub struct SendPaste {
     pub pane_id: PaneId,
     pub data: String,
+    /// Echoed back in the render changes that follow the paste,
+    /// so that the client knows when the paste has been processed
+    pub input_serial: InputSerial,
 }
 
 #[derive(Deserialize, Serialize, PartialEq, Debug)]
@@ -887,6 +962,107 @@ pub struct SetPaneZoomed {
     pub zoomed: bool,
 }
 
+/// Replaces the activity and silence monitors of a pane
+#[derive(Deserialize, Serialize, PartialEq, Debug)]
+pub struct SetPaneMonitors {
+    pub pane_id: PaneId,
+    pub monitors: PaneMonitors,
+}
+
+/// Starts recording a pane when `options` is set, otherwise stops
+/// recording it.  The events of the recording are streamed back to
+/// the client that started it via `PaneRecordingOutput`.
+#[derive(Deserialize, Serialize, PartialEq, Debug)]
+pub struct SetPaneRecording {
+    pub pane_id: PaneId,
+    pub options: Option<RecordingOptions>,
+}
+
+#[derive(Deserialize, Serialize, PartialEq, Debug)]
+pub struct PaneRecordingOutput {
+    pub pane_id: PaneId,
+    pub events: Vec<RecordingEvent>,
+}
+
+/// What a share lets its guests see
+#[derive(Deserialize, Serialize, PartialEq, Eq, Debug, Clone, Copy)]
+pub enum ShareScope {
+    Pane(PaneId),
+    Tab(TabId),
+}
+
+#[derive(Deserialize, Serialize, PartialEq, Eq, Debug, Clone, Copy)]
+pub enum ShareAccess {
+    /// Guests can only watch
+    ReadOnly,
+    /// Guests can also send input to the shared panes
+    Interactive,
+}
+
+#[derive(Deserialize, Serialize, PartialEq, Debug, Clone)]
+pub struct ShareInfo {
+    pub share_id: String,
+    pub scope: ShareScope,
+    pub access: ShareAccess,
+    pub label: Option<String>,
+    /// Seconds since the unix epoch
+    pub created_at: u64,
+    /// Seconds since the unix epoch
+    pub expires_at: u64,
+    /// The number of guests that are connected
+    pub guests: usize,
+}
+
+/// Issues a credential with which a guest can connect to a
+/// `tls_servers` listener and see the pane or tab of `scope`.
+/// The credential expires after `ttl_secs`.
+#[derive(Deserialize, Serialize, PartialEq, Debug)]
+pub struct CreateShare {
+    pub scope: ShareScope,
+    pub access: ShareAccess,
+    pub ttl_secs: u64,
+    pub label: Option<String>,
+}
+
+#[derive(Deserialize, Serialize, PartialEq, Debug)]
+pub struct CreateShareResponse {
+    pub share: ShareInfo,
+    /// The signing certificate
+    pub ca_cert_pem: String,
+    /// The client certificate and private key of the guest,
+    /// PEM encoded
+    pub client_cert_pem: String,
+}
+
+#[derive(Deserialize, Serialize, PartialEq, Debug)]
+pub struct ListShares {}
+
+#[derive(Deserialize, Serialize, PartialEq, Debug)]
+pub struct ListSharesResponse {
+    pub shares: Vec<ShareInfo>,
+}
+
+#[derive(Deserialize, Serialize, PartialEq, Debug)]
+pub enum UpdateShareAction {
+    SetAccess(ShareAccess),
+    /// Invalidates the credential and disconnects its guests
+    Revoke,
+}
+
+/// Changes or revokes a share.  The response lists the shares
+/// that remain once the action has been performed.
+#[derive(Deserialize, Serialize, PartialEq, Debug)]
+pub struct UpdateShare {
+    pub share_id: String,
+    pub action: UpdateShareAction,
+}
+
+/// Advises the server of the viewport of the client
+#[derive(Deserialize, Serialize, PartialEq, Debug)]
+pub struct SetClientViewport {
+    pub viewport: Option<ClientViewport>,
+}
+
 #[derive(Deserialize, Serialize, PartialEq, Debug)]
 pub struct GetPaneDirection {
     pub pane_id: PaneId,
@@ -949,6 +1125,16 @@ pub struct GetPaneRenderChangesResponse {
 
     pub input_serial: Option<InputSerial>,
     pub seqno: SequenceNo,
+    /// The guests of shares that are looking at the pane
+    pub guests: Vec<GuestPresence>,
+    /// Whether the alternate screen is active
+    pub alt_screen_active: bool,
+    /// Whether the pty looks like it is reading a password; echo
+    /// is disabled while canonical input mode is enabled
+    pub password_input: bool,
+    /// The OSC 133 command blocks of the pane, or None if they
+    /// haven't changed since the previous response
+    pub command_blocks: Option<Vec<CommandBlock>>,
 }
 
 #[derive(Deserialize, Serialize, PartialEq, Debug)]
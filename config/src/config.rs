@@ -17,6 +17,7 @@ use crate::keys::{Key, LeaderKey, Mouse};
 use crate::lua::make_lua_context;
 use crate::ssh::{SshBackend, SshDomain};
 use crate::tls::{TlsDomainClient, TlsDomainServer};
+use crate::trigger::Trigger;
 use crate::units::Dimension;
 use crate::unix::UnixDomain;
 use crate::wsl::WslDomain;
@@ -182,6 +183,46 @@ pub struct Config {
     #[dynamic(default = "default_pane_select_bg_color")]
     pub pane_select_bg_color: RgbaColor,
 
+    /// The color of the outline drawn around panes whose input
+    /// is being broadcast to other panes
+    #[dynamic(default = "default_input_broadcast_border_color")]
+    pub input_broadcast_border_color: RgbaColor,
+
+    /// Whether to decorate the commands that were run at a shell
+    /// prompt with a marker in the gutter and their duration.
+    /// Requires shell integration that emits OSC 133.
+    #[dynamic(default = "default_true")]
+    pub show_command_blocks: bool,
+
+    /// The color of the gutter marker of a command that succeeded
+    #[dynamic(default = "default_command_block_success_color")]
+    pub command_block_success_color: RgbaColor,
+
+    /// The color of the gutter marker of a command that failed
+    #[dynamic(default = "default_command_block_failure_color")]
+    pub command_block_failure_color: RgbaColor,
+
+    /// The color of the gutter marker of a command that is still
+    /// running, or that didn't report its exit status
+    #[dynamic(default = "default_command_block_running_color")]
+    pub command_block_running_color: RgbaColor,
+
+    /// Whether the commands that finish in local panes are recorded
+    /// into the command history.  Requires shell integration that
+    /// emits OSC 133.
+    #[dynamic(default = "default_true")]
+    pub enable_command_history: bool,
+
+    /// The maximum number of commands that are kept in the
+    /// command history; the oldest are discarded first
+    #[dynamic(default = "default_command_history_max_entries")]
+    pub command_history_max_entries: usize,
+
+    /// Whether the commands that are recorded into the command history
+    /// are also posted to the journal of agentd
+    #[dynamic(default)]
+    pub command_history_share_with_agentd: bool,
+
     #[dynamic(default)]
     pub tab_bar_style: TabBarStyle,
 
@@ -203,6 +244,16 @@ pub struct Config {
     )]
     pub scrollback_lines: usize,
 
+    /// How many lines that have scrolled beyond `scrollback_lines`
+    /// to keep compressed on disk.  0 disables the disk tier.
+    #[dynamic(default)]
+    pub scrollback_spill_lines: usize,
+
+    /// Where to keep the per-pane scrollback spill files.
+    /// Defaults to a `scrollback` directory in the cache dir.
+    #[dynamic(default)]
+    pub scrollback_spill_dir: Option<PathBuf>,
+
     /// If no `prog` is specified on the command line, use this
     /// instead of running the user's shell.
     /// For example, to have `shelldone` always run `top` by default,
@@ -267,6 +318,11 @@ pub struct Config {
     #[dynamic(default = "default_hyperlink_rules")]
     pub hyperlink_rules: Vec<hyperlink::Rule>,
 
+    /// Rules that are evaluated against each new line of output
+    /// in local panes.  Panes may override these via `pane:set_triggers`.
+    #[dynamic(default)]
+    pub triggers: Vec<Trigger>,
+
     /// What to set the TERM variable to
     #[dynamic(default = "default_term")]
     pub term: String,
@@ -408,6 +464,20 @@ pub struct Config {
     #[dynamic(default = "default_mux_env_remove")]
     pub mux_env_remove: Vec<String>,
 
+    /// When true, the mux server periodically saves its windows, tabs
+    /// and split layouts and restores them the next time it starts.
+    #[dynamic(default)]
+    pub mux_session_persistence: bool,
+
+    /// How often, in seconds, the mux session snapshot is refreshed
+    #[dynamic(default = "default_mux_session_save_interval_secs")]
+    pub mux_session_save_interval_secs: u64,
+
+    /// How many lines of each pane's scrollback to include in the
+    /// mux session snapshot. 0 saves the layout only.
+    #[dynamic(default)]
+    pub mux_session_scrollback_lines: usize,
+
     #[dynamic(default)]
     pub keys: Vec<Key>,
     #[dynamic(default)]
@@ -712,6 +782,11 @@ pub struct Config {
     #[dynamic(default)]
     pub notification_handling: NotificationHandling,
 
+    /// Whether applications may read the clipboard using
+    /// the kitty OSC 5522 clipboard protocol
+    #[dynamic(default)]
+    pub clipboard_read_access: ClipboardReadAccess,
+
     #[dynamic(default = "default_true")]
     pub use_dead_keys: bool,
 
@@ -836,6 +911,32 @@ pub struct Config {
     #[dynamic(default)]
     pub audible_bell: AudibleBell,
 
+    /// Whether to alert when a pane that is not focused produces output.
+    /// Can be changed for an individual pane at runtime.
+    #[dynamic(default)]
+    pub monitor_activity: bool,
+
+    /// When set, alert when a pane has produced no output for this
+    /// many seconds.  Can be changed for an individual pane at runtime.
+    #[dynamic(default)]
+    pub monitor_silence_secs: Option<u64>,
+
+    /// Whether the activity and silence monitor alerts are also shown
+    /// as a toast notification, subject to `notification_handling`
+    #[dynamic(default = "default_true")]
+    pub monitor_alert_toast_notification: bool,
+
+    /// The directory in which pane recordings are saved when no path
+    /// is given.  Defaults to the `recordings` directory inside the
+    /// data directory.
+    #[dynamic(default)]
+    pub pane_recording_dir: Option<PathBuf>,
+
+    /// Whether pane recordings include the input that is sent to
+    /// the pane, such as key presses and pastes
+    #[dynamic(default)]
+    pub pane_recording_input: bool,
+
     #[dynamic(default)]
     pub canonicalize_pasted_newlines: Option<NewlineCanon>,
 
@@ -1618,6 +1719,26 @@ fn default_pane_select_bg_color() -> RgbaColor {
     SrgbaTuple(0., 0., 0., 0.5).into()
 }
 
+fn default_input_broadcast_border_color() -> RgbaColor {
+    SrgbaTuple(0.9, 0.75, 0.3, 1.0).into()
+}
+
+fn default_command_block_success_color() -> RgbaColor {
+    SrgbaTuple(0.3, 0.75, 0.4, 1.0).into()
+}
+
+fn default_command_block_failure_color() -> RgbaColor {
+    SrgbaTuple(0.9, 0.3, 0.3, 1.0).into()
+}
+
+fn default_command_block_running_color() -> RgbaColor {
+    SrgbaTuple(0.5, 0.5, 0.55, 1.0).into()
+}
+
+fn default_command_history_max_entries() -> usize {
+    10_000
+}
+
 fn default_pane_select_font_size() -> f64 {
     36.0
 }
@@ -1792,6 +1913,10 @@ fn default_unicode_version() -> u8 {
     9
 }
 
+fn default_mux_session_save_interval_secs() -> u64 {
+    30
+}
+
 fn default_mux_env_remove() -> Vec<String> {
     vec![
         "SSH_AUTH_SOCK".to_string(),
@@ -2155,6 +2280,15 @@ pub enum NotificationHandling {
     SuppressFromFocusedWindow,
 }
 
+#[derive(Debug, FromDynamic, ToDynamic, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ClipboardReadAccess {
+    /// Prompt the user each time an application reads the clipboard
+    #[default]
+    Ask,
+    Allow,
+    Deny,
+}
+
 fn validate_row_or_col(value: &u16) -> Result<(), String> {
     if *value < 1 {
         Err("initial_cols and initial_rows must be non-zero".to_string())
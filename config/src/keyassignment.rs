@@ -301,6 +301,17 @@ pub enum ClipboardCopyDestination {
 }
 impl_lua_conversion_dynamic!(ClipboardCopyDestination);
 
+/// Which part of a command block to act upon
+#[derive(Debug, Clone, Copy, PartialEq, Eq, FromDynamic, ToDynamic, Default)]
+pub enum CommandBlockPart {
+    /// The command line that was entered at the prompt
+    Command,
+    /// The output that was produced by the command
+    #[default]
+    Output,
+}
+impl_lua_conversion_dynamic!(CommandBlockPart);
+
 #[derive(Debug, Clone, Copy, PartialEq, Eq, FromDynamic, ToDynamic, Default)]
 pub enum ClipboardPasteSource {
     #[default]
@@ -534,6 +545,16 @@ pub enum KeyAssignment {
     ScrollByLine(isize),
     ScrollByCurrentEventWheelDelta,
     ScrollToPrompt(isize),
+    ActivateCommandBlockRelative(isize),
+    CopyCommandBlockTo {
+        #[dynamic(default)]
+        part: CommandBlockPart,
+        #[dynamic(default)]
+        destination: ClipboardCopyDestination,
+    },
+    RerunCommandBlock,
+    ToggleCommandBlockCollapsed,
+    ShowCommandHistory,
     ScrollToTop,
     ScrollToBottom,
     ShowTabNavigator,
@@ -612,6 +633,14 @@ pub enum KeyAssignment {
     PromptInputLine(PromptInputLine),
     InputSelector(InputSelector),
     Confirmation(Confirmation),
+    ToggleBroadcastInput(BroadcastInputScope),
+    TogglePaneBroadcastMark,
+    TogglePaneActivityMonitor,
+    TogglePaneSilenceMonitor(u64),
+    TogglePaneRecording,
+    ShowPlaybackControls,
+    JoinPane(JoinPaneArguments),
+    MoveTabToWindow(MoveTabToWindowArguments),
 }
 impl_lua_conversion_dynamic!(KeyAssignment);
 
@@ -626,6 +655,32 @@ pub struct SplitPane {
     pub top_level: bool,
 }
 
+#[derive(Debug, Clone, PartialEq, FromDynamic, ToDynamic)]
+pub struct JoinPaneArguments {
+    pub direction: PaneDirection,
+    #[dynamic(default)]
+    pub size: SplitSize,
+    #[dynamic(default)]
+    pub top_level: bool,
+    /// The index of the destination tab in the current window.
+    /// Negative values count back from the last tab.
+    /// If omitted, the most recently active tab is used.
+    #[dynamic(default)]
+    pub tab_index: Option<isize>,
+}
+
+#[derive(Default, Debug, Clone, PartialEq, Eq, FromDynamic, ToDynamic)]
+pub struct MoveTabToWindowArguments {
+    /// The index of the destination GUI window, in the same order
+    /// as used by `ActivateWindow`.  If omitted, a new window is created.
+    #[dynamic(default)]
+    pub window_index: Option<usize>,
+    /// The workspace to use when creating a new window.
+    /// If omitted, the workspace of the current window is used.
+    #[dynamic(default)]
+    pub workspace: Option<String>,
+}
+
 #[derive(Debug, Clone, PartialEq, Eq, FromDynamic, ToDynamic)]
 pub enum SplitSize {
     Cells(usize),
@@ -644,6 +699,19 @@ pub enum RotationDirection {
     CounterClockwise,
 }
 
+/// Selects the set of panes that receive a copy of the input typed
+/// into any one of them when input broadcast is toggled on
+#[derive(Debug, Clone, Copy, PartialEq, Eq, FromDynamic, ToDynamic, Default)]
+pub enum BroadcastInputScope {
+    /// Every pane in the active tab
+    #[default]
+    CurrentTab,
+    /// Every pane in every tab of the active workspace
+    CurrentWorkspace,
+    /// The panes marked via `TogglePaneBroadcastMark`
+    MarkedPanes,
+}
+
 #[derive(Debug, Clone, PartialEq, Eq, FromDynamic, ToDynamic)]
 pub enum CopyModeAssignment {
     MoveToViewportBottom,
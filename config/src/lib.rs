@@ -38,6 +38,7 @@ mod serial;
 mod ssh;
 mod terminal;
 mod tls;
+mod trigger;
 mod units;
 mod unix;
 mod version;
@@ -58,6 +59,7 @@ pub use serial::*;
 pub use ssh::*;
 pub use terminal::*;
 pub use tls::*;
+pub use trigger::*;
 pub use units::*;
 pub use unix::*;
 pub use version::*;
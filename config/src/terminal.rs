@@ -3,6 +3,7 @@
 use crate::{configuration, ConfigHandle, NewlineCanon};
 use shelldone_term::color::ColorPalette;
 use shelldone_term::config::BidiMode;
+use std::path::PathBuf;
 use std::sync::Mutex;
 use termwiz::cell::UnicodeVersion;
 
@@ -58,6 +59,17 @@ impl shelldone_term::TerminalConfiguration for TermConfig {
         self.configuration().scrollback_lines
     }
 
+    fn scrollback_spill_size(&self) -> usize {
+        self.configuration().scrollback_spill_lines
+    }
+
+    fn scrollback_spill_dir(&self) -> PathBuf {
+        self.configuration()
+            .scrollback_spill_dir
+            .clone()
+            .unwrap_or_else(|| crate::CACHE_DIR.join("scrollback"))
+    }
+
     fn enable_csi_u_key_encoding(&self) -> bool {
         self.configuration().enable_csi_u_key_encoding
     }
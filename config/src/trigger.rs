@@ -0,0 +1,114 @@
+use crate::color::ColorSpec;
+use luahelper::impl_lua_conversion_dynamic;
+use regex::Regex;
+use shelldone_dynamic::{FromDynamic, FromDynamicOptions, ToDynamic, Value};
+
+/// A rule that is evaluated against each line of output that
+/// is produced by a local pane, in the spirit of iTerm2 triggers.
+/// The regex is evaluated against the text of the logical line,
+/// so matches are found even when the line wraps.
+#[derive(Debug, Clone, FromDynamic, ToDynamic)]
+pub struct Trigger {
+    /// The regex syntax guarantees matching in linear time, which
+    /// bounds the cost of evaluating a trigger against a line.
+    #[dynamic(into = "RegexWrap", try_from = "RegexWrap")]
+    pub regex: Regex,
+    pub action: TriggerAction,
+    /// When set, the trigger only applies to the panes of the
+    /// domain with this name
+    #[dynamic(default)]
+    pub domain: Option<String>,
+}
+impl_lua_conversion_dynamic!(Trigger);
+
+/// What to do when a trigger matches a line.
+/// In the strings of the actions, `$N` (where N is a number) is
+/// replaced by capture number N of the regex, so `$0` expands to
+/// the whole of the matched text.
+#[derive(Debug, Clone, FromDynamic, ToDynamic)]
+pub enum TriggerAction {
+    /// Change the colors of the matched text
+    Highlight {
+        #[dynamic(default)]
+        foreground: Option<ColorSpec>,
+        #[dynamic(default)]
+        background: Option<ColorSpec>,
+    },
+    /// Show a toast notification
+    Notify {
+        #[dynamic(default)]
+        title: Option<String>,
+        #[dynamic(default = "default_notify_body")]
+        body: String,
+    },
+    /// Set a user var on the pane, as though the application had
+    /// set it via OSC 1337
+    SetUserVar {
+        name: String,
+        #[dynamic(default = "default_user_var_value")]
+        value: String,
+    },
+    /// Emit the named event, passing the window, the pane, the text
+    /// of the line and the captures of the regex to its handlers
+    EmitEvent(String),
+    /// Send the text to the pane, as though it had been typed
+    SendText(String),
+    /// Post an event of the specified kind to the journal of agentd
+    Journal {
+        #[dynamic(default = "default_journal_kind")]
+        kind: String,
+    },
+}
+
+fn default_notify_body() -> String {
+    "$0".to_string()
+}
+
+fn default_user_var_value() -> String {
+    "$0".to_string()
+}
+
+fn default_journal_kind() -> String {
+    "trigger.match".to_string()
+}
+
+/// Expands the `$N` references in `format` to the corresponding
+/// `captures`.  The replacements are carried out starting with the
+/// highest numbered capture, to avoid ambiguity between `$1` and `$11`.
+pub fn expand_trigger_captures(format: &str, captures: &[String]) -> String {
+    let mut result = format.to_string();
+    for (n, capture) in captures.iter().enumerate().rev() {
+        result = result.replace(&format!("${n}"), capture);
+    }
+    result
+}
+
+struct RegexWrap(Regex);
+
+impl FromDynamic for RegexWrap {
+    fn from_dynamic(
+        value: &Value,
+        options: FromDynamicOptions,
+    ) -> Result<RegexWrap, shelldone_dynamic::Error> {
+        let s = String::from_dynamic(value, options)?;
+        Ok(RegexWrap(Regex::new(&s).map_err(|e| e.to_string())?))
+    }
+}
+
+impl From<&Regex> for RegexWrap {
+    fn from(regex: &Regex) -> RegexWrap {
+        RegexWrap(regex.clone())
+    }
+}
+
+impl From<RegexWrap> for Regex {
+    fn from(val: RegexWrap) -> Self {
+        val.0
+    }
+}
+
+impl ToDynamic for RegexWrap {
+    fn to_dynamic(&self) -> Value {
+        self.0.to_string().to_dynamic()
+    }
+}
@@ -1,6 +1,8 @@
 use super::*;
 use mlua::UserDataRef;
 use mux::domain::{Domain, DomainId, DomainState};
+use mux::ssh::RemoteSshDomain;
+use shelldone_ssh::{ForwardId, ForwardInfo, ForwardKind, ForwardSpec};
 use std::sync::Arc;
 
 #[derive(Clone, Copy, Debug)]
@@ -13,6 +15,50 @@ impl MuxDomain {
     }
 }
 
+fn as_ssh_domain(domain: &Arc<dyn Domain>) -> mlua::Result<&RemoteSshDomain> {
+    domain.downcast_ref::<RemoteSshDomain>().ok_or_else(|| {
+        mlua::Error::external(format!(
+            "domain {} is not an ssh domain",
+            domain.domain_name()
+        ))
+    })
+}
+
+#[derive(Clone, FromDynamic, ToDynamic)]
+struct SshPortForward {
+    /// Used to remove the forward via `domain:remove_port_forward`
+    pub id: ForwardId,
+    /// One of "Local", "Remote" or "Dynamic"
+    pub kind: String,
+    pub listen: String,
+    /// Where connections are made to; not set for dynamic forwards
+    pub connect: Option<String>,
+    pub bound_port: u16,
+    pub connections: usize,
+    pub description: String,
+}
+impl_lua_conversion_dynamic!(SshPortForward);
+
+impl From<ForwardInfo> for SshPortForward {
+    fn from(info: ForwardInfo) -> Self {
+        let connect = match &info.spec {
+            ForwardSpec::Local { connect, .. } | ForwardSpec::Remote { connect, .. } => {
+                Some(connect.to_string())
+            }
+            ForwardSpec::Dynamic { .. } => None,
+        };
+        Self {
+            id: info.id,
+            kind: format!("{:?}", info.spec.kind()),
+            listen: info.spec.listen().to_string(),
+            connect,
+            bound_port: info.bound_port,
+            connections: info.connections,
+            description: info.to_string(),
+        }
+    }
+}
+
 impl UserData for MuxDomain {
     fn add_methods<'lua, M: UserDataMethods<'lua, Self>>(methods: &mut M) {
         methods.add_meta_method(mlua::MetaMethod::ToString, |_, this, _: ()| {
@@ -74,6 +120,46 @@ impl UserData for MuxDomain {
             Ok(domain.domain_label().await)
         });
 
+        methods.add_async_method("port_forwards", |_, this, _: ()| async move {
+            let mux = get_mux()?;
+            let domain = this.resolve(&mux)?;
+            let forwards = as_ssh_domain(&domain)?
+                .port_forwards()
+                .await
+                .map_err(|e| mlua::Error::external(format!("{:#}", e)))?;
+            Ok(forwards
+                .into_iter()
+                .map(SshPortForward::from)
+                .collect::<Vec<_>>())
+        });
+
+        methods.add_async_method(
+            "add_port_forward",
+            |_, this, (kind, spec): (String, String)| async move {
+                let mux = get_mux()?;
+                let domain = this.resolve(&mux)?;
+                let kind: ForwardKind = kind
+                    .parse()
+                    .map_err(|e| mlua::Error::external(format!("{:#}", e)))?;
+                let spec = ForwardSpec::from_ssh_arg(kind, &spec)
+                    .map_err(|e| mlua::Error::external(format!("{:#}", e)))?;
+                let info = as_ssh_domain(&domain)?
+                    .add_port_forward(spec)
+                    .await
+                    .map_err(|e| mlua::Error::external(format!("{:#}", e)))?;
+                Ok(SshPortForward::from(info))
+            },
+        );
+
+        methods.add_async_method("remove_port_forward", |_, this, id: ForwardId| async move {
+            let mux = get_mux()?;
+            let domain = this.resolve(&mux)?;
+            as_ssh_domain(&domain)?
+                .remove_port_forward(id)
+                .await
+                .map_err(|e| mlua::Error::external(format!("{:#}", e)))
+        });
+
         methods.add_method("has_any_panes", |_, this, _: ()| {
             let mux = get_mux()?;
             let domain = this.resolve(&mux)?;
@@ -1,8 +1,9 @@
 use config::keyassignment::SpawnTabDomain;
 use config::lua::mlua::{self, Lua, UserData, UserDataMethods, Value as LuaValue};
 use config::lua::{get_or_create_module, get_or_create_sub_module};
-use luahelper::impl_lua_conversion_dynamic;
+use luahelper::{from_lua, impl_lua_conversion_dynamic, to_lua};
 use mlua::UserDataRef;
+use mux::command_history::{default_history_path, CommandHistory, CommandHistoryQuery};
 use mux::domain::{DomainId, SplitSource};
 use mux::pane::{Pane, PaneId};
 use mux::tab::{SplitDirection, SplitRequest, SplitSize, Tab, TabId};
@@ -169,6 +170,19 @@ pub fn register(lua: &Lua) -> anyhow::Result<()> {
         })?,
     )?;
 
+    mux_mod.set(
+        "get_command_history",
+        lua.create_function(|lua, query: Option<LuaValue>| {
+            let query: CommandHistoryQuery = match query {
+                Some(query) => from_lua(query)?,
+                None => CommandHistoryQuery::default(),
+            };
+            let history =
+                CommandHistory::load(&default_history_path()).map_err(mlua::Error::external)?;
+            to_lua(lua, history.search(&query))
+        })?,
+    )?;
+
     Ok(())
 }
 
@@ -197,7 +211,7 @@ impl CommandBuilderFrag {
     }
 }
 
-#[derive(Debug, FromDynamic, ToDynamic, Default)]
+#[derive(Debug, Clone, Copy, FromDynamic, ToDynamic, Default)]
 enum HandySplitDirection {
     Left,
     #[default]
@@ -3,8 +3,10 @@ use luahelper::mlua::LuaSerdeExt;
 use luahelper::{dynamic_to_lua_value, from_lua, to_lua};
 use mlua::Value;
 use mux::pane::CachePolicy;
+use mux::recording::RecordingOptions;
 use shelldone_term::{SemanticZone, StableRowIndex};
 use std::cmp::Ordering;
+use std::path::PathBuf;
 use std::sync::Arc;
 use termwiz::cell::SemanticType;
 use termwiz_funcs::lines_to_escapes;
@@ -198,6 +200,48 @@ impl UserData for MuxPane {
             Ok(pane.copy_user_vars())
         });
 
+        methods.add_method(
+            "set_triggers",
+            |_, this, triggers: Option<Vec<config::Trigger>>| {
+                let mux = get_mux()?;
+                let pane = this.resolve(&mux)?;
+                mux::trigger::set_pane_triggers(pane.pane_id(), triggers);
+                Ok(())
+            },
+        );
+
+        methods.add_method(
+            "start_recording",
+            |_, this, args: Option<StartRecording>| {
+                let mux = get_mux()?;
+                let pane = this.resolve(&mux)?;
+                let args = args.unwrap_or_default();
+                let path = args
+                    .path
+                    .unwrap_or_else(|| mux::recording::default_recording_path(pane.pane_id()));
+                let options = RecordingOptions {
+                    input: args
+                        .input
+                        .unwrap_or_else(|| RecordingOptions::from_config().input),
+                };
+                mux::recording::record_pane_to_file(&pane, &path, options)
+                    .map_err(|e| mlua::Error::external(format!("{:#}", e)))?;
+                Ok(path.to_string_lossy().to_string())
+            },
+        );
+
+        methods.add_method("stop_recording", |_, this, _: ()| {
+            let mux = get_mux()?;
+            let pane = this.resolve(&mux)?;
+            Ok(pane.stop_recording())
+        });
+
+        methods.add_method("is_recording", |_, this, _: ()| {
+            let mux = get_mux()?;
+            let pane = this.resolve(&mux)?;
+            Ok(pane.is_recording())
+        });
+
         methods.add_method("has_unseen_output", |_, this, _: ()| {
             let mux = get_mux()?;
             let pane = this.resolve(&mux)?;
@@ -361,6 +405,12 @@ impl UserData for MuxPane {
             },
         );
 
+        methods.add_method("get_command_blocks", |lua, this, _: ()| {
+            let mux = get_mux()?;
+            let pane = this.resolve(&mux)?;
+            to_lua(lua, pane.get_command_blocks())
+        });
+
         methods.add_method("get_text_from_semantic_zone", |_lua, this, zone: Value| {
             let zone: SemanticZone = from_lua(zone)?;
             this.get_text_from_semantic_zone(zone)
@@ -404,6 +454,30 @@ impl UserData for MuxPane {
             },
         );
 
+        methods.add_async_method(
+            "move_to_tab",
+            |_lua, this, (tab, args): (UserDataRef<MuxTab>, Option<MoveToTab>)| async move {
+                let args = args.unwrap_or_default();
+                let request = split_request(args.direction, args.size, false);
+                let mux = Mux::get();
+                mux.join_pane(this.0, tab.0, request)
+                    .await
+                    .map_err(|e| mlua::Error::external(format!("{:#?}", e)))?;
+                Ok(())
+            },
+        );
+
+        methods.add_async_method(
+            "swap_with",
+            |_lua, this, other: UserDataRef<MuxPane>| async move {
+                let mux = Mux::get();
+                mux.swap_panes(this.0, other.0)
+                    .await
+                    .map_err(|e| mlua::Error::external(format!("{:#?}", e)))?;
+                Ok(())
+            },
+        );
+
         methods.add_method("activate", move |_lua, this, ()| {
             let mux = Mux::get();
             let pane = this.resolve(&mux)?;
@@ -455,6 +529,29 @@ fn default_split_size() -> f32 {
     0.5
 }
 
+fn split_request(direction: HandySplitDirection, size: f32, top_level: bool) -> SplitRequest {
+    let size = if size == 0.0 {
+        SplitSize::Percent(50)
+    } else if size < 1.0 {
+        SplitSize::Percent((size * 100.).floor() as u8)
+    } else {
+        SplitSize::Cells(size as usize)
+    };
+
+    SplitRequest {
+        direction: match direction {
+            HandySplitDirection::Right | HandySplitDirection::Left => SplitDirection::Horizontal,
+            HandySplitDirection::Top | HandySplitDirection::Bottom => SplitDirection::Vertical,
+        },
+        target_is_second: match direction {
+            HandySplitDirection::Top | HandySplitDirection::Left => false,
+            HandySplitDirection::Bottom | HandySplitDirection::Right => true,
+        },
+        top_level,
+        size,
+    }
+}
+
 impl SplitPane {
     async fn run(&self, pane: &MuxPane) -> mlua::Result<MuxPane> {
         let (command, command_dir) = self.cmd_builder.to_command_builder();
@@ -463,28 +560,7 @@ impl SplitPane {
             command_dir,
         };
 
-        let size = if self.size == 0.0 {
-            SplitSize::Percent(50)
-        } else if self.size < 1.0 {
-            SplitSize::Percent((self.size * 100.).floor() as u8)
-        } else {
-            SplitSize::Cells(self.size as usize)
-        };
-
-        let direction = match self.direction {
-            HandySplitDirection::Right | HandySplitDirection::Left => SplitDirection::Horizontal,
-            HandySplitDirection::Top | HandySplitDirection::Bottom => SplitDirection::Vertical,
-        };
-
-        let request = SplitRequest {
-            direction,
-            target_is_second: match self.direction {
-                HandySplitDirection::Top | HandySplitDirection::Left => false,
-                HandySplitDirection::Bottom | HandySplitDirection::Right => true,
-            },
-            top_level: self.top_level,
-            size,
-        };
+        let request = split_request(self.direction, self.size, self.top_level);
 
         let mux = get_mux()?;
         let (pane, _size) = mux
@@ -495,3 +571,21 @@ impl SplitPane {
         Ok(MuxPane(pane.pane_id()))
     }
 }
+
+#[derive(Debug, Default, FromDynamic, ToDynamic)]
+struct StartRecording {
+    #[dynamic(default)]
+    path: Option<PathBuf>,
+    #[dynamic(default)]
+    input: Option<bool>,
+}
+impl_lua_conversion_dynamic!(StartRecording);
+
+#[derive(Debug, Default, FromDynamic, ToDynamic)]
+struct MoveToTab {
+    #[dynamic(default)]
+    direction: HandySplitDirection,
+    #[dynamic(default = "default_split_size")]
+    size: f32,
+}
+impl_lua_conversion_dynamic!(MoveToTab);
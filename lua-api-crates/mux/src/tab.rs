@@ -122,6 +122,30 @@ impl UserData for MuxTab {
             Ok(())
         });
 
+        methods.add_async_method(
+            "move_to_window",
+            |_, this, window: UserDataRef<MuxWindow>| async move {
+                let mux = get_mux()?;
+                let window_id = mux
+                    .move_tab_to_window(this.0, Some(window.0), None)
+                    .await
+                    .map_err(|e| mlua::Error::external(format!("{:#?}", e)))?;
+                Ok(MuxWindow(window_id))
+            },
+        );
+
+        methods.add_async_method(
+            "move_to_new_window",
+            |_, this, workspace: Option<String>| async move {
+                let mux = get_mux()?;
+                let window_id = mux
+                    .move_tab_to_window(this.0, None, workspace)
+                    .await
+                    .map_err(|e| mlua::Error::external(format!("{:#?}", e)))?;
+                Ok(MuxWindow(window_id))
+            },
+        );
+
         methods.add_method("get_size", |lua, this, _: ()| {
             let mux = get_mux()?;
             let tab = this.resolve(&mux)?;
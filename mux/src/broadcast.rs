@@ -0,0 +1,141 @@
+//! Keeps track of which panes have their input broadcast to their peers
+use crate::pane::PaneId;
+use crate::tab::TabId;
+use std::collections::HashSet;
+
+/// A group of panes that can have input broadcast toggled on as a unit
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum BroadcastScope {
+    /// Every pane in the tab
+    Tab(TabId),
+    /// Every pane in every window of the named workspace
+    Workspace(String),
+    /// The panes that were explicitly marked for broadcast
+    Marked,
+}
+
+/// The set of broadcast scopes that are currently enabled,
+/// along with the panes that have been marked for broadcast.
+#[derive(Debug, Default, Clone)]
+pub struct InputBroadcast {
+    tabs: HashSet<TabId>,
+    workspaces: HashSet<String>,
+    marked: HashSet<PaneId>,
+    marked_enabled: bool,
+}
+
+impl InputBroadcast {
+    /// Returns true if nothing is being broadcast
+    pub fn is_idle(&self) -> bool {
+        self.tabs.is_empty()
+            && self.workspaces.is_empty()
+            && !(self.marked_enabled && !self.marked.is_empty())
+    }
+
+    pub fn is_enabled(&self, scope: &BroadcastScope) -> bool {
+        match scope {
+            BroadcastScope::Tab(tab_id) => self.tabs.contains(tab_id),
+            BroadcastScope::Workspace(workspace) => self.workspaces.contains(workspace),
+            BroadcastScope::Marked => self.marked_enabled,
+        }
+    }
+
+    /// Flips the enabled state of `scope`, returning the new state
+    pub fn toggle(&mut self, scope: BroadcastScope) -> bool {
+        match scope {
+            BroadcastScope::Tab(tab_id) => toggle_member(&mut self.tabs, tab_id),
+            BroadcastScope::Workspace(workspace) => toggle_member(&mut self.workspaces, workspace),
+            BroadcastScope::Marked => {
+                self.marked_enabled = !self.marked_enabled;
+                self.marked_enabled
+            }
+        }
+    }
+
+    /// Adds or removes `pane_id` from the marked set, returning
+    /// true if the pane is now marked
+    pub fn toggle_mark(&mut self, pane_id: PaneId) -> bool {
+        toggle_member(&mut self.marked, pane_id)
+    }
+
+    pub fn is_marked(&self, pane_id: PaneId) -> bool {
+        self.marked.contains(&pane_id)
+    }
+
+    /// Returns the marked panes if a member of the marked set is the
+    /// source of the input and marked broadcast is enabled
+    pub fn marked_peers(&self, pane_id: PaneId) -> Vec<PaneId> {
+        if self.marked_enabled && self.marked.contains(&pane_id) {
+            self.marked.iter().copied().collect()
+        } else {
+            vec![]
+        }
+    }
+
+    pub fn forget_pane(&mut self, pane_id: PaneId) {
+        self.marked.remove(&pane_id);
+    }
+
+    pub fn forget_tab(&mut self, tab_id: TabId) {
+        self.tabs.remove(&tab_id);
+    }
+
+    pub fn rename_workspace(&mut self, old_workspace: &str, new_workspace: &str) {
+        if self.workspaces.remove(old_workspace) {
+            self.workspaces.insert(new_workspace.to_string());
+        }
+    }
+}
+
+fn toggle_member<T: std::hash::Hash + Eq>(set: &mut HashSet<T>, item: T) -> bool {
+    if set.remove(&item) {
+        false
+    } else {
+        set.insert(item);
+        true
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn scopes_toggle_independently() {
+        let mut broadcast = InputBroadcast::default();
+        assert!(broadcast.is_idle());
+
+        assert!(broadcast.toggle(BroadcastScope::Tab(1)));
+        assert!(broadcast.toggle(BroadcastScope::Workspace("ops".to_string())));
+        assert!(!broadcast.is_idle());
+        assert!(broadcast.is_enabled(&BroadcastScope::Tab(1)));
+        assert!(!broadcast.is_enabled(&BroadcastScope::Tab(2)));
+
+        assert!(!broadcast.toggle(BroadcastScope::Tab(1)));
+        assert!(!broadcast.is_enabled(&BroadcastScope::Tab(1)));
+        assert!(broadcast.is_enabled(&BroadcastScope::Workspace("ops".to_string())));
+
+        broadcast.rename_workspace("ops", "fleet");
+        assert!(broadcast.is_enabled(&BroadcastScope::Workspace("fleet".to_string())));
+    }
+
+    #[test]
+    fn marked_panes_only_broadcast_once_enabled() {
+        let mut broadcast = InputBroadcast::default();
+        assert!(broadcast.toggle_mark(3));
+        assert!(broadcast.toggle_mark(5));
+        assert!(broadcast.is_idle());
+        assert!(broadcast.marked_peers(3).is_empty());
+
+        broadcast.toggle(BroadcastScope::Marked);
+        let mut peers = broadcast.marked_peers(3);
+        peers.sort();
+        assert_eq!(peers, vec![3, 5]);
+        assert!(broadcast.marked_peers(4).is_empty());
+
+        broadcast.forget_pane(5);
+        assert_eq!(broadcast.marked_peers(3), vec![3]);
+        assert!(!broadcast.toggle_mark(3));
+        assert!(broadcast.is_idle());
+    }
+}
@@ -2,6 +2,7 @@ use crate::PaneId;
 use chrono::serde::ts_seconds;
 use chrono::{DateTime, Utc};
 use serde::*;
+use shelldone_term::StableRowIndex;
 use std::sync::atomic::{AtomicUsize, Ordering};
 use std::sync::Arc;
 use std::time::SystemTime;
@@ -58,6 +59,38 @@ pub struct ClientInfo {
     pub last_input: DateTime<Utc>,
     /// The currently-focused pane
     pub focused_pane_id: Option<PaneId>,
+    /// Set when the client is the guest of a share
+    pub guest: Option<GuestInfo>,
+    /// The part of a pane that the client is looking at
+    pub viewport: Option<ClientViewport>,
+}
+
+/// Describes a client that connected with the credential of a share
+#[derive(Deserialize, Serialize, PartialEq, Eq, Debug, Clone)]
+pub struct GuestInfo {
+    pub share_id: String,
+    pub label: Option<String>,
+    /// Whether the guest may send input to the shared panes
+    pub interactive: bool,
+}
+
+/// The viewport of a client, and the position of its mouse pointer,
+/// in a pane
+#[derive(Deserialize, Serialize, PartialEq, Eq, Debug, Clone, Copy)]
+pub struct ClientViewport {
+    pub pane_id: PaneId,
+    /// The first row that is displayed, or None when the
+    /// client is following the bottom of the output
+    pub top: Option<StableRowIndex>,
+    /// The column and row of the mouse pointer
+    pub pointer: Option<(usize, StableRowIndex)>,
+}
+
+/// A guest that is looking at a pane
+#[derive(Deserialize, Serialize, PartialEq, Eq, Debug, Clone)]
+pub struct GuestPresence {
+    pub guest: GuestInfo,
+    pub viewport: ClientViewport,
 }
 
 impl ClientInfo {
@@ -68,6 +101,8 @@ impl ClientInfo {
             active_workspace: None,
             last_input: current_utc(),
             focused_pane_id: None,
+            guest: None,
+            viewport: None,
         }
     }
 
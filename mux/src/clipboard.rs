@@ -0,0 +1,79 @@
+//! Answers the OSC 5522 clipboard reads of the panes in the mux.
+//!
+//! The frontend decides whether to allow a read that is announced by
+//! `MuxNotification::RequestClipboard`; whatever it decides, the read
+//! is journaled and answered with these helpers.
+use crate::pane::PaneId;
+use crate::sigma_proxy::{report_clipboard_event, SigmaClipboardAction, SigmaClipboardEvent};
+use crate::Mux;
+use shelldone_term::{ClipboardReadRequest, ClipboardSelection};
+use std::io::Write;
+use std::time::SystemTime;
+use termwiz::escape::kitty_clipboard::{KittyClipboard, KittyClipboardType};
+use termwiz::escape::OperatingSystemCommand;
+
+pub use termwiz::escape::kitty_clipboard::KittyClipboardStatus;
+
+/// Refuses a read, answering the application with `status`
+pub fn deny_clipboard_read(
+    pane_id: PaneId,
+    request: &ClipboardReadRequest,
+    status: KittyClipboardStatus,
+) {
+    journal_clipboard_read(pane_id, request, false, 0);
+    send_clipboard_responses(
+        pane_id,
+        vec![KittyClipboard::response(
+            &response_template(request),
+            KittyClipboardType::Read,
+            status,
+        )],
+    );
+}
+
+pub fn journal_clipboard_read(
+    pane_id: PaneId,
+    request: &ClipboardReadRequest,
+    allowed: bool,
+    bytes: usize,
+) {
+    report_clipboard_event(SigmaClipboardEvent {
+        pane_id,
+        action: SigmaClipboardAction::Read,
+        selection: request.selection,
+        mime_types: request.mime_types.clone(),
+        bytes,
+        allowed,
+        occurred_at: SystemTime::now(),
+    });
+}
+
+/// A request with the location and id that must be echoed
+/// back in the responses to `request`
+pub fn response_template(request: &ClipboardReadRequest) -> KittyClipboard {
+    KittyClipboard {
+        primary: request.selection == ClipboardSelection::PrimarySelection,
+        id: request.id.clone(),
+        ..KittyClipboard::default()
+    }
+}
+
+pub fn send_clipboard_responses(pane_id: PaneId, responses: Vec<KittyClipboard>) {
+    let mux = Mux::get();
+    let pane = match mux.get_pane(pane_id) {
+        Some(pane) => pane,
+        None => return,
+    };
+    let mut writer = pane.writer();
+    for response in responses {
+        if let Err(err) = write!(
+            writer,
+            "{}",
+            OperatingSystemCommand::KittyClipboard(response)
+        ) {
+            log::error!("error while writing OSC 5522 response: {err:#}");
+            return;
+        }
+    }
+    writer.flush().ok();
+}
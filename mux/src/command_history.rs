@@ -0,0 +1,602 @@
+//! Record the commands that are run at the shell prompt of local panes.
+//!
+//! Commands are delimited by the OSC 133 semantic prompt escapes.  When a
+//! command finishes it is appended as a line of JSON to a history file in
+//! the data directory, which is shared by every pane and every shelldone
+//! process of the user.  The file is trimmed back down to
+//! `command_history_max_entries` from time to time.
+//!
+//! The file is written by a thread of its own, so that the main thread
+//! doesn't wait on the disk.  Appends share a lock on the history and a
+//! trim holds it alone, so that the commands that other processes append
+//! while the file is being rewritten are not lost.
+use crate::pane::{CachePolicy, Pane, PaneId};
+use crate::session::url_to_path;
+use crate::sigma_proxy::{report_command_event, SigmaCommandEvent};
+use anyhow::Context;
+use chrono::{DateTime, Utc};
+use frecency::Frecency;
+use nucleo_matcher::pattern::{CaseMatching, Normalization, Pattern};
+use nucleo_matcher::{Matcher, Utf32Str};
+use serde::{Deserialize, Serialize};
+use shelldone_dynamic::{FromDynamic, ToDynamic};
+use shelldone_term::CommandBlock;
+use std::collections::HashMap;
+use std::fs::{File, OpenOptions};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::sync::mpsc::{channel, Receiver, Sender};
+use std::sync::{Arc, OnceLock};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+const HISTORY_FILE: &str = "command-history.jsonl";
+
+/// The history file is trimmed once it may hold this fraction of
+/// `command_history_max_entries` beyond the maximum, so that it is
+/// rewritten only after that many commands have been appended
+const TRIM_SLACK_DIVISOR: usize = 4;
+
+static WRITER: OnceLock<Sender<CommandHistoryEntry>> = OnceLock::new();
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq, FromDynamic, ToDynamic)]
+pub struct CommandHistoryEntry {
+    /// The command line that was entered
+    pub command: String,
+    /// The working directory of the shell, as reported via OSC 7
+    /// or else determined from the process
+    #[serde(default)]
+    #[dynamic(default)]
+    pub cwd: Option<String>,
+    #[serde(default)]
+    #[dynamic(default)]
+    pub exit_code: Option<i32>,
+    /// When the command started, in milliseconds since the unix epoch
+    pub start_time_ms: u64,
+    /// When the command finished, in milliseconds since the unix epoch
+    pub end_time_ms: u64,
+    /// The host on which the command ran
+    pub hostname: String,
+    pub pane_id: PaneId,
+}
+
+impl CommandHistoryEntry {
+    /// Builds the entry for a command that finished in `pane`.
+    /// Returns None if no command was entered.
+    pub fn from_command_block(pane: &Arc<dyn Pane>, block: &CommandBlock) -> Option<Self> {
+        let command = block.command.trim();
+        if command.is_empty() {
+            return None;
+        }
+        let url = pane.get_current_working_dir(CachePolicy::AllowStale);
+        let hostname = url
+            .as_ref()
+            .and_then(|url| url.host_str())
+            .filter(|host| !host.is_empty() && *host != "localhost")
+            .map(|host| host.to_string())
+            .unwrap_or_else(local_hostname);
+        let end_time_ms = block.end_time_ms.unwrap_or_else(now_ms);
+        Some(Self {
+            command: command.to_string(),
+            cwd: url.as_ref().and_then(url_to_path),
+            exit_code: block.exit_status,
+            start_time_ms: block.start_time_ms.unwrap_or(end_time_ms),
+            end_time_ms,
+            hostname,
+            pane_id: pane.pane_id(),
+        })
+    }
+
+    pub fn duration(&self) -> Duration {
+        Duration::from_millis(self.end_time_ms.saturating_sub(self.start_time_ms))
+    }
+
+    pub fn start_time(&self) -> DateTime<Utc> {
+        ms_to_datetime(self.start_time_ms)
+    }
+
+    pub fn end_time(&self) -> DateTime<Utc> {
+        ms_to_datetime(self.end_time_ms)
+    }
+}
+
+/// Selects and ranks the entries of the history
+#[derive(Debug, Clone, Default, PartialEq, FromDynamic, ToDynamic)]
+pub struct CommandHistoryQuery {
+    /// A fuzzy pattern that the command must match.
+    /// When empty, all commands match.
+    #[dynamic(default)]
+    pub pattern: String,
+    /// Only include commands that ran in this directory
+    #[dynamic(default)]
+    pub cwd: Option<String>,
+    /// Only include commands that ran on this host
+    #[dynamic(default)]
+    pub hostname: Option<String>,
+    /// Only include commands that ran in this pane
+    #[dynamic(default)]
+    pub pane_id: Option<PaneId>,
+    /// Only include commands that exited with this status
+    #[dynamic(default)]
+    pub exit_code: Option<i32>,
+    /// Merge the runs of the same command line into the most recent one
+    #[dynamic(default)]
+    pub unique: bool,
+    /// The maximum number of results
+    #[dynamic(default)]
+    pub limit: Option<usize>,
+}
+
+impl CommandHistoryQuery {
+    /// Returns true if the entry satisfies the filters of the query
+    fn selects(&self, entry: &CommandHistoryEntry) -> bool {
+        if let Some(cwd) = &self.cwd {
+            if entry.cwd.as_ref() != Some(cwd) {
+                return false;
+            }
+        }
+        if let Some(hostname) = &self.hostname {
+            if &entry.hostname != hostname {
+                return false;
+            }
+        }
+        if let Some(pane_id) = self.pane_id {
+            if entry.pane_id != pane_id {
+                return false;
+            }
+        }
+        if let Some(exit_code) = self.exit_code {
+            if entry.exit_code != Some(exit_code) {
+                return false;
+            }
+        }
+        true
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, ToDynamic)]
+pub struct CommandHistoryMatch {
+    /// The most recent run of the command, when merged
+    pub entry: CommandHistoryEntry,
+    /// The number of times that the command line was run
+    pub runs: usize,
+    /// The rank of the result; higher is better
+    pub score: f64,
+}
+
+pub fn default_history_path() -> PathBuf {
+    config::DATA_DIR.join(HISTORY_FILE)
+}
+
+/// The entries of the history file, oldest first
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct CommandHistory {
+    entries: Vec<CommandHistoryEntry>,
+}
+
+impl CommandHistory {
+    pub fn new(entries: Vec<CommandHistoryEntry>) -> Self {
+        Self { entries }
+    }
+
+    /// Load the history at `path`.  A missing file yields an empty
+    /// history, and lines that can't be parsed are skipped.
+    pub fn load(path: &Path) -> anyhow::Result<Self> {
+        let data = match std::fs::read_to_string(path) {
+            Ok(data) => data,
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => return Ok(Self::default()),
+            Err(err) => {
+                return Err(err).with_context(|| format!("reading {}", path.display()));
+            }
+        };
+        let entries = data
+            .lines()
+            .filter(|line| !line.trim().is_empty())
+            .filter_map(|line| match serde_json::from_str(line) {
+                Ok(entry) => Some(entry),
+                Err(err) => {
+                    log::debug!("skipping command history line in {}: {err}", path.display());
+                    None
+                }
+            })
+            .collect();
+        Ok(Self { entries })
+    }
+
+    pub fn entries(&self) -> &[CommandHistoryEntry] {
+        &self.entries
+    }
+
+    /// Appends `entry` to the history file at `path`
+    pub fn append(path: &Path, entry: &CommandHistoryEntry) -> anyhow::Result<()> {
+        let mut line = serde_json::to_string(entry).context("serializing command history")?;
+        line.push('\n');
+        let _lock = HistoryLock::acquire(path, false)?;
+        let mut file = open_private(path, OpenOptions::new().create(true).append(true))?;
+        // A single write keeps the lines of concurrent writers intact
+        file.write_all(line.as_bytes())
+            .with_context(|| format!("writing {}", path.display()))
+    }
+
+    /// Discards the oldest entries of the history file at `path`
+    /// so that it holds no more than `max_entries`.
+    /// Returns the number of entries that it holds afterwards.
+    pub fn trim(path: &Path, max_entries: usize) -> anyhow::Result<usize> {
+        let _lock = HistoryLock::acquire(path, true)?;
+        let mut history = Self::load(path)?;
+        if history.entries.len() <= max_entries {
+            return Ok(history.entries.len());
+        }
+        let excess = history.entries.len() - max_entries;
+        history.entries.drain(0..excess);
+
+        let mut data = String::new();
+        for entry in &history.entries {
+            data.push_str(&serde_json::to_string(entry).context("serializing command history")?);
+            data.push('\n');
+        }
+        let tmp = path.with_extension("jsonl.tmp");
+        open_private(
+            &tmp,
+            OpenOptions::new().create(true).write(true).truncate(true),
+        )?
+        .write_all(data.as_bytes())
+        .with_context(|| format!("writing {}", tmp.display()))?;
+        std::fs::rename(&tmp, path)
+            .with_context(|| format!("renaming {} -> {}", tmp.display(), path.display()))?;
+        Ok(history.entries.len())
+    }
+
+    /// Returns the entries that are selected by the query.
+    /// When there is a pattern, or when the runs are merged, the results
+    /// are ranked by how well they match the pattern and by the frecency
+    /// of the command line.  Otherwise the most recent come first.
+    pub fn search(&self, query: &CommandHistoryQuery) -> Vec<CommandHistoryMatch> {
+        let selected: Vec<&CommandHistoryEntry> = self
+            .entries
+            .iter()
+            .filter(|entry| query.selects(entry))
+            .collect();
+
+        // The frecency and the number of runs of each command line,
+        // along with the index of its most recent run
+        let mut commands: HashMap<&str, (Frecency, usize, usize)> = HashMap::new();
+        for (idx, entry) in selected.iter().enumerate() {
+            let end_time = entry.end_time();
+            let (frecency, runs, last) = commands
+                .entry(entry.command.as_str())
+                .or_insert_with(|| (Frecency::new_at_time(end_time), 0, idx));
+            frecency.register_access_at_time(end_time);
+            *runs += 1;
+            *last = idx;
+        }
+
+        let pattern = if query.pattern.is_empty() {
+            None
+        } else {
+            Some(Pattern::parse(
+                &query.pattern,
+                CaseMatching::Ignore,
+                Normalization::Smart,
+            ))
+        };
+        let mut matcher = Matcher::new(nucleo_matcher::Config::DEFAULT);
+        let mut buf = vec![];
+        let now = Utc::now();
+
+        let mut results: Vec<CommandHistoryMatch> = selected
+            .iter()
+            .enumerate()
+            .filter_map(|(idx, entry)| {
+                let (frecency, runs, last) = &commands[entry.command.as_str()];
+                if query.unique && *last != idx {
+                    return None;
+                }
+                let frecency = frecency.score_at_time(now);
+                let score = match &pattern {
+                    Some(pattern) => {
+                        let fuzzy =
+                            pattern.score(Utf32Str::new(&entry.command, &mut buf), &mut matcher)?;
+                        fuzzy as f64 * (1.0 + frecency.ln_1p())
+                    }
+                    None => frecency,
+                };
+                Some(CommandHistoryMatch {
+                    entry: (*entry).clone(),
+                    runs: if query.unique { *runs } else { 1 },
+                    score,
+                })
+            })
+            .collect();
+
+        if pattern.is_some() || query.unique {
+            results.sort_by(|a, b| {
+                b.score
+                    .total_cmp(&a.score)
+                    .then(b.entry.end_time_ms.cmp(&a.entry.end_time_ms))
+            });
+        } else {
+            results.reverse();
+        }
+        if let Some(limit) = query.limit {
+            results.truncate(limit);
+        }
+        results
+    }
+}
+
+/// Records a command that finished in `pane` into the command history,
+/// and shares it with agentd when configured to do so
+pub fn record_finished_command(pane: &Arc<dyn Pane>, block: &CommandBlock) {
+    let config = config::configuration();
+    if !config.enable_command_history {
+        return;
+    }
+    let Some(entry) = CommandHistoryEntry::from_command_block(pane, block) else {
+        return;
+    };
+
+    if config.command_history_share_with_agentd {
+        report_command_event(SigmaCommandEvent {
+            pane_id: entry.pane_id,
+            command: entry.command.clone(),
+            cwd: entry.cwd.clone(),
+            hostname: entry.hostname.clone(),
+            exit_code: entry.exit_code,
+            started_at: UNIX_EPOCH + Duration::from_millis(entry.start_time_ms),
+            occurred_at: UNIX_EPOCH + Duration::from_millis(entry.end_time_ms),
+        });
+    }
+
+    let sender = WRITER.get_or_init(|| {
+        let (tx, rx) = channel();
+        std::thread::Builder::new()
+            .name("command-history".to_string())
+            .spawn(move || run_writer(rx))
+            .expect("failed to spawn command history thread");
+        tx
+    });
+    if sender.send(entry).is_err() {
+        log::error!("command history thread has terminated");
+    }
+}
+
+fn run_writer(rx: Receiver<CommandHistoryEntry>) {
+    let path = default_history_path();
+    restrict_permissions(&path);
+    // The first append made by the process also trims the file, which
+    // tells us how many entries it holds.  Other processes append to it
+    // too, so it may be trimmed a little later than this predicts.
+    let mut appends_until_trim = 0;
+    while let Ok(entry) = rx.recv() {
+        if let Err(err) = CommandHistory::append(&path, &entry) {
+            log::error!("Error while recording command history: {err:#}");
+            continue;
+        }
+        if appends_until_trim > 0 {
+            appends_until_trim -= 1;
+            continue;
+        }
+        let max_entries = config::configuration().command_history_max_entries;
+        match CommandHistory::trim(&path, max_entries) {
+            Ok(len) => {
+                let slack = (max_entries / TRIM_SLACK_DIVISOR).max(1);
+                appends_until_trim = (max_entries + slack).saturating_sub(len);
+            }
+            Err(err) => log::error!("Error while trimming command history: {err:#}"),
+        }
+    }
+}
+
+/// Held while the history file is read or written.  Appends share the
+/// lock and a trim holds it alone.  The lock is taken on a file beside
+/// the history, since a trim replaces the history file itself.
+struct HistoryLock {
+    _file: File,
+}
+
+impl HistoryLock {
+    fn acquire(path: &Path, exclusive: bool) -> anyhow::Result<Self> {
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)
+                .with_context(|| format!("creating {}", parent.display()))?;
+        }
+        let lock_path = path.with_extension("jsonl.lock");
+        let file = open_private(&lock_path, OpenOptions::new().create(true).write(true))?;
+        #[cfg(unix)]
+        {
+            use std::os::unix::io::AsRawFd;
+            let operation = if exclusive {
+                libc::LOCK_EX
+            } else {
+                libc::LOCK_SH
+            };
+            if unsafe { libc::flock(file.as_raw_fd(), operation) } != 0 {
+                return Err(std::io::Error::last_os_error())
+                    .with_context(|| format!("locking {}", lock_path.display()));
+            }
+        }
+        #[cfg(not(unix))]
+        let _ = exclusive;
+        Ok(Self { _file: file })
+    }
+}
+
+/// Opens a file of the history, creating it readable only by the user
+fn open_private(path: &Path, options: &mut OpenOptions) -> anyhow::Result<File> {
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::OpenOptionsExt;
+        options.mode(0o600);
+    }
+    options
+        .open(path)
+        .with_context(|| format!("opening {}", path.display()))
+}
+
+/// Makes a history file that was created by an earlier version
+/// readable only by the user
+fn restrict_permissions(path: &Path) {
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        match std::fs::set_permissions(path, std::fs::Permissions::from_mode(0o600)) {
+            Ok(()) => {}
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => {}
+            Err(err) => log::warn!("restricting permissions of {}: {err:#}", path.display()),
+        }
+    }
+    #[cfg(not(unix))]
+    let _ = path;
+}
+
+fn local_hostname() -> String {
+    hostname::get()
+        .map(|s| s.to_string_lossy().to_string())
+        .unwrap_or_else(|_| "localhost".to_string())
+}
+
+fn now_ms() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_millis() as u64)
+        .unwrap_or(0)
+}
+
+fn ms_to_datetime(ms: u64) -> DateTime<Utc> {
+    DateTime::from_timestamp_millis(ms as i64).unwrap_or_default()
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn entry(command: &str, cwd: &str, exit_code: i32, end_time_ms: u64) -> CommandHistoryEntry {
+        CommandHistoryEntry {
+            command: command.to_string(),
+            cwd: Some(cwd.to_string()),
+            exit_code: Some(exit_code),
+            start_time_ms: end_time_ms - 100,
+            end_time_ms,
+            hostname: "host".to_string(),
+            pane_id: 1,
+        }
+    }
+
+    fn history() -> CommandHistory {
+        let now = now_ms();
+        CommandHistory::new(vec![
+            entry("cargo build", "/src", 1, now - 5_000),
+            entry("cargo build", "/src", 0, now - 4_000),
+            entry("ls", "/tmp", 0, now - 3_000),
+            entry("cargo test", "/src", 0, now - 2_000),
+            entry("ls", "/src", 0, now - 1_000),
+        ])
+    }
+
+    fn commands(results: &[CommandHistoryMatch]) -> Vec<&str> {
+        results
+            .iter()
+            .map(|result| result.entry.command.as_str())
+            .collect()
+    }
+
+    #[test]
+    fn history_round_trips_on_disk() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("nested").join(HISTORY_FILE);
+        assert_eq!(
+            CommandHistory::load(&path).unwrap(),
+            CommandHistory::default()
+        );
+
+        let history = history();
+        for entry in history.entries() {
+            CommandHistory::append(&path, entry).unwrap();
+        }
+        assert_eq!(CommandHistory::load(&path).unwrap(), history);
+
+        assert_eq!(CommandHistory::trim(&path, 2).unwrap(), 2);
+        assert_eq!(
+            CommandHistory::load(&path).unwrap().entries(),
+            &history.entries()[3..]
+        );
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn history_is_private() {
+        use std::os::unix::fs::PermissionsExt;
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join(HISTORY_FILE);
+        let history = history();
+        for entry in history.entries() {
+            CommandHistory::append(&path, entry).unwrap();
+        }
+        let mode = |path: &Path| std::fs::metadata(path).unwrap().permissions().mode() & 0o777;
+        assert_eq!(mode(&path), 0o600);
+        CommandHistory::trim(&path, 2).unwrap();
+        assert_eq!(mode(&path), 0o600);
+    }
+
+    #[test]
+    fn malformed_lines_are_skipped() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join(HISTORY_FILE);
+        let history = history();
+        CommandHistory::append(&path, &history.entries()[0]).unwrap();
+        std::fs::OpenOptions::new()
+            .append(true)
+            .open(&path)
+            .unwrap()
+            .write_all(b"{\"command\": tru\n")
+            .unwrap();
+        CommandHistory::append(&path, &history.entries()[1]).unwrap();
+        assert_eq!(
+            CommandHistory::load(&path).unwrap().entries(),
+            &history.entries()[0..2]
+        );
+    }
+
+    #[test]
+    fn search_filters_and_orders_by_recency() {
+        let history = history();
+        let results = history.search(&CommandHistoryQuery::default());
+        assert_eq!(
+            commands(&results),
+            vec!["ls", "cargo test", "ls", "cargo build", "cargo build"]
+        );
+
+        let results = history.search(&CommandHistoryQuery {
+            cwd: Some("/src".to_string()),
+            exit_code: Some(0),
+            limit: Some(2),
+            ..Default::default()
+        });
+        assert_eq!(commands(&results), vec!["ls", "cargo test"]);
+    }
+
+    #[test]
+    fn search_ranks_by_pattern_and_frecency() {
+        let history = history();
+        let results = history.search(&CommandHistoryQuery {
+            unique: true,
+            ..Default::default()
+        });
+        assert_eq!(commands(&results), vec!["ls", "cargo build", "cargo test"]);
+        assert_eq!(results[1].runs, 2);
+        assert_eq!(results[1].entry.exit_code, Some(0));
+
+        let results = history.search(&CommandHistoryQuery {
+            pattern: "cargo".to_string(),
+            unique: true,
+            ..Default::default()
+        });
+        assert_eq!(commands(&results), vec!["cargo build", "cargo test"]);
+
+        let results = history.search(&CommandHistoryQuery {
+            pattern: "zzz".to_string(),
+            ..Default::default()
+        });
+        assert!(results.is_empty());
+    }
+}
@@ -162,6 +162,27 @@ pub trait Domain: Downcast + Send + Sync {
         Ok(None)
     }
 
+    /// The mux will call this method on the domain of the panes that
+    /// are being swapped to give the domain a chance to handle the swap.
+    /// If this method returns Ok(false), then the mux will swap the
+    /// panes itself by mutating its local Tabs.
+    async fn swap_panes(&self, _pane_id: PaneId, _other_pane_id: PaneId) -> anyhow::Result<bool> {
+        Ok(false)
+    }
+
+    /// The mux will call this method on the domain of the tab that
+    /// is being moved to give the domain a chance to handle the movement.
+    /// If this method returns Ok(None), then the mux will handle the
+    /// movement itself by mutating its local Windows.
+    async fn move_tab_to_window(
+        &self,
+        _tab_id: TabId,
+        _window_id: Option<WindowId>,
+        _workspace_for_new_window: Option<String>,
+    ) -> anyhow::Result<Option<WindowId>> {
+        Ok(None)
+    }
+
     /// Returns false if the `spawn` method will never succeed.
     /// There are some internal placeholder domains that are
     /// pre-created with local UI that we do not want to allow
@@ -493,12 +514,14 @@ impl LocalDomain {
 /// awkward at the moment.
 #[derive(Clone)]
 pub(crate) struct WriterWrapper {
+    pane_id: PaneId,
     writer: Arc<Mutex<Box<dyn Write + Send>>>,
 }
 
 impl WriterWrapper {
-    pub fn new(writer: Box<dyn Write + Send>) -> Self {
+    pub fn new(pane_id: PaneId, writer: Box<dyn Write + Send>) -> Self {
         Self {
+            pane_id,
             writer: Arc::new(Mutex::new(writer)),
         }
     }
@@ -506,7 +529,9 @@ impl WriterWrapper {
 
 impl std::io::Write for WriterWrapper {
     fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
-        self.writer.lock().write(buf)
+        let len = self.writer.lock().write(buf)?;
+        crate::recording::record_input(self.pane_id, &buf[..len]);
+        Ok(len)
     }
 
     fn flush(&mut self) -> std::io::Result<()> {
@@ -615,7 +640,7 @@ impl Domain for LocalDomain {
         );
         let mut proxy_master = Some(SigmaProxyPty::new(pair.master));
         let child_result = pair.slave.spawn_command(cmd);
-        let mut writer = WriterWrapper::new(proxy_master.as_mut().unwrap().take_writer()?);
+        let mut writer = WriterWrapper::new(pane_id, proxy_master.as_mut().unwrap().take_writer()?);
 
         let mut terminal = shelldone_term::Terminal::new(
             size,
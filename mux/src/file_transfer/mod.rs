@@ -0,0 +1,1444 @@
+//! Carries out the kitty file transfer protocol, OSC 5113, on behalf
+//! of the panes in the mux.
+//! <https://sw.kovidgoyal.net/kitty/file-transfer-protocol/>
+//!
+//! Every session must be permitted by the user: the mux emits
+//! `MuxNotification::RequestFileTransfer` and the frontend answers
+//! with `respond_to_file_transfer`.  When permitting a send session,
+//! the user also picks the directory into which its files are written;
+//! nothing outside of that directory is written.  The paths involved are checked
+//! by the sigma policy, and the contents of files are staged through
+//! blob lease storage on their way to and from the disk.
+//!
+//! Sessions are processed on a dedicated thread, so that the disk
+//! I/O doesn't stall the parsing of the output of the pane.
+use crate::pane::PaneId;
+use crate::sigma_proxy::{
+    check_file_transfer_dest, check_file_transfer_path, report_file_transfer_event,
+    SigmaFileTransferEvent,
+};
+use crate::{Mux, MuxNotification};
+use anyhow::{anyhow, Context};
+use shelldone_blob_leases::BlobManager;
+use shelldone_term::FileTransferHandler;
+use std::collections::HashMap;
+use std::io::{Read, Write};
+use std::path::{Component, Path, PathBuf};
+use std::sync::mpsc::{channel, Receiver, Sender};
+use std::sync::OnceLock;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use termwiz::escape::kitty_file_transfer::{
+    FileTransfer, FileTransferAction, FileTransferCompression, FileTransferFileType,
+    FileTransferTransmission, FILE_TRANSFER_CANCELED, FILE_TRANSFER_OK, FILE_TRANSFER_STARTED,
+};
+use termwiz::escape::osc::Progress;
+use termwiz::escape::{Action, OperatingSystemCommand};
+
+mod rsync;
+
+/// The largest file that may be transferred
+const MAX_FILE_SIZE: usize = 1024 * 1024 * 1024;
+/// Bounds the data that is buffered while waiting for the user
+/// to permit a session
+const MAX_QUEUED_SIZE: usize = 64 * 1024 * 1024;
+/// The size of the chunks of file data that are sent to the application
+const CHUNK_SIZE: usize = 4096;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FileTransferDirection {
+    /// The application sends files, which are written to this machine
+    Send,
+    /// The application receives files, which are read from this machine
+    Receive,
+}
+
+/// A session that is waiting for the user to permit it
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FileTransferRequest {
+    pub id: String,
+    pub direction: FileTransferDirection,
+    /// The paths that a receive session would read
+    pub paths: Vec<PathBuf>,
+}
+
+/// Permits or denies the file transfer session that was
+/// described by a `MuxNotification::RequestFileTransfer`.
+/// A send session is only permitted along with `dest_dir`, the
+/// directory that the user chose to hold the files that it sends.
+pub fn respond_to_file_transfer(
+    pane_id: PaneId,
+    id: String,
+    allowed: bool,
+    dest_dir: Option<PathBuf>,
+) {
+    submit(Message::Permission {
+        pane_id,
+        id,
+        allowed,
+        dest_dir,
+    });
+}
+
+/// Abandons any sessions that belong to a pane that has been removed
+pub(crate) fn forget_pane(pane_id: PaneId) {
+    if WORKER.get().is_some() {
+        submit(Message::PaneRemoved(pane_id));
+    }
+}
+
+pub(crate) struct MuxFileTransfer {
+    pub pane_id: PaneId,
+}
+
+impl FileTransferHandler for MuxFileTransfer {
+    fn handle_file_transfer(&self, transfer: FileTransfer) {
+        submit(Message::Request {
+            pane_id: self.pane_id,
+            transfer,
+        });
+    }
+}
+
+enum Message {
+    Request {
+        pane_id: PaneId,
+        transfer: FileTransfer,
+    },
+    Permission {
+        pane_id: PaneId,
+        id: String,
+        allowed: bool,
+        dest_dir: Option<PathBuf>,
+    },
+    PaneRemoved(PaneId),
+}
+
+static WORKER: OnceLock<Sender<Message>> = OnceLock::new();
+
+fn submit(message: Message) {
+    let sender = WORKER.get_or_init(|| {
+        let (tx, rx) = channel();
+        std::thread::Builder::new()
+            .name("file-transfer".to_string())
+            .spawn(move || run_worker(rx))
+            .expect("failed to spawn file transfer thread");
+        tx
+    });
+    if sender.send(message).is_err() {
+        log::error!("file transfer thread has terminated");
+    }
+}
+
+fn run_worker(rx: Receiver<Message>) {
+    let mut transfers = Transfers::new(config::HOME_DIR.clone());
+    while let Ok(message) = rx.recv() {
+        let (pane_id, effects) = match message {
+            Message::Request { pane_id, transfer } => {
+                (pane_id, transfers.handle(pane_id, transfer))
+            }
+            Message::Permission {
+                pane_id,
+                id,
+                allowed,
+                dest_dir,
+            } => (pane_id, transfers.permit(pane_id, &id, allowed, dest_dir)),
+            Message::PaneRemoved(pane_id) => {
+                transfers.forget_pane(pane_id);
+                continue;
+            }
+        };
+        apply_effects(pane_id, effects);
+    }
+}
+
+fn apply_effects(pane_id: PaneId, effects: Vec<Effect>) {
+    let pane = Mux::try_get().and_then(|mux| mux.get_pane(pane_id));
+    for effect in effects {
+        match effect {
+            Effect::Reply(transfer) => {
+                let Some(pane) = &pane else { continue };
+                let mut writer = pane.writer();
+                if let Err(err) = write!(
+                    writer,
+                    "{}",
+                    OperatingSystemCommand::KittyFileTransfer(transfer)
+                ) {
+                    log::error!("error while writing OSC 5113 response: {err:#}");
+                }
+                writer.flush().ok();
+            }
+            Effect::Progress(progress) => {
+                if let Some(pane) = &pane {
+                    pane.perform_actions(vec![Action::OperatingSystemCommand(Box::new(
+                        OperatingSystemCommand::ConEmuProgress(progress),
+                    ))]);
+                }
+            }
+            Effect::Prompt(request) => {
+                Mux::notify_from_any_thread(MuxNotification::RequestFileTransfer {
+                    pane_id,
+                    request,
+                });
+            }
+            Effect::Journal(event) => report_file_transfer_event(event),
+        }
+    }
+}
+
+#[derive(Debug)]
+enum Effect {
+    /// Send a response to the application
+    Reply(FileTransfer),
+    /// Update the progress bar of the pane
+    Progress(Progress),
+    /// Ask the user to permit a session
+    Prompt(FileTransferRequest),
+    Journal(SigmaFileTransferEvent),
+}
+
+/// A file that is being sent by the application
+struct IncomingFile {
+    request: FileTransfer,
+    dest: PathBuf,
+    /// The existing contents of `dest`, against which an
+    /// rsync delta is applied
+    base: Vec<u8>,
+    data: Vec<u8>,
+}
+
+/// A file that is waiting to be sent to the application
+struct OutgoingFile {
+    file_id: String,
+    path: PathBuf,
+    file_type: FileTransferFileType,
+    transmission: FileTransferTransmission,
+    compression: FileTransferCompression,
+    /// For rsync transmission, the signature of the copy
+    /// that the application already has
+    signature: Vec<u8>,
+}
+
+struct Session {
+    id: String,
+    direction: FileTransferDirection,
+    quiet: u8,
+    granted: bool,
+    /// The directory that the user chose for the files of a send session
+    dest_dir: Option<PathBuf>,
+    /// Requests that arrived before the user permitted the session
+    queued: Vec<FileTransfer>,
+    queued_size: usize,
+    /// The number of files that a receive session asked for,
+    /// and the requests that describe them
+    expected: usize,
+    specs: Vec<FileTransfer>,
+    incoming: HashMap<String, IncomingFile>,
+    /// Files that are waiting for their signature to arrive
+    outgoing: HashMap<String, OutgoingFile>,
+    /// The paths that were written, by file id, for use by hard links
+    written: HashMap<String, PathBuf>,
+    total_bytes: u64,
+    done_bytes: u64,
+    progress: Option<Progress>,
+}
+
+struct Transfers {
+    home: PathBuf,
+    sessions: HashMap<(PaneId, String), Session>,
+}
+
+impl Transfers {
+    fn new(home: PathBuf) -> Self {
+        Self {
+            home,
+            sessions: HashMap::new(),
+        }
+    }
+
+    fn forget_pane(&mut self, pane_id: PaneId) {
+        self.sessions.retain(|(pane, _), _| *pane != pane_id);
+    }
+
+    fn handle(&mut self, pane_id: PaneId, transfer: FileTransfer) -> Vec<Effect> {
+        let mut effects = vec![];
+        let Some(id) = transfer.id.clone() else {
+            if transfer.quiet < 2 {
+                effects.push(Effect::Reply(FileTransfer::error(
+                    &transfer,
+                    "EINVAL",
+                    "missing session id",
+                )));
+            }
+            return effects;
+        };
+        let key = (pane_id, id.clone());
+
+        match transfer.action {
+            FileTransferAction::Send | FileTransferAction::Receive => {
+                if self.sessions.contains_key(&key) {
+                    if transfer.quiet < 2 {
+                        effects.push(Effect::Reply(FileTransfer::error(
+                            &transfer,
+                            "EINVAL",
+                            "session already exists",
+                        )));
+                    }
+                    return effects;
+                }
+                let direction = if transfer.action == FileTransferAction::Send {
+                    FileTransferDirection::Send
+                } else {
+                    FileTransferDirection::Receive
+                };
+                let mut session = Session::new(id.clone(), direction, transfer.quiet);
+                match direction {
+                    FileTransferDirection::Send => {
+                        effects.push(Effect::Prompt(FileTransferRequest {
+                            id,
+                            direction,
+                            paths: vec![],
+                        }));
+                    }
+                    FileTransferDirection::Receive => {
+                        // The user is asked once all of the
+                        // requested paths are known
+                        session.expected = transfer.size.unwrap_or(0) as usize;
+                        if session.expected == 0 {
+                            session.reply_error(
+                                &transfer,
+                                "EINVAL",
+                                "no files were requested",
+                                &mut effects,
+                            );
+                            return effects;
+                        }
+                    }
+                }
+                self.sessions.insert(key, session);
+            }
+            FileTransferAction::Cancel => {
+                if let Some(mut session) = self.sessions.remove(&key) {
+                    session.reply(
+                        FileTransfer::status(&transfer, FILE_TRANSFER_CANCELED),
+                        &mut effects,
+                    );
+                    session.clear_progress(&mut effects);
+                }
+            }
+            FileTransferAction::Finish => {
+                if let Some(mut session) = self.sessions.remove(&key) {
+                    session.clear_progress(&mut effects);
+                }
+            }
+            FileTransferAction::Status => {}
+            FileTransferAction::File | FileTransferAction::Data | FileTransferAction::EndData => {
+                let Some(session) = self.sessions.get_mut(&key) else {
+                    if transfer.quiet < 2 {
+                        effects.push(Effect::Reply(FileTransfer::error(
+                            &transfer,
+                            "EINVAL",
+                            "unknown session",
+                        )));
+                    }
+                    return effects;
+                };
+
+                if session.granted {
+                    session.process(&self.home, pane_id, transfer, &mut effects);
+                } else if session.direction == FileTransferDirection::Receive
+                    && transfer.action == FileTransferAction::File
+                    && session.specs.len() < session.expected
+                {
+                    session.specs.push(transfer);
+                    if session.specs.len() == session.expected {
+                        let home = &self.home;
+                        let paths = session
+                            .specs
+                            .iter()
+                            .filter_map(|spec| spec.name.as_deref())
+                            .map(|name| resolve_path(home, home, name))
+                            .collect();
+                        effects.push(Effect::Prompt(FileTransferRequest {
+                            id,
+                            direction: session.direction,
+                            paths,
+                        }));
+                    }
+                } else {
+                    session.queued_size += transfer.data.len();
+                    if session.queued_size > MAX_QUEUED_SIZE {
+                        if let Some(session) = self.sessions.remove(&key) {
+                            session.reply_error(
+                                &transfer,
+                                "EFBIG",
+                                "too much data was sent before the transfer was permitted",
+                                &mut effects,
+                            );
+                        }
+                        return effects;
+                    }
+                    session.queued.push(transfer);
+                }
+            }
+        }
+        effects
+    }
+
+    fn permit(
+        &mut self,
+        pane_id: PaneId,
+        id: &str,
+        allowed: bool,
+        dest_dir: Option<PathBuf>,
+    ) -> Vec<Effect> {
+        let mut effects = vec![];
+        let key = (pane_id, id.to_string());
+        let Some(session) = self.sessions.get_mut(&key) else {
+            return effects;
+        };
+        if session.granted {
+            return effects;
+        }
+        let request = FileTransfer {
+            action: FileTransferAction::Status,
+            id: Some(id.to_string()),
+            quiet: session.quiet,
+            ..Default::default()
+        };
+
+        let refusal = if !allowed {
+            Some("the user refused the transfer")
+        } else if session.direction == FileTransferDirection::Send && dest_dir.is_none() {
+            Some("no destination directory was chosen")
+        } else {
+            None
+        };
+        if let Some(reason) = refusal {
+            effects.push(Effect::Journal(SigmaFileTransferEvent {
+                pane_id,
+                direction: session.direction,
+                path: None,
+                bytes: 0,
+                allowed: false,
+                reason: Some(reason.to_string()),
+                occurred_at: SystemTime::now(),
+            }));
+            session.reply_error(&request, "EPERM", reason, &mut effects);
+            self.sessions.remove(&key);
+            return effects;
+        }
+
+        session.granted = true;
+        session.dest_dir = dest_dir;
+        session.reply(
+            FileTransfer::status(&request, FILE_TRANSFER_OK),
+            &mut effects,
+        );
+        if session.direction == FileTransferDirection::Receive {
+            session.start_receive(&self.home, pane_id, &mut effects);
+        }
+        session.queued_size = 0;
+        for transfer in std::mem::take(&mut session.queued) {
+            session.process(&self.home, pane_id, transfer, &mut effects);
+        }
+        effects
+    }
+}
+
+impl Session {
+    fn new(id: String, direction: FileTransferDirection, quiet: u8) -> Self {
+        Self {
+            id,
+            direction,
+            quiet,
+            granted: false,
+            dest_dir: None,
+            queued: vec![],
+            queued_size: 0,
+            expected: 0,
+            specs: vec![],
+            incoming: HashMap::new(),
+            outgoing: HashMap::new(),
+            written: HashMap::new(),
+            total_bytes: 0,
+            done_bytes: 0,
+            progress: None,
+        }
+    }
+
+    /// Sends a status response, unless the application asked
+    /// for those to be suppressed
+    fn reply(&self, status: FileTransfer, effects: &mut Vec<Effect>) {
+        let quiet = if status.is_error() { 2 } else { 1 };
+        if self.quiet < quiet {
+            effects.push(Effect::Reply(status));
+        }
+    }
+
+    fn reply_error(
+        &self,
+        request: &FileTransfer,
+        code: &str,
+        message: &str,
+        effects: &mut Vec<Effect>,
+    ) {
+        self.reply(FileTransfer::error(request, code, message), effects);
+    }
+
+    fn journal(
+        &self,
+        pane_id: PaneId,
+        path: &Path,
+        bytes: u64,
+        refused: Option<&str>,
+        effects: &mut Vec<Effect>,
+    ) {
+        effects.push(Effect::Journal(SigmaFileTransferEvent {
+            pane_id,
+            direction: self.direction,
+            path: Some(path.to_path_buf()),
+            bytes,
+            allowed: refused.is_none(),
+            reason: refused.map(|reason| reason.to_string()),
+            occurred_at: SystemTime::now(),
+        }));
+    }
+
+    fn update_progress(&mut self, effects: &mut Vec<Effect>) {
+        let progress = if self.total_bytes == 0 {
+            Progress::SetIndeterminate
+        } else {
+            Progress::SetPercentage((self.done_bytes * 100 / self.total_bytes).min(100) as u8)
+        };
+        if self.progress.as_ref() != Some(&progress) {
+            self.progress.replace(progress.clone());
+            effects.push(Effect::Progress(progress));
+        }
+    }
+
+    fn clear_progress(&mut self, effects: &mut Vec<Effect>) {
+        if self.progress.take().is_some() {
+            effects.push(Effect::Progress(Progress::None));
+        }
+    }
+
+    fn process(
+        &mut self,
+        home: &Path,
+        pane_id: PaneId,
+        transfer: FileTransfer,
+        effects: &mut Vec<Effect>,
+    ) {
+        let Some(file_id) = transfer.file_id.clone() else {
+            self.reply_error(&transfer, "EINVAL", "missing file id", effects);
+            return;
+        };
+        match (self.direction, transfer.action) {
+            (FileTransferDirection::Send, FileTransferAction::File) => {
+                self.start_incoming_file(home, pane_id, file_id, transfer, effects)
+            }
+            (
+                FileTransferDirection::Send,
+                FileTransferAction::Data | FileTransferAction::EndData,
+            ) => self.incoming_data(home, pane_id, file_id, transfer, effects),
+            (
+                FileTransferDirection::Receive,
+                FileTransferAction::Data | FileTransferAction::EndData,
+            ) => self.signature_data(pane_id, file_id, transfer, effects),
+            _ => self.reply_error(&transfer, "EINVAL", "unexpected action", effects),
+        }
+    }
+
+    /// Checks that a send session may write `dest`
+    fn check_dest(&self, home: &Path, dest: &Path) -> Result<(), &'static str> {
+        check_file_transfer_path(dest, home, self.direction)?;
+        match &self.dest_dir {
+            Some(dest_dir) => check_file_transfer_dest(dest, dest_dir),
+            None => Err("no destination directory was chosen"),
+        }
+    }
+
+    fn start_incoming_file(
+        &mut self,
+        home: &Path,
+        pane_id: PaneId,
+        file_id: String,
+        transfer: FileTransfer,
+        effects: &mut Vec<Effect>,
+    ) {
+        let Some(name) = transfer.name.as_deref() else {
+            self.reply_error(&transfer, "EINVAL", "missing file name", effects);
+            return;
+        };
+        let dest = resolve_path(home, self.dest_dir.as_deref().unwrap_or(home), name);
+        if let Err(reason) = self.check_dest(home, &dest) {
+            self.journal(pane_id, &dest, 0, Some(reason), effects);
+            self.reply_error(&transfer, "EPERM", reason, effects);
+            return;
+        }
+
+        match transfer.file_type {
+            FileTransferFileType::Directory => {
+                match std::fs::create_dir_all(&dest).and_then(|_| set_metadata(&dest, &transfer)) {
+                    Ok(()) => {
+                        self.journal(pane_id, &dest, 0, None, effects);
+                        self.reply(FileTransfer::status(&transfer, FILE_TRANSFER_OK), effects);
+                        self.written.insert(file_id, dest);
+                    }
+                    Err(err) => self.reply_error(&transfer, "EIO", &err.to_string(), effects),
+                }
+            }
+            FileTransferFileType::Regular
+            | FileTransferFileType::Symlink
+            | FileTransferFileType::Link => {
+                let rsync = transfer.file_type == FileTransferFileType::Regular
+                    && transfer.transmission == FileTransferTransmission::Rsync;
+                // Without an existing copy, the delta is made
+                // against an empty base
+                let base = if rsync {
+                    std::fs::read(&dest).unwrap_or_default()
+                } else {
+                    vec![]
+                };
+                self.total_bytes += transfer.size.unwrap_or(0);
+                self.reply(
+                    FileTransfer::status(&transfer, FILE_TRANSFER_STARTED),
+                    effects,
+                );
+                if rsync {
+                    let signature = rsync::signature(&base, rsync::DEFAULT_BLOCK_SIZE);
+                    self.send_data(&file_id, signature, effects);
+                }
+                self.incoming.insert(
+                    file_id,
+                    IncomingFile {
+                        request: transfer,
+                        dest,
+                        base,
+                        data: vec![],
+                    },
+                );
+                self.update_progress(effects);
+            }
+        }
+    }
+
+    fn incoming_data(
+        &mut self,
+        home: &Path,
+        pane_id: PaneId,
+        file_id: String,
+        transfer: FileTransfer,
+        effects: &mut Vec<Effect>,
+    ) {
+        let Some(file) = self.incoming.get_mut(&file_id) else {
+            self.reply_error(&transfer, "EINVAL", "unknown file", effects);
+            return;
+        };
+        file.data.extend_from_slice(&transfer.data);
+        if file.data.len() > MAX_FILE_SIZE {
+            self.incoming.remove(&file_id);
+            self.reply_error(&transfer, "EFBIG", "file is too large", effects);
+            return;
+        }
+        self.done_bytes += transfer.data.len() as u64;
+        self.update_progress(effects);
+
+        if transfer.action == FileTransferAction::EndData {
+            if let Some(file) = self.incoming.remove(&file_id) {
+                self.finish_incoming_file(home, pane_id, file_id, file, effects);
+            }
+        }
+    }
+
+    fn finish_incoming_file(
+        &mut self,
+        home: &Path,
+        pane_id: PaneId,
+        file_id: String,
+        file: IncomingFile,
+        effects: &mut Vec<Effect>,
+    ) {
+        let IncomingFile {
+            request,
+            dest,
+            base,
+            data,
+        } = file;
+
+        // A link created earlier in the session may have
+        // redirected the destination
+        if let Err(reason) = self.check_dest(home, &dest) {
+            self.journal(pane_id, &dest, 0, Some(reason), effects);
+            self.reply_error(&request, "EPERM", reason, effects);
+            return;
+        }
+
+        let result = (|| -> anyhow::Result<u64> {
+            let data = decompress(request.compression, data)?;
+            match request.file_type {
+                FileTransferFileType::Regular => {
+                    let data = match request.transmission {
+                        FileTransferTransmission::Simple => data,
+                        FileTransferTransmission::Rsync => {
+                            rsync::patch(&base, &data, MAX_FILE_SIZE)?
+                        }
+                    };
+                    write_staged(&dest, &data, &request)
+                }
+                FileTransferFileType::Symlink => {
+                    let target = String::from_utf8(data).context("symlink target")?;
+                    remove_existing(&dest)?;
+                    create_symlink(Path::new(&target), &dest)?;
+                    Ok(0)
+                }
+                FileTransferFileType::Link => {
+                    let target_id = String::from_utf8(data).context("link target")?;
+                    let target = self
+                        .written
+                        .get(&target_id)
+                        .ok_or_else(|| anyhow!("unknown link target {target_id}"))?;
+                    remove_existing(&dest)?;
+                    std::fs::hard_link(target, &dest)?;
+                    Ok(0)
+                }
+                FileTransferFileType::Directory => Ok(0),
+            }
+        })();
+
+        match result {
+            Ok(size) => {
+                self.journal(pane_id, &dest, size, None, effects);
+                let mut status = FileTransfer::status(&request, FILE_TRANSFER_OK);
+                status.size = Some(size);
+                self.reply(status, effects);
+                self.written.insert(file_id, dest);
+            }
+            Err(err) => {
+                log::error!("OSC 5113 failed to write {}: {err:#}", dest.display());
+                self.reply_error(&request, "EIO", &format!("{err:#}"), effects);
+            }
+        }
+    }
+
+    /// Sends `data` to the application as a series of chunks
+    fn send_data(&self, file_id: &str, data: Vec<u8>, effects: &mut Vec<Effect>) {
+        let mut chunks = data.chunks(CHUNK_SIZE).peekable();
+        loop {
+            let chunk = chunks.next().unwrap_or_default();
+            let last = chunks.peek().is_none();
+            effects.push(Effect::Reply(FileTransfer {
+                action: if last {
+                    FileTransferAction::EndData
+                } else {
+                    FileTransferAction::Data
+                },
+                id: Some(self.id.clone()),
+                file_id: Some(file_id.to_string()),
+                data: chunk.to_vec(),
+                ..Default::default()
+            }));
+            if last {
+                break;
+            }
+        }
+    }
+
+    /// Describes the files that the application asked for, and sends
+    /// those that don't need to wait for a signature
+    fn start_receive(&mut self, home: &Path, pane_id: PaneId, effects: &mut Vec<Effect>) {
+        let mut ready = vec![];
+        for spec in std::mem::take(&mut self.specs) {
+            let (Some(file_id), Some(name)) = (spec.file_id.clone(), spec.name.as_deref()) else {
+                self.reply_error(&spec, "EINVAL", "missing file id or name", effects);
+                continue;
+            };
+            let path = resolve_path(home, home, name);
+            if let Err(reason) = check_file_transfer_path(&path, home, self.direction) {
+                self.journal(pane_id, &path, 0, Some(reason), effects);
+                self.reply_error(&spec, "EPERM", reason, effects);
+                continue;
+            }
+            let entries = match collect_entries(&path) {
+                Ok(entries) => entries,
+                Err(err) => {
+                    self.reply_error(&spec, "ENOENT", &err.to_string(), effects);
+                    continue;
+                }
+            };
+
+            for (idx, (entry, metadata)) in entries.into_iter().enumerate() {
+                // The contents of a directory are checked individually,
+                // so that sending a home directory doesn't send its
+                // credentials along with it
+                if let Err(reason) = check_file_transfer_path(&entry, home, self.direction) {
+                    self.journal(pane_id, &entry, 0, Some(reason), effects);
+                    continue;
+                }
+                let file_id = if idx == 0 {
+                    file_id.clone()
+                } else {
+                    format!("{file_id}.{idx}")
+                };
+                let file_type = if metadata.is_dir() {
+                    FileTransferFileType::Directory
+                } else if metadata.is_symlink() {
+                    FileTransferFileType::Symlink
+                } else {
+                    FileTransferFileType::Regular
+                };
+                let size = if file_type == FileTransferFileType::Regular {
+                    metadata.len()
+                } else {
+                    0
+                };
+                self.total_bytes += size;
+                effects.push(Effect::Reply(FileTransfer {
+                    action: FileTransferAction::File,
+                    id: Some(self.id.clone()),
+                    file_id: Some(file_id.clone()),
+                    name: Some(entry.to_string_lossy().into_owned()),
+                    file_type,
+                    transmission: spec.transmission,
+                    compression: spec.compression,
+                    size: Some(size),
+                    mtime: metadata
+                        .modified()
+                        .ok()
+                        .and_then(|mtime| mtime.duration_since(UNIX_EPOCH).ok())
+                        .map(|mtime| mtime.as_nanos() as u64),
+                    permissions: permissions(&metadata),
+                    ..Default::default()
+                }));
+
+                let outgoing = OutgoingFile {
+                    file_id: file_id.clone(),
+                    path: entry,
+                    file_type,
+                    transmission: spec.transmission,
+                    compression: spec.compression,
+                    signature: vec![],
+                };
+                if file_type == FileTransferFileType::Regular
+                    && spec.transmission == FileTransferTransmission::Rsync
+                {
+                    self.outgoing.insert(file_id, outgoing);
+                } else {
+                    ready.push(outgoing);
+                }
+            }
+        }
+
+        self.update_progress(effects);
+        for outgoing in ready {
+            self.send_file(pane_id, outgoing, effects);
+        }
+        self.maybe_finish_receive(effects);
+    }
+
+    fn signature_data(
+        &mut self,
+        pane_id: PaneId,
+        file_id: String,
+        transfer: FileTransfer,
+        effects: &mut Vec<Effect>,
+    ) {
+        let Some(outgoing) = self.outgoing.get_mut(&file_id) else {
+            self.reply_error(&transfer, "EINVAL", "unknown file", effects);
+            return;
+        };
+        outgoing.signature.extend_from_slice(&transfer.data);
+        if outgoing.signature.len() > MAX_QUEUED_SIZE {
+            self.outgoing.remove(&file_id);
+            self.reply_error(&transfer, "EFBIG", "signature is too large", effects);
+            self.maybe_finish_receive(effects);
+            return;
+        }
+        if transfer.action == FileTransferAction::EndData {
+            if let Some(outgoing) = self.outgoing.remove(&file_id) {
+                self.send_file(pane_id, outgoing, effects);
+            }
+            self.maybe_finish_receive(effects);
+        }
+    }
+
+    fn send_file(&mut self, pane_id: PaneId, outgoing: OutgoingFile, effects: &mut Vec<Effect>) {
+        let result = (|| -> anyhow::Result<Option<(Vec<u8>, u64)>> {
+            match outgoing.file_type {
+                FileTransferFileType::Directory | FileTransferFileType::Link => Ok(None),
+                FileTransferFileType::Symlink => {
+                    let target = std::fs::read_link(&outgoing.path)?;
+                    Ok(Some((
+                        target.to_string_lossy().into_owned().into_bytes(),
+                        0,
+                    )))
+                }
+                FileTransferFileType::Regular => {
+                    let data = read_staged(&outgoing.path)?;
+                    let size = data.len() as u64;
+                    let data = match outgoing.transmission {
+                        FileTransferTransmission::Simple => data,
+                        FileTransferTransmission::Rsync => {
+                            rsync::delta(&outgoing.signature, &data)?
+                        }
+                    };
+                    let data = match outgoing.compression {
+                        FileTransferCompression::None => data,
+                        FileTransferCompression::Zlib => {
+                            miniz_oxide::deflate::compress_to_vec_zlib(&data, 6)
+                        }
+                    };
+                    Ok(Some((data, size)))
+                }
+            }
+        })();
+
+        let request = FileTransfer {
+            id: Some(self.id.clone()),
+            file_id: Some(outgoing.file_id.clone()),
+            ..Default::default()
+        };
+        match result {
+            Ok(Some((data, size))) => {
+                self.send_data(&outgoing.file_id, data, effects);
+                self.journal(pane_id, &outgoing.path, size, None, effects);
+                self.done_bytes += size;
+                self.update_progress(effects);
+            }
+            Ok(None) => self.journal(pane_id, &outgoing.path, 0, None, effects),
+            Err(err) => {
+                log::error!(
+                    "OSC 5113 failed to read {}: {err:#}",
+                    outgoing.path.display()
+                );
+                self.reply_error(&request, "EIO", &format!("{err:#}"), effects);
+            }
+        }
+    }
+
+    /// Once every file has been sent, tells the application that
+    /// the session is complete
+    fn maybe_finish_receive(&mut self, effects: &mut Vec<Effect>) {
+        if self.outgoing.is_empty() {
+            effects.push(Effect::Reply(FileTransfer {
+                action: FileTransferAction::Finish,
+                id: Some(self.id.clone()),
+                ..Default::default()
+            }));
+            self.clear_progress(effects);
+        }
+    }
+}
+
+/// Resolves the path named by the application: `~` refers to the
+/// home directory, and relative paths are relative to `base`
+fn resolve_path(home: &Path, base: &Path, name: &str) -> PathBuf {
+    let path = if name == "~" {
+        home.to_path_buf()
+    } else if let Some(rest) = name.strip_prefix("~/") {
+        home.join(rest)
+    } else {
+        base.join(name)
+    };
+
+    let mut resolved = PathBuf::new();
+    for component in path.components() {
+        match component {
+            Component::CurDir => {}
+            Component::ParentDir => {
+                resolved.pop();
+            }
+            component => resolved.push(component),
+        }
+    }
+    resolved
+}
+
+/// Returns the path and its contents, if it is a directory.
+/// Directories precede their contents.
+fn collect_entries(path: &Path) -> std::io::Result<Vec<(PathBuf, std::fs::Metadata)>> {
+    let mut entries = vec![];
+    let mut stack = vec![path.to_path_buf()];
+    while let Some(path) = stack.pop() {
+        let metadata = std::fs::symlink_metadata(&path)?;
+        if metadata.is_dir() {
+            let mut children = std::fs::read_dir(&path)?
+                .map(|entry| entry.map(|entry| entry.path()))
+                .collect::<std::io::Result<Vec<_>>>()?;
+            children.sort();
+            stack.extend(children.into_iter().rev());
+        }
+        entries.push((path, metadata));
+    }
+    Ok(entries)
+}
+
+fn decompress(compression: FileTransferCompression, data: Vec<u8>) -> anyhow::Result<Vec<u8>> {
+    match compression {
+        FileTransferCompression::None => Ok(data),
+        FileTransferCompression::Zlib => {
+            miniz_oxide::inflate::decompress_to_vec_zlib_with_limit(&data, MAX_FILE_SIZE)
+                .map_err(|e| anyhow!("decompressing data: {:?}", e))
+        }
+    }
+}
+
+/// Stores `data` in blob storage, and then copies it from there
+/// into place, replacing `dest` only once it has been completely
+/// written
+fn write_staged(dest: &Path, data: &[u8], request: &FileTransfer) -> anyhow::Result<u64> {
+    let lease = BlobManager::store(data).context("staging file")?;
+    let mut reader = lease.get_reader().context("reading staged file")?;
+
+    let dir = dest
+        .parent()
+        .ok_or_else(|| anyhow!("{} has no parent directory", dest.display()))?;
+    std::fs::create_dir_all(dir)?;
+    let name = dest
+        .file_name()
+        .ok_or_else(|| anyhow!("{} has no file name", dest.display()))?;
+    let temp = dir.join(format!(".{}.shelldone-transfer", name.to_string_lossy()));
+
+    let result = (|| -> anyhow::Result<u64> {
+        let mut file = std::fs::File::create(&temp)?;
+        let size = std::io::copy(&mut reader, &mut file)?;
+        drop(file);
+        set_metadata(&temp, request)?;
+        std::fs::rename(&temp, dest)?;
+        Ok(size)
+    })();
+    if result.is_err() {
+        std::fs::remove_file(&temp).ok();
+    }
+    result
+}
+
+/// Reads `path` through blob storage
+fn read_staged(path: &Path) -> anyhow::Result<Vec<u8>> {
+    let size = std::fs::metadata(path)?.len();
+    anyhow::ensure!(size as usize <= MAX_FILE_SIZE, "file is too large");
+    let lease = BlobManager::store(&std::fs::read(path)?).context("staging file")?;
+    let mut data = vec![];
+    lease
+        .get_reader()
+        .context("reading staged file")?
+        .read_to_end(&mut data)?;
+    Ok(data)
+}
+
+fn set_metadata(path: &Path, request: &FileTransfer) -> std::io::Result<()> {
+    #[cfg(unix)]
+    if let Some(mode) = request.permissions {
+        use std::os::unix::fs::PermissionsExt;
+        std::fs::set_permissions(path, std::fs::Permissions::from_mode(mode & 0o7777))?;
+    }
+    if let Some(mtime) = request.mtime {
+        if path.is_file() {
+            let mtime = UNIX_EPOCH + Duration::from_nanos(mtime);
+            std::fs::OpenOptions::new()
+                .write(true)
+                .open(path)?
+                .set_modified(mtime)?;
+        }
+    }
+    Ok(())
+}
+
+fn permissions(metadata: &std::fs::Metadata) -> Option<u32> {
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        Some(metadata.permissions().mode() & 0o7777)
+    }
+    #[cfg(not(unix))]
+    {
+        let _ = metadata;
+        None
+    }
+}
+
+fn remove_existing(path: &Path) -> std::io::Result<()> {
+    match std::fs::symlink_metadata(path) {
+        Ok(metadata) if metadata.is_dir() => Err(std::io::Error::new(
+            std::io::ErrorKind::AlreadyExists,
+            format!("{} is a directory", path.display()),
+        )),
+        Ok(_) => std::fs::remove_file(path),
+        Err(_) => Ok(()),
+    }
+}
+
+#[cfg(unix)]
+fn create_symlink(target: &Path, link: &Path) -> std::io::Result<()> {
+    std::os::unix::fs::symlink(target, link)
+}
+
+#[cfg(windows)]
+fn create_symlink(target: &Path, link: &Path) -> std::io::Result<()> {
+    std::os::windows::fs::symlink_file(target, link)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use std::sync::Once;
+
+    fn register_storage() {
+        static REGISTER: Once = Once::new();
+        REGISTER.call_once(|| {
+            shelldone_blob_leases::register_storage(std::sync::Arc::new(
+                shelldone_blob_leases::simple_tempdir::SimpleTempDir::new().unwrap(),
+            ));
+        });
+    }
+
+    fn request(action: FileTransferAction, file_id: Option<&str>) -> FileTransfer {
+        FileTransfer {
+            action,
+            id: Some("s".to_string()),
+            file_id: file_id.map(|fid| fid.to_string()),
+            ..Default::default()
+        }
+    }
+
+    fn file(file_id: &str, name: &str) -> FileTransfer {
+        FileTransfer {
+            name: Some(name.to_string()),
+            ..request(FileTransferAction::File, Some(file_id))
+        }
+    }
+
+    fn end_data(file_id: &str, data: &[u8]) -> FileTransfer {
+        FileTransfer {
+            data: data.to_vec(),
+            ..request(FileTransferAction::EndData, Some(file_id))
+        }
+    }
+
+    fn replies(effects: &[Effect]) -> Vec<&FileTransfer> {
+        effects
+            .iter()
+            .filter_map(|effect| match effect {
+                Effect::Reply(reply) => Some(reply),
+                _ => None,
+            })
+            .collect()
+    }
+
+    fn statuses(effects: &[Effect]) -> Vec<String> {
+        replies(effects)
+            .into_iter()
+            .filter_map(|reply| reply.status.clone())
+            .collect()
+    }
+
+    /// Concatenates the data that was sent for `file_id`
+    fn sent_data(effects: &[Effect], file_id: &str) -> Vec<u8> {
+        replies(effects)
+            .into_iter()
+            .filter(|reply| {
+                matches!(
+                    reply.action,
+                    FileTransferAction::Data | FileTransferAction::EndData
+                ) && reply.file_id.as_deref() == Some(file_id)
+            })
+            .flat_map(|reply| reply.data.clone())
+            .collect()
+    }
+
+    #[test]
+    fn send_waits_for_permission() {
+        register_storage();
+        let home = tempfile::tempdir().unwrap();
+        let mut transfers = Transfers::new(home.path().to_path_buf());
+
+        let effects = transfers.handle(1, request(FileTransferAction::Send, None));
+        assert!(matches!(
+            effects.as_slice(),
+            [Effect::Prompt(FileTransferRequest {
+                direction: FileTransferDirection::Send,
+                ..
+            })]
+        ));
+
+        // Nothing is written until the user permits the session
+        assert!(transfers.handle(1, file("f", "dir/hello.txt")).is_empty());
+        assert!(transfers.handle(1, end_data("f", b"hello")).is_empty());
+        assert!(!home.path().join("dir/hello.txt").exists());
+
+        let effects = transfers.permit(1, "s", true, Some(home.path().to_path_buf()));
+        assert_eq!(statuses(&effects), vec!["OK", "STARTED", "OK"]);
+        assert_eq!(replies(&effects)[2].size, Some(5));
+        assert_eq!(
+            std::fs::read(home.path().join("dir/hello.txt")).unwrap(),
+            b"hello"
+        );
+        assert!(effects.iter().any(
+            |effect| matches!(effect, Effect::Journal(event) if event.allowed && event.bytes == 5)
+        ));
+
+        let effects = transfers.handle(1, request(FileTransferAction::Finish, None));
+        assert!(matches!(
+            effects.as_slice(),
+            [Effect::Progress(Progress::None)]
+        ));
+        assert!(transfers.sessions.is_empty());
+    }
+
+    #[test]
+    fn refused_send() {
+        let home = tempfile::tempdir().unwrap();
+        let mut transfers = Transfers::new(home.path().to_path_buf());
+
+        transfers.handle(1, request(FileTransferAction::Send, None));
+        transfers.handle(1, file("f", "hello.txt"));
+        transfers.handle(1, end_data("f", b"hello"));
+        let effects = transfers.permit(1, "s", false, None);
+        assert_eq!(
+            statuses(&effects),
+            vec!["EPERM:the user refused the transfer"]
+        );
+        assert!(!home.path().join("hello.txt").exists());
+        assert!(transfers.sessions.is_empty());
+    }
+
+    #[test]
+    fn send_to_protected_path_is_refused() {
+        let home = tempfile::tempdir().unwrap();
+        let mut transfers = Transfers::new(home.path().to_path_buf());
+
+        transfers.handle(1, request(FileTransferAction::Send, None));
+        transfers.permit(1, "s", true, Some(home.path().to_path_buf()));
+        let effects = transfers.handle(1, file("f", "~/.ssh/authorized_keys"));
+        assert_eq!(replies(&effects).len(), 1);
+        assert!(replies(&effects)[0].is_error());
+        assert!(effects
+            .iter()
+            .any(|effect| matches!(effect, Effect::Journal(event) if !event.allowed)));
+
+        // `..` doesn't escape the policy
+        let effects = transfers.handle(1, file("g", "dir/../.bashrc"));
+        assert!(replies(&effects)[0].is_error());
+        assert!(!home.path().join(".ssh").exists());
+    }
+
+    #[test]
+    fn send_is_confined_to_the_chosen_directory() {
+        register_storage();
+        let home = tempfile::tempdir().unwrap();
+        let downloads = home.path().join("Downloads");
+        std::fs::create_dir(&downloads).unwrap();
+        let mut transfers = Transfers::new(home.path().to_path_buf());
+
+        transfers.handle(1, request(FileTransferAction::Send, None));
+        transfers.permit(1, "s", true, Some(downloads.clone()));
+
+        // Relative names are relative to the chosen directory
+        transfers.handle(1, file("f", "notes.txt"));
+        let effects = transfers.handle(1, end_data("f", b"notes"));
+        assert_eq!(statuses(&effects), vec!["OK"]);
+        assert_eq!(
+            std::fs::read(downloads.join("notes.txt")).unwrap(),
+            b"notes"
+        );
+
+        for name in ["~/notes.txt", "../notes.txt", "/tmp/notes.txt"] {
+            let effects = transfers.handle(1, file("g", name));
+            assert!(replies(&effects)[0].is_error(), "{name}");
+        }
+        assert!(!home.path().join("notes.txt").exists());
+    }
+
+    #[test]
+    fn send_without_a_directory_is_refused() {
+        let home = tempfile::tempdir().unwrap();
+        let mut transfers = Transfers::new(home.path().to_path_buf());
+
+        transfers.handle(1, request(FileTransferAction::Send, None));
+        let effects = transfers.permit(1, "s", true, None);
+        assert_eq!(
+            statuses(&effects),
+            vec!["EPERM:no destination directory was chosen"]
+        );
+        assert!(transfers.sessions.is_empty());
+    }
+
+    #[test]
+    fn send_rsync_delta_with_compression() {
+        register_storage();
+        let home = tempfile::tempdir().unwrap();
+        let original: Vec<u8> = (0..20_000u32).map(|i| (i % 199) as u8).collect();
+        std::fs::write(home.path().join("data.bin"), &original).unwrap();
+        let mut transfers = Transfers::new(home.path().to_path_buf());
+
+        transfers.handle(1, request(FileTransferAction::Send, None));
+        transfers.permit(1, "s", true, Some(home.path().to_path_buf()));
+        let effects = transfers.handle(
+            1,
+            FileTransfer {
+                transmission: FileTransferTransmission::Rsync,
+                compression: FileTransferCompression::Zlib,
+                ..file("f", "data.bin")
+            },
+        );
+        assert_eq!(statuses(&effects), vec!["STARTED"]);
+        let signature = sent_data(&effects, "f");
+
+        let mut updated = original.clone();
+        updated.splice(10_000..10_010, b"changed!".iter().copied());
+        let delta = rsync::delta(&signature, &updated).unwrap();
+        let compressed = miniz_oxide::deflate::compress_to_vec_zlib(&delta, 6);
+
+        let effects = transfers.handle(1, end_data("f", &compressed));
+        assert_eq!(statuses(&effects), vec!["OK"]);
+        assert_eq!(
+            std::fs::read(home.path().join("data.bin")).unwrap(),
+            updated
+        );
+    }
+
+    #[test]
+    fn receive_directory() {
+        register_storage();
+        let home = tempfile::tempdir().unwrap();
+        let dir = home.path().join("project");
+        std::fs::create_dir_all(dir.join("src")).unwrap();
+        std::fs::write(dir.join("README"), b"read me").unwrap();
+        std::fs::write(dir.join("src/main.rs"), b"fn main() {}").unwrap();
+        let mut transfers = Transfers::new(home.path().to_path_buf());
+
+        transfers.handle(
+            1,
+            FileTransfer {
+                size: Some(1),
+                ..request(FileTransferAction::Receive, None)
+            },
+        );
+        let effects = transfers.handle(
+            1,
+            FileTransfer {
+                compression: FileTransferCompression::Zlib,
+                ..file("f", "project")
+            },
+        );
+        match effects.as_slice() {
+            [Effect::Prompt(request)] => {
+                assert_eq!(request.direction, FileTransferDirection::Receive);
+                assert_eq!(request.paths, vec![dir.clone()]);
+            }
+            _ => panic!("expected a prompt, got {effects:?}"),
+        }
+
+        let effects = transfers.permit(1, "s", true, None);
+        let described: Vec<(String, String, FileTransferFileType)> = replies(&effects)
+            .into_iter()
+            .filter(|reply| reply.action == FileTransferAction::File)
+            .map(|reply| {
+                (
+                    reply.file_id.clone().unwrap(),
+                    reply.name.clone().unwrap(),
+                    reply.file_type,
+                )
+            })
+            .collect();
+        let name = |path: &Path| path.to_string_lossy().into_owned();
+        assert_eq!(
+            described,
+            vec![
+                ("f".to_string(), name(&dir), FileTransferFileType::Directory),
+                (
+                    "f.1".to_string(),
+                    name(&dir.join("README")),
+                    FileTransferFileType::Regular
+                ),
+                (
+                    "f.2".to_string(),
+                    name(&dir.join("src")),
+                    FileTransferFileType::Directory
+                ),
+                (
+                    "f.3".to_string(),
+                    name(&dir.join("src/main.rs")),
+                    FileTransferFileType::Regular
+                ),
+            ]
+        );
+        let readme =
+            miniz_oxide::inflate::decompress_to_vec_zlib(&sent_data(&effects, "f.1")).unwrap();
+        assert_eq!(readme, b"read me");
+        assert_eq!(
+            replies(&effects).last().unwrap().action,
+            FileTransferAction::Finish
+        );
+    }
+
+    #[test]
+    fn receive_with_rsync_waits_for_signature() {
+        register_storage();
+        let home = tempfile::tempdir().unwrap();
+        let content: Vec<u8> = (0..9_000u32).map(|i| (i % 97) as u8).collect();
+        std::fs::write(home.path().join("big.bin"), &content).unwrap();
+        let mut transfers = Transfers::new(home.path().to_path_buf());
+
+        transfers.handle(
+            1,
+            FileTransfer {
+                size: Some(1),
+                ..request(FileTransferAction::Receive, None)
+            },
+        );
+        transfers.handle(
+            1,
+            FileTransfer {
+                transmission: FileTransferTransmission::Rsync,
+                ..file("f", "big.bin")
+            },
+        );
+        let effects = transfers.permit(1, "s", true, None);
+        assert!(sent_data(&effects, "f").is_empty());
+
+        let mut stale = content.clone();
+        stale.truncate(5_000);
+        let signature = rsync::signature(&stale, 1024);
+        let effects = transfers.handle(1, end_data("f", &signature));
+        let delta = sent_data(&effects, "f");
+        assert!(delta.len() < content.len());
+        assert_eq!(rsync::patch(&stale, &delta, usize::MAX).unwrap(), content);
+        assert_eq!(
+            replies(&effects).last().unwrap().action,
+            FileTransferAction::Finish
+        );
+    }
+
+    #[test]
+    fn receive_skips_credentials() {
+        register_storage();
+        let home = tempfile::tempdir().unwrap();
+        std::fs::create_dir_all(home.path().join(".ssh")).unwrap();
+        std::fs::write(home.path().join(".ssh/id_ed25519"), b"secret").unwrap();
+        std::fs::write(home.path().join("notes.txt"), b"notes").unwrap();
+        let mut transfers = Transfers::new(home.path().to_path_buf());
+
+        transfers.handle(
+            1,
+            FileTransfer {
+                size: Some(1),
+                ..request(FileTransferAction::Receive, None)
+            },
+        );
+        transfers.handle(1, file("f", "~"));
+        let effects = transfers.permit(1, "s", true, None);
+        let names: Vec<String> = replies(&effects)
+            .into_iter()
+            .filter(|reply| reply.action == FileTransferAction::File)
+            .filter_map(|reply| reply.name.clone())
+            .collect();
+        assert_eq!(
+            names,
+            vec![
+                home.path().to_string_lossy().into_owned(),
+                home.path().join("notes.txt").to_string_lossy().into_owned(),
+            ]
+        );
+    }
+}
@@ -0,0 +1,293 @@
+//! An implementation of the rsync algorithm, which is used by OSC 5113
+//! transfers with `tt=rsync` to transmit only the parts of a file that
+//! differ from the copy that the receiver already has.
+//!
+//! The receiver sends the signature of its copy (the base):
+//! the block size as a big endian u32, the length of the base as a
+//! big endian u64, and then the weak (u32) and strong (the first 8 bytes
+//! of its sha256 digest) checksums of each block of the base.
+//!
+//! The sender replies with a delta: the block size as a big endian u32,
+//! followed by a sequence of operations.  `B` and a big endian u64 block
+//! index copies that block of the base, while `L`, a big endian u32
+//! length and that many bytes inserts literal data.
+use anyhow::{anyhow, bail, ensure};
+use sha2::{Digest, Sha256};
+use std::collections::HashMap;
+
+pub const DEFAULT_BLOCK_SIZE: usize = 4096;
+const MAX_BLOCK_SIZE: usize = 1024 * 1024;
+const OP_BLOCK: u8 = b'B';
+const OP_LITERAL: u8 = b'L';
+/// Literal runs are split so that their length fits in a u32
+const MAX_LITERAL: usize = 1024 * 1024;
+
+type Strong = [u8; 8];
+
+fn strong_checksum(block: &[u8]) -> Strong {
+    let digest = Sha256::digest(block);
+    let mut strong = [0u8; 8];
+    strong.copy_from_slice(&digest[..8]);
+    strong
+}
+
+/// The rsync rolling checksum over a window of bytes
+#[derive(Debug, Clone, Copy)]
+struct Rolling {
+    a: u16,
+    b: u16,
+    len: usize,
+}
+
+impl Rolling {
+    fn new(window: &[u8]) -> Self {
+        let mut a = 0u16;
+        let mut b = 0u16;
+        for (i, &x) in window.iter().enumerate() {
+            a = a.wrapping_add(x as u16);
+            b = b.wrapping_add(((window.len() - i) as u16).wrapping_mul(x as u16));
+        }
+        Self {
+            a,
+            b,
+            len: window.len(),
+        }
+    }
+
+    /// Slides the window along by one byte
+    fn roll(&mut self, out: u8, incoming: u8) {
+        self.a = self
+            .a
+            .wrapping_sub(out as u16)
+            .wrapping_add(incoming as u16);
+        self.b = self
+            .b
+            .wrapping_sub((self.len as u16).wrapping_mul(out as u16))
+            .wrapping_add(self.a);
+    }
+
+    fn digest(&self) -> u32 {
+        self.a as u32 | ((self.b as u32) << 16)
+    }
+}
+
+/// Computes the signature of `base`
+pub fn signature(base: &[u8], block_size: usize) -> Vec<u8> {
+    let blocks = base.chunks(block_size);
+    let mut sig = Vec::with_capacity(12 + blocks.len() * 12);
+    sig.extend_from_slice(&(block_size as u32).to_be_bytes());
+    sig.extend_from_slice(&(base.len() as u64).to_be_bytes());
+    for block in blocks {
+        sig.extend_from_slice(&Rolling::new(block).digest().to_be_bytes());
+        sig.extend_from_slice(&strong_checksum(block));
+    }
+    sig
+}
+
+struct Signature {
+    block_size: usize,
+    base_len: usize,
+    /// weak checksum -> (block index, strong checksum)
+    blocks: HashMap<u32, Vec<(u64, Strong)>>,
+}
+
+impl Signature {
+    fn parse(sig: &[u8]) -> anyhow::Result<Self> {
+        ensure!(sig.len() >= 12, "rsync signature is truncated");
+        let block_size = u32::from_be_bytes(sig[0..4].try_into()?) as usize;
+        let base_len = u64::from_be_bytes(sig[4..12].try_into()?) as usize;
+        ensure!(
+            block_size > 0 && block_size <= MAX_BLOCK_SIZE,
+            "invalid rsync block size {block_size}"
+        );
+        let entries = &sig[12..];
+        ensure!(
+            entries.len() % 12 == 0 && entries.len() / 12 == base_len.div_ceil(block_size),
+            "rsync signature doesn't describe a base of {base_len} bytes"
+        );
+
+        let mut blocks: HashMap<u32, Vec<(u64, Strong)>> = HashMap::new();
+        for (idx, entry) in entries.chunks(12).enumerate() {
+            let weak = u32::from_be_bytes(entry[0..4].try_into()?);
+            let strong: Strong = entry[4..12].try_into()?;
+            blocks.entry(weak).or_default().push((idx as u64, strong));
+        }
+        Ok(Self {
+            block_size,
+            base_len,
+            blocks,
+        })
+    }
+
+    fn block_len(&self, idx: u64) -> usize {
+        let start = idx as usize * self.block_size;
+        self.block_size.min(self.base_len - start)
+    }
+
+    /// Returns the index of the base block whose contents are `window`
+    fn find(&self, weak: u32, window: &[u8]) -> Option<u64> {
+        let candidates = self.blocks.get(&weak)?;
+        let mut strong = None;
+        candidates.iter().find_map(|&(idx, candidate)| {
+            if self.block_len(idx) != window.len() {
+                return None;
+            }
+            let strong = *strong.get_or_insert_with(|| strong_checksum(window));
+            (strong == candidate).then_some(idx)
+        })
+    }
+}
+
+fn push_literal(delta: &mut Vec<u8>, literal: &mut Vec<u8>) {
+    for chunk in literal.chunks(MAX_LITERAL) {
+        delta.push(OP_LITERAL);
+        delta.extend_from_slice(&(chunk.len() as u32).to_be_bytes());
+        delta.extend_from_slice(chunk);
+    }
+    literal.clear();
+}
+
+/// Computes the delta that transforms the base described by
+/// `signature` into `data`
+pub fn delta(signature: &[u8], data: &[u8]) -> anyhow::Result<Vec<u8>> {
+    let sig = Signature::parse(signature)?;
+    let block_size = sig.block_size;
+    let mut delta = vec![];
+    delta.extend_from_slice(&(block_size as u32).to_be_bytes());
+
+    let mut literal = vec![];
+    let mut rolling: Option<Rolling> = None;
+    let mut i = 0;
+    while i < data.len() {
+        if data.len() - i < block_size {
+            // The tail can only match the short final block of the base
+            let tail = &data[i..];
+            match sig.find(Rolling::new(tail).digest(), tail) {
+                Some(idx) => {
+                    push_literal(&mut delta, &mut literal);
+                    delta.push(OP_BLOCK);
+                    delta.extend_from_slice(&idx.to_be_bytes());
+                }
+                None => literal.extend_from_slice(tail),
+            }
+            break;
+        }
+
+        let window = &data[i..i + block_size];
+        let state = *rolling.get_or_insert_with(|| Rolling::new(window));
+        if let Some(idx) = sig.find(state.digest(), window) {
+            push_literal(&mut delta, &mut literal);
+            delta.push(OP_BLOCK);
+            delta.extend_from_slice(&idx.to_be_bytes());
+            i += block_size;
+            rolling = None;
+            continue;
+        }
+
+        literal.push(data[i]);
+        rolling = data.get(i + block_size).map(|&incoming| {
+            let mut state = state;
+            state.roll(data[i], incoming);
+            state
+        });
+        i += 1;
+    }
+    push_literal(&mut delta, &mut literal);
+    Ok(delta)
+}
+
+/// Applies `delta` to `base`, returning the transformed data.
+/// `limit` bounds the size of the result.
+pub fn patch(base: &[u8], delta: &[u8], limit: usize) -> anyhow::Result<Vec<u8>> {
+    ensure!(delta.len() >= 4, "rsync delta is truncated");
+    let block_size = u32::from_be_bytes(delta[0..4].try_into()?) as usize;
+    ensure!(
+        block_size > 0 && block_size <= MAX_BLOCK_SIZE,
+        "invalid rsync block size {block_size}"
+    );
+
+    let mut result = vec![];
+    let mut ops = &delta[4..];
+    while let Some((&op, rest)) = ops.split_first() {
+        match op {
+            OP_BLOCK => {
+                ensure!(rest.len() >= 8, "rsync delta is truncated");
+                let idx = u64::from_be_bytes(rest[0..8].try_into()?) as usize;
+                let start = idx
+                    .checked_mul(block_size)
+                    .filter(|&start| start < base.len())
+                    .ok_or_else(|| {
+                        anyhow!("rsync delta refers to block {idx}, which is beyond the base")
+                    })?;
+                let end = base.len().min(start + block_size);
+                result.extend_from_slice(&base[start..end]);
+                ops = &rest[8..];
+            }
+            OP_LITERAL => {
+                ensure!(rest.len() >= 4, "rsync delta is truncated");
+                let len = u32::from_be_bytes(rest[0..4].try_into()?) as usize;
+                ensure!(rest.len() >= 4 + len, "rsync delta is truncated");
+                result.extend_from_slice(&rest[4..4 + len]);
+                ops = &rest[4 + len..];
+            }
+            _ => bail!("invalid rsync delta operation {op:#x}"),
+        }
+        ensure!(result.len() <= limit, "rsync delta produces too much data");
+    }
+    Ok(result)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn round_trip(base: &[u8], data: &[u8], block_size: usize) -> Vec<u8> {
+        let sig = signature(base, block_size);
+        let delta = delta(&sig, data).unwrap();
+        assert_eq!(patch(base, &delta, usize::MAX).unwrap(), data);
+        delta
+    }
+
+    #[test]
+    fn identical_data_is_all_blocks() {
+        let base: Vec<u8> = (0..10_000u32).map(|i| (i * 7 % 251) as u8).collect();
+        let delta = round_trip(&base, &base, 1024);
+        // 10 blocks, including the short final block, and no literals
+        assert_eq!(delta.len(), 4 + 10 * 9);
+    }
+
+    #[test]
+    fn modified_data() {
+        let base: Vec<u8> = (0..10_000u32).map(|i| (i * 13 % 241) as u8).collect();
+
+        let mut inserted = base.clone();
+        inserted.splice(1500..1500, b"inserted text".iter().copied());
+        let delta = round_trip(&base, &inserted, 1024);
+        assert!(delta.len() < 2048);
+
+        let mut changed = base.clone();
+        changed[5000] ^= 0xff;
+        round_trip(&base, &changed, 1024);
+
+        let mut truncated = base.clone();
+        truncated.truncate(4321);
+        round_trip(&base, &truncated, 1024);
+
+        round_trip(&base, b"", 1024);
+        round_trip(b"", &base, 1024);
+    }
+
+    #[test]
+    fn invalid_delta() {
+        let base = b"hello world";
+        let mut delta = 4u32.to_be_bytes().to_vec();
+        delta.push(OP_BLOCK);
+        delta.extend_from_slice(&100u64.to_be_bytes());
+        assert!(patch(base, &delta, usize::MAX).is_err());
+
+        let mut delta = 4u32.to_be_bytes().to_vec();
+        delta.push(OP_LITERAL);
+        delta.extend_from_slice(&100u32.to_be_bytes());
+        assert!(patch(base, &delta, usize::MAX).is_err());
+    }
+}
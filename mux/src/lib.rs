@@ -1,6 +1,10 @@
-use crate::client::{ClientId, ClientInfo};
+use crate::broadcast::{BroadcastScope, InputBroadcast};
+use crate::client::{ClientId, ClientInfo, ClientViewport, GuestInfo, GuestPresence};
+use crate::file_transfer::{FileTransferRequest, MuxFileTransfer};
 use crate::pane::{CachePolicy, Pane, PaneId};
-use crate::sigma_proxy::SigmaDirection;
+use crate::sigma_proxy::{
+    report_clipboard_event, SigmaClipboardAction, SigmaClipboardEvent, SigmaDirection,
+};
 use crate::ssh_agent::AgentProxy;
 use crate::tab::{SplitRequest, Tab, TabId};
 use crate::window::{Window, WindowId};
@@ -18,7 +22,10 @@ use parking_lot::{
 };
 use percent_encoding::percent_decode_str;
 use portable_pty::{CommandBuilder, ExitStatus, PtySize};
-use shelldone_term::{Clipboard, ClipboardSelection, DownloadHandler, TerminalSize};
+use shelldone_term::{
+    Clipboard, ClipboardData, ClipboardReadRequest, ClipboardSelection, DownloadHandler,
+    FileTransferHandler, TerminalSize,
+};
 use std::collections::{HashMap, HashSet};
 use std::convert::TryInto;
 use std::io::{Read, Write};
@@ -35,12 +42,20 @@ use thiserror::*;
 use winapi::um::winsock2::{SOL_SOCKET, SO_RCVBUF, SO_SNDBUF};
 
 pub mod activity;
+pub mod broadcast;
 pub mod client;
+pub mod clipboard;
+pub mod command_history;
 pub mod connui;
 pub mod domain;
+pub mod file_transfer;
 pub mod localpane;
+pub mod monitor;
 pub mod pane;
+pub mod playback;
+pub mod recording;
 pub mod renderable;
+pub mod session;
 pub mod sigma_proxy;
 pub mod ssh;
 pub mod ssh_agent;
@@ -49,6 +64,7 @@ pub mod termwiztermtab;
 pub mod tmux;
 pub mod tmux_commands;
 mod tmux_pty;
+pub mod trigger;
 pub mod window;
 
 use crate::activity::Activity;
@@ -75,6 +91,24 @@ pub enum MuxNotification {
         selection: ClipboardSelection,
         clipboard: Option<String>,
     },
+    /// Place data for several MIME types in the clipboard
+    AssignClipboardData {
+        pane_id: PaneId,
+        selection: ClipboardSelection,
+        data: Arc<Vec<ClipboardData>>,
+    },
+    /// The application in the pane would like to read the clipboard,
+    /// which the frontend answers via the helpers in `clipboard`
+    RequestClipboard {
+        pane_id: PaneId,
+        request: ClipboardReadRequest,
+    },
+    /// The application in the pane would like to transfer files,
+    /// which the user must permit via `respond_to_file_transfer`
+    RequestFileTransfer {
+        pane_id: PaneId,
+        request: FileTransferRequest,
+    },
     SaveToDownloads {
         name: Option<String>,
         data: Arc<Vec<u8>>,
@@ -123,9 +157,13 @@ pub struct Mux {
     domains_by_name: RwLock<HashMap<String, Arc<dyn Domain>>>,
     subscribers: RwLock<SubscriberMap>,
     banner: RwLock<Option<String>>,
+    /// Banners set by set_next_pane_banner, keyed by the pane that
+    /// they were assigned to when its id was allocated
+    pane_banners: Mutex<HashMap<PaneId, String>>,
     clients: RwLock<HashMap<ClientId, ClientInfo>>,
     identity: RwLock<Option<Arc<ClientId>>>,
     num_panes_by_workspace: RwLock<HashMap<String, usize>>,
+    input_broadcast: RwLock<InputBroadcast>,
     main_thread_id: std::thread::ThreadId,
     agent: Option<AgentProxy>,
 }
@@ -344,6 +382,7 @@ fn read_from_pane_pty(
             Ok(size) => {
                 histogram!("read_from_pane_pty.bytes.rate").record(size as f64);
                 log::trace!("read_pty pane {pane_id} read {size} bytes");
+                recording::record_output(pane_id, &buf[..size]);
                 if let Err(err) = tx.write_all(&buf[..size]) {
                     error!(
                         "read_pty failed to write to parser: pane {} {:?}",
@@ -382,6 +421,11 @@ lazy_static::lazy_static! {
     static ref MUX: Mutex<Option<Arc<Mux>>> = Mutex::new(None);
 }
 
+thread_local! {
+    /// See Mux::set_next_pane_banner
+    static NEXT_PANE_BANNER: std::cell::RefCell<Option<String>> = std::cell::RefCell::new(None);
+}
+
 pub struct MuxWindowBuilder {
     window_id: WindowId,
     activity: Option<Activity>,
@@ -469,9 +513,11 @@ impl Mux {
             domains: RwLock::new(domains),
             subscribers: RwLock::new(HashMap::new()),
             banner: RwLock::new(None),
+            pane_banners: Mutex::new(HashMap::new()),
             clients: RwLock::new(HashMap::new()),
             identity: RwLock::new(None),
             num_panes_by_workspace: RwLock::new(HashMap::new()),
+            input_broadcast: RwLock::new(InputBroadcast::default()),
             main_thread_id: std::thread::current().id(),
             agent,
         }
@@ -538,6 +584,7 @@ impl Mux {
     }
 
     pub fn record_focus_for_client(&self, client_id: &ClientId, pane_id: PaneId) {
+        monitor::pane_focused(pane_id);
         let mut prior = None;
         if let Some(info) = self.clients.write().get_mut(client_id) {
             prior = info.focused_pane_id;
@@ -558,6 +605,14 @@ impl Mux {
         }
     }
 
+    /// Returns true if any client has `pane_id` as its focused pane
+    pub fn is_pane_focused(&self, pane_id: PaneId) -> bool {
+        self.clients
+            .read()
+            .values()
+            .any(|info| info.focused_pane_id == Some(pane_id))
+    }
+
     /// Called by PaneFocused event handlers to reconcile a remote
     /// pane focus event and apply its effects locally
     pub fn focus_pane_and_containing_tab(&self, pane_id: PaneId) -> anyhow::Result<()> {
@@ -600,6 +655,53 @@ impl Mux {
         self.clients.read().values().cloned().collect()
     }
 
+    /// Marks a client as the guest of a share
+    pub fn set_client_guest(&self, client_id: &ClientId, guest: GuestInfo) {
+        if let Some(info) = self.clients.write().get_mut(client_id) {
+            info.guest.replace(guest);
+        }
+    }
+
+    /// Records the viewport of a client.  The panes that it moved
+    /// away from and to are repainted so that the presence of guests
+    /// is reflected by the clients of the panes.
+    pub fn record_viewport_for_client(
+        &self,
+        client_id: &ClientId,
+        viewport: Option<ClientViewport>,
+    ) {
+        let prior = match self.clients.write().get_mut(client_id) {
+            Some(info) => std::mem::replace(&mut info.viewport, viewport),
+            None => return,
+        };
+        if prior == viewport {
+            return;
+        }
+        for pane_id in [prior, viewport].iter().flatten().map(|v| v.pane_id) {
+            self.notify(MuxNotification::PaneOutput(pane_id));
+        }
+    }
+
+    /// Returns the guests whose viewport is in the specified pane
+    pub fn pane_guests(&self, pane_id: PaneId) -> Vec<GuestPresence> {
+        let mut guests: Vec<GuestPresence> = self
+            .clients
+            .read()
+            .values()
+            .filter_map(|info| match (&info.guest, info.viewport) {
+                (Some(guest), Some(viewport)) if viewport.pane_id == pane_id => {
+                    Some(GuestPresence {
+                        guest: guest.clone(),
+                        viewport,
+                    })
+                }
+                _ => None,
+            })
+            .collect();
+        guests.sort_by(|a, b| a.guest.share_id.cmp(&b.guest.share_id));
+        guests
+    }
+
     /// Returns a list of the unique workspace names known to the mux.
     /// This is taken from all known windows.
     pub fn iter_workspaces(&self) -> Vec<String> {
@@ -678,6 +780,9 @@ impl Mux {
             }
         }
         self.recompute_pane_count();
+        self.input_broadcast
+            .write()
+            .rename_workspace(old_workspace, new_workspace);
         for client in self.clients.write().values_mut() {
             if client.active_workspace.as_deref() == Some(old_workspace) {
                 client.active_workspace.replace(new_workspace.to_string());
@@ -708,7 +813,10 @@ impl Mux {
     }
 
     pub fn unregister_client(&self, client_id: &ClientId) {
-        self.clients.write().remove(client_id);
+        let removed = self.clients.write().remove(client_id);
+        if let Some(viewport) = removed.and_then(|info| info.viewport) {
+            self.notify(MuxNotification::PaneOutput(viewport.pane_id));
+        }
     }
 
     pub fn subscribe<F>(&self, subscriber: F)
@@ -741,6 +849,16 @@ impl Mux {
         .detach();
     }
 
+    /// Asks the gui to repaint the window that contains a pane,
+    /// from any thread
+    pub(crate) fn invalidate_window_for_pane(pane_id: PaneId) {
+        if let Some(mux) = Mux::try_get() {
+            if let Some((_domain_id, window_id, _tab_id)) = mux.resolve_pane_id(pane_id) {
+                Mux::notify_from_any_thread(MuxNotification::WindowInvalidated(window_id));
+            }
+        }
+    }
+
     pub fn default_domain(&self) -> Arc<dyn Domain> {
         self.default_domain.read().as_ref().map(Arc::clone).unwrap()
     }
@@ -806,10 +924,21 @@ impl Mux {
         let downloader: Arc<dyn DownloadHandler> = Arc::new(MuxDownloader {});
         pane.set_download_handler(&downloader);
 
+        let file_transfer: Arc<dyn FileTransferHandler> = Arc::new(MuxFileTransfer {
+            pane_id: pane.pane_id(),
+        });
+        pane.set_file_transfer_handler(&file_transfer);
+
         self.panes.write().insert(pane.pane_id(), Arc::clone(pane));
         let pane_id = pane.pane_id();
         if let Some(reader) = pane.reader()? {
-            let banner = self.banner.read().clone();
+            let banner = match (
+                self.banner.read().clone(),
+                self.pane_banners.lock().remove(&pane_id),
+            ) {
+                (Some(banner), Some(next)) => Some(banner + &next),
+                (banner, next) => banner.or(next),
+            };
             let pane = Arc::downgrade(pane);
             thread::spawn(move || read_from_pane_pty(pane, banner, reader));
         }
@@ -836,6 +965,11 @@ impl Mux {
         let mut changed = false;
         if let Some(pane) = self.panes.write().remove(&pane_id).clone() {
             log::debug!("killing pane {}", pane_id);
+            self.input_broadcast.write().forget_pane(pane_id);
+            file_transfer::forget_pane(pane_id);
+            trigger::forget_pane(pane_id);
+            monitor::forget_pane(pane_id);
+            recording::forget_pane(pane_id);
             pane.kill();
             self.notify(MuxNotification::PaneRemoved(pane_id));
             changed = true;
@@ -850,6 +984,7 @@ impl Mux {
         log::debug!("remove_tab_internal tab {}", tab_id);
 
         let tab = self.tabs.write().remove(&tab_id)?;
+        self.input_broadcast.write().forget_tab(tab_id);
 
         if let Some(mut windows) = self.windows.try_write() {
             for w in windows.values_mut() {
@@ -1101,6 +1236,88 @@ impl Mux {
         Some((domain_id, window_id, tab_id))
     }
 
+    /// Toggles input broadcast for the panes in `scope`, returning
+    /// true if broadcast is now enabled for that scope.
+    pub fn toggle_input_broadcast(&self, scope: BroadcastScope) -> bool {
+        let enabled = self.input_broadcast.write().toggle(scope);
+        self.invalidate_all_windows();
+        enabled
+    }
+
+    pub fn is_input_broadcast_enabled(&self, scope: &BroadcastScope) -> bool {
+        self.input_broadcast.read().is_enabled(scope)
+    }
+
+    /// Adds or removes the pane from the set of panes that are used
+    /// by `BroadcastScope::Marked`, returning true if it is now marked.
+    pub fn toggle_pane_broadcast_mark(&self, pane_id: PaneId) -> bool {
+        let marked = self.input_broadcast.write().toggle_mark(pane_id);
+        self.invalidate_all_windows();
+        marked
+    }
+
+    pub fn is_pane_broadcast_marked(&self, pane_id: PaneId) -> bool {
+        self.input_broadcast.read().is_marked(pane_id)
+    }
+
+    /// Returns true if input sent to this pane is also sent to others
+    pub fn is_pane_broadcasting(&self, pane_id: PaneId) -> bool {
+        !self.input_broadcast_peers(pane_id).is_empty()
+    }
+
+    /// Returns the other panes that should receive a copy of any
+    /// input that is sent to `pane_id`
+    pub fn input_broadcast_targets(&self, pane_id: PaneId) -> Vec<Arc<dyn Pane>> {
+        self.input_broadcast_peers(pane_id)
+            .into_iter()
+            .filter_map(|id| self.get_pane(id))
+            .collect()
+    }
+
+    fn input_broadcast_peers(&self, pane_id: PaneId) -> Vec<PaneId> {
+        let broadcast = self.input_broadcast.read().clone();
+        if broadcast.is_idle() {
+            return vec![];
+        }
+
+        let mut peers = broadcast.marked_peers(pane_id);
+        if let Some((_domain_id, window_id, tab_id)) = self.resolve_pane_id(pane_id) {
+            let mut tabs = vec![];
+            if broadcast.is_enabled(&BroadcastScope::Tab(tab_id)) {
+                tabs.extend(self.get_tab(tab_id));
+            }
+            let workspace = self
+                .get_window(window_id)
+                .map(|window| window.get_workspace().to_string());
+            if let Some(workspace) = workspace {
+                if broadcast.is_enabled(&BroadcastScope::Workspace(workspace.clone())) {
+                    for window_id in self.iter_windows_in_workspace(&workspace) {
+                        if let Some(window) = self.get_window(window_id) {
+                            tabs.extend(window.iter().cloned());
+                        }
+                    }
+                }
+            }
+            for tab in tabs {
+                peers.extend(
+                    tab.iter_panes_ignoring_zoom()
+                        .into_iter()
+                        .map(|pos| pos.pane.pane_id()),
+                );
+            }
+        }
+
+        let mut seen = HashSet::new();
+        peers.retain(|&id| id != pane_id && seen.insert(id));
+        peers
+    }
+
+    fn invalidate_all_windows(&self) {
+        for window_id in self.iter_windows() {
+            self.notify(MuxNotification::WindowInvalidated(window_id));
+        }
+    }
+
     pub fn domain_was_detached(&self, domain: DomainId) {
         let mut dead_panes = vec![];
         for pane in self.panes.read().values() {
@@ -1130,6 +1347,21 @@ impl Mux {
         *self.banner.write() = banner;
     }
 
+    /// Feed `banner` to the parser of the next pane whose id is allocated
+    /// on the calling thread, ahead of anything read from its pty, so that
+    /// it cannot interleave with the first output of the freshly spawned
+    /// program.  The banner is tied to that pane as soon as its id is
+    /// allocated, so spawns running concurrently elsewhere can't take it.
+    pub fn set_next_pane_banner(&self, banner: Option<String>) {
+        NEXT_PANE_BANNER.with(|next| *next.borrow_mut() = banner);
+    }
+
+    pub(crate) fn assign_next_pane_banner(&self, pane_id: PaneId) {
+        if let Some(banner) = NEXT_PANE_BANNER.with(|next| next.borrow_mut().take()) {
+            self.pane_banners.lock().insert(pane_id, banner);
+        }
+    }
+
     pub fn resolve_spawn_tab_domain(
         &self,
         // TODO: disambiguate with TabId
@@ -1320,6 +1552,150 @@ impl Mux {
         Ok((tab, window_id))
     }
 
+    /// Exchanges the positions of two panes, which may be in
+    /// different tabs or windows.
+    pub async fn swap_panes(&self, pane_id: PaneId, other_pane_id: PaneId) -> anyhow::Result<()> {
+        if pane_id == other_pane_id {
+            return Ok(());
+        }
+        let (domain_id, _window, tab_id) = self
+            .resolve_pane_id(pane_id)
+            .ok_or_else(|| anyhow!("pane {} not found", pane_id))?;
+        let (_other_domain, _other_window, other_tab_id) = self
+            .resolve_pane_id(other_pane_id)
+            .ok_or_else(|| anyhow!("pane {} not found", other_pane_id))?;
+
+        let domain = self
+            .get_domain(domain_id)
+            .ok_or_else(|| anyhow!("domain {domain_id} of pane {pane_id} not found"))?;
+        if domain.swap_panes(pane_id, other_pane_id).await? {
+            return Ok(());
+        }
+
+        let tab = self
+            .get_tab(tab_id)
+            .ok_or_else(|| anyhow!("Invalid tab id {}", tab_id))?;
+        if tab_id == other_tab_id {
+            tab.swap_panes(pane_id, other_pane_id);
+            return Ok(());
+        }
+
+        let other_tab = self
+            .get_tab(other_tab_id)
+            .ok_or_else(|| anyhow!("Invalid tab id {}", other_tab_id))?;
+        let pane = self
+            .get_pane(pane_id)
+            .ok_or_else(|| anyhow!("pane {} not found", pane_id))?;
+        let other_pane = self
+            .get_pane(other_pane_id)
+            .ok_or_else(|| anyhow!("pane {} not found", other_pane_id))?;
+
+        // Check both tabs before changing either, so that a failure
+        // can't leave a pane without a tab
+        for (tab, pane_id) in [(&tab, pane_id), (&other_tab, other_pane_id)] {
+            if !tab.contains_pane(pane_id) {
+                anyhow::bail!("pane {} wasn't in its containing tab!?", pane_id);
+            }
+        }
+
+        tab.replace_pane(pane_id, Arc::clone(&other_pane))
+            .ok_or_else(|| anyhow!("pane {} wasn't in its containing tab!?", pane_id))?;
+        if other_tab
+            .replace_pane(other_pane_id, Arc::clone(&pane))
+            .is_none()
+        {
+            tab.replace_pane(other_pane_id, pane);
+            anyhow::bail!("pane {} wasn't in its containing tab!?", other_pane_id);
+        }
+        Ok(())
+    }
+
+    /// Moves a pane out of its current tab and splits it into `tab_id`
+    /// alongside the active pane of that tab.
+    pub async fn join_pane(
+        &self,
+        pane_id: PaneId,
+        tab_id: TabId,
+        request: SplitRequest,
+    ) -> anyhow::Result<(Arc<dyn Pane>, TerminalSize)> {
+        let tab = self
+            .get_tab(tab_id)
+            .ok_or_else(|| anyhow!("Invalid tab id {}", tab_id))?;
+        if tab.contains_pane(pane_id) {
+            anyhow::bail!("pane {} is already in tab {}", pane_id, tab_id);
+        }
+        let target = tab
+            .get_active_pane()
+            .ok_or_else(|| anyhow!("tab {} has no active pane", tab_id))?;
+
+        self.split_pane(
+            target.pane_id(),
+            request,
+            SplitSource::MovePane(pane_id),
+            SpawnTabDomain::CurrentPaneDomain,
+        )
+        .await
+    }
+
+    /// Moves a tab into another window, or into a new window if
+    /// `window_id` is None.  Returns the window that now holds the tab.
+    pub async fn move_tab_to_window(
+        &self,
+        tab_id: TabId,
+        window_id: Option<WindowId>,
+        workspace_for_new_window: Option<String>,
+    ) -> anyhow::Result<WindowId> {
+        let tab = self
+            .get_tab(tab_id)
+            .ok_or_else(|| anyhow!("Invalid tab id {}", tab_id))?;
+        let src_window = self
+            .window_containing_tab(tab_id)
+            .ok_or_else(|| anyhow!("tab {} is not in a window", tab_id))?;
+        if window_id == Some(src_window) {
+            return Ok(src_window);
+        }
+
+        if let Some(domain) = tab
+            .get_active_pane()
+            .and_then(|pane| self.get_domain(pane.domain_id()))
+        {
+            if let Some(window_id) = domain
+                .move_tab_to_window(tab_id, window_id, workspace_for_new_window.clone())
+                .await?
+            {
+                return Ok(window_id);
+            }
+        }
+
+        let window_builder;
+        let (window_id, size) = if let Some(window_id) = window_id {
+            let window = self
+                .get_window(window_id)
+                .ok_or_else(|| anyhow!("window_id {} not found on this server", window_id))?;
+            let size = window.get_active().map(|active| active.get_size());
+            (window_id, size)
+        } else {
+            window_builder = self.new_empty_window(workspace_for_new_window, None);
+            (*window_builder, None)
+        };
+
+        if let Some(mut window) = self.get_window_mut(src_window) {
+            window.remove_by_id(tab_id);
+        }
+        if let Some(size) = size {
+            tab.resize(size);
+        }
+        self.add_tab_to_window(&tab, window_id)?;
+        if let Some(mut window) = self.get_window_mut(window_id) {
+            if let Some(idx) = window.idx_by_id(tab_id) {
+                window.save_and_then_set_active(idx);
+            }
+        }
+        self.prune_dead_windows();
+
+        Ok(window_id)
+    }
+
     pub async fn spawn_tab_or_window(
         &self,
         request: SpawnRequest,
@@ -1463,6 +1839,9 @@ impl Clipboard for MuxClipboard {
     ) -> anyhow::Result<()> {
         let mux =
             Mux::try_get().ok_or_else(|| anyhow::anyhow!("MuxClipboard::set_contents: no Mux?"))?;
+        if let Some(text) = &clipboard {
+            self.journal_write(selection, vec!["text/plain".to_string()], text.len());
+        }
         mux.notify(MuxNotification::AssignClipboard {
             pane_id: self.pane_id,
             selection,
@@ -1470,6 +1849,51 @@ impl Clipboard for MuxClipboard {
         });
         Ok(())
     }
+
+    fn set_data(
+        &self,
+        selection: ClipboardSelection,
+        data: Vec<ClipboardData>,
+    ) -> anyhow::Result<()> {
+        let mux =
+            Mux::try_get().ok_or_else(|| anyhow::anyhow!("MuxClipboard::set_data: no Mux?"))?;
+        self.journal_write(
+            selection,
+            data.iter().map(|d| d.mime.clone()).collect(),
+            data.iter().map(|d| d.data.len()).sum(),
+        );
+        mux.notify(MuxNotification::AssignClipboardData {
+            pane_id: self.pane_id,
+            selection,
+            data: Arc::new(data),
+        });
+        Ok(())
+    }
+
+    fn request_read(&self, request: ClipboardReadRequest) -> anyhow::Result<()> {
+        let mux =
+            Mux::try_get().ok_or_else(|| anyhow::anyhow!("MuxClipboard::request_read: no Mux?"))?;
+        // The read is journaled once the user has decided whether to allow it
+        mux.notify(MuxNotification::RequestClipboard {
+            pane_id: self.pane_id,
+            request,
+        });
+        Ok(())
+    }
+}
+
+impl MuxClipboard {
+    fn journal_write(&self, selection: ClipboardSelection, mime_types: Vec<String>, bytes: usize) {
+        report_clipboard_event(SigmaClipboardEvent {
+            pane_id: self.pane_id,
+            action: SigmaClipboardAction::Write,
+            selection,
+            mime_types,
+            bytes,
+            allowed: true,
+            occurred_at: SystemTime::now(),
+        });
+    }
 }
 
 struct MuxDownloader {}
@@ -1,8 +1,10 @@
+use crate::command_history::record_finished_command;
 use crate::domain::DomainId;
 use crate::pane::{
     CachePolicy, CloseReason, ForEachPaneLogicalLine, LogicalLine, Pane, PaneId, Pattern,
     SearchResult, WithPaneLines,
 };
+use crate::recording::CommandBoundary;
 use crate::renderable::*;
 use crate::tmux::{TmuxDomain, TmuxDomainState};
 use crate::{Domain, Mux, MuxNotification};
@@ -18,8 +20,9 @@ use rangeset::RangeSet;
 use shelldone_dynamic::Value;
 use shelldone_term::color::ColorPalette;
 use shelldone_term::{
-    Alert, AlertHandler, Clipboard, DownloadHandler, KeyCode, KeyModifiers, MouseEvent, Progress,
-    SemanticZone, StableRowIndex, Terminal, TerminalConfiguration, TerminalSize,
+    Alert, AlertHandler, Clipboard, CommandBlock, DownloadHandler, FileTransferHandler, KeyCode,
+    KeyModifiers, MouseEvent, Progress, SemanticZone, StableRowIndex, Terminal,
+    TerminalConfiguration, TerminalSize,
 };
 use smol::channel::{bounded, Receiver, TryRecvError};
 use std::borrow::Cow;
@@ -379,6 +382,10 @@ impl Pane for LocalPane {
         self.terminal.lock().set_download_handler(handler);
     }
 
+    fn set_file_transfer_handler(&self, handler: &Arc<dyn FileTransferHandler>) {
+        self.terminal.lock().set_file_transfer_handler(handler);
+    }
+
     fn set_config(&self, config: Arc<dyn TerminalConfiguration>) {
         self.terminal.lock().set_config(config);
     }
@@ -388,7 +395,13 @@ impl Pane for LocalPane {
     }
 
     fn perform_actions(&self, actions: Vec<termwiz::escape::Action>) {
-        self.terminal.lock().perform_actions(actions)
+        if crate::recording::is_recording(self.pane_id) {
+            self.perform_recorded_actions(actions);
+        } else {
+            self.terminal.lock().perform_actions(actions);
+        }
+        crate::trigger::output_changed(self.pane_id);
+        crate::monitor::output_changed(self.pane_id);
     }
 
     fn mouse_event(&self, event: MouseEvent) -> Result<(), Error> {
@@ -422,6 +435,7 @@ impl Pane for LocalPane {
             pixel_height: size.pixel_height.try_into()?,
         })?;
         self.terminal.lock().resize(size);
+        crate::recording::record_resize(self.pane_id, size.cols, size.rows);
         Ok(())
     }
 
@@ -643,184 +657,197 @@ impl Pane for LocalPane {
         term.get_semantic_zones()
     }
 
+    fn get_command_blocks(&self) -> Vec<CommandBlock> {
+        self.terminal.lock().get_command_blocks()
+    }
+
     async fn search(
         &self,
         pattern: Pattern,
         range: Range<StableRowIndex>,
         limit: Option<u32>,
     ) -> anyhow::Result<Vec<SearchResult>> {
-        let term = self.terminal.lock();
-        let screen = term.screen();
+        terminal_search(&self.terminal.lock(), pattern, range, limit)
+    }
+}
 
-        enum CompiledPattern {
-            CaseSensitiveString(String),
-            CaseInSensitiveString(String),
-            Regex(Regex),
-        }
+/// Searches the lines of a terminal; this implements `Pane::search`
+/// for the panes that are backed by a local `Terminal`
+pub(crate) fn terminal_search(
+    term: &Terminal,
+    pattern: Pattern,
+    range: Range<StableRowIndex>,
+    limit: Option<u32>,
+) -> anyhow::Result<Vec<SearchResult>> {
+    let screen = term.screen();
+
+    enum CompiledPattern {
+        CaseSensitiveString(String),
+        CaseInSensitiveString(String),
+        Regex(Regex),
+    }
 
-        let pattern = match pattern {
-            Pattern::CaseSensitiveString(s) => CompiledPattern::CaseSensitiveString(s),
-            Pattern::CaseInSensitiveString(s) => {
-                // normalize the case so we match everything lowercase
-                CompiledPattern::CaseInSensitiveString(s.to_lowercase())
-            }
-            Pattern::Regex(r) => CompiledPattern::Regex(Regex::new(&r)?),
-        };
+    let pattern = match pattern {
+        Pattern::CaseSensitiveString(s) => CompiledPattern::CaseSensitiveString(s),
+        Pattern::CaseInSensitiveString(s) => {
+            // normalize the case so we match everything lowercase
+            CompiledPattern::CaseInSensitiveString(s.to_lowercase())
+        }
+        Pattern::Regex(r) => CompiledPattern::Regex(Regex::new(&r)?),
+    };
 
-        let mut results = vec![];
-        let mut uniq_matches: HashMap<String, usize> = HashMap::new();
+    let mut results = vec![];
+    let mut uniq_matches: HashMap<String, usize> = HashMap::new();
 
-        screen.for_each_logical_line_in_stable_range(range, |sr, lines| {
-            if let Some(limit) = limit {
-                if results.len() == limit as usize {
-                    // We've reach the limit, stop iteration.
-                    return false;
-                }
+    screen.for_each_logical_line_in_stable_range(range, |sr, lines| {
+        if let Some(limit) = limit {
+            if results.len() == limit as usize {
+                // We've reach the limit, stop iteration.
+                return false;
             }
+        }
 
-            if lines.is_empty() {
-                // Nothing to do on this iteration, carry on with the next.
-                return true;
+        if lines.is_empty() {
+            // Nothing to do on this iteration, carry on with the next.
+            return true;
+        }
+        let haystack = if lines.len() == 1 {
+            lines[0].as_str()
+        } else {
+            let mut s = String::new();
+            for line in lines {
+                s.push_str(&line.as_str());
             }
-            let haystack = if lines.len() == 1 {
-                lines[0].as_str()
-            } else {
-                let mut s = String::new();
-                for line in lines {
-                    s.push_str(&line.as_str());
-                }
-                Cow::Owned(s)
-            };
-            let stable_idx = sr.start;
+            Cow::Owned(s)
+        };
+        let stable_idx = sr.start;
 
-            if haystack.is_empty() {
-                return true;
-            }
+        if haystack.is_empty() {
+            return true;
+        }
 
-            let haystack = match &pattern {
-                CompiledPattern::CaseInSensitiveString(_) => Cow::Owned(haystack.to_lowercase()),
-                _ => haystack,
-            };
-            let mut coords = None;
-
-            match &pattern {
-                CompiledPattern::CaseInSensitiveString(s)
-                | CompiledPattern::CaseSensitiveString(s) => {
-                    for (idx, s) in haystack.match_indices(s) {
-                        found_match(
-                            s,
-                            idx,
-                            lines,
-                            stable_idx,
-                            &mut uniq_matches,
-                            &mut coords,
-                            &mut results,
-                        );
-                    }
+        let haystack = match &pattern {
+            CompiledPattern::CaseInSensitiveString(_) => Cow::Owned(haystack.to_lowercase()),
+            _ => haystack,
+        };
+        let mut coords = None;
+
+        match &pattern {
+            CompiledPattern::CaseInSensitiveString(s) | CompiledPattern::CaseSensitiveString(s) => {
+                for (idx, s) in haystack.match_indices(s) {
+                    found_match(
+                        s,
+                        idx,
+                        lines,
+                        stable_idx,
+                        &mut uniq_matches,
+                        &mut coords,
+                        &mut results,
+                    );
                 }
-                CompiledPattern::Regex(re) => {
-                    // Allow for the regex to contain captures
-                    for c in re.captures_iter(&haystack).flatten() {
-                        // Look for the captures in reverse order, as index==0 is
-                        // the whole matched string.  We can't just call
-                        // `c.iter().rev()` as the capture iterator isn't double-ended.
-                        for idx in (0..c.len()).rev() {
-                            if let Some(m) = c.get(idx) {
-                                found_match(
-                                    m.as_str(),
-                                    m.start(),
-                                    lines,
-                                    stable_idx,
-                                    &mut uniq_matches,
-                                    &mut coords,
-                                    &mut results,
-                                );
-                                break;
-                            }
+            }
+            CompiledPattern::Regex(re) => {
+                // Allow for the regex to contain captures
+                for c in re.captures_iter(&haystack).flatten() {
+                    // Look for the captures in reverse order, as index==0 is
+                    // the whole matched string.  We can't just call
+                    // `c.iter().rev()` as the capture iterator isn't double-ended.
+                    for idx in (0..c.len()).rev() {
+                        if let Some(m) = c.get(idx) {
+                            found_match(
+                                m.as_str(),
+                                m.start(),
+                                lines,
+                                stable_idx,
+                                &mut uniq_matches,
+                                &mut coords,
+                                &mut results,
+                            );
+                            break;
                         }
                     }
                 }
             }
+        }
 
-            // Keep iterating
-            true
-        });
+        // Keep iterating
+        true
+    });
 
-        #[derive(Copy, Clone, Debug)]
-        struct Coord {
-            byte_idx: usize,
-            grapheme_idx: usize,
-            stable_row: StableRowIndex,
-        }
+    #[derive(Copy, Clone, Debug)]
+    struct Coord {
+        byte_idx: usize,
+        grapheme_idx: usize,
+        stable_row: StableRowIndex,
+    }
 
-        fn found_match(
-            text: &str,
-            byte_idx: usize,
-            lines: &[&Line],
-            stable_idx: StableRowIndex,
-            uniq_matches: &mut HashMap<String, usize>,
-            coords: &mut Option<Vec<Coord>>,
-            results: &mut Vec<SearchResult>,
-        ) {
-            if coords.is_none() {
-                coords.replace(make_coords(lines, stable_idx));
-            }
-            let coords = coords.as_ref().unwrap();
-
-            let match_id = match uniq_matches.get(text).copied() {
-                Some(id) => id,
-                None => {
-                    let id = uniq_matches.len();
-                    uniq_matches.insert(text.to_owned(), id);
-                    id
-                }
-            };
-            let (start_x, start_y) = haystack_idx_to_coord(byte_idx, coords);
-            let (end_x, end_y) = haystack_idx_to_coord(byte_idx + text.len(), coords);
-            results.push(SearchResult {
-                start_x,
-                start_y,
-                end_x,
-                end_y,
-                match_id,
-            });
+    fn found_match(
+        text: &str,
+        byte_idx: usize,
+        lines: &[&Line],
+        stable_idx: StableRowIndex,
+        uniq_matches: &mut HashMap<String, usize>,
+        coords: &mut Option<Vec<Coord>>,
+        results: &mut Vec<SearchResult>,
+    ) {
+        if coords.is_none() {
+            coords.replace(make_coords(lines, stable_idx));
         }
-
-        fn make_coords(lines: &[&Line], stable_row: StableRowIndex) -> Vec<Coord> {
-            let mut byte_idx = 0;
-            let mut coords = vec![];
-
-            for (row_idx, line) in lines.iter().enumerate() {
-                for cell in line.visible_cells() {
-                    coords.push(Coord {
-                        byte_idx,
-                        grapheme_idx: cell.cell_index(),
-                        stable_row: stable_row + row_idx as StableRowIndex,
-                    });
-                    byte_idx += cell.str().len();
-                }
+        let coords = coords.as_ref().unwrap();
+
+        let match_id = match uniq_matches.get(text).copied() {
+            Some(id) => id,
+            None => {
+                let id = uniq_matches.len();
+                uniq_matches.insert(text.to_owned(), id);
+                id
             }
+        };
+        let (start_x, start_y) = haystack_idx_to_coord(byte_idx, coords);
+        let (end_x, end_y) = haystack_idx_to_coord(byte_idx + text.len(), coords);
+        results.push(SearchResult {
+            start_x,
+            start_y,
+            end_x,
+            end_y,
+            match_id,
+        });
+    }
 
-            coords
-        }
+    fn make_coords(lines: &[&Line], stable_row: StableRowIndex) -> Vec<Coord> {
+        let mut byte_idx = 0;
+        let mut coords = vec![];
 
-        fn haystack_idx_to_coord(idx: usize, coords: &[Coord]) -> (usize, StableRowIndex) {
-            let c = coords
-                .binary_search_by(|ele| ele.byte_idx.cmp(&idx))
-                .or_else(|i| -> Result<usize, usize> { Ok(i) })
-                .unwrap();
-            let coord = coords.get(c).copied().unwrap_or_else(|| {
-                let last = coords.last().unwrap();
-                Coord {
-                    grapheme_idx: last.grapheme_idx + 1,
-                    ..*last
-                }
-            });
-            (coord.grapheme_idx, coord.stable_row)
+        for (row_idx, line) in lines.iter().enumerate() {
+            for cell in line.visible_cells() {
+                coords.push(Coord {
+                    byte_idx,
+                    grapheme_idx: cell.cell_index(),
+                    stable_row: stable_row + row_idx as StableRowIndex,
+                });
+                byte_idx += cell.str().len();
+            }
         }
 
-        Ok(results)
+        coords
+    }
+
+    fn haystack_idx_to_coord(idx: usize, coords: &[Coord]) -> (usize, StableRowIndex) {
+        let c = coords
+            .binary_search_by(|ele| ele.byte_idx.cmp(&idx))
+            .or_else(|i| -> Result<usize, usize> { Ok(i) })
+            .unwrap();
+        let coord = coords.get(c).copied().unwrap_or_else(|| {
+            let last = coords.last().unwrap();
+            Coord {
+                grapheme_idx: last.grapheme_idx + 1,
+                ..*last
+            }
+        });
+        (coord.grapheme_idx, coord.stable_row)
     }
+
+    Ok(results)
 }
 
 struct LocalPaneDCSHandler {
@@ -941,6 +968,11 @@ impl AlertHandler for LocalPaneNotifHandler {
                         }
                     }
                 }
+                Alert::CommandFinished(block) => {
+                    if let Some(pane) = mux.get_pane(pane_id) {
+                        record_finished_command(&pane, block);
+                    }
+                }
                 _ => {}
             }
 
@@ -1021,6 +1053,31 @@ impl LocalPane {
         }
     }
 
+    /// Runs `func` with the terminal locked
+    pub(crate) fn with_terminal<R>(&self, func: impl FnOnce(&mut Terminal) -> R) -> R {
+        func(&mut self.terminal.lock())
+    }
+
+    /// Applies actions to the terminal while the pane is being recorded.
+    /// The output itself was recorded as it was read from the pty; the
+    /// actions are split after each OSC 133 command boundary, so that
+    /// the marker that describes the command can be taken from the
+    /// command block that the escape updated.
+    fn perform_recorded_actions(&self, actions: Vec<termwiz::escape::Action>) {
+        let mut terminal = self.terminal.lock();
+        let mut batch = vec![];
+        for action in actions {
+            let boundary = CommandBoundary::from_action(&action);
+            batch.push(action);
+            if let Some(boundary) = boundary {
+                terminal.perform_actions(std::mem::take(&mut batch));
+                let block = terminal.get_command_blocks().pop();
+                crate::recording::record_command_boundary(self.pane_id, boundary, block.as_ref());
+            }
+        }
+        terminal.perform_actions(batch);
+    }
+
     #[cfg(unix)]
     fn get_leader(&self, policy: CachePolicy) -> CachedLeaderInfo {
         let mut leader = self.leader.lock();
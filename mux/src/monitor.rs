@@ -0,0 +1,306 @@
+//! Monitors panes for activity and for silence, in the manner of the
+//! `monitor-activity` and `monitor-silence` options of tmux.
+//!
+//! An activity monitor alerts when a pane that is not focused by any
+//! client produces output.  A silence monitor alerts when a pane has
+//! produced no output for the monitored interval; the classic signal
+//! that a long running build has finished.  Each monitor alerts once,
+//! and is then re-armed by the pane being focused (activity) or by it
+//! producing output again (silence).
+//!
+//! The alerts are delivered as `MuxNotification::Alert`, so they reach
+//! the gui of a remote mux client in the same way as the bell.  A pane
+//! that alerted while it was not focused has a pending alert, which
+//! is shown in the tab bar until the pane is focused.
+use crate::pane::PaneId;
+use crate::{Mux, MuxNotification};
+use config::configuration;
+use parking_lot::Mutex;
+use serde::{Deserialize, Serialize};
+use shelldone_term::Alert;
+use std::collections::HashMap;
+use std::sync::OnceLock;
+use std::time::{Duration, Instant};
+
+/// The monitors that are enabled for a pane.
+/// This type is used directly by the codec, take care to bump
+/// the codec version if you change this
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PaneMonitors {
+    /// Alert when the pane produces output while it is not focused
+    pub activity: bool,
+    /// Alert when the pane has produced no output for this long
+    pub silence: Option<Duration>,
+}
+
+impl PaneMonitors {
+    /// Returns the monitors from the `monitor_activity` and
+    /// `monitor_silence_secs` configuration
+    pub fn from_config() -> Self {
+        let config = configuration();
+        Self {
+            activity: config.monitor_activity,
+            silence: config
+                .monitor_silence_secs
+                .filter(|&secs| secs > 0)
+                .map(Duration::from_secs),
+        }
+    }
+
+    pub fn is_idle(&self) -> bool {
+        !self.activity && self.silence.is_none()
+    }
+}
+
+/// Identifies the monitor that raised an alert
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum MonitorAlert {
+    Activity,
+    Silence,
+}
+
+impl MonitorAlert {
+    pub fn from_alert(alert: &Alert) -> Option<Self> {
+        match alert {
+            Alert::Activity => Some(Self::Activity),
+            Alert::Silence(_) => Some(Self::Silence),
+            _ => None,
+        }
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Activity => "activity",
+            Self::Silence => "silence",
+        }
+    }
+}
+
+#[derive(Default)]
+struct MonitorState {
+    /// Overrides the configured monitors for the pane
+    monitors: Option<PaneMonitors>,
+    last_output: Option<Instant>,
+    /// Whether a silence timer is running for the pane
+    silence_timer: bool,
+    /// Whether the silence monitor alerted since the last output
+    silence_alerted: bool,
+    /// Whether a check for the focus of the pane has been
+    /// scheduled by the activity monitor
+    activity_check: bool,
+    pending: Option<MonitorAlert>,
+}
+
+impl MonitorState {
+    fn monitors(&self) -> PaneMonitors {
+        self.monitors.unwrap_or_else(PaneMonitors::from_config)
+    }
+}
+
+fn states() -> &'static Mutex<HashMap<PaneId, MonitorState>> {
+    static STATES: OnceLock<Mutex<HashMap<PaneId, MonitorState>>> = OnceLock::new();
+    STATES.get_or_init(|| Mutex::new(HashMap::new()))
+}
+
+/// Returns the monitors that are in effect for a pane
+pub fn pane_monitors(pane_id: PaneId) -> PaneMonitors {
+    states()
+        .lock()
+        .get(&pane_id)
+        .and_then(|state| state.monitors)
+        .unwrap_or_else(PaneMonitors::from_config)
+}
+
+/// Overrides the configured monitors for a pane.
+/// The silence interval is measured from the time that this is called.
+pub fn set_pane_monitors(pane_id: PaneId, monitors: PaneMonitors) {
+    let start_timer = {
+        let mut states = states().lock();
+        let state = states.entry(pane_id).or_default();
+        state.monitors = Some(monitors);
+        state.silence_alerted = false;
+        state.last_output = Some(Instant::now());
+        let disabled = match state.pending {
+            Some(MonitorAlert::Activity) => !monitors.activity,
+            Some(MonitorAlert::Silence) => monitors.silence.is_none(),
+            None => false,
+        };
+        if disabled {
+            state.pending.take();
+        }
+        monitors.silence.is_some() && !std::mem::replace(&mut state.silence_timer, true)
+    };
+    if start_timer {
+        schedule_silence_timer(pane_id);
+    }
+    Mux::invalidate_window_for_pane(pane_id);
+}
+
+/// Returns the alert that was raised for a pane that has not
+/// been focused since
+pub fn pending_alert(pane_id: PaneId) -> Option<MonitorAlert> {
+    states()
+        .lock()
+        .get(&pane_id)
+        .and_then(|state| state.pending)
+}
+
+/// Records an alert that was raised for a pane by a monitor that
+/// runs elsewhere, such as in a remote mux server
+pub fn record_alert(pane_id: PaneId, alert: MonitorAlert) {
+    states().lock().entry(pane_id).or_default().pending = Some(alert);
+}
+
+/// Called when a client focuses a pane; clears its pending alert
+pub(crate) fn pane_focused(pane_id: PaneId) {
+    let cleared = states()
+        .lock()
+        .get_mut(&pane_id)
+        .and_then(|state| state.pending.take())
+        .is_some();
+    if cleared {
+        Mux::invalidate_window_for_pane(pane_id);
+    }
+}
+
+/// Called after output has been applied to the terminal of a local pane
+pub(crate) fn output_changed(pane_id: PaneId) {
+    let mut states = states().lock();
+    if !states.contains_key(&pane_id) && PaneMonitors::from_config().is_idle() {
+        return;
+    }
+    let state = states.entry(pane_id).or_default();
+    let monitors = state.monitors();
+    state.last_output = Some(Instant::now());
+    state.silence_alerted = false;
+
+    let start_timer =
+        monitors.silence.is_some() && !std::mem::replace(&mut state.silence_timer, true);
+    let check_activity = monitors.activity
+        && state.pending != Some(MonitorAlert::Activity)
+        && !std::mem::replace(&mut state.activity_check, true);
+    drop(states);
+
+    if start_timer {
+        schedule_silence_timer(pane_id);
+    }
+    if check_activity {
+        promise::spawn::spawn_into_main_thread(async move {
+            let Some(mux) = Mux::try_get() else {
+                return;
+            };
+            let alert = {
+                let mut states = states().lock();
+                let Some(state) = states.get_mut(&pane_id) else {
+                    return;
+                };
+                state.activity_check = false;
+                state.monitors().activity
+                    && state.pending != Some(MonitorAlert::Activity)
+                    && !mux.is_pane_focused(pane_id)
+            };
+            if alert {
+                raise_alert(&mux, pane_id, Alert::Activity);
+            }
+        })
+        .detach();
+    }
+}
+
+/// Discards the state that is held for a pane that has been removed
+pub(crate) fn forget_pane(pane_id: PaneId) {
+    states().lock().remove(&pane_id);
+}
+
+enum SilenceStep {
+    Wait(Instant),
+    Alert(Duration),
+    Stop,
+}
+
+/// Waits until the pane has been silent for the monitored interval.
+/// Output only moves the deadline along, so there is a single timer
+/// per pane no matter how much output it produces.
+fn schedule_silence_timer(pane_id: PaneId) {
+    promise::spawn::spawn_into_main_thread(async move {
+        loop {
+            let step = {
+                let mut states = states().lock();
+                let Some(state) = states.get_mut(&pane_id) else {
+                    return;
+                };
+                match (state.monitors().silence, state.last_output) {
+                    (Some(interval), Some(last_output)) if !state.silence_alerted => {
+                        let deadline = last_output + interval;
+                        if deadline > Instant::now() {
+                            SilenceStep::Wait(deadline)
+                        } else {
+                            state.silence_alerted = true;
+                            state.silence_timer = false;
+                            SilenceStep::Alert(interval)
+                        }
+                    }
+                    _ => {
+                        state.silence_timer = false;
+                        SilenceStep::Stop
+                    }
+                }
+            };
+            match step {
+                SilenceStep::Wait(deadline) => {
+                    smol::Timer::at(deadline).await;
+                }
+                SilenceStep::Alert(interval) => {
+                    if let Some(mux) = Mux::try_get() {
+                        raise_alert(&mux, pane_id, Alert::Silence(interval));
+                    }
+                    return;
+                }
+                SilenceStep::Stop => return,
+            }
+        }
+    })
+    .detach();
+}
+
+fn raise_alert(mux: &Mux, pane_id: PaneId, alert: Alert) {
+    if mux.get_pane(pane_id).is_none() {
+        return;
+    }
+    if !mux.is_pane_focused(pane_id) {
+        if let Some(kind) = MonitorAlert::from_alert(&alert) {
+            record_alert(pane_id, kind);
+        }
+    }
+    log::trace!("pane {pane_id} monitor alert {alert:?}");
+    mux.notify(MuxNotification::Alert { pane_id, alert });
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn pending_alert_is_cleared_by_focus_or_by_disabling_the_monitor() {
+        // Well clear of the ids that other tests may allocate
+        let pane_id = 1_000_000;
+        let monitors = PaneMonitors {
+            activity: true,
+            silence: None,
+        };
+        set_pane_monitors(pane_id, monitors);
+        assert_eq!(pane_monitors(pane_id), monitors);
+
+        record_alert(pane_id, MonitorAlert::Activity);
+        assert_eq!(pending_alert(pane_id), Some(MonitorAlert::Activity));
+        pane_focused(pane_id);
+        assert_eq!(pending_alert(pane_id), None);
+
+        record_alert(pane_id, MonitorAlert::Activity);
+        set_pane_monitors(pane_id, PaneMonitors::default());
+        assert_eq!(pending_alert(pane_id), None);
+
+        forget_pane(pane_id);
+        assert_eq!(pane_monitors(pane_id), PaneMonitors::from_config());
+    }
+}
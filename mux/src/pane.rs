@@ -1,4 +1,7 @@
+use crate::client::GuestPresence;
 use crate::domain::DomainId;
+use crate::monitor::PaneMonitors;
+use crate::recording::{RecordingOptions, RecordingSink};
 use crate::renderable::*;
 use crate::ExitBehavior;
 use async_trait::async_trait;
@@ -10,8 +13,8 @@ use serde::{Deserialize, Serialize};
 use shelldone_dynamic::Value;
 use shelldone_term::color::ColorPalette;
 use shelldone_term::{
-    Clipboard, DownloadHandler, KeyCode, KeyModifiers, MouseEvent, Progress, SemanticZone,
-    StableRowIndex, TerminalConfiguration, TerminalSize,
+    Clipboard, CommandBlock, DownloadHandler, FileTransferHandler, KeyCode, KeyModifiers,
+    MouseEvent, Progress, SemanticZone, StableRowIndex, TerminalConfiguration, TerminalSize,
 };
 use std::collections::HashMap;
 use std::ops::Range;
@@ -25,7 +28,11 @@ static PANE_ID: ::std::sync::atomic::AtomicUsize = ::std::sync::atomic::AtomicUs
 pub type PaneId = usize;
 
 pub fn alloc_pane_id() -> PaneId {
-    PANE_ID.fetch_add(1, ::std::sync::atomic::Ordering::Relaxed)
+    let pane_id = PANE_ID.fetch_add(1, ::std::sync::atomic::Ordering::Relaxed);
+    if let Some(mux) = crate::Mux::try_get() {
+        mux.assign_next_pane_banner(pane_id);
+    }
+    pane_id
 }
 
 #[derive(Clone, Copy, Debug, Eq, PartialEq)]
@@ -271,10 +278,54 @@ pub trait Pane: Downcast + Send + Sync {
     /// for the current identity
     fn advise_focus(&self) {}
 
+    /// Called to advise remote mux of the first row of this pane that
+    /// is displayed, or None when following the output, and of the
+    /// position of the mouse pointer in it, so that the owner of a
+    /// shared pane can see what its guests are looking at
+    fn advise_viewport(
+        &self,
+        _top: Option<StableRowIndex>,
+        _pointer: Option<(usize, StableRowIndex)>,
+    ) {
+    }
+
+    /// Returns the guests of shares that are looking at this pane
+    fn get_guests(&self) -> Vec<GuestPresence> {
+        match crate::Mux::try_get() {
+            Some(mux) => mux.pane_guests(self.pane_id()),
+            None => vec![],
+        }
+    }
+
     fn has_unseen_output(&self) -> bool {
         false
     }
 
+    /// Returns the activity and silence monitors of the pane
+    fn get_monitors(&self) -> PaneMonitors {
+        crate::monitor::pane_monitors(self.pane_id())
+    }
+
+    /// Changes the activity and silence monitors of the pane
+    fn set_monitors(&self, monitors: PaneMonitors) {
+        crate::monitor::set_pane_monitors(self.pane_id(), monitors);
+    }
+
+    /// Starts recording the pane, delivering the asciicast events to
+    /// `sink`.  Replaces any recording that is already running.
+    fn start_recording(&self, options: RecordingOptions, sink: RecordingSink) {
+        crate::recording::start_recording(self.pane_id(), options, sink);
+    }
+
+    /// Stops recording the pane.  Returns false if it was not being recorded.
+    fn stop_recording(&self) -> bool {
+        crate::recording::stop_recording(self.pane_id())
+    }
+
+    fn is_recording(&self) -> bool {
+        crate::recording::is_recording(self.pane_id())
+    }
+
     /// Certain panes are OK to be closed with impunity (no prompts)
     fn can_close_without_prompting(&self, _reason: CloseReason) -> bool {
         false
@@ -304,6 +355,12 @@ pub trait Pane: Downcast + Send + Sync {
         Ok(vec![])
     }
 
+    /// Retrieve the commands that were run at the shell prompt,
+    /// as delimited by OSC 133, oldest first
+    fn get_command_blocks(&self) -> Vec<CommandBlock> {
+        vec![]
+    }
+
     /// Returns true if the terminal has grabbed the mouse and wants to
     /// give the embedded application a chance to process events.
     /// In practice this controls whether the gui will perform local
@@ -313,6 +370,7 @@ pub trait Pane: Downcast + Send + Sync {
 
     fn set_clipboard(&self, _clipboard: &Arc<dyn Clipboard>) {}
     fn set_download_handler(&self, _handler: &Arc<dyn DownloadHandler>) {}
+    fn set_file_transfer_handler(&self, _handler: &Arc<dyn FileTransferHandler>) {}
     fn set_config(&self, _config: Arc<dyn TerminalConfiguration>) {}
     fn get_config(&self) -> Option<Arc<dyn TerminalConfiguration>> {
         None
@@ -0,0 +1,992 @@
+//! Plays back asciicast recordings in read-only panes.
+//!
+//! The `playback` domain opens the `.cast` file that is passed as the
+//! command of a spawn, such as
+//! `shelldone cli spawn --domain-name playback -- incident.cast`,
+//! and replays it into the terminal of a pane.  Both the v2 and v3
+//! asciicast formats are accepted.
+//!
+//! The playback can be paused, seeked to a timestamp or to a marker,
+//! sped up or slowed down, and the idle time between events can be
+//! compressed.  Seeking backwards resets the terminal and replays the
+//! events up to the new position, so the scrollback always holds the
+//! output that was recorded before the position, where it can be
+//! searched and selected with copy mode.
+use crate::domain::{alloc_domain_id, Domain, DomainId, DomainState};
+use crate::localpane::terminal_search;
+use crate::pane::{
+    alloc_pane_id, CachePolicy, CloseReason, ForEachPaneLogicalLine, LogicalLine, Pane, PaneId,
+    Pattern, SearchResult, WithPaneLines,
+};
+use crate::renderable::*;
+use crate::{Mux, MuxNotification};
+use anyhow::{bail, Context};
+use async_trait::async_trait;
+use parking_lot::{Condvar, MappedMutexGuard, Mutex, MutexGuard};
+use portable_pty::CommandBuilder;
+use rangeset::RangeSet;
+use serde::Deserialize;
+use shelldone_term::color::ColorPalette;
+use shelldone_term::{
+    KeyCode, KeyModifiers, MouseEvent, StableRowIndex, Terminal, TerminalConfiguration,
+    TerminalSize,
+};
+use std::ops::Range;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use termwiz::escape::parser::Parser;
+use termwiz::escape::{Action, Esc, EscCode};
+use termwiz::surface::{Line, SequenceNo};
+use url::Url;
+
+/// The idle time limit that is used when idle time compression is
+/// turned on for a recording that does not specify one
+const DEFAULT_IDLE_TIME_LIMIT: f64 = 2.0;
+
+/// The distance that the arrow keys seek by
+const SEEK_STEP: f64 = 5.0;
+const LONG_SEEK_STEP: f64 = 30.0;
+
+const MIN_SPEED: f64 = 0.25;
+const MAX_SPEED: f64 = 16.0;
+
+#[derive(Deserialize, Debug)]
+struct CastTerm {
+    cols: usize,
+    rows: usize,
+}
+
+/// The fields of the header of both v2 and v3 asciicasts
+/// that are used for playback
+#[derive(Deserialize, Debug)]
+struct CastHeader {
+    version: u32,
+    /// v2
+    #[serde(default)]
+    width: Option<usize>,
+    /// v2
+    #[serde(default)]
+    height: Option<usize>,
+    /// v3
+    #[serde(default)]
+    term: Option<CastTerm>,
+    #[serde(default)]
+    title: Option<String>,
+    #[serde(default)]
+    idle_time_limit: Option<f64>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct CastEvent {
+    /// The number of seconds since the previous event
+    pub interval: f64,
+    pub code: String,
+    pub data: String,
+}
+
+/// A parsed asciicast
+#[derive(Debug, Clone, PartialEq)]
+pub struct Cast {
+    pub cols: usize,
+    pub rows: usize,
+    pub title: Option<String>,
+    pub idle_time_limit: Option<f64>,
+    pub events: Vec<CastEvent>,
+}
+
+impl Cast {
+    pub fn load(path: &Path) -> anyhow::Result<Self> {
+        let text =
+            std::fs::read_to_string(path).with_context(|| format!("reading {}", path.display()))?;
+        Self::parse(&text).with_context(|| format!("parsing {}", path.display()))
+    }
+
+    pub fn parse(text: &str) -> anyhow::Result<Self> {
+        let mut lines = text.lines().filter(|line| !line.trim().is_empty());
+        let header = lines.next().context("the asciicast is empty")?;
+        let header: CastHeader = serde_json::from_str(header).context("parsing header")?;
+
+        let (cols, rows) = match (header.version, &header.term) {
+            (2, _) => (
+                header.width.context("header has no width")?,
+                header.height.context("header has no height")?,
+            ),
+            (3, Some(term)) => (term.cols, term.rows),
+            (3, None) => bail!("header has no term"),
+            (version, _) => bail!("asciicast version {version} is not supported"),
+        };
+
+        let mut events = vec![];
+        let mut prior_time = 0.;
+        for (idx, line) in lines.enumerate() {
+            // Comment lines were introduced by v3
+            if header.version == 3 && line.starts_with('#') {
+                continue;
+            }
+            let (time, code, data): (f64, String, String) =
+                serde_json::from_str(line).with_context(|| format!("parsing event {}", idx + 1))?;
+            let interval = if header.version == 2 {
+                // v2 events hold the time since the start of the recording
+                let interval = (time - prior_time).max(0.);
+                prior_time = time;
+                interval
+            } else {
+                time.max(0.)
+            };
+            events.push(CastEvent {
+                interval,
+                code,
+                data,
+            });
+        }
+
+        Ok(Self {
+            cols: cols.max(1),
+            rows: rows.max(1),
+            title: header.title,
+            idle_time_limit: header.idle_time_limit,
+            events,
+        })
+    }
+
+    /// Returns the time of each event since the start of the recording,
+    /// limiting each interval to `idle_time_limit` when it is set
+    pub fn timeline(&self, idle_time_limit: Option<f64>) -> Vec<f64> {
+        let mut time = 0.;
+        self.events
+            .iter()
+            .map(|event| {
+                time += match idle_time_limit {
+                    Some(limit) => event.interval.min(limit),
+                    None => event.interval,
+                };
+                time
+            })
+            .collect()
+    }
+}
+
+/// Parses a timestamp of the form `SECS`, `MINS:SECS` or
+/// `HOURS:MINS:SECS` into a number of seconds.
+/// The seconds may have a fractional part.
+pub fn parse_timestamp(text: &str) -> Option<f64> {
+    let mut seconds = 0.;
+    let mut fields = text.trim().rsplit(':');
+    let secs: f64 = fields.next()?.parse().ok()?;
+    if !secs.is_finite() || secs < 0. {
+        return None;
+    }
+    seconds += secs;
+    for (field, scale) in fields.zip([60., 3600.]) {
+        let value: u64 = field.parse().ok()?;
+        seconds += value as f64 * scale;
+    }
+    // At most 3 fields are accepted
+    if text.trim().split(':').count() > 3 {
+        return None;
+    }
+    Some(seconds)
+}
+
+/// Formats a number of seconds as `MM:SS`, or `H:MM:SS` past an hour
+pub fn format_timestamp(seconds: f64) -> String {
+    let total = seconds.max(0.) as u64;
+    let (hours, mins, secs) = (total / 3600, (total / 60) % 60, total % 60);
+    if hours > 0 {
+        format!("{hours}:{mins:02}:{secs:02}")
+    } else {
+        format!("{mins:02}:{secs:02}")
+    }
+}
+
+/// A marker that was recorded into a cast
+#[derive(Debug, Clone, PartialEq)]
+pub struct PlaybackMarker {
+    /// The time of the marker on the current timeline
+    pub time: f64,
+    pub label: String,
+}
+
+/// A snapshot of the transport state of a player
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct PlaybackStatus {
+    pub position: f64,
+    pub duration: f64,
+    pub paused: bool,
+    pub speed: f64,
+    /// The idle time limit, when idle time compression is on
+    pub idle_time_limit: Option<f64>,
+}
+
+struct PlayerState {
+    parser: Parser,
+    /// The time of each event on the current timeline
+    times: Vec<f64>,
+    /// The index of the next event to be applied
+    next: usize,
+    /// The position of the playback, as of `anchor` when playing
+    position: f64,
+    /// When the playback was last (re)started
+    anchor: Instant,
+    paused: bool,
+    speed: f64,
+    idle_time_limit: Option<f64>,
+    /// The terminal size of the pane, from which the pixel
+    /// dimensions of the terminal are derived
+    pane_size: TerminalSize,
+    closed: bool,
+}
+
+impl PlayerState {
+    fn duration(&self) -> f64 {
+        self.times.last().copied().unwrap_or(0.)
+    }
+
+    fn position_at(&self, now: Instant) -> f64 {
+        if self.paused {
+            self.position
+        } else {
+            let elapsed = now.saturating_duration_since(self.anchor).as_secs_f64();
+            (self.position + elapsed * self.speed).min(self.duration())
+        }
+    }
+
+    /// Freezes the current position so that the speed or the
+    /// timeline can be changed from this point on
+    fn rebase(&mut self) {
+        let now = Instant::now();
+        self.position = self.position_at(now);
+        self.anchor = now;
+    }
+}
+
+/// Replays a cast into a terminal
+pub struct Player {
+    pane_id: PaneId,
+    path: PathBuf,
+    cast: Cast,
+    terminal: Mutex<Terminal>,
+    state: Mutex<PlayerState>,
+    wakeup: Condvar,
+}
+
+impl Player {
+    fn new(pane_id: PaneId, path: PathBuf, cast: Cast, size: TerminalSize) -> Self {
+        let idle_time_limit = cast.idle_time_limit;
+        let times = cast.timeline(idle_time_limit);
+        let terminal = Terminal::new(
+            Self::terminal_size(&size, cast.cols, cast.rows),
+            Arc::new(config::TermConfig::new()),
+            "Shelldone",
+            config::shelldone_version(),
+            Box::new(std::io::sink()),
+        );
+        Self {
+            pane_id,
+            path,
+            cast,
+            terminal: Mutex::new(terminal),
+            state: Mutex::new(PlayerState {
+                parser: Parser::new(),
+                times,
+                next: 0,
+                position: 0.,
+                anchor: Instant::now(),
+                paused: false,
+                speed: 1.,
+                idle_time_limit,
+                pane_size: size,
+                closed: false,
+            }),
+            wakeup: Condvar::new(),
+        }
+    }
+
+    /// The terminal keeps the geometry of the recording, whatever the
+    /// size of the pane, so that the output is laid out as it was recorded
+    fn terminal_size(pane_size: &TerminalSize, cols: usize, rows: usize) -> TerminalSize {
+        let cell_width = pane_size.pixel_width / pane_size.cols.max(1);
+        let cell_height = pane_size.pixel_height / pane_size.rows.max(1);
+        TerminalSize {
+            cols,
+            rows,
+            pixel_width: cell_width * cols,
+            pixel_height: cell_height * rows,
+            dpi: pane_size.dpi,
+        }
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    pub fn status(&self) -> PlaybackStatus {
+        let state = self.state.lock();
+        PlaybackStatus {
+            position: state.position_at(Instant::now()),
+            duration: state.duration(),
+            paused: state.paused,
+            speed: state.speed,
+            idle_time_limit: state.idle_time_limit,
+        }
+    }
+
+    /// Returns the markers of the cast, in order
+    pub fn markers(&self) -> Vec<PlaybackMarker> {
+        let state = self.state.lock();
+        self.cast
+            .events
+            .iter()
+            .zip(state.times.iter())
+            .filter(|(event, _)| event.code == "m")
+            .map(|(event, &time)| PlaybackMarker {
+                time,
+                label: event.data.clone(),
+            })
+            .collect()
+    }
+
+    pub fn set_paused(&self, paused: bool) {
+        let mut state = self.state.lock();
+        state.rebase();
+        if !paused && state.position >= state.duration() {
+            // Play from the start again once the end was reached
+            self.seek_locked(&mut state, 0.);
+        }
+        state.paused = paused;
+        self.changed(state);
+    }
+
+    pub fn toggle_paused(&self) {
+        let paused = self.state.lock().paused;
+        self.set_paused(!paused);
+    }
+
+    pub fn set_speed(&self, speed: f64) {
+        let mut state = self.state.lock();
+        state.rebase();
+        state.speed = speed.clamp(MIN_SPEED, MAX_SPEED);
+        self.changed(state);
+    }
+
+    /// Turns idle time compression on or off.  When it is turned on
+    /// the idle time limit of the cast is used, or else 2 seconds.
+    pub fn set_idle_time_compression(&self, enable: bool) {
+        let mut state = self.state.lock();
+        state.idle_time_limit = if enable {
+            Some(self.cast.idle_time_limit.unwrap_or(DEFAULT_IDLE_TIME_LIMIT))
+        } else {
+            None
+        };
+        state.times = self.cast.timeline(state.idle_time_limit);
+        // Stay at the most recently applied event
+        state.position = match state.next {
+            0 => 0.,
+            next => state.times[next - 1],
+        };
+        state.anchor = Instant::now();
+        self.changed(state);
+    }
+
+    /// Seeks to a number of seconds since the start of the recording,
+    /// on the current timeline
+    pub fn seek(&self, position: f64) {
+        let mut state = self.state.lock();
+        self.seek_locked(&mut state, position);
+        self.changed(state);
+    }
+
+    pub fn seek_relative(&self, delta: f64) {
+        let position = self.status().position + delta;
+        self.seek(position);
+    }
+
+    /// Seeks to the next marker, or the previous one when `forward`
+    /// is false.  Returns false if there is no such marker.
+    pub fn seek_marker(&self, forward: bool) -> bool {
+        let position = self.status().position;
+        let markers = self.markers();
+        // Allow some slack so that seeking back from just after
+        // a marker goes to the one before it
+        let target = if forward {
+            markers.iter().find(|m| m.time > position + 0.001)
+        } else {
+            markers.iter().rev().find(|m| m.time < position - 0.5)
+        };
+        match target {
+            Some(marker) => {
+                self.seek(marker.time);
+                true
+            }
+            None => false,
+        }
+    }
+
+    fn seek_locked(&self, state: &mut PlayerState, position: f64) {
+        let position = position.clamp(0., state.duration());
+        let mut terminal = self.terminal.lock();
+        let applied_time = match state.next {
+            0 => 0.,
+            next => state.times[next - 1],
+        };
+        if position < applied_time {
+            terminal.perform_actions(vec![Action::Esc(Esc::Code(EscCode::FullReset))]);
+            terminal.erase_scrollback_and_viewport();
+            terminal.resize(Self::terminal_size(
+                &state.pane_size,
+                self.cast.cols,
+                self.cast.rows,
+            ));
+            state.parser = Parser::new();
+            state.next = 0;
+        }
+        self.apply_until(state, &mut terminal, position);
+        state.position = position;
+        state.anchor = Instant::now();
+    }
+
+    /// Applies the events up to and including `position`.
+    /// Returns true if any were applied.
+    fn apply_until(&self, state: &mut PlayerState, terminal: &mut Terminal, position: f64) -> bool {
+        let start = state.next;
+        let mut actions = vec![];
+        while state.next < self.cast.events.len() && state.times[state.next] <= position {
+            let event = &self.cast.events[state.next];
+            state.next += 1;
+            match event.code.as_str() {
+                "o" => state
+                    .parser
+                    .parse(event.data.as_bytes(), |action| actions.push(action)),
+                "r" => {
+                    let Some((cols, rows)) = event.data.split_once('x') else {
+                        continue;
+                    };
+                    let (Ok(cols), Ok(rows)) = (cols.parse::<usize>(), rows.parse::<usize>())
+                    else {
+                        continue;
+                    };
+                    terminal.perform_actions(std::mem::take(&mut actions));
+                    terminal.resize(Self::terminal_size(
+                        &state.pane_size,
+                        cols.max(1),
+                        rows.max(1),
+                    ));
+                }
+                _ => {}
+            }
+        }
+        if !actions.is_empty() {
+            terminal.perform_actions(actions);
+        }
+        state.next != start
+    }
+
+    /// Wakes the playback thread and repaints the pane
+    fn changed(&self, state: MutexGuard<PlayerState>) {
+        drop(state);
+        self.wakeup.notify_all();
+        Mux::notify_from_any_thread(MuxNotification::PaneOutput(self.pane_id));
+    }
+
+    fn close(&self) {
+        self.state.lock().closed = true;
+        self.wakeup.notify_all();
+    }
+
+    fn is_closed(&self) -> bool {
+        self.state.lock().closed
+    }
+
+    /// Applies the events as they fall due, until the pane is closed
+    fn run(&self) {
+        let mut state = self.state.lock();
+        let mut last_second = None;
+        while !state.closed {
+            let now = Instant::now();
+            let position = state.position_at(now);
+            let applied = {
+                let mut terminal = self.terminal.lock();
+                self.apply_until(&mut state, &mut terminal, position)
+            };
+
+            // Repaint when output was applied, and once a second for
+            // the sake of the position that is shown in the title
+            let second = position as u64;
+            if applied || last_second != Some(second) {
+                last_second = Some(second);
+                Mux::notify_from_any_thread(MuxNotification::PaneOutput(self.pane_id));
+            }
+
+            if state.paused || position >= state.duration() {
+                if !state.paused {
+                    state.rebase();
+                    state.paused = true;
+                    Mux::notify_from_any_thread(MuxNotification::PaneOutput(self.pane_id));
+                }
+                self.wakeup.wait(&mut state);
+                continue;
+            }
+
+            let until_next = state
+                .times
+                .get(state.next)
+                .map(|&time| (time - position).max(0.) / state.speed)
+                .unwrap_or(1.);
+            let wait = Duration::from_secs_f64(until_next.min(1.));
+            self.wakeup.wait_for(&mut state, wait);
+        }
+    }
+
+    /// Handles the transport keys of a playback pane.
+    /// Returns false if the key is not a transport key.
+    pub fn key_down(&self, key: KeyCode, mods: KeyModifiers) -> bool {
+        match (key, mods) {
+            (KeyCode::Char(' ') | KeyCode::Char('k'), KeyModifiers::NONE) => self.toggle_paused(),
+            (KeyCode::LeftArrow, KeyModifiers::NONE) => self.seek_relative(-SEEK_STEP),
+            (KeyCode::RightArrow, KeyModifiers::NONE) => self.seek_relative(SEEK_STEP),
+            (KeyCode::DownArrow, KeyModifiers::NONE) => self.seek_relative(-LONG_SEEK_STEP),
+            (KeyCode::UpArrow, KeyModifiers::NONE) => self.seek_relative(LONG_SEEK_STEP),
+            (KeyCode::Home, _) | (KeyCode::Char('0'), KeyModifiers::NONE) => self.seek(0.),
+            (KeyCode::End, _) => self.seek(f64::MAX),
+            (KeyCode::Char('+') | KeyCode::Char('='), _) => {
+                self.set_speed(self.status().speed * 2.)
+            }
+            (KeyCode::Char('-'), _) => self.set_speed(self.status().speed / 2.),
+            (KeyCode::Char(']'), _) => {
+                self.seek_marker(true);
+            }
+            (KeyCode::Char('['), _) => {
+                self.seek_marker(false);
+            }
+            (KeyCode::Char('i'), KeyModifiers::NONE) => {
+                self.set_idle_time_compression(self.status().idle_time_limit.is_none())
+            }
+            _ => return false,
+        }
+        true
+    }
+}
+
+/// A read-only pane that plays back an asciicast
+pub struct PlaybackPane {
+    pane_id: PaneId,
+    domain_id: DomainId,
+    player: Arc<Player>,
+    writer: Mutex<std::io::Sink>,
+}
+
+impl PlaybackPane {
+    pub fn player(&self) -> &Arc<Player> {
+        &self.player
+    }
+}
+
+#[async_trait(?Send)]
+impl Pane for PlaybackPane {
+    fn pane_id(&self) -> PaneId {
+        self.pane_id
+    }
+
+    fn get_cursor_position(&self) -> StableCursorPosition {
+        terminal_get_cursor_position(&mut self.player.terminal.lock())
+    }
+
+    fn get_current_seqno(&self) -> SequenceNo {
+        self.player.terminal.lock().current_seqno()
+    }
+
+    fn get_changed_since(
+        &self,
+        lines: Range<StableRowIndex>,
+        seqno: SequenceNo,
+    ) -> RangeSet<StableRowIndex> {
+        terminal_get_dirty_lines(&mut self.player.terminal.lock(), lines, seqno)
+    }
+
+    fn for_each_logical_line_in_stable_range_mut(
+        &self,
+        lines: Range<StableRowIndex>,
+        for_line: &mut dyn ForEachPaneLogicalLine,
+    ) {
+        terminal_for_each_logical_line_in_stable_range_mut(
+            &mut self.player.terminal.lock(),
+            lines,
+            for_line,
+        );
+    }
+
+    fn get_logical_lines(&self, lines: Range<StableRowIndex>) -> Vec<LogicalLine> {
+        crate::pane::impl_get_logical_lines_via_get_lines(self, lines)
+    }
+
+    fn with_lines_mut(&self, lines: Range<StableRowIndex>, with_lines: &mut dyn WithPaneLines) {
+        terminal_with_lines_mut(&mut self.player.terminal.lock(), lines, with_lines)
+    }
+
+    fn get_lines(&self, lines: Range<StableRowIndex>) -> (StableRowIndex, Vec<Line>) {
+        terminal_get_lines(&mut self.player.terminal.lock(), lines)
+    }
+
+    fn get_dimensions(&self) -> RenderableDimensions {
+        terminal_get_dimensions(&mut self.player.terminal.lock())
+    }
+
+    /// Shows the transport state ahead of the title of the cast
+    fn get_title(&self) -> String {
+        let status = self.player.status();
+        let title = match &self.player.cast.title {
+            Some(title) => title.clone(),
+            None => self
+                .player
+                .path
+                .file_name()
+                .map(|name| name.to_string_lossy().to_string())
+                .unwrap_or_default(),
+        };
+        format!(
+            "{} {} / {}{} {}",
+            if status.paused { "⏸" } else { "▶" },
+            format_timestamp(status.position),
+            format_timestamp(status.duration),
+            if status.speed == 1. {
+                String::new()
+            } else {
+                format!(" {}x", status.speed)
+            },
+            title
+        )
+    }
+
+    fn can_close_without_prompting(&self, _reason: CloseReason) -> bool {
+        true
+    }
+
+    /// The pane is read-only
+    fn send_paste(&self, _text: &str) -> anyhow::Result<()> {
+        Ok(())
+    }
+
+    fn reader(&self) -> anyhow::Result<Option<Box<dyn std::io::Read + Send>>> {
+        Ok(None)
+    }
+
+    fn writer(&self) -> MappedMutexGuard<'_, dyn std::io::Write> {
+        MutexGuard::map(self.writer.lock(), |writer| {
+            let w: &mut dyn std::io::Write = writer;
+            w
+        })
+    }
+
+    fn resize(&self, size: TerminalSize) -> anyhow::Result<()> {
+        self.player.state.lock().pane_size = size;
+        Ok(())
+    }
+
+    fn key_down(&self, key: KeyCode, mods: KeyModifiers) -> anyhow::Result<()> {
+        self.player.key_down(key, mods.remove_positional_mods());
+        Ok(())
+    }
+
+    fn key_up(&self, _key: KeyCode, _mods: KeyModifiers) -> anyhow::Result<()> {
+        Ok(())
+    }
+
+    fn mouse_event(&self, _event: MouseEvent) -> anyhow::Result<()> {
+        Ok(())
+    }
+
+    fn set_config(&self, config: Arc<dyn TerminalConfiguration>) {
+        self.player.terminal.lock().set_config(config);
+    }
+
+    fn get_config(&self) -> Option<Arc<dyn TerminalConfiguration>> {
+        Some(self.player.terminal.lock().get_config())
+    }
+
+    fn perform_actions(&self, actions: Vec<Action>) {
+        self.player.terminal.lock().perform_actions(actions)
+    }
+
+    fn kill(&self) {
+        self.player.close();
+    }
+
+    fn is_dead(&self) -> bool {
+        self.player.is_closed()
+    }
+
+    fn palette(&self) -> ColorPalette {
+        self.player.terminal.lock().palette()
+    }
+
+    fn domain_id(&self) -> DomainId {
+        self.domain_id
+    }
+
+    fn is_mouse_grabbed(&self) -> bool {
+        false
+    }
+
+    fn is_alt_screen_active(&self) -> bool {
+        self.player.terminal.lock().is_alt_screen_active()
+    }
+
+    fn get_current_working_dir(&self, _policy: CachePolicy) -> Option<Url> {
+        self.player.terminal.lock().get_current_dir().cloned()
+    }
+
+    fn erase_scrollback(&self, _erase_mode: config::keyassignment::ScrollbackEraseMode) {}
+
+    async fn search(
+        &self,
+        pattern: Pattern,
+        range: Range<StableRowIndex>,
+        limit: Option<u32>,
+    ) -> anyhow::Result<Vec<SearchResult>> {
+        terminal_search(&self.player.terminal.lock(), pattern, range, limit)
+    }
+}
+
+impl Drop for PlaybackPane {
+    fn drop(&mut self) {
+        self.player.close();
+    }
+}
+
+/// Opens asciicast files as read-only panes
+pub struct PlaybackDomain {
+    domain_id: DomainId,
+}
+
+impl PlaybackDomain {
+    pub const NAME: &'static str = "playback";
+
+    pub fn new() -> Self {
+        Self {
+            domain_id: alloc_domain_id(),
+        }
+    }
+}
+
+impl Default for PlaybackDomain {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[async_trait(?Send)]
+impl Domain for PlaybackDomain {
+    async fn spawn_pane(
+        &self,
+        size: TerminalSize,
+        command: Option<CommandBuilder>,
+        command_dir: Option<String>,
+    ) -> anyhow::Result<Arc<dyn Pane>> {
+        let path = match command.as_ref().and_then(|cmd| cmd.get_argv().first()) {
+            Some(path) => PathBuf::from(path),
+            None => bail!("the playback domain requires the path of an asciicast to play"),
+        };
+        let path = match command_dir {
+            Some(dir) if path.is_relative() => Path::new(&dir).join(path),
+            _ => path,
+        };
+        let cast = Cast::load(&path)?;
+
+        let pane_id = alloc_pane_id();
+        let player = Arc::new(Player::new(pane_id, path, cast, size));
+        std::thread::Builder::new()
+            .name(format!("playback pane {pane_id}"))
+            .spawn({
+                let player = Arc::clone(&player);
+                move || player.run()
+            })?;
+
+        let pane: Arc<dyn Pane> = Arc::new(PlaybackPane {
+            pane_id,
+            domain_id: self.domain_id,
+            player,
+            writer: Mutex::new(std::io::sink()),
+        });
+        Mux::get().add_pane(&pane)?;
+        Ok(pane)
+    }
+
+    /// A cast must be given in order to spawn, so the domain
+    /// is not offered in the launcher
+    fn spawnable(&self) -> bool {
+        false
+    }
+
+    fn detachable(&self) -> bool {
+        false
+    }
+
+    fn domain_id(&self) -> DomainId {
+        self.domain_id
+    }
+
+    fn domain_name(&self) -> &str {
+        Self::NAME
+    }
+
+    async fn attach(&self, _window_id: Option<crate::window::WindowId>) -> anyhow::Result<()> {
+        Ok(())
+    }
+
+    fn detach(&self) -> anyhow::Result<()> {
+        bail!("detach not implemented for PlaybackDomain");
+    }
+
+    fn state(&self) -> DomainState {
+        DomainState::Attached
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    /// Prints "one ", "two " and "three", with a marker after
+    /// each of the first two, and a long pause before "two "
+    const CAST: &str = "{\"version\": 3, \"term\": {\"cols\": 20, \"rows\": 2}}\n\
+                        [1.0, \"o\", \"one \"]\n\
+                        [0.5, \"m\", \"first\"]\n\
+                        [5.0, \"o\", \"two \"]\n\
+                        [0.5, \"m\", \"second\"]\n\
+                        [1.0, \"o\", \"three\"]\n";
+
+    /// Returns a paused player for `CAST`.  The events are applied
+    /// only by seeking, as the playback thread isn't started.
+    fn player() -> Player {
+        let size = TerminalSize {
+            cols: 20,
+            rows: 2,
+            pixel_width: 200,
+            pixel_height: 40,
+            dpi: 96,
+        };
+        let player = Player::new(
+            0,
+            PathBuf::from("test.cast"),
+            Cast::parse(CAST).unwrap(),
+            size,
+        );
+        player.set_paused(true);
+        player
+    }
+
+    fn screen_text(player: &Player) -> String {
+        player.terminal.lock().screen().visible_lines()[0]
+            .as_str()
+            .trim_end()
+            .to_string()
+    }
+
+    #[test]
+    fn seek() {
+        // Seeking notifies the mux, from the main thread
+        let _executor = promise::spawn::SimpleExecutor::new();
+        let player = player();
+
+        player.seek(1.0);
+        assert_eq!(player.status().position, 1.0);
+        assert_eq!(screen_text(&player), "one");
+
+        player.seek(f64::MAX);
+        assert_eq!(player.status().position, 8.0);
+        assert_eq!(screen_text(&player), "one two three");
+
+        // Seeking backwards replays the cast from the start
+        player.seek(1.2);
+        assert_eq!(player.status().position, 1.2);
+        assert_eq!(screen_text(&player), "one");
+
+        player.seek(-3.0);
+        assert_eq!(player.status().position, 0.);
+        assert_eq!(screen_text(&player), "");
+    }
+
+    #[test]
+    fn idle_time_compression() {
+        let _executor = promise::spawn::SimpleExecutor::new();
+        let player = player();
+
+        player.seek(6.5);
+        assert_eq!(screen_text(&player), "one two");
+
+        // The 5 second pause before "two " is shortened to 2 seconds,
+        // and the position stays at the same event
+        player.set_idle_time_compression(true);
+        let status = player.status();
+        assert_eq!(status.idle_time_limit, Some(DEFAULT_IDLE_TIME_LIMIT));
+        assert_eq!(status.position, 3.5);
+        assert_eq!(status.duration, 5.0);
+        let times: Vec<f64> = player.markers().iter().map(|m| m.time).collect();
+        assert_eq!(times, vec![1.5, 4.0]);
+        assert_eq!(screen_text(&player), "one two");
+
+        player.set_idle_time_compression(false);
+        let status = player.status();
+        assert_eq!(status.idle_time_limit, None);
+        assert_eq!(status.position, 6.5);
+        assert_eq!(status.duration, 8.0);
+        assert_eq!(screen_text(&player), "one two");
+    }
+
+    #[test]
+    fn seek_marker() {
+        let _executor = promise::spawn::SimpleExecutor::new();
+        let player = player();
+        player.seek(0.);
+
+        assert!(player.seek_marker(true));
+        assert_eq!(player.status().position, 1.5);
+        assert!(player.seek_marker(true));
+        assert_eq!(player.status().position, 7.0);
+        assert_eq!(screen_text(&player), "one two");
+        assert!(!player.seek_marker(true));
+
+        assert!(player.seek_marker(false));
+        assert_eq!(player.status().position, 1.5);
+        assert_eq!(screen_text(&player), "one");
+        assert!(!player.seek_marker(false));
+        assert_eq!(player.status().position, 1.5);
+    }
+
+    #[test]
+    fn parse_v2_and_v3() {
+        let v2 = Cast::parse(
+            "{\"version\": 2, \"width\": 80, \"height\": 24}\n\
+             [0.5, \"o\", \"hello\"]\n\
+             [1.25, \"o\", \" world\"]\n",
+        )
+        .unwrap();
+        let v3 = Cast::parse(
+            "{\"version\": 3, \"term\": {\"cols\": 80, \"rows\": 24}}\n\
+             # a comment\n\
+             [0.5, \"o\", \"hello\"]\n\
+             [0.75, \"o\", \" world\"]\n",
+        )
+        .unwrap();
+        assert_eq!(v2, v3);
+        assert_eq!(v3.timeline(None), vec![0.5, 1.25]);
+        assert_eq!(v3.timeline(Some(0.6)), vec![0.5, 1.1]);
+
+        assert!(Cast::parse("{\"version\": 1, \"width\": 80, \"height\": 24}").is_err());
+        assert!(Cast::parse("").is_err());
+    }
+
+    #[test]
+    fn timestamps() {
+        assert_eq!(parse_timestamp("90"), Some(90.));
+        assert_eq!(parse_timestamp("1:30.5"), Some(90.5));
+        assert_eq!(parse_timestamp(" 1:01:00 "), Some(3660.));
+        assert_eq!(parse_timestamp("1:2:3:4"), None);
+        assert_eq!(parse_timestamp("-3"), None);
+        assert_eq!(parse_timestamp("abc"), None);
+
+        assert_eq!(format_timestamp(90.9), "01:30");
+        assert_eq!(format_timestamp(3661.), "1:01:01");
+    }
+}
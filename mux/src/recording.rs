@@ -0,0 +1,604 @@
+//! Records panes in the asciicast v3 format of asciinema.
+//!
+//! A recording can be started and stopped at any time for an existing
+//! pane.  The output of the pane is recorded together with its resize
+//! events and, optionally, the input that was sent to it.  A marker is
+//! inserted into the recording at each OSC 133 command boundary, so that
+//! a player can jump between the commands that were run in the pane.
+//!
+//! The output is recorded as it is read from the pty, before it is
+//! parsed, and the events are passed to the sinks by a background
+//! thread, so that a slow sink doesn't hold up the pane.
+//!
+//! Events are produced where the pane is hosted: for a pane of a remote
+//! mux they are produced by the mux server and streamed to the client,
+//! which delivers them to the sink that was passed to
+//! `Pane::start_recording`.
+//!
+//! See <https://docs.asciinema.org/manual/asciicast/v3/> for the file format.
+use crate::pane::{Pane, PaneId};
+use crate::Mux;
+use anyhow::Context;
+use config::configuration;
+use parking_lot::Mutex;
+use serde::{Deserialize, Serialize};
+use shelldone_term::CommandBlock;
+use std::collections::HashMap;
+use std::io::{LineWriter, Write};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::mpsc::{channel, Receiver, Sender};
+use std::sync::{Arc, OnceLock};
+use std::time::Instant;
+use termwiz::escape::osc::FinalTermSemanticPrompt;
+use termwiz::escape::{Action, OperatingSystemCommand};
+
+/// The options of a recording.
+/// This type is used directly by the codec, take care to bump
+/// the codec version if you change this
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RecordingOptions {
+    /// Whether the input that is sent to the pane is recorded
+    pub input: bool,
+}
+
+impl RecordingOptions {
+    /// Returns the options from the `pane_recording_input` configuration
+    pub fn from_config() -> Self {
+        Self {
+            input: configuration().pane_recording_input,
+        }
+    }
+}
+
+/// The type of an event of an asciicast
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum RecordingEventCode {
+    Output,
+    Input,
+    Resize,
+    Marker,
+}
+
+impl RecordingEventCode {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Output => "o",
+            Self::Input => "i",
+            Self::Resize => "r",
+            Self::Marker => "m",
+        }
+    }
+}
+
+/// An event of an asciicast.
+/// This type is used directly by the codec, take care to bump
+/// the codec version if you change this
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RecordingEvent {
+    /// The number of seconds since the previous event
+    pub interval: f64,
+    pub code: RecordingEventCode,
+    pub data: String,
+}
+
+impl RecordingEvent {
+    /// Returns the event as a line of an asciicast v3 file
+    pub fn to_json(&self) -> serde_json::Result<String> {
+        // Microsecond precision is plenty, and keeps the file compact
+        let interval = (self.interval * 1_000_000.).round() / 1_000_000.;
+        serde_json::to_string(&(interval, self.code.as_str(), &self.data))
+    }
+}
+
+/// Receives the events of a recording.  Sinks are called from the
+/// thread that writes the recordings, so that writing them never holds
+/// up the pane.  The recording is stopped if the sink returns an error.
+pub type RecordingSink = Box<dyn FnMut(RecordingEvent) -> anyhow::Result<()> + Send>;
+
+/// Distinguishes a recording from one that later replaced it
+/// for the same pane
+type RecordingId = usize;
+
+struct Recording {
+    id: RecordingId,
+    options: RecordingOptions,
+    last_event: Instant,
+    /// The end of the output that was read so far, when it stops
+    /// part way through a UTF-8 sequence
+    partial_output: Vec<u8>,
+}
+
+/// The work of the thread that writes the recordings
+enum WriterMessage {
+    Start {
+        pane_id: PaneId,
+        id: RecordingId,
+        sink: RecordingSink,
+    },
+    Events {
+        id: RecordingId,
+        events: Vec<RecordingEvent>,
+    },
+    Stop {
+        id: RecordingId,
+    },
+}
+
+static WRITER: OnceLock<Sender<WriterMessage>> = OnceLock::new();
+static NEXT_RECORDING_ID: AtomicUsize = AtomicUsize::new(0);
+
+fn recordings() -> &'static Mutex<HashMap<PaneId, Recording>> {
+    static RECORDINGS: OnceLock<Mutex<HashMap<PaneId, Recording>>> = OnceLock::new();
+    RECORDINGS.get_or_init(|| Mutex::new(HashMap::new()))
+}
+
+/// Passes a message to the writer thread.  This is called with the
+/// recordings locked, so that the writer sees the recordings start
+/// and stop in the same order as the events that are passed to them.
+fn send_to_writer(message: WriterMessage) {
+    let sender = WRITER.get_or_init(|| {
+        let (tx, rx) = channel();
+        std::thread::Builder::new()
+            .name("pane-recording".to_string())
+            .spawn(move || run_writer(rx))
+            .expect("failed to spawn pane recording thread");
+        tx
+    });
+    if sender.send(message).is_err() {
+        log::error!("the pane recording thread has stopped");
+    }
+}
+
+fn run_writer(rx: Receiver<WriterMessage>) {
+    let mut sinks: HashMap<RecordingId, (PaneId, RecordingSink)> = HashMap::new();
+    for message in rx {
+        match message {
+            WriterMessage::Start { pane_id, id, sink } => {
+                sinks.insert(id, (pane_id, sink));
+            }
+            WriterMessage::Stop { id } => {
+                sinks.remove(&id);
+            }
+            WriterMessage::Events { id, events } => {
+                let Some((pane_id, sink)) = sinks.get_mut(&id) else {
+                    continue;
+                };
+                let pane_id = *pane_id;
+                if let Err(err) = events.into_iter().try_for_each(sink) {
+                    log::error!("stopping recording of pane {pane_id}: {err:#}");
+                    let stopped = {
+                        let mut recordings = recordings().lock();
+                        let current = recordings.get(&pane_id).map(|recording| recording.id);
+                        current == Some(id) && recordings.remove(&pane_id).is_some()
+                    };
+                    // The sink is dropped only once the recording is
+                    // no longer reported as running
+                    sinks.remove(&id);
+                    if stopped {
+                        Mux::invalidate_window_for_pane(pane_id);
+                    }
+                }
+            }
+        }
+    }
+}
+
+/// Starts recording a pane, replacing any recording that is already
+/// running for it.  For a pane of a remote mux, this only registers
+/// the sink for the events that are passed to `deliver_events`.
+pub fn start_recording(pane_id: PaneId, options: RecordingOptions, sink: RecordingSink) {
+    let id = NEXT_RECORDING_ID.fetch_add(1, Ordering::Relaxed);
+    {
+        let mut recordings = recordings().lock();
+        send_to_writer(WriterMessage::Start { pane_id, id, sink });
+        let prior = recordings.insert(
+            pane_id,
+            Recording {
+                id,
+                options,
+                last_event: Instant::now(),
+                partial_output: vec![],
+            },
+        );
+        if let Some(prior) = prior {
+            send_to_writer(WriterMessage::Stop { id: prior.id });
+        }
+    }
+    Mux::invalidate_window_for_pane(pane_id);
+}
+
+/// Removes the recording of a pane, returning false if it was not
+/// being recorded
+fn remove_recording(pane_id: PaneId) -> bool {
+    let mut recordings = recordings().lock();
+    match recordings.remove(&pane_id) {
+        Some(recording) => {
+            send_to_writer(WriterMessage::Stop { id: recording.id });
+            true
+        }
+        None => false,
+    }
+}
+
+/// Stops recording a pane.  Returns false if it was not being recorded.
+/// The events that were recorded before it stopped are still written.
+pub fn stop_recording(pane_id: PaneId) -> bool {
+    let stopped = remove_recording(pane_id);
+    if stopped {
+        Mux::invalidate_window_for_pane(pane_id);
+    }
+    stopped
+}
+
+pub fn is_recording(pane_id: PaneId) -> bool {
+    recordings().lock().contains_key(&pane_id)
+}
+
+impl Recording {
+    fn emit(&mut self, code: RecordingEventCode, data: String) {
+        let now = Instant::now();
+        let interval = (now - self.last_event).as_secs_f64();
+        self.last_event = now;
+        send_to_writer(WriterMessage::Events {
+            id: self.id,
+            events: vec![RecordingEvent {
+                interval,
+                code,
+                data,
+            }],
+        });
+    }
+}
+
+fn emit(pane_id: PaneId, code: RecordingEventCode, data: String) {
+    let mut recordings = recordings().lock();
+    let Some(recording) = recordings.get_mut(&pane_id) else {
+        return;
+    };
+    if code == RecordingEventCode::Input && !recording.options.input {
+        return;
+    }
+    recording.emit(code, data);
+}
+
+/// Delivers events that were recorded elsewhere, such as in a remote
+/// mux server, to the sink of a pane.  Returns false if the pane is
+/// no longer being recorded.
+pub fn deliver_events(pane_id: PaneId, events: Vec<RecordingEvent>) -> bool {
+    let mut recordings = recordings().lock();
+    let Some(recording) = recordings.get_mut(&pane_id) else {
+        return false;
+    };
+    recording.last_event = Instant::now();
+    send_to_writer(WriterMessage::Events {
+        id: recording.id,
+        events,
+    });
+    true
+}
+
+/// Returns the length of the longest prefix of `bytes` that does not
+/// stop part way through a UTF-8 sequence
+fn complete_utf8_len(bytes: &[u8]) -> usize {
+    // A sequence is at most 4 bytes long, so its first byte is
+    // within the last 3 bytes if it is incomplete
+    for back in 1..=bytes.len().min(3) {
+        let idx = bytes.len() - back;
+        let byte = bytes[idx];
+        if byte & 0xc0 == 0x80 {
+            // A continuation byte; keep looking for the first byte
+            continue;
+        }
+        let len = match byte {
+            0xf0..=0xff => 4,
+            0xe0..=0xef => 3,
+            0xc0..=0xdf => 2,
+            _ => 1,
+        };
+        return if len > back { idx } else { bytes.len() };
+    }
+    bytes.len()
+}
+
+/// Called with the bytes that were read from the pty of a local pane,
+/// before they are parsed
+pub(crate) fn record_output(pane_id: PaneId, output: &[u8]) {
+    let mut recordings = recordings().lock();
+    let Some(recording) = recordings.get_mut(&pane_id) else {
+        return;
+    };
+    let mut pending = std::mem::take(&mut recording.partial_output);
+    pending.extend_from_slice(output);
+    let partial = pending.split_off(complete_utf8_len(&pending));
+    recording.partial_output = partial;
+    if pending.is_empty() {
+        return;
+    }
+    let output = String::from_utf8_lossy(&pending).into_owned();
+    recording.emit(RecordingEventCode::Output, output);
+}
+
+/// Called with the bytes that were written to the pty of a local pane
+pub(crate) fn record_input(pane_id: PaneId, input: &[u8]) {
+    if input.is_empty() {
+        return;
+    }
+    emit(
+        pane_id,
+        RecordingEventCode::Input,
+        String::from_utf8_lossy(input).into_owned(),
+    );
+}
+
+/// Called when a local pane is resized
+pub(crate) fn record_resize(pane_id: PaneId, cols: usize, rows: usize) {
+    emit(
+        pane_id,
+        RecordingEventCode::Resize,
+        format!("{cols}x{rows}"),
+    );
+}
+
+/// Discards the recording of a pane that has been removed
+pub(crate) fn forget_pane(pane_id: PaneId) {
+    remove_recording(pane_id);
+}
+
+/// The OSC 133 escapes at which a marker is inserted into a recording
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum CommandBoundary {
+    /// The command line was entered and the command started to run
+    Started,
+    /// The command finished with this status
+    Finished(i32),
+}
+
+impl CommandBoundary {
+    pub(crate) fn from_action(action: &Action) -> Option<Self> {
+        match action {
+            Action::OperatingSystemCommand(osc) => match &**osc {
+                OperatingSystemCommand::FinalTermSemanticPrompt(
+                    FinalTermSemanticPrompt::MarkEndOfInputAndStartOfOutput { .. },
+                ) => Some(Self::Started),
+                OperatingSystemCommand::FinalTermSemanticPrompt(
+                    FinalTermSemanticPrompt::CommandStatus { status, .. },
+                ) => Some(Self::Finished(*status)),
+                _ => None,
+            },
+            _ => None,
+        }
+    }
+
+    /// Returns the label of the marker, given the command block
+    /// that the escape was applied to
+    pub(crate) fn label(&self, block: Option<&CommandBlock>) -> String {
+        let command = block
+            .map(|block| block.command.trim())
+            .filter(|command| !command.is_empty());
+        match (self, command) {
+            (Self::Started, Some(command)) => format!("$ {command}"),
+            (Self::Started, None) => "command started".to_string(),
+            (Self::Finished(status), Some(command)) => {
+                format!("{command} exited with status {status}")
+            }
+            (Self::Finished(status), None) => format!("exited with status {status}"),
+        }
+    }
+}
+
+/// Called once an OSC 133 escape has been applied to the terminal
+/// of a local pane
+pub(crate) fn record_command_boundary(
+    pane_id: PaneId,
+    boundary: CommandBoundary,
+    block: Option<&CommandBlock>,
+) {
+    emit(pane_id, RecordingEventCode::Marker, boundary.label(block));
+}
+
+/// The header of an asciicast v3 file
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AsciicastHeader {
+    pub version: u32,
+    pub term: AsciicastTerm,
+    /// Unix timestamp of the start of the recording
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub timestamp: Option<i64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub command: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub title: Option<String>,
+    #[serde(default, skip_serializing_if = "HashMap::is_empty")]
+    pub env: HashMap<String, String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AsciicastTerm {
+    pub cols: usize,
+    pub rows: usize,
+    #[serde(rename = "type", default, skip_serializing_if = "Option::is_none")]
+    pub term_type: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub theme: Option<AsciicastTheme>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AsciicastTheme {
+    /// Normal text color
+    pub fg: String,
+    /// Normal background color
+    pub bg: String,
+    /// List of 8 or 16 colors separated by a colon character
+    pub palette: String,
+}
+
+impl AsciicastHeader {
+    /// Describes the current state of a pane
+    pub fn for_pane(pane: &Arc<dyn Pane>) -> Self {
+        let config = configuration();
+        let dims = pane.get_dimensions();
+
+        let palette = pane.palette();
+        let ansi_colors: Vec<String> = palette.colors.0[0..16]
+            .iter()
+            .map(|c| c.to_rgb_string())
+            .collect();
+        let theme = AsciicastTheme {
+            fg: palette.foreground.to_rgb_string(),
+            bg: palette.background.to_rgb_string(),
+            palette: ansi_colors.join(":"),
+        };
+
+        let mut env = HashMap::new();
+        env.insert(
+            "SHELLDONE_VERSION".to_string(),
+            config::shelldone_version().to_string(),
+        );
+        if let Ok(lang) = std::env::var("LANG") {
+            env.insert("LANG".to_string(), lang);
+        }
+
+        Self {
+            version: 3,
+            term: AsciicastTerm {
+                cols: dims.cols,
+                rows: dims.viewport_rows,
+                term_type: Some(config.term.clone()),
+                theme: Some(theme),
+            },
+            timestamp: Some(chrono::Utc::now().timestamp()),
+            command: pane.get_foreground_process_name(crate::pane::CachePolicy::AllowStale),
+            title: Some(pane.get_title()),
+            env,
+        }
+    }
+}
+
+/// Returns the path at which a new recording of a pane is saved
+/// when no path is given; it is placed in `pane_recording_dir`
+pub fn default_recording_path(pane_id: PaneId) -> PathBuf {
+    let dir = configuration()
+        .pane_recording_dir
+        .clone()
+        .unwrap_or_else(|| config::DATA_DIR.join("recordings"));
+    let now = chrono::Local::now().format("%Y%m%d-%H%M%S");
+    dir.join(format!("shelldone-{now}-pane-{pane_id}.cast"))
+}
+
+/// Starts recording a pane into an asciicast v3 file at `path`,
+/// which is created or truncated.
+pub fn record_pane_to_file(
+    pane: &Arc<dyn Pane>,
+    path: &Path,
+    options: RecordingOptions,
+) -> anyhow::Result<()> {
+    if let Some(dir) = path.parent() {
+        std::fs::create_dir_all(dir)
+            .with_context(|| format!("creating directory {}", dir.display()))?;
+    }
+    let file =
+        std::fs::File::create(path).with_context(|| format!("creating {}", path.display()))?;
+    let mut file = LineWriter::new(file);
+
+    let header = AsciicastHeader::for_pane(pane);
+    writeln!(file, "{}", serde_json::to_string(&header)?)?;
+
+    let path = path.to_path_buf();
+    pane.start_recording(
+        options,
+        Box::new(move |event| {
+            writeln!(file, "{}", event.to_json()?)
+                .with_context(|| format!("writing to {}", path.display()))
+        }),
+    );
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn events_are_recorded_until_stopped() {
+        // Well clear of the ids that other tests may allocate
+        let pane_id = 1_000_001;
+        let (tx, rx) = channel();
+        start_recording(
+            pane_id,
+            RecordingOptions::default(),
+            Box::new(move |event| {
+                tx.send(event)?;
+                Ok(())
+            }),
+        );
+        assert!(is_recording(pane_id));
+
+        // A character that is split across reads is recorded whole
+        record_output(pane_id, b"hi \xe2\x9c");
+        record_output(pane_id, b"\x93");
+        // Input is not recorded unless it was requested
+        record_input(pane_id, b"ls\r");
+        record_resize(pane_id, 80, 24);
+        let block = CommandBlock {
+            id: 0,
+            prompt_y: 0,
+            output_y: Some(1),
+            end_y: Some(2),
+            command: "make".to_string(),
+            exit_status: Some(2),
+            start_time_ms: None,
+            end_time_ms: None,
+        };
+        record_command_boundary(pane_id, CommandBoundary::Finished(2), Some(&block));
+
+        assert!(stop_recording(pane_id));
+        record_output(pane_id, b"x");
+        assert!(!stop_recording(pane_id));
+
+        // The events are written by another thread, which drops
+        // the sink once the recording has stopped
+        let events: Vec<(RecordingEventCode, String)> =
+            rx.iter().map(|event| (event.code, event.data)).collect();
+        assert_eq!(
+            events,
+            vec![
+                (RecordingEventCode::Output, "hi ".to_string()),
+                (RecordingEventCode::Output, "\u{2713}".to_string()),
+                (RecordingEventCode::Resize, "80x24".to_string()),
+                (
+                    RecordingEventCode::Marker,
+                    "make exited with status 2".to_string()
+                ),
+            ]
+        );
+    }
+
+    #[test]
+    fn failing_sink_stops_the_recording() {
+        let pane_id = 1_000_002;
+        let (tx, rx) = channel::<()>();
+        start_recording(
+            pane_id,
+            RecordingOptions { input: true },
+            Box::new(move |_event| {
+                let _tx = &tx;
+                anyhow::bail!("disk full")
+            }),
+        );
+        record_input(pane_id, b"q");
+        // Wait for the writer thread to drop the sink
+        assert!(rx.recv().is_err());
+        assert!(!is_recording(pane_id));
+    }
+
+    #[test]
+    fn event_json() {
+        let event = RecordingEvent {
+            interval: 0.25,
+            code: RecordingEventCode::Output,
+            data: "a\"b\r\n".to_string(),
+        };
+        assert_eq!(event.to_json().unwrap(), r#"[0.25,"o","a\"b\r\n"]"#);
+    }
+}
@@ -0,0 +1,650 @@
+//! Persist mux workspaces across server restarts.
+//!
+//! The server periodically captures every window, tab and split layout into a
+//! [`MuxSnapshot`] and writes it to disk.  On startup the snapshot is replayed:
+//! each pane respawns its shell in the saved working directory and, when a
+//! scrollback tail was captured, shows it above a "restored" marker.
+use crate::domain::{Domain, DomainState, SplitSource};
+use crate::pane::{CachePolicy, Pane};
+use crate::tab::{
+    PaneEntry, PaneNode, SplitDirection, SplitDirectionAndSize, SplitRequest, SplitSize,
+};
+use crate::Mux;
+use anyhow::Context;
+use base64::engine::general_purpose::STANDARD as BASE64_STANDARD;
+use base64::Engine;
+use chrono::{DateTime, Utc};
+use config::keyassignment::SpawnTabDomain;
+use percent_encoding::percent_decode_str;
+use serde::{Deserialize, Serialize};
+use shelldone_term::TerminalSize;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::Duration;
+use url::Url;
+
+/// Bump when the on-disk layout changes incompatibly; older snapshots are
+/// ignored rather than half-restored.
+pub const SNAPSHOT_VERSION: u32 = 1;
+
+const ZSTD_LEVEL: i32 = 3;
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct MuxSnapshot {
+    pub version: u32,
+    pub saved_at: DateTime<Utc>,
+    pub windows: Vec<WindowSnapshot>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct WindowSnapshot {
+    pub workspace: String,
+    #[serde(default)]
+    pub title: String,
+    #[serde(default)]
+    pub active_tab: usize,
+    pub tabs: Vec<TabSnapshot>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct TabSnapshot {
+    #[serde(default)]
+    pub title: String,
+    pub size: TerminalSize,
+    pub root: PaneSnapshotNode,
+}
+
+/// The split tree of a tab; mirrors [`PaneNode`] without live ids.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub enum PaneSnapshotNode {
+    Split {
+        left: Box<PaneSnapshotNode>,
+        right: Box<PaneSnapshotNode>,
+        node: SplitDirectionAndSize,
+    },
+    Leaf(PaneSnapshot),
+}
+
+impl PaneSnapshotNode {
+    fn first_leaf(&self) -> &PaneSnapshot {
+        match self {
+            PaneSnapshotNode::Split { left, .. } => left.first_leaf(),
+            PaneSnapshotNode::Leaf(pane) => pane,
+        }
+    }
+
+    fn leaves(&self) -> usize {
+        match self {
+            PaneSnapshotNode::Split { left, right, .. } => left.leaves() + right.leaves(),
+            PaneSnapshotNode::Leaf(_) => 1,
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct PaneSnapshot {
+    pub domain: String,
+    /// Working directory as last reported via OSC 7 (or the process cwd).
+    #[serde(default)]
+    pub cwd: Option<String>,
+    /// argv of the foreground process at save time.  Informational only:
+    /// restored panes run the domain's default program.
+    #[serde(default)]
+    pub foreground: Option<Vec<String>>,
+    #[serde(default)]
+    pub title: String,
+    #[serde(default)]
+    pub is_active: bool,
+    #[serde(default)]
+    pub scrollback: Option<CompressedScrollback>,
+}
+
+/// Tail of a pane's scrollback as zstd-compressed, base64-encoded text.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct CompressedScrollback {
+    pub lines: usize,
+    pub zstd: String,
+}
+
+impl CompressedScrollback {
+    pub fn compress(lines: &[String]) -> anyhow::Result<Self> {
+        let text = lines.join("\n");
+        let compressed =
+            zstd::encode_all(text.as_bytes(), ZSTD_LEVEL).context("compressing scrollback")?;
+        Ok(Self {
+            lines: lines.len(),
+            zstd: BASE64_STANDARD.encode(compressed),
+        })
+    }
+
+    pub fn decompress(&self) -> anyhow::Result<Vec<String>> {
+        let compressed = BASE64_STANDARD
+            .decode(&self.zstd)
+            .context("decoding scrollback")?;
+        let text = zstd::decode_all(&compressed[..]).context("decompressing scrollback")?;
+        let text = String::from_utf8(text).context("scrollback is not utf8")?;
+        Ok(text.split('\n').map(str::to_string).collect())
+    }
+}
+
+/// The snapshot of the server that serves the unix domain `domain_name`.
+/// Each domain has its own file so that concurrently running servers
+/// don't overwrite each other's sessions.
+pub fn snapshot_path(domain_name: &str) -> PathBuf {
+    let name: String = domain_name
+        .chars()
+        .map(|c| {
+            if c.is_ascii_alphanumeric() || c == '-' || c == '_' {
+                c
+            } else {
+                '_'
+            }
+        })
+        .collect();
+    config::DATA_DIR.join(format!("mux-session-{name}.json"))
+}
+
+impl MuxSnapshot {
+    /// Capture every window of `mux`.  Panes that are dead or belong to a
+    /// domain that cannot spawn are left out; tabs left without panes are
+    /// dropped.  `scrollback_lines == 0` skips scrollback entirely.
+    pub fn capture(mux: &Mux, scrollback_lines: usize) -> Self {
+        let mut windows = vec![];
+        for window_id in mux.iter_windows() {
+            let (workspace, title, active_idx, tabs) = match mux.get_window(window_id) {
+                Some(window) => (
+                    window.get_workspace().to_string(),
+                    window.get_title().to_string(),
+                    window.get_active_idx(),
+                    window.iter().cloned().collect::<Vec<_>>(),
+                ),
+                None => continue,
+            };
+
+            let mut snapshots = vec![];
+            let mut active_tab = 0;
+            for (idx, tab) in tabs.iter().enumerate() {
+                let Some(root) = capture_node(mux, tab.codec_pane_tree(), scrollback_lines) else {
+                    continue;
+                };
+                if idx == active_idx {
+                    active_tab = snapshots.len();
+                }
+                snapshots.push(TabSnapshot {
+                    title: tab.get_title(),
+                    size: tab.get_size(),
+                    root,
+                });
+            }
+
+            if !snapshots.is_empty() {
+                windows.push(WindowSnapshot {
+                    workspace,
+                    title,
+                    active_tab,
+                    tabs: snapshots,
+                });
+            }
+        }
+
+        Self {
+            version: SNAPSHOT_VERSION,
+            saved_at: Utc::now(),
+            windows,
+        }
+    }
+
+    pub fn pane_count(&self) -> usize {
+        self.windows
+            .iter()
+            .flat_map(|window| window.tabs.iter())
+            .map(|tab| tab.root.leaves())
+            .sum()
+    }
+
+    /// Load the snapshot at `path`.  A missing file yields `Ok(None)`, as
+    /// does a snapshot written by an incompatible version.
+    pub fn load(path: &Path) -> anyhow::Result<Option<Self>> {
+        let data = match std::fs::read(path) {
+            Ok(data) => data,
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => return Ok(None),
+            Err(err) => {
+                return Err(err).with_context(|| format!("reading {}", path.display()));
+            }
+        };
+        let snapshot: Self = serde_json::from_slice(&data)
+            .with_context(|| format!("parsing mux session {}", path.display()))?;
+        if snapshot.version != SNAPSHOT_VERSION {
+            log::warn!(
+                "ignoring mux session {} with version {} (expected {})",
+                path.display(),
+                snapshot.version,
+                SNAPSHOT_VERSION
+            );
+            return Ok(None);
+        }
+        Ok(Some(snapshot))
+    }
+
+    /// Atomically replace the snapshot at `path`.
+    pub fn save(&self, path: &Path) -> anyhow::Result<()> {
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)
+                .with_context(|| format!("creating {}", parent.display()))?;
+        }
+        let data = serde_json::to_vec(self).context("serializing mux session")?;
+        let tmp = path.with_extension("json.tmp");
+        std::fs::write(&tmp, data).with_context(|| format!("writing {}", tmp.display()))?;
+        std::fs::rename(&tmp, path)
+            .with_context(|| format!("renaming {} -> {}", tmp.display(), path.display()))?;
+        Ok(())
+    }
+
+    /// Recreate the captured windows in `mux`, returning the number of panes
+    /// that were spawned.  Failures are logged per tab so that one broken
+    /// domain does not prevent the rest of the session from coming back.
+    pub async fn restore(&self, mux: &Arc<Mux>) -> anyhow::Result<usize> {
+        let marker = restored_marker(&self.saved_at);
+        let mut restored = 0;
+        for window in &self.windows {
+            // Keep the builder alive until the tabs are in place so that
+            // WindowCreated is only announced for a populated window.
+            let window_builder = mux.new_empty_window(Some(window.workspace.clone()), None);
+            let window_id = *window_builder;
+            if !window.title.is_empty() {
+                if let Some(mut win) = mux.get_window_mut(window_id) {
+                    win.set_title(&window.title);
+                }
+            }
+            for tab in &window.tabs {
+                match restore_tab(mux, window_id, tab, &marker).await {
+                    Ok(count) => restored += count,
+                    Err(err) => log::error!(
+                        "restoring tab {:?} in workspace {}: {:#}",
+                        tab.title,
+                        window.workspace,
+                        err
+                    ),
+                }
+            }
+            if let Some(mut win) = mux.get_window_mut(window_id) {
+                if window.active_tab < win.len() {
+                    win.set_active_without_saving(window.active_tab);
+                }
+            }
+        }
+        Ok(restored)
+    }
+}
+
+/// Periodically capture `mux` and write it to `path`.  The file is only
+/// rewritten when the layout or scrollback changed since the last save.
+pub async fn save_periodically(path: PathBuf, interval: Duration, scrollback_lines: usize) {
+    let mut last_written: Option<MuxSnapshot> = None;
+    loop {
+        smol::Timer::after(interval).await;
+        let Some(mux) = Mux::try_get() else {
+            return;
+        };
+        let snapshot = MuxSnapshot::capture(&mux, scrollback_lines);
+        let unchanged = last_written
+            .as_ref()
+            .map(|prior| prior.windows == snapshot.windows)
+            .unwrap_or(false);
+        if unchanged {
+            continue;
+        }
+        match snapshot.save(&path) {
+            Ok(()) => last_written = Some(snapshot),
+            Err(err) => log::error!("saving mux session: {:#}", err),
+        }
+    }
+}
+
+fn capture_node(mux: &Mux, node: PaneNode, scrollback_lines: usize) -> Option<PaneSnapshotNode> {
+    match node {
+        PaneNode::Empty => None,
+        PaneNode::Leaf(entry) => {
+            capture_pane(mux, entry, scrollback_lines).map(PaneSnapshotNode::Leaf)
+        }
+        PaneNode::Split { left, right, node } => {
+            let left = capture_node(mux, *left, scrollback_lines);
+            let right = capture_node(mux, *right, scrollback_lines);
+            match (left, right) {
+                (Some(left), Some(right)) => Some(PaneSnapshotNode::Split {
+                    left: Box::new(left),
+                    right: Box::new(right),
+                    node,
+                }),
+                (Some(only), None) | (None, Some(only)) => Some(only),
+                (None, None) => None,
+            }
+        }
+    }
+}
+
+fn capture_pane(mux: &Mux, entry: PaneEntry, scrollback_lines: usize) -> Option<PaneSnapshot> {
+    let pane = mux.get_pane(entry.pane_id)?;
+    if pane.is_dead() {
+        return None;
+    }
+    let domain = mux.get_domain(pane.domain_id())?;
+    if !domain.spawnable() {
+        return None;
+    }
+
+    let cwd = entry
+        .working_dir
+        .map(Url::from)
+        .or_else(|| pane.get_current_working_dir(CachePolicy::AllowStale))
+        .and_then(|url| url_to_path(&url));
+    let foreground = pane
+        .get_foreground_process_info(CachePolicy::AllowStale)
+        .map(|info| info.argv)
+        .filter(|argv| !argv.is_empty());
+    let scrollback = if scrollback_lines > 0 && !pane.is_alt_screen_active() {
+        let tail = scrollback_tail(&pane, scrollback_lines);
+        if tail.is_empty() {
+            None
+        } else {
+            match CompressedScrollback::compress(&tail) {
+                Ok(compressed) => Some(compressed),
+                Err(err) => {
+                    log::warn!("pane {}: {:#}", entry.pane_id, err);
+                    None
+                }
+            }
+        }
+    } else {
+        None
+    };
+
+    Some(PaneSnapshot {
+        domain: domain.domain_name().to_string(),
+        cwd,
+        foreground,
+        title: entry.title,
+        is_active: entry.is_active_pane,
+        scrollback,
+    })
+}
+
+/// The last `limit` logical lines up to the cursor row, with trailing blank
+/// lines (typically the empty screen below the prompt) removed.
+fn scrollback_tail(pane: &Arc<dyn Pane>, limit: usize) -> Vec<String> {
+    let dims = pane.get_dimensions();
+    let cursor = pane.get_cursor_position();
+    let end = (cursor.y + 1).min(dims.physical_top + dims.viewport_rows as isize);
+    let start = dims.scrollback_top.max(end - limit as isize);
+    if start >= end {
+        return vec![];
+    }
+    let mut lines: Vec<String> = pane
+        .get_logical_lines(start..end)
+        .into_iter()
+        .map(|line| line.logical.as_str().trim_end().to_string())
+        .collect();
+    while lines.last().map(|line| line.is_empty()).unwrap_or(false) {
+        lines.pop();
+    }
+    if lines.len() > limit {
+        lines.drain(..lines.len() - limit);
+    }
+    lines
+}
+
+/// Returns the local path of a `file://` URL, such as the working
+/// directory of a pane
+pub fn url_to_path(url: &Url) -> Option<String> {
+    if url.scheme() != "file" {
+        return None;
+    }
+    let path = percent_decode_str(url.path())
+        .decode_utf8()
+        .ok()?
+        .into_owned();
+    // See Mux::resolve_cwd: strip the leading slash of `/C:\...` on Windows.
+    let bytes = path.as_bytes();
+    if bytes.len() > 2 && bytes[0] == b'/' && bytes[2] == b':' {
+        Some(path[1..].to_owned())
+    } else {
+        Some(path)
+    }
+}
+
+fn restored_marker(saved_at: &DateTime<Utc>) -> String {
+    format!(
+        "\x1b[2m--- restored session from {} ---\x1b[0m",
+        saved_at
+            .with_timezone(&chrono::Local)
+            .format("%Y-%m-%d %H:%M:%S")
+    )
+}
+
+fn resolve_domain(mux: &Mux, name: &str) -> Arc<dyn Domain> {
+    match mux.get_domain_by_name(name) {
+        Some(domain) if domain.spawnable() => domain,
+        _ => {
+            log::warn!("domain {name} is not available; restoring into the default domain");
+            mux.default_domain()
+        }
+    }
+}
+
+async fn restore_tab(
+    mux: &Arc<Mux>,
+    window_id: crate::window::WindowId,
+    snapshot: &TabSnapshot,
+    marker: &str,
+) -> anyhow::Result<usize> {
+    let first = snapshot.root.first_leaf();
+    let domain = resolve_domain(mux, &first.domain);
+    if domain.state() == DomainState::Detached {
+        domain.attach(Some(window_id)).await?;
+    }
+    // The restored scrollback is queued as the banner of each pane before
+    // it is spawned, so that it is painted before the prompt of the new shell.
+    // The domain is attached first so that nothing is awaited before the
+    // spawn allocates its pane and claims the banner.
+    mux.set_next_pane_banner(Some(restored_text(first, marker)));
+    let tab = domain
+        .spawn(snapshot.size, None, first.cwd.clone(), window_id)
+        .await;
+    mux.set_next_pane_banner(None);
+    let tab = tab.with_context(|| format!("spawning in domain {}", domain.domain_name()))?;
+    if !snapshot.title.is_empty() {
+        tab.set_title(&snapshot.title);
+    }
+    let root_pane = tab
+        .get_active_pane()
+        .ok_or_else(|| anyhow::anyhow!("freshly spawned tab has no pane"))?;
+
+    // Splitting a pane only subdivides its own region, so the tree can be
+    // rebuilt top-down: split each node's pane for its right child, then
+    // descend into both halves.
+    let mut active = None;
+    let mut restored = 0;
+    let mut pending = vec![(&snapshot.root, root_pane)];
+    while let Some((node, pane)) = pending.pop() {
+        match node {
+            PaneSnapshotNode::Leaf(saved) => {
+                if saved.is_active {
+                    active = Some(Arc::clone(&pane));
+                }
+                restored += 1;
+            }
+            PaneSnapshotNode::Split { left, right, node } => {
+                let target = right.first_leaf();
+                let domain = resolve_domain(mux, &target.domain);
+                if domain.state() == DomainState::Detached {
+                    domain.attach(Some(window_id)).await?;
+                }
+                let request = SplitRequest {
+                    direction: node.direction,
+                    target_is_second: true,
+                    top_level: false,
+                    size: SplitSize::Cells(second_extent(node)),
+                };
+                mux.set_next_pane_banner(Some(restored_text(target, marker)));
+                let split = mux
+                    .split_pane(
+                        pane.pane_id(),
+                        request,
+                        SplitSource::Spawn {
+                            command: None,
+                            command_dir: target.cwd.clone(),
+                        },
+                        SpawnTabDomain::DomainName(domain.domain_name().to_string()),
+                    )
+                    .await;
+                mux.set_next_pane_banner(None);
+                let (new_pane, _size) = split.context("splitting restored pane")?;
+                pending.push((left, pane));
+                pending.push((right, new_pane));
+            }
+        }
+    }
+
+    if let Some(pane) = active {
+        tab.set_active_pane(&pane);
+    }
+    Ok(restored)
+}
+
+fn second_extent(node: &SplitDirectionAndSize) -> usize {
+    match node.direction {
+        SplitDirection::Horizontal => node.second.cols,
+        SplitDirection::Vertical => node.second.rows,
+    }
+}
+
+/// The scrollback of `saved` followed by the "restored" marker
+fn restored_text(saved: &PaneSnapshot, marker: &str) -> String {
+    let mut text = String::new();
+    if let Some(scrollback) = &saved.scrollback {
+        match scrollback.decompress() {
+            Ok(lines) => {
+                for line in lines {
+                    text.push_str(&line);
+                    text.push_str("\r\n");
+                }
+            }
+            Err(err) => log::warn!("restoring scrollback: {:#}", err),
+        }
+    }
+    text.push_str(marker);
+    if let Some(argv) = &saved.foreground {
+        text.push_str(&format!(
+            " \x1b[2m(was running: {})\x1b[0m",
+            shell_words::join(argv)
+        ));
+    }
+    text.push_str("\r\n");
+    text
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn leaf(cwd: &str) -> PaneSnapshotNode {
+        PaneSnapshotNode::Leaf(PaneSnapshot {
+            domain: "local".to_string(),
+            cwd: Some(cwd.to_string()),
+            foreground: Some(vec!["vim".to_string(), "notes.md".to_string()]),
+            title: "vim".to_string(),
+            is_active: false,
+            scrollback: Some(
+                CompressedScrollback::compress(&["$ ls".to_string(), "notes.md".to_string()])
+                    .unwrap(),
+            ),
+        })
+    }
+
+    fn snapshot() -> MuxSnapshot {
+        let size = TerminalSize::default();
+        MuxSnapshot {
+            version: SNAPSHOT_VERSION,
+            saved_at: Utc::now(),
+            windows: vec![WindowSnapshot {
+                workspace: "work".to_string(),
+                title: String::new(),
+                active_tab: 0,
+                tabs: vec![TabSnapshot {
+                    title: "editor".to_string(),
+                    size,
+                    root: PaneSnapshotNode::Split {
+                        left: Box::new(leaf("/tmp/a")),
+                        right: Box::new(PaneSnapshotNode::Split {
+                            left: Box::new(leaf("/tmp/b")),
+                            right: Box::new(leaf("/tmp/c")),
+                            node: SplitDirectionAndSize {
+                                direction: SplitDirection::Vertical,
+                                first: size,
+                                second: size,
+                            },
+                        }),
+                        node: SplitDirectionAndSize {
+                            direction: SplitDirection::Horizontal,
+                            first: size,
+                            second: size,
+                        },
+                    },
+                }],
+            }],
+        }
+    }
+
+    #[test]
+    fn scrollback_round_trips_through_compression() {
+        let lines: Vec<String> = (0..500).map(|i| format!("line {i}")).collect();
+        let compressed = CompressedScrollback::compress(&lines).unwrap();
+        assert_eq!(compressed.lines, 500);
+        assert!(compressed.zstd.len() < lines.join("\n").len());
+        assert_eq!(compressed.decompress().unwrap(), lines);
+    }
+
+    #[test]
+    fn snapshot_round_trips_on_disk() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("nested").join("mux-session.json");
+        let snapshot = snapshot();
+        snapshot.save(&path).unwrap();
+        let loaded = MuxSnapshot::load(&path).unwrap().unwrap();
+        assert_eq!(loaded, snapshot);
+        assert_eq!(loaded.pane_count(), 3);
+        assert_eq!(
+            loaded.windows[0].tabs[0].root.first_leaf().cwd.as_deref(),
+            Some("/tmp/a")
+        );
+    }
+
+    #[test]
+    fn missing_or_foreign_snapshots_are_ignored() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("mux-session.json");
+        assert!(MuxSnapshot::load(&path).unwrap().is_none());
+
+        let mut snapshot = snapshot();
+        snapshot.version = SNAPSHOT_VERSION + 1;
+        snapshot.save(&path).unwrap();
+        assert!(MuxSnapshot::load(&path).unwrap().is_none());
+    }
+
+    #[test]
+    fn snapshot_paths_are_per_domain() {
+        assert_ne!(snapshot_path("unix"), snapshot_path("work"));
+        assert_eq!(
+            snapshot_path("../my domain").file_name().unwrap(),
+            "mux-session-___my_domain.json"
+        );
+    }
+
+    #[test]
+    fn osc7_urls_become_paths() {
+        let url = Url::parse("file://host/home/user/my%20dir").unwrap();
+        assert_eq!(url_to_path(&url).as_deref(), Some("/home/user/my dir"));
+        let url = Url::parse("https://example.com/").unwrap();
+        assert_eq!(url_to_path(&url), None);
+    }
+}
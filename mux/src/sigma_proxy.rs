@@ -1,11 +1,15 @@
+use crate::file_transfer::FileTransferDirection;
+use crate::pane::PaneId;
 use crate::{Mux, MuxNotification, SigmaGuardData};
 use anyhow::Result;
 use log::{debug, warn};
 use portable_pty::{Child, ChildKiller, ExitStatus, MasterPty, PtySize};
 use promise::spawn::is_scheduler_configured;
+use shelldone_term::ClipboardSelection;
 use std::cmp::min;
 use std::fmt::Write as FmtWrite;
 use std::io::{self, Read, Write};
+use std::path::{Path, PathBuf};
 use std::sync::{Arc, OnceLock, RwLock};
 use std::time::SystemTime;
 
@@ -173,6 +177,12 @@ impl Write for SigmaProxyWriter {
 const ESC: u8 = 0x1B;
 const BEL: u8 = 0x07;
 const MAX_OSC52_PAYLOAD: usize = 8 * 1024;
+/// OSC 5522 transfers large payloads as a series of chunks,
+/// each of which must fit within this limit
+const MAX_OSC5522_CHUNK: usize = 8 * 1024;
+/// OSC 5113 transfers files as a series of chunks,
+/// each of which must fit within this limit
+const MAX_OSC5113_CHUNK: usize = 16 * 1024;
 const VIOLATION_PREVIEW_BYTES: usize = 32;
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
@@ -190,8 +200,80 @@ pub struct SigmaViolation {
     pub occurred_at: SystemTime,
 }
 
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SigmaClipboardAction {
+    Read,
+    Write,
+}
+
+/// An application read from or wrote to the clipboard,
+/// or was denied a read
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SigmaClipboardEvent {
+    pub pane_id: PaneId,
+    pub action: SigmaClipboardAction,
+    pub selection: ClipboardSelection,
+    pub mime_types: Vec<String>,
+    pub bytes: usize,
+    /// False if the user denied a read
+    pub allowed: bool,
+    pub occurred_at: SystemTime,
+}
+
+/// A file was transferred by an application via OSC 5113,
+/// or a transfer was refused
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SigmaFileTransferEvent {
+    pub pane_id: PaneId,
+    pub direction: FileTransferDirection,
+    /// None when the user refused the whole session
+    pub path: Option<PathBuf>,
+    pub bytes: u64,
+    pub allowed: bool,
+    /// Why the transfer was refused
+    pub reason: Option<String>,
+    pub occurred_at: SystemTime,
+}
+
+/// An output trigger with a `Journal` action matched a line
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SigmaTriggerEvent {
+    pub pane_id: PaneId,
+    /// The kind of journal event, as configured by the trigger
+    pub kind: String,
+    /// The text of the logical line that matched
+    pub line: String,
+    pub occurred_at: SystemTime,
+}
+
+/// A command that finished at the shell prompt was recorded
+/// into the command history
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SigmaCommandEvent {
+    pub pane_id: PaneId,
+    pub command: String,
+    pub cwd: Option<String>,
+    pub hostname: String,
+    pub exit_code: Option<i32>,
+    pub started_at: SystemTime,
+    pub occurred_at: SystemTime,
+}
+
 pub trait SigmaPolicyReporter {
     fn report(&self, violation: SigmaViolation);
+
+    /// Records an access to the clipboard that was made
+    /// via an escape sequence
+    fn report_clipboard(&self, _event: SigmaClipboardEvent) {}
+
+    /// Records a file transfer that was made via an escape sequence
+    fn report_file_transfer(&self, _event: SigmaFileTransferEvent) {}
+
+    /// Records a match of an output trigger
+    fn report_trigger(&self, _event: SigmaTriggerEvent) {}
+
+    /// Records a command that was added to the command history
+    fn report_command(&self, _event: SigmaCommandEvent) {}
 }
 
 struct NoopReporter;
@@ -206,6 +288,39 @@ impl SigmaPolicyReporter for NoopReporter {
             violation.sequence_preview
         );
     }
+
+    fn report_clipboard(&self, event: SigmaClipboardEvent) {
+        debug!(
+            "sigma noop reporter clipboard {:?} pane={} {:?} {:?} bytes={} allowed={}",
+            event.action,
+            event.pane_id,
+            event.selection,
+            event.mime_types,
+            event.bytes,
+            event.allowed
+        );
+    }
+
+    fn report_file_transfer(&self, event: SigmaFileTransferEvent) {
+        debug!(
+            "sigma noop reporter file transfer {:?} pane={} {:?} bytes={} allowed={} {:?}",
+            event.direction, event.pane_id, event.path, event.bytes, event.allowed, event.reason
+        );
+    }
+
+    fn report_trigger(&self, event: SigmaTriggerEvent) {
+        debug!(
+            "sigma noop reporter trigger {} pane={} {}",
+            event.kind, event.pane_id, event.line
+        );
+    }
+
+    fn report_command(&self, event: SigmaCommandEvent) {
+        debug!(
+            "sigma noop reporter command pane={} {:?} exit={:?}",
+            event.pane_id, event.command, event.exit_code
+        );
+    }
 }
 
 static REPORTER: OnceLock<RwLock<Arc<dyn SigmaPolicyReporter + Send + Sync>>> = OnceLock::new();
@@ -227,6 +342,119 @@ pub fn set_sigma_policy_reporter(reporter: Arc<dyn SigmaPolicyReporter + Send +
         .expect("sigma reporter lock poisoned") = reporter;
 }
 
+/// Journals an access to the clipboard via the reporter
+pub fn report_clipboard_event(event: SigmaClipboardEvent) {
+    global_reporter().report_clipboard(event);
+}
+
+/// Journals a file transfer via the reporter
+pub fn report_file_transfer_event(event: SigmaFileTransferEvent) {
+    global_reporter().report_file_transfer(event);
+}
+
+/// Journals a match of an output trigger via the reporter
+pub fn report_trigger_event(event: SigmaTriggerEvent) {
+    global_reporter().report_trigger(event);
+}
+
+/// Journals a command from the command history via the reporter
+pub fn report_command_event(event: SigmaCommandEvent) {
+    global_reporter().report_command(event);
+}
+
+/// Paths beneath the home directory that hold credentials,
+/// which file transfers may neither read nor write
+const PROTECTED_TRANSFER_PATHS: &[&str] = &[".ssh", ".gnupg"];
+/// Paths beneath the home directory that control what runs at
+/// login or startup, which file transfers may not write
+const PROTECTED_TRANSFER_WRITE_PATHS: &[&str] = &[
+    ".bashrc",
+    ".bash_profile",
+    ".bash_login",
+    ".profile",
+    ".zshrc",
+    ".zshenv",
+    ".zprofile",
+    ".zlogin",
+    ".config/fish",
+    ".config/autostart",
+    ".config/shelldone",
+    ".shelldone.lua",
+];
+
+/// Checks whether an OSC 5113 file transfer may access `path`.
+/// Links in the existing portion of the path are resolved first,
+/// so that they can't be used to reach a protected path.
+pub fn check_file_transfer_path(
+    path: &Path,
+    home: &Path,
+    direction: FileTransferDirection,
+) -> Result<(), &'static str> {
+    let canonical_home = home.canonicalize().unwrap_or_else(|_| home.to_path_buf());
+    let resolved = resolve_existing(path);
+    for (path, home) in [
+        (path, home),
+        (path, canonical_home.as_path()),
+        (resolved.as_path(), home),
+        (resolved.as_path(), canonical_home.as_path()),
+    ] {
+        let relative = match path.strip_prefix(home) {
+            Ok(relative) => relative,
+            Err(_) => continue,
+        };
+        if PROTECTED_TRANSFER_PATHS
+            .iter()
+            .any(|protected| relative.starts_with(protected))
+        {
+            return Err("file transfer of credentials is not allowed");
+        }
+        if direction == FileTransferDirection::Send
+            && PROTECTED_TRANSFER_WRITE_PATHS
+                .iter()
+                .any(|protected| relative.starts_with(protected))
+        {
+            return Err("file transfer may not replace startup files");
+        }
+    }
+    Ok(())
+}
+
+/// Checks that an OSC 5113 send session writes `path` inside of
+/// `dest_dir`, the directory that the user chose for it.  As above,
+/// links in the existing portion of the path are resolved first.
+pub fn check_file_transfer_dest(path: &Path, dest_dir: &Path) -> Result<(), &'static str> {
+    let canonical_dir = dest_dir
+        .canonicalize()
+        .unwrap_or_else(|_| dest_dir.to_path_buf());
+    if path.starts_with(dest_dir) && resolve_existing(path).starts_with(&canonical_dir) {
+        Ok(())
+    } else {
+        Err("file transfer may only write inside of the chosen directory")
+    }
+}
+
+/// Canonicalizes the longest existing prefix of `path`
+fn resolve_existing(path: &Path) -> PathBuf {
+    let mut existing = path;
+    let mut rest = vec![];
+    loop {
+        if let Ok(canonical) = existing.canonicalize() {
+            let mut resolved = canonical;
+            for name in rest.iter().rev() {
+                resolved.push(name);
+            }
+            return resolved;
+        }
+        match (existing.parent(), existing.file_name()) {
+            (Some(parent), Some(name)) => {
+                rest.push(name);
+                existing = parent;
+            }
+            _ => return path.to_path_buf(),
+        }
+    }
+}
+
 fn report_violation(
     reporter: &Arc<dyn SigmaPolicyReporter + Send + Sync>,
     direction: SigmaDirection,
@@ -416,7 +644,23 @@ fn parse_osc(data: &[u8]) -> EscapeParse {
             }
             EscapeParse::Allowed(len)
         }
-        Some(0 | 2 | 4 | 8 | 133 | 1337) => EscapeParse::Allowed(len),
+        Some(5522) => {
+            // Reads are allowed through; the user is asked to
+            // permit them before the clipboard is read
+            if payload.len() > MAX_OSC5522_CHUNK {
+                return EscapeParse::Filtered(len, "OSC 5522 chunk too large");
+            }
+            EscapeParse::Allowed(len)
+        }
+        Some(5113) => {
+            // Each transfer must be permitted by the user before
+            // any file is read or written
+            if payload.len() > MAX_OSC5113_CHUNK {
+                return EscapeParse::Filtered(len, "OSC 5113 chunk too large");
+            }
+            EscapeParse::Allowed(len)
+        }
+        Some(0 | 2 | 4 | 8 | 66 | 99 | 133 | 1337) => EscapeParse::Allowed(len),
         Some(other) => EscapeParse::Filtered(
             len,
             match other {
@@ -479,6 +723,103 @@ mod tests {
         set_sigma_policy_reporter(Arc::new(NoopReporter));
     }
 
+    #[test]
+    fn osc99_notification_passes_through() {
+        let recorder = Arc::new(RecordingReporter::default());
+        let reporter: Arc<dyn SigmaPolicyReporter + Send + Sync> = recorder.clone();
+        let seq = b"\x1b]99;i=1:d=0;title\x1b\\body";
+        let out = sanitize_output(seq, &reporter);
+        assert_eq!(out, seq);
+        assert!(recorder
+            .violations
+            .lock()
+            .expect("violations lock poisoned")
+            .is_empty());
+    }
+
+    #[test]
+    fn osc66_sized_text_passes_through() {
+        let recorder = Arc::new(RecordingReporter::default());
+        let reporter: Arc<dyn SigmaPolicyReporter + Send + Sync> = recorder.clone();
+        let seq = b"\x1b]66;s=2:w=3;Big\x1b\\";
+        let out = sanitize_output(seq, &reporter);
+        assert_eq!(out, seq);
+        assert!(recorder
+            .violations
+            .lock()
+            .expect("violations lock poisoned")
+            .is_empty());
+    }
+
+    #[test]
+    fn osc5522_chunks_are_limited() {
+        let recorder = Arc::new(RecordingReporter::default());
+        let reporter: Arc<dyn SigmaPolicyReporter + Send + Sync> = recorder.clone();
+
+        let chunk = b"\x1b]5522;type=wdata:mime=dGV4dC9wbGFpbg==;aGk=\x1b\\";
+        assert_eq!(sanitize_output(chunk, &reporter), chunk);
+
+        let mut big = b"\x1b]5522;type=wdata:mime=dGV4dC9wbGFpbg==;".to_vec();
+        big.extend(std::iter::repeat(b'A').take(MAX_OSC5522_CHUNK + 4));
+        big.extend_from_slice(b"\x1b\\after");
+        assert_eq!(sanitize_output(&big, &reporter), b"after");
+
+        let violations = recorder
+            .violations
+            .lock()
+            .expect("violations lock poisoned")
+            .clone();
+        assert_eq!(violations.len(), 1);
+        assert_eq!(violations[0].reason, "OSC 5522 chunk too large");
+    }
+
+    #[test]
+    fn osc5113_chunks_are_limited() {
+        let recorder = Arc::new(RecordingReporter::default());
+        let reporter: Arc<dyn SigmaPolicyReporter + Send + Sync> = recorder.clone();
+
+        let chunk = b"\x1b]5113;ac=data;id=s;fid=f;d=aGk=\x1b\\";
+        assert_eq!(sanitize_output(chunk, &reporter), chunk);
+
+        let mut big = b"\x1b]5113;ac=data;id=s;fid=f;d=".to_vec();
+        big.extend(std::iter::repeat(b'A').take(MAX_OSC5113_CHUNK + 4));
+        big.extend_from_slice(b"\x1b\\after");
+        assert_eq!(sanitize_output(&big, &reporter), b"after");
+
+        let violations = recorder
+            .violations
+            .lock()
+            .expect("violations lock poisoned")
+            .clone();
+        assert_eq!(violations.len(), 1);
+        assert_eq!(violations[0].reason, "OSC 5113 chunk too large");
+    }
+
+    #[test]
+    fn file_transfer_paths_are_checked() {
+        let home = tempfile::tempdir().unwrap();
+        let home = home.path();
+        let send = FileTransferDirection::Send;
+        let receive = FileTransferDirection::Receive;
+
+        assert!(check_file_transfer_path(&home.join("notes.txt"), home, send).is_ok());
+        assert!(check_file_transfer_path(&home.join(".bashrc"), home, receive).is_ok());
+        assert!(check_file_transfer_path(&home.join(".bashrc"), home, send).is_err());
+        assert!(check_file_transfer_path(&home.join(".ssh/id_rsa"), home, receive).is_err());
+        assert!(check_file_transfer_path(&home.join(".ssh"), home, send).is_err());
+        assert!(check_file_transfer_path(&home.join(".sshrc"), home, send).is_ok());
+
+        // A link can't be used to reach a protected path
+        #[cfg(unix)]
+        {
+            std::fs::create_dir(home.join(".ssh")).unwrap();
+            std::os::unix::fs::symlink(home.join(".ssh"), home.join("keys")).unwrap();
+            assert!(
+                check_file_transfer_path(&home.join("keys/authorized_keys"), home, send).is_err()
+            );
+        }
+    }
+
     #[test]
     fn osc133_marker_passes_through() {
         let recorder = Arc::new(RecordingReporter::default());
@@ -10,7 +10,8 @@ use filedescriptor::{poll, pollfd, socketpair, AsRawSocketDescriptor, FileDescri
 use portable_pty::cmdbuilder::CommandBuilder;
 use portable_pty::{ChildKiller, ExitStatus, MasterPty, PtySize};
 use shelldone_ssh::{
-    ConfigMap, HostVerificationFailed, Session, SessionEvent, SshChildProcess, SshPty,
+    ConfigMap, ForwardId, ForwardInfo, ForwardSpec, HostVerificationFailed, Session, SessionEvent,
+    SshChildProcess, SshPty,
 };
 use shelldone_term::TerminalSize;
 use smol::channel::{bounded, Receiver as AsyncReceiver};
@@ -266,6 +267,35 @@ impl RemoteSshDomain {
         ssh_domain_to_ssh_config(&self.dom)
     }
 
+    /// Returns the session; the session is established by the
+    /// first spawn into the domain
+    fn connected_session(&self) -> anyhow::Result<Session> {
+        self.session
+            .lock()
+            .unwrap()
+            .as_ref()
+            .cloned()
+            .ok_or_else(|| anyhow!("ssh domain {} is not connected", self.name))
+    }
+
+    /// Starts forwarding ports over the session
+    pub async fn add_port_forward(&self, spec: ForwardSpec) -> anyhow::Result<ForwardInfo> {
+        self.connected_session()?.add_forward(spec).await
+    }
+
+    pub async fn remove_port_forward(&self, id: ForwardId) -> anyhow::Result<()> {
+        self.connected_session()?.remove_forward(id).await
+    }
+
+    /// Returns the active port forwards, including any that were
+    /// configured via LocalForward, RemoteForward or DynamicForward
+    pub async fn port_forwards(&self) -> anyhow::Result<Vec<ForwardInfo>> {
+        match self.connected_session() {
+            Ok(session) => session.forwards().await,
+            Err(_) => Ok(vec![]),
+        }
+    }
+
     fn build_command(
         &self,
         pane_id: PaneId,
@@ -784,7 +814,7 @@ impl Domain for RemoteSshDomain {
         // eg: tmux integration to be tunnelled via the remote
         // session without duplicating a lot of logic over here.
 
-        let writer = WriterWrapper::new(writer);
+        let writer = WriterWrapper::new(pane_id, writer);
 
         let terminal = shelldone_term::Terminal::new(
             size,
@@ -817,6 +847,21 @@ impl Domain for RemoteSshDomain {
         &self.name
     }
 
+    async fn domain_label(&self) -> String {
+        match self.port_forwards().await {
+            Ok(forwards) if !forwards.is_empty() => format!(
+                "{} ({})",
+                self.name,
+                forwards
+                    .iter()
+                    .map(|info| info.spec.to_string())
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            ),
+            _ => self.name.clone(),
+        }
+    }
+
     async fn attach(&self, _window_id: Option<crate::WindowId>) -> anyhow::Result<()> {
         Ok(())
     }
@@ -1,4 +1,5 @@
 use crate::domain::DomainId;
+use crate::monitor::{MonitorAlert, PaneMonitors};
 use crate::pane::*;
 use crate::renderable::StableCursorPosition;
 use crate::{Mux, MuxNotification, WindowId};
@@ -219,6 +220,7 @@ struct PaneTreeContext<'a> {
     active: Option<&'a Arc<dyn Pane>>,
     zoomed: Option<&'a Arc<dyn Pane>>,
     workspace: &'a str,
+    is_active_tab: bool,
 }
 
 fn pane_tree(tree: &Tree, ctx: &PaneTreeContext<'_>, left_col: usize, top_row: usize) -> PaneNode {
@@ -249,6 +251,9 @@ fn pane_tree(tree: &Tree, ctx: &PaneTreeContext<'_>, left_col: usize, top_row: u
             let dims = pane.get_dimensions();
             let working_dir = pane.get_current_working_dir(CachePolicy::AllowStale);
             let cursor_pos = pane.get_cursor_position();
+            let domain_name = Mux::try_get()
+                .and_then(|mux| mux.get_domain(pane.domain_id()))
+                .map(|domain| domain.domain_name().to_string());
 
             PaneNode::Leaf(PaneEntry {
                 window_id: ctx.window_id,
@@ -257,6 +262,7 @@ fn pane_tree(tree: &Tree, ctx: &PaneTreeContext<'_>, left_col: usize, top_row: u
                 title: pane.get_title(),
                 is_active_pane: is_pane(pane, &ctx.active),
                 is_zoomed_pane: is_pane(pane, &ctx.zoomed),
+                is_active_tab: ctx.is_active_tab,
                 size: TerminalSize {
                     cols: dims.cols,
                     rows: dims.viewport_rows,
@@ -271,6 +277,9 @@ fn pane_tree(tree: &Tree, ctx: &PaneTreeContext<'_>, left_col: usize, top_row: u
                 left_col,
                 top_row,
                 tty_name: pane.tty_name(),
+                monitors: pane.get_monitors(),
+                monitor_alert: crate::monitor::pending_alert(pane.pane_id()),
+                domain_name,
             })
         }
     }
@@ -713,6 +722,19 @@ impl Tab {
             .swap_active_with_index(pane_index, keep_focus)
     }
 
+    /// Exchanges the positions of two panes within this tab.
+    /// Focus follows the pane that was active.
+    /// Returns false if either pane is not in this tab.
+    pub fn swap_panes(&self, pane_id: PaneId, other_pane_id: PaneId) -> bool {
+        self.inner.lock().swap_panes(pane_id, other_pane_id)
+    }
+
+    /// Puts `pane` into the position occupied by `pane_id`, resizing it
+    /// to fit, and returns the pane that was displaced.
+    pub fn replace_pane(&self, pane_id: PaneId, pane: Arc<dyn Pane>) -> Option<Arc<dyn Pane>> {
+        self.inner.lock().replace_pane(pane_id, pane)
+    }
+
     /// Computes the size of the pane that would result if the specified
     /// pane was split in a particular direction.
     /// The intent is to call this prior to spawning the new pane so that
@@ -822,11 +844,14 @@ impl TabInner {
             }
         };
 
-        let workspace = match mux
-            .get_window(window_id)
-            .map(|w| w.get_workspace().to_string())
-        {
-            Some(ws) => ws,
+        let window_state = mux.get_window(window_id).map(|w| {
+            (
+                w.get_workspace().to_string(),
+                w.get_active().map(|tab| tab.tab_id()) == Some(tab_id),
+            )
+        });
+        let (workspace, is_active_tab) = match window_state {
+            Some(state) => state,
             None => {
                 log::error!("window id {} doesn't have a window!?", window_id);
                 return PaneNode::Empty;
@@ -842,6 +867,7 @@ impl TabInner {
                 active: active.as_ref(),
                 zoomed,
                 workspace: &workspace,
+                is_active_tab,
             };
             pane_tree(root, &ctx, 0, 0)
         } else {
@@ -1862,6 +1888,74 @@ impl TabInner {
         None
     }
 
+    fn find_pane(&mut self, pane_id: PaneId) -> Option<(usize, Arc<dyn Pane>)> {
+        self.iter_panes_ignoring_zoom()
+            .into_iter()
+            .find(|p| p.pane.pane_id() == pane_id)
+            .map(|p| (p.index, p.pane))
+    }
+
+    fn replace_nth_pane(
+        &mut self,
+        pane_index: usize,
+        mut pane: Arc<dyn Pane>,
+    ) -> Option<Arc<dyn Pane>> {
+        let mut cursor = self.pane.take()?.cursor();
+        match cursor.go_to_nth_leaf(pane_index) {
+            Ok(c) => cursor = c,
+            Err(c) => {
+                self.pane.replace(c.tree());
+                return None;
+            }
+        };
+        std::mem::swap(&mut pane, cursor.leaf_mut().unwrap());
+        self.pane.replace(cursor.tree());
+        Some(pane)
+    }
+
+    fn swap_panes(&mut self, pane_id: PaneId, other_pane_id: PaneId) -> bool {
+        let (idx, pane, other_idx, other_pane) =
+            match (self.find_pane(pane_id), self.find_pane(other_pane_id)) {
+                (Some((idx, pane)), Some((other_idx, other_pane))) => {
+                    (idx, pane, other_idx, other_pane)
+                }
+                _ => return false,
+            };
+        if idx == other_idx {
+            return true;
+        }
+
+        self.set_zoomed(false);
+        self.replace_nth_pane(idx, other_pane);
+        self.replace_nth_pane(other_idx, pane);
+
+        if self.active == idx {
+            self.active = other_idx;
+        } else if self.active == other_idx {
+            self.active = idx;
+        }
+
+        let size = self.size;
+        apply_sizes_from_splits(self.pane.as_mut().unwrap(), &size);
+        if let Some(mux) = Mux::try_get() {
+            mux.notify(MuxNotification::TabResized(self.id));
+        }
+        true
+    }
+
+    fn replace_pane(&mut self, pane_id: PaneId, pane: Arc<dyn Pane>) -> Option<Arc<dyn Pane>> {
+        let (idx, _) = self.find_pane(pane_id)?;
+        self.set_zoomed(false);
+        let prior = self.replace_nth_pane(idx, pane)?;
+
+        let size = self.size;
+        apply_sizes_from_splits(self.pane.as_mut().unwrap(), &size);
+        if let Some(mux) = Mux::try_get() {
+            mux.notify(MuxNotification::TabResized(self.id));
+        }
+        Some(prior)
+    }
+
     fn compute_split_size(
         &mut self,
         pane_index: usize,
@@ -2150,12 +2244,18 @@ pub struct PaneEntry {
     pub working_dir: Option<SerdeUrl>,
     pub is_active_pane: bool,
     pub is_zoomed_pane: bool,
+    /// Whether the tab is the active tab of its window
+    pub is_active_tab: bool,
     pub workspace: String,
     pub cursor_pos: StableCursorPosition,
     pub physical_top: StableRowIndex,
     pub top_row: usize,
     pub left_col: usize,
     pub tty_name: Option<String>,
+    pub monitors: PaneMonitors,
+    pub monitor_alert: Option<MonitorAlert>,
+    /// The name of the domain to which the pane belongs
+    pub domain_name: Option<String>,
 }
 
 #[derive(Deserialize, Clone, Serialize, PartialEq, Debug)]
@@ -2512,6 +2612,66 @@ mod test {
         assert_eq!(600, panes[2].pixel_height);
     }
 
+    fn fake_size(pane: &Arc<dyn Pane>) -> TerminalSize {
+        *pane.downcast_ref::<FakePane>().unwrap().size.lock()
+    }
+
+    fn pane_ids(tab: &Tab) -> Vec<PaneId> {
+        tab.iter_panes_ignoring_zoom()
+            .iter()
+            .map(|p| p.pane.pane_id())
+            .collect()
+    }
+
+    #[test]
+    fn swap_and_replace_panes() {
+        let size = TerminalSize {
+            rows: 24,
+            cols: 80,
+            pixel_width: 800,
+            pixel_height: 600,
+            dpi: 96,
+        };
+
+        let tab = Tab::new(&size);
+        tab.assign_pane(&FakePane::make(1, size));
+        tab.split_and_insert(
+            0,
+            SplitRequest {
+                direction: SplitDirection::Horizontal,
+                ..Default::default()
+            },
+            FakePane::make(2, size),
+        )
+        .unwrap();
+        tab.split_and_insert(
+            1,
+            SplitRequest {
+                direction: SplitDirection::Vertical,
+                ..Default::default()
+            },
+            FakePane::make(3, size),
+        )
+        .unwrap();
+        assert_eq!(pane_ids(&tab), vec![1, 2, 3]);
+        tab.set_active_idx(0);
+
+        assert!(tab.swap_panes(1, 3));
+        assert_eq!(pane_ids(&tab), vec![3, 2, 1]);
+        // focus follows the pane that was active
+        assert_eq!(tab.get_active_pane().unwrap().pane_id(), 1);
+        let panes = tab.iter_panes();
+        assert_eq!(fake_size(&panes[0].pane).cols, 39);
+        assert_eq!(fake_size(&panes[2].pane).rows, 12);
+        assert!(!tab.swap_panes(1, 42));
+
+        let displaced = tab.replace_pane(2, FakePane::make(4, size)).unwrap();
+        assert_eq!(displaced.pane_id(), 2);
+        assert_eq!(pane_ids(&tab), vec![3, 4, 1]);
+        assert_eq!(fake_size(&tab.iter_panes()[1].pane).rows, 11);
+        assert!(tab.replace_pane(2, FakePane::make(5, size)).is_none());
+    }
+
     fn assert_send_and_sync<T: Send + Sync>() {}
 
     #[test]
@@ -192,7 +192,7 @@ impl TmuxDomainState {
             master_pane: ref_pane,
         };
 
-        let writer = WriterWrapper::new(pane_pty.take_writer()?);
+        let writer = WriterWrapper::new(local_pane_id, pane_pty.take_writer()?);
 
         let size = TerminalSize {
             rows: pane.pane_height as usize,
@@ -0,0 +1,818 @@
+//! Evaluates output triggers: rules that match a regex against each
+//! new line of output of a `LocalPane` and then act upon the match.
+//! The triggers come from the `triggers` configuration, optionally
+//! scoped to a domain, or are set on an individual pane via
+//! `set_pane_triggers`.
+//!
+//! Lines are evaluated on a dedicated thread, so that neither the
+//! parsing nor the rendering of the output waits on the regexes.
+//! The terminal is only locked while the text of the new logical lines
+//! is copied out, and while matches are highlighted.  Only complete
+//! lines are evaluated: those above the logical line that holds the
+//! cursor.  Iterating by logical line means that a match is found
+//! even when the line wraps, and that the lines are evaluated as a
+//! whole again after they are rewrapped by a resize.
+//!
+//! A trigger that sends text to the pane could match the output that
+//! the text produces and so send it again, indefinitely.  Each such
+//! trigger sends at most once per `SEND_TEXT_COOLDOWN`, and ignores
+//! the lines that follow what it sent for as long as they keep matching.
+use crate::localpane::LocalPane;
+use crate::pane::{Pane, PaneId};
+use crate::sigma_proxy::{report_trigger_event, SigmaTriggerEvent};
+use crate::{Mux, MuxNotification};
+use config::{configuration, expand_trigger_captures, ColorSpec, Trigger, TriggerAction};
+use parking_lot::Mutex;
+use shelldone_term::{Alert, Line, StableRowIndex, Terminal};
+use std::collections::{HashMap, HashSet};
+use std::io::Write;
+use std::ops::Range;
+use std::sync::mpsc::{channel, Receiver, Sender};
+use std::sync::{Arc, OnceLock};
+use std::time::{Duration, Instant, SystemTime};
+use termwiz::color::ColorAttribute;
+use termwiz::escape::osc::ITermProprietary;
+use termwiz::escape::{Action, OperatingSystemCommand};
+
+/// Text beyond this many bytes of a logical line is not evaluated
+const MAX_LINE_LEN: usize = 4096;
+/// Bounds the rows that are evaluated in one pass.  When the output
+/// outpaces the triggers, the oldest rows are skipped.
+const MAX_ROWS_PER_PASS: StableRowIndex = 2048;
+/// Bounds the matches of a highlight trigger within a line
+const MAX_HIGHLIGHTS_PER_LINE: usize = 64;
+/// The least time between two sends of text by the same trigger,
+/// and the time for which the output that followed them must stop
+/// matching before the trigger may send again
+const SEND_TEXT_COOLDOWN: Duration = Duration::from_secs(1);
+
+/// Overrides the configured triggers for a pane.
+/// `None` restores the triggers from the configuration.
+pub fn set_pane_triggers(pane_id: PaneId, triggers: Option<Vec<Trigger>>) {
+    let mut overrides = pane_overrides().lock();
+    match triggers {
+        Some(triggers) => {
+            overrides.insert(pane_id, Arc::new(triggers));
+        }
+        None => {
+            overrides.remove(&pane_id);
+        }
+    }
+}
+
+/// Called after output has been applied to the terminal of a local pane
+pub(crate) fn output_changed(pane_id: PaneId) {
+    if configuration().triggers.is_empty() && !pane_overrides().lock().contains_key(&pane_id) {
+        return;
+    }
+    submit(Message::Output(pane_id));
+}
+
+/// Discards the state that is held for a pane that has been removed
+pub(crate) fn forget_pane(pane_id: PaneId) {
+    pane_overrides().lock().remove(&pane_id);
+    if WORKER.get().is_some() {
+        submit(Message::PaneRemoved(pane_id));
+    }
+}
+
+fn pane_overrides() -> &'static Mutex<HashMap<PaneId, Arc<Vec<Trigger>>>> {
+    static OVERRIDES: OnceLock<Mutex<HashMap<PaneId, Arc<Vec<Trigger>>>>> = OnceLock::new();
+    OVERRIDES.get_or_init(|| Mutex::new(HashMap::new()))
+}
+
+/// Returns the triggers that apply to `pane`
+fn triggers_for_pane(pane: &dyn Pane) -> Vec<Trigger> {
+    if let Some(triggers) = pane_overrides().lock().get(&pane.pane_id()) {
+        return triggers.to_vec();
+    }
+    let config = configuration();
+    let domain_name = Mux::try_get()
+        .and_then(|mux| mux.get_domain(pane.domain_id()))
+        .map(|domain| domain.domain_name().to_string());
+    config
+        .triggers
+        .iter()
+        .filter(|trigger| match &trigger.domain {
+            Some(domain) => domain_name.as_deref() == Some(domain.as_str()),
+            None => true,
+        })
+        .cloned()
+        .collect()
+}
+
+enum Message {
+    Output(PaneId),
+    PaneRemoved(PaneId),
+}
+
+static WORKER: OnceLock<Sender<Message>> = OnceLock::new();
+
+fn submit(message: Message) {
+    let sender = WORKER.get_or_init(|| {
+        let (tx, rx) = channel();
+        std::thread::Builder::new()
+            .name("triggers".to_string())
+            .spawn(move || run_worker(rx))
+            .expect("failed to spawn trigger thread");
+        tx
+    });
+    if sender.send(message).is_err() {
+        log::error!("trigger thread has terminated");
+    }
+}
+
+fn run_worker(rx: Receiver<Message>) {
+    let mut panes: HashMap<PaneId, PaneState> = HashMap::new();
+    while let Ok(message) = rx.recv() {
+        // Coalesce the notifications that arrived while the
+        // previous pass was running
+        let mut dirty = HashSet::new();
+        for message in std::iter::once(message).chain(rx.try_iter()) {
+            match message {
+                Message::Output(pane_id) => {
+                    dirty.insert(pane_id);
+                }
+                Message::PaneRemoved(pane_id) => {
+                    dirty.remove(&pane_id);
+                    panes.remove(&pane_id);
+                }
+            }
+        }
+        for pane_id in dirty {
+            evaluate_pane(&mut panes, pane_id);
+        }
+    }
+}
+
+fn evaluate_pane(panes: &mut HashMap<PaneId, PaneState>, pane_id: PaneId) {
+    let Some(pane) = Mux::try_get().and_then(|mux| mux.get_pane(pane_id)) else {
+        panes.remove(&pane_id);
+        return;
+    };
+    let Some(local) = pane.downcast_ref::<LocalPane>() else {
+        return;
+    };
+    let triggers = triggers_for_pane(&*pane);
+    if triggers.is_empty() {
+        panes.remove(&pane_id);
+        return;
+    }
+
+    let state = panes.entry(pane_id).or_default();
+    let (lines, watermark) = local.with_terminal(|term| collect_new_lines(term, state.watermark));
+    if watermark.is_some() {
+        state.watermark = watermark;
+    }
+    if lines.is_empty() {
+        return;
+    }
+    let next_row = state.watermark.map_or(0, |mark| mark.next_row);
+    let (highlights, effects) = evaluate(
+        &triggers,
+        &lines,
+        &mut state.sends,
+        next_row,
+        Instant::now(),
+    );
+
+    if !highlights.is_empty() && local.with_terminal(|term| apply_highlights(term, &highlights)) {
+        Mux::notify_from_any_thread(MuxNotification::PaneOutput(pane_id));
+    }
+    apply_effects(&*pane, effects);
+}
+
+/// What the trigger thread keeps for each pane
+#[derive(Debug, Default)]
+struct PaneState {
+    watermark: Option<Watermark>,
+    sends: SendTextGuard,
+}
+
+/// Keeps the triggers that send text from feeding on their own output
+#[derive(Debug, Default)]
+struct SendTextGuard {
+    /// By the index of the trigger
+    sent: HashMap<usize, SentText>,
+}
+
+#[derive(Debug, Clone, Copy)]
+struct SentText {
+    /// The output from this row on followed the text that was sent
+    row: StableRowIndex,
+    sent_at: Instant,
+    /// Matching lines from `row` on are ignored until this time,
+    /// which moves on each time that one is ignored
+    quiet_until: Instant,
+}
+
+impl SendTextGuard {
+    /// Returns true if the trigger at `idx` may send its text in response
+    /// to `line`, and if so, records that it did.  The output that follows
+    /// the text will start at `next_row`.
+    fn permit(
+        &mut self,
+        idx: usize,
+        line: &NewLine,
+        next_row: StableRowIndex,
+        now: Instant,
+    ) -> bool {
+        if let Some(sent) = self.sent.get_mut(&idx) {
+            if line.rows.start >= sent.row && now < sent.quiet_until {
+                sent.quiet_until = now + SEND_TEXT_COOLDOWN;
+                return false;
+            }
+            if now < sent.sent_at + SEND_TEXT_COOLDOWN {
+                return false;
+            }
+        }
+        self.sent.insert(
+            idx,
+            SentText {
+                row: next_row,
+                sent_at: now,
+                quiet_until: now + SEND_TEXT_COOLDOWN,
+            },
+        );
+        true
+    }
+}
+
+/// Tracks how far the output of a pane has been evaluated
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct Watermark {
+    /// The logical lines that start before this row have been evaluated
+    next_row: StableRowIndex,
+    /// The width of the screen at the time; a resize rewraps the
+    /// lines, which invalidates `next_row`
+    cols: usize,
+}
+
+/// A complete logical line that has not yet been evaluated
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct NewLine {
+    rows: Range<StableRowIndex>,
+    text: String,
+}
+
+/// Returns the text of a logical line, without trailing whitespace
+fn logical_line_text<'a>(lines: impl Iterator<Item = &'a Line>) -> String {
+    let mut text = String::new();
+    for line in lines {
+        for cell in line.visible_cells() {
+            text.push_str(cell.str());
+        }
+    }
+    text.truncate(text.trim_end().len());
+    text
+}
+
+/// Truncates `text` to at most `len` bytes, respecting char boundaries
+fn truncate_text(text: &mut String, len: usize) {
+    if text.len() > len {
+        let mut end = len;
+        while !text.is_char_boundary(end) {
+            end -= 1;
+        }
+        text.truncate(end);
+    }
+}
+
+/// Copies out the complete logical lines that were output since
+/// `watermark`, and returns them along with the watermark advanced
+/// to the line that holds the cursor
+fn collect_new_lines(
+    term: &mut Terminal,
+    watermark: Option<Watermark>,
+) -> (Vec<NewLine>, Option<Watermark>) {
+    // Full screen applications repaint the alternate screen
+    // rather than output lines
+    if term.is_alt_screen_active() {
+        return (vec![], watermark);
+    }
+    let screen = term.screen();
+    let cursor_row = screen.visible_row_to_stable_row(term.cursor_pos().y);
+    let cols = screen.physical_cols;
+
+    let mut start = match watermark {
+        // The lines above the cursor were evaluated before they were
+        // rewrapped, or the cursor was moved back up over them:
+        // start again from the line that holds the cursor
+        Some(mark) if mark.cols != cols || mark.next_row > cursor_row => cursor_row,
+        Some(mark) => mark.next_row,
+        // Evaluate what a new pane shows on the screen
+        None => screen.visible_row_to_stable_row(0),
+    };
+    start = start.max(screen.scrollback_top());
+    if cursor_row - start > MAX_ROWS_PER_PASS {
+        log::debug!(
+            "triggers skipped {} rows of output",
+            cursor_row - start - MAX_ROWS_PER_PASS
+        );
+        start = cursor_row - MAX_ROWS_PER_PASS;
+    }
+
+    let mut lines = vec![];
+    let mut next_row = cursor_row;
+    screen.for_each_logical_line_in_stable_range(start..cursor_row + 1, |rows, phys| {
+        if rows.end > cursor_row {
+            // The line that holds the cursor may still be incomplete
+            next_row = rows.start;
+            return false;
+        }
+        // Skip a line that started before the range; its start
+        // has already been evaluated or skipped
+        if rows.start >= start {
+            let mut text = logical_line_text(phys.iter().copied());
+            truncate_text(&mut text, MAX_LINE_LEN);
+            if !text.is_empty() {
+                lines.push(NewLine { rows, text });
+            }
+        }
+        true
+    });
+
+    (lines, Some(Watermark { next_row, cols }))
+}
+
+/// Matches of a highlight trigger within a line
+#[derive(Debug, Clone, PartialEq)]
+struct Highlight {
+    line: NewLine,
+    /// Byte ranges of the text of the line
+    spans: Vec<Range<usize>>,
+    foreground: Option<ColorSpec>,
+    background: Option<ColorSpec>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Effect {
+    Notify {
+        title: Option<String>,
+        body: String,
+    },
+    SetUserVar {
+        name: String,
+        value: String,
+    },
+    EmitEvent {
+        name: String,
+        line: String,
+        captures: Vec<String>,
+    },
+    SendText(String),
+    Journal {
+        kind: String,
+        line: String,
+    },
+}
+
+/// Evaluates `triggers` against `lines`, which is done without
+/// holding the lock on the terminal.  `next_row` is the row
+/// at which the output that follows `lines` will start.
+fn evaluate(
+    triggers: &[Trigger],
+    lines: &[NewLine],
+    sends: &mut SendTextGuard,
+    next_row: StableRowIndex,
+    now: Instant,
+) -> (Vec<Highlight>, Vec<Effect>) {
+    let mut highlights = vec![];
+    let mut effects = vec![];
+    for line in lines {
+        for (idx, trigger) in triggers.iter().enumerate() {
+            match &trigger.action {
+                TriggerAction::Highlight {
+                    foreground,
+                    background,
+                } => {
+                    let spans: Vec<Range<usize>> = trigger
+                        .regex
+                        .find_iter(&line.text)
+                        .map(|m| m.range())
+                        .filter(|range| !range.is_empty())
+                        .take(MAX_HIGHLIGHTS_PER_LINE)
+                        .collect();
+                    if !spans.is_empty() {
+                        highlights.push(Highlight {
+                            line: line.clone(),
+                            spans,
+                            foreground: *foreground,
+                            background: *background,
+                        });
+                    }
+                }
+                action => {
+                    if let Some(caps) = trigger.regex.captures(&line.text) {
+                        if matches!(action, TriggerAction::SendText(_))
+                            && !sends.permit(idx, line, next_row, now)
+                        {
+                            continue;
+                        }
+                        let captures: Vec<String> = caps
+                            .iter()
+                            .map(|c| c.map(|c| c.as_str().to_string()).unwrap_or_default())
+                            .collect();
+                        effects.extend(effect_for_match(action, line, captures));
+                    }
+                }
+            }
+        }
+    }
+    (highlights, effects)
+}
+
+fn effect_for_match(
+    action: &TriggerAction,
+    line: &NewLine,
+    captures: Vec<String>,
+) -> Option<Effect> {
+    let expand = |format: &str| expand_trigger_captures(format, &captures);
+    Some(match action {
+        TriggerAction::Highlight { .. } => return None,
+        TriggerAction::Notify { title, body } => Effect::Notify {
+            title: title.as_deref().map(expand),
+            body: expand(body),
+        },
+        TriggerAction::SetUserVar { name, value } => Effect::SetUserVar {
+            name: name.clone(),
+            value: expand(value),
+        },
+        TriggerAction::SendText(text) => Effect::SendText(expand(text)),
+        TriggerAction::Journal { kind } => Effect::Journal {
+            kind: kind.clone(),
+            line: line.text.clone(),
+        },
+        TriggerAction::EmitEvent(name) => Effect::EmitEvent {
+            name: name.clone(),
+            line: line.text.clone(),
+            captures,
+        },
+    })
+}
+
+/// Colors the matched cells.  A line whose text changed since it was
+/// evaluated is left alone.  Returns true if any line was changed.
+fn apply_highlights(term: &mut Terminal, highlights: &[Highlight]) -> bool {
+    term.increment_seqno();
+    let seqno = term.current_seqno();
+    let screen = term.screen_mut();
+    let mut changed = false;
+    for highlight in highlights {
+        let wanted = &highlight.line;
+        screen.for_each_logical_line_in_stable_range_mut(wanted.rows.clone(), |rows, lines| {
+            let mut text = logical_line_text(lines.iter().map(|line| &**line));
+            truncate_text(&mut text, MAX_LINE_LEN);
+            if rows != wanted.rows || text != wanted.text {
+                return false;
+            }
+
+            let mut offset = 0;
+            for line in lines.iter_mut() {
+                let mut cell_indices = vec![];
+                for cell in line.visible_cells() {
+                    let range = offset..offset + cell.str().len();
+                    offset = range.end;
+                    if highlight
+                        .spans
+                        .iter()
+                        .any(|span| span.start < range.end && range.start < span.end)
+                    {
+                        cell_indices.push(cell.cell_index());
+                    }
+                }
+                if cell_indices.is_empty() {
+                    continue;
+                }
+
+                let cells = line.cells_mut_for_attr_changes_only();
+                for idx in cell_indices {
+                    if let Some(cell) = cells.get_mut(idx) {
+                        let attrs = cell.attrs_mut();
+                        if let Some(fg) = highlight.foreground {
+                            attrs.set_foreground(ColorAttribute::from(fg));
+                        }
+                        if let Some(bg) = highlight.background {
+                            attrs.set_background(ColorAttribute::from(bg));
+                        }
+                    }
+                }
+                line.update_last_change_seqno(seqno);
+                line.clear_appdata();
+                changed = true;
+            }
+            false
+        });
+    }
+    changed
+}
+
+fn apply_effects(pane: &dyn Pane, effects: Vec<Effect>) {
+    let pane_id = pane.pane_id();
+    for effect in effects {
+        match effect {
+            Effect::Notify { title, body } => {
+                Mux::notify_from_any_thread(MuxNotification::Alert {
+                    pane_id,
+                    alert: Alert::ToastNotification {
+                        title,
+                        body,
+                        focus: true,
+                        id: None,
+                        urgency: Default::default(),
+                        occasion: Default::default(),
+                        report_activation: false,
+                        report_close: false,
+                        timeout: None,
+                    },
+                });
+            }
+            Effect::SetUserVar { name, value } => {
+                pane.perform_actions(vec![Action::OperatingSystemCommand(Box::new(
+                    OperatingSystemCommand::ITermProprietary(ITermProprietary::SetUserVar {
+                        name,
+                        value,
+                    }),
+                ))]);
+            }
+            Effect::EmitEvent {
+                name,
+                line,
+                captures,
+            } => {
+                Mux::notify_from_any_thread(MuxNotification::Alert {
+                    pane_id,
+                    alert: Alert::TriggerEvent {
+                        name,
+                        line,
+                        captures,
+                    },
+                });
+            }
+            Effect::SendText(text) => {
+                let mut writer = pane.writer();
+                if let Err(err) = writer.write_all(text.as_bytes()) {
+                    log::error!("error while sending trigger text to pane {pane_id}: {err:#}");
+                }
+                writer.flush().ok();
+            }
+            Effect::Journal { kind, line } => report_trigger_event(SigmaTriggerEvent {
+                pane_id,
+                kind,
+                line,
+                occurred_at: SystemTime::now(),
+            }),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use config::TermConfig;
+    use shelldone_term::TerminalSize;
+    use termwiz::color::AnsiColor;
+
+    fn new_terminal(cols: usize) -> Terminal {
+        Terminal::new(
+            TerminalSize {
+                rows: 5,
+                cols,
+                ..Default::default()
+            },
+            Arc::new(TermConfig::new()),
+            "test",
+            "0",
+            Box::new(Vec::new()),
+        )
+    }
+
+    fn trigger(regex: &str, action: TriggerAction) -> Trigger {
+        Trigger {
+            regex: regex::Regex::new(regex).unwrap(),
+            action,
+            domain: None,
+        }
+    }
+
+    fn collect(term: &mut Terminal, watermark: &mut Option<Watermark>) -> Vec<String> {
+        let (lines, mark) = collect_new_lines(term, *watermark);
+        *watermark = mark;
+        lines.into_iter().map(|line| line.text).collect()
+    }
+
+    #[test]
+    fn only_complete_lines_are_collected_once() {
+        let mut term = new_terminal(20);
+        let mut watermark = None;
+
+        term.advance_bytes("first\r\nsecond");
+        assert_eq!(collect(&mut term, &mut watermark), vec!["first"]);
+        assert_eq!(collect(&mut term, &mut watermark), Vec::<String>::new());
+
+        term.advance_bytes(" line\r\nthird\r\n");
+        assert_eq!(
+            collect(&mut term, &mut watermark),
+            vec!["second line", "third"]
+        );
+    }
+
+    #[test]
+    fn wrapped_lines_are_collected_whole() {
+        let mut term = new_terminal(10);
+        term.advance_bytes("0123456789ERROR: disk full\r\n");
+        let (lines, mut watermark) = collect_new_lines(&mut term, None);
+        assert_eq!(lines.len(), 1);
+        assert_eq!(lines[0].text, "0123456789ERROR: disk full");
+        assert_eq!(lines[0].rows, 0..3);
+
+        // Rewrapping the lines doesn't evaluate them again
+        term.resize(TerminalSize {
+            rows: 5,
+            cols: 20,
+            ..Default::default()
+        });
+        assert_eq!(collect(&mut term, &mut watermark), Vec::<String>::new());
+        term.advance_bytes("next\r\n");
+        assert_eq!(collect(&mut term, &mut watermark), vec!["next"]);
+    }
+
+    #[test]
+    fn actions_expand_captures() {
+        let lines = vec![NewLine {
+            rows: 0..1,
+            text: "ERROR code=42 in worker".to_string(),
+        }];
+        let triggers = vec![
+            trigger(
+                r"ERROR code=(\d+)",
+                TriggerAction::Notify {
+                    title: Some("Error $1".to_string()),
+                    body: "$0".to_string(),
+                },
+            ),
+            trigger(
+                r"code=(\d+)",
+                TriggerAction::SetUserVar {
+                    name: "last_error".to_string(),
+                    value: "$1".to_string(),
+                },
+            ),
+            trigger(
+                r"in (\w+)",
+                TriggerAction::EmitEvent("worker-error".to_string()),
+            ),
+            trigger(r"no match", TriggerAction::SendText("x".to_string())),
+            trigger(
+                r"ERROR",
+                TriggerAction::Journal {
+                    kind: "trigger.error".to_string(),
+                },
+            ),
+        ];
+
+        let (highlights, effects) = evaluate(
+            &triggers,
+            &lines,
+            &mut SendTextGuard::default(),
+            0,
+            Instant::now(),
+        );
+        assert!(highlights.is_empty());
+        assert_eq!(
+            effects,
+            vec![
+                Effect::Notify {
+                    title: Some("Error 42".to_string()),
+                    body: "ERROR code=42".to_string(),
+                },
+                Effect::SetUserVar {
+                    name: "last_error".to_string(),
+                    value: "42".to_string(),
+                },
+                Effect::EmitEvent {
+                    name: "worker-error".to_string(),
+                    line: "ERROR code=42 in worker".to_string(),
+                    captures: vec!["in worker".to_string(), "worker".to_string()],
+                },
+                Effect::Journal {
+                    kind: "trigger.error".to_string(),
+                    line: "ERROR code=42 in worker".to_string(),
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn highlight_spans_wrapped_lines() {
+        let mut term = new_terminal(10);
+        term.advance_bytes("abcdefFAIL this FAIL\r\n");
+        let (lines, _) = collect_new_lines(&mut term, None);
+        let triggers = vec![trigger(
+            "FAIL",
+            TriggerAction::Highlight {
+                foreground: None,
+                background: Some(ColorSpec::AnsiColor(AnsiColor::Maroon)),
+            },
+        )];
+        let (highlights, effects) = evaluate(
+            &triggers,
+            &lines,
+            &mut SendTextGuard::default(),
+            0,
+            Instant::now(),
+        );
+        assert!(effects.is_empty());
+        assert_eq!(highlights[0].spans, vec![6..10, 16..20]);
+
+        let seqno = term.current_seqno();
+        assert!(apply_highlights(&mut term, &highlights));
+
+        let maroon = ColorAttribute::PaletteIndex(AnsiColor::Maroon.into());
+        let mut highlighted = vec![];
+        for (row, line) in term.screen().lines_in_phys_range(0..2).iter().enumerate() {
+            assert!(line.changed_since(seqno));
+            for cell in line.visible_cells() {
+                if cell.attrs().background() == maroon {
+                    highlighted.push((row, cell.cell_index()));
+                }
+            }
+        }
+        assert_eq!(
+            highlighted,
+            vec![
+                (0, 6),
+                (0, 7),
+                (0, 8),
+                (0, 9),
+                (1, 6),
+                (1, 7),
+                (1, 8),
+                (1, 9)
+            ]
+        );
+    }
+
+    #[test]
+    fn changed_lines_are_not_highlighted() {
+        let mut term = new_terminal(20);
+        term.advance_bytes("FAIL\r\n");
+        let (lines, _) = collect_new_lines(&mut term, None);
+        let triggers = vec![trigger(
+            "FAIL",
+            TriggerAction::Highlight {
+                foreground: Some(ColorSpec::AnsiColor(AnsiColor::Red)),
+                background: None,
+            },
+        )];
+        let (highlights, _) = evaluate(
+            &triggers,
+            &lines,
+            &mut SendTextGuard::default(),
+            0,
+            Instant::now(),
+        );
+
+        // The line is overwritten before the highlight is applied
+        term.advance_bytes("\x1b[1;1HPASS");
+        assert!(!apply_highlights(&mut term, &highlights));
+    }
+
+    #[test]
+    fn send_text_does_not_feed_on_its_own_output() {
+        let triggers = vec![trigger(
+            "password:",
+            TriggerAction::SendText("hunter2\r".to_string()),
+        )];
+        let line = |row: StableRowIndex| NewLine {
+            rows: row..row + 1,
+            text: "password:".to_string(),
+        };
+        let sent = vec![Effect::SendText("hunter2\r".to_string())];
+        let mut sends = SendTextGuard::default();
+        let now = Instant::now();
+        let after = |millis| now + Duration::from_millis(millis);
+
+        // Only one send for a burst of matches
+        let (_, effects) = evaluate(&triggers, &[line(0), line(1)], &mut sends, 2, now);
+        assert_eq!(effects, sent);
+
+        // The output that follows is ignored for as long as it keeps
+        // matching, even once the cooldown has passed
+        let (_, effects) = evaluate(&triggers, &[line(2)], &mut sends, 3, after(900));
+        assert!(effects.is_empty());
+        let (_, effects) = evaluate(&triggers, &[line(3)], &mut sends, 4, after(1800));
+        assert!(effects.is_empty());
+
+        // Once it has stopped matching, the trigger fires again
+        let (_, effects) = evaluate(&triggers, &[line(9)], &mut sends, 10, after(3000));
+        assert_eq!(effects, sent);
+    }
+
+    #[test]
+    fn long_lines_are_truncated() {
+        let mut text = "é".repeat(MAX_LINE_LEN);
+        truncate_text(&mut text, MAX_LINE_LEN + 1);
+        assert_eq!(text.len(), MAX_LINE_LEN);
+    }
+}
@@ -121,3 +121,19 @@ termbridge_deny_reason contains msg if {
         [input.action, input.bytes, input.backend, input.cwd]
     )
 }
+
+# Token-bucket quotas for agent-issued actions, keyed by AgentBinding
+# provider, MCP session and persona. "*" matches any action in a scope.
+rate_limits := {
+    "provider": {
+        "agent.exec": {"per_second": 20, "burst": 40},
+        "termbridge.send_text": {"per_second": 50, "burst": 100},
+    },
+    "session": {
+        "agent.exec": {"per_second": 10, "burst": 20},
+    },
+    "persona": {
+        "agent.exec": {"per_second": 20, "burst": 40},
+        "termbridge.send_text": {"per_second": 50, "burst": 100},
+    },
+}
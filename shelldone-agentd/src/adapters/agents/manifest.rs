@@ -0,0 +1,153 @@
+use anyhow::{anyhow, Context, Result};
+use serde::Deserialize;
+use std::env;
+use std::path::{Path, PathBuf};
+
+const PYTHON_PLACEHOLDER: &str = "{python}";
+const ROOT_PREFIX: &str = "{ROOT}/";
+const SCRIPT_SUFFIXES: &[&str] = &[".py", ".mjs", ".js", ".sh"];
+
+/// Adapter entry from `agents/manifest.json`.
+#[derive(Clone, Debug, PartialEq, Eq, Deserialize)]
+pub struct AdapterSpec {
+    pub id: String,
+    pub command: Vec<String>,
+    #[serde(default)]
+    pub error_contains: Vec<String>,
+    #[serde(default = "default_enabled")]
+    pub enabled: bool,
+}
+
+fn default_enabled() -> bool {
+    true
+}
+
+#[derive(Debug, Deserialize)]
+struct ManifestFile {
+    #[serde(default)]
+    adapters: Vec<AdapterSpec>,
+}
+
+/// Parsed manifest with commands resolved against the repository root.
+#[derive(Clone, Debug)]
+pub struct AdapterManifest {
+    root: PathBuf,
+    adapters: Vec<AdapterSpec>,
+}
+
+impl AdapterManifest {
+    /// Load the manifest at `path`. Relative script paths resolve against the
+    /// directory above `agents/`, matching `scripts/agentd.py`.
+    pub fn load(path: &Path) -> Result<Self> {
+        let data = std::fs::read(path)
+            .with_context(|| format!("reading adapter manifest {}", path.display()))?;
+        let parsed: ManifestFile = serde_json::from_slice(&data)
+            .with_context(|| format!("parsing adapter manifest {}", path.display()))?;
+        let root = path
+            .parent()
+            .and_then(Path::parent)
+            .map(Path::to_path_buf)
+            .unwrap_or_else(|| PathBuf::from("."));
+        Self::from_specs(root, parsed.adapters)
+    }
+
+    pub fn from_specs(root: PathBuf, adapters: Vec<AdapterSpec>) -> Result<Self> {
+        for spec in &adapters {
+            if spec.id.trim().is_empty() || spec.command.is_empty() {
+                return Err(anyhow!("invalid adapter entry in manifest: {:?}", spec.id));
+            }
+        }
+        let adapters = adapters
+            .into_iter()
+            .map(|mut spec| {
+                spec.command = expand_command(&root, &spec.command);
+                spec
+            })
+            .collect();
+        Ok(Self { root, adapters })
+    }
+
+    pub fn root(&self) -> &Path {
+        &self.root
+    }
+
+    pub fn adapters(&self) -> &[AdapterSpec] {
+        &self.adapters
+    }
+
+    #[cfg(test)]
+    pub fn get(&self, id: &str) -> Option<&AdapterSpec> {
+        self.adapters.iter().find(|spec| spec.id == id)
+    }
+}
+
+fn expand_command(root: &Path, raw: &[String]) -> Vec<String> {
+    raw.iter()
+        .enumerate()
+        .map(|(idx, piece)| {
+            if piece == PYTHON_PLACEHOLDER {
+                env::var("SHELLDONE_PYTHON").unwrap_or_else(|_| "python3".to_string())
+            } else if let Some(rest) = piece.strip_prefix(ROOT_PREFIX) {
+                root.join(rest).display().to_string()
+            } else if idx > 0
+                && !Path::new(piece).is_absolute()
+                && SCRIPT_SUFFIXES.iter().any(|suffix| piece.ends_with(suffix))
+            {
+                root.join(piece).display().to_string()
+            } else {
+                piece.clone()
+            }
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[test]
+    fn load_resolves_scripts_against_repo_root() {
+        let temp = TempDir::new().unwrap();
+        let agents = temp.path().join("agents");
+        std::fs::create_dir_all(&agents).unwrap();
+        std::fs::write(
+            agents.join("manifest.json"),
+            r#"{"adapters": [
+                {"id": "claude", "command": ["node", "agents/claude/bridge.mjs"]},
+                {"id": "custom", "command": ["{ROOT}/bin/bridge", "--flag"], "enabled": false}
+            ]}"#,
+        )
+        .unwrap();
+
+        let manifest = AdapterManifest::load(&agents.join("manifest.json")).unwrap();
+        let claude = manifest.get("claude").unwrap();
+        assert!(claude.enabled);
+        assert_eq!(claude.command[0], "node");
+        assert_eq!(
+            PathBuf::from(&claude.command[1]),
+            temp.path().join("agents/claude/bridge.mjs")
+        );
+        let custom = manifest.get("custom").unwrap();
+        assert!(!custom.enabled);
+        assert_eq!(
+            PathBuf::from(&custom.command[0]),
+            temp.path().join("bin/bridge")
+        );
+        assert_eq!(custom.command[1], "--flag");
+    }
+
+    #[test]
+    fn entries_without_command_are_rejected() {
+        let result = AdapterManifest::from_specs(
+            PathBuf::from("."),
+            vec![AdapterSpec {
+                id: "broken".into(),
+                command: Vec::new(),
+                error_contains: Vec::new(),
+                enabled: true,
+            }],
+        );
+        assert!(result.is_err());
+    }
+}
@@ -1,3 +1,14 @@
+pub mod manifest;
+pub mod repo_file;
+/// Test double; agentd persists bindings with [`FileAgentBindingRepository`]
+#[cfg(test)]
 pub mod repo_mem;
+pub mod supervisor;
 
+pub use manifest::{AdapterManifest, AdapterSpec};
+pub use repo_file::FileAgentBindingRepository;
+#[cfg(test)]
 pub use repo_mem::InMemoryAgentBindingRepository;
+pub use supervisor::{
+    BridgeEvent, BridgeSupervisor, BridgeSupervisorConfig, SmokeOutcome, SmokeReport,
+};
@@ -0,0 +1,220 @@
+use crate::domain::agents::{AgentBinding, AgentBindingId, AgentBindingSnapshot};
+use crate::ports::agents::{AgentBindingRepository, BindingVersionConflict};
+use anyhow::{Context, Result};
+use async_trait::async_trait;
+use std::collections::HashMap;
+use std::path::PathBuf;
+use tokio::fs;
+use tokio::io::AsyncWriteExt;
+use tokio::sync::RwLock;
+
+const TMP_SUFFIX: &str = ".tmp";
+
+/// Verify `binding` was loaded at the stored version and advance it.
+pub(super) fn stamp_version(
+    stored: Option<&AgentBinding>,
+    mut binding: AgentBinding,
+) -> Result<AgentBinding, BindingVersionConflict> {
+    let actual = stored.map(AgentBinding::version).unwrap_or(0);
+    if actual != binding.version() {
+        return Err(BindingVersionConflict {
+            id: binding.id(),
+            expected: binding.version(),
+            actual,
+        });
+    }
+    binding.set_version(actual + 1);
+    Ok(binding)
+}
+
+/// JSON-file backed binding store. Every mutation rewrites the file atomically
+/// (temp file + rename) while holding the write lock, so versions observed on
+/// disk never run ahead of memory.
+pub struct FileAgentBindingRepository {
+    path: PathBuf,
+    inner: RwLock<HashMap<AgentBindingId, AgentBinding>>,
+}
+
+impl FileAgentBindingRepository {
+    pub fn new(path: PathBuf) -> Result<Self> {
+        let bindings = if path.exists() {
+            let data = std::fs::read(&path)
+                .with_context(|| format!("reading agent binding store {}", path.display()))?;
+            if data.is_empty() {
+                HashMap::new()
+            } else {
+                let snapshots: Vec<AgentBindingSnapshot> = serde_json::from_slice(&data)
+                    .with_context(|| format!("parsing agent binding store {}", path.display()))?;
+                snapshots
+                    .into_iter()
+                    .map(|snapshot| {
+                        let id = snapshot.id.clone();
+                        AgentBinding::from_snapshot(snapshot)
+                            .map(|binding| (id, binding))
+                            .map_err(|err| anyhow::anyhow!(err))
+                    })
+                    .collect::<Result<HashMap<_, _>>>()?
+            }
+        } else {
+            HashMap::new()
+        };
+
+        Ok(Self {
+            path,
+            inner: RwLock::new(bindings),
+        })
+    }
+
+    async fn persist(&self, bindings: &HashMap<AgentBindingId, AgentBinding>) -> Result<()> {
+        let snapshots: Vec<AgentBindingSnapshot> =
+            bindings.values().map(AgentBinding::to_snapshot).collect();
+        let json = serde_json::to_vec_pretty(&snapshots)?;
+        let tmp_path = self.path.with_extension(format!(
+            "{}{}",
+            self.path
+                .extension()
+                .and_then(|ext| ext.to_str())
+                .unwrap_or("json"),
+            TMP_SUFFIX
+        ));
+
+        if let Some(parent) = self.path.parent() {
+            fs::create_dir_all(parent).await?;
+        }
+
+        let mut file = fs::File::create(&tmp_path)
+            .await
+            .with_context(|| format!("creating temp agent binding store {}", tmp_path.display()))?;
+        file.write_all(&json)
+            .await
+            .with_context(|| format!("writing temp agent binding store {}", tmp_path.display()))?;
+        file.flush().await?;
+        drop(file);
+        fs::rename(&tmp_path, &self.path)
+            .await
+            .with_context(|| format!("renaming agent binding store to {}", self.path.display()))?;
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl AgentBindingRepository for FileAgentBindingRepository {
+    async fn save(&self, binding: AgentBinding) -> Result<()> {
+        let mut bindings = self.inner.write().await;
+        let binding = stamp_version(bindings.get(&binding.id()), binding)?;
+        let previous = bindings.insert(binding.id(), binding.clone());
+        if let Err(err) = self.persist(&bindings).await {
+            match previous {
+                Some(previous) => bindings.insert(binding.id(), previous),
+                None => bindings.remove(&binding.id()),
+            };
+            return Err(err);
+        }
+        Ok(())
+    }
+
+    async fn get(&self, id: &AgentBindingId) -> Result<Option<AgentBinding>> {
+        Ok(self.inner.read().await.get(id).cloned())
+    }
+
+    async fn list(&self) -> Result<Vec<AgentBinding>> {
+        Ok(self.inner.read().await.values().cloned().collect())
+    }
+
+    async fn delete(&self, id: &AgentBindingId) -> Result<()> {
+        let mut bindings = self.inner.write().await;
+        if let Some(previous) = bindings.remove(id) {
+            if let Err(err) = self.persist(&bindings).await {
+                bindings.insert(id.clone(), previous);
+                return Err(err);
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::domain::agents::{
+        AgentProvider, BindingStatus, CapabilityName, CapabilitySet, SdkChannel, SdkVersion,
+    };
+    use crate::ports::agents::BindingVersionConflict;
+    use tempfile::TempDir;
+
+    fn mk_binding() -> AgentBinding {
+        let (binding, _) = AgentBinding::register(
+            AgentProvider::Claude,
+            SdkVersion::new("1.1.0").unwrap(),
+            SdkChannel::Stable,
+            CapabilitySet::new(vec![CapabilityName::new("agent.exec").unwrap()]).unwrap(),
+        )
+        .unwrap();
+        binding
+    }
+
+    #[tokio::test]
+    async fn bindings_survive_restart() {
+        let temp = TempDir::new().unwrap();
+        let path = temp.path().join("agents").join("bindings.json");
+        let repo = FileAgentBindingRepository::new(path.clone()).unwrap();
+
+        let binding = mk_binding();
+        let id = binding.id();
+        repo.save(binding).await.unwrap();
+        let mut stored = repo.get(&id).await.unwrap().unwrap();
+        stored.activate().unwrap();
+        repo.save(stored).await.unwrap();
+
+        let reloaded = FileAgentBindingRepository::new(path).unwrap();
+        let restored = reloaded.get(&id).await.unwrap().unwrap();
+        assert_eq!(restored.status(), &BindingStatus::Active);
+        assert_eq!(restored.version(), 2);
+        assert!(restored.last_heartbeat_at().is_some());
+    }
+
+    #[tokio::test]
+    async fn stale_version_is_rejected_and_not_persisted() {
+        let temp = TempDir::new().unwrap();
+        let path = temp.path().join("bindings.json");
+        let repo = FileAgentBindingRepository::new(path.clone()).unwrap();
+        let binding = mk_binding();
+        let id = binding.id();
+        repo.save(binding).await.unwrap();
+
+        let mut first = repo.get(&id).await.unwrap().unwrap();
+        let mut second = first.clone();
+        first.activate().unwrap();
+        repo.save(first).await.unwrap();
+        second.activate().unwrap();
+        second.deactivate().unwrap();
+        let err = repo.save(second).await.unwrap_err();
+        assert!(err.downcast_ref::<BindingVersionConflict>().is_some());
+
+        let reloaded = FileAgentBindingRepository::new(path).unwrap();
+        let restored = reloaded.get(&id).await.unwrap().unwrap();
+        assert_eq!(restored.status(), &BindingStatus::Active);
+    }
+
+    #[tokio::test]
+    async fn delete_removes_from_disk() {
+        let temp = TempDir::new().unwrap();
+        let path = temp.path().join("bindings.json");
+        let repo = FileAgentBindingRepository::new(path.clone()).unwrap();
+        let binding = mk_binding();
+        let id = binding.id();
+        repo.save(binding).await.unwrap();
+        repo.delete(&id).await.unwrap();
+
+        let reloaded = FileAgentBindingRepository::new(path).unwrap();
+        assert!(reloaded.list().await.unwrap().is_empty());
+    }
+
+    #[test]
+    fn corrupted_store_returns_error() {
+        let temp = TempDir::new().unwrap();
+        let path = temp.path().join("bindings.json");
+        std::fs::write(&path, b"not-json").unwrap();
+        assert!(FileAgentBindingRepository::new(path).is_err());
+    }
+}
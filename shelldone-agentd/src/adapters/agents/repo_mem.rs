@@ -1,3 +1,4 @@
+use super::repo_file::stamp_version;
 use crate::domain::agents::{AgentBinding, AgentBindingId};
 use crate::ports::agents::AgentBindingRepository;
 use anyhow::Result;
@@ -19,7 +20,9 @@ impl InMemoryAgentBindingRepository {
 #[async_trait]
 impl AgentBindingRepository for InMemoryAgentBindingRepository {
     async fn save(&self, binding: AgentBinding) -> Result<()> {
-        self.bindings.write().await.insert(binding.id(), binding);
+        let mut bindings = self.bindings.write().await;
+        let binding = stamp_version(bindings.get(&binding.id()), binding)?;
+        bindings.insert(binding.id(), binding);
         Ok(())
     }
 
@@ -43,6 +46,7 @@ mod tests {
     use crate::domain::agents::{
         AgentBinding, AgentProvider, CapabilityName, CapabilitySet, SdkChannel, SdkVersion,
     };
+    use crate::ports::agents::BindingVersionConflict;
 
     fn mk_binding() -> AgentBinding {
         let (binding, _) = AgentBinding::register(
@@ -68,4 +72,26 @@ mod tests {
         repo.delete(&id).await.unwrap();
         assert!(repo.get(&id).await.unwrap().is_none());
     }
+
+    #[tokio::test]
+    async fn stale_save_is_rejected() {
+        let repo = InMemoryAgentBindingRepository::new();
+        let binding = mk_binding();
+        let id = binding.id();
+        repo.save(binding.clone()).await.unwrap();
+
+        let mut first = repo.get(&id).await.unwrap().unwrap();
+        let mut second = first.clone();
+        assert_eq!(first.version(), 1);
+        first.activate().unwrap();
+        repo.save(first).await.unwrap();
+
+        second.activate().unwrap();
+        let err = repo.save(second).await.unwrap_err();
+        let conflict = err.downcast_ref::<BindingVersionConflict>().unwrap();
+        assert_eq!(conflict.expected, 1);
+        assert_eq!(conflict.actual, 2);
+        // Re-saving the original unsaved aggregate must not clobber state.
+        assert!(repo.save(binding).await.is_err());
+    }
 }
@@ -0,0 +1,626 @@
+use super::manifest::AdapterSpec;
+use serde::Serialize;
+use serde_json::Value;
+use std::collections::HashMap;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::process::Stdio;
+use std::time::Duration;
+use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader, Lines};
+use tokio::process::{Child, ChildStderr, ChildStdin, ChildStdout, Command};
+use tokio::sync::{mpsc, watch, Mutex};
+use tokio::task::JoinHandle;
+use tokio::time::{self, Instant, MissedTickBehavior};
+use tracing::{debug, info, warn};
+
+const SHUTDOWN_COMMAND: &[u8] = b"{\"type\":\"shutdown\"}\n";
+
+#[derive(Clone, Debug)]
+pub struct BridgeSupervisorConfig {
+    /// First restart delay; doubles per consecutive crash.
+    pub backoff_initial: Duration,
+    pub backoff_max: Duration,
+    /// Uptime after which a bridge counts as stable and backoff resets.
+    pub stable_after: Duration,
+    pub handshake_timeout: Duration,
+    /// Time granted after `{"type":"shutdown"}` before the child is killed.
+    pub shutdown_grace: Duration,
+    /// Cadence of [`BridgeEvent::Alive`] while the bridge runs.
+    pub heartbeat_interval: Duration,
+}
+
+impl Default for BridgeSupervisorConfig {
+    fn default() -> Self {
+        Self {
+            backoff_initial: Duration::from_millis(500),
+            backoff_max: Duration::from_secs(30),
+            stable_after: Duration::from_secs(60),
+            handshake_timeout: Duration::from_secs(10),
+            shutdown_grace: Duration::from_secs(5),
+            heartbeat_interval: Duration::from_secs(10),
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum BridgeEvent {
+    Ready {
+        adapter: String,
+        pid: Option<u32>,
+        handshake: Value,
+    },
+    Alive {
+        adapter: String,
+    },
+    Exited {
+        adapter: String,
+        code: Option<i32>,
+        restart_in: Duration,
+    },
+    /// The bridge answered the handshake with `status: "error"` (typically a
+    /// missing SDK). It is not restarted until its binding is re-activated.
+    HandshakeFailed {
+        adapter: String,
+        error: String,
+    },
+}
+
+impl BridgeEvent {
+    pub fn adapter(&self) -> &str {
+        match self {
+            BridgeEvent::Ready { adapter, .. }
+            | BridgeEvent::Alive { adapter }
+            | BridgeEvent::Exited { adapter, .. }
+            | BridgeEvent::HandshakeFailed { adapter, .. } => adapter,
+        }
+    }
+}
+
+struct SupervisedBridge {
+    stop: watch::Sender<bool>,
+    task: JoinHandle<()>,
+}
+
+/// Runs manifest adapters as child processes and restarts them on crash.
+pub struct BridgeSupervisor {
+    root: PathBuf,
+    config: BridgeSupervisorConfig,
+    events: mpsc::UnboundedSender<BridgeEvent>,
+    bridges: Mutex<HashMap<String, SupervisedBridge>>,
+}
+
+impl BridgeSupervisor {
+    pub fn new(
+        root: PathBuf,
+        config: BridgeSupervisorConfig,
+    ) -> (Self, mpsc::UnboundedReceiver<BridgeEvent>) {
+        let (events, receiver) = mpsc::unbounded_channel();
+        (
+            Self {
+                root,
+                config,
+                events,
+                bridges: Mutex::new(HashMap::new()),
+            },
+            receiver,
+        )
+    }
+
+    /// Start supervising `spec`. Returns `false` if the adapter is already
+    /// supervised, including bridges parked after a rejected handshake.
+    pub async fn start(&self, spec: &AdapterSpec) -> bool {
+        let mut bridges = self.bridges.lock().await;
+        if bridges.contains_key(&spec.id) {
+            return false;
+        }
+        let (stop, stop_rx) = watch::channel(false);
+        let task = tokio::spawn(supervise(
+            spec.clone(),
+            self.root.clone(),
+            self.config.clone(),
+            self.events.clone(),
+            stop_rx,
+        ));
+        bridges.insert(spec.id.clone(), SupervisedBridge { stop, task });
+        true
+    }
+
+    /// Shut the bridge down gracefully and forget it. Returns `false` if the
+    /// adapter was not supervised.
+    pub async fn stop(&self, id: &str) -> bool {
+        let bridge = self.bridges.lock().await.remove(id);
+        let Some(bridge) = bridge else {
+            return false;
+        };
+        let _ = bridge.stop.send(true);
+        if let Err(err) = bridge.task.await {
+            warn!(adapter = id, %err, "agent bridge supervisor task failed");
+        }
+        true
+    }
+
+    pub async fn stop_all(&self) {
+        let ids: Vec<String> = self.bridges.lock().await.keys().cloned().collect();
+        for id in ids {
+            self.stop(&id).await;
+        }
+    }
+
+    #[cfg(test)]
+    pub async fn is_supervised(&self, id: &str) -> bool {
+        self.bridges.lock().await.contains_key(id)
+    }
+}
+
+enum RunOutcome {
+    Stopped,
+    Rejected(String),
+    Exited(Option<i32>),
+}
+
+async fn supervise(
+    spec: AdapterSpec,
+    root: PathBuf,
+    config: BridgeSupervisorConfig,
+    events: mpsc::UnboundedSender<BridgeEvent>,
+    mut stop: watch::Receiver<bool>,
+) {
+    let mut attempt: u32 = 0;
+    loop {
+        let started = Instant::now();
+        match run_once(&spec, &root, &config, &events, &mut stop).await {
+            RunOutcome::Stopped => return,
+            RunOutcome::Rejected(error) => {
+                warn!(adapter = %spec.id, %error, "agent bridge rejected handshake");
+                let _ = events.send(BridgeEvent::HandshakeFailed {
+                    adapter: spec.id.clone(),
+                    error,
+                });
+                return;
+            }
+            RunOutcome::Exited(code) => {
+                if started.elapsed() >= config.stable_after {
+                    attempt = 0;
+                }
+                let delay = backoff_delay(&config, attempt);
+                attempt = attempt.saturating_add(1);
+                warn!(
+                    adapter = %spec.id,
+                    ?code,
+                    restart_in_ms = delay.as_millis() as u64,
+                    "agent bridge exited; restarting"
+                );
+                let _ = events.send(BridgeEvent::Exited {
+                    adapter: spec.id.clone(),
+                    code,
+                    restart_in: delay,
+                });
+                tokio::select! {
+                    _ = time::sleep(delay) => {}
+                    _ = stop.changed() => return,
+                }
+            }
+        }
+    }
+}
+
+enum Wake {
+    Exited(Option<i32>),
+    Stop,
+    Tick,
+}
+
+async fn run_once(
+    spec: &AdapterSpec,
+    root: &Path,
+    config: &BridgeSupervisorConfig,
+    events: &mpsc::UnboundedSender<BridgeEvent>,
+    stop: &mut watch::Receiver<bool>,
+) -> RunOutcome {
+    let mut child = match spawn_bridge(spec, root) {
+        Ok(child) => child,
+        Err(err) => {
+            warn!(adapter = %spec.id, %err, "failed to spawn agent bridge");
+            return RunOutcome::Exited(None);
+        }
+    };
+    let pid = child.id();
+    let mut stdin = child.stdin.take();
+    if let Some(stderr) = child.stderr.take() {
+        tokio::spawn(forward_stderr(spec.id.clone(), stderr));
+    }
+    let Some(stdout) = child.stdout.take() else {
+        let _ = child.kill().await;
+        return RunOutcome::Exited(None);
+    };
+    let mut lines = BufReader::new(stdout).lines();
+
+    let handshake = tokio::select! {
+        result = read_handshake(&mut lines, config.handshake_timeout) => Some(result),
+        _ = stop.changed() => None,
+    };
+    match handshake {
+        None => {
+            shutdown_child(&mut child, stdin.as_mut(), config.shutdown_grace).await;
+            return RunOutcome::Stopped;
+        }
+        Some(Ok(Handshake::Ready(payload))) => {
+            info!(adapter = %spec.id, ?pid, "agent bridge ready");
+            let _ = events.send(BridgeEvent::Ready {
+                adapter: spec.id.clone(),
+                pid,
+                handshake: payload,
+            });
+        }
+        Some(Ok(Handshake::Rejected(error))) => {
+            shutdown_child(&mut child, stdin.as_mut(), config.shutdown_grace).await;
+            return RunOutcome::Rejected(error);
+        }
+        Some(Err(reason)) => {
+            warn!(adapter = %spec.id, %reason, "agent bridge handshake failed");
+            let _ = child.kill().await;
+            let code = child.try_wait().ok().flatten().and_then(|s| s.code());
+            return RunOutcome::Exited(code);
+        }
+    }
+
+    let drain = tokio::spawn(drain_stdout(spec.id.clone(), lines));
+    let mut ticker = time::interval_at(
+        Instant::now() + config.heartbeat_interval,
+        config.heartbeat_interval,
+    );
+    ticker.set_missed_tick_behavior(MissedTickBehavior::Delay);
+    let outcome = loop {
+        let wake = tokio::select! {
+            status = child.wait() => Wake::Exited(status.ok().and_then(|s| s.code())),
+            _ = stop.changed() => Wake::Stop,
+            _ = ticker.tick() => Wake::Tick,
+        };
+        match wake {
+            Wake::Exited(code) => break RunOutcome::Exited(code),
+            Wake::Stop => {
+                shutdown_child(&mut child, stdin.as_mut(), config.shutdown_grace).await;
+                break RunOutcome::Stopped;
+            }
+            Wake::Tick => {
+                let _ = events.send(BridgeEvent::Alive {
+                    adapter: spec.id.clone(),
+                });
+            }
+        }
+    };
+    drain.abort();
+    outcome
+}
+
+fn spawn_bridge(spec: &AdapterSpec, root: &Path) -> io::Result<Child> {
+    let (program, args) = spec
+        .command
+        .split_first()
+        .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidInput, "empty adapter command"))?;
+    Command::new(program)
+        .args(args)
+        .current_dir(root)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .kill_on_drop(true)
+        .spawn()
+}
+
+enum Handshake {
+    Ready(Value),
+    Rejected(String),
+}
+
+async fn read_handshake(
+    lines: &mut Lines<BufReader<ChildStdout>>,
+    timeout: Duration,
+) -> Result<Handshake, String> {
+    match time::timeout(timeout, lines.next_line()).await {
+        Err(_) => Err(format!("no handshake within {} ms", timeout.as_millis())),
+        Ok(Err(err)) => Err(format!("reading handshake: {err}")),
+        Ok(Ok(None)) => Err("bridge closed stdout before handshake".into()),
+        Ok(Ok(Some(line))) => parse_handshake(&line),
+    }
+}
+
+fn parse_handshake(line: &str) -> Result<Handshake, String> {
+    let payload: Value = serde_json::from_str(line.trim())
+        .map_err(|err| format!("invalid handshake JSON: {err}"))?;
+    match payload.get("status").and_then(Value::as_str) {
+        Some("ready") => Ok(Handshake::Ready(payload)),
+        Some("error") => Ok(Handshake::Rejected(
+            payload
+                .get("error")
+                .and_then(Value::as_str)
+                .unwrap_or("bridge reported an error")
+                .to_string(),
+        )),
+        other => Err(format!("unexpected handshake status {other:?}")),
+    }
+}
+
+async fn shutdown_child(child: &mut Child, stdin: Option<&mut ChildStdin>, grace: Duration) {
+    if let Some(stdin) = stdin {
+        let _ = stdin.write_all(SHUTDOWN_COMMAND).await;
+        let _ = stdin.flush().await;
+    }
+    if time::timeout(grace, child.wait()).await.is_err() {
+        let _ = child.kill().await;
+    }
+}
+
+async fn forward_stderr(adapter: String, stderr: ChildStderr) {
+    let mut lines = BufReader::new(stderr).lines();
+    while let Ok(Some(line)) = lines.next_line().await {
+        if !line.trim().is_empty() {
+            info!(adapter = %adapter, stream = "stderr", "{line}");
+        }
+    }
+}
+
+async fn drain_stdout(adapter: String, mut lines: Lines<BufReader<ChildStdout>>) {
+    while let Ok(Some(line)) = lines.next_line().await {
+        debug!(adapter = %adapter, stream = "stdout", "{line}");
+    }
+}
+
+fn backoff_delay(config: &BridgeSupervisorConfig, attempt: u32) -> Duration {
+    let factor = 1u32.checked_shl(attempt.min(16)).unwrap_or(u32::MAX);
+    config
+        .backoff_initial
+        .saturating_mul(factor)
+        .min(config.backoff_max)
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize)]
+#[serde(tag = "outcome", rename_all = "snake_case")]
+pub enum SmokeOutcome {
+    Passed {
+        handshake: Value,
+    },
+    /// Runtime or SDK missing in a way the manifest anticipates.
+    Skipped {
+        reason: String,
+    },
+    Failed {
+        reason: String,
+    },
+}
+
+#[derive(Clone, Debug, Serialize)]
+pub struct SmokeReport {
+    pub adapter: String,
+    #[serde(flatten)]
+    pub outcome: SmokeOutcome,
+}
+
+/// Launch the bridge once, verify its handshake and shut it down.
+pub async fn smoke_test(spec: &AdapterSpec, root: &Path, timeout: Duration) -> SmokeReport {
+    let outcome = match spawn_bridge(spec, root) {
+        Err(err) if err.kind() == io::ErrorKind::NotFound => SmokeOutcome::Skipped {
+            reason: format!("runtime {} not found", spec.command[0]),
+        },
+        Err(err) => SmokeOutcome::Failed {
+            reason: format!("failed to spawn bridge: {err}"),
+        },
+        Ok(mut child) => {
+            let mut stdin = child.stdin.take();
+            if let Some(stderr) = child.stderr.take() {
+                tokio::spawn(forward_stderr(spec.id.clone(), stderr));
+            }
+            let outcome = match child.stdout.take() {
+                None => SmokeOutcome::Failed {
+                    reason: "bridge stdout unavailable".into(),
+                },
+                Some(stdout) => {
+                    let mut lines = BufReader::new(stdout).lines();
+                    match read_handshake(&mut lines, timeout).await {
+                        Ok(Handshake::Ready(handshake)) => SmokeOutcome::Passed { handshake },
+                        Ok(Handshake::Rejected(error))
+                            if spec
+                                .error_contains
+                                .iter()
+                                .any(|token| error.contains(token.as_str())) =>
+                        {
+                            SmokeOutcome::Skipped { reason: error }
+                        }
+                        Ok(Handshake::Rejected(error)) => SmokeOutcome::Failed {
+                            reason: format!("unexpected bridge error: {error}"),
+                        },
+                        Err(reason) => SmokeOutcome::Failed { reason },
+                    }
+                }
+            };
+            shutdown_child(&mut child, stdin.as_mut(), Duration::from_secs(2)).await;
+            outcome
+        }
+    };
+    SmokeReport {
+        adapter: spec.id.clone(),
+        outcome,
+    }
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    const READY_BRIDGE: &str = r#"
+echo "fake bridge booting" >&2
+echo '{"status":"ready","model":"fake"}'
+while read line; do
+  case "$line" in
+    *shutdown*) touch "$(dirname "$0")/shutdown.marker"; echo '{"status":"ok"}'; exit 0;;
+  esac
+done
+"#;
+
+    const CRASHING_BRIDGE: &str = r#"
+echo '{"status":"ready"}'
+exit 3
+"#;
+
+    const MISSING_SDK_BRIDGE: &str = r#"
+echo '{"status":"error","error":"fake-agents-sdk is not installed"}'
+exit 1
+"#;
+
+    fn fake_bridge(dir: &TempDir, id: &str, script: &str) -> AdapterSpec {
+        let path = dir.path().join(format!("{id}.sh"));
+        std::fs::write(&path, script).unwrap();
+        AdapterSpec {
+            id: id.to_string(),
+            command: vec!["sh".into(), path.display().to_string()],
+            error_contains: vec!["fake-agents-sdk".into()],
+            enabled: true,
+        }
+    }
+
+    fn fast_config() -> BridgeSupervisorConfig {
+        BridgeSupervisorConfig {
+            backoff_initial: Duration::from_millis(10),
+            backoff_max: Duration::from_millis(40),
+            stable_after: Duration::from_secs(60),
+            handshake_timeout: Duration::from_secs(5),
+            shutdown_grace: Duration::from_secs(2),
+            heartbeat_interval: Duration::from_millis(50),
+        }
+    }
+
+    async fn next_event(events: &mut mpsc::UnboundedReceiver<BridgeEvent>) -> BridgeEvent {
+        time::timeout(Duration::from_secs(5), events.recv())
+            .await
+            .expect("bridge event in time")
+            .expect("event channel open")
+    }
+
+    #[test]
+    fn backoff_grows_and_caps() {
+        let config = BridgeSupervisorConfig::default();
+        assert_eq!(backoff_delay(&config, 0), Duration::from_millis(500));
+        assert_eq!(backoff_delay(&config, 1), Duration::from_secs(1));
+        assert_eq!(backoff_delay(&config, 3), Duration::from_secs(4));
+        assert_eq!(backoff_delay(&config, 40), Duration::from_secs(30));
+    }
+
+    #[test]
+    fn handshake_parsing() {
+        assert!(matches!(
+            parse_handshake(r#"{"status":"ready"}"#),
+            Ok(Handshake::Ready(_))
+        ));
+        assert!(matches!(
+            parse_handshake(r#"{"status":"error","error":"boom"}"#),
+            Ok(Handshake::Rejected(error)) if error == "boom"
+        ));
+        assert!(parse_handshake(r#"{"status":"ok"}"#).is_err());
+        assert!(parse_handshake("not json").is_err());
+    }
+
+    #[tokio::test]
+    async fn ready_bridge_heartbeats_and_stops_gracefully() {
+        let temp = TempDir::new().unwrap();
+        let spec = fake_bridge(&temp, "ready", READY_BRIDGE);
+        let (supervisor, mut events) =
+            BridgeSupervisor::new(temp.path().to_path_buf(), fast_config());
+
+        assert!(supervisor.start(&spec).await);
+        assert!(!supervisor.start(&spec).await);
+        assert!(matches!(
+            next_event(&mut events).await,
+            BridgeEvent::Ready { ref handshake, .. } if handshake["model"] == "fake"
+        ));
+        assert_eq!(
+            next_event(&mut events).await,
+            BridgeEvent::Alive {
+                adapter: "ready".into()
+            }
+        );
+
+        assert!(supervisor.stop("ready").await);
+        assert!(temp.path().join("shutdown.marker").exists());
+        assert!(!supervisor.is_supervised("ready").await);
+    }
+
+    #[tokio::test]
+    async fn crashed_bridge_is_restarted_with_backoff() {
+        let temp = TempDir::new().unwrap();
+        let spec = fake_bridge(&temp, "crashy", CRASHING_BRIDGE);
+        let (supervisor, mut events) =
+            BridgeSupervisor::new(temp.path().to_path_buf(), fast_config());
+        supervisor.start(&spec).await;
+
+        let mut delays = Vec::new();
+        while delays.len() < 3 {
+            match next_event(&mut events).await {
+                BridgeEvent::Exited {
+                    code, restart_in, ..
+                } => {
+                    assert_eq!(code, Some(3));
+                    delays.push(restart_in);
+                }
+                BridgeEvent::Ready { .. } | BridgeEvent::Alive { .. } => {}
+                other => panic!("unexpected event {other:?}"),
+            }
+        }
+        assert_eq!(
+            delays,
+            vec![
+                Duration::from_millis(10),
+                Duration::from_millis(20),
+                Duration::from_millis(40)
+            ]
+        );
+        supervisor.stop_all().await;
+    }
+
+    #[tokio::test]
+    async fn rejected_handshake_parks_bridge() {
+        let temp = TempDir::new().unwrap();
+        let spec = fake_bridge(&temp, "nosdk", MISSING_SDK_BRIDGE);
+        let (supervisor, mut events) =
+            BridgeSupervisor::new(temp.path().to_path_buf(), fast_config());
+        supervisor.start(&spec).await;
+
+        assert!(matches!(
+            next_event(&mut events).await,
+            BridgeEvent::HandshakeFailed { ref error, .. } if error.contains("fake-agents-sdk")
+        ));
+        assert!(supervisor.is_supervised("nosdk").await);
+        assert!(!supervisor.start(&spec).await);
+        assert!(supervisor.stop("nosdk").await);
+        assert!(supervisor.start(&spec).await);
+        supervisor.stop_all().await;
+    }
+
+    #[tokio::test]
+    async fn smoke_reports_each_outcome() {
+        let temp = TempDir::new().unwrap();
+        let timeout = Duration::from_secs(5);
+
+        let ready = fake_bridge(&temp, "ready", READY_BRIDGE);
+        let report = smoke_test(&ready, temp.path(), timeout).await;
+        assert!(matches!(report.outcome, SmokeOutcome::Passed { .. }));
+
+        let nosdk = fake_bridge(&temp, "nosdk", MISSING_SDK_BRIDGE);
+        let report = smoke_test(&nosdk, temp.path(), timeout).await;
+        assert!(matches!(report.outcome, SmokeOutcome::Skipped { .. }));
+
+        let mut unexpected = nosdk.clone();
+        unexpected.error_contains = vec!["other-sdk".into()];
+        let report = smoke_test(&unexpected, temp.path(), timeout).await;
+        assert!(matches!(report.outcome, SmokeOutcome::Failed { .. }));
+
+        let mut missing_runtime = ready.clone();
+        missing_runtime.command = vec!["/nonexistent/shelldone-runtime".into()];
+        let report = smoke_test(&missing_runtime, temp.path(), timeout).await;
+        assert!(matches!(report.outcome, SmokeOutcome::Skipped { .. }));
+
+        let json = serde_json::to_value(smoke_test(&ready, temp.path(), timeout).await).unwrap();
+        assert_eq!(json["adapter"], "ready");
+        assert_eq!(json["outcome"], "passed");
+    }
+}
@@ -1,12 +1,17 @@
 use crate::app::ack::service::AckPort;
-use crate::app::mcp::service::{McpBridgeError, McpBridgeService};
-use crate::domain::mcp::SessionId;
+use crate::app::mcp::service::{tool_result_payload, McpBridgeError, McpBridgeService};
+use crate::domain::mcp::{BufferedToolResult, SessionId};
 use crate::ports::mcp::repo_port::McpSessionRepository;
+use crate::telemetry;
+use opentelemetry::trace::{FutureExt as _, SpanKind};
+use opentelemetry::{Context as OtelContext, KeyValue};
 use prost_types::Timestamp;
 use serde_json::Value;
 use std::sync::Arc;
 use std::time::SystemTime;
+use tokio::sync::oneshot;
 use tonic::{async_trait, Response, Status};
+use tracing::warn;
 use uuid::Uuid;
 
 pub mod proto {
@@ -15,8 +20,9 @@ pub mod proto {
 
 use proto::mcp_bridge_server::{McpBridge, McpBridgeServer};
 use proto::{
-    CallToolRequest, CallToolResponse, HeartbeatRequest, HeartbeatResponse, InitializeRequest,
-    InitializeResponse, ListToolsRequest, ListToolsResponse, ToolDescriptor,
+    BufferedResult, CallToolRequest, CallToolResponse, HeartbeatRequest, HeartbeatResponse,
+    InitializeRequest, InitializeResponse, ListToolsRequest, ListToolsResponse, ResumeRequest,
+    ResumeResponse, ToolDescriptor,
 };
 
 #[derive(Clone)]
@@ -52,117 +58,196 @@ where
         &self,
         request: tonic::Request<InitializeRequest>,
     ) -> Result<Response<InitializeResponse>, Status> {
-        let payload = request.into_inner();
-        if payload.protocol_version.trim().is_empty() {
-            return Err(Status::invalid_argument("protocol_version is required"));
-        }
+        let cx = grpc_span("Initialize", &request);
+        async move {
+            let payload = request.into_inner();
+            if payload.protocol_version.trim().is_empty() {
+                return Err(Status::invalid_argument("protocol_version is required"));
+            }
 
-        let session = self
-            .bridge
-            .initialize_session(
-                optional_string(payload.persona),
-                payload.protocol_version.clone(),
-                payload.capabilities.clone(),
-            )
-            .await
-            .map_err(map_bridge_error)?;
+            let (session, resume_token) = self
+                .bridge
+                .initialize_session(
+                    optional_string(payload.persona),
+                    payload.protocol_version.clone(),
+                    payload.capabilities.clone(),
+                )
+                .await
+                .map_err(map_bridge_error)?;
 
-        let response = InitializeResponse {
-            session_id: session.id().to_string(),
-            protocol_version: payload.protocol_version,
-            capabilities: session.capability_names().into_iter().collect(),
-        };
+            let response = InitializeResponse {
+                session_id: session.id().to_string(),
+                protocol_version: payload.protocol_version,
+                capabilities: session.capability_names().into_iter().collect(),
+                resume_token,
+            };
 
-        Ok(Response::new(response))
+            Ok(Response::new(response))
+        }
+        .with_context(cx)
+        .await
+    }
+
+    async fn resume(
+        &self,
+        request: tonic::Request<ResumeRequest>,
+    ) -> Result<Response<ResumeResponse>, Status> {
+        let cx = grpc_span("Resume", &request);
+        async move {
+            let payload = request.into_inner();
+            let session_id = parse_session_id(&payload.session_id)?;
+            let (session, replay) = self
+                .bridge
+                .resume_session(&session_id, &payload.resume_token)
+                .await
+                .map_err(map_bridge_error)?;
+            Ok(Response::new(ResumeResponse {
+                session_id: session.id().to_string(),
+                protocol_version: session.protocol_version().cloned().unwrap_or_default(),
+                capabilities: session.capability_names(),
+                replay: replay.into_iter().map(buffered_to_proto).collect(),
+            }))
+        }
+        .with_context(cx)
+        .await
     }
 
     async fn list_tools(
         &self,
-        _request: tonic::Request<ListToolsRequest>,
+        request: tonic::Request<ListToolsRequest>,
     ) -> Result<Response<ListToolsResponse>, Status> {
-        let tools_json = self.bridge.list_tools().await;
-        let mut tools = Vec::new();
-        if let Some(array) = tools_json.get("tools").and_then(Value::as_array) {
-            for tool in array {
-                let name = tool
-                    .get("name")
-                    .and_then(Value::as_str)
-                    .unwrap_or_default()
-                    .to_string();
-                let description = tool
-                    .get("description")
-                    .and_then(Value::as_str)
-                    .unwrap_or_default()
-                    .to_string();
-                let input_schema_json = tool
-                    .get("inputSchema")
-                    .map(|schema| schema.to_string())
-                    .unwrap_or_else(|| "{}".into());
-                tools.push(ToolDescriptor {
-                    name,
-                    description,
-                    input_schema_json,
-                });
+        let cx = grpc_span("ListTools", &request);
+        async move {
+            let tools_json = self.bridge.list_tools().await;
+            let mut tools = Vec::new();
+            if let Some(array) = tools_json.get("tools").and_then(Value::as_array) {
+                for tool in array {
+                    let name = tool
+                        .get("name")
+                        .and_then(Value::as_str)
+                        .unwrap_or_default()
+                        .to_string();
+                    let description = tool
+                        .get("description")
+                        .and_then(Value::as_str)
+                        .unwrap_or_default()
+                        .to_string();
+                    let input_schema_json = tool
+                        .get("inputSchema")
+                        .map(|schema| schema.to_string())
+                        .unwrap_or_else(|| "{}".into());
+                    tools.push(ToolDescriptor {
+                        name,
+                        description,
+                        input_schema_json,
+                    });
+                }
             }
+            Ok(Response::new(ListToolsResponse { tools }))
         }
-        Ok(Response::new(ListToolsResponse { tools }))
+        .with_context(cx)
+        .await
     }
 
     async fn call_tool(
         &self,
         request: tonic::Request<CallToolRequest>,
     ) -> Result<Response<CallToolResponse>, Status> {
-        let payload = request.into_inner();
-        let session_id = parse_session_id(&payload.session_id)?;
-        let arguments = parse_json(&payload.arguments_json)?;
+        let cx = grpc_span("CallTool", &request);
+        async move {
+            let payload = request.into_inner();
+            let session_id = parse_session_id(&payload.session_id)?;
+            let arguments = parse_json(&payload.arguments_json)?;
 
-        let mut session = self
-            .bridge
-            .get_session(&session_id)
-            .await
-            .ok_or_else(|| Status::not_found("session not found"))?;
+            let mut session = self
+                .bridge
+                .get_session(&session_id)
+                .await
+                .ok_or_else(|| Status::not_found("session not found"))?;
 
-        let exec = self
-            .bridge
-            .call_tool(&mut session, &payload.tool_name, arguments)
-            .await
-            .map_err(map_bridge_error)?;
-
-        let response = CallToolResponse {
-            exit_code: exec.exit_code,
-            stdout: exec.stdout,
-            stderr: exec.stderr,
-            event_id: exec.event_id,
-            spectral_tag: exec.spectral_tag,
-            duration_ms: exec.duration_ms,
-            is_error: exec.exit_code != 0,
-        };
+            // Run the tool detached from this request so a client that hangs up
+            // mid-call can collect the result through Resume.
+            let bridge = self.bridge.clone();
+            let tool_name = payload.tool_name;
+            let call_id = optional_string(payload.call_id).map(Value::String);
+            let (tx, rx) = oneshot::channel();
+            tokio::spawn(
+                async move {
+                    let outcome = bridge.call_tool(&mut session, &tool_name, arguments).await;
+                    if let Err(Ok(exec)) = tx.send(outcome) {
+                        let result = tool_result_payload(&exec);
+                        if let Err(err) = bridge
+                            .buffer_result(&session.id(), call_id, &tool_name, result)
+                            .await
+                        {
+                            warn!("failed to buffer MCP tool result: {err}");
+                        }
+                    }
+                }
+                .with_current_context(),
+            );
+            let exec = rx
+                .await
+                .map_err(|_| Status::internal("tool call aborted"))?
+                .map_err(map_bridge_error)?;
+
+            let response = CallToolResponse {
+                exit_code: exec.exit_code,
+                stdout: exec.stdout,
+                stderr: exec.stderr,
+                event_id: exec.event_id,
+                spectral_tag: exec.spectral_tag,
+                duration_ms: exec.duration_ms,
+                is_error: exec.exit_code != 0,
+            };
 
-        Ok(Response::new(response))
+            Ok(Response::new(response))
+        }
+        .with_context(cx)
+        .await
     }
 
     async fn heartbeat(
         &self,
         request: tonic::Request<HeartbeatRequest>,
     ) -> Result<Response<HeartbeatResponse>, Status> {
-        let payload = request.into_inner();
-        let session_id = parse_session_id(&payload.session_id)?;
-        let mut session = self
-            .bridge
-            .get_session(&session_id)
-            .await
-            .ok_or_else(|| Status::not_found("session not found"))?;
-        self.bridge
-            .record_heartbeat(&mut session)
-            .await
-            .map_err(map_bridge_error)?;
-        let timestamp = Timestamp::from(SystemTime::now());
-        Ok(Response::new(HeartbeatResponse {
-            occurred_at: Some(timestamp),
-        }))
+        let cx = grpc_span("Heartbeat", &request);
+        async move {
+            let payload = request.into_inner();
+            let session_id = parse_session_id(&payload.session_id)?;
+            let mut session = self
+                .bridge
+                .get_session(&session_id)
+                .await
+                .ok_or_else(|| Status::not_found("session not found"))?;
+            self.bridge
+                .record_heartbeat(&mut session)
+                .await
+                .map_err(map_bridge_error)?;
+            let timestamp = Timestamp::from(SystemTime::now());
+            Ok(Response::new(HeartbeatResponse {
+                occurred_at: Some(timestamp),
+            }))
+        }
+        .with_context(cx)
+        .await
     }
 }
 
+/// Server span for one RPC, parented to the caller's `traceparent` metadata.
+fn grpc_span<T>(method: &'static str, request: &tonic::Request<T>) -> OtelContext {
+    let parent = telemetry::remote_context(&telemetry::MetadataCarrier(request.metadata()));
+    telemetry::start_span(
+        format!("mcp.grpc {method}"),
+        SpanKind::Server,
+        &parent,
+        vec![
+            KeyValue::new("rpc.system", "grpc"),
+            KeyValue::new("rpc.method", method),
+        ],
+    )
+}
+
 #[allow(clippy::result_large_err)]
 fn parse_session_id(raw: &str) -> Result<SessionId, Status> {
     let uuid = Uuid::parse_str(raw).map_err(|_| Status::invalid_argument("invalid session_id"))?;
@@ -177,6 +262,20 @@ fn parse_json(raw: &str) -> Result<Value, Status> {
     serde_json::from_str(raw).map_err(|err| Status::invalid_argument(err.to_string()))
 }
 
+fn buffered_to_proto(buffered: BufferedToolResult) -> BufferedResult {
+    let call_id = match buffered.call_id {
+        Some(Value::String(id)) => id,
+        Some(other) => other.to_string(),
+        None => String::new(),
+    };
+    BufferedResult {
+        call_id,
+        tool_name: buffered.tool,
+        result_json: buffered.result.to_string(),
+        completed_at: Some(Timestamp::from(SystemTime::from(buffered.completed_at))),
+    }
+}
+
 fn optional_string(input: String) -> Option<String> {
     let trimmed = input.trim();
     if trimmed.is_empty() {
@@ -194,6 +293,17 @@ fn map_bridge_error(err: McpBridgeError) -> Status {
         }
         McpBridgeError::ToolFailure(reason) => Status::failed_precondition(reason),
         McpBridgeError::Internal(reason) => Status::internal(reason),
+        McpBridgeError::Forbidden(reason) => Status::permission_denied(reason),
+        McpBridgeError::RateLimited {
+            message,
+            retry_after_ms,
+        } => {
+            let mut status = Status::resource_exhausted(message);
+            if let Ok(value) = retry_after_ms.to_string().parse() {
+                status.metadata_mut().insert("retry-after-ms", value);
+            }
+            status
+        }
     }
 }
 
@@ -275,6 +385,7 @@ mod tests {
             session_id,
             tool_name: "agent.exec".into(),
             arguments_json: "{\"cmd\":\"echo grpc\"}".into(),
+            call_id: String::new(),
         };
 
         let response = grpc
@@ -287,4 +398,67 @@ mod tests {
         assert!(response.stdout.contains("grpc"));
         assert!(!response.event_id.is_empty());
     }
+
+    #[tokio::test]
+    async fn resume_replays_buffered_results() {
+        let bridge = build_bridge();
+        let grpc = GrpcBridge::new(bridge.clone());
+        let init = grpc
+            .initialize(Request::new(InitializeRequest {
+                persona: "core".into(),
+                protocol_version: "1.0".into(),
+                capabilities: vec![],
+            }))
+            .await
+            .unwrap()
+            .into_inner();
+        assert!(!init.resume_token.is_empty());
+        let session_id = parse_session_id(&init.session_id).unwrap();
+        bridge
+            .buffer_result(
+                &session_id,
+                Some(Value::String("call-1".into())),
+                "agent.exec",
+                serde_json::json!({"isError": false}),
+            )
+            .await
+            .unwrap();
+
+        let denied = grpc
+            .resume(Request::new(ResumeRequest {
+                session_id: init.session_id.clone(),
+                resume_token: "nope".into(),
+            }))
+            .await
+            .unwrap_err();
+        assert_eq!(denied.code(), tonic::Code::PermissionDenied);
+
+        let resumed = grpc
+            .resume(Request::new(ResumeRequest {
+                session_id: init.session_id.clone(),
+                resume_token: init.resume_token,
+            }))
+            .await
+            .unwrap()
+            .into_inner();
+        assert_eq!(resumed.session_id, init.session_id);
+        assert_eq!(resumed.protocol_version, "1.0");
+        assert_eq!(resumed.replay.len(), 1);
+        assert_eq!(resumed.replay[0].call_id, "call-1");
+        assert_eq!(resumed.replay[0].tool_name, "agent.exec");
+    }
+
+    #[test]
+    fn rate_limited_maps_to_resource_exhausted() {
+        let status = map_bridge_error(McpBridgeError::RateLimited {
+            message: "rate limit exceeded".into(),
+            retry_after_ms: 250,
+        });
+        assert_eq!(status.code(), tonic::Code::ResourceExhausted);
+        let retry_after = status
+            .metadata()
+            .get("retry-after-ms")
+            .and_then(|value| value.to_str().ok());
+        assert_eq!(retry_after, Some("250"));
+    }
 }
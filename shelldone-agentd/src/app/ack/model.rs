@@ -14,6 +14,11 @@ pub struct EventRecord {
     pub payload: Value,
     pub spectral_tag: Option<String>,
     pub bytes: Option<usize>,
+    /// Trace of the request that produced the event (W3C hex ids).
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub trace_id: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub span_id: Option<String>,
 }
 
 impl EventRecord {
@@ -25,6 +30,7 @@ impl EventRecord {
         spectral_tag: Option<String>,
         bytes: Option<usize>,
     ) -> Self {
+        let (trace_id, span_id) = crate::telemetry::current_trace_ids().unzip();
         Self {
             event_id: event_id.unwrap_or_else(|| uuid::Uuid::new_v4().to_string()),
             kind: kind.to_string(),
@@ -33,6 +39,8 @@ impl EventRecord {
             payload,
             spectral_tag,
             bytes,
+            trace_id,
+            span_id,
         }
     }
 }
@@ -3,8 +3,9 @@ use super::model::{EventRecord, ExecArgs, ExecRequest, ExecResult, UndoRequest,
 use crate::continuum::{ContinuumSnapshot, ContinuumStore};
 use crate::policy_engine::{AckPolicyInput, PolicyDecision, PolicyEngine};
 use crate::ports::ack::command_runner::CommandRunner;
-use crate::telemetry::PrismMetrics;
+use crate::telemetry::{self, PrismMetrics};
 use async_trait::async_trait;
+use opentelemetry::KeyValue;
 use serde_json::json;
 use std::path::{Path, PathBuf};
 use std::sync::{Arc, Mutex};
@@ -95,11 +96,26 @@ impl<R: CommandRunner> AckService<R> {
         self.record_policy_metrics("agent.exec", true, request.persona.as_deref());
 
         let start = chrono::Utc::now();
-        let output = self
-            .command_runner
-            .run(&request.args)
-            .await
-            .map_err(|err| AckError::Internal(err.to_string()))?;
+        let output = telemetry::in_span(
+            "ack.exec",
+            vec![KeyValue::new(
+                "shelldone.persona",
+                request.persona.clone().unwrap_or_default(),
+            )],
+            async {
+                let output = self.command_runner.run(&request.args).await;
+                match &output {
+                    Ok(output) => telemetry::annotate_span(KeyValue::new(
+                        "process.exit.code",
+                        i64::from(output.status.code().unwrap_or(-1)),
+                    )),
+                    Err(err) => telemetry::record_span_error(err.to_string()),
+                }
+                output
+            },
+        )
+        .await
+        .map_err(|err| AckError::Internal(err.to_string()))?;
         let duration_ms = (chrono::Utc::now() - start).num_milliseconds() as f64;
 
         if let Some(metrics) = &self.metrics {
@@ -275,6 +291,15 @@ impl<R: CommandRunner> AckService<R> {
     }
 
     pub async fn append_event(&self, event: &EventRecord) -> anyhow::Result<()> {
+        telemetry::in_span(
+            "continuum.append",
+            vec![KeyValue::new("shelldone.event.kind", event.kind.clone())],
+            self.write_journal_line(event),
+        )
+        .await
+    }
+
+    async fn write_journal_line(&self, event: &EventRecord) -> anyhow::Result<()> {
         let dir = self
             .journal_path()
             .parent()
@@ -1,3 +1,5 @@
 pub mod service;
+pub mod watchdog;
 
 pub use service::AgentBindingService;
+pub use watchdog::{HeartbeatWatchdogConfig, WatchdogAction, WatchdogVerdict};
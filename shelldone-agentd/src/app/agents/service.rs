@@ -2,7 +2,7 @@ use crate::domain::agents::{
     AgentBinding, AgentBindingId, AgentEventEnvelope, AgentProvider, BindingStatus, CapabilityName,
     CapabilitySet, SdkChannel, SdkVersion,
 };
-use crate::ports::agents::AgentBindingRepository;
+use crate::ports::agents::{AgentBindingRepository, BindingVersionConflict};
 use std::sync::Arc;
 use thiserror::Error;
 
@@ -12,6 +12,8 @@ pub enum AgentServiceError {
     Invalid(String),
     #[error("not found: {0}")]
     NotFound(String),
+    #[error("conflict: {0}")]
+    Conflict(String),
     #[error("internal error: {0}")]
     Internal(String),
 }
@@ -38,10 +40,7 @@ impl<R: AgentBindingRepository> AgentBindingService<R> {
             .map_err(|e| AgentServiceError::Invalid(e.to_string()))?;
         let (binding, event) = AgentBinding::register(provider, sdk_version, channel, capabilities)
             .map_err(AgentServiceError::Invalid)?;
-        self.repository
-            .save(binding.clone())
-            .await
-            .map_err(|e| AgentServiceError::Internal(e.to_string()))?;
+        self.persist(binding.clone()).await?;
         Ok((binding, event))
     }
 
@@ -86,6 +85,30 @@ impl<R: AgentBindingRepository> AgentBindingService<R> {
         Ok(event)
     }
 
+    pub async fn record_bridge_alive(&self, id: &AgentBindingId) -> AgentServiceResult<()> {
+        let mut binding = self
+            .load_binding(id)
+            .await
+            .ok_or_else(|| AgentServiceError::NotFound(id.to_string()))?;
+        binding.record_bridge_alive();
+        self.persist(binding).await
+    }
+
+    pub async fn mark_unhealthy(
+        &self,
+        id: &AgentBindingId,
+    ) -> AgentServiceResult<AgentEventEnvelope> {
+        let mut binding = self
+            .load_binding(id)
+            .await
+            .ok_or_else(|| AgentServiceError::NotFound(id.to_string()))?;
+        let event = binding
+            .mark_unhealthy()
+            .map_err(AgentServiceError::Invalid)?;
+        self.persist(binding).await?;
+        Ok(event)
+    }
+
     pub async fn set_capabilities(
         &self,
         id: &AgentBindingId,
@@ -111,6 +134,16 @@ impl<R: AgentBindingRepository> AgentBindingService<R> {
             .map_err(|e| AgentServiceError::Internal(e.to_string()))
     }
 
+    pub async fn get_binding(
+        &self,
+        id: &AgentBindingId,
+    ) -> AgentServiceResult<Option<AgentBinding>> {
+        self.repository
+            .get(id)
+            .await
+            .map_err(|e| AgentServiceError::Internal(e.to_string()))
+    }
+
     pub async fn list_active(&self) -> AgentServiceResult<Vec<AgentBinding>> {
         let bindings = self.list_bindings().await?;
         Ok(bindings
@@ -135,10 +168,12 @@ impl<R: AgentBindingRepository> AgentBindingService<R> {
     }
 
     async fn persist(&self, binding: AgentBinding) -> AgentServiceResult<()> {
-        self.repository
-            .save(binding)
-            .await
-            .map_err(|e| AgentServiceError::Internal(e.to_string()))
+        self.repository.save(binding).await.map_err(|e| {
+            match e.downcast_ref::<BindingVersionConflict>() {
+                Some(conflict) => AgentServiceError::Conflict(conflict.to_string()),
+                None => AgentServiceError::Internal(e.to_string()),
+            }
+        })
     }
 }
 
@@ -199,4 +234,25 @@ mod tests {
         service.deactivate_binding(&id).await.unwrap();
         assert!(service.record_heartbeat(&id).await.is_err());
     }
+
+    #[tokio::test]
+    async fn stale_write_surfaces_conflict() {
+        let repo = Arc::new(InMemoryAgentBindingRepository::new());
+        let service = AgentBindingService::new(repo.clone());
+        let (binding, _) = service
+            .register_binding(
+                AgentProvider::Claude,
+                SdkVersion::new("1.1.0").unwrap(),
+                SdkChannel::Stable,
+                vec![capability("agent.exec")],
+            )
+            .await
+            .unwrap();
+        let id = binding.id();
+        let mut stale = repo.get(&id).await.unwrap().unwrap();
+        service.activate_binding(&id).await.unwrap();
+        stale.activate().unwrap();
+        let err = service.persist(stale).await.unwrap_err();
+        assert!(matches!(err, AgentServiceError::Conflict(_)));
+    }
 }
@@ -0,0 +1,231 @@
+use super::service::{AgentBindingService, AgentServiceError, AgentServiceResult};
+use crate::domain::agents::{AgentBinding, AgentBindingId, AgentProvider, BindingStatus};
+use crate::ports::agents::AgentBindingRepository;
+use chrono::{DateTime, Utc};
+use std::env;
+use std::time::Duration;
+use tracing::{debug, warn};
+
+const INTERVAL_ENV: &str = "SHELLDONE_AGENT_WATCHDOG_INTERVAL_MS";
+const UNHEALTHY_ENV: &str = "SHELLDONE_AGENT_HEARTBEAT_BUDGET_MS";
+const DEACTIVATE_ENV: &str = "SHELLDONE_AGENT_HEARTBEAT_DEACTIVATE_MS";
+
+/// Heartbeat SLA enforced by the binding watchdog.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct HeartbeatWatchdogConfig {
+    /// How often bindings are swept.
+    pub interval: Duration,
+    /// Heartbeat age after which an active binding is marked `Unhealthy`.
+    pub unhealthy_after: Duration,
+    /// Heartbeat age after which the binding is deactivated. `None` keeps
+    /// stale bindings unhealthy until they heartbeat again.
+    pub deactivate_after: Option<Duration>,
+}
+
+impl Default for HeartbeatWatchdogConfig {
+    fn default() -> Self {
+        Self {
+            interval: Duration::from_secs(5),
+            unhealthy_after: Duration::from_secs(30),
+            deactivate_after: None,
+        }
+    }
+}
+
+impl HeartbeatWatchdogConfig {
+    pub fn from_env() -> Self {
+        let defaults = Self::default();
+        let millis = |key: &str| {
+            env::var(key)
+                .ok()
+                .and_then(|raw| raw.trim().parse::<u64>().ok())
+                .map(Duration::from_millis)
+        };
+        Self {
+            interval: millis(INTERVAL_ENV)
+                .filter(|value| !value.is_zero())
+                .unwrap_or(defaults.interval),
+            unhealthy_after: millis(UNHEALTHY_ENV)
+                .filter(|value| !value.is_zero())
+                .unwrap_or(defaults.unhealthy_after),
+            // Explicit 0 disables deactivation.
+            deactivate_after: match millis(DEACTIVATE_ENV) {
+                Some(value) if value.is_zero() => None,
+                Some(value) => Some(value),
+                None => defaults.deactivate_after,
+            },
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum WatchdogAction {
+    MarkedUnhealthy,
+    Deactivated,
+}
+
+impl WatchdogAction {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            WatchdogAction::MarkedUnhealthy => "unhealthy",
+            WatchdogAction::Deactivated => "deactivated",
+        }
+    }
+}
+
+/// Outcome of the watchdog acting on a single binding.
+#[derive(Clone, Debug)]
+pub struct WatchdogVerdict {
+    pub binding_id: AgentBindingId,
+    pub provider: AgentProvider,
+    pub action: WatchdogAction,
+    pub heartbeat_age: Duration,
+    pub budget: Duration,
+}
+
+/// Apply the heartbeat budget to every live binding as of `now`.
+///
+/// Bindings that heartbeat concurrently lose the optimistic version race and
+/// are skipped until the next sweep.
+pub async fn sweep<R: AgentBindingRepository>(
+    service: &AgentBindingService<R>,
+    config: &HeartbeatWatchdogConfig,
+    now: DateTime<Utc>,
+) -> AgentServiceResult<Vec<WatchdogVerdict>> {
+    let mut verdicts = Vec::new();
+    for binding in service.list_bindings().await? {
+        let Some((action, budget)) = judge(&binding, config, now) else {
+            continue;
+        };
+        let id = binding.id();
+        let outcome = match action {
+            WatchdogAction::MarkedUnhealthy => service.mark_unhealthy(&id).await,
+            WatchdogAction::Deactivated => service.deactivate_binding(&id).await,
+        };
+        match outcome {
+            Ok(_) => verdicts.push(WatchdogVerdict {
+                binding_id: id,
+                provider: binding.provider().clone(),
+                action,
+                heartbeat_age: heartbeat_age(&binding, now),
+                budget,
+            }),
+            Err(AgentServiceError::Conflict(reason)) | Err(AgentServiceError::Invalid(reason)) => {
+                debug!(binding = %id, "watchdog skipped binding: {reason}");
+            }
+            Err(AgentServiceError::NotFound(_)) => {}
+            Err(err) => warn!(binding = %id, "watchdog failed to update binding: {err}"),
+        }
+    }
+    Ok(verdicts)
+}
+
+fn judge(
+    binding: &AgentBinding,
+    config: &HeartbeatWatchdogConfig,
+    now: DateTime<Utc>,
+) -> Option<(WatchdogAction, Duration)> {
+    let live = matches!(
+        binding.status(),
+        BindingStatus::Active | BindingStatus::Unhealthy
+    );
+    if !live {
+        return None;
+    }
+    let age = heartbeat_age(binding, now);
+    if let Some(limit) = config.deactivate_after {
+        if age > limit {
+            return Some((WatchdogAction::Deactivated, limit));
+        }
+    }
+    if matches!(binding.status(), BindingStatus::Active) && age > config.unhealthy_after {
+        return Some((WatchdogAction::MarkedUnhealthy, config.unhealthy_after));
+    }
+    None
+}
+
+fn heartbeat_age(binding: &AgentBinding, now: DateTime<Utc>) -> Duration {
+    binding
+        .heartbeat_age(now)
+        .unwrap_or_else(|| now - binding.registered_at())
+        .to_std()
+        .unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::adapters::agents::InMemoryAgentBindingRepository;
+    use crate::domain::agents::{CapabilityName, SdkChannel, SdkVersion};
+    use std::sync::Arc;
+
+    async fn active_binding(
+        service: &AgentBindingService<InMemoryAgentBindingRepository>,
+    ) -> AgentBindingId {
+        let (binding, _) = service
+            .register_binding(
+                AgentProvider::OpenAi,
+                SdkVersion::new("1.2.0").unwrap(),
+                SdkChannel::Stable,
+                vec![CapabilityName::new("agent.exec").unwrap()],
+            )
+            .await
+            .unwrap();
+        let id = binding.id();
+        service.activate_binding(&id).await.unwrap();
+        id
+    }
+
+    fn config(deactivate_after: Option<Duration>) -> HeartbeatWatchdogConfig {
+        HeartbeatWatchdogConfig {
+            interval: Duration::from_millis(10),
+            unhealthy_after: Duration::from_secs(30),
+            deactivate_after,
+        }
+    }
+
+    #[tokio::test]
+    async fn fresh_bindings_are_left_alone() {
+        let service = AgentBindingService::new(Arc::new(InMemoryAgentBindingRepository::new()));
+        active_binding(&service).await;
+        let verdicts = sweep(&service, &config(None), Utc::now()).await.unwrap();
+        assert!(verdicts.is_empty());
+    }
+
+    #[tokio::test]
+    async fn stale_binding_marked_unhealthy_then_recovers() {
+        let service = AgentBindingService::new(Arc::new(InMemoryAgentBindingRepository::new()));
+        let id = active_binding(&service).await;
+        let later = Utc::now() + chrono::Duration::seconds(45);
+
+        let verdicts = sweep(&service, &config(None), later).await.unwrap();
+        assert_eq!(verdicts.len(), 1);
+        assert_eq!(verdicts[0].action, WatchdogAction::MarkedUnhealthy);
+        assert!(verdicts[0].heartbeat_age > Duration::from_secs(30));
+        assert!(service.list_active().await.unwrap().is_empty());
+
+        // Already unhealthy: no repeated verdicts without a deactivate budget.
+        assert!(sweep(&service, &config(None), later)
+            .await
+            .unwrap()
+            .is_empty());
+
+        service.record_heartbeat(&id).await.unwrap();
+        assert_eq!(service.list_active().await.unwrap().len(), 1);
+    }
+
+    #[tokio::test]
+    async fn binding_past_deactivate_budget_is_disabled() {
+        let service = AgentBindingService::new(Arc::new(InMemoryAgentBindingRepository::new()));
+        let id = active_binding(&service).await;
+        let later = Utc::now() + chrono::Duration::minutes(10);
+
+        let verdicts = sweep(&service, &config(Some(Duration::from_secs(300))), later)
+            .await
+            .unwrap();
+        assert_eq!(verdicts.len(), 1);
+        assert_eq!(verdicts[0].action, WatchdogAction::Deactivated);
+        assert_eq!(verdicts[0].budget, Duration::from_secs(300));
+        assert!(service.record_heartbeat(&id).await.is_err());
+    }
+}
@@ -0,0 +1,40 @@
+use std::env;
+use std::time::Duration;
+
+const IDLE_TTL_ENV: &str = "SHELLDONE_MCP_SESSION_IDLE_TTL_MS";
+const SWEEP_INTERVAL_ENV: &str = "SHELLDONE_MCP_SESSION_SWEEP_MS";
+
+/// Idle budget for MCP sessions, including detached ones awaiting resume.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct SessionExpiryConfig {
+    /// How often sessions are swept.
+    pub interval: Duration,
+    /// Inactivity after which an open session is closed for good.
+    pub idle_ttl: Duration,
+}
+
+impl Default for SessionExpiryConfig {
+    fn default() -> Self {
+        Self {
+            interval: Duration::from_secs(60),
+            idle_ttl: Duration::from_secs(30 * 60),
+        }
+    }
+}
+
+impl SessionExpiryConfig {
+    pub fn from_env() -> Self {
+        let defaults = Self::default();
+        let millis = |key: &str| {
+            env::var(key)
+                .ok()
+                .and_then(|raw| raw.trim().parse::<u64>().ok())
+                .filter(|value| *value > 0)
+                .map(Duration::from_millis)
+        };
+        Self {
+            interval: millis(SWEEP_INTERVAL_ENV).unwrap_or(defaults.interval),
+            idle_ttl: millis(IDLE_TTL_ENV).unwrap_or(defaults.idle_ttl),
+        }
+    }
+}
@@ -1 +1,4 @@
+pub mod expiry;
 pub mod service;
+
+pub use expiry::SessionExpiryConfig;
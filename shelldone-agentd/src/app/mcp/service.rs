@@ -1,13 +1,18 @@
 use crate::app::ack::model::{ExecArgs, ExecRequest, ExecResult};
 use crate::app::ack::service::{AckError, AckPort};
+use crate::app::quota::{QuotaService, QuotaSubject, RateLimited};
 use crate::app::termbridge::TermBridgeDiscoveryHandle;
 use crate::domain::mcp::{
-    CapabilityName, McpEventEnvelope, McpSession, PersonaProfile, SessionId, ToolName,
+    BufferedToolResult, CapabilityName, McpEventEnvelope, McpSession, PersonaProfile, SessionId,
+    SessionStatus, ToolName,
 };
 use crate::ports::mcp::repo_port::McpSessionRepository;
+use chrono::{DateTime, Utc};
 use serde_json::{json, Value};
 use std::sync::Arc;
+use std::time::Duration;
 use thiserror::Error;
+use tracing::warn;
 
 pub struct McpBridgeService<A, R>
 where
@@ -17,6 +22,7 @@ where
     sessions: Arc<R>,
     ack: Arc<A>,
     discovery: Option<TermBridgeDiscoveryHandle>,
+    quota: Option<Arc<QuotaService>>,
 }
 
 #[derive(Debug, Error)]
@@ -29,6 +35,13 @@ pub enum McpBridgeError {
     ToolFailure(String),
     #[error("internal error: {0}")]
     Internal(String),
+    #[error("forbidden: {0}")]
+    Forbidden(String),
+    #[error("{message}")]
+    RateLimited {
+        message: String,
+        retry_after_ms: u64,
+    },
 }
 
 impl<A, R> McpBridgeService<A, R>
@@ -45,15 +58,23 @@ where
             sessions,
             ack,
             discovery,
+            quota: None,
         }
     }
 
+    /// Enforce per-session and per-persona quotas on tool calls.
+    pub fn with_quota(mut self, quota: Arc<QuotaService>) -> Self {
+        self.quota = Some(quota);
+        self
+    }
+
+    /// Performs the handshake and returns the session with its resume token.
     pub async fn initialize_session(
         &self,
         persona: Option<String>,
         protocol_version: String,
         capabilities: Vec<String>,
-    ) -> Result<McpSession, McpBridgeError> {
+    ) -> Result<(McpSession, String), McpBridgeError> {
         let persona_value = persona.unwrap_or_else(|| "core".to_string());
         let persona_profile: PersonaProfile =
             persona_value.parse().map_err(McpBridgeError::Protocol)?;
@@ -66,10 +87,94 @@ where
         let envelope = session
             .complete_handshake(protocol_version, capability_names)
             .map_err(McpBridgeError::Protocol)?;
+        let resume_token = session
+            .issue_resume_token()
+            .map_err(McpBridgeError::Protocol)?;
         self.sessions.insert(session.clone()).await;
         self.log_event(&session, envelope).await?;
         self.notify_discovery("mcp.session.established");
-        Ok(session)
+        Ok((session, resume_token))
+    }
+
+    /// Re-attaches a client to a detached (or pre-restart) session and returns
+    /// the tool results buffered while it was away.
+    pub async fn resume_session(
+        &self,
+        id: &SessionId,
+        token: &str,
+    ) -> Result<(McpSession, Vec<BufferedToolResult>), McpBridgeError> {
+        // Unknown ids and bad tokens look the same to the caller.
+        let denied = || McpBridgeError::Forbidden("session cannot be resumed".into());
+        let mut session = self.sessions.get(id).await.ok_or_else(denied)?;
+        let (envelope, replay) = session.resume(token).map_err(|_| denied())?;
+        self.sessions.update(session.clone()).await;
+        self.log_event(&session, envelope).await?;
+        Ok((session, replay))
+    }
+
+    /// Keeps the session open for resumption after its transport went away.
+    pub async fn detach_session(&self, id: &SessionId) -> Result<(), McpBridgeError> {
+        let Some(mut session) = self.sessions.get(id).await else {
+            return Ok(());
+        };
+        if !matches!(session.status(), SessionStatus::Active) {
+            return Ok(());
+        }
+        let envelope = session.detach().map_err(McpBridgeError::Protocol)?;
+        self.sessions.update(session.clone()).await;
+        self.log_event(&session, envelope).await
+    }
+
+    /// Stores a tool result the client never received so `resume_session` can
+    /// replay it.
+    pub async fn buffer_result(
+        &self,
+        id: &SessionId,
+        call_id: Option<Value>,
+        tool: &str,
+        result: Value,
+    ) -> Result<(), McpBridgeError> {
+        let Some(mut session) = self.sessions.get(id).await else {
+            return Err(McpBridgeError::Protocol("session not found".into()));
+        };
+        let dropped = session
+            .buffer_result(BufferedToolResult {
+                call_id,
+                tool: tool.to_string(),
+                result,
+                completed_at: Utc::now(),
+            })
+            .map_err(McpBridgeError::Protocol)?;
+        if dropped {
+            warn!(session = %id, "MCP result buffer full; dropped oldest result");
+        }
+        self.sessions.update(session).await;
+        Ok(())
+    }
+
+    /// Closes every open session idle for longer than `ttl`, journaling
+    /// `mcp.session.expired` for each.
+    pub async fn expire_idle_sessions(
+        &self,
+        now: DateTime<Utc>,
+        ttl: Duration,
+    ) -> Result<Vec<SessionId>, McpBridgeError> {
+        let ttl = chrono::Duration::from_std(ttl)
+            .map_err(|err| McpBridgeError::Internal(err.to_string()))?;
+        let mut expired = Vec::new();
+        for mut session in self.sessions.list().await {
+            if !session.is_idle(now, ttl) {
+                continue;
+            }
+            let envelope = session.expire(now).map_err(McpBridgeError::Protocol)?;
+            self.sessions.update(session.clone()).await;
+            self.log_event(&session, envelope).await?;
+            expired.push(session.id());
+        }
+        if !expired.is_empty() {
+            self.notify_discovery("mcp.session.expired");
+        }
+        Ok(expired)
     }
 
     pub async fn list_tools(&self) -> Value {
@@ -98,6 +203,7 @@ where
     }
 
     pub async fn record_heartbeat(&self, session: &mut McpSession) -> Result<(), McpBridgeError> {
+        self.ensure_open(session).await?;
         let envelope = session.heartbeat().map_err(McpBridgeError::Protocol)?;
         self.sessions.update(session.clone()).await;
         self.log_event(session, envelope).await
@@ -125,6 +231,15 @@ where
             return Err(McpBridgeError::UnsupportedTool(tool_name.to_string()));
         }
         let exec_args = parse_exec_args(arguments)?;
+        self.ensure_open(session).await?;
+        if let Some(quota) = &self.quota {
+            let subject = QuotaSubject {
+                provider: None,
+                session: Some(session.id().to_string()),
+                persona: Some(session.persona().name().to_string()),
+            };
+            quota.check(tool_name, &subject)?;
+        }
         let persona = Some(session.persona().name().to_string());
         let spectral_tag = Some(format!("mcp::{}", tool_name));
         let request = ExecRequest {
@@ -142,7 +257,6 @@ where
         Ok(exec_result)
     }
 
-    #[allow(dead_code)]
     pub async fn get_session(&self, id: &SessionId) -> Option<McpSession> {
         self.sessions.get(id).await
     }
@@ -151,6 +265,17 @@ where
         self.sessions.list().await
     }
 
+    /// Picks up expiry applied by the background sweep to the stored copy.
+    async fn ensure_open(&self, session: &mut McpSession) -> Result<(), McpBridgeError> {
+        if let Some(stored) = self.sessions.get(&session.id()).await {
+            if matches!(stored.status(), SessionStatus::Closed) {
+                *session = stored;
+                return Err(McpBridgeError::Protocol("session closed".into()));
+            }
+        }
+        Ok(())
+    }
+
     async fn log_event(
         &self,
         session: &McpSession,
@@ -195,6 +320,29 @@ where
                     "occurred_at": envelope.occurred_at.to_rfc3339(),
                 }),
             ),
+            crate::domain::mcp::McpDomainEvent::SessionDetached => (
+                "mcp.session.detached".to_string(),
+                json!({
+                    "session_id": envelope.session_id.to_string(),
+                    "occurred_at": envelope.occurred_at.to_rfc3339(),
+                }),
+            ),
+            crate::domain::mcp::McpDomainEvent::SessionResumed { replayed } => (
+                "mcp.session.resumed".to_string(),
+                json!({
+                    "session_id": envelope.session_id.to_string(),
+                    "replayed": replayed,
+                    "occurred_at": envelope.occurred_at.to_rfc3339(),
+                }),
+            ),
+            crate::domain::mcp::McpDomainEvent::SessionExpired { idle_ms } => (
+                "mcp.session.expired".to_string(),
+                json!({
+                    "session_id": envelope.session_id.to_string(),
+                    "idle_ms": idle_ms,
+                    "occurred_at": envelope.occurred_at.to_rfc3339(),
+                }),
+            ),
         };
         self.ack
             .journal_custom(
@@ -216,6 +364,26 @@ where
     }
 }
 
+/// JSON shape of an `agent.exec` result as returned to MCP clients.
+pub fn tool_result_payload(exec: &ExecResult) -> Value {
+    json!({
+        "content": [
+            {
+                "type": "text",
+                "text": exec.stdout,
+            }
+        ],
+        "isError": exec.exit_code != 0,
+        "metadata": {
+            "exitCode": exec.exit_code,
+            "stderr": exec.stderr,
+            "eventId": exec.event_id,
+            "spectralTag": exec.spectral_tag,
+            "durationMs": exec.duration_ms,
+        }
+    })
+}
+
 fn parse_exec_args(value: Value) -> Result<ExecArgs, McpBridgeError> {
     let cmd = value
         .get("cmd")
@@ -248,6 +416,15 @@ impl From<AckError> for McpBridgeError {
     }
 }
 
+impl From<RateLimited> for McpBridgeError {
+    fn from(value: RateLimited) -> Self {
+        McpBridgeError::RateLimited {
+            message: value.to_string(),
+            retry_after_ms: value.retry_after_ms(),
+        }
+    }
+}
+
 impl From<String> for McpBridgeError {
     fn from(value: String) -> Self {
         McpBridgeError::Protocol(value)
@@ -270,6 +447,15 @@ mod tests {
     fn build_bridge() -> (
         Arc<McpBridgeService<AckService<ShellCommandRunner>, InMemoryMcpSessionRepository>>,
         tempfile::TempDir,
+    ) {
+        build_bridge_with_quota(None)
+    }
+
+    fn build_bridge_with_quota(
+        quota: Option<Arc<QuotaService>>,
+    ) -> (
+        Arc<McpBridgeService<AckService<ShellCommandRunner>, InMemoryMcpSessionRepository>>,
+        tempfile::TempDir,
     ) {
         let tmp = tempdir().unwrap();
         let journal_path = tmp.path().join("journal.jsonl");
@@ -289,14 +475,17 @@ mod tests {
             approvals,
         ));
         let repo = Arc::new(InMemoryMcpSessionRepository::new());
-        let bridge = Arc::new(McpBridgeService::new(repo, ack, None));
-        (bridge, tmp)
+        let mut bridge = McpBridgeService::new(repo, ack, None);
+        if let Some(quota) = quota {
+            bridge = bridge.with_quota(quota);
+        }
+        (Arc::new(bridge), tmp)
     }
 
     #[tokio::test]
     async fn initialize_creates_active_session() {
         let (bridge, tmp) = build_bridge();
-        let session = bridge
+        let (session, _) = bridge
             .initialize_session(Some("nova".into()), "1.0".into(), vec!["fs".into()])
             .await
             .expect("handshake");
@@ -321,7 +510,7 @@ mod tests {
     #[tokio::test]
     async fn call_tool_executes_command() {
         let (bridge, tmp) = build_bridge();
-        let mut session = bridge
+        let (mut session, _) = bridge
             .initialize_session(None, "1.0".into(), vec![])
             .await
             .unwrap();
@@ -351,7 +540,7 @@ mod tests {
     #[tokio::test]
     async fn heartbeat_updates_session() {
         let (bridge, _) = build_bridge();
-        let mut session = bridge
+        let (mut session, _) = bridge
             .initialize_session(None, "1.0".into(), vec![])
             .await
             .unwrap();
@@ -361,6 +550,116 @@ mod tests {
             .expect("heartbeat");
     }
 
+    #[tokio::test]
+    async fn call_tool_enforces_session_quota() {
+        use crate::app::quota::{QuotaLimit, QuotaPolicy};
+
+        let mut policy = QuotaPolicy::default();
+        policy
+            .session
+            .insert("agent.exec".to_string(), QuotaLimit::new(1, 1));
+        let (bridge, _tmp) =
+            build_bridge_with_quota(Some(Arc::new(QuotaService::new(policy, None))));
+        let (mut session, _) = bridge
+            .initialize_session(None, "1.0".into(), vec![])
+            .await
+            .unwrap();
+        bridge
+            .call_tool(&mut session, "agent.exec", json!({"cmd": "true"}))
+            .await
+            .expect("first call admitted");
+        let err = bridge
+            .call_tool(&mut session, "agent.exec", json!({"cmd": "true"}))
+            .await
+            .unwrap_err();
+        match err {
+            McpBridgeError::RateLimited { retry_after_ms, .. } => assert!(retry_after_ms > 0),
+            other => panic!("expected rate limit, got {other:?}"),
+        }
+    }
+
+    #[tokio::test]
+    async fn detached_session_resumes_with_buffered_results() {
+        let (bridge, _tmp) = build_bridge();
+        let (session, token) = bridge
+            .initialize_session(None, "1.0".into(), vec![])
+            .await
+            .unwrap();
+        let id = session.id();
+        bridge
+            .buffer_result(&id, Some(json!(3)), "agent.exec", json!({"isError": false}))
+            .await
+            .unwrap();
+        bridge.detach_session(&id).await.unwrap();
+        assert_eq!(
+            bridge.get_session(&id).await.unwrap().status(),
+            &SessionStatus::Detached
+        );
+
+        let err = bridge.resume_session(&id, "bogus").await.unwrap_err();
+        assert!(matches!(err, McpBridgeError::Forbidden(_)));
+        let err = bridge
+            .resume_session(&SessionId::new(), &token)
+            .await
+            .unwrap_err();
+        assert!(matches!(err, McpBridgeError::Forbidden(_)));
+
+        let (resumed, replay) = bridge.resume_session(&id, &token).await.unwrap();
+        assert_eq!(resumed.id(), id);
+        assert_eq!(resumed.status(), &SessionStatus::Active);
+        assert_eq!(replay.len(), 1);
+        assert_eq!(replay[0].call_id, Some(json!(3)));
+        // Replay is delivered once.
+        let (_, replay) = bridge.resume_session(&id, &token).await.unwrap();
+        assert!(replay.is_empty());
+    }
+
+    #[tokio::test]
+    async fn idle_sessions_expire_and_reject_calls() {
+        let (bridge, tmp) = build_bridge();
+        let (mut session, token) = bridge
+            .initialize_session(None, "1.0".into(), vec![])
+            .await
+            .unwrap();
+        let id = session.id();
+        let later = chrono::Utc::now() + chrono::Duration::minutes(5);
+
+        let expired = bridge
+            .expire_idle_sessions(later, Duration::from_secs(600))
+            .await
+            .unwrap();
+        assert!(expired.is_empty());
+        let expired = bridge
+            .expire_idle_sessions(later, Duration::from_secs(60))
+            .await
+            .unwrap();
+        assert_eq!(expired, vec![id.clone()]);
+
+        assert!(bridge.resume_session(&id, &token).await.is_err());
+        let err = bridge
+            .call_tool(&mut session, "agent.exec", json!({"cmd": "true"}))
+            .await
+            .unwrap_err();
+        assert!(matches!(err, McpBridgeError::Protocol(_)));
+        assert_eq!(session.status(), &SessionStatus::Closed);
+
+        let journal_path = tmp.path().join("journal.jsonl");
+        let mut attempts = 0;
+        loop {
+            let journal = tokio::fs::read_to_string(&journal_path).await.unwrap();
+            if journal.contains("mcp.session.expired") {
+                break;
+            }
+            attempts += 1;
+            assert!(
+                attempts < 10,
+                "journal missing expiry event after retries: {}",
+                journal
+            );
+            tokio::time::sleep(Duration::from_millis(50)).await;
+        }
+    }
+
     #[test]
     fn parse_exec_args_validates_input() {
         let args = json!({"cmd": "ls", "shell": "/bin/bash"});
@@ -1,4 +1,5 @@
 pub mod ack;
 pub mod agents;
 pub mod mcp;
+pub mod quota;
 pub mod termbridge;
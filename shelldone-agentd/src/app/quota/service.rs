@@ -0,0 +1,379 @@
+use crate::telemetry::PrismMetrics;
+use governor::clock::{Clock, DefaultClock};
+use governor::state::keyed::DefaultKeyedStateStore;
+use governor::{Quota, RateLimiter};
+use serde::Deserialize;
+use std::collections::HashMap;
+use std::fmt;
+use std::num::NonZeroU32;
+use std::sync::{Arc, RwLock};
+use std::time::Duration;
+use tracing::{info, warn};
+
+type KeyedLimiter = RateLimiter<String, DefaultKeyedStateStore<String>, DefaultClock>;
+
+/// Rule key matching every action within a scope.
+const WILDCARD_ACTION: &str = "*";
+
+/// Dimension a quota is accounted against.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum QuotaScope {
+    Provider,
+    Session,
+    Persona,
+}
+
+impl QuotaScope {
+    pub const ALL: [QuotaScope; 3] = [
+        QuotaScope::Provider,
+        QuotaScope::Session,
+        QuotaScope::Persona,
+    ];
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            QuotaScope::Provider => "provider",
+            QuotaScope::Session => "session",
+            QuotaScope::Persona => "persona",
+        }
+    }
+}
+
+impl fmt::Display for QuotaScope {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.as_str())
+    }
+}
+
+/// Token bucket parameters: `per_second` refill rate and `burst` capacity.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Deserialize)]
+pub struct QuotaLimit {
+    pub per_second: u32,
+    #[serde(default)]
+    pub burst: Option<u32>,
+}
+
+impl QuotaLimit {
+    pub const fn new(per_second: u32, burst: u32) -> Self {
+        Self {
+            per_second,
+            burst: Some(burst),
+        }
+    }
+
+    fn to_quota(self) -> Option<Quota> {
+        let rate = NonZeroU32::new(self.per_second)?;
+        let burst = self
+            .burst
+            .and_then(NonZeroU32::new)
+            .unwrap_or(rate)
+            .max(rate);
+        Some(Quota::per_second(rate).allow_burst(burst))
+    }
+}
+
+/// Quotas keyed by scope and action, as published in
+/// `data.shelldone.policy.rate_limits`.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Deserialize)]
+#[serde(default)]
+pub struct QuotaPolicy {
+    pub provider: HashMap<String, QuotaLimit>,
+    pub session: HashMap<String, QuotaLimit>,
+    pub persona: HashMap<String, QuotaLimit>,
+}
+
+impl QuotaPolicy {
+    /// Limits applied when the policy bundle does not define `rate_limits`.
+    pub fn builtin() -> Self {
+        let mut policy = Self::default();
+        policy
+            .provider
+            .insert("agent.exec".to_string(), QuotaLimit::new(20, 40));
+        policy
+            .session
+            .insert("agent.exec".to_string(), QuotaLimit::new(10, 20));
+        policy
+            .persona
+            .insert("agent.exec".to_string(), QuotaLimit::new(20, 40));
+        policy
+            .provider
+            .insert("termbridge.send_text".to_string(), QuotaLimit::new(50, 100));
+        policy
+            .persona
+            .insert("termbridge.send_text".to_string(), QuotaLimit::new(50, 100));
+        policy
+    }
+
+    pub fn from_policy_value(value: serde_json::Value) -> Result<Self, String> {
+        serde_json::from_value(value).map_err(|err| format!("invalid rate_limits: {err}"))
+    }
+
+    fn rules(&self, scope: QuotaScope) -> &HashMap<String, QuotaLimit> {
+        match scope {
+            QuotaScope::Provider => &self.provider,
+            QuotaScope::Session => &self.session,
+            QuotaScope::Persona => &self.persona,
+        }
+    }
+}
+
+/// Identity of the caller a request is accounted against. Absent keys skip
+/// the corresponding scope.
+#[derive(Clone, Debug, Default)]
+pub struct QuotaSubject {
+    pub provider: Option<String>,
+    pub session: Option<String>,
+    pub persona: Option<String>,
+}
+
+impl QuotaSubject {
+    fn key(&self, scope: QuotaScope) -> Option<&str> {
+        match scope {
+            QuotaScope::Provider => self.provider.as_deref(),
+            QuotaScope::Session => self.session.as_deref(),
+            QuotaScope::Persona => self.persona.as_deref(),
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct RateLimited {
+    pub action: String,
+    pub scope: QuotaScope,
+    pub key: String,
+    pub retry_after: Duration,
+}
+
+impl RateLimited {
+    /// Retry hint rounded up so clients never retry early.
+    pub fn retry_after_ms(&self) -> u64 {
+        let millis = self.retry_after.as_millis() as u64;
+        if Duration::from_millis(millis) < self.retry_after {
+            millis + 1
+        } else {
+            millis.max(1)
+        }
+    }
+}
+
+impl fmt::Display for RateLimited {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "rate limit exceeded for {} ({}={}); retry after {} ms",
+            self.action,
+            self.scope,
+            self.key,
+            self.retry_after_ms()
+        )
+    }
+}
+
+/// Token-bucket quotas for agent-issued actions, keyed by provider, MCP
+/// session and persona.
+pub struct QuotaService {
+    limiters: RwLock<HashMap<(QuotaScope, String), KeyedLimiter>>,
+    clock: DefaultClock,
+    metrics: Option<Arc<PrismMetrics>>,
+}
+
+impl QuotaService {
+    pub fn new(policy: QuotaPolicy, metrics: Option<Arc<PrismMetrics>>) -> Self {
+        Self {
+            limiters: RwLock::new(build_limiters(&policy)),
+            clock: DefaultClock::default(),
+            metrics,
+        }
+    }
+
+    /// Replace the active quotas. Buckets restart full, mirroring how the
+    /// terminal's `ratelim` resets on config generation changes.
+    pub fn reload(&self, policy: QuotaPolicy) {
+        let limiters = build_limiters(&policy);
+        match self.limiters.write() {
+            Ok(mut guard) => {
+                *guard = limiters;
+                info!("Quota rules reloaded ({} limiters)", guard.len());
+            }
+            Err(err) => warn!("quota limiter lock poisoned: {err}"),
+        }
+    }
+
+    /// Admit one `action` for `subject`, checking every scope the subject
+    /// carries a key for.
+    pub fn check(&self, action: &str, subject: &QuotaSubject) -> Result<(), RateLimited> {
+        let guard = match self.limiters.read() {
+            Ok(guard) => guard,
+            Err(err) => {
+                warn!("quota limiter lock poisoned: {err}");
+                return Ok(());
+            }
+        };
+
+        for scope in QuotaScope::ALL {
+            let Some(key) = subject.key(scope) else {
+                continue;
+            };
+            let limiter = guard
+                .get(&(scope, action.to_string()))
+                .or_else(|| guard.get(&(scope, WILDCARD_ACTION.to_string())));
+            let Some(limiter) = limiter else {
+                continue;
+            };
+            if let Err(not_until) = limiter.check_key(&key.to_string()) {
+                let rejection = RateLimited {
+                    action: action.to_string(),
+                    scope,
+                    key: key.to_string(),
+                    retry_after: not_until.wait_time_from(self.clock.now()),
+                };
+                warn!(
+                    action,
+                    scope = scope.as_str(),
+                    key,
+                    retry_after_ms = rejection.retry_after_ms(),
+                    "rate limit exceeded"
+                );
+                if let Some(metrics) = &self.metrics {
+                    metrics.record_rate_limited(
+                        action,
+                        scope.as_str(),
+                        rejection.retry_after_ms() as f64,
+                    );
+                }
+                return Err(rejection);
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Drop idle buckets that have fully refilled.
+    pub fn prune(&self) {
+        if let Ok(guard) = self.limiters.read() {
+            for limiter in guard.values() {
+                limiter.retain_recent();
+            }
+        }
+    }
+}
+
+fn build_limiters(policy: &QuotaPolicy) -> HashMap<(QuotaScope, String), KeyedLimiter> {
+    let mut limiters = HashMap::new();
+    for scope in QuotaScope::ALL {
+        for (action, limit) in policy.rules(scope) {
+            match limit.to_quota() {
+                Some(quota) => {
+                    limiters.insert((scope, action.clone()), RateLimiter::keyed(quota));
+                }
+                None => warn!(
+                    scope = scope.as_str(),
+                    action = action.as_str(),
+                    "ignoring rate limit with per_second=0"
+                ),
+            }
+        }
+    }
+    limiters
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn policy(scope: QuotaScope, action: &str, limit: QuotaLimit) -> QuotaPolicy {
+        let mut policy = QuotaPolicy::default();
+        match scope {
+            QuotaScope::Provider => policy.provider.insert(action.to_string(), limit),
+            QuotaScope::Session => policy.session.insert(action.to_string(), limit),
+            QuotaScope::Persona => policy.persona.insert(action.to_string(), limit),
+        };
+        policy
+    }
+
+    fn persona(name: &str) -> QuotaSubject {
+        QuotaSubject {
+            persona: Some(name.to_string()),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn burst_exhaustion_returns_retry_after() {
+        let service = QuotaService::new(
+            policy(QuotaScope::Persona, "agent.exec", QuotaLimit::new(1, 2)),
+            None,
+        );
+        let subject = persona("core");
+        assert!(service.check("agent.exec", &subject).is_ok());
+        assert!(service.check("agent.exec", &subject).is_ok());
+        let rejection = service.check("agent.exec", &subject).unwrap_err();
+        assert_eq!(rejection.scope, QuotaScope::Persona);
+        assert_eq!(rejection.key, "core");
+        assert!(rejection.retry_after_ms() > 0);
+        assert!(rejection.retry_after <= Duration::from_secs(1));
+    }
+
+    #[test]
+    fn keys_are_accounted_independently() {
+        let service = QuotaService::new(
+            policy(QuotaScope::Session, "agent.exec", QuotaLimit::new(1, 1)),
+            None,
+        );
+        let first = QuotaSubject {
+            session: Some("a".into()),
+            ..Default::default()
+        };
+        let second = QuotaSubject {
+            session: Some("b".into()),
+            ..Default::default()
+        };
+        assert!(service.check("agent.exec", &first).is_ok());
+        assert!(service.check("agent.exec", &first).is_err());
+        assert!(service.check("agent.exec", &second).is_ok());
+        // Subjects without a session key are not limited by session rules.
+        assert!(service.check("agent.exec", &persona("core")).is_ok());
+    }
+
+    #[test]
+    fn wildcard_rule_covers_unlisted_actions() {
+        let service = QuotaService::new(
+            policy(QuotaScope::Provider, "*", QuotaLimit::new(1, 1)),
+            None,
+        );
+        let subject = QuotaSubject {
+            provider: Some("openai".into()),
+            ..Default::default()
+        };
+        assert!(service.check("termbridge.send_text", &subject).is_ok());
+        let rejection = service.check("termbridge.send_text", &subject).unwrap_err();
+        assert_eq!(rejection.scope, QuotaScope::Provider);
+    }
+
+    #[test]
+    fn reload_replaces_rules() {
+        let service = QuotaService::new(
+            policy(QuotaScope::Persona, "agent.exec", QuotaLimit::new(1, 1)),
+            None,
+        );
+        let subject = persona("nova");
+        assert!(service.check("agent.exec", &subject).is_ok());
+        assert!(service.check("agent.exec", &subject).is_err());
+        service.reload(QuotaPolicy::default());
+        assert!(service.check("agent.exec", &subject).is_ok());
+    }
+
+    #[test]
+    fn policy_value_parses_scopes() {
+        let parsed = QuotaPolicy::from_policy_value(json!({
+            "persona": {"agent.exec": {"per_second": 5, "burst": 10}},
+            "session": {"*": {"per_second": 2}},
+        }))
+        .unwrap();
+        assert_eq!(parsed.persona["agent.exec"], QuotaLimit::new(5, 10));
+        assert_eq!(parsed.session["*"].burst, None);
+        assert!(parsed.provider.is_empty());
+        assert!(QuotaPolicy::from_policy_value(json!({"persona": {"x": {}}})).is_err());
+    }
+}
@@ -1,11 +1,13 @@
 use crate::domain::termbridge::{
     CapabilityRecord, CurrentWorkingDirectory, TermBridgeState, TerminalBinding, TerminalBindingId,
+    TerminalId,
 };
 use crate::ports::termbridge::{
     SpawnRequest, TermBridgeCommandRequest, TermBridgeError, TermBridgeStateRepository,
     TerminalBindingRepository, TerminalControlPort,
 };
-use crate::telemetry::PrismMetrics;
+use crate::telemetry::{self, PrismMetrics};
+use opentelemetry::KeyValue;
 use std::sync::Arc;
 use std::time::Instant;
 use thiserror::Error;
@@ -84,7 +86,12 @@ where
         let mut observations = Vec::with_capacity(self.adapters.len());
         for adapter in &self.adapters {
             let terminal = adapter.terminal_id();
-            let observation = adapter.detect().await;
+            let observation = telemetry::in_span(
+                "termbridge.adapter",
+                adapter_attributes("detect", &terminal),
+                adapter.detect(),
+            )
+            .await;
             observations.push((terminal, observation));
         }
 
@@ -177,7 +184,12 @@ where
                 ))
             })?;
 
-        let spawn_result = adapter.spawn(&request).await;
+        let spawn_result = telemetry::in_span(
+            "termbridge.adapter",
+            adapter_attributes("spawn", &request.terminal),
+            adapter.spawn(&request),
+        )
+        .await;
         let binding = match spawn_result {
             Ok(binding) => binding,
             Err(TermBridgeError::NotSupported {
@@ -263,7 +275,13 @@ where
                 ))
             })?;
 
-        match adapter.send_text(&binding, &payload, bracketed).await {
+        let outcome = telemetry::in_span(
+            "termbridge.adapter",
+            adapter_attributes("send_text", &binding.terminal),
+            adapter.send_text(&binding, &payload, bracketed),
+        )
+        .await;
+        match outcome {
             Ok(()) => {
                 if let Some(metrics) = &self.metrics {
                     let latency_ms = started.elapsed().as_secs_f64() * 1000.0;
@@ -389,7 +407,13 @@ where
                 ))
             })?;
 
-        match adapter.focus(&binding).await {
+        let outcome = telemetry::in_span(
+            "termbridge.adapter",
+            adapter_attributes("focus", &binding.terminal),
+            adapter.focus(&binding),
+        )
+        .await;
+        match outcome {
             Ok(()) => {
                 if let Some(metrics) = &self.metrics {
                     let latency_ms = started.elapsed().as_secs_f64() * 1000.0;
@@ -447,6 +471,13 @@ where
     }
 }
 
+fn adapter_attributes(action: &'static str, terminal: &TerminalId) -> Vec<KeyValue> {
+    vec![
+        KeyValue::new("termbridge.action", action),
+        KeyValue::new("termbridge.terminal", terminal.as_str().to_string()),
+    ]
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -25,6 +25,11 @@ pub struct ContinuumEvent {
     /// Parent event hash for chain validation
     #[serde(skip_serializing_if = "Option::is_none")]
     pub parent_hash: Option<String>,
+    /// Trace of the request that produced the event
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub trace_id: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub span_id: Option<String>,
 }
 
 impl ContinuumEvent {
@@ -54,6 +59,10 @@ impl ContinuumEvent {
             hasher.update(parent.as_bytes());
         }
 
+        if let Some(trace_id) = &self.trace_id {
+            hasher.update(trace_id.as_bytes());
+        }
+
         let result = hasher.finalize();
         self.merkle_hash = Some(hex::encode(result));
     }
@@ -367,11 +376,27 @@ mod tests {
             bytes: Some(10),
             merkle_hash: None,
             parent_hash,
+            trace_id: None,
+            span_id: None,
         };
         event.compute_hash();
         event
     }
 
+    #[test]
+    fn trace_id_is_optional_and_hashed() {
+        let mut event = create_test_event("traced", None);
+        let line = serde_json::to_string(&event).unwrap();
+        assert!(!line.contains("trace_id"));
+        let restored: ContinuumEvent = serde_json::from_str(&line).unwrap();
+        assert!(restored.verify_hash());
+
+        event.trace_id = Some("4bf92f3577b34da6a3ce929d0e0e4736".to_string());
+        assert!(!event.verify_hash());
+        event.compute_hash();
+        assert!(event.verify_hash());
+    }
+
     #[test]
     fn event_hash_computation() {
         let mut event = create_test_event("test1", None);
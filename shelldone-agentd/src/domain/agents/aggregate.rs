@@ -1,13 +1,16 @@
 use super::events::{AgentDomainEvent, AgentEventEnvelope};
 use super::value_object::{AgentBindingId, AgentProvider, CapabilityName, SdkChannel, SdkVersion};
 use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
 use std::collections::HashSet;
 use std::fmt;
 
-#[derive(Clone, Debug, PartialEq, Eq)]
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
 pub enum BindingStatus {
     Registered,
     Active,
+    Unhealthy,
     Disabled,
 }
 
@@ -16,6 +19,7 @@ impl BindingStatus {
         match self {
             BindingStatus::Registered => "registered",
             BindingStatus::Active => "active",
+            BindingStatus::Unhealthy => "unhealthy",
             BindingStatus::Disabled => "disabled",
         }
     }
@@ -68,6 +72,10 @@ pub struct AgentBinding {
     status: BindingStatus,
     registered_at: DateTime<Utc>,
     last_heartbeat_at: Option<DateTime<Utc>>,
+    /// Last time the supervised bridge process was seen running. Kept apart
+    /// from `last_heartbeat_at`: a live process is not a live agent.
+    bridge_alive_at: Option<DateTime<Utc>>,
+    version: u64,
 }
 
 impl AgentBinding {
@@ -88,6 +96,8 @@ impl AgentBinding {
             status: BindingStatus::Registered,
             registered_at: now,
             last_heartbeat_at: None,
+            bridge_alive_at: None,
+            version: 0,
         };
         let event = AgentEventEnvelope::new(
             id,
@@ -104,6 +114,7 @@ impl AgentBinding {
     pub fn activate(&mut self) -> Result<AgentEventEnvelope, String> {
         match self.status {
             BindingStatus::Active => Err("binding already active".into()),
+            BindingStatus::Unhealthy => Err("unhealthy binding recovers via heartbeat".into()),
             BindingStatus::Disabled => Err("disabled binding must be re-registered".into()),
             BindingStatus::Registered => {
                 self.status = BindingStatus::Active;
@@ -122,7 +133,7 @@ impl AgentBinding {
         match self.status {
             BindingStatus::Registered => Err("binding not active".into()),
             BindingStatus::Disabled => Err("binding already disabled".into()),
-            BindingStatus::Active => {
+            BindingStatus::Active | BindingStatus::Unhealthy => {
                 self.status = BindingStatus::Disabled;
                 Ok(AgentEventEnvelope::new(
                     self.id.clone(),
@@ -150,11 +161,16 @@ impl AgentBinding {
         ))
     }
 
+    /// Record a keepalive. An unhealthy binding returns to `Active`.
     pub fn record_heartbeat(&mut self) -> Result<AgentEventEnvelope, String> {
-        if !matches!(self.status, BindingStatus::Active) {
+        if !matches!(
+            self.status,
+            BindingStatus::Active | BindingStatus::Unhealthy
+        ) {
             return Err("heartbeat only allowed in active state".into());
         }
         let now = Utc::now();
+        self.status = BindingStatus::Active;
         self.last_heartbeat_at = Some(now);
         Ok(AgentEventEnvelope::new(
             self.id.clone(),
@@ -162,6 +178,84 @@ impl AgentBinding {
         ))
     }
 
+    pub fn mark_unhealthy(&mut self) -> Result<AgentEventEnvelope, String> {
+        match self.status {
+            BindingStatus::Active => {
+                self.status = BindingStatus::Unhealthy;
+                Ok(AgentEventEnvelope::new(
+                    self.id.clone(),
+                    AgentDomainEvent::StatusChanged {
+                        status: BindingStatus::Unhealthy,
+                    },
+                ))
+            }
+            BindingStatus::Unhealthy => Err("binding already unhealthy".into()),
+            _ => Err("binding not active".into()),
+        }
+    }
+
+    /// Record that the supervised bridge process is running. Neither the
+    /// heartbeat nor the status changes.
+    pub fn record_bridge_alive(&mut self) {
+        self.bridge_alive_at = Some(Utc::now());
+    }
+
+    /// Time elapsed since the last heartbeat, or `None` if none was observed.
+    pub fn heartbeat_age(&self, now: DateTime<Utc>) -> Option<chrono::Duration> {
+        self.last_heartbeat_at.map(|at| now - at)
+    }
+
+    /// Optimistic concurrency version; bumped by the repository on each save.
+    pub fn version(&self) -> u64 {
+        self.version
+    }
+
+    pub(crate) fn set_version(&mut self, version: u64) {
+        self.version = version;
+    }
+
+    pub fn to_snapshot(&self) -> AgentBindingSnapshot {
+        AgentBindingSnapshot {
+            id: self.id.clone(),
+            provider: self.provider.clone(),
+            sdk_version: self.sdk_version.clone(),
+            channel: self.channel.clone(),
+            capabilities: self.capabilities.to_vec(),
+            status: self.status.clone(),
+            registered_at: self.registered_at,
+            last_heartbeat_at: self.last_heartbeat_at,
+            bridge_alive_at: self.bridge_alive_at,
+            version: self.version,
+        }
+    }
+
+    pub fn from_snapshot(snapshot: AgentBindingSnapshot) -> Result<Self, String> {
+        let AgentBindingSnapshot {
+            id,
+            provider,
+            sdk_version,
+            channel,
+            capabilities,
+            status,
+            registered_at,
+            last_heartbeat_at,
+            bridge_alive_at,
+            version,
+        } = snapshot;
+        Ok(Self {
+            id,
+            provider,
+            sdk_version,
+            channel,
+            capabilities: CapabilitySet::new(capabilities)?,
+            status,
+            registered_at,
+            last_heartbeat_at,
+            bridge_alive_at,
+            version,
+        })
+    }
+
     pub fn id(&self) -> AgentBindingId {
         self.id.clone()
     }
@@ -190,11 +284,31 @@ impl AgentBinding {
         self.last_heartbeat_at
     }
 
+    pub fn bridge_alive_at(&self) -> Option<DateTime<Utc>> {
+        self.bridge_alive_at
+    }
+
     pub fn capabilities(&self) -> &CapabilitySet {
         &self.capabilities
     }
 }
 
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct AgentBindingSnapshot {
+    pub id: AgentBindingId,
+    pub provider: AgentProvider,
+    pub sdk_version: SdkVersion,
+    pub channel: SdkChannel,
+    pub capabilities: Vec<CapabilityName>,
+    pub status: BindingStatus,
+    pub registered_at: DateTime<Utc>,
+    pub last_heartbeat_at: Option<DateTime<Utc>>,
+    #[serde(default)]
+    pub bridge_alive_at: Option<DateTime<Utc>>,
+    #[serde(default)]
+    pub version: u64,
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -255,6 +369,72 @@ mod tests {
         assert!(binding.record_heartbeat().is_err());
     }
 
+    #[test]
+    fn unhealthy_binding_recovers_on_heartbeat() {
+        let (mut binding, _) = AgentBinding::register(
+            AgentProvider::Claude,
+            SdkVersion::new("1.1.0").unwrap(),
+            SdkChannel::Stable,
+            mk_capabilities(),
+        )
+        .unwrap();
+        assert!(binding.mark_unhealthy().is_err());
+        binding.activate().unwrap();
+        let event = binding.mark_unhealthy().unwrap();
+        assert!(matches!(
+            event.event,
+            AgentDomainEvent::StatusChanged {
+                status: BindingStatus::Unhealthy
+            }
+        ));
+        assert!(binding.activate().is_err());
+        binding.record_heartbeat().unwrap();
+        assert!(matches!(binding.status(), BindingStatus::Active));
+        binding.mark_unhealthy().unwrap();
+        binding.deactivate().unwrap();
+        assert!(matches!(binding.status(), BindingStatus::Disabled));
+    }
+
+    #[test]
+    fn bridge_liveness_is_not_a_heartbeat() {
+        let (mut binding, _) = AgentBinding::register(
+            AgentProvider::Claude,
+            SdkVersion::new("1.1.0").unwrap(),
+            SdkChannel::Stable,
+            mk_capabilities(),
+        )
+        .unwrap();
+        binding.activate().unwrap();
+        binding.mark_unhealthy().unwrap();
+        let heartbeat = binding.last_heartbeat_at();
+        binding.record_bridge_alive();
+        assert!(binding.bridge_alive_at().is_some());
+        assert_eq!(binding.last_heartbeat_at(), heartbeat);
+        assert!(matches!(binding.status(), BindingStatus::Unhealthy));
+    }
+
+    #[test]
+    fn snapshot_roundtrip_preserves_state() {
+        let (mut binding, _) = AgentBinding::register(
+            AgentProvider::OpenAi,
+            SdkVersion::new("1.2.0").unwrap(),
+            SdkChannel::Stable,
+            mk_capabilities(),
+        )
+        .unwrap();
+        binding.activate().unwrap();
+        binding.record_bridge_alive();
+        binding.set_version(3);
+        let json = serde_json::to_string(&binding.to_snapshot()).unwrap();
+        let snapshot: AgentBindingSnapshot = serde_json::from_str(&json).unwrap();
+        let restored = AgentBinding::from_snapshot(snapshot).unwrap();
+        assert_eq!(restored.id(), binding.id());
+        assert_eq!(restored.status(), &BindingStatus::Active);
+        assert_eq!(restored.last_heartbeat_at(), binding.last_heartbeat_at());
+        assert_eq!(restored.bridge_alive_at(), binding.bridge_alive_at());
+        assert_eq!(restored.version(), 3);
+    }
+
     #[test]
     fn update_capabilities_rejects_empty_and_duplicates() {
         let (mut binding, _) = AgentBinding::register(
@@ -2,6 +2,6 @@ pub mod aggregate;
 pub mod events;
 pub mod value_object;
 
-pub use aggregate::{AgentBinding, BindingStatus, CapabilitySet};
+pub use aggregate::{AgentBinding, AgentBindingSnapshot, BindingStatus, CapabilitySet};
 pub use events::AgentEventEnvelope;
 pub use value_object::{AgentBindingId, AgentProvider, CapabilityName, SdkChannel, SdkVersion};
@@ -1,16 +1,35 @@
 use super::events::{McpDomainEvent, McpEventEnvelope};
 use super::value_object::{CapabilityName, PersonaProfile, SessionId, ToolName};
-use chrono::{DateTime, Utc};
+use chrono::{DateTime, Duration, Utc};
 use serde::{Deserialize, Serialize};
-use std::collections::HashSet;
+use serde_json::Value;
+use sha2::{Digest, Sha256};
+use std::collections::{HashSet, VecDeque};
+use uuid::Uuid;
+
+/// Upper bound on tool results kept for a disconnected client; oldest are
+/// dropped first.
+pub const MAX_BUFFERED_RESULTS: usize = 32;
 
 #[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
 pub enum SessionStatus {
     Negotiating,
     Active,
+    /// Transport went away; the session can be resumed with its token.
+    Detached,
     Closed,
 }
 
+/// Tool result that completed while no client was attached to receive it.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct BufferedToolResult {
+    /// Client-supplied request id (JSON-RPC `id` or gRPC `call_id`).
+    pub call_id: Option<Value>,
+    pub tool: String,
+    pub result: Value,
+    pub completed_at: DateTime<Utc>,
+}
+
 /// Aggregate capturing the lifecycle of an MCP session.
 #[allow(dead_code)]
 #[derive(Clone, Debug)]
@@ -22,6 +41,8 @@ pub struct McpSession {
     status: SessionStatus,
     created_at: DateTime<Utc>,
     last_active_at: DateTime<Utc>,
+    resume_token_sha256: Option<String>,
+    buffered_results: VecDeque<BufferedToolResult>,
 }
 
 #[allow(dead_code)]
@@ -36,6 +57,8 @@ impl McpSession {
             status: SessionStatus::Negotiating,
             created_at: now,
             last_active_at: now,
+            resume_token_sha256: None,
+            buffered_results: VecDeque::new(),
         }
     }
 
@@ -66,6 +89,10 @@ impl McpSession {
             .collect()
     }
 
+    pub fn buffered_results(&self) -> usize {
+        self.buffered_results.len()
+    }
+
     pub fn to_snapshot(&self) -> McpSessionSnapshot {
         McpSessionSnapshot {
             id: self.id.clone(),
@@ -75,6 +102,8 @@ impl McpSession {
             status: self.status.clone(),
             created_at: self.created_at,
             last_active_at: self.last_active_at,
+            resume_token_sha256: self.resume_token_sha256.clone(),
+            buffered_results: self.buffered_results.iter().cloned().collect(),
         }
     }
 
@@ -87,6 +116,8 @@ impl McpSession {
             status,
             created_at,
             last_active_at,
+            resume_token_sha256,
+            buffered_results,
         } = snapshot;
 
         if matches!(status, SessionStatus::Negotiating) && protocol_version.is_some() {
@@ -101,6 +132,8 @@ impl McpSession {
             status,
             created_at,
             last_active_at,
+            resume_token_sha256,
+            buffered_results: buffered_results.into_iter().collect(),
         };
 
         if session.last_active_at < session.created_at {
@@ -169,11 +202,112 @@ impl McpSession {
         }
         self.status = SessionStatus::Closed;
         self.last_active_at = Utc::now();
+        self.resume_token_sha256 = None;
+        self.buffered_results.clear();
         Ok(McpEventEnvelope::new(
             self.id.clone(),
             McpDomainEvent::SessionClosed { reason },
         ))
     }
+
+    /// Issues a fresh resume token, invalidating any previous one. Only the
+    /// SHA-256 digest is retained, so the token must be handed to the client
+    /// right away.
+    pub fn issue_resume_token(&mut self) -> Result<String, String> {
+        if !matches!(self.status, SessionStatus::Active) {
+            return Err("resume token issued only for active sessions".into());
+        }
+        let token = format!("{}{}", Uuid::new_v4().simple(), Uuid::new_v4().simple());
+        self.resume_token_sha256 = Some(token_digest(&token));
+        Ok(token)
+    }
+
+    /// Marks the session as detached after its transport went away.
+    pub fn detach(&mut self) -> Result<McpEventEnvelope, String> {
+        if !matches!(self.status, SessionStatus::Active) {
+            return Err("only active sessions can be detached".into());
+        }
+        self.status = SessionStatus::Detached;
+        self.last_active_at = Utc::now();
+        Ok(McpEventEnvelope::new(
+            self.id.clone(),
+            McpDomainEvent::SessionDetached,
+        ))
+    }
+
+    /// Re-attaches a client holding `token` and hands back results buffered
+    /// while it was away.
+    ///
+    /// Active sessions are accepted too: after an agentd restart the stored
+    /// session never saw its transport close.
+    pub fn resume(
+        &mut self,
+        token: &str,
+    ) -> Result<(McpEventEnvelope, Vec<BufferedToolResult>), String> {
+        if !matches!(self.status, SessionStatus::Active | SessionStatus::Detached) {
+            return Err("session is not resumable".into());
+        }
+        match &self.resume_token_sha256 {
+            Some(digest) if *digest == token_digest(token) => {}
+            _ => return Err("resume token mismatch".into()),
+        }
+        self.status = SessionStatus::Active;
+        self.last_active_at = Utc::now();
+        let replay: Vec<BufferedToolResult> = self.buffered_results.drain(..).collect();
+        Ok((
+            McpEventEnvelope::new(
+                self.id.clone(),
+                McpDomainEvent::SessionResumed {
+                    replayed: replay.len(),
+                },
+            ),
+            replay,
+        ))
+    }
+
+    /// Keeps a tool result for replay on resume. Returns `true` when the
+    /// buffer was full and the oldest result was dropped.
+    pub fn buffer_result(&mut self, result: BufferedToolResult) -> Result<bool, String> {
+        if !matches!(self.status, SessionStatus::Active | SessionStatus::Detached) {
+            return Err("results can be buffered only for open sessions".into());
+        }
+        self.buffered_results.push_back(result);
+        let overflow = self.buffered_results.len() > MAX_BUFFERED_RESULTS;
+        if overflow {
+            self.buffered_results.pop_front();
+        }
+        Ok(overflow)
+    }
+
+    /// Whether an open session has seen no activity for longer than `ttl`.
+    pub fn is_idle(&self, now: DateTime<Utc>, ttl: Duration) -> bool {
+        matches!(self.status, SessionStatus::Active | SessionStatus::Detached)
+            && now - self.last_active_at > ttl
+    }
+
+    /// Closes an idle session, dropping its resume token and buffer.
+    pub fn expire(&mut self, now: DateTime<Utc>) -> Result<McpEventEnvelope, String> {
+        if matches!(
+            self.status,
+            SessionStatus::Negotiating | SessionStatus::Closed
+        ) {
+            return Err("only open sessions can expire".into());
+        }
+        let idle_for = (now - self.last_active_at).to_std().unwrap_or_default();
+        self.status = SessionStatus::Closed;
+        self.resume_token_sha256 = None;
+        self.buffered_results.clear();
+        Ok(McpEventEnvelope::new(
+            self.id.clone(),
+            McpDomainEvent::SessionExpired {
+                idle_ms: idle_for.as_millis() as u64,
+            },
+        ))
+    }
+}
+
+fn token_digest(token: &str) -> String {
+    hex::encode(Sha256::digest(token.as_bytes()))
 }
 
 #[derive(Clone, Debug, Serialize, Deserialize)]
@@ -185,6 +319,10 @@ pub struct McpSessionSnapshot {
     pub status: SessionStatus,
     pub created_at: DateTime<Utc>,
     pub last_active_at: DateTime<Utc>,
+    #[serde(default)]
+    pub resume_token_sha256: Option<String>,
+    #[serde(default)]
+    pub buffered_results: Vec<BufferedToolResult>,
 }
 
 #[cfg(test)]
@@ -255,4 +393,86 @@ mod tests {
         ));
         assert!(session.close(None).is_err());
     }
+
+    fn buffered(tool: &str) -> BufferedToolResult {
+        BufferedToolResult {
+            call_id: Some(Value::from(7)),
+            tool: tool.to_string(),
+            result: Value::String("done".into()),
+            completed_at: Utc::now(),
+        }
+    }
+
+    #[test]
+    fn resume_requires_matching_token_and_replays_buffer() {
+        let mut session = mk_session();
+        session
+            .complete_handshake("1.0".to_string(), Vec::<CapabilityName>::new())
+            .unwrap();
+        let token = session.issue_resume_token().unwrap();
+        session.detach().unwrap();
+        session.buffer_result(buffered("agent.exec")).unwrap();
+
+        assert!(session.resume("wrong").is_err());
+        assert!(matches!(session.status(), SessionStatus::Detached));
+
+        let (event, replay) = session.resume(&token).unwrap();
+        assert!(matches!(session.status(), SessionStatus::Active));
+        assert_eq!(event.event, McpDomainEvent::SessionResumed { replayed: 1 });
+        assert_eq!(replay.len(), 1);
+        assert_eq!(replay[0].call_id, Some(Value::from(7)));
+        assert_eq!(session.buffered_results(), 0);
+    }
+
+    #[test]
+    fn buffer_drops_oldest_past_limit() {
+        let mut session = mk_session();
+        session
+            .complete_handshake("1.0".to_string(), Vec::<CapabilityName>::new())
+            .unwrap();
+        for _ in 0..MAX_BUFFERED_RESULTS {
+            assert!(!session.buffer_result(buffered("agent.exec")).unwrap());
+        }
+        assert!(session.buffer_result(buffered("agent.exec")).unwrap());
+        assert_eq!(session.buffered_results(), MAX_BUFFERED_RESULTS);
+    }
+
+    #[test]
+    fn snapshot_round_trip_keeps_resume_state() {
+        let mut session = mk_session();
+        session
+            .complete_handshake("1.0".to_string(), Vec::<CapabilityName>::new())
+            .unwrap();
+        let token = session.issue_resume_token().unwrap();
+        session.buffer_result(buffered("agent.exec")).unwrap();
+
+        let json = serde_json::to_string(&session.to_snapshot()).unwrap();
+        assert!(!json.contains(&token));
+        let snapshot: McpSessionSnapshot = serde_json::from_str(&json).unwrap();
+        let mut restored = McpSession::from_snapshot(snapshot).unwrap();
+        let (_, replay) = restored.resume(&token).unwrap();
+        assert_eq!(replay.len(), 1);
+    }
+
+    #[test]
+    fn idle_sessions_expire_once() {
+        let mut session = mk_session();
+        session
+            .complete_handshake("1.0".to_string(), Vec::<CapabilityName>::new())
+            .unwrap();
+        let token = session.issue_resume_token().unwrap();
+        let later = Utc::now() + Duration::minutes(10);
+        assert!(!session.is_idle(later, Duration::minutes(30)));
+        assert!(session.is_idle(later, Duration::minutes(5)));
+
+        let event = session.expire(later).unwrap();
+        assert!(matches!(
+            event.event,
+            McpDomainEvent::SessionExpired { idle_ms } if idle_ms >= 600_000
+        ));
+        assert!(matches!(session.status(), SessionStatus::Closed));
+        assert!(!session.is_idle(later, Duration::minutes(5)));
+        assert!(session.resume(&token).is_err());
+        assert!(session.expire(later).is_err());
+    }
 }
@@ -15,6 +15,13 @@ pub enum McpDomainEvent {
     SessionClosed {
         reason: Option<String>,
     },
+    SessionDetached,
+    SessionResumed {
+        replayed: usize,
+    },
+    SessionExpired {
+        idle_ms: u64,
+    },
 }
 
 #[derive(Clone, Debug, PartialEq)]
@@ -9,7 +9,10 @@ mod telemetry; // Public for benchmarks
 pub use adapters::mcp::tls::CipherPolicy;
 
 use adapters::ack::command_runner::ShellCommandRunner;
-use adapters::agents::InMemoryAgentBindingRepository;
+use adapters::agents::{
+    AdapterManifest, BridgeEvent, BridgeSupervisor, BridgeSupervisorConfig,
+    FileAgentBindingRepository, SmokeOutcome,
+};
 use adapters::mcp::grpc::GrpcBridge;
 use adapters::mcp::repo_file::FileMcpSessionRepository;
 use adapters::mcp::tls::{load_tls_snapshot, snapshots_equal, TlsPaths, TlsSnapshot};
@@ -23,17 +26,22 @@ use anyhow::{anyhow, Context, Result as AnyResult};
 use app::ack::approvals::{ApprovalRegistry, ApprovalStatus, PendingApproval};
 use app::ack::model::{EventRecord, ExecArgs, ExecRequest, UndoRequest};
 use app::ack::service::{AckError, AckService};
-use app::agents::AgentBindingService;
-use app::mcp::service::{McpBridgeError, McpBridgeService};
+use app::agents::service::AgentServiceError;
+use app::agents::watchdog;
+use app::agents::{AgentBindingService, HeartbeatWatchdogConfig, WatchdogVerdict};
+use app::mcp::service::{tool_result_payload, McpBridgeError, McpBridgeService};
+use app::mcp::SessionExpiryConfig;
+use app::quota::{QuotaPolicy, QuotaService, QuotaSubject, RateLimited};
 use app::termbridge::service::{TermBridgeDiscoveryOutcome, TermBridgeSyncPort};
 use app::termbridge::{
     spawn_discovery_task, ClipboardBridgeService, TermBridgeDiscoveryDiff,
     TermBridgeDiscoveryHandle, TermBridgeService, TermBridgeServiceConfig, TermBridgeServiceError,
 };
 use axum::extract::ws::{Message, WebSocket, WebSocketUpgrade};
-use axum::extract::State;
+use axum::extract::{MatchedPath, State};
 use axum::http::{header, HeaderMap, StatusCode};
-use axum::response::IntoResponse;
+use axum::middleware::{self, Next};
+use axum::response::{IntoResponse, Response};
 use axum::routing::{get, post};
 use axum::{Json, Router};
 use base64::engine::general_purpose::STANDARD as BASE64_STANDARD;
@@ -43,15 +51,18 @@ use config::CACHE_DIR;
 use continuum::ContinuumStore;
 use dirs::config_dir;
 use domain::agents::{
-    AgentBinding, AgentProvider, BindingStatus, CapabilityName, SdkChannel, SdkVersion,
+    AgentBinding, AgentBindingId, AgentProvider, BindingStatus, CapabilityName, SdkChannel,
+    SdkVersion,
 };
-use domain::mcp::{McpSession, SessionStatus};
+use domain::mcp::{BufferedToolResult, McpSession, SessionId, SessionStatus};
 use domain::termbridge::{
     CapabilityRecord, ClipboardBackendDescriptor, ClipboardChannel, ClipboardContent,
     ClipboardMime, CurrentWorkingDirectory, TermBridgeState, TerminalBinding as TermBridgeBinding,
     TerminalBindingId, TerminalCapabilities, TerminalId,
 };
 use notify::{EventKind, RecommendedWatcher, RecursiveMode, Watcher};
+use opentelemetry::trace::{FutureExt as _, SpanKind, Status as SpanStatus, TraceContextExt};
+use opentelemetry::{Context as OtelContext, KeyValue};
 use policy_engine::{PolicyEngine, TermBridgePolicyInput};
 use ports::termbridge::{
     ClipboardBackend, ClipboardError, ClipboardReadRequest, ClipboardServiceError,
@@ -62,6 +73,7 @@ use serde::{Deserialize, Serialize};
 use serde_json::{json, Value};
 use std::collections::{BTreeMap, HashMap, HashSet};
 use std::net::SocketAddr;
+use std::ops::ControlFlow;
 use std::path::{Path, PathBuf};
 use std::sync::{Arc, Mutex};
 use std::{env, thread};
@@ -74,7 +86,7 @@ use tokio::time::{Duration, Instant};
 use tonic::transport::Server;
 use tracing::{error, info, warn};
 
-type AgentBridgeService = AgentBindingService<InMemoryAgentBindingRepository>;
+type AgentBridgeService = AgentBindingService<FileAgentBindingRepository>;
 type TermBridgeServiceType =
     TermBridgeService<FileTermBridgeStateRepository, InMemoryTermBridgeBindingRepository>;
 type ClipboardBridgeServiceType = ClipboardBridgeService;
@@ -102,6 +114,12 @@ const DEFAULT_AGENT_BINDINGS: &[(&str, &str, &str, &[&str])] = &[
 ];
 
 const TERMBRIDGE_DISCOVERY_TOKEN_ENV: &str = "SHELLDONE_TERMBRIDGE_DISCOVERY_TOKEN";
+const AGENT_BINDING_HEADER: &str = "x-shelldone-agent-binding";
+/// Provider quota bucket shared by callers that don't name a live binding.
+const ANONYMOUS_QUOTA_KEY: &str = "anonymous";
+const QUOTA_PRUNE_INTERVAL: Duration = Duration::from_secs(60);
+const BRIDGE_RECONCILE_INTERVAL: Duration = Duration::from_secs(5);
+const BRIDGE_SMOKE_TIMEOUT: Duration = Duration::from_secs(10);
 
 #[derive(Clone)]
 struct AppState {
@@ -116,6 +134,7 @@ struct AppState {
     policy_engine: Arc<Mutex<PolicyEngine>>,
     consent_repo: Arc<dyn ConsentRepository>,
     approvals: Arc<ApprovalRegistry>,
+    quota_service: Arc<QuotaService>,
     listen: SocketAddr,
     grpc_listen: SocketAddr,
     grpc_tls_policy: CipherPolicy,
@@ -146,6 +165,12 @@ impl AppState {
             PolicyEngine::new(None).expect("creating disabled policy engine")
         });
 
+        let quota_policy = quota_policy_from_engine(&policy_engine).unwrap_or_else(|err| {
+            warn!("{err:#}; using built-in quotas");
+            QuotaPolicy::builtin()
+        });
+        let quota_service = Arc::new(QuotaService::new(quota_policy, metrics.clone()));
+
         let policy_engine = Arc::new(Mutex::new(policy_engine));
         let continuum_store = Arc::new(tokio::sync::Mutex::new({
             let snapshot_dir = state_dir.join("snapshots");
@@ -171,7 +196,10 @@ impl AppState {
                 .context("initializing MCP session store")?,
         );
 
-        let agent_repo = Arc::new(InMemoryAgentBindingRepository::new());
+        let agent_repo = Arc::new(
+            FileAgentBindingRepository::new(state_dir.join("agents").join("bindings.json"))
+                .context("initializing agent binding store")?,
+        );
         let agent_service = Arc::new(AgentBindingService::new(agent_repo));
 
         let termbridge_state_repo = Arc::new(FileTermBridgeStateRepository::new(
@@ -200,12 +228,15 @@ impl AppState {
         discovery_handle.notify_refresh("bootstrap");
 
         let termbridge_sync: Arc<dyn TermBridgeSyncPort + Send + Sync> = termbridge_service.clone();
-        let mcp_service = Arc::new(McpBridgeService::new(
-            repo,
-            ack_service.clone(),
-            Some(termbridge_sync),
-            Some(discovery_handle.clone()),
-        ));
+        let mcp_service = Arc::new(
+            McpBridgeService::new(
+                repo,
+                ack_service.clone(),
+                Some(termbridge_sync),
+                Some(discovery_handle.clone()),
+            )
+            .with_quota(quota_service.clone()),
+        );
 
         let clipboard_executor: Arc<dyn CommandExecutor> = Arc::new(SystemCommandExecutor);
         let clipboard_backends_raw = default_clipboard_backends(Arc::clone(&clipboard_executor));
@@ -246,6 +277,7 @@ impl AppState {
             metrics,
             tls_status: Arc::new(RwLock::new(TlsStatusReport::disabled())),
             approvals,
+            quota_service,
         })
     }
 
@@ -287,14 +319,20 @@ impl AppState {
             Some(Duration::from_secs(3600)),
         );
         let termbridge_sync: Arc<dyn TermBridgeSyncPort + Send + Sync> = termbridge_service.clone();
-        let mcp_service = Arc::new(McpBridgeService::new(
-            repo,
-            ack_service.clone(),
-            Some(termbridge_sync),
-            Some(discovery_handle.clone()),
-        ));
+        let quota_service = Arc::new(QuotaService::new(QuotaPolicy::builtin(), None));
+        let mcp_service = Arc::new(
+            McpBridgeService::new(
+                repo,
+                ack_service.clone(),
+                Some(termbridge_sync),
+                Some(discovery_handle.clone()),
+            )
+            .with_quota(quota_service.clone()),
+        );
 
-        let agent_repo = Arc::new(InMemoryAgentBindingRepository::new());
+        let agent_repo = Arc::new(FileAgentBindingRepository::new(
+            state_dir.join("agents").join("bindings.json"),
+        )?);
         let agent_service = Arc::new(AgentBindingService::new(agent_repo));
 
         let clipboard_backends: Vec<Arc<dyn ClipboardBackend>> = Vec::new();
@@ -320,6 +358,7 @@ impl AppState {
             tls_status: Arc::new(RwLock::new(TlsStatusReport::disabled())),
             consent_repo: Arc::new(FileConsentRepository::new(&state_dir)),
             approvals,
+            quota_service,
         })
     }
 
@@ -377,6 +416,26 @@ impl AppState {
         self.approvals.clone()
     }
 
+    fn quota(&self) -> Arc<QuotaService> {
+        self.quota_service.clone()
+    }
+
+    /// Re-read the policy file and apply the `rate_limits` it declares,
+    /// so that quotas never drift from the policy in force. Invalid
+    /// `rate_limits` keep the quotas that were already in force.
+    fn reload_policy(&self) -> AnyResult<()> {
+        let engine = self
+            .policy_engine
+            .lock()
+            .map_err(|e| anyhow!("policy engine lock poisoned: {e}"))?;
+        engine.reload()?;
+        match quota_policy_from_engine(&engine) {
+            Ok(policy) => self.quota_service.reload(policy),
+            Err(err) => warn!("{err:#}; keeping the previous quotas"),
+        }
+        Ok(())
+    }
+
     fn tls_status(&self) -> Arc<RwLock<TlsStatusReport>> {
         self.tls_status.clone()
     }
@@ -448,6 +507,41 @@ fn ack_error_to_api(command: &str, err: AckError) -> ApiError {
     }
 }
 
+/// Quotas declared by the policy, or the built-in ones when it declares none.
+fn quota_policy_from_engine(engine: &PolicyEngine) -> AnyResult<QuotaPolicy> {
+    let value = engine
+        .rate_limits()
+        .context("reading rate_limits from policy")?;
+    match value {
+        Some(value) => QuotaPolicy::from_policy_value(value).map_err(|err| anyhow!(err)),
+        None => Ok(QuotaPolicy::builtin()),
+    }
+}
+
+/// Provider quota key of a Σ-json caller: the provider of the live
+/// `AgentBinding` named by `x-shelldone-agent-binding`. Everything else
+/// shares one bucket, so inventing identities doesn't buy a fresh quota.
+async fn quota_provider_key(state: &AppState, headers: &HeaderMap) -> String {
+    let binding_id = headers
+        .get(AGENT_BINDING_HEADER)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.trim().parse::<uuid::Uuid>().ok())
+        .map(AgentBindingId::from_uuid);
+    let binding = match binding_id {
+        Some(id) => state.agent_service().get_binding(&id).await.ok().flatten(),
+        None => None,
+    };
+    binding
+        .filter(|binding| {
+            matches!(
+                binding.status(),
+                BindingStatus::Active | BindingStatus::Unhealthy
+            )
+        })
+        .map(|binding| binding.provider().slug().to_string())
+        .unwrap_or_else(|| ANONYMOUS_QUOTA_KEY.to_string())
+}
+
 #[derive(Serialize)]
 struct StatusResponse {
     version: &'static str,
@@ -819,6 +913,7 @@ struct AgentBindingSummary {
     status: String,
     capabilities: Vec<String>,
     last_heartbeat_at: Option<String>,
+    bridge_alive_at: Option<String>,
     registered_at: String,
 }
 
@@ -837,6 +932,7 @@ impl From<AgentBinding> for AgentBindingSummary {
             status: binding.status().to_string(),
             capabilities,
             last_heartbeat_at: binding.last_heartbeat_at().map(|ts| ts.to_rfc3339()),
+            bridge_alive_at: binding.bridge_alive_at().map(|ts| ts.to_rfc3339()),
             registered_at: binding.registered_at().to_rfc3339(),
         }
     }
@@ -967,6 +1063,9 @@ impl From<TermBridgeBinding> for TermBridgeBindingSummary {
 struct TermBridgeSendTextRequest {
     binding_id: String,
     payload: String,
+    /// Persona the caller acts as, for quota accounting.
+    #[serde(default)]
+    persona: Option<String>,
     #[serde(default = "default_true")]
     bracketed_paste: bool,
 }
@@ -1377,6 +1476,9 @@ async fn apply_agent_overrides(service: Arc<AgentBridgeService>) -> anyhow::Resu
         }
 
         if disabled.contains(&provider_slug) {
+            if matches!(status, BindingStatus::Disabled) {
+                continue;
+            }
             service
                 .deactivate_binding(&binding_id)
                 .await
@@ -1393,7 +1495,7 @@ async fn apply_agent_overrides(service: Arc<AgentBridgeService>) -> anyhow::Resu
             info!("Agent binding {} capabilities overridden", provider_slug);
         }
 
-        if matches!(status, BindingStatus::Active) {
+        if matches!(status, BindingStatus::Active | BindingStatus::Unhealthy) {
             if let Err(err) = service.record_heartbeat(&binding_id).await {
                 warn!(
                     "Agent binding heartbeat update failed for {}: {err}",
@@ -1411,6 +1513,280 @@ async fn apply_agent_overrides(service: Arc<AgentBridgeService>) -> anyhow::Resu
     Ok(())
 }
 
+fn spawn_agent_watchdog(
+    state: AppState,
+    config: HeartbeatWatchdogConfig,
+) -> tokio::task::JoinHandle<()> {
+    tokio::spawn(async move {
+        let mut interval = tokio::time::interval(config.interval);
+        interval.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+        loop {
+            interval.tick().await;
+            match watchdog::sweep(state.agent_service().as_ref(), &config, Utc::now()).await {
+                Ok(verdicts) => {
+                    for verdict in verdicts {
+                        report_watchdog_verdict(&state, &verdict).await;
+                    }
+                }
+                Err(err) => warn!("Agent heartbeat watchdog sweep failed: {err}"),
+            }
+        }
+    })
+}
+
+/// Watch the policy file and hot-reload it, together with its quotas,
+/// whenever it changes. The parent directory is watched because editors
+/// usually replace the file rather than writing it in place.
+fn spawn_policy_watcher(
+    state: AppState,
+    path: &Path,
+) -> AnyResult<(RecommendedWatcher, tokio::task::JoinHandle<()>)> {
+    let (event_tx, mut event_rx) = mpsc::channel::<()>(16);
+    let file_name = path.file_name().map(|name| name.to_os_string());
+    let mut watcher = RecommendedWatcher::new(
+        move |res: Result<notify::Event, notify::Error>| match res {
+            Ok(event) => {
+                let touches_policy = event
+                    .paths
+                    .iter()
+                    .any(|p| p.file_name().map(|n| n.to_os_string()) == file_name);
+                if touches_policy
+                    && matches!(event.kind, EventKind::Modify(_) | EventKind::Create(_))
+                {
+                    let _ = event_tx.try_send(());
+                }
+            }
+            Err(err) => warn!(%err, "policy watcher error"),
+        },
+        notify::Config::default(),
+    )?;
+    let dir = path
+        .parent()
+        .filter(|dir| !dir.as_os_str().is_empty())
+        .unwrap_or_else(|| Path::new("."));
+    watcher.watch(dir, RecursiveMode::NonRecursive)?;
+
+    let task = tokio::spawn(async move {
+        let debounce = Duration::from_millis(200);
+        while event_rx.recv().await.is_some() {
+            // Coalesce the burst of events that a single save produces
+            tokio::time::sleep(debounce).await;
+            while event_rx.try_recv().is_ok() {}
+            if let Err(err) = state.reload_policy() {
+                warn!("Policy reload failed: {err:#}; keeping the previous policy");
+            }
+        }
+    });
+    Ok((watcher, task))
+}
+
+fn spawn_mcp_session_expiry(
+    state: AppState,
+    config: SessionExpiryConfig,
+) -> tokio::task::JoinHandle<()> {
+    tokio::spawn(async move {
+        let mut interval = tokio::time::interval(config.interval);
+        interval.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+        loop {
+            interval.tick().await;
+            match state
+                .mcp()
+                .expire_idle_sessions(Utc::now(), config.idle_ttl)
+                .await
+            {
+                Ok(expired) => {
+                    for id in expired {
+                        info!(session = %id, "MCP session expired after idle timeout");
+                    }
+                }
+                Err(err) => warn!("MCP session expiry sweep failed: {err}"),
+            }
+        }
+    })
+}
+
+async fn report_watchdog_verdict(state: &AppState, verdict: &WatchdogVerdict) {
+    let heartbeat_age_ms = verdict.heartbeat_age.as_millis() as u64;
+    warn!(
+        binding = %verdict.binding_id,
+        provider = %verdict.provider,
+        action = verdict.action.as_str(),
+        heartbeat_age_ms,
+        "Agent adapter missed heartbeat budget"
+    );
+    if let Some(metrics) = state.metrics() {
+        metrics.record_agent_unhealthy(
+            verdict.provider.slug(),
+            verdict.action.as_str(),
+            heartbeat_age_ms as f64,
+        );
+    }
+    let event = EventRecord::new(
+        "agent.adapter.unhealthy",
+        None,
+        json!({
+            "binding_id": verdict.binding_id.to_string(),
+            "provider": verdict.provider.slug(),
+            "action": verdict.action.as_str(),
+            "heartbeat_age_ms": heartbeat_age_ms,
+            "budget_ms": verdict.budget.as_millis() as u64,
+        }),
+        None,
+        Some("agent::watchdog".to_string()),
+        None,
+    );
+    if let Err(err) = state.append_event(&event).await {
+        warn!(%err, "failed to append agent.adapter.unhealthy event");
+    }
+}
+
+fn spawn_bridge_supervision(
+    state: AppState,
+    supervisor: Arc<BridgeSupervisor>,
+    manifest: AdapterManifest,
+    mut events: mpsc::UnboundedReceiver<BridgeEvent>,
+) -> tokio::task::JoinHandle<()> {
+    tokio::spawn(async move {
+        let mut interval = tokio::time::interval(BRIDGE_RECONCILE_INTERVAL);
+        interval.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+        loop {
+            tokio::select! {
+                event = events.recv() => match event {
+                    Some(event) => handle_bridge_event(&state, event).await,
+                    None => break,
+                },
+                _ = interval.tick() => reconcile_bridges(&state, &supervisor, &manifest).await,
+            }
+        }
+    })
+}
+
+/// Run a bridge for every enabled adapter whose binding is live and stop the
+/// rest, so bridge processes follow binding activation and deactivation.
+async fn reconcile_bridges(
+    state: &AppState,
+    supervisor: &BridgeSupervisor,
+    manifest: &AdapterManifest,
+) {
+    let bindings = match state.agent_service().list_bindings().await {
+        Ok(bindings) => bindings,
+        Err(err) => {
+            warn!("Agent bridge reconcile skipped: {err}");
+            return;
+        }
+    };
+    for spec in manifest.adapters() {
+        let live = spec.enabled
+            && bindings.iter().any(|binding| {
+                binding.provider().slug() == spec.id
+                    && matches!(
+                        binding.status(),
+                        BindingStatus::Active | BindingStatus::Unhealthy
+                    )
+            });
+        if live {
+            if supervisor.start(spec).await {
+                info!("Agent bridge {} launched", spec.id);
+            }
+        } else if supervisor.stop(&spec.id).await {
+            info!("Agent bridge {} stopped (binding inactive)", spec.id);
+        }
+    }
+}
+
+async fn handle_bridge_event(state: &AppState, event: BridgeEvent) {
+    if matches!(event, BridgeEvent::Ready { .. } | BridgeEvent::Alive { .. }) {
+        record_bridge_alive(state, event.adapter()).await;
+    }
+    let (kind, payload) = match event {
+        BridgeEvent::Alive { .. } => return,
+        BridgeEvent::Ready {
+            adapter,
+            pid,
+            handshake,
+        } => (
+            "agent.adapter.started",
+            json!({ "adapter": adapter, "pid": pid, "handshake": handshake }),
+        ),
+        BridgeEvent::Exited {
+            adapter,
+            code,
+            restart_in,
+        } => {
+            if let Some(metrics) = state.metrics() {
+                metrics.record_bridge_error(&adapter, "exited");
+            }
+            (
+                "agent.adapter.exited",
+                json!({
+                    "adapter": adapter,
+                    "code": code,
+                    "restart_in_ms": restart_in.as_millis() as u64,
+                }),
+            )
+        }
+        BridgeEvent::HandshakeFailed { adapter, error } => {
+            if let Some(metrics) = state.metrics() {
+                metrics.record_bridge_error(&adapter, "handshake");
+            }
+            (
+                "agent.adapter.handshake_failed",
+                json!({ "adapter": adapter, "error": error }),
+            )
+        }
+    };
+    let event = EventRecord::new(
+        kind,
+        None,
+        payload,
+        None,
+        Some("agent::bridge".to_string()),
+        None,
+    );
+    if let Err(err) = state.append_event(&event).await {
+        warn!(%err, "failed to append {kind} event");
+    }
+}
+
+/// Process liveness only: the binding heartbeat stays with the agent itself,
+/// so a wedged bridge still trips the watchdog.
+async fn record_bridge_alive(state: &AppState, adapter: &str) {
+    let service = state.agent_service();
+    let Ok(bindings) = service.list_bindings().await else {
+        return;
+    };
+    let binding = bindings.into_iter().find(|binding| {
+        binding.provider().slug() == adapter
+            && matches!(
+                binding.status(),
+                BindingStatus::Active | BindingStatus::Unhealthy
+            )
+    });
+    if let Some(binding) = binding {
+        if let Err(err) = service.record_bridge_alive(&binding.id()).await {
+            warn!("Agent bridge {adapter} liveness not recorded: {err}");
+        }
+    }
+}
+
+/// Launch every enabled adapter in `manifest` once and verify its STDIO
+/// handshake, printing one JSON report per adapter. Returns `false` if any
+/// adapter failed.
+pub async fn smoke_adapters(manifest: &Path) -> anyhow::Result<bool> {
+    let manifest = AdapterManifest::load(manifest)?;
+    let mut passed = true;
+    for spec in manifest.adapters().iter().filter(|spec| spec.enabled) {
+        let report =
+            adapters::agents::supervisor::smoke_test(spec, manifest.root(), BRIDGE_SMOKE_TIMEOUT)
+                .await;
+        if matches!(report.outcome, SmokeOutcome::Failed { .. }) {
+            passed = false;
+        }
+        println!("{}", serde_json::to_string(&report)?);
+    }
+    Ok(passed)
+}
+
 fn capability_override_env(provider_slug: &str) -> AnyResult<Option<Vec<CapabilityName>>> {
     let key = format!(
         "SHELLDONE_AGENT_CAPABILITIES_{}",
@@ -1467,6 +1843,9 @@ pub struct Settings {
     pub state_dir: PathBuf,
     pub policy_path: Option<PathBuf>,
     pub otlp_endpoint: Option<String>,
+    /// `agents/manifest.json` whose enabled adapters agentd launches and
+    /// supervises. `None` leaves bridges to be started externally.
+    pub agent_manifest: Option<PathBuf>,
 }
 
 impl Default for Settings {
@@ -1481,6 +1860,7 @@ impl Default for Settings {
             state_dir: PathBuf::from("state"),
             policy_path: Some(PathBuf::from("policies/default.rego")),
             otlp_endpoint: None,
+            agent_manifest: None,
         }
     }
 }
@@ -1565,6 +1945,13 @@ pub async fn run(settings: Settings) -> anyhow::Result<()> {
     } else {
         (None, None)
     };
+    let tracer_provider = match settings.otlp_endpoint {
+        Some(ref endpoint) => Some(telemetry::init_traces(
+            Some(endpoint.clone()),
+            "shelldone-agentd",
+        )?),
+        None => None,
+    };
 
     let state = AppState::new(
         settings.listen,
@@ -1847,6 +2234,51 @@ pub async fn run(settings: Settings) -> anyhow::Result<()> {
 
     let (shutdown_tx, _) = broadcast::channel::<()>(1);
 
+    let agent_watchdog_task =
+        spawn_agent_watchdog(state.clone(), HeartbeatWatchdogConfig::from_env());
+    let mcp_expiry_task = spawn_mcp_session_expiry(state.clone(), SessionExpiryConfig::from_env());
+
+    let bridge_supervision = match settings.agent_manifest.as_deref() {
+        Some(path) => {
+            let manifest = AdapterManifest::load(path)?;
+            let (supervisor, events) = BridgeSupervisor::new(
+                manifest.root().to_path_buf(),
+                BridgeSupervisorConfig::default(),
+            );
+            let supervisor = Arc::new(supervisor);
+            reconcile_bridges(&state, &supervisor, &manifest).await;
+            let task =
+                spawn_bridge_supervision(state.clone(), supervisor.clone(), manifest, events);
+            Some((supervisor, task))
+        }
+        None => None,
+    };
+
+    let policy_watch = match settings.policy_path.as_deref() {
+        Some(path) => match spawn_policy_watcher(state.clone(), path) {
+            Ok(watch) => Some(watch),
+            Err(err) => {
+                warn!(
+                    "Failed to watch {}: {err:#}; policy hot-reload disabled",
+                    path.display()
+                );
+                None
+            }
+        },
+        None => None,
+    };
+
+    let quota_prune_task = tokio::spawn({
+        let quota = state.quota();
+        async move {
+            let mut interval = tokio::time::interval(QUOTA_PRUNE_INTERVAL);
+            loop {
+                interval.tick().await;
+                quota.prune();
+            }
+        }
+    });
+
     let grpc_addr = settings.grpc_listen;
     info!("grpc_listen" = %grpc_addr, "msg" = "starting MCP gRPC bridge");
     let grpc_handle = tokio::spawn(manage_grpc_server(
@@ -1884,11 +2316,13 @@ pub async fn run(settings: Settings) -> anyhow::Result<()> {
         )
         .route("/sigma/handshake", post(handshake))
         .route("/ack/exec", post(agent_exec))
+        .route("/agents/heartbeat", post(agent_heartbeat))
         .route("/journal/event", post(journal_event))
         .route("/ack/undo", post(agent_undo))
         .route("/approvals/pending", get(list_pending_approvals))
         .route("/approvals/grant", post(grant_approval))
         .route("/mcp", get(mcp_ws_upgrade))
+        .layer(middleware::from_fn(trace_http))
         .with_state(state.clone());
 
     let listener = TcpListener::bind(settings.listen).await?;
@@ -1910,6 +2344,17 @@ pub async fn run(settings: Settings) -> anyhow::Result<()> {
         warn!(%err, "MCP gRPC bridge task join error");
     }
 
+    quota_prune_task.abort();
+    agent_watchdog_task.abort();
+    mcp_expiry_task.abort();
+    if let Some((_watcher, task)) = policy_watch {
+        task.abort();
+    }
+    if let Some((supervisor, task)) = bridge_supervision {
+        task.abort();
+        supervisor.stop_all().await;
+    }
+
     if let Some(guard) = tls_watch_guard {
         guard.shutdown().await;
     }
@@ -1920,10 +2365,44 @@ pub async fn run(settings: Settings) -> anyhow::Result<()> {
             warn!("Failed to shutdown telemetry: {}", e);
         }
     }
+    if let Some(provider) = tracer_provider {
+        if let Err(e) = telemetry::shutdown_traces(provider) {
+            warn!("Failed to shutdown traces: {}", e);
+        }
+    }
 
     Ok(())
 }
 
+/// Server span per HTTP request, parented to the caller's `traceparent`.
+async fn trace_http(request: axum::extract::Request, next: Next) -> Response {
+    let parent = telemetry::remote_context(&telemetry::HeaderCarrier(request.headers()));
+    let route = request
+        .extensions()
+        .get::<MatchedPath>()
+        .map(|path| path.as_str().to_string())
+        .unwrap_or_else(|| request.uri().path().to_string());
+    let cx = telemetry::start_span(
+        format!("{} {}", request.method(), route),
+        SpanKind::Server,
+        &parent,
+        vec![
+            KeyValue::new("http.request.method", request.method().to_string()),
+            KeyValue::new("http.route", route),
+        ],
+    );
+    let response = next.run(request).with_context(cx.clone()).await;
+    let span = cx.span();
+    span.set_attribute(KeyValue::new(
+        "http.response.status_code",
+        i64::from(response.status().as_u16()),
+    ));
+    if response.status().is_server_error() {
+        span.set_status(SpanStatus::error(response.status().to_string()));
+    }
+    response
+}
+
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 enum ReloadOutcome {
     Reload,
@@ -2502,11 +2981,25 @@ async fn termbridge_duplicate(
 
 async fn termbridge_send_text(
     State(state): State<AppState>,
+    headers: HeaderMap,
     Json(req): Json<TermBridgeSendTextRequest>,
 ) -> Result<Json<Value>, ApiError> {
     let binding_id = TerminalBindingId::parse(&req.binding_id)
         .map_err(|err| ApiError::invalid("invalid_binding_id", err))?;
     let persona = env::var("SHELLDONE_PERSONA").ok().filter(|v| !v.is_empty());
+    let subject = QuotaSubject {
+        provider: Some(quota_provider_key(&state, &headers).await),
+        session: None,
+        persona: req
+            .persona
+            .clone()
+            .filter(|v| !v.is_empty())
+            .or_else(|| persona.clone()),
+    };
+    state
+        .quota()
+        .check("termbridge.send_text", &subject)
+        .map_err(ApiError::rate_limited)?;
 
     let binding = state
         .termbridge()
@@ -3428,6 +3921,14 @@ struct InitializeIdentity {
     persona: Option<String>,
 }
 
+#[derive(Debug, Deserialize)]
+struct ResumeParams {
+    #[serde(rename = "sessionId")]
+    session_id: String,
+    #[serde(rename = "resumeToken")]
+    resume_token: String,
+}
+
 #[derive(Debug, Deserialize)]
 struct ToolCallParams {
     name: String,
@@ -3461,8 +3962,93 @@ fn default_journal_kind() -> String {
     "custom".to_string()
 }
 
+#[derive(Debug, Deserialize)]
+struct AgentHeartbeatRequest {
+    #[serde(default)]
+    binding_id: Option<String>,
+    #[serde(default)]
+    provider: Option<String>,
+}
+
+async fn agent_heartbeat(
+    State(state): State<AppState>,
+    Json(req): Json<AgentHeartbeatRequest>,
+) -> Result<Json<AgentBindingSummary>, ApiError> {
+    let service = state.agent_service();
+    let bindings = service
+        .list_bindings()
+        .await
+        .map_err(|err| ApiError::internal("agent_heartbeat_failed", anyhow!(err.to_string())))?;
+    let binding = match (req.binding_id.as_deref(), req.provider.as_deref()) {
+        (Some(raw), _) => bindings
+            .into_iter()
+            .find(|binding| binding.id().to_string() == raw),
+        (None, Some(provider)) => {
+            let provider = provider
+                .parse::<AgentProvider>()
+                .map_err(|err| ApiError::invalid("invalid_provider", err))?;
+            bindings.into_iter().find(|binding| {
+                binding.provider() == &provider
+                    && matches!(
+                        binding.status(),
+                        BindingStatus::Active | BindingStatus::Unhealthy
+                    )
+            })
+        }
+        (None, None) => {
+            return Err(ApiError::invalid(
+                "invalid_request",
+                "binding_id or provider is required",
+            ))
+        }
+    }
+    .ok_or_else(|| ApiError::not_found("binding_not_found", "agent binding not found"))?;
+
+    let binding_id = binding.id();
+    let recovered = matches!(binding.status(), BindingStatus::Unhealthy);
+    service
+        .record_heartbeat(&binding_id)
+        .await
+        .map_err(|err| match err {
+            AgentServiceError::NotFound(message) => {
+                ApiError::not_found("binding_not_found", message)
+            }
+            AgentServiceError::Invalid(message) => ApiError::invalid("binding_inactive", message),
+            AgentServiceError::Conflict(message) => ApiError::conflict("binding_conflict", message),
+            AgentServiceError::Internal(message) => {
+                ApiError::internal("agent_heartbeat_failed", anyhow!(message))
+            }
+        })?;
+
+    if recovered {
+        state
+            .append_event(&EventRecord::new(
+                "agent.adapter.recovered",
+                None,
+                json!({
+                    "binding_id": binding_id.to_string(),
+                    "provider": binding.provider().slug(),
+                }),
+                None,
+                Some("agent::watchdog".to_string()),
+                None,
+            ))
+            .await
+            .map_err(|err| ApiError::internal("journal_write", err))?;
+    }
+
+    let refreshed = service
+        .get_binding(&binding_id)
+        .await
+        .ok()
+        .flatten()
+        .unwrap_or(binding);
+    Ok(Json(AgentBindingSummary::from(refreshed)))
+}
+
 async fn agent_exec(
     State(state): State<AppState>,
+    headers: HeaderMap,
     Json(packet): Json<AckPacket>,
 ) -> Result<Json<ExecResponse>, ApiError> {
     if packet.command != "agent.exec" {
@@ -3471,6 +4057,15 @@ async fn agent_exec(
             "command not implemented",
         ));
     }
+    let subject = QuotaSubject {
+        provider: Some(quota_provider_key(&state, &headers).await),
+        session: None,
+        persona: packet.persona.clone(),
+    };
+    state
+        .quota()
+        .check("agent.exec", &subject)
+        .map_err(ApiError::rate_limited)?;
     let args_value = packet
         .args
         .clone()
@@ -3596,6 +4191,8 @@ async fn grant_approval(
 struct ErrorBody {
     code: &'static str,
     message: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    retry_after_ms: Option<u64>,
 }
 
 #[derive(Debug)]
@@ -3611,6 +4208,7 @@ impl ApiError {
             body: ErrorBody {
                 code,
                 message: err.into(),
+                retry_after_ms: None,
             },
         }
     }
@@ -3621,6 +4219,7 @@ impl ApiError {
             body: ErrorBody {
                 code,
                 message: message.into(),
+                retry_after_ms: None,
             },
         }
     }
@@ -3631,6 +4230,7 @@ impl ApiError {
             body: ErrorBody {
                 code,
                 message: message.into(),
+                retry_after_ms: None,
             },
         }
     }
@@ -3641,6 +4241,7 @@ impl ApiError {
             body: ErrorBody {
                 code,
                 message: message.into(),
+                retry_after_ms: None,
             },
         }
     }
@@ -3651,6 +4252,18 @@ impl ApiError {
             body: ErrorBody {
                 code,
                 message: message.into(),
+                retry_after_ms: None,
+            },
+        }
+    }
+
+    fn conflict(code: &'static str, message: impl Into<String>) -> Self {
+        Self {
+            status: StatusCode::CONFLICT,
+            body: ErrorBody {
+                code,
+                message: message.into(),
+                retry_after_ms: None,
             },
         }
     }
@@ -3661,6 +4274,18 @@ impl ApiError {
             body: ErrorBody {
                 code,
                 message: message.into(),
+                retry_after_ms: None,
+            },
+        }
+    }
+
+    fn rate_limited(rejection: RateLimited) -> Self {
+        Self {
+            status: StatusCode::TOO_MANY_REQUESTS,
+            body: ErrorBody {
+                code: "rate_limited",
+                message: rejection.to_string(),
+                retry_after_ms: Some(rejection.retry_after_ms()),
             },
         }
     }
@@ -3672,6 +4297,7 @@ impl ApiError {
             body: ErrorBody {
                 code,
                 message: err.to_string(),
+                retry_after_ms: None,
             },
         }
     }
@@ -3680,8 +4306,14 @@ impl ApiError {
 impl IntoResponse for ApiError {
     fn into_response(self) -> axum::response::Response {
         let status = self.status;
-        let body = Json(self.body);
-        (status, body).into_response()
+        let retry_after_secs = self.body.retry_after_ms.map(|ms| ms.div_ceil(1000).max(1));
+        let mut response = (status, Json(self.body)).into_response();
+        if let Some(secs) = retry_after_secs {
+            if let Ok(value) = header::HeaderValue::from_str(&secs.to_string()) {
+                response.headers_mut().insert(header::RETRY_AFTER, value);
+            }
+        }
+        response
     }
 }
 
@@ -3824,6 +4456,7 @@ mod tests {
     use crate::domain::termbridge::{
         CapabilityRecord, TerminalBinding, TerminalBindingId, TerminalCapabilities, TerminalId,
     };
+    use crate::ports::agents::AgentBindingRepository;
     use crate::ports::termbridge::{
         CapabilityObservation, DuplicateOptions, DuplicateStrategy, SpawnRequest, TermBridgeError,
         TerminalBindingRepository, TerminalControlPort,
@@ -3885,6 +4518,77 @@ mod tests {
         assert!(response.changed);
     }
 
+    #[tokio::test]
+    async fn agent_heartbeat_recovers_unhealthy_binding() {
+        let temp = TempDir::new().unwrap();
+        let termbridge_service = Arc::new(TermBridgeService::new(
+            Arc::new(FileTermBridgeStateRepository::new(
+                temp.path().join("termbridge_state.json"),
+            )),
+            Arc::new(InMemoryTermBridgeBindingRepository::default()),
+            Vec::new(),
+            None,
+            TermBridgeServiceConfig::default(),
+        ));
+        let state = AppState::for_termbridge_test(termbridge_service, temp.path().to_path_buf())
+            .expect("test state");
+        state.termbridge_discovery().shutdown().await;
+        seed_default_agent_bindings(state.agent_service())
+            .await
+            .unwrap();
+        let openai = state
+            .agent_service()
+            .list_bindings()
+            .await
+            .unwrap()
+            .into_iter()
+            .find(|binding| binding.provider() == &AgentProvider::OpenAi)
+            .unwrap();
+        state
+            .agent_service()
+            .mark_unhealthy(&openai.id())
+            .await
+            .unwrap();
+
+        let app = Router::new()
+            .route("/agents/heartbeat", post(agent_heartbeat))
+            .with_state(state.clone());
+        let response = app
+            .oneshot(
+                Request::builder()
+                    .method("POST")
+                    .uri("/agents/heartbeat")
+                    .header("content-type", "application/json")
+                    .body(Body::from(json!({"provider": "openai"}).to_string()))
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        let bytes = response
+            .into_body()
+            .into_data_stream()
+            .try_fold(Vec::new(), |mut acc, chunk| async move {
+                acc.extend_from_slice(&chunk);
+                Ok(acc)
+            })
+            .await
+            .unwrap();
+        let body: serde_json::Value = serde_json::from_slice(&bytes).unwrap();
+        assert_eq!(body["status"], "active");
+        assert_eq!(body["id"], openai.id().to_string());
+
+        // Bindings are persisted under the state dir and survive a reload.
+        let reloaded =
+            FileAgentBindingRepository::new(temp.path().join("agents").join("bindings.json"))
+                .unwrap();
+        let restored = reloaded.get(&openai.id()).await.unwrap().unwrap();
+        assert_eq!(restored.status(), &BindingStatus::Active);
+
+        let journal = std::fs::read_to_string(state.journal_path()).unwrap();
+        assert!(journal.contains("agent.adapter.recovered"));
+    }
+
     #[tokio::test]
     async fn termbridge_discover_uses_cache_within_ttl() {
         use axum::http::Request;
@@ -4294,6 +4998,126 @@ mod tests {
         }
     }
 
+    #[tokio::test]
+    async fn ack_exec_spans_follow_agent_traceparent() {
+        const TRACE_ID: &str = "0af7651916cd43dd8448eb211c80319c";
+        let collector = telemetry::testing::SpanCollector::global();
+        let temp = TempDir::new().unwrap();
+        let state = AppState::new(
+            SocketAddr::from(([127, 0, 0, 1], 0)),
+            SocketAddr::from(([127, 0, 0, 1], 0)),
+            temp.path().to_path_buf(),
+            CipherPolicy::Balanced,
+            None,
+            None,
+        )
+        .unwrap();
+        let app = Router::new()
+            .route("/ack/exec", post(agent_exec))
+            .layer(middleware::from_fn(trace_http))
+            .with_state(state.clone());
+
+        let response = app
+            .oneshot(
+                Request::builder()
+                    .method("POST")
+                    .uri("/ack/exec")
+                    .header("content-type", "application/json")
+                    .header("traceparent", format!("00-{TRACE_ID}-b7ad6b7169203331-01"))
+                    .body(Body::from(
+                        json!({"command": "agent.exec", "args": {"cmd": "echo traced"}})
+                            .to_string(),
+                    ))
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+
+        let spans = collector.spans_in_trace(TRACE_ID);
+        let names: Vec<&str> = spans.iter().map(|span| span.name.as_ref()).collect();
+        for expected in [
+            "POST /ack/exec",
+            "policy.evaluate",
+            "ack.exec",
+            "continuum.append",
+        ] {
+            assert!(names.contains(&expected), "missing {expected}: {names:?}");
+        }
+        let server = spans
+            .iter()
+            .find(|span| span.name == "POST /ack/exec")
+            .unwrap();
+        assert!(spans
+            .iter()
+            .filter(|span| span.name != "POST /ack/exec")
+            .all(|span| span.parent_span_id == server.span_context.span_id()));
+
+        let journal = tokio::fs::read_to_string(state.journal_path())
+            .await
+            .unwrap();
+        let exec_event: EventRecord = journal
+            .lines()
+            .filter_map(|line| serde_json::from_str(line).ok())
+            .find(|event: &EventRecord| event.kind == "exec")
+            .expect("exec event journaled");
+        assert_eq!(exec_event.trace_id.as_deref(), Some(TRACE_ID));
+    }
+
+    fn write_rate_limits(path: &Path, persona_limit: &str) {
+        std::fs::write(
+            path,
+            format!(
+                "package shelldone.policy\n\nimport rego.v1\n\n\
+                 rate_limits := {{\"persona\": {{\"agent.exec\": {persona_limit}}}}}\n"
+            ),
+        )
+        .unwrap();
+    }
+
+    #[tokio::test]
+    async fn policy_watcher_reloads_quotas_and_keeps_them_on_error() {
+        let temp = TempDir::new().unwrap();
+        let policy_path = temp.path().join("policy.rego");
+        write_rate_limits(&policy_path, r#"{"per_second": 1000, "burst": 1000}"#);
+        let state = AppState::new(
+            SocketAddr::from(([127, 0, 0, 1], 0)),
+            SocketAddr::from(([127, 0, 0, 1], 0)),
+            temp.path().to_path_buf(),
+            CipherPolicy::Balanced,
+            Some(policy_path.clone()),
+            None,
+        )
+        .unwrap();
+        let subject = QuotaSubject {
+            persona: Some("core".to_string()),
+            ..Default::default()
+        };
+        let quota = state.quota();
+        for _ in 0..10 {
+            quota.check("agent.exec", &subject).unwrap();
+        }
+
+        let (_watcher, task) = spawn_policy_watcher(state.clone(), &policy_path).unwrap();
+        write_rate_limits(&policy_path, r#"{"per_second": 1, "burst": 1}"#);
+        let deadline = Instant::now() + Duration::from_secs(10);
+        while quota.check("agent.exec", &subject).is_ok() {
+            assert!(
+                Instant::now() < deadline,
+                "rewritten rate_limits were not applied"
+            );
+            tokio::time::sleep(Duration::from_millis(50)).await;
+        }
+        task.abort();
+
+        // A broken rate_limits leaves the previous quotas in force rather
+        // than reverting to the built-in ones
+        write_rate_limits(&policy_path, r#"{"per_second": "fast"}"#);
+        state.reload_policy().unwrap();
+        let _ = quota.check("agent.exec", &subject);
+        assert!(quota.check("agent.exec", &subject).is_err());
+    }
+
     #[tokio::test]
     async fn journal_endpoint_appends_event() {
         let temp = TempDir::new().unwrap();
@@ -5031,15 +5855,24 @@ async fn agent_undo(
     }))
 }
 
-async fn mcp_ws_upgrade(ws: WebSocketUpgrade, State(state): State<AppState>) -> impl IntoResponse {
+async fn mcp_ws_upgrade(
+    ws: WebSocketUpgrade,
+    State(state): State<AppState>,
+    headers: HeaderMap,
+) -> impl IntoResponse {
+    let connection = telemetry::remote_context(&telemetry::HeaderCarrier(&headers));
     ws.on_upgrade(move |socket| async move {
-        if let Err(err) = handle_mcp_socket(socket, state).await {
+        if let Err(err) = handle_mcp_socket(socket, state, connection).await {
             warn!("MCP session terminated: {err:#}");
         }
     })
 }
 
-async fn handle_mcp_socket(mut socket: WebSocket, state: AppState) -> AnyResult<()> {
+async fn handle_mcp_socket(
+    mut socket: WebSocket,
+    state: AppState,
+    connection: OtelContext,
+) -> AnyResult<()> {
     let bridge = state.mcp();
     let mut session: Option<McpSession> = None;
 
@@ -5081,144 +5914,259 @@ async fn handle_mcp_socket(mut socket: WebSocket, state: AppState) -> AnyResult<
                     continue;
                 }
 
-                match request.method.as_str() {
-                    "initialize" => {
-                        let id = match request.id.clone() {
-                            Some(id) => id,
-                            None => continue,
-                        };
-                        let params: InitializeParams = request
-                            .params
-                            .clone()
-                            .and_then(|value| serde_json::from_value(value).ok())
-                            .unwrap_or_default();
-                        let protocol_version = params.protocol_version.ok_or_else(|| {
-                            McpBridgeError::Protocol("protocolVersion is required".into())
-                        })?;
-                        let persona = params.identity.and_then(|ident| ident.persona);
-                        let capabilities = extract_capabilities(params.client_capabilities.clone());
-                        let new_session = bridge
-                            .initialize_session(persona, protocol_version.clone(), capabilities)
-                            .await;
-                        match new_session {
-                            Ok(session_obj) => {
-                                let session_id = session_obj.id().to_string();
-                                let result = json!({
-                                    "protocolVersion": protocol_version,
-                                    "sessionId": session_id,
-                                    "serverCapabilities": {
-                                        "tools": {
-                                            "listChanged": false
-                                        }
-                                    }
-                                });
-                                session = Some(session_obj);
-                                send_json_response(&mut socket, id, Ok(result)).await?;
-                            }
-                            Err(err) => {
-                                send_json_response(&mut socket, id, Err(err)).await?;
+                let cx = mcp_request_span(&request, &connection);
+                let flow = dispatch_mcp_request(&mut socket, &mut session, &bridge, request)
+                    .with_context(cx)
+                    .await?;
+                if flow.is_break() {
+                    break;
+                }
+            }
+            Message::Binary(_) => {}
+            Message::Ping(payload) => {
+                socket.send(Message::Pong(payload)).await.ok();
+            }
+            Message::Pong(_) => continue,
+            Message::Close(_) => break,
+        }
+    }
+
+    // Keep the session resumable; idle expiry closes it if nobody returns.
+    if let Some(session) = session {
+        detach_mcp_session(&bridge, &session).await;
+    }
+
+    Ok(())
+}
+
+async fn dispatch_mcp_request(
+    socket: &mut WebSocket,
+    session: &mut Option<McpSession>,
+    bridge: &McpBridgeService<AckService<ShellCommandRunner>, FileMcpSessionRepository>,
+    request: JsonRpcRequest,
+) -> AnyResult<ControlFlow<()>> {
+    match request.method.as_str() {
+        "initialize" => {
+            let id = match request.id.clone() {
+                Some(id) => id,
+                None => return Ok(ControlFlow::Continue(())),
+            };
+            let params: InitializeParams = request
+                .params
+                .clone()
+                .and_then(|value| serde_json::from_value(value).ok())
+                .unwrap_or_default();
+            let protocol_version = params
+                .protocol_version
+                .ok_or_else(|| McpBridgeError::Protocol("protocolVersion is required".into()))?;
+            let persona = params.identity.and_then(|ident| ident.persona);
+            let capabilities = extract_capabilities(params.client_capabilities.clone());
+            let new_session = bridge
+                .initialize_session(persona, protocol_version.clone(), capabilities)
+                .await;
+            match new_session {
+                Ok((session_obj, resume_token)) => {
+                    let session_id = session_obj.id().to_string();
+                    let result = json!({
+                        "protocolVersion": protocol_version,
+                        "sessionId": session_id,
+                        "resumeToken": resume_token,
+                        "serverCapabilities": {
+                            "tools": {
+                                "listChanged": false
                             }
                         }
+                    });
+                    if let Some(previous) = session.take() {
+                        detach_mcp_session(bridge, &previous).await;
                     }
-                    "tools/list" => {
-                        if let Some(id) = request.id.clone() {
-                            let result = bridge.list_tools().await;
-                            send_json_response(&mut socket, id, Ok(result)).await?;
+                    *session = Some(session_obj);
+                    send_json_response(socket, id, Ok(result)).await?;
+                }
+                Err(err) => {
+                    send_json_response(socket, id, Err(err)).await?;
+                }
+            }
+        }
+        "session/resume" => {
+            let id = match request.id.clone() {
+                Some(id) => id,
+                None => return Ok(ControlFlow::Continue(())),
+            };
+            let params: ResumeParams = match request.params.clone().map(serde_json::from_value) {
+                Some(Ok(params)) => params,
+                _ => {
+                    let error =
+                        McpBridgeError::Protocol("sessionId and resumeToken are required".into());
+                    send_json_response(socket, id, Err(error)).await?;
+                    return Ok(ControlFlow::Continue(()));
+                }
+            };
+            let outcome = match params.session_id.parse::<uuid::Uuid>() {
+                Ok(uuid) => {
+                    bridge
+                        .resume_session(&SessionId::from_uuid(uuid), &params.resume_token)
+                        .await
+                }
+                Err(_) => Err(McpBridgeError::Protocol("invalid sessionId".into())),
+            };
+            match outcome {
+                Ok((resumed, replay)) => {
+                    if let Some(previous) = session.take() {
+                        if previous.id() != resumed.id() {
+                            detach_mcp_session(bridge, &previous).await;
                         }
                     }
-                    "tools/call" => {
-                        let id = match request.id.clone() {
-                            Some(id) => id,
-                            None => continue,
-                        };
-                        let params_value = request.params.clone().unwrap_or(Value::Null);
-                        let params: ToolCallParams =
-                            serde_json::from_value(params_value).map_err(|err| anyhow!(err))?;
-                        let mut current_session = match session.take() {
-                            Some(sess) => sess,
-                            None => {
-                                send_json_response(
-                                    &mut socket,
-                                    id,
-                                    Err(McpBridgeError::Protocol("session not initialized".into())),
-                                )
-                                .await?;
-                                continue;
-                            }
-                        };
-                        if let Some(requested) = params.session_id.as_ref() {
-                            if &current_session.id().to_string() != requested {
-                                let error = McpBridgeError::Protocol("session mismatch".into());
-                                send_json_response(&mut socket, id, Err(error)).await?;
-                                session = Some(current_session);
-                                continue;
-                            }
-                        }
-                        let outcome = bridge
-                            .call_tool(&mut current_session, &params.name, params.arguments)
+                    let result = json!({
+                        "sessionId": resumed.id().to_string(),
+                        "protocolVersion": resumed.protocol_version(),
+                        "replay": replay.iter().map(replay_entry).collect::<Vec<_>>(),
+                    });
+                    *session = Some(resumed);
+                    send_json_response(socket, id, Ok(result)).await?;
+                }
+                Err(err) => {
+                    send_json_response(socket, id, Err(err)).await?;
+                }
+            }
+        }
+        "session/close" => {
+            let outcome = match session.take() {
+                Some(mut current_session) => bridge
+                    .close_session(&mut current_session, Some("client closed".into()))
+                    .await
+                    .map(|_| json!({})),
+                None => Err(McpBridgeError::Protocol("session not initialized".into())),
+            };
+            if let Some(id) = request.id.clone() {
+                send_json_response(socket, id, outcome).await?;
+            }
+        }
+        "tools/list" => {
+            if let Some(id) = request.id.clone() {
+                let result = bridge.list_tools().await;
+                send_json_response(socket, id, Ok(result)).await?;
+            }
+        }
+        "tools/call" => {
+            let id = match request.id.clone() {
+                Some(id) => id,
+                None => return Ok(ControlFlow::Continue(())),
+            };
+            let params_value = request.params.clone().unwrap_or(Value::Null);
+            let params: ToolCallParams =
+                serde_json::from_value(params_value).map_err(|err| anyhow!(err))?;
+            let mut current_session = match session.take() {
+                Some(sess) => sess,
+                None => {
+                    send_json_response(
+                        socket,
+                        id,
+                        Err(McpBridgeError::Protocol("session not initialized".into())),
+                    )
+                    .await?;
+                    return Ok(ControlFlow::Continue(()));
+                }
+            };
+            if let Some(requested) = params.session_id.as_ref() {
+                if &current_session.id().to_string() != requested {
+                    let error = McpBridgeError::Protocol("session mismatch".into());
+                    send_json_response(socket, id, Err(error)).await?;
+                    *session = Some(current_session);
+                    return Ok(ControlFlow::Continue(()));
+                }
+            }
+            let outcome = bridge
+                .call_tool(&mut current_session, &params.name, params.arguments)
+                .await
+                .map(|exec| tool_result_payload(&exec));
+            let delivered = match outcome {
+                Ok(result) => {
+                    let sent = send_json_response(socket, id.clone(), Ok(result.clone())).await;
+                    if sent.is_err() {
+                        // Client vanished mid-call: keep the result for resume.
+                        if let Err(err) = bridge
+                            .buffer_result(&current_session.id(), Some(id), &params.name, result)
                             .await
-                            .map(|exec| {
-                                json!({
-                                    "content": [
-                                        {
-                                            "type": "text",
-                                            "text": exec.stdout,
-                                        }
-                                    ],
-                                    "isError": exec.exit_code != 0,
-                                    "metadata": {
-                                        "exitCode": exec.exit_code,
-                                        "stderr": exec.stderr,
-                                        "eventId": exec.event_id,
-                                        "spectralTag": exec.spectral_tag,
-                                        "durationMs": exec.duration_ms,
-                                    }
-                                })
-                            });
-                        send_json_response(&mut socket, id, outcome).await?;
-                        session = Some(current_session);
-                    }
-                    "ping" => {
-                        if let Some(id) = request.id.clone() {
-                            send_json_response(&mut socket, id, Ok(json!({}))).await?;
-                        }
-                    }
-                    "notifications/heartbeat" | "session/heartbeat" => {
-                        if let Some(mut current_session) = session.take() {
-                            if let Err(err) = bridge.record_heartbeat(&mut current_session).await {
-                                warn!("heartbeat failed: {err}");
-                            }
-                            session = Some(current_session);
-                        }
-                    }
-                    _ => {
-                        if let Some(id) = request.id.clone() {
-                            send_json_response(
-                                &mut socket,
-                                id,
-                                Err(McpBridgeError::UnsupportedTool(request.method)),
-                            )
-                            .await?;
+                        {
+                            warn!("failed to buffer MCP tool result: {err}");
                         }
                     }
+                    sent
                 }
+                Err(err) => send_json_response(socket, id, Err(err)).await,
+            };
+            *session = Some(current_session);
+            if let Err(err) = delivered {
+                warn!("MCP response delivery failed: {err:#}");
+                return Ok(ControlFlow::Break(()));
             }
-            Message::Binary(_) => {}
-            Message::Ping(payload) => {
-                socket.send(Message::Pong(payload)).await.ok();
+        }
+        "ping" => {
+            if let Some(id) = request.id.clone() {
+                send_json_response(socket, id, Ok(json!({}))).await?;
+            }
+        }
+        "notifications/heartbeat" | "session/heartbeat" => {
+            if let Some(mut current_session) = session.take() {
+                if let Err(err) = bridge.record_heartbeat(&mut current_session).await {
+                    warn!("heartbeat failed: {err}");
+                }
+                *session = Some(current_session);
+            }
+        }
+        _ => {
+            if let Some(id) = request.id.clone() {
+                send_json_response(
+                    socket,
+                    id,
+                    Err(McpBridgeError::UnsupportedTool(request.method)),
+                )
+                .await?;
             }
-            Message::Pong(_) => continue,
-            Message::Close(_) => break,
         }
     }
+    Ok(ControlFlow::Continue(()))
+}
+
+/// Span for one JSON-RPC message. A `traceparent` in `params._meta` takes
+/// precedence over the one sent with the WebSocket upgrade.
+fn mcp_request_span(request: &JsonRpcRequest, connection: &OtelContext) -> OtelContext {
+    let parent = request
+        .params
+        .as_ref()
+        .and_then(|params| params.get("_meta"))
+        .and_then(Value::as_object)
+        .filter(|meta| meta.contains_key("traceparent"))
+        .map(|meta| telemetry::remote_context(&telemetry::JsonCarrier(meta)))
+        .unwrap_or_else(|| connection.clone());
+    telemetry::start_span(
+        format!("mcp.ws {}", request.method),
+        SpanKind::Server,
+        &parent,
+        vec![
+            KeyValue::new("rpc.system", "jsonrpc"),
+            KeyValue::new("rpc.method", request.method.clone()),
+        ],
+    )
+}
 
-    if let Some(mut session) = session {
-        let _ = bridge
-            .close_session(&mut session, Some("socket closed".into()))
-            .await;
+async fn detach_mcp_session(
+    bridge: &McpBridgeService<AckService<ShellCommandRunner>, FileMcpSessionRepository>,
+    session: &McpSession,
+) {
+    if let Err(err) = bridge.detach_session(&session.id()).await {
+        warn!(session = %session.id(), "failed to detach MCP session: {err}");
     }
+}
 
-    Ok(())
+fn replay_entry(buffered: &BufferedToolResult) -> Value {
+    json!({
+        "id": buffered.call_id,
+        "tool": buffered.tool,
+        "result": buffered.result,
+        "completedAt": buffered.completed_at.to_rfc3339(),
+    })
 }
 
 fn extract_capabilities(capabilities: Option<Value>) -> Vec<String> {
@@ -5282,5 +6230,16 @@ fn map_mcp_error(err: McpBridgeError) -> JsonRpcError {
             message,
             data: None,
         },
+        McpBridgeError::RateLimited {
+            message,
+            retry_after_ms,
+        } => JsonRpcError {
+            code: -32029,
+            message,
+            data: Some(json!({
+                "code": "rate_limited",
+                "retryAfterMs": retry_after_ms,
+            })),
+        },
     }
 }
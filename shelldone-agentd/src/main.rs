@@ -1,5 +1,5 @@
 use clap::Parser;
-use shelldone_agentd::{run, CipherPolicy, Settings};
+use shelldone_agentd::{run, smoke_adapters, CipherPolicy, Settings};
 use std::net::SocketAddr;
 use std::path::PathBuf;
 use tracing_subscriber::EnvFilter;
@@ -68,6 +68,19 @@ struct Cli {
         help = "OTLP endpoint for Prism telemetry (e.g., http://localhost:4318)"
     )]
     otlp_endpoint: Option<String>,
+
+    #[arg(
+        long,
+        value_name = "PATH",
+        help = "Adapter manifest (agents/manifest.json) whose bridges agentd launches and supervises"
+    )]
+    agent_manifest: Option<PathBuf>,
+
+    #[arg(
+        long,
+        help = "Verify the handshake of every enabled adapter in the manifest and exit"
+    )]
+    smoke_adapters: bool,
 }
 
 #[tokio::main]
@@ -82,6 +95,15 @@ async fn main() -> anyhow::Result<()> {
         .compact()
         .init();
 
+    if cli.smoke_adapters {
+        let manifest = cli
+            .agent_manifest
+            .clone()
+            .unwrap_or_else(|| PathBuf::from("agents/manifest.json"));
+        let passed = smoke_adapters(&manifest).await?;
+        std::process::exit(if passed { 0 } else { 1 });
+    }
+
     let policy_path = cli.policy.or_else(|| {
         let default_path = PathBuf::from("policies/default.rego");
         if default_path.exists() {
@@ -101,6 +123,7 @@ async fn main() -> anyhow::Result<()> {
         state_dir: cli.state_dir,
         policy_path,
         otlp_endpoint: cli.otlp_endpoint,
+        agent_manifest: cli.agent_manifest,
     };
 
     run(settings).await
@@ -1,5 +1,7 @@
+use crate::telemetry;
 use anyhow::{Context, Result};
 use lru::LruCache;
+use opentelemetry::KeyValue;
 use regorus::Engine;
 use serde::Serialize;
 use std::num::NonZeroUsize;
@@ -110,8 +112,6 @@ impl PolicyEngine {
     }
 
     /// Reload policy from disk (hot-reload support)
-    /// Wave 2: Will be used for dynamic policy updates
-    #[allow(dead_code)]
     pub fn reload(&self) -> Result<()> {
         if !self.enabled {
             return Ok(());
@@ -157,6 +157,13 @@ impl PolicyEngine {
 
     /// Evaluate ACK command against policy
     pub fn evaluate_ack(&self, input: &AckPolicyInput) -> Result<PolicyDecision> {
+        let _span = telemetry::enter_span(
+            "policy.evaluate",
+            vec![
+                KeyValue::new("policy.domain", "ack"),
+                KeyValue::new("policy.action", input.command.clone()),
+            ],
+        );
         if !self.enabled {
             debug!("Policy engine disabled, allowing by default");
             return Ok(PolicyDecision::allow());
@@ -235,6 +242,13 @@ impl PolicyEngine {
 
     /// Evaluate TermBridge action against policy (clipboard, spawn, send_text)
     pub fn evaluate_termbridge(&self, input: &TermBridgePolicyInput) -> Result<PolicyDecision> {
+        let _span = telemetry::enter_span(
+            "policy.evaluate",
+            vec![
+                KeyValue::new("policy.domain", "termbridge"),
+                KeyValue::new("policy.action", input.action.clone()),
+            ],
+        );
         if !self.enabled {
             debug!("Policy engine disabled, allowing termbridge action by default");
             return Ok(PolicyDecision::allow());
@@ -339,6 +353,35 @@ impl PolicyEngine {
         Ok(PolicyDecision::allow())
     }
 
+    /// Read agent rate limit quotas from `data.shelldone.policy.rate_limits`.
+    /// Returns `None` when the engine is disabled or the policy defines none.
+    pub fn rate_limits(&self) -> Result<Option<serde_json::Value>> {
+        if !self.enabled {
+            return Ok(None);
+        }
+
+        let mut engine = self
+            .engine
+            .write()
+            .map_err(|e| anyhow::anyhow!("failed to acquire write lock on policy engine: {}", e))?;
+
+        let result = engine
+            .eval_query("data.shelldone.policy.rate_limits".to_string(), false)
+            .context("evaluating policy query data.shelldone.policy.rate_limits")?;
+
+        let Some(value) = result
+            .result
+            .first()
+            .and_then(|r| r.expressions.first())
+            .map(|e| e.value.clone())
+        else {
+            return Ok(None);
+        };
+
+        let value = serde_json::to_value(&value).context("converting rate_limits to JSON")?;
+        Ok(Some(value))
+    }
+
     /// Evaluate OSC escape sequence against policy
     /// Wave 2: OSC filtering integration with Σ-pty proxy
     #[allow(dead_code)]
@@ -909,6 +952,24 @@ tls_deny_reason contains msg if {{
         assert!(!decision.is_allowed(), "OSC 999 should be denied");
     }
 
+    #[test]
+    fn policy_rate_limits_absent_in_test_policy() {
+        let policy_file = create_test_policy();
+        let engine = PolicyEngine::new(Some(policy_file.path())).unwrap();
+        assert!(engine.rate_limits().unwrap().is_none());
+        let disabled = PolicyEngine::new(None).unwrap();
+        assert!(disabled.rate_limits().unwrap().is_none());
+    }
+
+    #[test]
+    fn policy_rate_limits_read_from_default_policy() {
+        let path = Path::new(env!("CARGO_MANIFEST_DIR")).join("../policies/default.rego");
+        let engine = PolicyEngine::new(Some(&path)).unwrap();
+        let limits = engine.rate_limits().unwrap().expect("rate_limits defined");
+        assert_eq!(limits["persona"]["agent.exec"]["per_second"], 20);
+        assert_eq!(limits["session"]["agent.exec"]["burst"], 20);
+    }
+
     #[test]
     fn policy_engine_disabled_allows_all() {
         let engine = PolicyEngine::new(None).unwrap();
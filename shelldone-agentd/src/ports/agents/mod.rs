@@ -1,3 +1,3 @@
 pub mod repo_port;
 
-pub use repo_port::AgentBindingRepository;
+pub use repo_port::{AgentBindingRepository, BindingVersionConflict};
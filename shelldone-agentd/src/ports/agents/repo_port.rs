@@ -1,8 +1,21 @@
 use crate::domain::agents::{AgentBinding, AgentBindingId};
 use async_trait::async_trait;
+use thiserror::Error;
+
+/// Raised by `save` when the stored binding moved past the version the caller
+/// loaded.
+#[derive(Debug, Error, PartialEq, Eq)]
+#[error("binding {id} version conflict: expected {expected}, found {actual}")]
+pub struct BindingVersionConflict {
+    pub id: AgentBindingId,
+    pub expected: u64,
+    pub actual: u64,
+}
 
 #[async_trait]
 pub trait AgentBindingRepository: Send + Sync {
+    /// Persist `binding` if its version matches the stored one (0 for new
+    /// bindings). Fails with [`BindingVersionConflict`] otherwise.
     async fn save(&self, binding: AgentBinding) -> anyhow::Result<()>;
     async fn get(&self, id: &AgentBindingId) -> anyhow::Result<Option<AgentBinding>>;
     async fn list(&self) -> anyhow::Result<Vec<AgentBinding>>;
@@ -1,12 +1,20 @@
 use anyhow::{Context, Result};
 use opentelemetry::metrics::{Counter, Histogram, Meter};
-use opentelemetry::{global, KeyValue};
+use opentelemetry::propagation::{Extractor, TextMapPropagator};
+use opentelemetry::trace::{FutureExt as _, SpanKind, Status, TraceContextExt, Tracer as _};
+use opentelemetry::{global, Context as OtelContext, ContextGuard, KeyValue};
 use opentelemetry_otlp::WithExportConfig;
 use opentelemetry_sdk::metrics::SdkMeterProvider;
+use opentelemetry_sdk::propagation::TraceContextPropagator;
+use opentelemetry_sdk::trace::TracerProvider;
 use opentelemetry_sdk::Resource;
+use std::borrow::Cow;
+use std::future::Future;
 use std::time::Duration;
 use tracing::info;
 
+const TRACER_NAME: &str = "shelldone-agentd";
+
 /// Prism telemetry metrics
 pub struct PrismMetrics {
     // ACK command latency
@@ -44,6 +52,15 @@ pub struct PrismMetrics {
     pub termbridge_latency: Histogram<f64>,
     #[allow(dead_code)]
     pub termbridge_clipboard_bytes: Counter<u64>,
+
+    // Agent quotas
+    pub rate_limit_rejections: Counter<u64>,
+    pub rate_limit_retry_after: Histogram<f64>,
+
+    // Agent adapter heartbeat watchdog
+    pub agent_unhealthy: Counter<u64>,
+    pub agent_heartbeat_age: Histogram<f64>,
+    pub agent_bridge_errors: Counter<u64>,
 }
 
 impl PrismMetrics {
@@ -124,6 +141,31 @@ impl PrismMetrics {
             .with_description("Bytes transferred through clipboard bridge")
             .build();
 
+        let rate_limit_rejections = meter
+            .u64_counter("shelldone.quota.rejections")
+            .with_description("Agent requests rejected by rate limit quotas")
+            .build();
+
+        let rate_limit_retry_after = meter
+            .f64_histogram("shelldone.quota.retry_after_ms")
+            .with_description("Retry-after hint returned to rate limited agents in milliseconds")
+            .build();
+
+        let agent_unhealthy = meter
+            .u64_counter("agent.adapter.unhealthy")
+            .with_description("Agent bindings flagged by the heartbeat watchdog")
+            .build();
+
+        let agent_heartbeat_age = meter
+            .f64_histogram("agent.heartbeat.age_ms")
+            .with_description("Heartbeat age of bindings flagged by the watchdog in milliseconds")
+            .build();
+
+        let agent_bridge_errors = meter
+            .u64_counter("agent.bridge.errors")
+            .with_description("Supervised agent bridge crashes and rejected handshakes")
+            .build();
+
         Self {
             exec_latency,
             undo_latency,
@@ -140,6 +182,11 @@ impl PrismMetrics {
             termbridge_errors,
             termbridge_latency,
             termbridge_clipboard_bytes,
+            rate_limit_rejections,
+            rate_limit_retry_after,
+            agent_unhealthy,
+            agent_heartbeat_age,
+            agent_bridge_errors,
         }
     }
 
@@ -286,6 +333,35 @@ impl PrismMetrics {
         self.termbridge_latency.record(latency_ms, &attrs);
         self.termbridge_clipboard_bytes.add(bytes, &attrs);
     }
+
+    /// Record a request rejected by a rate limit quota
+    pub fn record_rate_limited(&self, action: &str, scope: &str, retry_after_ms: f64) {
+        let attrs = [
+            KeyValue::new("action", action.to_string()),
+            KeyValue::new("scope", scope.to_string()),
+        ];
+        self.rate_limit_rejections.add(1, &attrs);
+        self.rate_limit_retry_after.record(retry_after_ms, &attrs);
+    }
+
+    /// Record a binding marked unhealthy or deactivated by the heartbeat watchdog
+    pub fn record_agent_unhealthy(&self, provider: &str, action: &str, heartbeat_age_ms: f64) {
+        let attrs = [
+            KeyValue::new("provider", provider.to_string()),
+            KeyValue::new("action", action.to_string()),
+        ];
+        self.agent_unhealthy.add(1, &attrs);
+        self.agent_heartbeat_age.record(heartbeat_age_ms, &attrs);
+    }
+
+    /// Record a supervised bridge exit or handshake rejection
+    pub fn record_bridge_error(&self, adapter: &str, reason: &str) {
+        let attrs = [
+            KeyValue::new("adapter", adapter.to_string()),
+            KeyValue::new("reason", reason.to_string()),
+        ];
+        self.agent_bridge_errors.add(1, &attrs);
+    }
 }
 
 /// Initialize Prism OTLP telemetry
@@ -345,10 +421,222 @@ pub fn shutdown_prism(provider: SdkMeterProvider) -> Result<()> {
     Ok(())
 }
 
+/// Initialize OTLP trace export for the agent request path
+pub fn init_traces(endpoint: Option<String>, service_name: &str) -> Result<TracerProvider> {
+    let endpoint = endpoint.unwrap_or_else(|| "http://localhost:4318".to_string());
+
+    info!("Initializing Prism OTLP traces: endpoint={}", endpoint);
+
+    let export_config = opentelemetry_otlp::ExportConfig {
+        endpoint: Some(endpoint),
+        timeout: Duration::from_secs(10),
+        ..Default::default()
+    };
+
+    let exporter = opentelemetry_otlp::SpanExporter::builder()
+        .with_http()
+        .with_export_config(export_config)
+        .build()
+        .context("building OTLP span exporter")?;
+
+    let resource = Resource::new(vec![
+        KeyValue::new("service.name", service_name.to_string()),
+        KeyValue::new("service.version", env!("CARGO_PKG_VERSION")),
+    ]);
+
+    let provider = TracerProvider::builder()
+        .with_batch_exporter(exporter, opentelemetry_sdk::runtime::Tokio)
+        .with_resource(resource)
+        .build();
+
+    global::set_tracer_provider(provider.clone());
+
+    Ok(provider)
+}
+
+/// Flush pending spans and stop the trace exporter
+pub fn shutdown_traces(provider: TracerProvider) -> Result<()> {
+    info!("Shutting down Prism traces");
+    provider
+        .shutdown()
+        .context("shutting down tracer provider")?;
+    Ok(())
+}
+
+/// Parent context carried by a W3C `traceparent`/`tracestate` pair.
+///
+/// Extraction does not depend on an exporter being configured, so agent
+/// trace ids reach Continuum even when spans are not exported.
+pub fn remote_context(carrier: &dyn Extractor) -> OtelContext {
+    TraceContextPropagator::new().extract_with_context(&OtelContext::new(), carrier)
+}
+
+/// Start a span under `parent` and return the context that carries it.
+pub fn start_span(
+    name: impl Into<Cow<'static, str>>,
+    kind: SpanKind,
+    parent: &OtelContext,
+    attributes: Vec<KeyValue>,
+) -> OtelContext {
+    let tracer = global::tracer(TRACER_NAME);
+    let span = tracer
+        .span_builder(name)
+        .with_kind(kind)
+        .with_attributes(attributes)
+        .start_with_context(&tracer, parent);
+    parent.with_span(span)
+}
+
+/// Run `fut` inside a child span of the current context.
+pub async fn in_span<T>(
+    name: &'static str,
+    attributes: Vec<KeyValue>,
+    fut: impl Future<Output = T>,
+) -> T {
+    let cx = start_span(
+        name,
+        SpanKind::Internal,
+        &OtelContext::current(),
+        attributes,
+    );
+    fut.with_context(cx).await
+}
+
+/// Enter a child span of the current context for synchronous work; the span
+/// ends when the guard drops. Never hold the guard across an `.await`.
+pub fn enter_span(name: &'static str, attributes: Vec<KeyValue>) -> ContextGuard {
+    start_span(
+        name,
+        SpanKind::Internal,
+        &OtelContext::current(),
+        attributes,
+    )
+    .attach()
+}
+
+/// Attach an attribute to the current span.
+pub fn annotate_span(attribute: KeyValue) {
+    OtelContext::current().span().set_attribute(attribute);
+}
+
+/// Mark the current span as failed.
+pub fn record_span_error(message: impl Into<Cow<'static, str>>) {
+    OtelContext::current()
+        .span()
+        .set_status(Status::error(message));
+}
+
+/// Trace and span id of the current span, if it belongs to a valid trace.
+pub fn current_trace_ids() -> Option<(String, String)> {
+    let cx = OtelContext::current();
+    let span = cx.span();
+    let span_context = span.span_context();
+    span_context.is_valid().then(|| {
+        (
+            span_context.trace_id().to_string(),
+            span_context.span_id().to_string(),
+        )
+    })
+}
+
+/// `traceparent` carrier over HTTP headers.
+pub struct HeaderCarrier<'a>(pub &'a axum::http::HeaderMap);
+
+impl Extractor for HeaderCarrier<'_> {
+    fn get(&self, key: &str) -> Option<&str> {
+        self.0.get(key).and_then(|value| value.to_str().ok())
+    }
+
+    fn keys(&self) -> Vec<&str> {
+        self.0.keys().map(|key| key.as_str()).collect()
+    }
+}
+
+/// `traceparent` carrier over gRPC request metadata.
+pub struct MetadataCarrier<'a>(pub &'a tonic::metadata::MetadataMap);
+
+impl Extractor for MetadataCarrier<'_> {
+    fn get(&self, key: &str) -> Option<&str> {
+        self.0.get(key).and_then(|value| value.to_str().ok())
+    }
+
+    fn keys(&self) -> Vec<&str> {
+        self.0
+            .keys()
+            .filter_map(|key| match key {
+                tonic::metadata::KeyRef::Ascii(key) => Some(key.as_str()),
+                tonic::metadata::KeyRef::Binary(_) => None,
+            })
+            .collect()
+    }
+}
+
+/// `traceparent` carrier over a JSON-RPC `params._meta` object.
+pub struct JsonCarrier<'a>(pub &'a serde_json::Map<String, serde_json::Value>);
+
+impl Extractor for JsonCarrier<'_> {
+    fn get(&self, key: &str) -> Option<&str> {
+        self.0.get(key).and_then(serde_json::Value::as_str)
+    }
+
+    fn keys(&self) -> Vec<&str> {
+        self.0.keys().map(String::as_str).collect()
+    }
+}
+
+/// In-process stand-in for an OTLP collector.
+#[cfg(test)]
+pub(crate) mod testing {
+    use futures::future::BoxFuture;
+    use opentelemetry::global;
+    use opentelemetry_sdk::export::trace::{ExportResult, SpanData, SpanExporter};
+    use opentelemetry_sdk::trace::TracerProvider;
+    use std::sync::{Arc, Mutex, OnceLock};
+
+    #[derive(Clone, Debug, Default)]
+    pub struct SpanCollector {
+        spans: Arc<Mutex<Vec<SpanData>>>,
+    }
+
+    impl SpanExporter for SpanCollector {
+        fn export(&mut self, batch: Vec<SpanData>) -> BoxFuture<'static, ExportResult> {
+            self.spans.lock().unwrap().extend(batch);
+            Box::pin(std::future::ready(Ok(())))
+        }
+    }
+
+    impl SpanCollector {
+        /// Collector installed as the global tracer provider. Tests share it,
+        /// so assertions should filter by trace id.
+        pub fn global() -> &'static SpanCollector {
+            static COLLECTOR: OnceLock<SpanCollector> = OnceLock::new();
+            COLLECTOR.get_or_init(|| {
+                let collector = SpanCollector::default();
+                let provider = TracerProvider::builder()
+                    .with_simple_exporter(collector.clone())
+                    .build();
+                global::set_tracer_provider(provider);
+                collector
+            })
+        }
+
+        pub fn spans_in_trace(&self, trace_id: &str) -> Vec<SpanData> {
+            self.spans
+                .lock()
+                .unwrap()
+                .iter()
+                .filter(|span| span.span_context.trace_id().to_string() == trace_id)
+                .cloned()
+                .collect()
+        }
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
     use opentelemetry::metrics::MeterProvider;
+    use opentelemetry::trace::FutureExt;
 
     #[test]
     fn test_metrics_initialization() {
@@ -362,5 +650,60 @@ mod tests {
         metrics.record_policy_evaluation(true);
         metrics.record_snapshot_created(100);
         metrics.record_events_restored(50);
+        metrics.record_rate_limited("agent.exec", "persona", 250.0);
+        metrics.record_agent_unhealthy("openai", "unhealthy", 45_000.0);
+        metrics.record_bridge_error("claude", "handshake");
+    }
+
+    const TRACE_ID: &str = "4bf92f3577b34da6a3ce929d0e0e4736";
+
+    fn traceparent_headers() -> axum::http::HeaderMap {
+        let mut headers = axum::http::HeaderMap::new();
+        headers.insert(
+            "traceparent",
+            format!("00-{TRACE_ID}-00f067aa0ba902b7-01")
+                .parse()
+                .unwrap(),
+        );
+        headers
+    }
+
+    #[tokio::test]
+    async fn spans_inherit_remote_trace_id() {
+        let collector = testing::SpanCollector::global();
+        let parent = remote_context(&HeaderCarrier(&traceparent_headers()));
+        let cx = start_span("test.request", SpanKind::Server, &parent, Vec::new());
+
+        let ids = async { in_span("test.child", Vec::new(), async { current_trace_ids() }).await }
+            .with_context(cx)
+            .await;
+
+        let (trace_id, _) = ids.expect("trace ids inside span");
+        assert_eq!(trace_id, TRACE_ID);
+        let names: Vec<_> = collector
+            .spans_in_trace(TRACE_ID)
+            .into_iter()
+            .map(|span| span.name.into_owned())
+            .collect();
+        assert!(names.contains(&"test.child".to_string()), "{names:?}");
+    }
+
+    #[test]
+    fn missing_traceparent_yields_no_trace_ids() {
+        let parent = remote_context(&HeaderCarrier(&axum::http::HeaderMap::new()));
+        let _guard = parent.attach();
+        assert!(current_trace_ids().is_none());
+    }
+
+    #[test]
+    fn json_carrier_reads_meta_traceparent() {
+        let meta = serde_json::json!({
+            "traceparent": format!("00-{TRACE_ID}-00f067aa0ba902b7-01")
+        });
+        let parent = remote_context(&JsonCarrier(meta.as_object().unwrap()));
+        assert_eq!(
+            parent.span().span_context().trace_id().to_string(),
+            TRACE_ID
+        );
     }
 }
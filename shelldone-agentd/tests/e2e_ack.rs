@@ -26,6 +26,7 @@ async fn e2e_agent_exec_echo_command() {
         state_dir: state_dir.clone(),
         policy_path: None,
         otlp_endpoint: None,
+        agent_manifest: None,
     };
 
     let server_handle = tokio::spawn(async move {
@@ -90,6 +91,7 @@ async fn e2e_sigma_guard_journal_event() {
         state_dir: state_dir.clone(),
         policy_path: None,
         otlp_endpoint: None,
+        agent_manifest: None,
     };
 
     let server_handle = tokio::spawn(async move {
@@ -175,6 +177,7 @@ allow if {
         state_dir: state_dir.clone(),
         policy_path: Some(policy_path),
         otlp_endpoint: None,
+        agent_manifest: None,
     };
 
     let server_handle = tokio::spawn(async move {
@@ -245,6 +248,7 @@ async fn e2e_agent_undo_snapshot_restore() {
         state_dir: state_dir.clone(),
         policy_path: None,
         otlp_endpoint: None,
+        agent_manifest: None,
     };
 
     let server_handle = tokio::spawn(async move {
@@ -296,6 +300,7 @@ async fn e2e_healthz_endpoint() {
         state_dir: temp.path().to_path_buf(),
         policy_path: None,
         otlp_endpoint: None,
+        agent_manifest: None,
     };
 
     let server_handle = tokio::spawn(async move {
@@ -335,6 +340,7 @@ async fn e2e_concurrent_requests() {
         state_dir: temp.path().to_path_buf(),
         policy_path: None,
         otlp_endpoint: None,
+        agent_manifest: None,
     };
 
     let server_handle = tokio::spawn(async move {
@@ -54,6 +54,7 @@ async fn grpc_mutual_tls_allows_authorized_client() {
         state_dir: state_dir.clone(),
         policy_path: None,
         otlp_endpoint: None,
+        agent_manifest: None,
     };
 
     let server_handle = tokio::spawn(async move {
@@ -99,6 +100,7 @@ async fn grpc_mutual_tls_blocks_client_without_certificate() {
         state_dir: state_dir.clone(),
         policy_path: None,
         otlp_endpoint: None,
+        agent_manifest: None,
     };
 
     let server_handle = tokio::spawn(async move {
@@ -165,6 +167,7 @@ async fn tls_hot_reload_accepts_rotated_certificate() {
         state_dir: state_dir.clone(),
         policy_path: None,
         otlp_endpoint: None,
+        agent_manifest: None,
     };
 
     let server_handle = tokio::spawn(async move {
@@ -306,6 +309,7 @@ async fn exercise_bridge(mut client: McpBridgeClient<Channel>) {
                 "cmd": "echo grpc mTLS"
             })
             .to_string(),
+            call_id: String::new(),
         }))
         .await
         .unwrap()
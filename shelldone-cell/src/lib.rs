@@ -14,6 +14,9 @@ use shelldone_char_props::emoji_variation::WCWIDTH_TABLE;
 use shelldone_char_props::widechar_width::WcWidth;
 use shelldone_dynamic::{FromDynamic, ToDynamic};
 pub use shelldone_escape_parser::osc::Hyperlink;
+pub use shelldone_escape_parser::text_sizing::{
+    TextSize, TextSizeHorizontalAlign, TextSizeVerticalAlign,
+};
 
 extern crate alloc;
 use crate::alloc::string::ToString;
@@ -95,6 +98,27 @@ struct FatAttributes {
     underline_color: ColorAttribute,
     foreground: ColorAttribute,
     background: ColorAttribute,
+    /// Set for cells that are part of a block of scaled text
+    text_size: Option<TextSizeCell>,
+}
+
+/// Records that a cell belongs to a block of text that was sized
+/// using the kitty text sizing protocol.  The block spans `size.scale`
+/// rows; the cell in the first row holds the text and is as wide as
+/// the block, while the cells below it are blank placeholders.
+#[cfg_attr(feature = "use_serde", derive(Serialize, Deserialize))]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct TextSizeCell {
+    pub size: TextSize,
+    /// The row within the block; 0 for the row that holds the text
+    pub row: u8,
+}
+
+impl TextSizeCell {
+    /// Returns true if this cell holds the text of the block
+    pub fn is_origin(&self) -> bool {
+        self.row == 0
+    }
 }
 
 impl FatAttributes {
@@ -109,6 +133,7 @@ impl FatAttributes {
         self.underline_color.hash(hasher);
         self.foreground.hash(hasher);
         self.background.hash(hasher);
+        self.text_size.hash(hasher);
     }
 }
 
@@ -315,6 +340,7 @@ impl CellAttributes {
                 underline_color: ColorAttribute::Default,
                 foreground: ColorAttribute::Default,
                 background: ColorAttribute::Default,
+                text_size: None,
             }));
         }
     }
@@ -334,6 +360,7 @@ impl CellAttributes {
                     && fat.underline_color == ColorAttribute::Default
                     && fat.foreground == ColorAttribute::Default
                     && fat.background == ColorAttribute::Default
+                    && fat.text_size.is_none()
             })
             .unwrap_or(false);
         if deallocate {
@@ -411,6 +438,18 @@ impl CellAttributes {
         }
     }
 
+    /// Mark the cell as being part of a block of scaled text
+    pub fn set_text_size(&mut self, text_size: Option<TextSizeCell>) -> &mut Self {
+        if text_size.is_none() && self.fat.is_none() {
+            self
+        } else {
+            self.allocate_fat_attributes();
+            self.fat.as_mut().unwrap().text_size = text_size;
+            self.deallocate_fat_attributes_if_none();
+            self
+        }
+    }
+
     /// Clone the attributes, but exclude fancy extras such
     /// as hyperlinks or future sprite things
     pub fn clone_sgr_only(&self) -> Self {
@@ -468,6 +507,10 @@ impl CellAttributes {
         Some(fat.image.clone())
     }
 
+    pub fn text_size(&self) -> Option<&TextSizeCell> {
+        self.fat.as_ref().and_then(|fat| fat.text_size.as_ref())
+    }
+
     pub fn underline_color(&self) -> ColorAttribute {
         self.fat
             .as_ref()
@@ -1367,12 +1367,17 @@ impl Client {
         MovePaneToNewTab,
         MovePaneToNewTabResponse
     );
+    rpc!(swap_panes, SwapPanes, UnitResponse);
+    rpc!(move_tab_to_window, MoveTabToWindow, MoveTabToWindowResponse);
+    rpc!(ssh_port_forwards, SshPortForwards, SshPortForwardsResponse);
     rpc!(write_to_pane, WriteToPane, UnitResponse);
     rpc!(send_paste, SendPaste, UnitResponse);
     rpc!(key_down, SendKeyDown, UnitResponse);
     rpc!(mouse_event, SendMouseEvent, UnitResponse);
     rpc!(resize, Resize, UnitResponse);
     rpc!(set_zoomed, SetPaneZoomed, UnitResponse);
+    rpc!(set_pane_monitors, SetPaneMonitors, UnitResponse);
+    rpc!(set_pane_recording, SetPaneRecording, UnitResponse);
     rpc!(activate_pane_direction, ActivatePaneDirection, UnitResponse);
     rpc!(
         get_pane_render_changes,
@@ -1409,4 +1414,8 @@ impl Client {
         GetPaneDirectionResponse
     );
     rpc!(adjust_pane_size, AdjustPaneSize, UnitResponse);
+    rpc!(create_share, CreateShare, CreateShareResponse);
+    rpc!(list_shares, ListShares = (), ListSharesResponse);
+    rpc!(update_share, UpdateShare, ListSharesResponse);
+    rpc!(set_client_viewport, SetClientViewport, UnitResponse);
 }
@@ -5,6 +5,7 @@ use async_trait::async_trait;
 use codec::{ListPanesResponse, SpawnV2, SplitPane};
 use config::keyassignment::SpawnTabDomain;
 use config::{SshDomain, TlsDomainClient, UnixDomain};
+use mux::client::ClientViewport;
 use mux::connui::{ConnectionUI, ConnectionUIParams};
 use mux::domain::{alloc_domain_id, Domain, DomainId, DomainState, SplitSource};
 use mux::pane::{Pane, PaneId};
@@ -26,6 +27,7 @@ pub struct ClientInner {
     remote_to_local_tab: Mutex<HashMap<TabId, TabId>>,
     remote_to_local_pane: Mutex<HashMap<PaneId, PaneId>>,
     pub focused_remote_pane_id: Mutex<Option<PaneId>>,
+    pub remote_viewport: Mutex<Option<ClientViewport>>,
 }
 
 impl ClientInner {
@@ -246,6 +248,7 @@ impl ClientInner {
             remote_to_local_tab: Mutex::new(HashMap::new()),
             remote_to_local_pane: Mutex::new(HashMap::new()),
             focused_remote_pane_id: Mutex::new(None),
+            remote_viewport: Mutex::new(None),
         }
     }
 }
@@ -814,6 +817,84 @@ impl Domain for ClientDomain {
         Ok(Some((tab, local_win_id)))
     }
 
+    /// Forward the request to the remote and resync the changed structure
+    async fn swap_panes(&self, pane_id: PaneId, other_pane_id: PaneId) -> anyhow::Result<bool> {
+        let inner = self
+            .inner()
+            .ok_or_else(|| anyhow!("domain is not attached"))?;
+
+        let mux = Mux::get();
+        let remote_pane_id = |pane_id: PaneId| -> anyhow::Result<PaneId> {
+            let local_pane = mux
+                .get_pane(pane_id)
+                .ok_or_else(|| anyhow!("pane_id {} is invalid", pane_id))?;
+            let pane = local_pane
+                .downcast_ref::<ClientPane>()
+                .ok_or_else(|| anyhow!("pane_id {} is not a ClientPane", pane_id))?;
+            if local_pane.domain_id() != self.local_domain_id {
+                bail!("pane_id {} belongs to a different domain", pane_id);
+            }
+            Ok(pane.remote_pane_id)
+        };
+
+        inner
+            .client
+            .swap_panes(codec::SwapPanes {
+                pane_id: remote_pane_id(pane_id)?,
+                other_pane_id: remote_pane_id(other_pane_id)?,
+            })
+            .await?;
+
+        self.resync().await?;
+        Ok(true)
+    }
+
+    /// Forward the request to the remote; we need to translate the local ids
+    /// to those that match the remote for the request, resync the changed
+    /// structure, and then translate the results back to local
+    async fn move_tab_to_window(
+        &self,
+        tab_id: TabId,
+        window_id: Option<WindowId>,
+        workspace_for_new_window: Option<String>,
+    ) -> anyhow::Result<Option<WindowId>> {
+        let inner = self
+            .inner()
+            .ok_or_else(|| anyhow!("domain is not attached"))?;
+
+        let remote_tab_id = self
+            .local_to_remote_tab_id(tab_id)
+            .ok_or_else(|| anyhow!("tab {} is not known to the remote", tab_id))?;
+        let remote_window_id = match window_id {
+            Some(local_window) => Some(self.local_to_remote_window_id(local_window).ok_or_else(
+                || anyhow!("window {} has no counterpart on the remote", local_window),
+            )?),
+            None => None,
+        };
+
+        let result = inner
+            .client
+            .move_tab_to_window(codec::MoveTabToWindow {
+                tab_id: remote_tab_id,
+                window_id: remote_window_id,
+                workspace_for_new_window,
+            })
+            .await?;
+
+        self.resync().await?;
+
+        let local_win_id = self
+            .remote_to_local_window_id(result.window_id)
+            .ok_or_else(|| {
+                anyhow!(
+                    "remote window {} didn't resolve after resync",
+                    result.window_id
+                )
+            })?;
+
+        Ok(Some(local_win_id))
+    }
+
     async fn spawn(
         &self,
         size: TerminalSize,
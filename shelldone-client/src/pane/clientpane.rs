@@ -6,11 +6,14 @@ use async_trait::async_trait;
 use codec::*;
 use config::configuration;
 use config::keyassignment::ScrollbackEraseMode;
+use mux::client::{ClientViewport, GuestPresence};
 use mux::domain::DomainId;
+use mux::monitor::{self, MonitorAlert, PaneMonitors};
 use mux::pane::{
     alloc_pane_id, CachePolicy, CloseReason, ForEachPaneLogicalLine, LogicalLine, Pane, PaneId,
     Pattern, SearchResult, WithPaneLines,
 };
+use mux::recording::{self, RecordingOptions, RecordingSink};
 use mux::renderable::{RenderableDimensions, StableCursorPosition};
 use mux::tab::TabId;
 use mux::{Mux, MuxNotification};
@@ -20,8 +23,8 @@ use ratelim::RateLimiter;
 use shelldone_dynamic::Value;
 use shelldone_term::color::ColorPalette;
 use shelldone_term::{
-    Alert, Clipboard, KeyCode, KeyModifiers, Line, MouseEvent, Progress, StableRowIndex,
-    TerminalConfiguration, TerminalSize,
+    Alert, Clipboard, CommandBlock, KeyCode, KeyModifiers, Line, MouseEvent, Progress,
+    StableRowIndex, TerminalConfiguration, TerminalSize,
 };
 use std::cell::RefCell;
 use std::collections::{BTreeMap, HashMap};
@@ -49,6 +52,8 @@ pub struct ClientPane {
     config: Mutex<Option<Arc<dyn TerminalConfiguration>>>,
     unseen_output: Mutex<bool>,
     progress: Mutex<Progress>,
+    monitors: Mutex<PaneMonitors>,
+    guests: Mutex<Vec<GuestPresence>>,
 }
 
 impl ClientPane {
@@ -131,14 +136,32 @@ impl ClientPane {
             user_vars: Mutex::new(HashMap::new()),
             config: Mutex::new(None),
             progress: Mutex::new(Progress::default()),
+            monitors: Mutex::new(PaneMonitors::from_config()),
+            guests: Mutex::new(vec![]),
         }
     }
 
+    fn set_remote_recording(&self, options: Option<RecordingOptions>) {
+        let client = Arc::clone(&self.client);
+        let remote_pane_id = self.remote_pane_id;
+        promise::spawn::spawn(async move {
+            client
+                .client
+                .set_pane_recording(SetPaneRecording {
+                    pane_id: remote_pane_id,
+                    options,
+                })
+                .await
+        })
+        .detach();
+    }
+
     pub async fn process_unilateral(&self, pdu: Pdu) -> anyhow::Result<()> {
         match pdu {
             Pdu::GetPaneRenderChangesResponse(payload) => {
                 let mut delta = *payload;
                 *self.mouse_grabbed.lock() = delta.mouse_grabbed;
+                *self.guests.lock() = std::mem::take(&mut delta.guests);
 
                 let bonus_lines = std::mem::take(&mut delta.bonus_lines);
                 let client = { Arc::clone(&self.renderable.lock().inner.borrow().client) };
@@ -205,6 +228,13 @@ impl ClientPane {
                             alert: Alert::Progress(progress.clone()),
                         });
                     }
+                    Alert::Activity | Alert::Silence(_) => {
+                        if !mux.is_pane_focused(self.local_pane_id) {
+                            if let Some(kind) = MonitorAlert::from_alert(&alert) {
+                                monitor::record_alert(self.local_pane_id, kind);
+                            }
+                        }
+                    }
                     _ => {}
                 }
                 mux.notify(MuxNotification::Alert {
@@ -212,6 +242,13 @@ impl ClientPane {
                     alert,
                 });
             }
+            Pdu::PaneRecordingOutput(PaneRecordingOutput { events, .. }) => {
+                if !recording::deliver_events(self.local_pane_id, events) {
+                    // The recording was stopped here, perhaps because
+                    // the sink failed; there is no need for more events
+                    self.set_remote_recording(None);
+                }
+            }
             Pdu::PaneRemoved(PaneRemoved { pane_id }) => {
                 log::trace!("remote pane {} has been removed", pane_id);
                 self.renderable.lock().inner.borrow_mut().dead = true;
@@ -277,6 +314,10 @@ impl Pane for ClientPane {
             Value::String("since_last_response_ms".to_string()),
             Value::U64(inner.last_recv_time.elapsed().as_millis() as u64),
         );
+        map.insert(
+            Value::String("password_input".to_string()),
+            Value::Bool(inner.password_input),
+        );
 
         Value::Object(map.into())
     }
@@ -338,11 +379,12 @@ impl Pane for ClientPane {
     fn send_paste(&self, text: &str) -> anyhow::Result<()> {
         let client = Arc::clone(&self.client);
         let remote_pane_id = self.remote_pane_id;
-        self.renderable
+        let input_serial = self
+            .renderable
             .lock()
             .inner
             .borrow_mut()
-            .predict_from_paste(text);
+            .interrupt_prediction();
 
         let data = text.to_owned();
         promise::spawn::spawn(async move {
@@ -351,6 +393,7 @@ impl Pane for ClientPane {
                 .send_paste(SendPaste {
                     pane_id: remote_pane_id,
                     data,
+                    input_serial,
                 })
                 .await
         })
@@ -370,6 +413,46 @@ impl Pane for ClientPane {
         })
     }
 
+    fn get_monitors(&self) -> PaneMonitors {
+        *self.monitors.lock()
+    }
+
+    /// The monitors run in the remote mux, where the output is produced
+    fn set_monitors(&self, monitors: PaneMonitors) {
+        *self.monitors.lock() = monitors;
+        let client = Arc::clone(&self.client);
+        let remote_pane_id = self.remote_pane_id;
+        promise::spawn::spawn(async move {
+            client
+                .client
+                .set_pane_monitors(SetPaneMonitors {
+                    pane_id: remote_pane_id,
+                    monitors,
+                })
+                .await
+        })
+        .detach();
+    }
+
+    /// The recording is made in the remote mux, which streams the
+    /// events to us
+    fn start_recording(&self, options: RecordingOptions, sink: RecordingSink) {
+        recording::start_recording(self.local_pane_id, options, sink);
+        self.set_remote_recording(Some(options));
+    }
+
+    fn stop_recording(&self) -> bool {
+        let stopped = recording::stop_recording(self.local_pane_id);
+        if stopped {
+            self.set_remote_recording(None);
+        }
+        stopped
+    }
+
+    fn is_recording(&self) -> bool {
+        recording::is_recording(self.local_pane_id)
+    }
+
     fn set_zoomed(&self, zoomed: bool) {
         let render = self.renderable.lock();
         let mut inner = render.inner.borrow_mut();
@@ -455,12 +538,16 @@ impl Pane for ClientPane {
 
     fn key_down(&self, key: KeyCode, mods: KeyModifiers) -> anyhow::Result<()> {
         let input_serial;
+        let predicted;
         {
             let renderable = self.renderable.lock();
             let mut inner = renderable.inner.borrow_mut();
             inner.input_serial = InputSerial::now();
             input_serial = inner.input_serial;
-            inner.predict_from_key_event(key, mods);
+            predicted = inner.predict_from_key_event(key, mods);
+        }
+        if predicted {
+            Mux::get().notify(MuxNotification::PaneOutput(self.local_pane_id));
         }
         let client = Arc::clone(&self.client);
         let remote_pane_id = self.remote_pane_id;
@@ -552,8 +639,11 @@ impl Pane for ClientPane {
     }
 
     fn is_alt_screen_active(&self) -> bool {
-        // FIXME: retrieve this from the remote
-        false
+        self.renderable.lock().inner.borrow().alt_screen_active
+    }
+
+    fn get_command_blocks(&self) -> Vec<CommandBlock> {
+        self.renderable.lock().inner.borrow().command_blocks.clone()
     }
 
     fn get_current_working_dir(&self, _policy: CachePolicy) -> Option<Url> {
@@ -600,6 +690,38 @@ impl Pane for ClientPane {
         }
     }
 
+    fn advise_viewport(
+        &self,
+        top: Option<StableRowIndex>,
+        pointer: Option<(usize, StableRowIndex)>,
+    ) {
+        let viewport = ClientViewport {
+            pane_id: self.remote_pane_id,
+            top,
+            pointer,
+        };
+        let mut remote_viewport = self.client.remote_viewport.lock().unwrap();
+        if *remote_viewport != Some(viewport) {
+            remote_viewport.replace(viewport);
+            let client = Arc::clone(&self.client);
+            promise::spawn::spawn(async move {
+                client
+                    .client
+                    .set_client_viewport(SetClientViewport {
+                        viewport: Some(viewport),
+                    })
+                    .await
+            })
+            .detach();
+        }
+    }
+
+    /// The guests are tracked by the remote mux, which sends them
+    /// along with the changes to the pane
+    fn get_guests(&self) -> Vec<GuestPresence> {
+        self.guests.lock().clone()
+    }
+
     fn has_unseen_output(&self) -> bool {
         *self.unseen_output.lock()
     }
@@ -0,0 +1,610 @@
+//! Speculative local echo for panes whose server is far away.
+//!
+//! When the round trip to the server is slow, waiting for the echo
+//! of each keystroke makes typing feel sluggish.  The `Predictor`
+//! guesses what the echo will look like and overlays that guess on
+//! the lines that we received from the server, underlined so that it
+//! is distinguishable from confirmed content.  Each guess is checked
+//! against the lines that the server subsequently sends; it is
+//! dropped once the server shows the same thing, and if the server
+//! has processed the key but doesn't show the guess within a grace
+//! period, all of the outstanding guesses are rolled back.
+//!
+//! The approach is modelled on mosh: after a wrong guess, or after a
+//! key whose effect we cannot guess (Enter, cursor keys and so on),
+//! new guesses are tentative and are not shown until one of them
+//! has been confirmed by the server.
+use codec::InputSerial;
+use mux::renderable::StableCursorPosition;
+use shelldone_term::{KeyCode, KeyModifiers, Line, StableRowIndex};
+use std::collections::VecDeque;
+use std::time::{Duration, Instant};
+use termwiz::cell::{Cell, CellAttributes, Underline};
+use termwiz::surface::SEQ_ZERO;
+
+/// The minimum amount of time that we allow for the echo to arrive
+/// after the server has acknowledged the key that produces it.
+/// The echo is generated by the remote application, so it is
+/// typically sent a little while after the acknowledgement.
+pub const MIN_CONFIRM_GRACE: Duration = Duration::from_millis(250);
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Echo {
+    /// The character is expected to appear at the position
+    Char(char),
+    /// The cell is expected to become blank
+    Erase,
+}
+
+#[derive(Debug)]
+struct Prediction {
+    serial: InputSerial,
+    row: StableRowIndex,
+    col: usize,
+    echo: Echo,
+    /// Where the cursor will be once the server has echoed this
+    cursor_x: usize,
+    /// The epoch in which the prediction was made; it is only shown
+    /// once a prediction from the same epoch has been confirmed
+    epoch: usize,
+    /// When we learned that the server had processed the key
+    acked_at: Option<Instant>,
+}
+
+impl Prediction {
+    fn is_confirmed_by(&self, line: &Line) -> bool {
+        let cell = line.get_cell(self.col);
+        match self.echo {
+            Echo::Char(c) => {
+                let mut buf = [0u8; 4];
+                let expected: &str = c.encode_utf8(&mut buf);
+                cell.map(|cell| cell.str() == expected).unwrap_or(false)
+            }
+            // Cells beyond the end of the line are blank
+            Echo::Erase => cell.map(|cell| cell.str() == " ").unwrap_or(true),
+        }
+    }
+
+    fn cell(&self) -> Cell {
+        let attrs = CellAttributes::default()
+            .set_underline(Underline::Double)
+            .clone();
+        match self.echo {
+            Echo::Char(c) => Cell::new(c, attrs),
+            Echo::Erase => Cell::new(' ', attrs),
+        }
+    }
+}
+
+pub struct Predictor {
+    pending: VecDeque<Prediction>,
+    epoch: usize,
+    confirmed_epoch: usize,
+    /// The most recent input serial that the server has processed
+    acked: InputSerial,
+    /// Input that we couldn't predict; no predictions are made
+    /// until the server has processed it
+    barrier: InputSerial,
+    /// Set when the server tells us that the alt screen is active
+    /// or that a password is being read
+    suppressed: bool,
+    mispredictions: usize,
+}
+
+impl Predictor {
+    pub fn new() -> Self {
+        Self {
+            pending: VecDeque::new(),
+            epoch: 0,
+            confirmed_epoch: 0,
+            acked: InputSerial::empty(),
+            barrier: InputSerial::empty(),
+            suppressed: false,
+            mispredictions: 0,
+        }
+    }
+
+    fn is_displayed(&self, pred: &Prediction) -> bool {
+        pred.epoch <= self.confirmed_epoch
+    }
+
+    /// Returns true if any predictions are currently being shown
+    pub fn has_displayed(&self) -> bool {
+        self.pending.iter().any(|pred| self.is_displayed(pred))
+    }
+
+    /// Subsequent predictions are not shown until the server has
+    /// confirmed one of them
+    fn become_tentative(&mut self) {
+        self.epoch += 1;
+    }
+
+    /// Record input that we can't predict.  Predictions are paused
+    /// until the server has processed it, as we don't know where the
+    /// cursor will end up.
+    pub fn interrupt(&mut self, serial: InputSerial) {
+        self.barrier = self.barrier.max(serial);
+        self.become_tentative();
+    }
+
+    /// Discards all predictions, returning true if any of them
+    /// were being shown
+    pub fn clear(&mut self) -> bool {
+        let displayed = self.has_displayed();
+        self.pending.clear();
+        displayed
+    }
+
+    /// Local echo makes no sense for full screen applications, and
+    /// must not reveal passwords.  Returns true if predictions that
+    /// were being shown have been removed.
+    pub fn set_suppressed(&mut self, suppressed: bool) -> bool {
+        self.suppressed = suppressed;
+        if suppressed {
+            self.become_tentative();
+            self.clear()
+        } else {
+            false
+        }
+    }
+
+    /// Where the cursor will be once the server has caught up with
+    /// the predictions that are being shown
+    pub fn cursor_position(&self, server: StableCursorPosition) -> StableCursorPosition {
+        match self.pending.back() {
+            Some(pred) if self.is_displayed(pred) => StableCursorPosition {
+                x: pred.cursor_x,
+                y: pred.row,
+                ..server
+            },
+            _ => server,
+        }
+    }
+
+    /// The position from which the next prediction follows on
+    fn next_position(&self, server: &StableCursorPosition) -> (StableRowIndex, usize) {
+        match self.pending.back() {
+            Some(pred) if pred.epoch == self.epoch => (pred.row, pred.cursor_x),
+            _ => (server.y, server.x),
+        }
+    }
+
+    /// Predicts the echo of a key press.  `cursor` is the position
+    /// of the cursor as last reported by the server and `cols` is the
+    /// width of the pane.  Returns true if the prediction is shown.
+    pub fn predict_key(
+        &mut self,
+        key: KeyCode,
+        mods: KeyModifiers,
+        serial: InputSerial,
+        cursor: &StableCursorPosition,
+        cols: usize,
+    ) -> bool {
+        if self.suppressed {
+            return false;
+        }
+        if self.acked < self.barrier {
+            // Still waiting to learn the outcome of input that we
+            // couldn't predict; anything that we might guess now
+            // is likely to be wrong
+            return false;
+        }
+
+        let (row, col) = self.next_position(cursor);
+        let (echo, pred_col, cursor_x) = match key {
+            KeyCode::Char(c)
+                if (mods == KeyModifiers::NONE || mods == KeyModifiers::SHIFT)
+                    && !c.is_control() =>
+            {
+                let width = Cell::new(c, CellAttributes::default()).width();
+                if col + width > cols {
+                    // How the line wraps is up to the application
+                    self.interrupt(serial);
+                    return false;
+                }
+                (Echo::Char(c), col, col + width)
+            }
+            KeyCode::Backspace if mods == KeyModifiers::NONE => match self.pending.back() {
+                // We can only be confident about erasing something
+                // that we typed ourselves; the application decides
+                // whether its prompt is erasable
+                Some(pred) if pred.epoch == self.epoch && matches!(pred.echo, Echo::Char(_)) => {
+                    (Echo::Erase, pred.col, pred.col)
+                }
+                _ => {
+                    self.interrupt(serial);
+                    return false;
+                }
+            },
+            _ => {
+                self.interrupt(serial);
+                return false;
+            }
+        };
+
+        let pred = Prediction {
+            serial,
+            row,
+            col: pred_col,
+            echo,
+            cursor_x,
+            epoch: self.epoch,
+            acked_at: None,
+        };
+        let displayed = self.is_displayed(&pred);
+        self.pending.push_back(pred);
+        displayed
+    }
+
+    /// Record that the server has processed the input up to and
+    /// including `serial`
+    pub fn acknowledge(&mut self, serial: InputSerial, now: Instant) {
+        self.acked = self.acked.max(serial);
+        let acked = self.acked;
+        for pred in self.pending.iter_mut() {
+            if pred.serial <= acked && pred.acked_at.is_none() {
+                pred.acked_at.replace(now);
+            }
+        }
+    }
+
+    /// The rows that hold predictions which need checking against
+    /// the lines that the server sends
+    pub fn pending_rows(&self) -> Vec<StableRowIndex> {
+        let mut rows: Vec<StableRowIndex> = self.pending.iter().map(|pred| pred.row).collect();
+        rows.sort_unstable();
+        rows.dedup();
+        rows
+    }
+
+    /// Compare the predictions for a row with the line that the server
+    /// sent for it.  Predictions for input that the server has processed
+    /// and whose echo is present are confirmed and removed.
+    /// Returns true if any predictions that were being shown were removed.
+    pub fn verify(&mut self, row: StableRowIndex, line: &Line) -> bool {
+        let mut changed = false;
+        let mut confirmed_epoch = self.confirmed_epoch;
+        let acked = self.acked;
+        self.pending.retain(|pred| {
+            if pred.row == row && pred.serial <= acked && pred.is_confirmed_by(line) {
+                if pred.epoch <= confirmed_epoch {
+                    changed = true;
+                }
+                confirmed_epoch = confirmed_epoch.max(pred.epoch);
+                false
+            } else {
+                true
+            }
+        });
+        if confirmed_epoch != self.confirmed_epoch {
+            // Tentative predictions from the newly confirmed epoch
+            // are now shown
+            self.confirmed_epoch = confirmed_epoch;
+            changed = true;
+        }
+        changed
+    }
+
+    /// Roll back all predictions if the echo of any of them hasn't
+    /// shown up within `grace` of the server processing the input.
+    /// Returns true if predictions that were being shown were removed.
+    pub fn expire(&mut self, now: Instant, grace: Duration) -> bool {
+        let mispredicted = self.pending.iter().any(|pred| {
+            pred.acked_at
+                .map(|acked_at| now.saturating_duration_since(acked_at) >= grace)
+                .unwrap_or(false)
+        });
+        if !mispredicted {
+            return false;
+        }
+        self.mispredictions += 1;
+        log::debug!(
+            "local echo mispredicted; rolling back {} predictions ({} mispredictions so far)",
+            self.pending.len(),
+            self.mispredictions
+        );
+        self.become_tentative();
+        self.clear()
+    }
+
+    /// The time at which `expire` should next be called
+    pub fn next_deadline(&self, grace: Duration) -> Option<Instant> {
+        self.pending
+            .iter()
+            .filter_map(|pred| pred.acked_at)
+            .min()
+            .map(|acked_at| acked_at + grace)
+    }
+
+    /// Overlay the predictions that are being shown for `row`
+    pub fn apply_to_line(&self, row: StableRowIndex, line: &mut Line) {
+        for pred in &self.pending {
+            if pred.row == row && self.is_displayed(pred) {
+                line.set_cell(pred.col, pred.cell(), SEQ_ZERO);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use std::time::SystemTime;
+
+    /// What travels between the simulated client and server
+    enum Message {
+        /// A key, on its way to the server
+        Key(KeyCode, InputSerial),
+        /// The server's response to a key, on its way to the client
+        Ack(InputSerial),
+        /// The line and cursor column, pushed by the server after
+        /// the application produced output
+        Screen(Line, usize),
+    }
+
+    /// A latency simulator: a single line shell on the far side of
+    /// a link with a configurable round trip time.  The shell echoes
+    /// printable input and erases on backspace unless echo is disabled.
+    struct Harness {
+        start: Instant,
+        now: Duration,
+        one_way: Duration,
+        /// How long the remote application takes to echo
+        app_delay: Duration,
+        in_flight: Vec<(Duration, Message)>,
+        predictor: Predictor,
+
+        server_line: Line,
+        server_cursor: usize,
+        server_echo: bool,
+
+        client_line: Line,
+        client_cursor: StableCursorPosition,
+    }
+
+    const COLS: usize = 20;
+
+    impl Harness {
+        fn new(rtt_ms: u64) -> Self {
+            let prompt = Line::from_text("$ ", &CellAttributes::default(), SEQ_ZERO, None);
+            Self {
+                start: Instant::now(),
+                now: Duration::ZERO,
+                one_way: Duration::from_millis(rtt_ms / 2),
+                app_delay: Duration::from_millis(5),
+                in_flight: vec![],
+                predictor: Predictor::new(),
+                server_line: prompt.clone(),
+                server_cursor: 2,
+                server_echo: true,
+                client_line: prompt,
+                client_cursor: StableCursorPosition {
+                    x: 2,
+                    ..Default::default()
+                },
+            }
+        }
+
+        fn instant(&self) -> Instant {
+            self.start + self.now
+        }
+
+        fn grace(&self) -> Duration {
+            MIN_CONFIRM_GRACE.max(self.one_way * 2)
+        }
+
+        fn send(&mut self, delay: Duration, msg: Message) {
+            self.in_flight.push((self.now + delay, msg));
+        }
+
+        fn type_key(&mut self, key: KeyCode) {
+            let serial =
+                InputSerial::from(SystemTime::UNIX_EPOCH + Duration::from_secs(1) + self.now);
+            self.predictor
+                .predict_key(key, KeyModifiers::NONE, serial, &self.client_cursor, COLS);
+            self.send(self.one_way, Message::Key(key, serial));
+        }
+
+        fn type_str(&mut self, s: &str, interval_ms: u64) {
+            for c in s.chars() {
+                self.type_key(KeyCode::Char(c));
+                self.advance(interval_ms);
+            }
+        }
+
+        fn server_receive(&mut self, key: KeyCode, serial: InputSerial) {
+            let echo = match key {
+                KeyCode::Char(c) if self.server_echo => {
+                    self.server_line.set_cell(
+                        self.server_cursor,
+                        Cell::new(c, Default::default()),
+                        SEQ_ZERO,
+                    );
+                    self.server_cursor += 1;
+                    true
+                }
+                KeyCode::Backspace if self.server_echo && self.server_cursor > 2 => {
+                    self.server_cursor -= 1;
+                    self.server_line.set_cell(
+                        self.server_cursor,
+                        Cell::new(' ', Default::default()),
+                        SEQ_ZERO,
+                    );
+                    true
+                }
+                _ => false,
+            };
+            self.send(self.one_way, Message::Ack(serial));
+            if echo {
+                let line = self.server_line.clone();
+                self.send(
+                    self.app_delay + self.one_way,
+                    Message::Screen(line, self.server_cursor),
+                );
+            }
+        }
+
+        fn advance(&mut self, ms: u64) {
+            let end = self.now + Duration::from_millis(ms);
+            loop {
+                self.in_flight.sort_by_key(|(at, _)| *at);
+                if self
+                    .in_flight
+                    .first()
+                    .map(|(at, _)| *at > end)
+                    .unwrap_or(true)
+                {
+                    break;
+                }
+                let (at, msg) = self.in_flight.remove(0);
+                self.now = at;
+                let now = self.instant();
+                match msg {
+                    Message::Key(key, serial) => self.server_receive(key, serial),
+                    Message::Ack(serial) => self.predictor.acknowledge(serial, now),
+                    Message::Screen(line, cursor_x) => {
+                        self.predictor.verify(0, &line);
+                        self.client_line = line;
+                        self.client_cursor.x = cursor_x;
+                    }
+                }
+                let grace = self.grace();
+                self.predictor.expire(now, grace);
+            }
+            self.now = end;
+            let now = self.instant();
+            let grace = self.grace();
+            self.predictor.expire(now, grace);
+        }
+
+        /// The text that the user sees, and which columns are underlined
+        fn render(&self) -> (String, String) {
+            let mut line = self.client_line.clone();
+            self.predictor.apply_to_line(0, &mut line);
+            let text = line.as_str().trim_end().to_string();
+            let underlined = line
+                .visible_cells()
+                .map(|cell| {
+                    if cell.attrs().underline() == Underline::None {
+                        ' '
+                    } else {
+                        '^'
+                    }
+                })
+                .collect::<String>()
+                .trim_end()
+                .to_string();
+            (text, underlined)
+        }
+
+        fn cursor_x(&self) -> usize {
+            self.predictor.cursor_position(self.client_cursor).x
+        }
+    }
+
+    #[test]
+    fn echo_is_immediate_and_then_confirmed() {
+        let mut h = Harness::new(300);
+        h.type_str("ls", 50);
+        assert_eq!(h.render(), ("$ ls".to_string(), "  ^^".to_string()));
+        assert_eq!(h.cursor_x(), 4);
+
+        // After a round trip the first character has been echoed
+        h.advance(220);
+        assert_eq!(h.render(), ("$ ls".to_string(), "   ^".to_string()));
+
+        h.advance(100);
+        assert_eq!(h.render(), ("$ ls".to_string(), "".to_string()));
+        assert!(h.predictor.pending.is_empty());
+        assert_eq!(h.cursor_x(), 4);
+        assert_eq!(h.predictor.mispredictions, 0);
+    }
+
+    #[test]
+    fn backspace_erases_prediction() {
+        let mut h = Harness::new(300);
+        h.type_str("lx", 20);
+        h.type_key(KeyCode::Backspace);
+        h.advance(20);
+        h.type_str("s", 20);
+        assert_eq!(h.render().0, "$ ls");
+        assert_eq!(h.cursor_x(), 4);
+
+        h.advance(1000);
+        assert_eq!(h.render(), ("$ ls".to_string(), "".to_string()));
+        assert_eq!(h.predictor.mispredictions, 0);
+    }
+
+    #[test]
+    fn rolls_back_when_the_echo_never_arrives() {
+        let mut h = Harness::new(300);
+        h.server_echo = false;
+        h.type_str("secret", 10);
+        assert_eq!(h.render().0, "$ secret");
+
+        // The server acknowledged the first key after 300ms; give it the
+        // grace period and a little more and everything is rolled back
+        h.advance(300 + h.grace().as_millis() as u64);
+        assert_eq!(h.render(), ("$".to_string(), "".to_string()));
+        assert_eq!(h.cursor_x(), 2);
+        assert_eq!(h.predictor.mispredictions, 1);
+
+        // Subsequent predictions are tentative until one is confirmed
+        h.server_echo = true;
+        h.type_str("ab", 10);
+        assert_eq!(h.render().0, "$");
+        h.advance(320);
+        assert_eq!(h.render(), ("$ ab".to_string(), "".to_string()));
+
+        // ... after which we're back to predicting immediately
+        h.type_str("c", 10);
+        assert_eq!(h.render(), ("$ abc".to_string(), "    ^".to_string()));
+    }
+
+    #[test]
+    fn unpredictable_keys_pause_prediction() {
+        let mut h = Harness::new(300);
+        h.type_key(KeyCode::UpArrow);
+        h.advance(10);
+        h.type_str("x", 10);
+        assert_eq!(h.render().0, "$");
+        assert!(h.predictor.pending.is_empty());
+
+        // Once the server has processed the arrow key, we predict
+        // again, but tentatively
+        h.advance(300);
+        h.type_str("y", 10);
+        assert_eq!(h.render().0, "$ x");
+        assert_eq!(h.predictor.pending.len(), 1);
+        h.advance(320);
+        assert_eq!(h.render(), ("$ xy".to_string(), "".to_string()));
+    }
+
+    #[test]
+    fn suppressed_in_alt_screen_and_password_input() {
+        let mut h = Harness::new(300);
+        h.type_str("ab", 10);
+        assert!(h.predictor.has_displayed());
+        assert!(h.predictor.set_suppressed(true));
+        assert_eq!(h.render().0, "$");
+
+        h.type_str("cd", 10);
+        assert!(h.predictor.pending.is_empty());
+        assert_eq!(h.render().0, "$");
+
+        h.advance(1000);
+        assert_eq!(h.render(), ("$ abcd".to_string(), "".to_string()));
+        assert!(!h.predictor.set_suppressed(false));
+    }
+
+    #[test]
+    fn does_not_predict_wrapping() {
+        let mut h = Harness::new(300);
+        h.type_str(&"x".repeat(COLS), 1);
+        let (text, _) = h.render();
+        assert_eq!(text.len(), COLS);
+        assert_eq!(h.predictor.pending.len(), COLS - 2);
+        h.advance(1000);
+        assert_eq!(h.predictor.mispredictions, 0);
+    }
+}
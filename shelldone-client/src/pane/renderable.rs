@@ -1,5 +1,6 @@
 use crate::domain::ClientInner;
 use crate::pane::clientpane::ClientPane;
+use crate::pane::prediction::{Predictor, MIN_CONFIRM_GRACE};
 use anyhow::anyhow;
 use codec::*;
 use config::{configuration, ConfigHandle};
@@ -10,7 +11,7 @@ use mux::Mux;
 use promise::BrokenPromise;
 use rangeset::*;
 use ratelim::RateLimiter;
-use shelldone_term::{KeyCode, KeyModifiers, Line, StableRowIndex};
+use shelldone_term::{CommandBlock, KeyCode, KeyModifiers, Line, StableRowIndex};
 use std::cell::RefCell;
 use std::collections::HashMap;
 use std::num::NonZeroUsize;
@@ -18,7 +19,7 @@ use std::ops::Range;
 use std::sync::atomic::{AtomicBool, Ordering};
 use std::sync::{Arc, Mutex};
 use std::time::{Duration, Instant};
-use termwiz::cell::{Cell, CellAttributes, Underline};
+use termwiz::cell::CellAttributes;
 use termwiz::color::AnsiColor;
 use termwiz::image::{ImageCell, ImageCellIdentity, ImageCellPadding, ImageData};
 use termwiz::surface::{SequenceNo, SEQ_ZERO};
@@ -78,6 +79,11 @@ pub struct RenderableInner {
     last_input_rtt: u64,
 
     pub input_serial: InputSerial,
+    predictor: Predictor,
+    prediction_deadline: Option<Instant>,
+    pub alt_screen_active: bool,
+    pub password_input: bool,
+    pub command_blocks: Vec<CommandBlock>,
 }
 
 pub struct RenderableState {
@@ -116,6 +122,11 @@ impl RenderableInner {
             last_late_dirty: now,
             last_input_rtt: 0,
             input_serial: InputSerial::empty(),
+            predictor: Predictor::new(),
+            prediction_deadline: None,
+            alt_screen_active: false,
+            password_input: false,
+            command_blocks: vec![],
             seqno: SEQ_ZERO,
         }
     }
@@ -142,159 +153,98 @@ impl RenderableInner {
             .unwrap_or(false)
     }
 
-    /// Compute a "prediction" and apply it to the line data that we
-    /// have available, marking it as dirty so that it gets rendered.
-    /// The prediction is basically just local echo.
-    /// Open questions:
-    /// how do we tell if the intent is to suppress local echo during eg:
-    ///   * password prompt? One option is to look back and see if the line
-    ///     looks like a password prompt.
-    ///   * normal mode in vim: letter presses are typically movement or
-    ///     other editor commands
-    ///     There are bound to be a number of other edge cases that we should
-    ///     handle.
-    fn apply_prediction(&mut self, c: KeyCode, line: &mut Line) {
-        let text = line.as_str();
-        if text.contains("sword") {
-            // This line might be a password prompt.  Don't force
-            // on local echo here, as we don't want to reveal content
-            // from their password
-            return;
-        }
-
-        match c {
-            KeyCode::Enter => {
-                self.cursor_position.x = 0;
-                self.cursor_position.y += 1;
-            }
-            KeyCode::UpArrow => {
-                self.cursor_position.y = self.cursor_position.y.saturating_sub(1);
-            }
-            KeyCode::DownArrow => {
-                self.cursor_position.y += 1;
-            }
-            KeyCode::RightArrow => {
-                self.cursor_position.x += 1;
-            }
-            KeyCode::LeftArrow => {
-                self.cursor_position.x = self.cursor_position.x.saturating_sub(1);
-            }
-            KeyCode::Delete => {
-                line.erase_cell(self.cursor_position.x, SEQ_ZERO);
-            }
-            KeyCode::Backspace => {
-                if self.cursor_position.x > 0 {
-                    line.erase_cell(self.cursor_position.x - 1, SEQ_ZERO);
-                    self.cursor_position.x -= 1;
-                }
-            }
-            KeyCode::Char(c) => {
-                let cell = Cell::new(
-                    c,
-                    CellAttributes::default()
-                        .set_underline(Underline::Double)
-                        .clone(),
-                );
-
-                let width = cell.width();
-                line.set_cell(self.cursor_position.x, cell, SEQ_ZERO);
-                // Adjust the cursor to reflect the width of this new cell
-                self.cursor_position.x += width;
-            }
-            _ => {}
-        }
-    }
-
-    /// Based on a keypress, apply a "prediction" of what the terminal
-    /// content will look like once we receive the response from the
-    /// remote system.  The prediction helps to reduce perceived latency
-    /// when a user is typing at any reasonable velocity.
-    pub fn predict_from_key_event(&mut self, key: KeyCode, mods: KeyModifiers) {
+    /// Based on a keypress, predict what the terminal content will
+    /// look like once we receive the response from the remote system.
+    /// The prediction helps to reduce perceived latency when a user is
+    /// typing at any reasonable velocity.
+    /// Returns true if the prediction needs to be rendered.
+    pub fn predict_from_key_event(&mut self, key: KeyCode, mods: KeyModifiers) -> bool {
         if !self.should_predict() {
-            return;
-        }
-
-        let c = match key {
-            KeyCode::LeftArrow
-            | KeyCode::RightArrow
-            | KeyCode::UpArrow
-            | KeyCode::DownArrow
-            | KeyCode::Delete
-            | KeyCode::Backspace
-            | KeyCode::Enter
-            | KeyCode::Char(_) => key,
-            _ => return,
-        };
-        if mods != KeyModifiers::NONE && mods != KeyModifiers::SHIFT {
-            return;
-        }
-
-        let row = self.cursor_position.y;
-        match self.lines.pop(&row) {
-            Some(LineEntry::Stale(mut line)) | Some(LineEntry::Line(mut line)) => {
-                self.apply_prediction(c, &mut line);
-                self.lines.put(row, LineEntry::Line(line));
-            }
-            Some(LineEntry::LineAndFetching(mut line, instant)) => {
-                self.apply_prediction(c, &mut line);
-                self.lines
-                    .put(row, LineEntry::LineAndFetching(line, instant));
-            }
-            Some(entry) => {
-                self.lines.put(row, entry);
-            }
-            None => {}
-        }
+            // Leave any outstanding predictions to be confirmed,
+            // but don't build upon them
+            self.predictor.interrupt(self.input_serial);
+            return false;
+        }
+        self.predictor.predict_key(
+            key,
+            mods,
+            self.input_serial,
+            &self.cursor_position,
+            self.dimensions.cols,
+        )
     }
 
-    fn apply_paste_prediction(&mut self, row: usize, text: &str, line: &mut Line) {
-        let attrs = CellAttributes::default()
-            .set_underline(Underline::Double)
-            .clone();
+    /// We don't try to predict the effect of a paste; it may be
+    /// multiple lines or trigger arbitrary actions in the application.
+    /// Returns the serial to send with the paste; prediction resumes
+    /// once the server acknowledges it.
+    pub fn interrupt_prediction(&mut self) -> InputSerial {
+        self.input_serial = InputSerial::now();
+        self.predictor.interrupt(self.input_serial);
+        self.input_serial
+    }
 
-        let text_line = Line::from_text(text, &attrs, SEQ_ZERO, None);
+    /// How long we wait for the echo of a key after the server has
+    /// processed it before deciding that a prediction was wrong
+    fn prediction_grace(&self) -> Duration {
+        MIN_CONFIRM_GRACE.max(Duration::from_millis(self.last_input_rtt))
+    }
 
-        if row == 0 {
-            for cell in text_line.visible_cells() {
-                line.set_cell(self.cursor_position.x, cell.as_cell(), SEQ_ZERO);
-                self.cursor_position.x += cell.width();
+    /// Check the outstanding predictions against the lines that we
+    /// have received from the server.
+    /// Returns true if the rendered content changed as a result.
+    fn verify_predictions(&mut self, now: Instant) -> bool {
+        let mut changed = false;
+        for row in self.predictor.pending_rows() {
+            match self.lines.peek(&row) {
+                Some(LineEntry::Line(line)) | Some(LineEntry::LineAndFetching(line, _)) => {
+                    changed |= self.predictor.verify(row, line);
+                }
+                _ => {}
             }
-        } else {
-            // The pasted line replaces the data for the existing line
-            line.resize_and_clear(0, SEQ_ZERO, CellAttributes::default());
-            line.append_line(text_line, SEQ_ZERO);
-            self.cursor_position.x = line.len();
         }
+        let grace = self.prediction_grace();
+        changed |= self.predictor.expire(now, grace);
+        changed
     }
 
-    pub fn predict_from_paste(&mut self, text: &str) {
-        if !self.should_predict() {
-            return;
+    /// Arrange to roll back the predictions whose echo doesn't show
+    /// up in time, even if we don't hear from the server again
+    fn schedule_prediction_expiry(&mut self) {
+        let deadline = match self.predictor.next_deadline(self.prediction_grace()) {
+            Some(deadline) => deadline,
+            None => return,
+        };
+        if let Some(scheduled) = self.prediction_deadline {
+            if scheduled <= deadline {
+                // We'll reschedule when that fires
+                return;
+            }
         }
-
-        let text = textwrap::fill(text, self.dimensions.cols);
-        let lines: Vec<&str> = text.split("\n").collect();
-
-        for (idx, paste_line) in lines.iter().enumerate() {
-            let row = self.cursor_position.y + idx as StableRowIndex;
-
-            match self.lines.pop(&row) {
-                Some(LineEntry::Stale(mut line)) | Some(LineEntry::Line(mut line)) => {
-                    self.apply_paste_prediction(idx, paste_line, &mut line);
-                    self.lines.put(row, LineEntry::Line(line));
-                }
-                Some(LineEntry::LineAndFetching(mut line, instant)) => {
-                    self.apply_paste_prediction(idx, paste_line, &mut line);
-                    self.lines
-                        .put(row, LineEntry::LineAndFetching(line, instant));
-                }
-                Some(entry) => {
-                    self.lines.put(row, entry);
+        self.prediction_deadline.replace(deadline);
+        let local_pane_id = self.local_pane_id;
+        promise::spawn::spawn(async move {
+            smol::Timer::at(deadline).await;
+            let mux = Mux::get();
+            let pane = mux
+                .get_pane(local_pane_id)
+                .ok_or_else(|| anyhow!("no such tab {}", local_pane_id))?;
+            if let Some(client_pane) = pane.downcast_ref::<ClientPane>() {
+                let changed = {
+                    let renderable = client_pane.renderable.lock();
+                    let mut inner = renderable.inner.borrow_mut();
+                    inner.prediction_deadline = None;
+                    let changed = inner.verify_predictions(Instant::now());
+                    inner.schedule_prediction_expiry();
+                    changed
+                };
+                if changed {
+                    mux.notify(mux::MuxNotification::PaneOutput(local_pane_id));
                 }
-                None => {}
             }
-        }
-        self.cursor_position.y += lines.len().saturating_sub(1) as StableRowIndex;
+            Ok::<(), anyhow::Error>(())
+        })
+        .detach();
     }
 
     pub fn update_last_send(&mut self) {
@@ -331,6 +281,21 @@ impl RenderableInner {
         // long it took for this response to come back
         if let Some(serial) = delta.input_serial {
             self.last_input_rtt = serial.elapsed_millis();
+            self.predictor.acknowledge(serial, now);
+        }
+
+        // Predictions are positioned relative to the content, so a change
+        // in width invalidates them.  Full screen applications and password
+        // prompts don't echo what is typed, so we don't predict for them.
+        if delta.dimensions.cols != self.dimensions.cols {
+            self.predictor.clear();
+        }
+        self.alt_screen_active = delta.alt_screen_active;
+        self.password_input = delta.password_input;
+        self.predictor
+            .set_suppressed(delta.alt_screen_active || delta.password_input);
+        if let Some(blocks) = delta.command_blocks {
+            self.command_blocks = blocks;
         }
 
         // When it comes to updating the cursor position, if the update was tagged
@@ -366,6 +331,8 @@ impl RenderableInner {
             self.put_line(stable_row, line, &config, None);
             dirty.remove(stable_row);
         }
+        self.verify_predictions(now);
+        self.schedule_prediction_expiry();
 
         log::trace!(
             "apply_changes_to_surface: Generate PaneOutput event for local={}",
@@ -552,6 +519,7 @@ impl RenderableInner {
                     for (stable_row, line) in lines.into_iter() {
                         inner.put_line(stable_row, line, &config, Some(now));
                     }
+                    inner.verify_predictions(Instant::now());
                 }
                 Err(err) => {
                     log::error!("get_lines failed: {}", err);
@@ -724,7 +692,8 @@ pub(crate) async fn hydrate_lines(
 
 impl RenderableState {
     pub fn get_cursor_position(&self) -> StableCursorPosition {
-        self.inner.borrow().cursor_position
+        let inner = self.inner.borrow();
+        inner.predictor.cursor_position(inner.cursor_position)
     }
 
     pub fn get_lines(&self, lines: Range<StableRowIndex>) -> (StableRowIndex, Vec<Line>) {
@@ -764,6 +733,10 @@ impl RenderableState {
                 }
             };
 
+            inner
+                .predictor
+                .apply_to_line(idx, result.last_mut().unwrap());
+
             if inner.client.overlay_lag_indicator
                 && idx == inner.dimensions.physical_top
                 && inner.is_tardy()
@@ -0,0 +1,227 @@
+//! The kitty extended clipboard protocol, OSC 5522.
+//! <https://sw.kovidgoyal.net/kitty/clipboard/>
+use crate::osc::{base64_decode, base64_encode};
+use crate::{Result, bail};
+use core::fmt::{Display, Formatter, Result as FmtResult};
+use core::str;
+
+use crate::allocate::*;
+
+/// `type=`: the kind of operation
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum KittyClipboardType {
+    /// Request the clipboard contents for a list of MIME types,
+    /// or the response to that request
+    #[default]
+    Read,
+    /// Begin writing to the clipboard, or the response that
+    /// reports the outcome of the write
+    Write,
+    /// A chunk of data for a MIME type that is being written.
+    /// A `wdata` without a MIME type completes the write.
+    WriteData,
+    /// Makes the MIME types listed in the payload aliases of the
+    /// MIME type that is being written
+    WriteAlias,
+}
+
+impl KittyClipboardType {
+    fn parse(s: &str) -> Option<Self> {
+        Some(match s {
+            "read" => Self::Read,
+            "write" => Self::Write,
+            "wdata" => Self::WriteData,
+            "walias" => Self::WriteAlias,
+            _ => return None,
+        })
+    }
+
+    fn as_str(self) -> &'static str {
+        match self {
+            Self::Read => "read",
+            Self::Write => "write",
+            Self::WriteData => "wdata",
+            Self::WriteAlias => "walias",
+        }
+    }
+}
+
+/// `status=`: sent by the terminal in its responses
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum KittyClipboardStatus {
+    /// The read was permitted; data will follow
+    Ok,
+    /// A chunk of data for a MIME type
+    Data,
+    /// The operation completed
+    Done,
+    /// The user or the configuration denied the operation
+    PermissionDenied,
+    /// The terminal doesn't support the operation
+    NotSupported,
+    /// The clipboard couldn't be accessed or the data was too large
+    IoError,
+    /// The request was malformed
+    Invalid,
+    /// Another operation is in progress
+    Busy,
+}
+
+impl KittyClipboardStatus {
+    fn parse(s: &str) -> Option<Self> {
+        Some(match s {
+            "OK" => Self::Ok,
+            "DATA" => Self::Data,
+            "DONE" => Self::Done,
+            "EPERM" => Self::PermissionDenied,
+            "ENOSYS" => Self::NotSupported,
+            "EIO" => Self::IoError,
+            "EINVAL" => Self::Invalid,
+            "EBUSY" => Self::Busy,
+            _ => return None,
+        })
+    }
+
+    fn as_str(self) -> &'static str {
+        match self {
+            Self::Ok => "OK",
+            Self::Data => "DATA",
+            Self::Done => "DONE",
+            Self::PermissionDenied => "EPERM",
+            Self::NotSupported => "ENOSYS",
+            Self::IoError => "EIO",
+            Self::Invalid => "EINVAL",
+            Self::Busy => "EBUSY",
+        }
+    }
+}
+
+/// Reading this MIME type lists the MIME types that are
+/// available in the clipboard
+pub const KITTY_CLIPBOARD_LIST_MIME: &str = ".";
+
+/// A single OSC 5522 sequence.
+/// Large payloads are split by the sender into a number of
+/// `wdata` (or `DATA`) sequences for the same MIME type.
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct KittyClipboard {
+    /// `type=`
+    pub kind: KittyClipboardType,
+    /// `status=`; only present in responses from the terminal
+    pub status: Option<KittyClipboardStatus>,
+    /// `mime=`, which is base64 encoded on the wire
+    pub mime: Option<String>,
+    /// `loc=primary` selects the primary selection rather
+    /// than the clipboard
+    pub primary: bool,
+    /// `id=`: echoed back in the responses to a request
+    pub id: Option<String>,
+    /// The payload, which is base64 encoded on the wire
+    pub payload: Vec<u8>,
+}
+
+impl KittyClipboard {
+    /// A response to a request with the same id and location as `request`
+    pub fn response(
+        request: &Self,
+        kind: KittyClipboardType,
+        status: KittyClipboardStatus,
+    ) -> Self {
+        Self {
+            kind,
+            status: Some(status),
+            mime: None,
+            primary: request.primary,
+            id: request.id.clone(),
+            payload: vec![],
+        }
+    }
+
+    /// Returns the space separated list of MIME types
+    /// that is held in the payload of `read` and `walias`
+    pub fn mime_list(&self) -> Vec<String> {
+        String::from_utf8_lossy(&self.payload)
+            .split_ascii_whitespace()
+            .map(|s| s.to_string())
+            .collect()
+    }
+
+    pub fn parse(osc: &[&[u8]]) -> Result<Self> {
+        if osc.len() < 2 {
+            bail!("OSC 5522 requires metadata");
+        }
+        let mut clip = Self::default();
+        let mut have_type = false;
+
+        for item in osc[1].split(|&b| b == b':') {
+            if item.is_empty() {
+                continue;
+            }
+            let item = str::from_utf8(item)?;
+            let (key, value) = match item.split_once('=') {
+                Some(pair) => pair,
+                None => continue,
+            };
+            // Unknown keys are ignored, so that future extensions
+            // don't cause the whole request to be rejected
+            match key {
+                "type" => {
+                    clip.kind = match KittyClipboardType::parse(value) {
+                        Some(kind) => kind,
+                        None => bail!("unsupported OSC 5522 type {}", value),
+                    };
+                    have_type = true;
+                }
+                "status" => clip.status = KittyClipboardStatus::parse(value),
+                "mime" => {
+                    let mime = base64_decode(value)?;
+                    clip.mime = Some(String::from_utf8(mime).map_err(|err| {
+                        crate::format_err!("OSC 5522 mime is not UTF-8: {:#}", err)
+                    })?);
+                }
+                "loc" => clip.primary = value == "primary",
+                "id" => {
+                    if !value
+                        .chars()
+                        .all(|c| c.is_ascii_alphanumeric() || "-_+.".contains(c))
+                    {
+                        bail!("invalid OSC 5522 id {}", value);
+                    }
+                    clip.id = Some(value.to_string());
+                }
+                _ => {}
+            }
+        }
+        if !have_type {
+            bail!("OSC 5522 requires a type");
+        }
+
+        if let Some(payload) = osc.get(2) {
+            clip.payload = base64_decode(payload)?;
+        }
+
+        Ok(clip)
+    }
+}
+
+impl Display for KittyClipboard {
+    fn fmt(&self, f: &mut Formatter) -> FmtResult {
+        write!(f, "5522;type={}", self.kind.as_str())?;
+        if let Some(status) = self.status {
+            write!(f, ":status={}", status.as_str())?;
+        }
+        if let Some(mime) = &self.mime {
+            write!(f, ":mime={}", base64_encode(mime))?;
+        }
+        if self.primary {
+            write!(f, ":loc=primary")?;
+        }
+        if let Some(id) = &self.id {
+            write!(f, ":id={id}")?;
+        }
+        if !self.payload.is_empty() {
+            write!(f, ";{}", base64_encode(&self.payload))?;
+        }
+        Ok(())
+    }
+}
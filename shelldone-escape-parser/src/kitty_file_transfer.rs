@@ -0,0 +1,346 @@
+//! The kitty file transfer protocol, OSC 5113.
+//! <https://sw.kovidgoyal.net/kitty/file-transfer-protocol/>
+use crate::osc::{base64_decode, base64_encode};
+use crate::{Result, bail};
+use core::fmt::{Display, Formatter, Result as FmtResult};
+use core::str;
+
+use crate::allocate::*;
+
+/// `ac=`: the kind of operation
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum FileTransferAction {
+    /// Begin a session that sends files to the terminal
+    #[default]
+    Send,
+    /// Describes a file that is being transferred
+    File,
+    /// A chunk of the contents of a file
+    Data,
+    /// The last chunk of the contents of a file
+    EndData,
+    /// Begin a session that receives files from the terminal
+    Receive,
+    /// Abandon the session
+    Cancel,
+    /// A response reporting the outcome of an operation
+    Status,
+    /// Complete the session
+    Finish,
+}
+
+impl FileTransferAction {
+    fn parse(s: &str) -> Option<Self> {
+        Some(match s {
+            "send" => Self::Send,
+            "file" => Self::File,
+            "data" => Self::Data,
+            "end_data" => Self::EndData,
+            "receive" => Self::Receive,
+            "cancel" => Self::Cancel,
+            "status" => Self::Status,
+            "finish" => Self::Finish,
+            _ => return None,
+        })
+    }
+
+    fn as_str(self) -> &'static str {
+        match self {
+            Self::Send => "send",
+            Self::File => "file",
+            Self::Data => "data",
+            Self::EndData => "end_data",
+            Self::Receive => "receive",
+            Self::Cancel => "cancel",
+            Self::Status => "status",
+            Self::Finish => "finish",
+        }
+    }
+}
+
+/// `ft=`: the kind of file
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum FileTransferFileType {
+    #[default]
+    Regular,
+    Directory,
+    /// The data holds the target of the link
+    Symlink,
+    /// A hard link; the data holds the `fid` of the file
+    /// that it links to
+    Link,
+}
+
+impl FileTransferFileType {
+    fn parse(s: &str) -> Option<Self> {
+        Some(match s {
+            "regular" => Self::Regular,
+            "directory" => Self::Directory,
+            "symlink" => Self::Symlink,
+            "link" => Self::Link,
+            _ => return None,
+        })
+    }
+
+    fn as_str(self) -> &'static str {
+        match self {
+            Self::Regular => "regular",
+            Self::Directory => "directory",
+            Self::Symlink => "symlink",
+            Self::Link => "link",
+        }
+    }
+}
+
+/// `tt=`: how the contents of a file are transmitted
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum FileTransferTransmission {
+    /// The data is the contents of the file
+    #[default]
+    Simple,
+    /// The data is a delta against the signature of the
+    /// copy of the file that the receiver already has
+    Rsync,
+}
+
+impl FileTransferTransmission {
+    fn parse(s: &str) -> Option<Self> {
+        Some(match s {
+            "simple" => Self::Simple,
+            "rsync" => Self::Rsync,
+            _ => return None,
+        })
+    }
+
+    fn as_str(self) -> &'static str {
+        match self {
+            Self::Simple => "simple",
+            Self::Rsync => "rsync",
+        }
+    }
+}
+
+/// `zip=`: how the data is compressed
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum FileTransferCompression {
+    #[default]
+    None,
+    Zlib,
+}
+
+impl FileTransferCompression {
+    fn parse(s: &str) -> Option<Self> {
+        Some(match s {
+            "none" => Self::None,
+            "zlib" => Self::Zlib,
+            _ => return None,
+        })
+    }
+
+    fn as_str(self) -> &'static str {
+        match self {
+            Self::None => "none",
+            Self::Zlib => "zlib",
+        }
+    }
+}
+
+/// Status sent by the terminal when a session is permitted,
+/// or a file has been completely written
+pub const FILE_TRANSFER_OK: &str = "OK";
+/// Status sent by the terminal when it is ready for the
+/// data of a file
+pub const FILE_TRANSFER_STARTED: &str = "STARTED";
+/// Status sent by the terminal as the data of a file arrives
+pub const FILE_TRANSFER_PROGRESS: &str = "PROGRESS";
+/// Status sent by the terminal when a session is canceled
+pub const FILE_TRANSFER_CANCELED: &str = "CANCELED";
+
+/// A single OSC 5113 sequence.
+/// The contents of a file are split by the sender into a number
+/// of `data` sequences followed by an `end_data` sequence.
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct FileTransfer {
+    /// `ac=`
+    pub action: FileTransferAction,
+    /// `id=`: identifies the session
+    pub id: Option<String>,
+    /// `fid=`: identifies a file within the session
+    pub file_id: Option<String>,
+    /// `n=`: the path of the file, which is base64 encoded on the wire
+    pub name: Option<String>,
+    /// `st=`: the status carried by a response, which is base64
+    /// encoded on the wire.  Errors take the form `ECODE:message`.
+    pub status: Option<String>,
+    /// `pw=`: a password that the application may use to
+    /// authenticate itself, which is base64 encoded on the wire
+    pub password: Option<String>,
+    /// `ft=`
+    pub file_type: FileTransferFileType,
+    /// `tt=`
+    pub transmission: FileTransferTransmission,
+    /// `zip=`
+    pub compression: FileTransferCompression,
+    /// `sz=`: the size of a file, the number of files requested
+    /// by a `receive`, or the number of bytes written so far
+    pub size: Option<u64>,
+    /// `mod=`: modification time in nanoseconds since the epoch
+    pub mtime: Option<u64>,
+    /// `prm=`: the permission bits of the file
+    pub permissions: Option<u32>,
+    /// `q=`: 1 suppresses successful responses,
+    /// 2 suppresses all responses
+    pub quiet: u8,
+    /// `d=`: the payload, which is base64 encoded on the wire
+    pub data: Vec<u8>,
+}
+
+impl FileTransfer {
+    /// A response to `request` with the same session and file ids
+    pub fn status(request: &Self, status: impl Into<String>) -> Self {
+        Self {
+            action: FileTransferAction::Status,
+            id: request.id.clone(),
+            file_id: request.file_id.clone(),
+            status: Some(status.into()),
+            ..Default::default()
+        }
+    }
+
+    /// A response to `request` reporting an error, formatted
+    /// as `code:message`
+    pub fn error(request: &Self, code: &str, message: &str) -> Self {
+        Self::status(request, format!("{code}:{message}"))
+    }
+
+    /// Returns true if the status is an error
+    pub fn is_error(&self) -> bool {
+        matches!(&self.status, Some(st) if st.starts_with('E'))
+    }
+
+    pub fn parse(osc: &[&[u8]]) -> Result<Self> {
+        if osc.len() < 2 {
+            bail!("OSC 5113 requires an action");
+        }
+        let mut transfer = Self::default();
+        let mut have_action = false;
+
+        fn string(key: &str, value: &str) -> Result<String> {
+            String::from_utf8(base64_decode(value)?)
+                .map_err(|err| crate::format_err!("OSC 5113 {} is not UTF-8: {:#}", key, err))
+        }
+
+        fn id(key: &str, value: &str) -> Result<String> {
+            if value.is_empty()
+                || !value
+                    .chars()
+                    .all(|c| c.is_ascii_alphanumeric() || "-_+.".contains(c))
+            {
+                bail!("invalid OSC 5113 {}={}", key, value);
+            }
+            Ok(value.to_string())
+        }
+
+        for item in &osc[1..] {
+            if item.is_empty() {
+                continue;
+            }
+            let item = str::from_utf8(item)?;
+            let (key, value) = match item.split_once('=') {
+                Some(pair) => pair,
+                None => bail!("invalid OSC 5113 item {}", item),
+            };
+            // Unknown keys are ignored, so that future extensions
+            // don't cause the whole request to be rejected
+            match key {
+                "ac" => {
+                    transfer.action = match FileTransferAction::parse(value) {
+                        Some(action) => action,
+                        None => bail!("unsupported OSC 5113 action {}", value),
+                    };
+                    have_action = true;
+                }
+                "id" => transfer.id = Some(id(key, value)?),
+                "fid" => transfer.file_id = Some(id(key, value)?),
+                "n" => transfer.name = Some(string(key, value)?),
+                "st" => transfer.status = Some(string(key, value)?),
+                "pw" => transfer.password = Some(string(key, value)?),
+                "ft" => {
+                    transfer.file_type = match FileTransferFileType::parse(value) {
+                        Some(ft) => ft,
+                        None => bail!("unsupported OSC 5113 file type {}", value),
+                    }
+                }
+                "tt" => {
+                    transfer.transmission = match FileTransferTransmission::parse(value) {
+                        Some(tt) => tt,
+                        None => bail!("unsupported OSC 5113 transmission {}", value),
+                    }
+                }
+                "zip" => {
+                    transfer.compression = match FileTransferCompression::parse(value) {
+                        Some(zip) => zip,
+                        None => bail!("unsupported OSC 5113 compression {}", value),
+                    }
+                }
+                "sz" => transfer.size = Some(value.parse()?),
+                "mod" => transfer.mtime = Some(value.parse()?),
+                "prm" => transfer.permissions = Some(value.parse()?),
+                "q" => transfer.quiet = value.parse()?,
+                "d" => transfer.data = base64_decode(value)?,
+                _ => {}
+            }
+        }
+        if !have_action {
+            bail!("OSC 5113 requires an action");
+        }
+
+        Ok(transfer)
+    }
+}
+
+impl Display for FileTransfer {
+    fn fmt(&self, f: &mut Formatter) -> FmtResult {
+        write!(f, "5113;ac={}", self.action.as_str())?;
+        if let Some(id) = &self.id {
+            write!(f, ";id={id}")?;
+        }
+        if let Some(fid) = &self.file_id {
+            write!(f, ";fid={fid}")?;
+        }
+        if let Some(name) = &self.name {
+            write!(f, ";n={}", base64_encode(name))?;
+        }
+        if let Some(status) = &self.status {
+            write!(f, ";st={}", base64_encode(status))?;
+        }
+        if let Some(pw) = &self.password {
+            write!(f, ";pw={}", base64_encode(pw))?;
+        }
+        if self.file_type != FileTransferFileType::default() {
+            write!(f, ";ft={}", self.file_type.as_str())?;
+        }
+        if self.transmission != FileTransferTransmission::default() {
+            write!(f, ";tt={}", self.transmission.as_str())?;
+        }
+        if self.compression != FileTransferCompression::default() {
+            write!(f, ";zip={}", self.compression.as_str())?;
+        }
+        if let Some(size) = self.size {
+            write!(f, ";sz={size}")?;
+        }
+        if let Some(mtime) = self.mtime {
+            write!(f, ";mod={mtime}")?;
+        }
+        if let Some(prm) = self.permissions {
+            write!(f, ";prm={prm}")?;
+        }
+        if self.quiet != 0 {
+            write!(f, ";q={}", self.quiet)?;
+        }
+        if !self.data.is_empty() {
+            write!(f, ";d={}", base64_encode(&self.data))?;
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,336 @@
+//! The kitty desktop notification protocol, OSC 99.
+//! <https://sw.kovidgoyal.net/kitty/desktop-notifications/>
+use crate::osc::{base64_decode, base64_encode};
+use crate::{Result, bail};
+use core::fmt::{Display, Formatter, Result as FmtResult};
+use core::str;
+
+use crate::allocate::*;
+
+/// Describes what the payload of an OSC 99 sequence holds
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum KittyNotificationPayload {
+    /// `p=title`
+    #[default]
+    Title,
+    /// `p=body`
+    Body,
+    /// `p=close`: close the notification with the same id,
+    /// or, when sent by the terminal, report that it was closed
+    Close,
+    /// `p=?`: query the capabilities of the terminal
+    Query,
+    /// `p=alive`: query which of the listed notifications are still open
+    Alive,
+    /// `p=icon`: image data for the icon
+    Icon,
+    /// `p=buttons`: U+2028 separated labels for buttons
+    Buttons,
+}
+
+impl KittyNotificationPayload {
+    fn parse(s: &str) -> Option<Self> {
+        Some(match s {
+            "title" => Self::Title,
+            "body" => Self::Body,
+            "close" => Self::Close,
+            "?" => Self::Query,
+            "alive" => Self::Alive,
+            "icon" => Self::Icon,
+            "buttons" => Self::Buttons,
+            _ => return None,
+        })
+    }
+
+    fn as_str(self) -> &'static str {
+        match self {
+            Self::Title => "title",
+            Self::Body => "body",
+            Self::Close => "close",
+            Self::Query => "?",
+            Self::Alive => "alive",
+            Self::Icon => "icon",
+            Self::Buttons => "buttons",
+        }
+    }
+}
+
+/// `o=`: the circumstances under which the notification is shown
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum KittyNotificationOccasion {
+    Always,
+    /// Only when the window does not have keyboard focus
+    Unfocused,
+    /// Only when the window is not visible to the user
+    Invisible,
+}
+
+impl KittyNotificationOccasion {
+    fn parse(s: &str) -> Option<Self> {
+        Some(match s {
+            "always" => Self::Always,
+            "unfocused" => Self::Unfocused,
+            "invisible" => Self::Invisible,
+            _ => return None,
+        })
+    }
+
+    fn as_str(self) -> &'static str {
+        match self {
+            Self::Always => "always",
+            Self::Unfocused => "unfocused",
+            Self::Invisible => "invisible",
+        }
+    }
+}
+
+/// `u=`: how urgent the notification is
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum KittyNotificationUrgency {
+    Low = 0,
+    Normal = 1,
+    Critical = 2,
+}
+
+impl KittyNotificationUrgency {
+    fn parse(s: &str) -> Option<Self> {
+        Some(match s {
+            "0" => Self::Low,
+            "1" => Self::Normal,
+            "2" => Self::Critical,
+            _ => return None,
+        })
+    }
+}
+
+/// A single OSC 99 sequence.  Notifications may be split across
+/// a number of these that share the same `id`; all but the last
+/// have `done` set to false.
+/// Metadata that is not specified is represented as `None` so that
+/// the chunks can be merged; the documented defaults apply to
+/// whatever remains unspecified once the notification is complete.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct KittyNotification {
+    /// `i=`: identifies the notification so that its chunks can be
+    /// combined and so that it can be updated, closed or reported on
+    pub id: Option<String>,
+    /// `d=`: false if more chunks of this notification will follow
+    pub done: bool,
+    /// `p=`: what the payload holds
+    pub payload_type: KittyNotificationPayload,
+    /// `a=focus`: whether activating the notification should focus
+    /// the window that sent it.  Defaults to true.
+    pub focus: Option<bool>,
+    /// `a=report`: whether activating the notification should be
+    /// reported back to the application.  Defaults to false.
+    pub report: Option<bool>,
+    /// `o=`; defaults to `Always`
+    pub occasion: Option<KittyNotificationOccasion>,
+    /// `u=`; defaults to `Normal`
+    pub urgency: Option<KittyNotificationUrgency>,
+    /// `c=1`: report back to the application when the notification
+    /// is closed
+    pub report_close: Option<bool>,
+    /// `w=`: the number of milliseconds after which the notification
+    /// should expire, or -1 to use the system default
+    pub expire_ms: Option<i64>,
+    /// The payload, decoded if it was sent with `e=1`
+    pub payload: Vec<u8>,
+}
+
+impl Default for KittyNotification {
+    fn default() -> Self {
+        Self {
+            id: None,
+            done: true,
+            payload_type: KittyNotificationPayload::default(),
+            focus: None,
+            report: None,
+            occasion: None,
+            urgency: None,
+            report_close: None,
+            expire_ms: None,
+            payload: vec![],
+        }
+    }
+}
+
+/// The capabilities that we report in response to `p=?`
+pub const KITTY_NOTIFICATION_CAPABILITIES: &str =
+    "a=focus,report:c=1:o=always,unfocused,invisible:p=title,body,?,close:u=0,1,2:w=1";
+
+impl KittyNotification {
+    /// The sequence that reports that the notification `id` was
+    /// activated by the user
+    pub fn activated(id: &str) -> Self {
+        Self {
+            id: Some(id.to_string()),
+            ..Self::default()
+        }
+    }
+
+    /// The sequence that reports that the notification `id` was closed
+    pub fn closed(id: &str) -> Self {
+        Self {
+            id: Some(id.to_string()),
+            payload_type: KittyNotificationPayload::Close,
+            ..Self::default()
+        }
+    }
+
+    /// The response to a `p=?` query
+    pub fn capabilities(id: Option<String>) -> Self {
+        Self {
+            id,
+            payload_type: KittyNotificationPayload::Query,
+            payload: KITTY_NOTIFICATION_CAPABILITIES.as_bytes().to_vec(),
+            ..Self::default()
+        }
+    }
+
+    /// Returns the payload as text
+    pub fn payload_str(&self) -> String {
+        String::from_utf8_lossy(&self.payload).into_owned()
+    }
+
+    /// Merges `next`, a subsequent chunk with the same id and payload
+    /// type, into this one
+    pub fn merge(&mut self, next: KittyNotification) {
+        macro_rules! merge {
+            ($field:ident) => {
+                if next.$field.is_some() {
+                    self.$field = next.$field;
+                }
+            };
+        }
+        merge!(focus);
+        merge!(report);
+        merge!(occasion);
+        merge!(urgency);
+        merge!(report_close);
+        merge!(expire_ms);
+        self.done = next.done;
+        self.payload.extend(next.payload);
+    }
+
+    pub fn parse(osc: &[&[u8]]) -> Result<Self> {
+        if osc.len() < 2 {
+            bail!("OSC 99 requires metadata");
+        }
+        let mut notif = Self::default();
+        let mut encoded = false;
+
+        for item in osc[1].split(|&b| b == b':') {
+            if item.is_empty() {
+                continue;
+            }
+            let item = str::from_utf8(item)?;
+            let (key, value) = match item.split_once('=') {
+                Some(pair) => pair,
+                None => continue,
+            };
+            // Unknown keys and values are ignored, as required by the spec
+            match key {
+                "i" => notif.id = Some(value.to_string()),
+                "d" => notif.done = value != "0",
+                "e" => encoded = value == "1",
+                "p" => {
+                    notif.payload_type = match KittyNotificationPayload::parse(value) {
+                        Some(p) => p,
+                        None => bail!("unsupported OSC 99 payload type {}", value),
+                    }
+                }
+                "a" => {
+                    for action in value.split(',') {
+                        let (enable, action) = match action.strip_prefix('-') {
+                            Some(action) => (false, action),
+                            None => (true, action),
+                        };
+                        match action {
+                            "focus" => notif.focus = Some(enable),
+                            "report" => notif.report = Some(enable),
+                            _ => {}
+                        }
+                    }
+                }
+                "o" => notif.occasion = KittyNotificationOccasion::parse(value),
+                "u" => notif.urgency = KittyNotificationUrgency::parse(value),
+                "c" => notif.report_close = Some(value == "1"),
+                "w" => notif.expire_ms = value.parse().ok(),
+                _ => {}
+            }
+        }
+
+        // The payload should not contain `;`, but be tolerant of it
+        let mut payload = vec![];
+        for (idx, chunk) in osc.iter().skip(2).enumerate() {
+            if idx > 0 {
+                payload.push(b';');
+            }
+            payload.extend_from_slice(chunk);
+        }
+        notif.payload = if encoded {
+            base64_decode(&payload)?
+        } else {
+            payload
+        };
+
+        Ok(notif)
+    }
+}
+
+impl Display for KittyNotification {
+    fn fmt(&self, f: &mut Formatter) -> FmtResult {
+        write!(f, "99;")?;
+        let mut items: Vec<String> = vec![];
+        if let Some(id) = &self.id {
+            items.push(format!("i={id}"));
+        }
+        if !self.done {
+            items.push("d=0".to_string());
+        }
+        if self.payload_type != KittyNotificationPayload::Title {
+            items.push(format!("p={}", self.payload_type.as_str()));
+        }
+        let mut actions = vec![];
+        for (enabled, name) in [(self.focus, "focus"), (self.report, "report")] {
+            match enabled {
+                Some(true) => actions.push(name.to_string()),
+                Some(false) => actions.push(format!("-{name}")),
+                None => {}
+            }
+        }
+        if !actions.is_empty() {
+            items.push(format!("a={}", actions.join(",")));
+        }
+        if let Some(occasion) = self.occasion {
+            items.push(format!("o={}", occasion.as_str()));
+        }
+        if let Some(urgency) = self.urgency {
+            items.push(format!("u={}", urgency as u8));
+        }
+        if let Some(close) = self.report_close {
+            items.push(format!("c={}", if close { 1 } else { 0 }));
+        }
+        if let Some(expire) = self.expire_ms {
+            items.push(format!("w={expire}"));
+        }
+
+        let needs_encoding = self
+            .payload
+            .iter()
+            .any(|&b| b == b';' || b == 0x1b || !(0x20..0x7f).contains(&b));
+        if needs_encoding {
+            items.push("e=1".to_string());
+        }
+        write!(f, "{};", items.join(":"))?;
+
+        if needs_encoding {
+            write!(f, "{}", base64_encode(&self.payload))?;
+        } else {
+            // Verified to be printable ASCII above
+            f.write_str(str::from_utf8(&self.payload).map_err(|_| core::fmt::Error)?)?;
+        }
+        Ok(())
+    }
+}
@@ -25,8 +25,12 @@ pub mod csi;
 pub mod error;
 pub mod esc;
 pub mod hyperlink;
+pub mod kitty_clipboard;
+pub mod kitty_file_transfer;
+pub mod kitty_notification;
 pub mod osc;
 pub mod parser;
+pub mod text_sizing;
 #[cfg(feature = "tmux_cc")]
 pub mod tmux_cc;
 
@@ -1,5 +1,9 @@
 use crate::color::SrgbaTuple;
 pub use crate::hyperlink::Hyperlink;
+pub use crate::kitty_clipboard::KittyClipboard;
+pub use crate::kitty_file_transfer::FileTransfer;
+pub use crate::kitty_notification::KittyNotification;
+pub use crate::text_sizing::SizedText;
 use crate::{Result, bail, ensure, format_err};
 use base64::Engine;
 use bitflags::bitflags;
@@ -50,6 +54,10 @@ pub enum OperatingSystemCommand {
     ResetColors(Vec<u8>),
     RxvtExtension(Vec<String>),
     ConEmuProgress(Progress),
+    KittyNotification(KittyNotification),
+    KittyClipboard(KittyClipboard),
+    KittyFileTransfer(FileTransfer),
+    SizedText(SizedText),
 
     Unspecified(Vec<Vec<u8>>),
 }
@@ -362,6 +370,16 @@ impl OperatingSystemCommand {
             FinalTermSemanticPrompt => self::FinalTermSemanticPrompt::parse(osc)
                 .map(OperatingSystemCommand::FinalTermSemanticPrompt),
             ChangeColorNumber => Self::parse_change_color_number(osc),
+            KittyNotification => {
+                self::KittyNotification::parse(osc).map(OperatingSystemCommand::KittyNotification)
+            }
+            KittyClipboard => {
+                self::KittyClipboard::parse(osc).map(OperatingSystemCommand::KittyClipboard)
+            }
+            KittyFileTransfer => {
+                self::FileTransfer::parse(osc).map(OperatingSystemCommand::KittyFileTransfer)
+            }
+            TextSizing => self::SizedText::parse(osc).map(OperatingSystemCommand::SizedText),
             ResetColors => Self::parse_reset_colors(osc),
 
             ResetSpecialColor
@@ -495,8 +513,16 @@ osc_entries!(
     ResetTektronixCursorColor = "118",
     ResetHighlightForegroundColor = "119",
     RxvtProprietary = "777",
+    /// kitty text sizing protocol
+    TextSizing = "66",
+    /// kitty desktop notifications
+    KittyNotification = "99",
     FinalTermSemanticPrompt = "133",
     ITermProprietary = "1337",
+    /// kitty file transfer protocol
+    KittyFileTransfer = "5113",
+    /// kitty extended clipboard protocol
+    KittyClipboard = "5522",
     /// Here the "Sun" suffix comes from the table in
     /// <https://invisible-island.net/xterm/ctlseqs/ctlseqs.html#h3-Miscellaneous>
     /// that lays out various window related escape sequences.
@@ -583,6 +609,10 @@ impl Display for OperatingSystemCommand {
             SystemNotification(s) => write!(f, "9;{}", s)?,
             ITermProprietary(i) => i.fmt(f)?,
             FinalTermSemanticPrompt(i) => i.fmt(f)?,
+            KittyNotification(n) => n.fmt(f)?,
+            KittyClipboard(c) => c.fmt(f)?,
+            KittyFileTransfer(t) => t.fmt(f)?,
+            SizedText(t) => t.fmt(f)?,
             ResetColors(colors) => {
                 write!(f, "104")?;
                 for c in colors {
@@ -1711,6 +1741,291 @@ mod test {
         );
     }
 
+    #[test]
+    fn kitty_notification() {
+        use crate::kitty_notification::*;
+
+        assert_eq!(
+            parse(&["99", "i=1:d=0", "Hello"], "\x1b]99;i=1:d=0;Hello\x1b\\"),
+            OperatingSystemCommand::KittyNotification(KittyNotification {
+                id: Some("1".into()),
+                done: false,
+                payload: b"Hello".to_vec(),
+                ..Default::default()
+            })
+        );
+        assert_eq!(
+            parse(
+                &["99", "i=1:p=body:e=1", "SGVsbG8gd29ybGQ="],
+                "\x1b]99;i=1:p=body;Hello world\x1b\\"
+            ),
+            OperatingSystemCommand::KittyNotification(KittyNotification {
+                id: Some("1".into()),
+                payload_type: KittyNotificationPayload::Body,
+                payload: b"Hello world".to_vec(),
+                ..Default::default()
+            })
+        );
+        assert_eq!(
+            parse(
+                &[
+                    "99",
+                    "i=x:a=-focus,report:o=unfocused:u=2:c=1:w=5000:z=ignored",
+                    ""
+                ],
+                "\x1b]99;i=x:a=-focus,report:o=unfocused:u=2:c=1:w=5000;\x1b\\"
+            ),
+            OperatingSystemCommand::KittyNotification(KittyNotification {
+                id: Some("x".into()),
+                focus: Some(false),
+                report: Some(true),
+                occasion: Some(KittyNotificationOccasion::Unfocused),
+                urgency: Some(KittyNotificationUrgency::Critical),
+                report_close: Some(true),
+                expire_ms: Some(5000),
+                ..Default::default()
+            })
+        );
+        // A payload containing `;` is re-encoded as base64
+        assert_eq!(
+            parse(&["99", "", "a", "b"], "\x1b]99;e=1;YTti\x1b\\"),
+            OperatingSystemCommand::KittyNotification(KittyNotification {
+                payload: b"a;b".to_vec(),
+                ..Default::default()
+            })
+        );
+        assert_eq!(
+            encode(&OperatingSystemCommand::KittyNotification(
+                KittyNotification::closed("x")
+            )),
+            "\x1b]99;i=x:p=close;\x1b\\"
+        );
+        assert_eq!(
+            parse(&["99", "p=frob", "x"], "\x1b]99;p=frob;x\x1b\\"),
+            OperatingSystemCommand::Unspecified(vec![
+                b"99".to_vec(),
+                b"p=frob".to_vec(),
+                b"x".to_vec()
+            ])
+        );
+    }
+
+    #[test]
+    fn kitty_clipboard() {
+        use crate::kitty_clipboard::*;
+
+        let read = parse(
+            &["5522", "type=read:id=a1", "dGV4dC9wbGFpbiBpbWFnZS9wbmc="],
+            "\x1b]5522;type=read:id=a1;dGV4dC9wbGFpbiBpbWFnZS9wbmc=\x1b\\",
+        );
+        assert_eq!(
+            read,
+            OperatingSystemCommand::KittyClipboard(KittyClipboard {
+                kind: KittyClipboardType::Read,
+                id: Some("a1".into()),
+                payload: b"text/plain image/png".to_vec(),
+                ..Default::default()
+            })
+        );
+        match read {
+            OperatingSystemCommand::KittyClipboard(read) => {
+                assert_eq!(read.mime_list(), vec!["text/plain", "image/png"]);
+            }
+            _ => unreachable!(),
+        }
+
+        assert_eq!(
+            parse(
+                &[
+                    "5522",
+                    "type=wdata:mime=dGV4dC9odG1s:loc=primary",
+                    "PGI+aGk8L2I+"
+                ],
+                "\x1b]5522;type=wdata:mime=dGV4dC9odG1s:loc=primary;PGI+aGk8L2I+\x1b\\"
+            ),
+            OperatingSystemCommand::KittyClipboard(KittyClipboard {
+                kind: KittyClipboardType::WriteData,
+                mime: Some("text/html".into()),
+                primary: true,
+                payload: b"<b>hi</b>".to_vec(),
+                ..Default::default()
+            })
+        );
+
+        let request = KittyClipboard {
+            kind: KittyClipboardType::Write,
+            id: Some("w".into()),
+            ..Default::default()
+        };
+        assert_eq!(
+            encode(&OperatingSystemCommand::KittyClipboard(
+                KittyClipboard::response(
+                    &request,
+                    KittyClipboardType::Write,
+                    KittyClipboardStatus::PermissionDenied
+                )
+            )),
+            "\x1b]5522;type=write:status=EPERM:id=w\x1b\\"
+        );
+
+        // An unknown type or a bad id is not understood
+        assert_eq!(
+            parse(&["5522", "type=frob"], "\x1b]5522;type=frob\x1b\\"),
+            OperatingSystemCommand::Unspecified(vec![b"5522".to_vec(), b"type=frob".to_vec()])
+        );
+        assert_eq!(
+            parse(
+                &["5522", "type=read:id=a/b"],
+                "\x1b]5522;type=read:id=a/b\x1b\\"
+            ),
+            OperatingSystemCommand::Unspecified(vec![
+                b"5522".to_vec(),
+                b"type=read:id=a/b".to_vec()
+            ])
+        );
+    }
+
+    #[test]
+    fn kitty_file_transfer() {
+        use crate::kitty_file_transfer::*;
+
+        assert_eq!(
+            parse(
+                &["5113", "ac=send", "id=s1"],
+                "\x1b]5113;ac=send;id=s1\x1b\\"
+            ),
+            OperatingSystemCommand::KittyFileTransfer(FileTransfer {
+                action: FileTransferAction::Send,
+                id: Some("s1".into()),
+                ..Default::default()
+            })
+        );
+
+        assert_eq!(
+            parse(
+                &[
+                    "5113",
+                    "ac=file",
+                    "id=s1",
+                    "fid=f1",
+                    "n=ZmlsZS50eHQ=",
+                    "tt=rsync",
+                    "zip=zlib",
+                    "sz=5",
+                    "mod=1700000000000000000",
+                    "prm=420"
+                ],
+                "\x1b]5113;ac=file;id=s1;fid=f1;n=ZmlsZS50eHQ=;tt=rsync;zip=zlib;sz=5;mod=1700000000000000000;prm=420\x1b\\"
+            ),
+            OperatingSystemCommand::KittyFileTransfer(FileTransfer {
+                action: FileTransferAction::File,
+                id: Some("s1".into()),
+                file_id: Some("f1".into()),
+                name: Some("file.txt".into()),
+                transmission: FileTransferTransmission::Rsync,
+                compression: FileTransferCompression::Zlib,
+                size: Some(5),
+                mtime: Some(1700000000000000000),
+                permissions: Some(0o644),
+                ..Default::default()
+            })
+        );
+
+        assert_eq!(
+            parse(
+                &["5113", "ac=end_data", "id=s1", "fid=f1", "d=aGVsbG8="],
+                "\x1b]5113;ac=end_data;id=s1;fid=f1;d=aGVsbG8=\x1b\\"
+            ),
+            OperatingSystemCommand::KittyFileTransfer(FileTransfer {
+                action: FileTransferAction::EndData,
+                id: Some("s1".into()),
+                file_id: Some("f1".into()),
+                data: b"hello".to_vec(),
+                ..Default::default()
+            })
+        );
+
+        let request = FileTransfer {
+            action: FileTransferAction::File,
+            id: Some("s1".into()),
+            file_id: Some("f1".into()),
+            ..Default::default()
+        };
+        assert_eq!(
+            encode(&OperatingSystemCommand::KittyFileTransfer(
+                FileTransfer::status(&request, FILE_TRANSFER_OK)
+            )),
+            "\x1b]5113;ac=status;id=s1;fid=f1;st=T0s=\x1b\\"
+        );
+        assert!(FileTransfer::error(&request, "EPERM", "denied").is_error());
+
+        // An unknown action or a bad id is not understood
+        assert_eq!(
+            parse(&["5113", "ac=frob"], "\x1b]5113;ac=frob\x1b\\"),
+            OperatingSystemCommand::Unspecified(vec![b"5113".to_vec(), b"ac=frob".to_vec()])
+        );
+        assert_eq!(
+            parse(
+                &["5113", "ac=send", "id=a/b"],
+                "\x1b]5113;ac=send;id=a/b\x1b\\"
+            ),
+            OperatingSystemCommand::Unspecified(vec![
+                b"5113".to_vec(),
+                b"ac=send".to_vec(),
+                b"id=a/b".to_vec()
+            ])
+        );
+    }
+
+    #[test]
+    fn text_sizing() {
+        use crate::text_sizing::*;
+
+        assert_eq!(
+            parse(&["66", "s=2", "Title"], "\x1b]66;s=2;Title\x1b\\"),
+            OperatingSystemCommand::SizedText(SizedText {
+                size: TextSize {
+                    scale: 2,
+                    ..Default::default()
+                },
+                text: "Title".into(),
+            })
+        );
+
+        let half = parse(
+            &["66", "w=1:n=1:d=2:v=2:h=1", "a;b"],
+            "\x1b]66;w=1:n=1:d=2:v=2:h=1;a;b\x1b\\",
+        );
+        assert_eq!(
+            half,
+            OperatingSystemCommand::SizedText(SizedText {
+                size: TextSize {
+                    scale: 1,
+                    width: 1,
+                    numerator: 1,
+                    denominator: 2,
+                    vertical_align: TextSizeVerticalAlign::Centered,
+                    horizontal_align: TextSizeHorizontalAlign::Right,
+                },
+                text: "a;b".into(),
+            })
+        );
+        match half {
+            OperatingSystemCommand::SizedText(t) => assert_eq!(t.size.font_scale(), 0.5),
+            _ => unreachable!(),
+        }
+
+        // Out of range values are not understood
+        assert_eq!(
+            parse(&["66", "s=8", "x"], "\x1b]66;s=8;x\x1b\\"),
+            OperatingSystemCommand::Unspecified(vec![
+                b"66".to_vec(),
+                b"s=8".to_vec(),
+                b"x".to_vec()
+            ])
+        );
+    }
+
     #[test]
     fn iterm() {
         assert_eq!(
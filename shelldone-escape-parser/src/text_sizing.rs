@@ -0,0 +1,189 @@
+//! The kitty text sizing protocol, OSC 66.
+//! <https://sw.kovidgoyal.net/kitty/text-sizing-protocol/>
+use crate::{Result, bail};
+use core::fmt::{Display, Formatter, Result as FmtResult};
+use core::str;
+#[cfg(feature = "use_serde")]
+use serde::{Deserialize, Serialize};
+
+use crate::allocate::*;
+
+/// The longest text that may be carried by a single OSC 66 sequence
+pub const MAX_SIZED_TEXT_BYTES: usize = 4096;
+
+/// `v=`: where scaled text is placed within the rows of its block
+#[cfg_attr(feature = "use_serde", derive(Serialize, Deserialize))]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub enum TextSizeVerticalAlign {
+    #[default]
+    Top = 0,
+    Bottom = 1,
+    Centered = 2,
+}
+
+/// `h=`: where scaled text is placed within the columns of its block
+#[cfg_attr(feature = "use_serde", derive(Serialize, Deserialize))]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub enum TextSizeHorizontalAlign {
+    #[default]
+    Left = 0,
+    Right = 1,
+    Centered = 2,
+}
+
+/// The metadata of an OSC 66 sequence, which describes the block
+/// of cells that the text occupies and how the text is scaled
+/// within that block
+#[cfg_attr(feature = "use_serde", derive(Serialize, Deserialize))]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct TextSize {
+    /// `s=`: the block is this many rows tall, and each of the
+    /// `width` columns is this many cells wide.  1 through 7.
+    pub scale: u8,
+    /// `w=`: the width of the block in scaled columns, 0 through 7.
+    /// 0 means that each grapheme occupies its natural width.
+    pub width: u8,
+    /// `n=`: together with `denominator`, further scales the font
+    /// within the block by `numerator / denominator`
+    pub numerator: u8,
+    /// `d=`: the fractional scale applies only when this is
+    /// greater than `numerator`
+    pub denominator: u8,
+    pub vertical_align: TextSizeVerticalAlign,
+    pub horizontal_align: TextSizeHorizontalAlign,
+}
+
+impl Default for TextSize {
+    fn default() -> Self {
+        Self {
+            scale: 1,
+            width: 0,
+            numerator: 0,
+            denominator: 0,
+            vertical_align: TextSizeVerticalAlign::default(),
+            horizontal_align: TextSizeHorizontalAlign::default(),
+        }
+    }
+}
+
+impl TextSize {
+    /// Returns the fractional scale that applies to the font,
+    /// or 1.0 if none was specified
+    pub fn fraction(&self) -> f32 {
+        if self.numerator > 0 && self.denominator > self.numerator {
+            self.numerator as f32 / self.denominator as f32
+        } else {
+            1.0
+        }
+    }
+
+    /// Returns the overall scale factor for the font
+    pub fn font_scale(&self) -> f32 {
+        self.scale as f32 * self.fraction()
+    }
+}
+
+/// A single OSC 66 sequence
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct SizedText {
+    pub size: TextSize,
+    pub text: String,
+}
+
+impl SizedText {
+    pub fn parse(osc: &[&[u8]]) -> Result<Self> {
+        if osc.len() < 3 {
+            bail!("OSC 66 requires metadata and text");
+        }
+        let mut size = TextSize::default();
+
+        fn number(key: &str, value: &str, max: u8) -> Result<u8> {
+            match value.parse::<u8>() {
+                Ok(n) if n <= max => Ok(n),
+                _ => bail!("invalid OSC 66 {}={}", key, value),
+            }
+        }
+
+        for item in osc[1].split(|&b| b == b':') {
+            if item.is_empty() {
+                continue;
+            }
+            let item = str::from_utf8(item)?;
+            let (key, value) = match item.split_once('=') {
+                Some(pair) => pair,
+                None => bail!("invalid OSC 66 metadata {}", item),
+            };
+            match key {
+                "s" => {
+                    size.scale = number(key, value, 7)?;
+                    if size.scale == 0 {
+                        bail!("invalid OSC 66 s={}", value);
+                    }
+                }
+                "w" => size.width = number(key, value, 7)?,
+                "n" => size.numerator = number(key, value, 15)?,
+                "d" => size.denominator = number(key, value, 15)?,
+                "v" => {
+                    size.vertical_align = match number(key, value, 2)? {
+                        0 => TextSizeVerticalAlign::Top,
+                        1 => TextSizeVerticalAlign::Bottom,
+                        _ => TextSizeVerticalAlign::Centered,
+                    }
+                }
+                "h" => {
+                    size.horizontal_align = match number(key, value, 2)? {
+                        0 => TextSizeHorizontalAlign::Left,
+                        1 => TextSizeHorizontalAlign::Right,
+                        _ => TextSizeHorizontalAlign::Centered,
+                    }
+                }
+                // Unknown keys are ignored so that future extensions
+                // degrade gracefully
+                _ => {}
+            }
+        }
+
+        // The text may legitimately contain `;`
+        let mut text = vec![];
+        for (idx, chunk) in osc.iter().skip(2).enumerate() {
+            if idx > 0 {
+                text.push(b';');
+            }
+            text.extend_from_slice(chunk);
+        }
+        if text.len() > MAX_SIZED_TEXT_BYTES {
+            bail!("OSC 66 text is longer than {} bytes", MAX_SIZED_TEXT_BYTES);
+        }
+        let text = String::from_utf8(text)
+            .map_err(|err| crate::format_err!("OSC 66 text is not UTF-8: {:#}", err))?;
+
+        Ok(Self { size, text })
+    }
+}
+
+impl Display for SizedText {
+    fn fmt(&self, f: &mut Formatter) -> FmtResult {
+        let size = &self.size;
+        let default = TextSize::default();
+        let mut items: Vec<String> = vec![];
+        if size.scale != default.scale {
+            items.push(format!("s={}", size.scale));
+        }
+        if size.width != default.width {
+            items.push(format!("w={}", size.width));
+        }
+        if size.numerator != default.numerator {
+            items.push(format!("n={}", size.numerator));
+        }
+        if size.denominator != default.denominator {
+            items.push(format!("d={}", size.denominator));
+        }
+        if size.vertical_align != default.vertical_align {
+            items.push(format!("v={}", size.vertical_align as u8));
+        }
+        if size.horizontal_align != default.horizontal_align {
+            items.push(format!("h={}", size.horizontal_align as u8));
+        }
+        write!(f, "66;{};{}", items.join(":"), self.text)
+    }
+}
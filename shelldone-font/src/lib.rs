@@ -443,6 +443,7 @@ impl FallbackResolveInfo {
                     ),
                     url: Some(url.to_string()),
                     timeout: Some(Duration::from_secs(15)),
+                    ..Default::default()
                 }
                 .show();
             } else {
@@ -271,3 +271,53 @@ pub struct ShowKeysCommand {
     #[arg(long)]
     pub key_table: Option<String>,
 }
+
+#[derive(Debug, Parser, Clone)]
+#[command(trailing_var_arg = true)]
+pub struct RenderCommand {
+    /// Write the rendered image to this PNG file
+    #[arg(long, value_parser, value_hint=ValueHint::FilePath)]
+    pub snapshot: PathBuf,
+
+    /// Compare the rendered image with this golden PNG image, and
+    /// fail if they differ.  The actual image and a diff image are
+    /// written alongside the golden image to help track down
+    /// the difference.
+    #[arg(long, value_parser, value_hint=ValueHint::FilePath)]
+    pub golden: Option<PathBuf>,
+
+    /// The difference in each color channel, out of 255, that is
+    /// tolerated when comparing with the golden image
+    #[arg(long, default_value = "0", requires = "golden")]
+    pub tolerance: u8,
+
+    /// Render the whole window, including its padding and tab bar,
+    /// rather than just the pane
+    #[arg(long)]
+    pub window: bool,
+
+    /// The number of columns in the pane.
+    /// The default is taken from the initial_cols configuration.
+    #[arg(long)]
+    pub cols: Option<usize>,
+
+    /// The number of rows in the pane.
+    /// The default is taken from the initial_rows configuration.
+    #[arg(long)]
+    pub rows: Option<usize>,
+
+    /// The number of seconds to wait for the program to exit.
+    /// A program that is still running at that point is rendered
+    /// as it is, and is then killed.
+    #[arg(long, default_value = "10")]
+    pub timeout: u64,
+
+    /// Specify the current working directory for the program
+    #[arg(long = "cwd", value_parser, value_hint=ValueHint::DirPath)]
+    pub cwd: Option<PathBuf>,
+
+    /// Instead of executing your shell, run PROG.
+    /// For example: `shelldone render --snapshot out.png -- ls --color`
+    #[arg(value_parser, value_hint=ValueHint::CommandWithArguments, num_args=1..)]
+    pub prog: Vec<OsString>,
+}
@@ -1406,6 +1406,78 @@ pub fn derive_command_from_key_assignment(action: &KeyAssignment) -> Option<Comm
                 icon: Some("oct_terminal"),
             }
         }
+        ActivateCommandBlockRelative(n) => {
+            let (direction, amount) = if *n < 0 {
+                ("previous", -n)
+            } else {
+                ("next", *n)
+            };
+            CommandDef {
+                brief: format!("Activate {direction} command block").into(),
+                doc: format!(
+                    "Activates the command block {amount} command(s) \
+                     in the {direction} direction and scrolls it into view"
+                )
+                .into(),
+                keys: vec![],
+                args: &[ArgType::Pane],
+                menubar: &[],
+                icon: Some("oct_terminal"),
+            }
+        }
+        CopyCommandBlockTo { part, destination } => {
+            let part = match part {
+                CommandBlockPart::Command => "command line",
+                CommandBlockPart::Output => "output",
+            };
+            let destination = match destination {
+                ClipboardCopyDestination::Clipboard => "clipboard",
+                ClipboardCopyDestination::PrimarySelection => "primary selection",
+                ClipboardCopyDestination::ClipboardAndPrimarySelection => {
+                    "clipboard and primary selection"
+                }
+            };
+            CommandDef {
+                brief: format!("Copy command block {part}").into(),
+                doc: format!(
+                    "Copies the {part} of the active command block \
+                     to the {destination}"
+                )
+                .into(),
+                keys: vec![],
+                args: &[ArgType::Pane],
+                menubar: &[],
+                icon: Some("md_content_copy"),
+            }
+        }
+        RerunCommandBlock => CommandDef {
+            brief: "Re-run command block".into(),
+            doc: "Sends the command line of the active command block \
+                  to the pane to run it again"
+                .into(),
+            keys: vec![],
+            args: &[ArgType::Pane],
+            menubar: &[],
+            icon: Some("md_reload"),
+        },
+        ShowCommandHistory => CommandDef {
+            brief: "Search command history".into(),
+            doc: "Fuzzy searches the commands that were run at the shell \
+                  prompt of any pane, and inserts the chosen command"
+                .into(),
+            keys: vec![],
+            args: &[ArgType::Pane],
+            menubar: &[],
+            icon: Some("md_history"),
+        },
+        ToggleCommandBlockCollapsed => CommandDef {
+            brief: "Toggle command block collapsed".into(),
+            doc: "Collapses or expands the output of the active command block".into(),
+            keys: vec![],
+            args: &[ArgType::Pane],
+            menubar: &[],
+            icon: Some("oct_terminal"),
+        },
         ScrollByCurrentEventWheelDelta => CommandDef {
             brief: "Scrolls based on the mouse wheel position \
                 in the current mouse event"
@@ -2003,6 +2075,103 @@ pub fn derive_command_from_key_assignment(action: &KeyAssignment) -> Option<Comm
             menubar: &["Edit"],
             icon: None,
         },
+        ToggleBroadcastInput(scope) => CommandDef {
+            brief: match scope {
+                BroadcastInputScope::CurrentTab => "Toggle input broadcast to all panes in tab",
+                BroadcastInputScope::CurrentWorkspace => {
+                    "Toggle input broadcast to all panes in workspace"
+                }
+                BroadcastInputScope::MarkedPanes => "Toggle input broadcast to marked panes",
+            }
+            .into(),
+            doc: "Sends keyboard input, paste and SendString actions \
+                  to every pane in the set, not just the active pane"
+                .into(),
+            keys: vec![],
+            args: &[ArgType::Pane],
+            menubar: &["Window", "Broadcast Input"],
+            icon: Some("md_broadcast"),
+        },
+        TogglePaneBroadcastMark => CommandDef {
+            brief: "Mark/unmark pane for input broadcast".into(),
+            doc: "Adds or removes the current pane from the set of panes \
+                  used when broadcasting input to marked panes"
+                .into(),
+            keys: vec![],
+            args: &[ArgType::Pane],
+            menubar: &["Window", "Broadcast Input"],
+            icon: None,
+        },
+        TogglePaneActivityMonitor => CommandDef {
+            brief: "Toggle activity monitor for pane".into(),
+            doc: "Alerts when the current pane produces output \
+                  while it is not focused"
+                .into(),
+            keys: vec![],
+            args: &[ArgType::Pane],
+            menubar: &["Window", "Monitor Pane"],
+            icon: Some("md_pulse"),
+        },
+        TogglePaneSilenceMonitor(secs) => CommandDef {
+            brief: format!("Toggle {secs}s silence monitor for pane").into(),
+            doc: format!(
+                "Alerts when the current pane has produced no output \
+                 for {secs} seconds"
+            )
+            .into(),
+            keys: vec![],
+            args: &[ArgType::Pane],
+            menubar: &["Window", "Monitor Pane"],
+            icon: Some("md_bell_sleep"),
+        },
+        TogglePaneRecording => CommandDef {
+            brief: "Start/stop recording pane".into(),
+            doc: "Records the current pane as an asciicast, \
+                  or stops the recording that is running"
+                .into(),
+            keys: vec![],
+            args: &[ArgType::Pane],
+            menubar: &["Shell"],
+            icon: Some("md_record_rec"),
+        },
+        ShowPlaybackControls => CommandDef {
+            brief: "Show playback controls".into(),
+            doc: "Shows the position, speed and markers of the asciicast \
+                  that is playing in the current pane"
+                .into(),
+            keys: vec![],
+            args: &[ArgType::Pane],
+            menubar: &["Shell"],
+            icon: Some("md_play_pause"),
+        },
+        JoinPane(args) => CommandDef {
+            brief: match args.tab_index {
+                Some(idx) => format!("Move pane into tab {idx}"),
+                None => "Move pane into the last active tab".to_string(),
+            }
+            .into(),
+            doc: format!(
+                "Moves the current pane into a split {:?} of the active pane of another tab",
+                args.direction
+            )
+            .into(),
+            keys: vec![],
+            args: &[ArgType::Pane],
+            menubar: &["Window"],
+            icon: Some("cod_split_horizontal"),
+        },
+        MoveTabToWindow(args) => CommandDef {
+            brief: match args.window_index {
+                Some(idx) => format!("Move tab to window {idx}"),
+                None => "Move tab to a new window".to_string(),
+            }
+            .into(),
+            doc: "Moves the current tab, along with all of its panes, into another window".into(),
+            keys: vec![],
+            args: &[ArgType::Tab],
+            menubar: &["Window", "Move Tab"],
+            icon: Some("cod_multiple_windows"),
+        },
     })
 }
 
@@ -2055,6 +2224,19 @@ fn compute_default_actions() -> Vec<KeyAssignment> {
         ScrollByPage(NotNan::new(1.0).unwrap()),
         ScrollToTop,
         ScrollToBottom,
+        ActivateCommandBlockRelative(-1),
+        ActivateCommandBlockRelative(1),
+        CopyCommandBlockTo {
+            part: CommandBlockPart::Command,
+            destination: ClipboardCopyDestination::Clipboard,
+        },
+        CopyCommandBlockTo {
+            part: CommandBlockPart::Output,
+            destination: ClipboardCopyDestination::Clipboard,
+        },
+        RerunCommandBlock,
+        ToggleCommandBlockCollapsed,
+        ShowCommandHistory,
         // ----------------- Window
         ToggleFullScreen,
         ToggleAlwaysOnTop,
@@ -2116,6 +2298,13 @@ fn compute_default_actions() -> Vec<KeyAssignment> {
         ActivateWindowRelative(1),
         MoveTabRelative(-1),
         MoveTabRelative(1),
+        MoveTabToWindow(MoveTabToWindowArguments::default()),
+        JoinPane(JoinPaneArguments {
+            direction: PaneDirection::Right,
+            size: SplitSize::default(),
+            top_level: false,
+            tab_index: None,
+        }),
         AdjustPaneSize(PaneDirection::Left, 1),
         AdjustPaneSize(PaneDirection::Right, 1),
         AdjustPaneSize(PaneDirection::Up, 1),
@@ -2125,6 +2314,14 @@ fn compute_default_actions() -> Vec<KeyAssignment> {
         ActivatePaneDirection(PaneDirection::Up),
         ActivatePaneDirection(PaneDirection::Down),
         TogglePaneZoomState,
+        ToggleBroadcastInput(BroadcastInputScope::CurrentTab),
+        ToggleBroadcastInput(BroadcastInputScope::CurrentWorkspace),
+        ToggleBroadcastInput(BroadcastInputScope::MarkedPanes),
+        TogglePaneBroadcastMark,
+        TogglePaneActivityMonitor,
+        TogglePaneSilenceMonitor(30),
+        TogglePaneRecording,
+        ShowPlaybackControls,
         ActivateLastTab,
         ShowLauncher,
         ShowTabNavigator,
@@ -7,15 +7,20 @@ use anyhow::{Context, Error};
 use config::keyassignment::{KeyAssignment, SpawnCommand};
 use config::{ConfigSubscription, NotificationHandling};
 use mux::client::ClientId;
+use mux::pane::PaneId;
+use mux::tab::TabId;
 use mux::window::WindowId as MuxWindowId;
 use mux::{Mux, MuxNotification, SpawnRequest};
 use promise::{Future, Promise};
-use shelldone_term::{Alert, ClipboardSelection};
+use shelldone_escape_parser::kitty_notification::KittyNotification;
+use shelldone_escape_parser::OperatingSystemCommand;
+use shelldone_term::{Alert, ClipboardSelection, NotificationOccasion, NotificationUrgency};
 use shelldone_toast_notification::*;
 use std::cell::RefCell;
 use std::collections::{BTreeMap, HashSet};
+use std::io::Write;
 use std::rc::Rc;
-use std::sync::Arc;
+use std::sync::{Arc, Mutex};
 
 pub struct GuiFrontEnd {
     connection: Rc<Connection>,
@@ -24,6 +29,9 @@ pub struct GuiFrontEnd {
     known_windows: RefCell<BTreeMap<Window, MuxWindowId>>,
     client_id: Arc<ClientId>,
     config_subscription: RefCell<Option<ConfigSubscription>>,
+    /// The mux window of the gui window that has keyboard focus,
+    /// shared with the mux subscription that shows notifications
+    focused_window: Arc<Mutex<Option<MuxWindowId>>>,
 }
 
 impl Drop for GuiFrontEnd {
@@ -39,6 +47,7 @@ impl GuiFrontEnd {
 
         let mux = Mux::get();
         let client_id = mux.active_identity().expect("to have set my own id");
+        let focused_window = Arc::new(Mutex::new(None));
 
         let front_end = Rc::new(GuiFrontEnd {
             connection,
@@ -47,6 +56,7 @@ impl GuiFrontEnd {
             known_windows: RefCell::new(BTreeMap::new()),
             client_id: client_id.clone(),
             config_subscription: RefCell::new(None),
+            focused_window: Arc::clone(&focused_window),
         });
 
         mux.subscribe(move |n| {
@@ -97,42 +107,34 @@ impl GuiFrontEnd {
                 MuxNotification::PaneAdded(_) => {}
                 MuxNotification::Alert {
                     pane_id,
-                    alert:
-                        Alert::ToastNotification {
-                            title,
-                            body,
-                            focus: _,
-                        },
+                    alert: alert @ Alert::ToastNotification { .. },
                 } => {
-                    let mux = Mux::get();
-
-                    if let Some((_domain, window_id, tab_id)) = mux.resolve_pane_id(pane_id) {
-                        let config = config::configuration();
-
-                        if let Some((_fdomain, f_window, f_tab, f_pane)) =
-                            mux.resolve_focused_pane(&client_id)
+                    let focused_window = *focused_window.lock().unwrap();
+                    if let Some(notif) = toast_for_pane(&client_id, focused_window, pane_id, alert)
+                    {
+                        notif.show();
+                    }
+                }
+                MuxNotification::Alert {
+                    pane_id,
+                    alert: alert @ (Alert::Activity | Alert::Silence(_)),
+                } => {
+                    if config::configuration().monitor_alert_toast_notification {
+                        let alert = monitor_toast(pane_id, &alert);
+                        let focused_window = *focused_window.lock().unwrap();
+                        if let Some(notif) =
+                            toast_for_pane(&client_id, focused_window, pane_id, alert)
                         {
-                            let show = match config.notification_handling {
-                                NotificationHandling::NeverShow => false,
-                                NotificationHandling::AlwaysShow => true,
-                                NotificationHandling::SuppressFromFocusedPane => f_pane != pane_id,
-                                NotificationHandling::SuppressFromFocusedTab => f_tab != tab_id,
-                                NotificationHandling::SuppressFromFocusedWindow => {
-                                    f_window != window_id
-                                }
-                            };
-
-                            if show {
-                                let message = if title.is_none() { "" } else { &body };
-                                let title = title.as_ref().unwrap_or(&body);
-                                // FIXME: if notification.focus is true, we should do
-                                // something here to arrange to focus pane_id when the
-                                // notification is clicked
-                                persistent_toast_notification(title, message);
-                            }
+                            notif.show();
                         }
                     }
                 }
+                MuxNotification::Alert {
+                    pane_id,
+                    alert: Alert::CloseToastNotification { id },
+                } => {
+                    shelldone_toast_notification::close(&toast_tag(pane_id, &id));
+                }
                 MuxNotification::Alert {
                     pane_id: _,
                     alert: Alert::Bell | Alert::Progress(_),
@@ -148,7 +150,9 @@ impl GuiFrontEnd {
                         | Alert::WindowTitleChanged(_)
                         | Alert::TabTitleChanged(_)
                         | Alert::IconTitleChanged(_)
-                        | Alert::SetUserVar { .. },
+                        | Alert::SetUserVar { .. }
+                        | Alert::TriggerEvent { .. }
+                        | Alert::CommandFinished(_),
                 } => {}
                 MuxNotification::Empty => {
                     if config::configuration().quit_when_all_windows_are_closed {
@@ -201,6 +205,44 @@ impl GuiFrontEnd {
                     })
                     .detach();
                 }
+                MuxNotification::AssignClipboardData {
+                    pane_id,
+                    selection,
+                    data,
+                } => {
+                    promise::spawn::spawn_into_main_thread(async move {
+                        let fe = crate::frontend::front_end();
+                        log::trace!(
+                            "set clipboard data in pane {} {:?} {:?}",
+                            pane_id,
+                            selection,
+                            data.iter().map(|d| &d.mime).collect::<Vec<_>>()
+                        );
+                        if let Some(window) = fe.known_windows.borrow().keys().next() {
+                            window.set_clipboard_data(
+                                match selection {
+                                    ClipboardSelection::Clipboard => Clipboard::Clipboard,
+                                    ClipboardSelection::PrimarySelection => {
+                                        Clipboard::PrimarySelection
+                                    }
+                                },
+                                data.iter()
+                                    .map(|d| ClipboardData {
+                                        mime: d.mime.clone(),
+                                        data: d.data.clone(),
+                                    })
+                                    .collect(),
+                            );
+                        } else {
+                            log::error!("Cannot assign clipboard as there are no windows");
+                        };
+                    })
+                    .detach();
+                }
+                MuxNotification::RequestClipboard { .. }
+                | MuxNotification::RequestFileTransfer { .. } => {
+                    // Handled by the TermWindow that contains the pane
+                }
             }
             true
         });
@@ -472,6 +514,16 @@ impl GuiFrontEnd {
         *self.switching_workspaces.borrow()
     }
 
+    /// Called when a gui window gains or loses keyboard focus
+    pub fn record_window_focus(&self, mux_window_id: MuxWindowId, focused: bool) {
+        let mut focused_window = self.focused_window.lock().unwrap();
+        if focused {
+            focused_window.replace(mux_window_id);
+        } else if *focused_window == Some(mux_window_id) {
+            focused_window.take();
+        }
+    }
+
     pub fn gui_window_for_mux_window(&self, mux_window_id: MuxWindowId) -> Option<GuiWin> {
         let windows = self.known_windows.borrow();
         for (window, v) in windows.iter() {
@@ -490,6 +542,171 @@ thread_local! {
     static FRONT_END: RefCell<Option<Rc<GuiFrontEnd>>> = const { RefCell::new(None) };
 }
 
+/// Notifications are tagged with the pane that produced them, as
+/// the ids assigned by applications are only unique within a pane
+fn toast_tag(pane_id: PaneId, id: &str) -> String {
+    format!("{pane_id}:{id}")
+}
+
+/// Describes an alert raised by the activity or silence monitor
+/// of `pane_id` as a notification.  Subsequent alerts from the same
+/// pane replace it.
+fn monitor_toast(pane_id: PaneId, alert: &Alert) -> Alert {
+    let title = Mux::get().get_pane(pane_id).map(|pane| pane.get_title());
+    let body = match alert {
+        Alert::Silence(interval) => format!("No output for {}s", interval.as_secs()),
+        _ => "New output".to_string(),
+    };
+    Alert::ToastNotification {
+        title,
+        body,
+        focus: true,
+        id: Some("monitor".to_string()),
+        urgency: NotificationUrgency::Normal,
+        occasion: NotificationOccasion::Always,
+        report_activation: false,
+        report_close: false,
+        timeout: None,
+    }
+}
+
+/// Decides whether the notification produced by `pane_id` should be
+/// shown and, if so, returns the notification to show.
+/// `focused_window` is the mux window whose gui window has keyboard
+/// focus, if any.
+fn toast_for_pane(
+    client_id: &ClientId,
+    focused_window: Option<MuxWindowId>,
+    pane_id: PaneId,
+    alert: Alert,
+) -> Option<ToastNotification> {
+    let Alert::ToastNotification {
+        title,
+        body,
+        focus,
+        id,
+        urgency,
+        occasion,
+        report_activation,
+        report_close,
+        timeout,
+    } = alert
+    else {
+        return None;
+    };
+
+    let mux = Mux::get();
+    let (_domain, window_id, tab_id) = mux.resolve_pane_id(pane_id)?;
+    let (_fdomain, f_window, f_tab, f_pane) = mux.resolve_focused_pane(client_id)?;
+    let config = config::configuration();
+
+    let show = match config.notification_handling {
+        NotificationHandling::NeverShow => false,
+        NotificationHandling::AlwaysShow => true,
+        NotificationHandling::SuppressFromFocusedPane => f_pane != pane_id,
+        NotificationHandling::SuppressFromFocusedTab => f_tab != tab_id,
+        NotificationHandling::SuppressFromFocusedWindow => f_window != window_id,
+    };
+    // The application can further restrict when it is shown
+    let show = show
+        && match occasion {
+            NotificationOccasion::Always => true,
+            NotificationOccasion::Unfocused => {
+                f_pane != pane_id || focused_window != Some(window_id)
+            }
+            NotificationOccasion::Invisible => !is_tab_visible(window_id, tab_id),
+        };
+    if !show {
+        return None;
+    }
+
+    let message = if title.is_none() { "" } else { &body };
+    let title = title.as_ref().unwrap_or(&body);
+
+    let on_activate = if focus || report_activation {
+        let id = id.clone();
+        Some(NotificationCallback::new(move || {
+            let id = id.clone();
+            promise::spawn::spawn_into_main_thread(async move {
+                if focus {
+                    focus_pane(pane_id, window_id);
+                }
+                if let (true, Some(id)) = (report_activation, id) {
+                    report_to_pane(pane_id, KittyNotification::activated(&id));
+                }
+            })
+            .detach();
+        }))
+    } else {
+        None
+    };
+    let on_close = match (&id, report_close) {
+        (Some(id), true) => {
+            let id = id.clone();
+            Some(NotificationCallback::new(move || {
+                let id = id.clone();
+                promise::spawn::spawn_into_main_thread(async move {
+                    report_to_pane(pane_id, KittyNotification::closed(&id));
+                })
+                .detach();
+            }))
+        }
+        _ => None,
+    };
+
+    Some(ToastNotification {
+        title: title.to_string(),
+        message: message.to_string(),
+        url: None,
+        timeout,
+        urgency: Some(match urgency {
+            NotificationUrgency::Low => Urgency::Low,
+            NotificationUrgency::Normal => Urgency::Normal,
+            NotificationUrgency::Critical => Urgency::Critical,
+        }),
+        tag: id.as_deref().map(|id| toast_tag(pane_id, id)),
+        on_activate,
+        on_close,
+    })
+}
+
+/// Returns true if `tab_id` is the active tab of `window_id`
+/// and that window is in the active workspace
+fn is_tab_visible(window_id: MuxWindowId, tab_id: TabId) -> bool {
+    let mux = Mux::get();
+    let active_workspace = mux.active_workspace();
+    match mux.get_window(window_id) {
+        Some(window) => {
+            window.get_workspace() == active_workspace
+                && window.get_active().map(|tab| tab.tab_id()) == Some(tab_id)
+        }
+        None => false,
+    }
+}
+
+fn focus_pane(pane_id: PaneId, window_id: MuxWindowId) {
+    let mux = Mux::get();
+    if let Err(err) = mux.focus_pane_and_containing_tab(pane_id) {
+        log::error!("Error focusing pane {pane_id} for notification: {err:#}");
+        return;
+    }
+    if let Some(gui_win) = front_end().gui_window_for_mux_window(window_id) {
+        gui_win.window.focus();
+    }
+}
+
+/// Sends an OSC 99 report about a notification back to the
+/// application running in the pane
+fn report_to_pane(pane_id: PaneId, notif: KittyNotification) {
+    let mux = Mux::get();
+    if let Some(pane) = mux.get_pane(pane_id) {
+        let report = OperatingSystemCommand::KittyNotification(notif);
+        if let Err(err) = write!(pane.writer(), "{}", report) {
+            log::error!("Error reporting notification to pane {pane_id}: {err:#}");
+        }
+    }
+}
+
 pub fn try_front_end() -> Option<Rc<GuiFrontEnd>> {
     FRONT_END.with(|f| f.borrow().as_ref().map(Rc::clone))
 }
@@ -0,0 +1,505 @@
+//! Renders panes, and windows with their tab bar, into images without
+//! a display or a gpu.
+//!
+//! The window is painted by the same code as the gui, with its quads
+//! rasterized on the CPU rather than drawn by the gpu, so that the
+//! rendered image matches what the gui shows, short of the fine details
+//! of the gpu blending.  This is used by `shelldone render` to take
+//! snapshots of the output of a program in tests and CI, and to compare
+//! those snapshots with golden images.
+use crate::termwindow::TermWindow;
+use crate::utilsprites::RenderMetrics;
+use ::window::bitmaps::{BitmapImage, Image};
+use anyhow::{anyhow, Context};
+use config::{ConfigHandle, ExitBehavior};
+use mux::activity::Activity;
+use mux::domain::{Domain, LocalDomain};
+use mux::Mux;
+use portable_pty::cmdbuilder::CommandBuilder;
+use shelldone_font::FontConfiguration;
+use shelldone_gui_subcommands::RenderCommand;
+use shelldone_term::TerminalSize;
+use std::cell::RefCell;
+use std::path::{Path, PathBuf};
+use std::rc::Rc;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+/// How often `shelldone render` checks whether the program has exited
+const POLL_INTERVAL: Duration = Duration::from_millis(20);
+
+/// How long the output of a program must have been unchanged, after
+/// the program has exited, before it is rendered.  This allows the
+/// last of its output to make its way through the parser.
+const SETTLE_INTERVAL: Duration = Duration::from_millis(100);
+
+/// Set this to write the rendered image to the golden image,
+/// rather than comparing with it
+pub const UPDATE_GOLDEN_ENV: &str = "SHELLDONE_UPDATE_GOLDEN";
+
+/// Writes an image to a PNG file
+pub fn save_png(image: &Image, path: &Path) -> anyhow::Result<()> {
+    to_rgba_image(image)
+        .save(path)
+        .with_context(|| format!("writing {}", path.display()))
+}
+
+fn to_rgba_image(image: &Image) -> ::image::RgbaImage {
+    let (width, height) = image.image_dimensions();
+    ::image::RgbaImage::from_raw(
+        width as u32,
+        height as u32,
+        image.pixel_data_slice().to_vec(),
+    )
+    .expect("image data to match its dimensions")
+}
+
+/// Describes how an image differs from the image that was expected
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ImageMismatch {
+    Size {
+        actual: (u32, u32),
+        expected: (u32, u32),
+    },
+    Pixels {
+        differing: usize,
+        max_delta: u8,
+    },
+}
+
+impl std::fmt::Display for ImageMismatch {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            Self::Size { actual, expected } => write!(
+                f,
+                "image is {}x{} but {}x{} was expected",
+                actual.0, actual.1, expected.0, expected.1
+            ),
+            Self::Pixels {
+                differing,
+                max_delta,
+            } => write!(
+                f,
+                "{differing} pixels differ, by up to {max_delta} in a color channel"
+            ),
+        }
+    }
+}
+
+/// Compares two images, allowing each color channel of each pixel
+/// to differ by up to `tolerance`
+pub fn compare_images(
+    actual: &::image::RgbaImage,
+    expected: &::image::RgbaImage,
+    tolerance: u8,
+) -> Result<(), ImageMismatch> {
+    if actual.dimensions() != expected.dimensions() {
+        return Err(ImageMismatch::Size {
+            actual: actual.dimensions(),
+            expected: expected.dimensions(),
+        });
+    }
+    let mut differing = 0;
+    let mut max_delta = 0;
+    for (a, e) in actual.pixels().zip(expected.pixels()) {
+        let delta =
+            a.0.iter()
+                .zip(e.0.iter())
+                .map(|(a, e)| a.abs_diff(*e))
+                .max()
+                .unwrap_or(0);
+        if delta > tolerance {
+            differing += 1;
+            max_delta = max_delta.max(delta);
+        }
+    }
+    if differing > 0 {
+        Err(ImageMismatch::Pixels {
+            differing,
+            max_delta,
+        })
+    } else {
+        Ok(())
+    }
+}
+
+/// Returns the expected image, dimmed, with the pixels that differ
+/// from the actual image highlighted
+fn diff_image(
+    actual: &::image::RgbaImage,
+    expected: &::image::RgbaImage,
+    tolerance: u8,
+) -> ::image::RgbaImage {
+    ::image::RgbaImage::from_fn(expected.width(), expected.height(), |x, y| {
+        let e = expected.get_pixel(x, y);
+        let differs = match actual.get_pixel_checked(x, y) {
+            Some(a) => {
+                a.0.iter()
+                    .zip(e.0.iter())
+                    .any(|(a, e)| a.abs_diff(*e) > tolerance)
+            }
+            None => true,
+        };
+        if differs {
+            ::image::Rgba([0xff, 0, 0xff, 0xff])
+        } else {
+            ::image::Rgba([e[0] / 3, e[1] / 3, e[2] / 3, 0xff])
+        }
+    })
+}
+
+fn sibling_path(path: &Path, suffix: &str) -> PathBuf {
+    let stem = path
+        .file_stem()
+        .map(|s| s.to_string_lossy().to_string())
+        .unwrap_or_default();
+    path.with_file_name(format!("{stem}.{suffix}.png"))
+}
+
+/// Compares an image with the golden image stored at `golden`.
+/// When the `SHELLDONE_UPDATE_GOLDEN` environment variable is set,
+/// the golden image is written from `actual` instead.
+/// When they differ, the actual image and a diff image are written
+/// next to the golden image, as `NAME.actual.png` and `NAME.diff.png`.
+pub fn compare_with_golden(actual: &Image, golden: &Path, tolerance: u8) -> anyhow::Result<()> {
+    compare_rgba_with_golden(&to_rgba_image(actual), golden, tolerance)
+}
+
+fn compare_rgba_with_golden(
+    actual: &::image::RgbaImage,
+    golden: &Path,
+    tolerance: u8,
+) -> anyhow::Result<()> {
+    if std::env::var_os(UPDATE_GOLDEN_ENV).map_or(false, |v| !v.is_empty()) {
+        return actual
+            .save(golden)
+            .with_context(|| format!("writing {}", golden.display()));
+    }
+
+    let expected = ::image::open(golden)
+        .with_context(|| {
+            format!(
+                "reading golden image {}; set {UPDATE_GOLDEN_ENV}=1 to create it",
+                golden.display()
+            )
+        })?
+        .to_rgba8();
+
+    if let Err(mismatch) = compare_images(actual, &expected, tolerance) {
+        let actual_path = sibling_path(golden, "actual");
+        let diff_path = sibling_path(golden, "diff");
+        actual
+            .save(&actual_path)
+            .with_context(|| format!("writing {}", actual_path.display()))?;
+        if let ImageMismatch::Pixels { .. } = mismatch {
+            diff_image(actual, &expected, tolerance)
+                .save(&diff_path)
+                .with_context(|| format!("writing {}", diff_path.display()))?;
+        }
+        anyhow::bail!(
+            "rendered image does not match {}: {mismatch}. The rendered image \
+             was written to {}",
+            golden.display(),
+            actual_path.display()
+        );
+    }
+    Ok(())
+}
+
+/// Computes the size of a pane of `cols` by `rows` cells, or of the
+/// configured initial size, for the fonts of the configuration
+fn pane_size(
+    config: &ConfigHandle,
+    cols: Option<usize>,
+    rows: Option<usize>,
+) -> anyhow::Result<TerminalSize> {
+    let dpi = config.dpi.unwrap_or_else(::window::default_dpi) as usize;
+    let fontconfig = FontConfiguration::new(Some(config.clone()), dpi)?;
+    let metrics = RenderMetrics::new(&fontconfig)?;
+    let cell_width = metrics.cell_size.width as usize;
+    let cell_height = metrics.cell_size.height as usize;
+
+    let mut size = config.initial_size(dpi as u32, Some((cell_width, cell_height)));
+    if let Some(cols) = cols {
+        size.cols = cols;
+    }
+    if let Some(rows) = rows {
+        size.rows = rows;
+    }
+    size.pixel_width = size.cols * cell_width;
+    size.pixel_height = size.rows * cell_height;
+    Ok(size)
+}
+
+pub fn run_render(config: ConfigHandle, cmd: RenderCommand) -> anyhow::Result<()> {
+    config::configuration_result()?;
+
+    // Disable the normal config error UI window, as we don't have
+    // a fully baked GUI environment running
+    config::assign_error_callback(|err| eprintln!("{}", err));
+
+    // A pane that is held open after its program exits shows a message
+    // about that, which has no place in the snapshot
+    let mut config = (*config).clone();
+    config.exit_behavior = ExitBehavior::Close;
+    config::use_this_configuration(config);
+    let config = config::configuration();
+
+    let domain: Arc<dyn Domain> = Arc::new(LocalDomain::new("local")?);
+    let mux = Arc::new(Mux::new(Some(domain)));
+    Mux::set_mux(&mux);
+
+    let executor = promise::spawn::SimpleExecutor::new();
+    let result = Rc::new(RefCell::new(None));
+    {
+        let result = Rc::clone(&result);
+        promise::spawn::spawn(async move {
+            let res = async_run_render(config, cmd).await;
+            result.borrow_mut().replace(res);
+        })
+        .detach();
+    }
+
+    loop {
+        executor.tick()?;
+        if let Some(res) = result.borrow_mut().take() {
+            return res;
+        }
+    }
+}
+
+async fn async_run_render(config: ConfigHandle, cmd: RenderCommand) -> anyhow::Result<()> {
+    let size = pane_size(&config, cmd.cols, cmd.rows)?;
+
+    // Keep the tab and the window of the pane around after the program
+    // exits, so that there is something left to render
+    let _activity = Activity::new();
+
+    let mux = Mux::get();
+    let window_id = mux.new_empty_window(None, None);
+    let domain = mux.default_domain();
+    domain.attach(Some(*window_id)).await?;
+
+    let prog = if cmd.prog.is_empty() {
+        None
+    } else {
+        Some(CommandBuilder::from_argv(cmd.prog.clone()))
+    };
+    let cwd = cmd
+        .cwd
+        .as_ref()
+        .map(|cwd| cwd.to_string_lossy().to_string());
+    let tab = domain.spawn(size, prog, cwd, *window_id).await?;
+    let pane = tab
+        .get_active_pane()
+        .ok_or_else(|| anyhow!("spawned tab has no active pane"))?;
+
+    let mut seqno = pane.get_current_seqno();
+    let mut last_change = Instant::now();
+    let deadline = Instant::now() + Duration::from_secs(cmd.timeout);
+
+    loop {
+        smol::Timer::after(POLL_INTERVAL).await;
+
+        let current_seqno = pane.get_current_seqno();
+        if current_seqno != seqno {
+            seqno = current_seqno;
+            last_change = Instant::now();
+        }
+
+        if pane.is_dead() && last_change.elapsed() >= SETTLE_INTERVAL {
+            break;
+        }
+        if Instant::now() >= deadline {
+            log::warn!(
+                "program is still running after {} seconds; rendering it as it is",
+                cmd.timeout
+            );
+            pane.kill();
+            break;
+        }
+    }
+
+    let mut window = TermWindow::new_headless(*window_id)?;
+    let image = if cmd.window {
+        window.paint_to_image().await?
+    } else {
+        window.paint_pane_to_image().await?
+    };
+
+    save_png(&image, &cmd.snapshot)?;
+    if let Some(golden) = &cmd.golden {
+        compare_with_golden(&image, golden, cmd.tolerance)?;
+    }
+    Ok(())
+}
+
+/// Renders the output of a sequence of escapes into a pane of the
+/// given size, for tests that check for rendering regressions
+#[cfg(test)]
+pub(crate) fn render_escapes(cols: usize, rows: usize, text: &str) -> anyhow::Result<Image> {
+    use mux::tab::Tab;
+
+    // The mux is global, so render one at a time
+    static RENDER: std::sync::Mutex<()> = std::sync::Mutex::new(());
+    let _guard = RENDER.lock().unwrap_or_else(|err| err.into_inner());
+
+    let _executor = promise::spawn::SimpleExecutor::new();
+    let mux = Arc::new(Mux::new(None));
+    Mux::set_mux(&mux);
+
+    let size = pane_size(&config::configuration(), Some(cols), Some(rows))?;
+    let (_term, pane) = mux::termwiztermtab::allocate(size, Arc::new(config::TermConfig::new()));
+    let mut parser = termwiz::escape::parser::Parser::new();
+    pane.perform_actions(parser.parse_as_vec(text.as_bytes()));
+
+    let tab = Arc::new(Tab::new(&size));
+    tab.assign_pane(&pane);
+    mux.add_tab_no_panes(&tab);
+    let window_id = mux.new_empty_window(None, None);
+    mux.add_tab_to_window(&tab, *window_id)?;
+
+    let mut window = TermWindow::new_headless(*window_id)?;
+    smol::block_on(window.paint_pane_to_image())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn render(cols: usize, rows: usize, text: &str) -> ::image::RgbaImage {
+        to_rgba_image(&render_escapes(cols, rows, text).unwrap())
+    }
+
+    fn golden_path(name: &str) -> PathBuf {
+        Path::new(env!("CARGO_MANIFEST_DIR"))
+            .join("testdata")
+            .join("golden")
+            .join(name)
+    }
+
+    /// Reduces a rendering to one pixel per cell, so that it can be
+    /// compared with a golden image regardless of the size of the font.
+    /// `f` is passed the pixels of each cell.
+    fn per_cell(
+        image: &::image::RgbaImage,
+        cols: u32,
+        rows: u32,
+        f: impl Fn(&::image::RgbaImage) -> ::image::Rgba<u8>,
+    ) -> ::image::RgbaImage {
+        let cell_width = image.width() / cols;
+        let cell_height = image.height() / rows;
+        ::image::RgbaImage::from_fn(cols, rows, |x, y| {
+            f(&::image::imageops::crop_imm(
+                image,
+                x * cell_width,
+                y * cell_height,
+                cell_width,
+                cell_height,
+            )
+            .to_image())
+        })
+    }
+
+    #[test]
+    fn cell_colors_match_golden() {
+        config::use_test_configuration();
+
+        let image = render(
+            8,
+            3,
+            "\x1b[?25l\
+             \x1b[40m \x1b[41m \x1b[42m \x1b[43m \x1b[44m \x1b[45m \x1b[46m \x1b[47m \x1b[0m\r\n\
+             \x1b[100m \x1b[101m \x1b[102m \x1b[103m \x1b[104m \x1b[105m \x1b[106m \x1b[107m \x1b[0m\r\n\
+             \x20\x1b[7m \x1b[0m\x1b[48;2;18;52;86m \x1b[48;5;208m \x1b[48;5;244m \x1b[0m",
+        );
+        // The background fills the whole cell, so its center will do
+        let colors = per_cell(&image, 8, 3, |cell| {
+            *cell.get_pixel(cell.width() / 2, cell.height() / 2)
+        });
+        // Allow for rounding in the trip through linear color space
+        compare_rgba_with_golden(&colors, &golden_path("cell-colors.png"), 1).unwrap();
+    }
+
+    #[test]
+    fn cell_ink_matches_golden() {
+        config::use_test_configuration();
+
+        let image = render(
+            10,
+            2,
+            "\x1b[?25lab c\x1b[4m  \x1b[24m\x1b[9m \x1b[29m\r\n\
+             \x20\x20x \x1b[8mhid\x1b[28m",
+        );
+        // A cell has ink if anything was drawn over the black background
+        let ink = per_cell(&image, 10, 2, |cell| {
+            let inked = cell
+                .pixels()
+                .any(|pixel| pixel.0[..3].iter().any(|&c| c > 0x40));
+            if inked {
+                ::image::Rgba([0xff, 0xff, 0xff, 0xff])
+            } else {
+                ::image::Rgba([0, 0, 0, 0xff])
+            }
+        });
+        compare_rgba_with_golden(&ink, &golden_path("cell-ink.png"), 0).unwrap();
+    }
+
+    #[test]
+    fn equivalent_escapes_render_identically() {
+        config::use_test_configuration();
+
+        // The cursor is hidden so that it doesn't differ between the two
+        let hide_cursor = "\x1b[?25l";
+        let indexed = render(12, 2, &format!("{hide_cursor}\x1b[31mred\x1b[0m ok"));
+        let extended = render(12, 2, &format!("{hide_cursor}\x1b[38;5;1mred\x1b[m ok"));
+        assert_eq!(compare_images(&indexed, &extended, 0), Ok(()));
+
+        let positioned = render(12, 2, &format!("{hide_cursor}\x1b[2;4Hx"));
+        let spaced = render(12, 2, &format!("{hide_cursor}\r\n   x"));
+        assert_eq!(compare_images(&positioned, &spaced, 0), Ok(()));
+    }
+
+    #[test]
+    fn attributes_change_the_rendering() {
+        config::use_test_configuration();
+
+        let plain = render(8, 1, "\x1b[?25labc");
+        let underlined = render(8, 1, "\x1b[?25l\x1b[4mabc");
+        assert!(matches!(
+            compare_images(&plain, &underlined, 0),
+            Err(ImageMismatch::Pixels { .. })
+        ));
+
+        let background = render(8, 1, "\x1b[?25l\x1b[41m \x1b[0m");
+        let palette = config::TermConfig::new().color_palette();
+        let (r, g, b, _a) = palette.colors.0[1].to_srgb_u8();
+        let red = ::image::RgbaImage::from_pixel(1, 1, ::image::Rgba([r, g, b, 0xff]));
+        let corner = ::image::imageops::crop_imm(&background, 0, 0, 1, 1).to_image();
+        // Allow for rounding in the trip through linear color space
+        assert_eq!(compare_images(&corner, &red, 1), Ok(()));
+    }
+
+    #[test]
+    fn compare_images_reports_size_and_tolerance() {
+        let black = ::image::RgbaImage::from_pixel(2, 2, ::image::Rgba([0, 0, 0, 0xff]));
+        let grey = ::image::RgbaImage::from_pixel(2, 2, ::image::Rgba([3, 3, 3, 0xff]));
+        let wide = ::image::RgbaImage::from_pixel(3, 2, ::image::Rgba([0, 0, 0, 0xff]));
+
+        assert_eq!(compare_images(&black, &grey, 3), Ok(()));
+        assert_eq!(
+            compare_images(&black, &grey, 2),
+            Err(ImageMismatch::Pixels {
+                differing: 4,
+                max_delta: 3
+            })
+        );
+        assert_eq!(
+            compare_images(&wide, &black, 0),
+            Err(ImageMismatch::Size {
+                actual: (3, 2),
+                expected: (2, 2)
+            })
+        );
+    }
+}
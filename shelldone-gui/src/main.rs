@@ -41,6 +41,7 @@ mod download;
 mod experience;
 mod frontend;
 mod glyphcache;
+mod headless;
 mod inputmap;
 mod overlay;
 mod quad;
@@ -132,6 +133,12 @@ enum SubCommand {
 
     #[command(name = "show-keys", about = "Show key assignments")]
     ShowKeys(ShowKeysCommand),
+
+    #[command(
+        name = "render",
+        about = "Run a program and render its output to a PNG image, without a display"
+    )]
+    Render(RenderCommand),
 }
 
 async fn async_run_ssh(opts: SshCommand) -> anyhow::Result<()> {
@@ -1274,5 +1281,6 @@ fn run() -> anyhow::Result<()> {
         ),
         SubCommand::LsFonts(cmd) => run_ls_fonts(config, &cmd),
         SubCommand::ShowKeys(cmd) => run_show_keys(config, &cmd),
+        SubCommand::Render(cmd) => headless::run_render(config, cmd),
     }
 }
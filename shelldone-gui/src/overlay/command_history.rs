@@ -0,0 +1,244 @@
+use mux::command_history::{
+    default_history_path, CommandHistory, CommandHistoryMatch, CommandHistoryQuery,
+};
+use mux::pane::PaneId;
+use mux::termwiztermtab::TermWizTerminal;
+use mux::Mux;
+use termwiz::cell::{AttributeChange, CellAttributes, Intensity};
+use termwiz::color::{AnsiColor, ColorAttribute};
+use termwiz::input::{InputEvent, KeyCode, KeyEvent, Modifiers, MouseButtons, MouseEvent};
+use termwiz::surface::{Change, Position};
+use termwiz::terminal::Terminal;
+use termwiz_funcs::truncate_right;
+
+const ROW_OVERHEAD: usize = 2;
+
+struct CommandHistoryState {
+    history: CommandHistory,
+    results: Vec<CommandHistoryMatch>,
+    filter_term: String,
+    active_idx: usize,
+    top_row: usize,
+    max_items: usize,
+    pane_id: PaneId,
+}
+
+impl CommandHistoryState {
+    fn update_filter(&mut self) {
+        self.results = self.history.search(&CommandHistoryQuery {
+            pattern: self.filter_term.clone(),
+            unique: true,
+            ..Default::default()
+        });
+        self.active_idx = 0;
+        self.top_row = 0;
+    }
+
+    fn render(&mut self, term: &mut TermWizTerminal) -> termwiz::Result<()> {
+        let size = term.get_screen_size()?;
+        let max_width = size.cols.saturating_sub(2);
+        self.max_items = size.rows.saturating_sub(ROW_OVERHEAD);
+
+        let mut changes = vec![
+            Change::ClearScreen(ColorAttribute::Default),
+            Change::CursorPosition {
+                x: Position::Absolute(0),
+                y: Position::Absolute(0),
+            },
+            Change::Text(truncate_right(
+                &format!(
+                    "Command history ({} matches). Enter inserts, Esc cancels: {}",
+                    self.results.len(),
+                    self.filter_term
+                ),
+                max_width,
+            )),
+            Change::Text("\r\n".to_string()),
+        ];
+
+        for (row_num, (idx, result)) in self
+            .results
+            .iter()
+            .enumerate()
+            .skip(self.top_row)
+            .enumerate()
+        {
+            if row_num >= self.max_items {
+                break;
+            }
+            if idx == self.active_idx {
+                changes.push(AttributeChange::Reverse(true).into());
+            }
+
+            let command = result.entry.command.replace('\n', " ");
+            let mut details = vec![];
+            if let Some(cwd) = &result.entry.cwd {
+                details.push(cwd.clone());
+            }
+            if result.runs > 1 {
+                details.push(format!("{} runs", result.runs));
+            }
+            let details = details.join(" · ");
+            let command_width = max_width.saturating_sub(details.len() + 2);
+
+            let failed = matches!(result.entry.exit_code, Some(code) if code != 0);
+            if failed {
+                changes.push(AttributeChange::Foreground(AnsiColor::Red.into()).into());
+            }
+            changes.push(Change::Text(format!(
+                " {}",
+                truncate_right(&command, command_width)
+            )));
+            if failed {
+                changes.push(AttributeChange::Foreground(ColorAttribute::Default).into());
+            }
+            if !details.is_empty() {
+                changes.push(AttributeChange::Intensity(Intensity::Half).into());
+                changes.push(Change::Text(format!("  {details}")));
+                changes.push(AttributeChange::Intensity(Intensity::Normal).into());
+            }
+            changes.push(Change::AllAttributes(CellAttributes::default()));
+            changes.push(Change::Text("\r\n".to_string()));
+        }
+
+        term.render(&changes)
+    }
+
+    /// Inserts the command line of the result into the pane,
+    /// without running it
+    fn insert(&self, active_idx: usize) -> bool {
+        let Some(result) = self.results.get(active_idx) else {
+            return false;
+        };
+        let pane_id = self.pane_id;
+        let command = result.entry.command.clone();
+        promise::spawn::spawn_into_main_thread(async move {
+            let mux = Mux::get();
+            if let Some(pane) = mux.get_pane(pane_id) {
+                if let Err(err) = pane.send_paste(&command) {
+                    log::error!("Error while inserting command from history: {err:#}");
+                }
+            }
+        })
+        .detach();
+        true
+    }
+
+    fn move_up(&mut self) {
+        self.active_idx = self.active_idx.saturating_sub(1);
+        if self.active_idx < self.top_row {
+            self.top_row = self.active_idx;
+        }
+    }
+
+    fn move_down(&mut self) {
+        self.active_idx = (self.active_idx + 1).min(self.results.len().saturating_sub(1));
+        if self.active_idx >= self.top_row + self.max_items {
+            self.top_row = (self.active_idx + 1).saturating_sub(self.max_items);
+        }
+    }
+
+    fn run_loop(&mut self, term: &mut TermWizTerminal) -> anyhow::Result<()> {
+        while let Ok(Some(event)) = term.poll_input(None) {
+            match event {
+                InputEvent::Key(KeyEvent {
+                    key: KeyCode::Char('G' | 'C'),
+                    modifiers: Modifiers::CTRL,
+                })
+                | InputEvent::Key(KeyEvent {
+                    key: KeyCode::Escape,
+                    ..
+                }) => {
+                    break;
+                }
+                InputEvent::Key(KeyEvent {
+                    key: KeyCode::Char('P' | 'K'),
+                    modifiers: Modifiers::CTRL,
+                })
+                | InputEvent::Key(KeyEvent {
+                    key: KeyCode::UpArrow,
+                    ..
+                }) => {
+                    self.move_up();
+                }
+                InputEvent::Key(KeyEvent {
+                    key: KeyCode::Char('N' | 'J'),
+                    modifiers: Modifiers::CTRL,
+                })
+                | InputEvent::Key(KeyEvent {
+                    key: KeyCode::DownArrow,
+                    ..
+                }) => {
+                    self.move_down();
+                }
+                InputEvent::Key(KeyEvent {
+                    key: KeyCode::Backspace,
+                    ..
+                }) => {
+                    self.filter_term.pop();
+                    self.update_filter();
+                }
+                InputEvent::Key(KeyEvent {
+                    key: KeyCode::Char(c),
+                    modifiers: Modifiers::NONE | Modifiers::SHIFT,
+                }) => {
+                    self.filter_term.push(c);
+                    self.update_filter();
+                }
+                InputEvent::Key(KeyEvent {
+                    key: KeyCode::Enter,
+                    ..
+                }) => {
+                    if self.insert(self.active_idx) {
+                        break;
+                    }
+                }
+                InputEvent::Mouse(MouseEvent { mouse_buttons, .. })
+                    if mouse_buttons.contains(MouseButtons::VERT_WHEEL) =>
+                {
+                    if mouse_buttons.contains(MouseButtons::WHEEL_POSITIVE) {
+                        self.move_up();
+                    } else {
+                        self.move_down();
+                    }
+                }
+                InputEvent::Mouse(MouseEvent {
+                    y, mouse_buttons, ..
+                }) => {
+                    let row = y as usize;
+                    if row > 0 && self.top_row + row <= self.results.len() {
+                        self.active_idx = self.top_row + row - 1;
+                        if mouse_buttons == MouseButtons::LEFT && self.insert(self.active_idx) {
+                            break;
+                        }
+                    }
+                }
+                _ => {}
+            }
+            self.render(term)?;
+        }
+
+        Ok(())
+    }
+}
+
+/// Shows the commands of the command history, ranked by frecency,
+/// and lets the user fuzzy search them and insert one into the pane
+pub fn show_command_history(pane_id: PaneId, mut term: TermWizTerminal) -> anyhow::Result<()> {
+    let history = CommandHistory::load(&default_history_path())?;
+    let mut state = CommandHistoryState {
+        history,
+        results: vec![],
+        filter_term: String::new(),
+        active_idx: 0,
+        top_row: 0,
+        max_items: 0,
+        pane_id,
+    };
+
+    term.set_raw_mode()?;
+    term.render(&[Change::Title("Command History".to_string())])?;
+    state.update_filter();
+    state.render(&mut term)?;
+    state.run_loop(&mut term)
+}
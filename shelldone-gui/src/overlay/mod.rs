@@ -7,12 +7,14 @@ use std::future::Future;
 use std::pin::Pin;
 use std::sync::Arc;
 
+pub mod command_history;
 pub mod confirm;
 pub mod confirm_close_pane;
 pub mod copy;
 pub mod debug;
 pub mod experience;
 pub mod launcher;
+pub mod playback;
 pub mod prompt;
 pub mod quickselect;
 pub mod selector;
@@ -0,0 +1,261 @@
+use mux::playback::{format_timestamp, parse_timestamp, PlaybackMarker, Player};
+use mux::termwiztermtab::TermWizTerminal;
+use std::sync::Arc;
+use std::time::Duration;
+use termwiz::cell::{AttributeChange, CellAttributes, Intensity};
+use termwiz::color::ColorAttribute;
+use termwiz::input::{InputEvent, KeyCode, KeyEvent, Modifiers, MouseButtons, MouseEvent};
+use termwiz::surface::{Change, Position};
+use termwiz::terminal::Terminal;
+use termwiz_funcs::truncate_right;
+
+/// The row on which the list of markers starts
+const FIRST_MARKER_ROW: usize = 4;
+const ROW_OVERHEAD: usize = FIRST_MARKER_ROW + 1;
+
+/// How often the position is refreshed while the overlay is shown
+const REFRESH_INTERVAL: Duration = Duration::from_millis(250);
+
+struct PlaybackControlsState {
+    player: Arc<Player>,
+    markers: Vec<PlaybackMarker>,
+    active_idx: usize,
+    top_row: usize,
+    max_items: usize,
+    /// The timestamp that is being typed after pressing `g`
+    goto: Option<String>,
+}
+
+impl PlaybackControlsState {
+    fn render(&mut self, term: &mut TermWizTerminal) -> termwiz::Result<()> {
+        let size = term.get_screen_size()?;
+        let max_width = size.cols.saturating_sub(2);
+        self.max_items = size.rows.saturating_sub(ROW_OVERHEAD);
+        // The markers move when idle time compression is toggled
+        self.markers = self.player.markers();
+
+        let status = self.player.status();
+        let mut summary = format!(
+            "{} {} / {}  speed {}x",
+            if status.paused { "Paused" } else { "Playing" },
+            format_timestamp(status.position),
+            format_timestamp(status.duration),
+            status.speed
+        );
+        if let Some(limit) = status.idle_time_limit {
+            summary.push_str(&format!("  idle time limited to {limit}s"));
+        }
+
+        let bar_width = max_width.saturating_sub(2);
+        let filled = if status.duration > 0. {
+            ((status.position / status.duration) * bar_width as f64) as usize
+        } else {
+            0
+        }
+        .min(bar_width);
+
+        let mut changes = vec![
+            Change::ClearScreen(ColorAttribute::Default),
+            Change::CursorPosition {
+                x: Position::Absolute(0),
+                y: Position::Absolute(0),
+            },
+            Change::Text(truncate_right(
+                &format!("{summary}  ({})", self.player.path().display()),
+                max_width,
+            )),
+            Change::Text("\r\n".to_string()),
+            Change::Text(format!(
+                "[{}{}]\r\n",
+                "=".repeat(filled),
+                " ".repeat(bar_width - filled)
+            )),
+            AttributeChange::Intensity(Intensity::Half).into(),
+            Change::Text(truncate_right(
+                "Space pause, ←/→ seek, Home/End, +/- speed, i idle, [/] markers, \
+                 g go to time, Enter seek to marker, Esc close",
+                max_width,
+            )),
+            Change::AllAttributes(CellAttributes::default()),
+            Change::Text("\r\n".to_string()),
+        ];
+
+        match &self.goto {
+            Some(goto) => changes.push(Change::Text(format!("Go to time: {goto}\r\n"))),
+            None => changes.push(Change::Text(format!(
+                "Markers ({}):\r\n",
+                self.markers.len()
+            ))),
+        }
+
+        for (row_num, (idx, marker)) in self
+            .markers
+            .iter()
+            .enumerate()
+            .skip(self.top_row)
+            .enumerate()
+        {
+            if row_num >= self.max_items {
+                break;
+            }
+            if idx == self.active_idx {
+                changes.push(AttributeChange::Reverse(true).into());
+            }
+            let time = format_timestamp(marker.time);
+            let label = marker.label.replace('\n', " ");
+            let label_width = max_width.saturating_sub(time.len() + 3);
+            changes.push(Change::Text(format!(
+                " {time}  {}",
+                truncate_right(&label, label_width)
+            )));
+            changes.push(Change::AllAttributes(CellAttributes::default()));
+            changes.push(Change::Text("\r\n".to_string()));
+        }
+
+        term.render(&changes)
+    }
+
+    fn seek_to_marker(&self, idx: usize) {
+        if let Some(marker) = self.markers.get(idx) {
+            self.player.seek(marker.time);
+        }
+    }
+
+    fn move_up(&mut self) {
+        self.active_idx = self.active_idx.saturating_sub(1);
+        if self.active_idx < self.top_row {
+            self.top_row = self.active_idx;
+        }
+    }
+
+    fn move_down(&mut self) {
+        self.active_idx = (self.active_idx + 1).min(self.markers.len().saturating_sub(1));
+        if self.active_idx >= self.top_row + self.max_items {
+            self.top_row = (self.active_idx + 1).saturating_sub(self.max_items);
+        }
+    }
+
+    /// Handles a key while a timestamp is being typed
+    fn goto_key(&mut self, key: KeyCode) {
+        let Some(goto) = self.goto.as_mut() else {
+            return;
+        };
+        match key {
+            KeyCode::Escape => self.goto = None,
+            KeyCode::Backspace => {
+                goto.pop();
+            }
+            KeyCode::Char(c) if c.is_ascii_digit() || c == ':' || c == '.' => goto.push(c),
+            KeyCode::Enter => {
+                match parse_timestamp(goto) {
+                    Some(position) => self.player.seek(position),
+                    None => log::error!("{goto:?} is not a valid timestamp"),
+                }
+                self.goto = None;
+            }
+            _ => {}
+        }
+    }
+
+    fn run_loop(&mut self, term: &mut TermWizTerminal) -> anyhow::Result<()> {
+        loop {
+            let event = match term.poll_input(Some(REFRESH_INTERVAL)) {
+                Ok(Some(event)) => event,
+                Ok(None) => {
+                    self.render(term)?;
+                    continue;
+                }
+                Err(_) => break,
+            };
+            match event {
+                InputEvent::Key(KeyEvent { key, .. }) if self.goto.is_some() => {
+                    self.goto_key(key);
+                }
+                InputEvent::Key(KeyEvent {
+                    key: KeyCode::Char('G' | 'C'),
+                    modifiers: Modifiers::CTRL,
+                })
+                | InputEvent::Key(KeyEvent {
+                    key: KeyCode::Escape | KeyCode::Char('q'),
+                    ..
+                }) => {
+                    break;
+                }
+                InputEvent::Key(KeyEvent {
+                    key: KeyCode::UpArrow,
+                    ..
+                }) => {
+                    self.move_up();
+                }
+                InputEvent::Key(KeyEvent {
+                    key: KeyCode::DownArrow,
+                    ..
+                }) => {
+                    self.move_down();
+                }
+                InputEvent::Key(KeyEvent {
+                    key: KeyCode::Enter,
+                    ..
+                }) => {
+                    self.seek_to_marker(self.active_idx);
+                }
+                InputEvent::Key(KeyEvent {
+                    key: KeyCode::Char('g'),
+                    modifiers: Modifiers::NONE,
+                }) => {
+                    self.goto = Some(String::new());
+                }
+                InputEvent::Key(KeyEvent { key, modifiers }) => {
+                    self.player.key_down(key, modifiers);
+                }
+                InputEvent::Mouse(MouseEvent { mouse_buttons, .. })
+                    if mouse_buttons.contains(MouseButtons::VERT_WHEEL) =>
+                {
+                    if mouse_buttons.contains(MouseButtons::WHEEL_POSITIVE) {
+                        self.move_up();
+                    } else {
+                        self.move_down();
+                    }
+                }
+                InputEvent::Mouse(MouseEvent {
+                    y, mouse_buttons, ..
+                }) => {
+                    let row = y as usize;
+                    if row >= FIRST_MARKER_ROW
+                        && self.top_row + row - FIRST_MARKER_ROW < self.markers.len()
+                    {
+                        self.active_idx = self.top_row + row - FIRST_MARKER_ROW;
+                        if mouse_buttons == MouseButtons::LEFT {
+                            self.seek_to_marker(self.active_idx);
+                        }
+                    }
+                }
+                _ => {}
+            }
+            self.render(term)?;
+        }
+
+        Ok(())
+    }
+}
+
+/// Shows the transport state of a playback pane, and lets the user
+/// pause, seek, change the speed and jump to the recorded markers
+pub fn show_playback_controls(
+    player: Arc<Player>,
+    mut term: TermWizTerminal,
+) -> anyhow::Result<()> {
+    let mut state = PlaybackControlsState {
+        markers: player.markers(),
+        player,
+        active_idx: 0,
+        top_row: 0,
+        max_items: 0,
+        goto: None,
+    };
+
+    term.set_raw_mode()?;
+    term.render(&[Change::Title("Playback Controls".to_string())])?;
+    state.render(&mut term)?;
+    state.run_loop(&mut term)
+}
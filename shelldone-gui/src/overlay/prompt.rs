@@ -60,16 +60,12 @@ pub fn show_line_prompt_overlay(
         ),
     };
 
-    term.no_grab_mouse_in_raw_mode();
-    let mut text = args.description.replace("\r\n", "\n").replace("\n", "\r\n");
-    text.push_str("\r\n");
-    term.render(&[Change::Text(text)])?;
-
-    let mut host = PromptHost::new();
-    let mut editor = LineEditor::new(&mut term);
-    editor.set_prompt(&args.prompt);
-    let line =
-        editor.read_line_with_optional_initial_value(&mut host, args.initial_value.as_deref())?;
+    let line = read_line(
+        &mut term,
+        &args.description,
+        &args.prompt,
+        args.initial_value.as_deref(),
+    )?;
 
     promise::spawn::spawn_into_main_thread(async move {
         trampoline(name, window, pane, line);
@@ -80,6 +76,25 @@ pub fn show_line_prompt_overlay(
     Ok(())
 }
 
+/// Shows `description` and reads a line of input that starts out
+/// as `initial_value`.  Returns None if the user cancelled.
+pub fn read_line(
+    term: &mut TermWizTerminal,
+    description: &str,
+    prompt: &str,
+    initial_value: Option<&str>,
+) -> anyhow::Result<Option<String>> {
+    term.no_grab_mouse_in_raw_mode();
+    let mut text = description.replace("\r\n", "\n").replace("\n", "\r\n");
+    text.push_str("\r\n");
+    term.render(&[Change::Text(text)])?;
+
+    let mut host = PromptHost::new();
+    let mut editor = LineEditor::new(term);
+    editor.set_prompt(prompt);
+    Ok(editor.read_line_with_optional_initial_value(&mut host, initial_value)?)
+}
+
 fn trampoline(name: String, window: GuiWin, pane: MuxPane, line: Option<String>) {
     promise::spawn::spawn(async move {
         config::with_lua_config_on_main_thread(move |lua| do_event(lua, name, window, pane, line))
@@ -3,7 +3,7 @@ use super::quad::*;
 use super::utilsprites::{RenderMetrics, UtilSprites};
 use crate::termwindow::webgpu::{adapter_info_to_gpu_info, WebGpuState, WebGpuTexture};
 use ::window::bitmaps::atlas::OutOfTextureSpace;
-use ::window::bitmaps::Texture2d;
+use ::window::bitmaps::{Image, ImageTexture, Texture2d};
 use ::window::glium::backend::Context as GliumContext;
 use ::window::glium::buffer::{BufferMutSlice, Mapping};
 use ::window::glium::{
@@ -23,11 +23,14 @@ const INDICES_PER_CELL: usize = 6;
 pub enum RenderContext {
     Glium(Rc<GliumContext>),
     WebGpu(Rc<WebGpuState>),
+    /// Renders into memory, for windows that have no display
+    Cpu,
 }
 
 pub enum RenderFrame<'a> {
     Glium(&'a mut glium::Frame),
     WebGpu,
+    Cpu(&'a mut Image),
 }
 
 impl RenderContext {
@@ -39,12 +42,13 @@ impl RenderContext {
                 indices,
             )?))),
             Self::WebGpu(state) => Ok(IndexBuffer::WebGpu(WebGpuIndexBuffer::new(indices, state))),
+            Self::Cpu => Ok(IndexBuffer::Cpu),
         }
     }
 
     pub fn allocate_vertex_buffer_initializer(&self, num_quads: usize) -> Vec<Vertex> {
         match self {
-            Self::Glium(_) => {
+            Self::Glium(_) | Self::Cpu => {
                 vec![Vertex::default(); num_quads * VERTICES_PER_CELL]
             }
             Self::WebGpu(_) => vec![],
@@ -65,6 +69,7 @@ impl RenderContext {
                 num_quads * VERTICES_PER_CELL,
                 state,
             ))),
+            Self::Cpu => Ok(VertexBuffer::Cpu(initializer.to_vec())),
         }
     }
 
@@ -103,6 +108,10 @@ impl RenderContext {
                     Rc::new(WebGpuTexture::new(size as u32, size as u32, state)?);
                 Ok(texture)
             }
+            Self::Cpu => {
+                let texture: Rc<dyn Texture2d> = Rc::new(ImageTexture::new(size, size));
+                Ok(texture)
+            }
         }
     }
 
@@ -117,6 +126,7 @@ impl RenderContext {
                 let info = adapter_info_to_gpu_info(state.adapter_info.clone());
                 format!("WebGPU: {}", info)
             }
+            Self::Cpu => "CPU".to_string(),
         }
     }
 }
@@ -124,6 +134,8 @@ impl RenderContext {
 pub enum IndexBuffer {
     Glium(Box<GliumIndexBuffer<u32>>),
     WebGpu(WebGpuIndexBuffer),
+    /// The quads are rasterized directly, without indices
+    Cpu,
 }
 
 impl IndexBuffer {
@@ -144,6 +156,7 @@ impl IndexBuffer {
 pub enum VertexBuffer {
     Glium(Box<GliumVertexBuffer<Vertex>>),
     WebGpu(WebGpuVertexBuffer),
+    Cpu(Vec<Vertex>),
 }
 
 impl VertexBuffer {
@@ -165,11 +178,18 @@ impl VertexBuffer {
             _ => unreachable!(),
         }
     }
+    pub fn cpu(&self) -> &[Vertex] {
+        match self {
+            Self::Cpu(v) => v,
+            _ => unreachable!(),
+        }
+    }
 }
 
 enum MappedVertexBuffer {
     Glium(GliumMappedVertexBuffer),
     WebGpu(WebGpuMappedVertexBuffer),
+    Cpu(RefMut<'static, VertexBuffer>),
 }
 
 impl MappedVertexBuffer {
@@ -180,6 +200,10 @@ impl MappedVertexBuffer {
                 let mapping: &mut [Vertex] = bytemuck::cast_slice_mut(&mut g.mapping);
                 &mut mapping[range]
             }
+            Self::Cpu(vb) => match &mut **vb {
+                VertexBuffer::Cpu(v) => &mut v[range],
+                _ => unreachable!(),
+            },
         }
     }
 }
@@ -429,6 +453,7 @@ impl TripleVertexBuffer {
                 })
             }
             VertexBuffer::WebGpu(vb) => MappedVertexBuffer::WebGpu(vb.map()),
+            VertexBuffer::Cpu(_) => MappedVertexBuffer::Cpu(bufs),
         };
 
         MappedQuads {
@@ -595,7 +620,7 @@ impl RenderState {
                         RenderContext::Glium(context) => {
                             Some(Self::compile_prog(context, Self::glyph_shader)?)
                         }
-                        RenderContext::WebGpu(_) => None,
+                        RenderContext::WebGpu(_) | RenderContext::Cpu => None,
                     };
 
                     let main_layer = Rc::new(RenderLayer::new(&context, 1024, 0)?);
@@ -2,6 +2,7 @@ use crate::termwindow::{PaneInformation, TabInformation, UIItem, UIItemType};
 use config::{ConfigHandle, TabBarColors};
 use finl_unicode::grapheme_clusters::Graphemes;
 use mlua::FromLua;
+use mux::monitor::MonitorAlert;
 use shelldone_term::{Line, Progress};
 use termwiz::cell::{unicode_column_width, Cell, CellAttributes};
 use termwiz::color::{AnsiColor, ColorSpec};
@@ -103,6 +104,22 @@ fn call_format_tab_title(
     }
 }
 
+/// `md_broadcast`, shown ahead of the title of tabs whose active
+/// pane is broadcasting its input to other panes
+const BROADCAST_GLYPH: char = '\u{f1720}';
+
+/// `md_pulse`, shown ahead of the title of tabs in which a pane that is
+/// monitored for activity produced output
+const ACTIVITY_GLYPH: char = '\u{f0430}';
+
+/// `md_bell_sleep`, shown ahead of the title of tabs in which a pane
+/// that is monitored for silence has gone quiet
+const SILENCE_GLYPH: char = '\u{f00a0}';
+
+/// `md_record_rec`, shown ahead of the title of tabs in which
+/// a pane is being recorded
+const RECORDING_GLYPH: char = '\u{f044b}';
+
 /// pct is a percentage in the range 0-100.
 /// We want to map it to one of the nerdfonts:
 ///
@@ -170,6 +187,40 @@ fn compute_tab_title(
                     title = format!("{}{classic_spacing}", title);
                 }
 
+                if pane.is_broadcasting {
+                    let graphic = format!("{} ", BROADCAST_GLYPH);
+                    len += unicode_column_width(&graphic, None);
+                    items.push(FormatItem::Foreground(FormatColor::AnsiColor(
+                        AnsiColor::Yellow,
+                    )));
+                    items.push(FormatItem::Text(graphic));
+                    items.push(FormatItem::Foreground(FormatColor::Default));
+                }
+
+                if tab.is_recording {
+                    let graphic = format!("{} ", RECORDING_GLYPH);
+                    len += unicode_column_width(&graphic, None);
+                    items.push(FormatItem::Foreground(FormatColor::AnsiColor(
+                        AnsiColor::Red,
+                    )));
+                    items.push(FormatItem::Text(graphic));
+                    items.push(FormatItem::Foreground(FormatColor::Default));
+                }
+
+                if let Some(alert) = tab.monitor_alert {
+                    let glyph = match alert {
+                        MonitorAlert::Activity => ACTIVITY_GLYPH,
+                        MonitorAlert::Silence => SILENCE_GLYPH,
+                    };
+                    let graphic = format!("{} ", glyph);
+                    len += unicode_column_width(&graphic, None);
+                    items.push(FormatItem::Foreground(FormatColor::AnsiColor(
+                        AnsiColor::Fuchsia,
+                    )));
+                    items.push(FormatItem::Text(graphic));
+                    items.push(FormatItem::Foreground(FormatColor::Default));
+                }
+
                 match pane.progress {
                     Progress::None => {}
                     Progress::Percentage(pct) | Progress::Error(pct) => {
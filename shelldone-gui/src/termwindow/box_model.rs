@@ -4,11 +4,9 @@ use crate::customglyph::{BlockKey, Poly};
 use crate::glyphcache::CachedGlyph;
 use crate::quad::{QuadImpl, QuadTrait, TripleLayerQuadAllocator, TripleLayerQuadAllocatorTrait};
 use crate::termwindow::render::PolyQuadParams;
-use crate::termwindow::{
-    ColorEase, MouseCapture, RenderState, TermWindowNotif, UIItem, UIItemType,
-};
+use crate::termwindow::{ColorEase, MouseCapture, RenderState, UIItem, UIItemType};
 use crate::utilsprites::RenderMetrics;
-use ::window::{RectF, WindowOps};
+use ::window::RectF;
 use anyhow::anyhow;
 use config::{Dimension, DimensionContext};
 use finl_unicode::grapheme_clusters::Graphemes;
@@ -581,7 +579,7 @@ impl super::TermWindow {
 
         match &element.content {
             ElementContent::Text(s) => {
-                let window = self.window.as_ref().unwrap().clone();
+                let completion = self.shape_completion();
                 let direction = shelldone_bidi::Direction::LeftToRight;
                 let options = ShapeOptions {
                     presentation: element.presentation,
@@ -589,12 +587,10 @@ impl super::TermWindow {
                     range: None,
                     presentation_width: None,
                 };
-                let infos = element.font.shape(
-                    s,
-                    move || window.notify(TermWindowNotif::InvalidateShapeCache),
-                    BlockKey::filter_out_synthetic,
-                    options,
-                )?;
+                let infos =
+                    element
+                        .font
+                        .shape(s, completion, BlockKey::filter_out_synthetic, options)?;
                 let mut computed_cells = vec![];
                 let mut glyph_cache = context.gl_state.glyph_cache.borrow_mut();
                 let mut pixel_width = 0.0;
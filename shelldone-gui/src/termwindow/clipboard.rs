@@ -1,11 +1,24 @@
+use crate::overlay::{confirm, start_overlay_pane};
 use crate::termwindow::TermWindowNotif;
 use crate::TermWindow;
 use config::keyassignment::{ClipboardCopyDestination, ClipboardPasteSource};
-use mux::pane::Pane;
+use config::ClipboardReadAccess;
+use mux::clipboard::{
+    deny_clipboard_read, journal_clipboard_read, response_template, send_clipboard_responses,
+};
+use mux::pane::{Pane, PaneId};
 use mux::Mux;
+use shelldone_escape_parser::kitty_clipboard::{
+    KittyClipboard, KittyClipboardStatus, KittyClipboardType, KITTY_CLIPBOARD_LIST_MIME,
+};
+use shelldone_term::{ClipboardReadRequest, ClipboardSelection};
 use std::sync::Arc;
 use window::{Clipboard, WindowOps};
 
+/// The amount of clipboard data carried by each OSC 5522 `DATA`
+/// response; it is base64 encoded to 4KiB on the wire
+const READ_CHUNK_SIZE: usize = 3 * 1024;
+
 impl TermWindow {
     pub fn copy_to_clipboard(&self, clipboard: ClipboardCopyDestination, text: String) {
         let clipboard = match clipboard {
@@ -49,7 +62,7 @@ impl TermWindow {
                             mux.get_pane(pane_id)
                         })
                     {
-                        pane.send_paste(&clip).ok();
+                        myself.send_paste_to_panes(&pane, &clip).ok();
                     }
                 })));
             }
@@ -57,4 +70,139 @@ impl TermWindow {
         .detach();
         self.maybe_scroll_to_bottom_for_input(pane);
     }
+
+    /// Pastes `text` into `pane` and into any panes that are receiving
+    /// a broadcast copy of its input
+    pub fn send_paste_to_panes(&self, pane: &Arc<dyn Pane>, text: &str) -> anyhow::Result<()> {
+        pane.send_paste(text)?;
+        for target in self.input_broadcast_targets(pane) {
+            if let Err(err) = target.send_paste(text) {
+                log::debug!("broadcasting paste to pane {}: {:#}", target.pane_id(), err);
+            }
+        }
+        Ok(())
+    }
+}
+
+impl TermWindow {
+    /// Services an OSC 5522 request from the application in `pane_id`
+    /// to read the clipboard, subject to `clipboard_read_access`
+    pub fn request_clipboard_read(&mut self, pane_id: PaneId, request: ClipboardReadRequest) {
+        let mux = Mux::get();
+        let pane = match mux.get_pane(pane_id) {
+            Some(pane) => pane,
+            None => return,
+        };
+
+        match self.config.clipboard_read_access {
+            ClipboardReadAccess::Allow => self.read_clipboard_for_pane(pane_id, request),
+            ClipboardReadAccess::Deny => {
+                deny_clipboard_read(pane_id, &request, KittyClipboardStatus::PermissionDenied)
+            }
+            ClipboardReadAccess::Ask => {
+                if self.pane_state(pane_id).overlay.is_some() {
+                    // Don't stack a prompt on top of whatever the user
+                    // is currently doing in this pane
+                    deny_clipboard_read(pane_id, &request, KittyClipboardStatus::Busy);
+                    return;
+                }
+                let window = self.window.clone().unwrap();
+                let (overlay, future) = start_overlay_pane(self, &pane, move |pane_id, term| {
+                    confirm_clipboard_read(pane_id, request, term, window)
+                });
+                self.assign_overlay_for_pane(pane_id, overlay);
+                promise::spawn::spawn(future).detach();
+            }
+        }
+    }
+
+    fn read_clipboard_for_pane(&mut self, pane_id: PaneId, request: ClipboardReadRequest) {
+        let window = self.window.as_ref().unwrap().clone();
+        let clipboard = match request.selection {
+            ClipboardSelection::Clipboard => Clipboard::Clipboard,
+            ClipboardSelection::PrimarySelection => Clipboard::PrimarySelection,
+        };
+        promise::spawn::spawn(async move {
+            let mut data = vec![];
+            for mime in &request.mime_types {
+                let result = if mime == KITTY_CLIPBOARD_LIST_MIME {
+                    window
+                        .get_clipboard_mime_types(clipboard)
+                        .await
+                        .map(|types| types.join(" ").into_bytes())
+                } else {
+                    window.get_clipboard_data(clipboard, mime.clone()).await
+                };
+                match result {
+                    Ok(bytes) => data.push((mime.clone(), bytes)),
+                    // Formats that are not available are simply omitted
+                    Err(err) => log::debug!("OSC 5522 read of {mime}: {err:#}"),
+                }
+            }
+
+            journal_clipboard_read(
+                pane_id,
+                &request,
+                true,
+                data.iter().map(|(_, bytes)| bytes.len()).sum(),
+            );
+
+            let template = response_template(&request);
+            let mut responses = vec![KittyClipboard::response(
+                &template,
+                KittyClipboardType::Read,
+                KittyClipboardStatus::Ok,
+            )];
+            for (mime, bytes) in data {
+                for chunk in bytes.chunks(READ_CHUNK_SIZE) {
+                    responses.push(KittyClipboard {
+                        mime: Some(mime.clone()),
+                        payload: chunk.to_vec(),
+                        ..KittyClipboard::response(
+                            &template,
+                            KittyClipboardType::Read,
+                            KittyClipboardStatus::Data,
+                        )
+                    });
+                }
+            }
+            responses.push(KittyClipboard::response(
+                &template,
+                KittyClipboardType::Read,
+                KittyClipboardStatus::Done,
+            ));
+            send_clipboard_responses(pane_id, responses);
+        })
+        .detach();
+    }
+}
+
+fn confirm_clipboard_read(
+    pane_id: PaneId,
+    request: ClipboardReadRequest,
+    mut term: mux::termwiztermtab::TermWizTerminal,
+    window: ::window::Window,
+) -> anyhow::Result<()> {
+    let what = match request.selection {
+        ClipboardSelection::Clipboard => "the clipboard",
+        ClipboardSelection::PrimarySelection => "the primary selection",
+    };
+    let allowed = confirm::run_confirmation(
+        &format!(
+            "📋 The program running in this pane wants to read {what} \
+             ({}). Allow it?",
+            request.mime_types.join(", ")
+        ),
+        &mut term,
+    )?;
+    window.notify(TermWindowNotif::Apply(Box::new(move |myself| {
+        if allowed {
+            myself.read_clipboard_for_pane(pane_id, request);
+        } else {
+            deny_clipboard_read(pane_id, &request, KittyClipboardStatus::PermissionDenied);
+        }
+    })));
+    TermWindow::schedule_cancel_overlay_for_pane(window, pane_id);
+
+    Ok(())
 }
@@ -0,0 +1,318 @@
+use config::keyassignment::{ClipboardCopyDestination, CommandBlockPart};
+use mux::pane::Pane;
+use mux::renderable::RenderableDimensions;
+use shelldone_term::{CommandBlock, StableRowIndex};
+use std::collections::HashSet;
+use std::io::Write;
+use std::ops::Range;
+use std::sync::Arc;
+use std::time::Duration;
+use termwiz::surface::SequenceNo;
+
+#[derive(Clone, Default)]
+pub struct CommandBlockCache {
+    seqno: SequenceNo,
+    blocks: Arc<Vec<CommandBlock>>,
+    /// The most recently computed visible rows; discarded along
+    /// with the blocks when the seqno changes
+    visible_rows: Option<VisibleRows>,
+}
+
+#[derive(Clone)]
+struct VisibleRows {
+    collapsed: HashSet<u64>,
+    viewport: Option<StableRowIndex>,
+    dims: RenderableDimensions,
+    rows: Vec<StableRowIndex>,
+}
+
+/// Returns true if the block represents a command that was entered,
+/// rather than the prompt that is waiting for the next command
+pub fn is_entered_command(block: &CommandBlock) -> bool {
+    block.output_y.is_some() || block.exit_status.is_some()
+}
+
+/// Formats the duration of a command compactly, eg: `850ms`, `3.2s`, `2m05s`
+pub fn format_command_duration(duration: Duration) -> String {
+    let secs = duration.as_secs();
+    if secs < 1 {
+        format!("{}ms", duration.as_millis())
+    } else if secs < 60 {
+        format!("{:.1}s", duration.as_secs_f32())
+    } else if secs < 3600 {
+        format!("{}m{:02}s", secs / 60, secs % 60)
+    } else {
+        format!("{}h{:02}m", secs / 3600, (secs % 3600) / 60)
+    }
+}
+
+/// Groups the rows into runs of consecutive rows, returning the
+/// index of the first row of each run along with the rows of the run
+pub fn contiguous_row_runs(rows: &[StableRowIndex]) -> Vec<(usize, Range<StableRowIndex>)> {
+    let mut runs: Vec<(usize, Range<StableRowIndex>)> = vec![];
+    for (idx, &row) in rows.iter().enumerate() {
+        match runs.last_mut() {
+            Some((_, run)) if run.end == row => run.end += 1,
+            _ => runs.push((idx, row..row + 1)),
+        }
+    }
+    runs
+}
+
+impl crate::TermWindow {
+    /// Returns the command blocks of the pane, oldest first
+    pub fn get_command_blocks(&mut self, pane: &Arc<dyn Pane>) -> Arc<Vec<CommandBlock>> {
+        let cache = self.command_blocks.entry(pane.pane_id()).or_default();
+
+        let seqno = pane.get_current_seqno();
+        if cache.seqno != seqno {
+            cache.blocks = Arc::new(pane.get_command_blocks());
+            cache.seqno = seqno;
+            cache.visible_rows = None;
+        }
+        Arc::clone(&cache.blocks)
+    }
+
+    /// Returns the ranges of rows that are hidden because the output
+    /// of their command block has been collapsed.
+    /// All but the first row of the output is hidden.
+    fn collapsed_command_rows(
+        &mut self,
+        pane: &Arc<dyn Pane>,
+        bottom: StableRowIndex,
+    ) -> Vec<Range<StableRowIndex>> {
+        let collapsed = self
+            .pane_state(pane.pane_id())
+            .collapsed_command_blocks
+            .clone();
+        if collapsed.is_empty() || !self.config.show_command_blocks {
+            return vec![];
+        }
+        self.get_command_blocks(pane)
+            .iter()
+            .filter(|block| collapsed.contains(&block.id))
+            .filter_map(|block| {
+                let rows = block.output_rows(bottom)?;
+                let hidden = rows.start + 1..rows.end;
+                if hidden.is_empty() {
+                    None
+                } else {
+                    Some(hidden)
+                }
+            })
+            .collect()
+    }
+
+    /// Returns the stable rows that are displayed in the viewport of
+    /// the pane, from top to bottom, skipping over the rows of
+    /// collapsed command output.
+    pub fn visible_stable_rows(
+        &mut self,
+        pane: &Arc<dyn Pane>,
+        viewport: Option<StableRowIndex>,
+        dims: &RenderableDimensions,
+    ) -> Vec<StableRowIndex> {
+        let collapsed = self
+            .pane_state(pane.pane_id())
+            .collapsed_command_blocks
+            .clone();
+        if collapsed.is_empty() || !self.config.show_command_blocks {
+            let top = viewport.unwrap_or(dims.physical_top);
+            return (top..top + dims.viewport_rows as StableRowIndex).collect();
+        }
+
+        // Refreshes the blocks, which invalidates the cached rows
+        // if the pane has changed
+        self.get_command_blocks(pane);
+        let cache = self.command_blocks.entry(pane.pane_id()).or_default();
+        if let Some(cached) = &cache.visible_rows {
+            if cached.collapsed == collapsed && cached.viewport == viewport && cached.dims == *dims
+            {
+                return cached.rows.clone();
+            }
+        }
+
+        let rows = self.compute_visible_stable_rows(pane, viewport, dims);
+        self.command_blocks
+            .entry(pane.pane_id())
+            .or_default()
+            .visible_rows
+            .replace(VisibleRows {
+                collapsed,
+                viewport,
+                dims: *dims,
+                rows: rows.clone(),
+            });
+        rows
+    }
+
+    fn compute_visible_stable_rows(
+        &mut self,
+        pane: &Arc<dyn Pane>,
+        viewport: Option<StableRowIndex>,
+        dims: &RenderableDimensions,
+    ) -> Vec<StableRowIndex> {
+        let num_rows = dims.viewport_rows;
+        let bottom = dims.physical_top + num_rows as StableRowIndex;
+        let top = viewport.unwrap_or(dims.physical_top);
+
+        let hidden = self.collapsed_command_rows(pane, bottom);
+        if hidden.is_empty() {
+            return (top..top + num_rows as StableRowIndex).collect();
+        }
+        let is_visible = |row: &StableRowIndex| !hidden.iter().any(|r| r.contains(row));
+
+        if viewport.is_some() {
+            let rows: Vec<StableRowIndex> =
+                (top..bottom).filter(is_visible).take(num_rows).collect();
+            if rows.len() == num_rows {
+                return rows;
+            }
+            // There isn't enough below the viewport to fill it,
+            // so show the bottom instead
+        }
+
+        let mut rows: Vec<StableRowIndex> = (dims.scrollback_top..bottom)
+            .rev()
+            .filter(is_visible)
+            .take(num_rows)
+            .collect();
+        rows.reverse();
+        rows
+    }
+
+    /// Returns the stable row that is displayed at `row` of
+    /// the viewport of the pane
+    pub fn stable_row_at_viewport_row(&mut self, pane: &Arc<dyn Pane>, row: i64) -> StableRowIndex {
+        let dims = pane.get_dimensions();
+        let viewport = self.get_viewport(pane.pane_id());
+        let rows = self.visible_stable_rows(pane, viewport, &dims);
+        let (Some(&first), Some(&last)) = (rows.first(), rows.last()) else {
+            return viewport.unwrap_or(dims.physical_top) + row as StableRowIndex;
+        };
+        if row < 0 {
+            first + row as StableRowIndex
+        } else {
+            match rows.get(row as usize) {
+                Some(&stable_row) => stable_row,
+                None => last + (row as StableRowIndex - (rows.len() as StableRowIndex - 1)),
+            }
+        }
+    }
+
+    /// Returns the command block that the command block actions apply
+    /// to: the one that was activated via `ActivateCommandBlockRelative`,
+    /// or else the most recently entered command
+    pub fn current_command_block(&mut self, pane: &Arc<dyn Pane>) -> Option<CommandBlock> {
+        let blocks = self.get_command_blocks(pane);
+        let active = self.pane_state(pane.pane_id()).active_command_block;
+        active
+            .and_then(|id| blocks.iter().find(|block| block.id == id))
+            .or_else(|| blocks.iter().rev().find(|block| is_entered_command(block)))
+            .cloned()
+    }
+
+    /// Scrolls the viewport, if needed, so that the prompt of
+    /// the block is visible
+    fn scroll_to_command_block(&mut self, block: &CommandBlock, pane: &Arc<dyn Pane>) {
+        let dims = pane.get_dimensions();
+        let viewport = self.get_viewport(pane.pane_id());
+        let rows = self.visible_stable_rows(pane, viewport, &dims);
+        if !rows.contains(&block.prompt_y) {
+            self.set_viewport(pane.pane_id(), Some(block.prompt_y), dims);
+        }
+    }
+
+    pub fn activate_command_block_relative(
+        &mut self,
+        amount: isize,
+        pane: &Arc<dyn Pane>,
+    ) -> anyhow::Result<()> {
+        let blocks = self.get_command_blocks(pane);
+        let entered: Vec<&CommandBlock> = blocks
+            .iter()
+            .filter(|block| is_entered_command(block))
+            .collect();
+        if entered.is_empty() {
+            return Ok(());
+        }
+        let max_idx = entered.len() as isize - 1;
+
+        let active = self.pane_state(pane.pane_id()).active_command_block;
+        let idx = match active.and_then(|id| entered.iter().position(|block| block.id == id)) {
+            Some(idx) => idx as isize + amount,
+            // Nothing is active yet, so move relative to the
+            // prompt that is waiting for the next command
+            None => entered.len() as isize + amount,
+        };
+        let block = entered[idx.clamp(0, max_idx) as usize];
+
+        self.pane_state(pane.pane_id()).active_command_block = Some(block.id);
+        self.scroll_to_command_block(block, pane);
+
+        if let Some(win) = self.window.as_ref() {
+            win.invalidate();
+        }
+        Ok(())
+    }
+
+    pub fn copy_command_block_to(
+        &mut self,
+        part: CommandBlockPart,
+        destination: ClipboardCopyDestination,
+        pane: &Arc<dyn Pane>,
+    ) -> anyhow::Result<()> {
+        let Some(block) = self.current_command_block(pane) else {
+            return Ok(());
+        };
+        let text = match part {
+            CommandBlockPart::Command => block.command,
+            CommandBlockPart::Output => {
+                let dims = pane.get_dimensions();
+                let bottom = dims.physical_top + dims.viewport_rows as StableRowIndex;
+                match block.output_rows(bottom) {
+                    Some(rows) => self.text_for_rows(pane, rows),
+                    None => String::new(),
+                }
+            }
+        };
+        if !text.is_empty() {
+            self.copy_to_clipboard(destination, text);
+        }
+        Ok(())
+    }
+
+    /// Sends the command line of the current command block to the
+    /// pane, followed by a carriage return to run it
+    pub fn rerun_command_block(&mut self, pane: &Arc<dyn Pane>) -> anyhow::Result<()> {
+        let Some(block) = self.current_command_block(pane) else {
+            return Ok(());
+        };
+        if block.command.is_empty() {
+            return Ok(());
+        }
+        self.pane_state(pane.pane_id()).active_command_block = None;
+        pane.send_paste(&block.command)?;
+        pane.writer().write_all(b"\r")?;
+        self.scroll_to_bottom(pane);
+        Ok(())
+    }
+
+    pub fn toggle_command_block_collapsed(&mut self, pane: &Arc<dyn Pane>) -> anyhow::Result<()> {
+        let Some(block) = self.current_command_block(pane) else {
+            return Ok(());
+        };
+        {
+            let mut state = self.pane_state(pane.pane_id());
+            if !state.collapsed_command_blocks.remove(&block.id) {
+                state.collapsed_command_blocks.insert(block.id);
+            }
+        }
+        self.scroll_to_command_block(&block, pane);
+
+        if let Some(win) = self.window.as_ref() {
+            win.invalidate();
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,100 @@
+use crate::overlay::{confirm, prompt, start_overlay_pane};
+use crate::TermWindow;
+use mux::file_transfer::{respond_to_file_transfer, FileTransferDirection, FileTransferRequest};
+use mux::pane::PaneId;
+use mux::Mux;
+use std::path::PathBuf;
+
+impl TermWindow {
+    /// Asks the user to permit an OSC 5113 file transfer session
+    /// that was started by the application in `pane_id`
+    pub fn request_file_transfer(&mut self, pane_id: PaneId, request: FileTransferRequest) {
+        let mux = Mux::get();
+        let pane = match mux.get_pane(pane_id) {
+            Some(pane) => pane,
+            None => return,
+        };
+        if self.pane_state(pane_id).overlay.is_some() {
+            // Don't stack a prompt on top of whatever the user
+            // is currently doing in this pane
+            respond_to_file_transfer(pane_id, request.id, false, None);
+            return;
+        }
+        let window = self.window.clone().unwrap();
+        let (overlay, future) = start_overlay_pane(self, &pane, move |pane_id, term| {
+            confirm_file_transfer(pane_id, request, term, window)
+        });
+        self.assign_overlay_for_pane(pane_id, overlay);
+        promise::spawn::spawn(future).detach();
+    }
+}
+
+fn confirm_file_transfer(
+    pane_id: PaneId,
+    request: FileTransferRequest,
+    mut term: mux::termwiztermtab::TermWizTerminal,
+    window: ::window::Window,
+) -> anyhow::Result<()> {
+    // Respond even if the prompt fails, so that the
+    // application isn't left waiting
+    let result = match request.direction {
+        FileTransferDirection::Send => {
+            let default_dir = dirs_next::download_dir().unwrap_or_else(|| config::HOME_DIR.clone());
+            prompt::read_line(
+                &mut term,
+                "📁 The program running in this pane wants to send files to this computer.\n\
+                 They will only be written inside of the directory below.\n\
+                 Press Enter to allow it, or Escape to refuse.",
+                "Directory: ",
+                Some(&default_dir.display().to_string()),
+            )
+            .map(|line| {
+                let dest_dir = line.as_deref().and_then(chosen_dir);
+                (dest_dir.is_some(), dest_dir)
+            })
+        }
+        FileTransferDirection::Receive => {
+            let message = format!(
+                "📁 The program running in this pane wants to copy {} from this computer. \
+                 Allow it?",
+                request
+                    .paths
+                    .iter()
+                    .map(|path| path.display().to_string())
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            );
+            confirm::run_confirmation(&message, &mut term).map(|allowed| (allowed, None))
+        }
+    };
+    let (allowed, dest_dir) = match &result {
+        Ok((allowed, dest_dir)) => (*allowed, dest_dir.clone()),
+        Err(_) => (false, None),
+    };
+    respond_to_file_transfer(pane_id, request.id, allowed, dest_dir);
+    TermWindow::schedule_cancel_overlay_for_pane(window, pane_id);
+
+    result.map(|_| ())
+}
+
+/// Expands the directory that the user typed into the prompt;
+/// it must be an absolute path to an existing directory
+fn chosen_dir(line: &str) -> Option<PathBuf> {
+    let line = line.trim();
+    let dir = if line == "~" {
+        config::HOME_DIR.clone()
+    } else if let Some(rest) = line.strip_prefix("~/") {
+        config::HOME_DIR.join(rest)
+    } else {
+        PathBuf::from(line)
+    };
+    if dir.is_absolute() && dir.is_dir() {
+        Some(dir)
+    } else {
+        log::error!(
+            "refusing file transfer: {} is not an existing directory",
+            dir.display()
+        );
+        None
+    }
+}
@@ -5,6 +5,7 @@ use ::window::{
 use anyhow::Context;
 use config::keyassignment::{KeyAssignment, KeyTableEntry};
 use mux::pane::{Pane, PerformAssignmentResult};
+use mux::Mux;
 use smol::Timer;
 use std::sync::Arc;
 use std::time::{Duration, Instant};
@@ -218,6 +219,70 @@ impl super::TermWindow {
         }
     }
 
+    /// Returns the panes that should receive a copy of any input
+    /// that is sent to `pane`, according to the mux input broadcast state.
+    /// Panes from remote mux domains are client panes, so each of them
+    /// forwards its copy to its server as its own WriteToPane, SendKeyDown
+    /// or SendPaste request.
+    pub fn input_broadcast_targets(&self, pane: &Arc<dyn Pane>) -> Vec<Arc<dyn Pane>> {
+        Mux::get().input_broadcast_targets(pane.pane_id())
+    }
+
+    /// Sends a key event to `pane` and to any panes that are receiving
+    /// a broadcast copy of its input.  Each pane encodes the key according
+    /// to its own keyboard encoding mode.
+    fn send_key_to_panes(
+        &self,
+        pane: &Arc<dyn Pane>,
+        key_event: Option<&KeyEvent>,
+        key: ::termwiz::input::KeyCode,
+        modifiers: Modifiers,
+        is_down: bool,
+    ) -> anyhow::Result<()> {
+        self.send_key_to_pane(pane, key_event, key, modifiers, is_down)?;
+        for target in self.input_broadcast_targets(pane) {
+            if let Err(err) = self.send_key_to_pane(&target, key_event, key, modifiers, is_down) {
+                log::debug!("broadcasting key to pane {}: {:#}", target.pane_id(), err);
+            }
+        }
+        Ok(())
+    }
+
+    fn send_key_to_pane(
+        &self,
+        pane: &Arc<dyn Pane>,
+        key_event: Option<&KeyEvent>,
+        key: ::termwiz::input::KeyCode,
+        modifiers: Modifiers,
+        is_down: bool,
+    ) -> anyhow::Result<()> {
+        if let Some(key_event) = key_event {
+            if let Some(encoded) = self.encode_win32_input(pane, key_event) {
+                if self.config.debug_key_events {
+                    log::info!("win32: Encoded input as {:?}", encoded);
+                }
+                return pane
+                    .writer()
+                    .write_all(encoded.as_bytes())
+                    .context("sending win32-input-mode encoded data");
+            }
+            if let Some(encoded) = self.encode_kitty_input(pane, key_event) {
+                if self.config.debug_key_events {
+                    log::info!("kitty: Encoded input as {:?}", encoded);
+                }
+                return pane
+                    .writer()
+                    .write_all(encoded.as_bytes())
+                    .context("sending kitty encoded data");
+            }
+        }
+        if is_down {
+            pane.key_down(key, modifiers)
+        } else {
+            pane.key_up(key, modifiers)
+        }
+    }
+
     fn lookup_key(
         &mut self,
         pane: &Arc<dyn Pane>,
@@ -365,48 +430,18 @@ impl super::TermWindow {
 
             if bypass_compose {
                 if let Key::Code(term_key) = self.win_key_code_to_termwiz_key_code(keycode) {
-                    let tw_raw_modifiers = raw_modifiers;
-
-                    let mut did_encode = false;
-                    if let Some(key_event) = key_event {
-                        if let Some(encoded) = self.encode_win32_input(pane, key_event) {
-                            if self.config.debug_key_events {
-                                log::info!("win32: Encoded input as {:?}", encoded);
-                            }
-                            pane.writer()
-                                .write_all(encoded.as_bytes())
-                                .context("sending win32-input-mode encoded data")
-                                .ok();
-                            did_encode = true;
-                        } else if let Some(encoded) = self.encode_kitty_input(pane, key_event) {
-                            if self.config.debug_key_events {
-                                log::info!("kitty: Encoded input as {:?}", encoded);
-                            }
-                            pane.writer()
-                                .write_all(encoded.as_bytes())
-                                .context("sending kitty encoded data")
-                                .ok();
-                            did_encode = true;
-                        }
-                    };
-                    if !did_encode {
-                        if self.config.debug_key_events {
-                            log::info!(
-                                "{:?} {:?} -> send to pane {:?} {:?}",
-                                keycode,
-                                raw_modifiers,
-                                term_key,
-                                tw_raw_modifiers
-                            );
-                        }
+                    if self.config.debug_key_events {
+                        log::info!(
+                            "{:?} {:?} -> send to pane {:?}",
+                            keycode,
+                            raw_modifiers,
+                            term_key,
+                        );
+                    }
 
-                        did_encode = if is_down {
-                            pane.key_down(term_key, tw_raw_modifiers)
-                        } else {
-                            pane.key_up(term_key, tw_raw_modifiers)
-                        }
+                    let did_encode = self
+                        .send_key_to_panes(pane, key_event, term_key, raw_modifiers, is_down)
                         .is_ok();
-                    };
 
                     if did_encode {
                         if is_down
@@ -686,36 +721,22 @@ impl super::TermWindow {
                     return;
                 }
 
-                let res = if let Some(encoded) = self.encode_win32_input(&pane, &window_key) {
-                    if self.config.debug_key_events {
-                        log::info!("win32: Encoded input as {:?}", encoded);
-                    }
-                    pane.writer()
-                        .write_all(encoded.as_bytes())
-                        .context("sending win32-input-mode encoded data")
-                } else if let Some(encoded) = self.encode_kitty_input(&pane, &window_key) {
-                    if self.config.debug_key_events {
-                        log::info!("kitty: Encoded input as {:?}", encoded);
-                    }
-                    pane.writer()
-                        .write_all(encoded.as_bytes())
-                        .context("sending kitty encoded data")
-                } else {
-                    if self.config.debug_key_events {
-                        log::info!(
-                            "send to pane {} key={:?} mods={:?}",
-                            if window_key.key_is_down { "DOWN" } else { "UP" },
-                            key,
-                            modifiers
-                        );
-                    }
+                if self.config.debug_key_events {
+                    log::info!(
+                        "send to pane {} key={:?} mods={:?}",
+                        if window_key.key_is_down { "DOWN" } else { "UP" },
+                        key,
+                        modifiers
+                    );
+                }
 
-                    if window_key.key_is_down {
-                        pane.key_down(key, modifiers)
-                    } else {
-                        pane.key_up(key, modifiers)
-                    }
-                };
+                let res = self.send_key_to_panes(
+                    &pane,
+                    Some(&window_key),
+                    key,
+                    modifiers,
+                    window_key.key_is_down,
+                );
 
                 if res.is_ok() {
                     if window_key.key_is_down
@@ -751,6 +772,9 @@ impl super::TermWindow {
                     log::info!("send to pane string={:?}", s);
                 }
                 pane.writer().write_all(s.as_bytes()).ok();
+                for target in self.input_broadcast_targets(&pane) {
+                    target.writer().write_all(s.as_bytes()).ok();
+                }
                 self.maybe_scroll_to_bottom_for_input(&pane);
                 context.invalidate();
             }
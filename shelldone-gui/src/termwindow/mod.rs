@@ -20,9 +20,10 @@ use crate::tabbar::{TabBarItem, TabBarParams, TabBarState};
 use crate::termwindow::background::{
     load_background_image, reload_background_image, LoadedBackgroundLayer,
 };
+use crate::termwindow::commandblocks::CommandBlockCache;
 use crate::termwindow::keyevent::{KeyTableArgs, KeyTableState};
 use crate::termwindow::modal::Modal;
-use crate::termwindow::render::paint::AllowImage;
+use crate::termwindow::render::paint::{AllowImage, PendingShapes};
 use crate::termwindow::render::{
     CachedLineState, LineQuadCacheKey, LineQuadCacheValue, LineToEleShapeCacheKey,
     LineToElementShapeItem,
@@ -32,8 +33,9 @@ use ::shelldone_term::input::{ClickPosition, MouseButton as TMB};
 use ::window::*;
 use anyhow::{anyhow, ensure, Context};
 use config::keyassignment::{
-    Confirmation, KeyAssignment, LauncherActionArgs, PaneDirection, Pattern, PromptInputLine,
-    QuickSelectArguments, RotationDirection, SpawnCommand, SplitSize,
+    BroadcastInputScope, Confirmation, JoinPaneArguments, KeyAssignment, LauncherActionArgs,
+    MoveTabToWindowArguments, PaneDirection, Pattern, PromptInputLine, QuickSelectArguments,
+    RotationDirection, SpawnCommand, SplitSize,
 };
 use config::window::WindowLevel;
 use config::{
@@ -42,9 +44,12 @@ use config::{
 };
 use lfucache::*;
 use mlua::{FromLua, LuaSerdeExt, UserData, UserDataFields};
+use mux::broadcast::BroadcastScope;
+use mux::monitor::MonitorAlert;
 use mux::pane::{
     CachePolicy, CloseReason, Pane, PaneId, Pattern as MuxPattern, PerformAssignmentResult,
 };
+use mux::playback::PlaybackPane;
 use mux::renderable::RenderableDimensions;
 use mux::sigma_proxy::SigmaDirection;
 use mux::tab::{
@@ -63,7 +68,7 @@ use shelldone_toast_notification::persistent_toast_notification;
 use smol::channel::Sender;
 use smol::Timer;
 use std::cell::{RefCell, RefMut};
-use std::collections::{HashMap, LinkedList};
+use std::collections::{HashMap, HashSet, LinkedList};
 use std::env;
 use std::ops::Add;
 use std::rc::Rc;
@@ -77,6 +82,8 @@ pub mod background;
 pub mod box_model;
 pub mod charselect;
 pub mod clipboard;
+pub mod commandblocks;
+pub mod file_transfer;
 pub mod keyevent;
 pub mod modal;
 mod mouseevent;
@@ -315,6 +322,11 @@ pub struct PaneState {
 
     bell_start: Option<Instant>,
     pub mouse_terminal_coords: Option<(ClickPosition, StableRowIndex)>,
+    /// The ids of the command blocks whose output is collapsed
+    collapsed_command_blocks: HashSet<u64>,
+    /// The id of the command block that was activated via
+    /// ActivateCommandBlockRelative
+    active_command_block: Option<u64>,
 }
 
 /// Data used when synchronously formatting pane and window titles
@@ -327,6 +339,11 @@ pub struct TabInformation {
     pub active_pane: Option<PaneInformation>,
     pub window_id: MuxWindowId,
     pub tab_title: String,
+    /// The alert raised by a monitor of any pane in the tab,
+    /// that has not been focused since
+    pub monitor_alert: Option<MonitorAlert>,
+    /// Whether any pane in the tab is being recorded
+    pub is_recording: bool,
 }
 
 impl UserData for TabInformation {
@@ -356,6 +373,10 @@ impl UserData for TabInformation {
         });
         fields.add_field_method_get("window_id", |_, this| Ok(this.window_id));
         fields.add_field_method_get("tab_title", |_, this| Ok(this.tab_title.clone()));
+        fields.add_field_method_get("monitor_alert", |_, this| {
+            Ok(this.monitor_alert.map(|alert| alert.as_str()))
+        });
+        fields.add_field_method_get("is_recording", |_, this| Ok(this.is_recording));
         fields.add_field_method_get("window_title", |_, this| {
             let mux = Mux::get();
             let window = mux.get_window(this.window_id).ok_or_else(|| {
@@ -383,6 +404,9 @@ pub struct PaneInformation {
     pub title: String,
     pub user_vars: HashMap<String, String>,
     pub progress: Progress,
+    pub is_broadcasting: bool,
+    pub monitor_alert: Option<MonitorAlert>,
+    pub is_recording: bool,
 }
 
 impl UserData for PaneInformation {
@@ -392,6 +416,11 @@ impl UserData for PaneInformation {
         fields.add_field_method_get("is_active", |_, this| Ok(this.is_active));
         fields.add_field_method_get("is_zoomed", |_, this| Ok(this.is_zoomed));
         fields.add_field_method_get("has_unseen_output", |_, this| Ok(this.has_unseen_output));
+        fields.add_field_method_get("is_broadcasting", |_, this| Ok(this.is_broadcasting));
+        fields.add_field_method_get("is_recording", |_, this| Ok(this.is_recording));
+        fields.add_field_method_get("monitor_alert", |_, this| {
+            Ok(this.monitor_alert.map(|alert| alert.as_str()))
+        });
         fields.add_field_method_get("left", |_, this| Ok(this.left));
         fields.add_field_method_get("top", |_, this| Ok(this.top));
         fields.add_field_method_get("width", |_, this| Ok(this.width));
@@ -520,6 +549,7 @@ pub struct TermWindow {
     tab_state: RefCell<HashMap<TabId, TabState>>,
     pane_state: RefCell<HashMap<PaneId, PaneState>>,
     semantic_zones: HashMap<PaneId, SemanticZoneCache>,
+    command_blocks: HashMap<PaneId, CommandBlockCache>,
 
     window_background: Vec<LoadedBackgroundLayer>,
 
@@ -528,6 +558,7 @@ pub struct TermWindow {
     current_mouse_capture: Option<MouseCapture>,
 
     opengl_info: Option<String>,
+    pending_shapes: PendingShapes,
 
     /// Keeps track of double and triple clicks
     last_mouse_click: Option<LastMouseClick>,
@@ -639,6 +670,9 @@ impl TermWindow {
     fn focus_changed(&mut self, focused: bool, window: &Window) {
         log::trace!("Setting focus to {:?}", focused);
         self.focused = if focused { Some(Instant::now()) } else { None };
+        if let Some(fe) = crate::frontend::try_front_end() {
+            fe.record_window_focus(self.mux_window_id, focused);
+        }
         self.quad_generation += 1;
         self.load_os_parameters();
 
@@ -696,7 +730,12 @@ impl TermWindow {
 }
 
 impl TermWindow {
-    pub async fn new_window(mux_window_id: MuxWindowId) -> anyhow::Result<()> {
+    /// Builds the state of a window for `mux_window_id`, along with its
+    /// resize increments, without creating the window itself
+    fn new_state(
+        mux_window_id: MuxWindowId,
+        connection_name: String,
+    ) -> anyhow::Result<(Self, ResizeIncrementCalculator)> {
         let config = configuration();
         let dpi = config.dpi.unwrap_or_else(::window::default_dpi) as usize;
         let fontconfig = Rc::new(FontConfiguration::new(Some(config.clone()), dpi)?);
@@ -789,8 +828,6 @@ impl TermWindow {
 
         let render_state = None;
 
-        let connection_name = Connection::get().unwrap().name();
-
         let mut myself = Self {
             created: Instant::now(),
             connection_name,
@@ -898,6 +935,7 @@ impl TermWindow {
             scheduled_animation: RefCell::new(None),
             allow_images: AllowImage::Yes,
             semantic_zones: HashMap::new(),
+            command_blocks: HashMap::new(),
             ui_items: vec![],
             dragging: None,
             last_ui_item: None,
@@ -905,10 +943,45 @@ impl TermWindow {
             key_table_state: KeyTableState::default(),
             modal: RefCell::new(None),
             opengl_info: None,
+            pending_shapes: PendingShapes::default(),
         };
 
         myself.update_right_status_from_components();
 
+        let resize_increments = ResizeIncrementCalculator {
+            x: myself.render_metrics.cell_size.width as u16,
+            y: myself.render_metrics.cell_size.height as u16,
+            padding_left,
+            padding_top,
+            padding_right,
+            padding_bottom,
+            border,
+            tab_bar_height,
+        };
+
+        Ok((myself, resize_increments))
+    }
+
+    /// Creates a window that renders into memory rather than onto a
+    /// display; see `paint_to_image`
+    pub fn new_headless(mux_window_id: MuxWindowId) -> anyhow::Result<Self> {
+        let (mut myself, _resize_increments) =
+            Self::new_state(mux_window_id, "headless".to_string())?;
+        // Paint as the focused window is painted
+        myself.focused.replace(Instant::now());
+        myself.created(RenderContext::Cpu)?;
+        myself.update_title_impl();
+        Ok(myself)
+    }
+
+    pub async fn new_window(mux_window_id: MuxWindowId) -> anyhow::Result<()> {
+        let connection_name = Connection::get().unwrap().name();
+        let (myself, resize_increments) = Self::new_state(mux_window_id, connection_name)?;
+        let config = myself.config.clone();
+        let fontconfig = Rc::clone(&myself.fonts);
+        let dimensions = myself.dimensions;
+        let mux = Mux::get();
+
         let tw = Rc::new(RefCell::new(myself));
         let tw_event = Rc::clone(&tw);
 
@@ -979,19 +1052,7 @@ impl TermWindow {
             let mut myself = tw.borrow_mut();
             myself.config_subscription.replace(config_subscription);
             if config.use_resize_increments {
-                window.set_resize_increments(
-                    ResizeIncrementCalculator {
-                        x: myself.render_metrics.cell_size.width as u16,
-                        y: myself.render_metrics.cell_size.height as u16,
-                        padding_left,
-                        padding_top,
-                        padding_right,
-                        padding_bottom,
-                        border,
-                        tab_bar_height,
-                    }
-                    .into(),
-                );
+                window.set_resize_increments(resize_increments.into());
             }
 
             if let Some(gl) = gl {
@@ -1140,7 +1201,7 @@ impl TermWindow {
                     Some(pane) => pane,
                     None => return Ok(true),
                 };
-                pane.send_paste(text.as_str())?;
+                self.send_paste_to_panes(&pane, text.as_str())?;
                 Ok(true)
             }
             WindowEvent::DroppedUrl(urls) => {
@@ -1154,7 +1215,7 @@ impl TermWindow {
                     .collect::<Vec<_>>()
                     .join(" ")
                     + " ";
-                pane.send_paste(urls.as_str())?;
+                self.send_paste_to_panes(&pane, urls.as_str())?;
                 Ok(true)
             }
             WindowEvent::DroppedFile(paths) => {
@@ -1172,7 +1233,7 @@ impl TermWindow {
                     .collect::<Vec<_>>()
                     .join(" ")
                     + " ";
-                pane.send_paste(&paths)?;
+                self.send_paste_to_panes(&pane, &paths)?;
                 Ok(true)
             }
             WindowEvent::DraggedFile(_) => Ok(true),
@@ -1231,9 +1292,7 @@ impl TermWindow {
 
         match notif {
             TermWindowNotif::InvalidateShapeCache => {
-                self.shape_generation += 1;
-                self.shape_cache.borrow_mut().clear();
-                self.invalidate_modal();
+                self.invalidate_shape_cache();
                 window.invalidate();
             }
             TermWindowNotif::PerformAssignment {
@@ -1319,6 +1378,17 @@ impl TermWindow {
                 } => {
                     self.emit_user_var_event(pane_id, name, value);
                 }
+                MuxNotification::Alert {
+                    alert:
+                        Alert::TriggerEvent {
+                            name,
+                            line,
+                            captures,
+                        },
+                    pane_id,
+                } => {
+                    self.emit_trigger_event(pane_id, name, line, captures);
+                }
                 MuxNotification::WindowTitleChanged { .. }
                 | MuxNotification::Alert {
                     alert:
@@ -1365,7 +1435,25 @@ impl TermWindow {
                     window.invalidate();
                 }
                 MuxNotification::Alert {
-                    alert: Alert::ToastNotification { .. },
+                    alert: alert @ (Alert::Activity | Alert::Silence(_)),
+                    pane_id,
+                } => {
+                    if !self.window_contains_pane(pane_id) {
+                        return Ok(());
+                    }
+                    let name = match alert {
+                        Alert::Activity => "pane-activity",
+                        _ => "pane-silence",
+                    };
+                    self.emit_window_event(name, Some(pane_id));
+                    self.update_title();
+                    window.invalidate();
+                }
+                MuxNotification::Alert {
+                    alert:
+                        Alert::ToastNotification { .. }
+                        | Alert::CloseToastNotification { .. }
+                        | Alert::CommandFinished(_),
                     ..
                 } => {}
                 MuxNotification::TabAddedToWindow {
@@ -1415,6 +1503,15 @@ impl TermWindow {
                 MuxNotification::SaveToDownloads { .. } => {
                     // Handled by frontend
                 }
+                MuxNotification::AssignClipboardData { .. } => {
+                    // Handled by frontend
+                }
+                MuxNotification::RequestClipboard { pane_id, request } => {
+                    self.request_clipboard_read(pane_id, request);
+                }
+                MuxNotification::RequestFileTransfer { pane_id, request } => {
+                    self.request_file_transfer(pane_id, request);
+                }
                 MuxNotification::PaneFocused(_) => {
                     // Also handled by clientpane
                     self.update_title_post_status();
@@ -1583,6 +1680,9 @@ impl TermWindow {
                     | Alert::IconTitleChanged(_)
                     | Alert::Progress(_)
                     | Alert::SetUserVar { .. }
+                    | Alert::TriggerEvent { .. }
+                    | Alert::Activity
+                    | Alert::Silence(_)
                     | Alert::Bell,
             }
             | MuxNotification::PaneFocused(pane_id)
@@ -1620,6 +1720,18 @@ impl TermWindow {
                     return true;
                 }
             }
+            MuxNotification::RequestClipboard { pane_id, .. }
+            | MuxNotification::RequestFileTransfer { pane_id, .. } => {
+                // Only the window that contains the pane may prompt for
+                // and answer the request
+                let mux = Mux::get();
+                match mux.resolve_pane_id(pane_id) {
+                    Some((_domain_id, window_id, _tab_id)) if window_id == mux_window_id => {
+                        // fall through
+                    }
+                    _ => return true,
+                }
+            }
             MuxNotification::TabResized(tab_id)
             | MuxNotification::TabTitleChanged { tab_id, .. } => {
                 let mux = Mux::get();
@@ -1630,10 +1742,14 @@ impl TermWindow {
                 }
             }
             MuxNotification::Alert {
-                alert: Alert::ToastNotification { .. },
+                alert:
+                    Alert::ToastNotification { .. }
+                    | Alert::CloseToastNotification { .. }
+                    | Alert::CommandFinished(_),
                 ..
             }
             | MuxNotification::AssignClipboard { .. }
+            | MuxNotification::AssignClipboardData { .. }
             | MuxNotification::SaveToDownloads { .. }
             | MuxNotification::WindowCreated(_)
             | MuxNotification::ActiveWorkspaceChanged(_)
@@ -1961,6 +2077,14 @@ impl TermWindow {
         }
     }
 
+    /// Discards shaped text, so that it is shaped again with the fonts
+    /// that have been resolved since it was shaped
+    fn invalidate_shape_cache(&mut self) {
+        self.shape_generation += 1;
+        self.shape_cache.borrow_mut().clear();
+        self.invalidate_modal();
+    }
+
     pub fn cancel_modal(&self) {
         self.modal.borrow_mut().take();
         if let Some(window) = self.window.as_ref() {
@@ -2063,6 +2187,47 @@ impl TermWindow {
         .detach();
     }
 
+    fn emit_trigger_event(
+        &mut self,
+        pane_id: PaneId,
+        name: String,
+        line: String,
+        captures: Vec<String>,
+    ) {
+        if !self.window_contains_pane(pane_id) {
+            return;
+        }
+
+        let mux = Mux::get();
+        let window = GuiWin::new(self);
+        let pane = match mux.get_pane(pane_id) {
+            Some(pane) => mux_lua::MuxPane(pane.pane_id()),
+            None => return,
+        };
+
+        async fn do_event(
+            lua: Option<Rc<mlua::Lua>>,
+            name: String,
+            line: String,
+            captures: Vec<String>,
+            window: GuiWin,
+            pane: MuxPane,
+        ) -> anyhow::Result<()> {
+            if let Some(lua) = lua {
+                let args = lua.pack_multi((window, pane, line, captures))?;
+                if let Err(err) = config::lua::emit_event(&lua, (name.clone(), args)).await {
+                    log::error!("while processing trigger event {}: {:#}", name, err);
+                }
+            }
+            Ok(())
+        }
+
+        promise::spawn::spawn(config::with_lua_config_on_main_thread(move |lua| {
+            do_event(lua, name, line, captures, window, pane)
+        }))
+        .detach();
+    }
+
     /// Called by window:set_right_status after the status has
     /// been updated; let's update the bar
     pub fn update_title_post_status(&mut self) {
@@ -2524,6 +2689,27 @@ impl TermWindow {
         promise::spawn::spawn(future).detach();
     }
 
+    fn show_command_history(&mut self, pane: &Arc<dyn Pane>) {
+        let (overlay, future) = start_overlay_pane(self, pane, move |pane_id, term| {
+            crate::overlay::command_history::show_command_history(pane_id, term)
+        });
+        self.assign_overlay_for_pane(pane.pane_id(), overlay);
+        promise::spawn::spawn(future).detach();
+    }
+
+    fn show_playback_controls(&mut self, pane: &Arc<dyn Pane>) {
+        let Some(playback) = pane.downcast_ref::<PlaybackPane>() else {
+            log::error!("pane {} is not playing back an asciicast", pane.pane_id());
+            return;
+        };
+        let player = Arc::clone(playback.player());
+        let (overlay, future) = start_overlay_pane(self, pane, move |_pane_id, term| {
+            crate::overlay::playback::show_playback_controls(player, term)
+        });
+        self.assign_overlay_for_pane(pane.pane_id(), overlay);
+        promise::spawn::spawn(future).detach();
+    }
+
     fn show_debug_overlay(&mut self) {
         let mux = Mux::get();
         let tab = match mux.get_active_tab_for_window(self.mux_window_id) {
@@ -2961,7 +3147,12 @@ impl TermWindow {
             ActivateWindowRelativeNoWrap(n) => {
                 self.activate_window_relative(*n, false)?;
             }
-            SendString(s) => pane.writer().write_all(s.as_bytes())?,
+            SendString(s) => {
+                pane.writer().write_all(s.as_bytes())?;
+                for target in self.input_broadcast_targets(pane) {
+                    target.writer().write_all(s.as_bytes()).ok();
+                }
+            }
             SendKey(key) => {
                 use keyevent::Key;
                 let mods = key.mods;
@@ -2969,6 +3160,9 @@ impl TermWindow {
                     &key.key.resolve(self.config.key_map_preference),
                 ) {
                     pane.key_down(key, mods)?;
+                    for target in self.input_broadcast_targets(pane) {
+                        target.key_down(key, mods).ok();
+                    }
                 }
             }
             Hide => {
@@ -2991,6 +3185,13 @@ impl TermWindow {
             ScrollByLine(n) => self.scroll_by_line(*n, pane)?,
             ScrollByCurrentEventWheelDelta => self.scroll_by_current_event_wheel_delta(pane)?,
             ScrollToPrompt(n) => self.scroll_to_prompt(*n, pane)?,
+            ActivateCommandBlockRelative(n) => self.activate_command_block_relative(*n, pane)?,
+            CopyCommandBlockTo { part, destination } => {
+                self.copy_command_block_to(*part, *destination, pane)?
+            }
+            RerunCommandBlock => self.rerun_command_block(pane)?,
+            ToggleCommandBlockCollapsed => self.toggle_command_block_collapsed(pane)?,
+            ShowCommandHistory => self.show_command_history(pane),
             ScrollToTop => self.scroll_to_top(pane),
             ScrollToBottom => self.scroll_to_bottom(pane),
             ShowTabNavigator => self.show_tab_navigator(),
@@ -3375,6 +3576,41 @@ impl TermWindow {
             PromptInputLine(args) => self.show_prompt_input_line(args),
             InputSelector(args) => self.show_input_selector(args),
             Confirmation(args) => self.show_confirmation(args),
+            ToggleBroadcastInput(scope) => self.toggle_broadcast_input(*scope),
+            TogglePaneBroadcastMark => {
+                let mux = Mux::get();
+                if mux.get_pane(pane.pane_id()).is_some() {
+                    mux.toggle_pane_broadcast_mark(pane.pane_id());
+                }
+            }
+            TogglePaneActivityMonitor => {
+                let mut monitors = pane.get_monitors();
+                monitors.activity = !monitors.activity;
+                pane.set_monitors(monitors);
+            }
+            TogglePaneSilenceMonitor(secs) => {
+                let mut monitors = pane.get_monitors();
+                monitors.silence = match monitors.silence {
+                    Some(_) => None,
+                    None => Some(Duration::from_secs((*secs).max(1))),
+                };
+                pane.set_monitors(monitors);
+            }
+            TogglePaneRecording => {
+                if !pane.stop_recording() {
+                    let path = mux::recording::default_recording_path(pane.pane_id());
+                    let options = mux::recording::RecordingOptions::from_config();
+                    match mux::recording::record_pane_to_file(pane, &path, options) {
+                        Ok(()) => {
+                            log::info!("recording pane {} to {}", pane.pane_id(), path.display())
+                        }
+                        Err(err) => log::error!("failed to record pane: {err:#}"),
+                    }
+                }
+            }
+            ShowPlaybackControls => self.show_playback_controls(pane),
+            JoinPane(args) => self.join_pane(pane, args),
+            MoveTabToWindow(args) => self.move_tab_to_window(args),
         };
         Ok(PerformAssignmentResult::Handled)
     }
@@ -3422,6 +3658,112 @@ impl TermWindow {
             .detach();
         }
     }
+
+    fn toggle_broadcast_input(&mut self, scope: BroadcastInputScope) {
+        let mux = Mux::get();
+        let scope = match scope {
+            BroadcastInputScope::CurrentTab => {
+                match mux.get_active_tab_for_window(self.mux_window_id) {
+                    Some(tab) => BroadcastScope::Tab(tab.tab_id()),
+                    None => return,
+                }
+            }
+            BroadcastInputScope::CurrentWorkspace => match mux.get_window(self.mux_window_id) {
+                Some(window) => BroadcastScope::Workspace(window.get_workspace().to_string()),
+                None => return,
+            },
+            BroadcastInputScope::MarkedPanes => BroadcastScope::Marked,
+        };
+        let enabled = mux.toggle_input_broadcast(scope.clone());
+        log::info!(
+            "input broadcast {} for {:?}",
+            if enabled { "enabled" } else { "disabled" },
+            scope
+        );
+    }
+
+    /// Moves `pane` into a split alongside the active pane of another
+    /// tab in this window
+    fn join_pane(&mut self, pane: &Arc<dyn Pane>, args: &JoinPaneArguments) {
+        let mux = Mux::get();
+        let tab_id = {
+            let window = match mux.get_window(self.mux_window_id) {
+                Some(window) => window,
+                None => return,
+            };
+            let tab_idx = match args.tab_index {
+                Some(idx) if idx < 0 => window.len().checked_sub(idx.unsigned_abs()),
+                Some(idx) => Some(idx as usize),
+                None => window.get_last_active_idx(),
+            };
+            match tab_idx.and_then(|idx| window.get_by_idx(idx)) {
+                Some(tab) => tab.tab_id(),
+                None => {
+                    log::error!("JoinPane: no tab matching {:?}", args.tab_index);
+                    return;
+                }
+            }
+        };
+
+        let request = SplitRequest {
+            direction: match args.direction {
+                PaneDirection::Down | PaneDirection::Up => SplitDirection::Vertical,
+                PaneDirection::Left | PaneDirection::Right => SplitDirection::Horizontal,
+                PaneDirection::Next | PaneDirection::Prev => {
+                    log::error!("Invalid direction {:?} for JoinPane", args.direction);
+                    return;
+                }
+            },
+            target_is_second: matches!(args.direction, PaneDirection::Down | PaneDirection::Right),
+            size: match args.size {
+                SplitSize::Percent(n) => MuxSplitSize::Percent(n),
+                SplitSize::Cells(n) => MuxSplitSize::Cells(n),
+            },
+            top_level: args.top_level,
+        };
+
+        let pane_id = pane.pane_id();
+        promise::spawn::spawn(async move {
+            if let Err(err) = mux.join_pane(pane_id, tab_id, request).await {
+                log::error!("failed to join_pane: {err:#}");
+                return;
+            }
+            mux.focus_pane_and_containing_tab(pane_id).ok();
+        })
+        .detach();
+    }
+
+    /// Moves the active tab into the GUI window at `window_index`,
+    /// or into a new window
+    fn move_tab_to_window(&mut self, args: &MoveTabToWindowArguments) {
+        let mux = Mux::get();
+        let tab_id = match mux.get_active_tab_for_window(self.mux_window_id) {
+            Some(tab) => tab.tab_id(),
+            None => return,
+        };
+        let window_id = match args.window_index {
+            Some(idx) => match front_end().gui_windows().get(idx) {
+                Some(win) => Some(win.mux_window_id),
+                None => {
+                    log::error!("MoveTabToWindow: no window with index {idx}");
+                    return;
+                }
+            },
+            None => None,
+        };
+        if window_id == Some(self.mux_window_id) {
+            return;
+        }
+        let workspace = args.workspace.clone();
+
+        promise::spawn::spawn(async move {
+            if let Err(err) = mux.move_tab_to_window(tab_id, window_id, workspace).await {
+                log::error!("failed to move_tab_to_window: {err:#}");
+            }
+        })
+        .detach();
+    }
+
     fn close_current_pane(&mut self, confirm: bool) {
         let mux_window_id = self.mux_window_id;
         let mux = Mux::get();
@@ -3574,6 +3916,15 @@ impl TermWindow {
                     qs.viewport_changed(pos);
                 }
             }
+
+            let pointer = state
+                .mouse_terminal_coords
+                .as_ref()
+                .map(|(position, stable_row)| (position.column, *stable_row));
+            drop(state);
+            if let Some(pane) = Mux::get().get_pane(pane_id) {
+                pane.advise_viewport(pos, pointer);
+            }
         }
         self.window.as_ref().unwrap().invalidate();
     }
@@ -3661,6 +4012,9 @@ impl TermWindow {
             title: pos.pane.get_title(),
             user_vars: pos.pane.copy_user_vars(),
             progress: pos.pane.get_progress(),
+            is_broadcasting: Mux::get().is_pane_broadcasting(pos.pane.pane_id()),
+            monitor_alert: mux::monitor::pending_alert(pos.pane.pane_id()),
+            is_recording: pos.pane.is_recording(),
         }
     }
 
@@ -3688,6 +4042,14 @@ impl TermWindow {
                         .unwrap_or(false),
                     window_id: self.mux_window_id,
                     tab_title: tab.get_title(),
+                    monitor_alert: tab
+                        .iter_panes_ignoring_zoom()
+                        .iter()
+                        .find_map(|pos| mux::monitor::pending_alert(pos.pane.pane_id())),
+                    is_recording: tab
+                        .iter_panes_ignoring_zoom()
+                        .iter()
+                        .any(|pos| pos.pane.is_recording()),
                     active_pane: panes
                         .iter()
                         .find(|p| p.is_active)
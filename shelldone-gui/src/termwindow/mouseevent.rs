@@ -765,11 +765,7 @@ impl super::TermWindow {
             event
         );
 
-        let dims = pane.get_dimensions();
-        let stable_row = self
-            .get_viewport(pane.pane_id())
-            .unwrap_or(dims.physical_top)
-            + row as StableRowIndex;
+        let stable_row = self.stable_row_at_viewport_row(&pane, row);
 
         self.pane_state(pane.pane_id())
             .mouse_terminal_coords
@@ -782,6 +778,10 @@ impl super::TermWindow {
                 },
                 stable_row,
             ));
+        pane.advise_viewport(
+            self.get_viewport(pane.pane_id()),
+            Some((column, stable_row)),
+        );
 
         pane.apply_hyperlinks(stable_row..stable_row + 1, &self.config.hyperlink_rules);
 
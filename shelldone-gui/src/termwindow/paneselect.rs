@@ -178,10 +178,24 @@ impl PaneSelector {
                     }
                 }
                 PaneSelectMode::SwapWithActiveKeepFocus | PaneSelectMode::SwapWithActive => {
-                    tab.swap_active_with_index(
-                        pane_index,
-                        self.mode == PaneSelectMode::SwapWithActiveKeepFocus,
-                    );
+                    let active = tab.get_active_pane();
+                    if let (Some(active), Some(pos)) =
+                        (active, panes.iter().find(|p| p.index == pane_index))
+                    {
+                        let active_id = active.pane_id();
+                        let pane_id = pos.pane.pane_id();
+                        let keep_focus = self.mode == PaneSelectMode::SwapWithActiveKeepFocus;
+                        promise::spawn::spawn(async move {
+                            if let Err(err) = mux.swap_panes(active_id, pane_id).await {
+                                log::error!("failed to swap_panes: {err:#}");
+                                return;
+                            }
+                            if !keep_focus {
+                                mux.focus_pane_and_containing_tab(pane_id).ok();
+                            }
+                        })
+                        .detach();
+                    }
                 }
                 PaneSelectMode::MoveToNewWindow => {
                     if let Some(pos) = panes.iter().find(|p| p.index == pane_index) {
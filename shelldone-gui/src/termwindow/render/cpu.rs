@@ -0,0 +1,231 @@
+//! Rasterizes the quads of the render layers in software, for windows
+//! that render into memory rather than onto a display.
+//!
+//! Each quad is shaded as the glyph shader does, and blended as the gpu
+//! blends into an srgb framebuffer, so that the image matches what the
+//! gui shows.  Subpixel antialiasing is not emulated; those quads are
+//! blended by their alpha, as when it is disabled.
+use crate::quad::{Vertex, VERTICES_PER_CELL, V_BOT_RIGHT, V_TOP_LEFT};
+use ::window::bitmaps::{BitmapImage, Image, ImageTexture};
+use ::window::color::LinearRgba;
+use anyhow::anyhow;
+
+/// The values of `has_color` that select how a quad is shaded;
+/// see `glyph-frag.glsl`
+const IS_GLYPH: f32 = 0.0;
+const IS_COLOR_EMOJI: f32 = 1.0;
+const IS_BG_IMAGE: f32 = 2.0;
+const IS_SOLID_COLOR: f32 = 3.0;
+const IS_GRAY_SCALE: f32 = 4.0;
+
+impl crate::TermWindow {
+    pub(super) fn call_draw_cpu(&mut self, image: &mut Image) -> anyhow::Result<()> {
+        let render_state = self.render_state.as_ref().unwrap();
+        let tex = render_state.glyph_cache.borrow().atlas.texture();
+        let tex = tex
+            .downcast_ref::<ImageTexture>()
+            .ok_or_else(|| anyhow!("the texture atlas is not held in memory"))?;
+        let atlas = tex.image.borrow();
+
+        let foreground_text_hsb = self.config.foreground_text_hsb;
+        let foreground_text_hsb = [
+            foreground_text_hsb.hue,
+            foreground_text_hsb.saturation,
+            foreground_text_hsb.brightness,
+        ];
+
+        let mut canvas = Canvas::new(image, &atlas, foreground_text_hsb);
+        for layer in render_state.layers.borrow().iter() {
+            for idx in 0..3 {
+                let vb = &layer.vb.borrow()[idx];
+                let (vertex_count, _index_count) = vb.vertex_index_count();
+                if vertex_count > 0 {
+                    let vertices = vb.current_vb_mut();
+                    let vertices = vertices.cpu();
+                    let vertex_count = vertex_count.min(vertices.len());
+                    for quad in vertices[..vertex_count].chunks_exact(VERTICES_PER_CELL) {
+                        canvas.draw_quad(quad);
+                    }
+                }
+
+                vb.next_index();
+            }
+        }
+        canvas.finish(image);
+
+        Ok(())
+    }
+}
+
+struct Canvas<'a> {
+    width: usize,
+    height: usize,
+    /// The framebuffer, holding srgb encoded colors as the gpu does
+    pixels: Vec<[f32; 4]>,
+    atlas: &'a Image,
+    foreground_text_hsb: [f32; 3],
+}
+
+impl<'a> Canvas<'a> {
+    fn new(image: &Image, atlas: &'a Image, foreground_text_hsb: [f32; 3]) -> Self {
+        let (width, height) = image.image_dimensions();
+        Self {
+            width,
+            height,
+            pixels: vec![[0.; 4]; width * height],
+            atlas,
+            foreground_text_hsb,
+        }
+    }
+
+    /// Draws a quad; quads are always axis aligned rectangles, with
+    /// their texture coordinates interpolated across them
+    fn draw_quad(&mut self, quad: &[Vertex]) {
+        let top_left = &quad[V_TOP_LEFT];
+        let bottom_right = &quad[V_BOT_RIGHT];
+
+        // The positions are relative to the center of the window
+        let x0 = top_left.position[0] + self.width as f32 / 2.;
+        let y0 = top_left.position[1] + self.height as f32 / 2.;
+        let x1 = bottom_right.position[0] + self.width as f32 / 2.;
+        let y1 = bottom_right.position[1] + self.height as f32 / 2.;
+        if x1 <= x0 || y1 <= y0 {
+            return;
+        }
+
+        // A pixel is covered when its center is within the quad
+        let first_pixel = |edge: f32, limit: usize| (edge - 0.5).ceil().clamp(0., limit as f32);
+        let (px0, px1) = (first_pixel(x0, self.width), first_pixel(x1, self.width));
+        let (py0, py1) = (first_pixel(y0, self.height), first_pixel(y1, self.height));
+
+        let fg_color = mix4(top_left.fg_color, top_left.alt_color, top_left.mix_value);
+
+        for py in py0 as usize..py1 as usize {
+            let v = (py as f32 + 0.5 - y0) / (y1 - y0);
+            let tex_y = top_left.tex[1] + (bottom_right.tex[1] - top_left.tex[1]) * v;
+            for px in px0 as usize..px1 as usize {
+                let u = (px as f32 + 0.5 - x0) / (x1 - x0);
+                let tex_x = top_left.tex[0] + (bottom_right.tex[0] - top_left.tex[0]) * u;
+                let color = self.shade(top_left, fg_color, tex_x, tex_y);
+                self.blend(px, py, color);
+            }
+        }
+    }
+
+    /// Computes the color of a pixel of a quad, in linear space,
+    /// as `glyph-frag.glsl` does
+    fn shade(&self, vertex: &Vertex, fg_color: [f32; 4], tex_x: f32, tex_y: f32) -> [f32; 4] {
+        let mode = vertex.has_color;
+        let color = if mode == IS_SOLID_COLOR {
+            fg_color
+        } else if mode == IS_BG_IMAGE {
+            let mut color = self.sample(tex_x, tex_y);
+            color[3] *= fg_color[3];
+            color
+        } else if mode == IS_COLOR_EMOJI {
+            self.sample(tex_x, tex_y)
+        } else if mode == IS_GRAY_SCALE {
+            let mask = self.sample(tex_x, tex_y);
+            let mut color = fg_color;
+            color[3] = mix(
+                vertex.fg_color[3],
+                vertex.alt_color[3],
+                vertex.mix_value.clamp(0., 1.),
+            ) * mask[3];
+            color
+        } else if mode == IS_GLYPH {
+            let mask = self.sample(tex_x, tex_y);
+            let mut color = fg_color;
+            color[3] = mask[3];
+            apply_hsv(color, self.foreground_text_hsb)
+        } else {
+            [0.; 4]
+        };
+        apply_hsv(color, vertex.hsv)
+    }
+
+    /// Samples the atlas at the nearest texel, which holds srgb data
+    fn sample(&self, tex_x: f32, tex_y: f32) -> [f32; 4] {
+        let (width, height) = self.atlas.image_dimensions();
+        let x = ((tex_x * width as f32) as isize).clamp(0, width as isize - 1) as usize;
+        let y = ((tex_y * height as f32) as isize).clamp(0, height as isize - 1) as usize;
+        let offset = (y * width + x) * 4;
+        let data = &self.atlas.pixel_data_slice()[offset..offset + 4];
+        let (r, g, b, a) = LinearRgba::with_srgba(data[0], data[1], data[2], data[3]).tuple();
+        [r, g, b, a]
+    }
+
+    /// Blends with the alpha of the color, in srgb space, as the gpu
+    /// does when the shader outputs srgb
+    fn blend(&mut self, x: usize, y: usize, color: [f32; 4]) {
+        let srgb = LinearRgba::with_components(color[0], color[1], color[2], color[3]).to_srgb();
+        let alpha = srgb.3.clamp(0., 1.);
+        let dest = &mut self.pixels[y * self.width + x];
+        for (dest, src) in dest.iter_mut().zip([srgb.0, srgb.1, srgb.2]) {
+            *dest = src * alpha + *dest * (1. - alpha);
+        }
+        dest[3] = alpha + dest[3] * (1. - alpha);
+    }
+
+    fn finish(self, image: &mut Image) {
+        let data = image.pixel_data_slice_mut();
+        for (pixel, dest) in self.pixels.iter().zip(data.chunks_exact_mut(4)) {
+            for (channel, dest) in pixel.iter().zip(dest.iter_mut()) {
+                *dest = (channel.clamp(0., 1.) * 255.).round() as u8;
+            }
+        }
+    }
+}
+
+fn mix(a: f32, b: f32, t: f32) -> f32 {
+    a + (b - a) * t
+}
+
+fn mix4(a: [f32; 4], b: [f32; 4], t: f32) -> [f32; 4] {
+    [
+        mix(a[0], b[0], t),
+        mix(a[1], b[1], t),
+        mix(a[2], b[2], t),
+        mix(a[3], b[3], t),
+    ]
+}
+
+fn apply_hsv(color: [f32; 4], transform: [f32; 3]) -> [f32; 4] {
+    if transform == [1., 1., 1.] {
+        return color;
+    }
+    let [h, s, v] = rgb2hsv([color[0], color[1], color[2]]);
+    let [r, g, b] = hsv2rgb([h * transform[0], s * transform[1], v * transform[2]]);
+    [r, g, b, color[3]]
+}
+
+fn rgb2hsv([r, g, b]: [f32; 3]) -> [f32; 3] {
+    let k = [0.0, -1.0 / 3.0, 2.0 / 3.0, -1.0];
+    let p = if g >= b {
+        [g, b, k[0], k[1]]
+    } else {
+        [b, g, k[3], k[2]]
+    };
+    let q = if r >= p[0] {
+        [r, p[1], p[2], p[0]]
+    } else {
+        [p[0], p[1], p[3], r]
+    };
+    let d = q[0] - q[3].min(q[1]);
+    let e = 1.0e-10;
+    [
+        (q[2] + (q[3] - q[1]) / (6.0 * d + e)).abs(),
+        d / (q[0] + e),
+        q[0],
+    ]
+}
+
+fn hsv2rgb([h, s, v]: [f32; 3]) -> [f32; 3] {
+    let k = [1.0, 2.0 / 3.0, 1.0 / 3.0, 3.0];
+    let channel = |offset: f32| {
+        let x = h + offset;
+        let p = ((x - x.floor()) * 6.0 - k[3]).abs();
+        v * mix(k[0], (p - k[0]).clamp(0.0, 1.0), s)
+    };
+    [channel(k[0]), channel(k[1]), channel(k[2])]
+}
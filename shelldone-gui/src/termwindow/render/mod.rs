@@ -7,10 +7,10 @@ use crate::quad::{
 };
 use crate::shapecache::*;
 use crate::termwindow::render::paint::AllowImage;
-use crate::termwindow::{BorrowedShapeCacheKey, RenderState, ShapedInfo, TermWindowNotif};
+use crate::termwindow::{BorrowedShapeCacheKey, RenderState, ShapedInfo};
 use crate::utilsprites::RenderMetrics;
 use ::window::bitmaps::{TextureCoord, TextureRect, TextureSize};
-use ::window::{DeadKeyStatus, PointF, RectF, SizeF, WindowOps};
+use ::window::{DeadKeyStatus, PointF, RectF, SizeF};
 use anyhow::{anyhow, Context};
 use config::{
     BoldBrightening, ConfigHandle, DimensionContext, HorizontalWindowContentAlignment, TextStyle,
@@ -36,6 +36,7 @@ use window::color::LinearRgba;
 
 pub mod borders;
 pub mod corners;
+pub mod cpu;
 pub mod draw;
 pub mod fancy_tab_bar;
 pub mod paint;
@@ -847,7 +848,7 @@ impl crate::TermWindow {
                     Some(f) => Rc::clone(f),
                     None => self.fonts.resolve_font(style)?,
                 };
-                let window = self.window.as_ref().unwrap().clone();
+                let completion = self.shape_completion();
 
                 let presentation_width = PresentationWidth::with_cluster(cluster);
 
@@ -859,7 +860,7 @@ impl crate::TermWindow {
                 };
                 match font.shape(
                     &cluster.text,
-                    move || window.notify(TermWindowNotif::InvalidateShapeCache),
+                    completion,
                     BlockKey::filter_out_synthetic,
                     options,
                 ) {
@@ -1,11 +1,21 @@
 use crate::termwindow::{RenderFrame, TermWindowNotif};
 use ::window::bitmaps::atlas::OutOfTextureSpace;
-use ::window::WindowOps;
+use ::window::bitmaps::{BitmapImage, Image};
+use ::window::{Point, Rect, Size, WindowOps};
 use anyhow::Context;
 use shelldone_font::ClearShapeCache;
 use smol::Timer;
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::Arc;
 use std::time::{Duration, Instant};
 
+/// How often `paint_to_image` checks whether the fonts that text
+/// is waiting on have been resolved
+const PENDING_SHAPE_POLL_INTERVAL: Duration = Duration::from_millis(20);
+
+/// How long `paint_to_image` waits for those fonts before giving up
+const PENDING_SHAPE_TIMEOUT: Duration = Duration::from_secs(10);
+
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum AllowImage {
     Yes,
@@ -13,7 +23,129 @@ pub enum AllowImage {
     No,
 }
 
+/// Tracks text that was shaped while its fallback fonts were still
+/// being resolved, for windows that have no event loop to notify
+/// when they are; see `TermWindow::shape_completion`
+#[derive(Default, Clone)]
+pub struct PendingShapes {
+    outstanding: Arc<AtomicUsize>,
+    completed: Arc<AtomicBool>,
+}
+
+impl PendingShapes {
+    fn start(&self) -> PendingShape {
+        self.outstanding.fetch_add(1, Ordering::SeqCst);
+        PendingShape {
+            shapes: self.clone(),
+        }
+    }
+}
+
+/// Held by a shaping completion until it is either called, or
+/// dropped because the text didn't need to wait on fonts after all
+struct PendingShape {
+    shapes: PendingShapes,
+}
+
+impl PendingShape {
+    fn complete(self) {
+        self.shapes.completed.store(true, Ordering::SeqCst);
+    }
+}
+
+impl Drop for PendingShape {
+    fn drop(&mut self) {
+        self.shapes.outstanding.fetch_sub(1, Ordering::SeqCst);
+    }
+}
+
 impl crate::TermWindow {
+    /// Returns the completion to pass when shaping text, which is
+    /// called once the fonts that the text falls back to are resolved,
+    /// so that the text can be shaped again with them
+    pub(crate) fn shape_completion(&self) -> Box<dyn FnOnce() + Send> {
+        match self.window.clone() {
+            Some(window) => Box::new(move || window.notify(TermWindowNotif::InvalidateShapeCache)),
+            None => {
+                let pending = self.pending_shapes.start();
+                Box::new(move || pending.complete())
+            }
+        }
+    }
+
+    /// Paints a window that was created by `TermWindow::new_headless`
+    /// into an image.  Painting is repeated until text no longer needs
+    /// to wait on fallback fonts, so that the image shows the text as
+    /// the gui eventually does.
+    pub async fn paint_to_image(&mut self) -> anyhow::Result<Image> {
+        let deadline = Instant::now() + PENDING_SHAPE_TIMEOUT;
+        loop {
+            let mut image = Image::new(self.dimensions.pixel_width, self.dimensions.pixel_height);
+            self.paint_impl(&mut RenderFrame::Cpu(&mut image));
+
+            let shapes = self.pending_shapes.clone();
+            if shapes.outstanding.load(Ordering::SeqCst) == 0
+                && !shapes.completed.load(Ordering::SeqCst)
+            {
+                return Ok(image);
+            }
+            loop {
+                if Instant::now() >= deadline {
+                    anyhow::bail!(
+                        "text was still waiting on fallback fonts after {:?}",
+                        PENDING_SHAPE_TIMEOUT
+                    );
+                }
+                if shapes.outstanding.load(Ordering::SeqCst) == 0 {
+                    break;
+                }
+                Timer::after(PENDING_SHAPE_POLL_INTERVAL).await;
+            }
+            shapes.completed.store(false, Ordering::SeqCst);
+            self.invalidate_shape_cache();
+        }
+    }
+
+    /// Paints a window that was created by `TermWindow::new_headless`
+    /// and returns just the area of its active pane
+    pub async fn paint_pane_to_image(&mut self) -> anyhow::Result<Image> {
+        let window_image = self.paint_to_image().await?;
+
+        let pos = self
+            .get_panes_to_render()
+            .into_iter()
+            .find(|pos| pos.is_active)
+            .context("window has no active pane")?;
+
+        let (padding_left, padding_top) = self.padding_left_top();
+        let top_bar_height = if self.show_tab_bar && !self.config.tab_bar_at_bottom {
+            self.tab_bar_pixel_height()?
+        } else {
+            0.
+        };
+        let border = self.get_os_border();
+        let cell_width = self.render_metrics.cell_size.width as usize;
+        let cell_height = self.render_metrics.cell_size.height as usize;
+
+        let rect = Rect::new(
+            Point::new(
+                (padding_left as usize + border.left.get() + pos.left * cell_width) as isize,
+                (top_bar_height as usize
+                    + padding_top as usize
+                    + border.top.get()
+                    + pos.top * cell_height) as isize,
+            ),
+            Size::new(
+                (pos.width * cell_width) as isize,
+                (pos.height * cell_height) as isize,
+            ),
+        );
+
+        let mut image = Image::new(pos.width * cell_width, pos.height * cell_height);
+        image.draw_image(Point::new(0, 0), Some(rect), &window_image);
+        Ok(image)
+    }
+
     pub fn paint_impl(&mut self, frame: &mut RenderFrame) {
         self.num_frames += 1;
         // If nothing on screen needs animating, then we can avoid
@@ -127,16 +259,19 @@ impl crate::TermWindow {
                     }
                     _ => {
                         self.scheduled_animation.borrow_mut().replace(next_due);
-                        let window = self.window.clone().unwrap();
-                        promise::spawn::spawn(async move {
-                            Timer::at(next_due).await;
-                            let win = window.clone();
-                            window.notify(TermWindowNotif::Apply(Box::new(move |tw| {
-                                tw.scheduled_animation.borrow_mut().take();
-                                win.invalidate();
-                            })));
-                        })
-                        .detach();
+                        // Windows that render into memory are painted
+                        // only on request, so there is nothing to schedule
+                        if let Some(window) = self.window.clone() {
+                            promise::spawn::spawn(async move {
+                                Timer::at(next_due).await;
+                                let win = window.clone();
+                                window.notify(TermWindowNotif::Apply(Box::new(move |tw| {
+                                    tw.scheduled_animation.borrow_mut().take();
+                                    win.invalidate();
+                                })));
+                            })
+                            .detach();
+                        }
                     }
                 }
             }
@@ -1,11 +1,14 @@
 use crate::quad::{HeapQuadAllocator, QuadTrait, TripleLayerQuadAllocator};
 use crate::selection::SelectionRange;
 use crate::termwindow::box_model::*;
+use crate::termwindow::commandblocks::{
+    contiguous_row_runs, format_command_duration, is_entered_command,
+};
 use crate::termwindow::render::{
     same_hyperlink, CursorProperties, LineQuadCacheKey, LineQuadCacheValue, LineToEleShapeCacheKey,
     RenderScreenLineParams,
 };
-use crate::termwindow::{ScrollHit, UIItem, UIItemType};
+use crate::termwindow::{DimensionContext, ScrollHit, UIItem, UIItemType};
 use ::window::bitmaps::TextureRect;
 use ::window::DeadKeyStatus;
 use anyhow::Context;
@@ -13,6 +16,7 @@ use config::VisualBellTarget;
 use mux::pane::{PaneId, WithPaneLines};
 use mux::renderable::{RenderableDimensions, StableCursorPosition};
 use mux::tab::PositionedPane;
+use mux::Mux;
 use ordered_float::NotNan;
 use shelldone_dynamic::Value;
 use shelldone_term::color::{ColorAttribute, ColorPalette};
@@ -221,6 +225,48 @@ impl crate::TermWindow {
             }
         }
 
+        {
+            // Outline panes that take part in input broadcast so that it is
+            // clear where typed input is going to be sent.  Panes that are
+            // marked for broadcast but not currently receiving it are drawn
+            // with a fainter outline.
+            let mux = Mux::get();
+            let alpha = if mux.is_pane_broadcasting(pane_id) {
+                Some(1.0)
+            } else if mux.is_pane_broadcast_marked(pane_id) {
+                Some(0.4)
+            } else {
+                None
+            };
+            if let Some(alpha) = alpha {
+                let color = config
+                    .input_broadcast_border_color
+                    .to_linear()
+                    .mul_alpha(alpha);
+                let thickness = (self.render_metrics.underline_height as f32 * 2.).max(1.);
+                let rect = background_rect;
+                for edge in [
+                    euclid::rect(rect.min_x(), rect.min_y(), rect.width(), thickness),
+                    euclid::rect(
+                        rect.min_x(),
+                        rect.max_y() - thickness,
+                        rect.width(),
+                        thickness,
+                    ),
+                    euclid::rect(rect.min_x(), rect.min_y(), thickness, rect.height()),
+                    euclid::rect(
+                        rect.max_x() - thickness,
+                        rect.min_y(),
+                        thickness,
+                        rect.height(),
+                    ),
+                ] {
+                    self.filled_rectangle(layers, 2, edge, color)
+                        .context("filled_rectangle")?;
+                }
+            }
+        }
+
         // TODO: we only have a single scrollbar in a single position.
         // We only update it for the active pane, but we should probably
         // do a per-pane scrollbar.  That will require more extensive
@@ -302,14 +348,19 @@ impl crate::TermWindow {
         let cursor_is_default_color =
             palette.cursor_fg == global_cursor_fg && palette.cursor_bg == global_cursor_bg;
 
-        {
-            let stable_range = match current_viewport {
-                Some(top) => top..top + dims.viewport_rows as StableRowIndex,
-                None => dims.physical_top..dims.physical_top + dims.viewport_rows as StableRowIndex,
-            };
+        let visible_rows = self.visible_stable_rows(&pos.pane, current_viewport, &dims);
+        let left_pixel_x = padding_left
+            + border.left.get() as f32
+            + (pos.left as f32 * self.render_metrics.cell_size.width as f32);
 
-            pos.pane
-                .apply_hyperlinks(stable_range.clone(), &self.config.hyperlink_rules);
+        {
+            // Collapsed command output leaves gaps in the rows that
+            // are displayed, so render each contiguous run separately
+            let runs = contiguous_row_runs(&visible_rows);
+            for (_, stable_range) in &runs {
+                pos.pane
+                    .apply_hyperlinks(stable_range.clone(), &self.config.hyperlink_rules);
+            }
 
             struct LineRender<'a, 'b> {
                 term_window: &'a mut crate::TermWindow,
@@ -318,6 +369,9 @@ impl crate::TermWindow {
                 dims: RenderableDimensions,
                 top_pixel_y: f32,
                 left_pixel_x: f32,
+                /// The index of the viewport row at which the
+                /// current run of lines is displayed
+                visual_top: usize,
                 pos: &'a PositionedPane,
                 pane_id: PaneId,
                 cursor: &'a StableCursorPosition,
@@ -337,10 +391,6 @@ impl crate::TermWindow {
                 error: Option<anyhow::Error>,
             }
 
-            let left_pixel_x = padding_left
-                + border.left.get() as f32
-                + (pos.left as f32 * self.render_metrics.cell_size.width as f32);
-
             let mut render = LineRender {
                 term_window: self,
                 selrange,
@@ -348,6 +398,7 @@ impl crate::TermWindow {
                 dims,
                 top_pixel_y,
                 left_pixel_x,
+                visual_top: 0,
                 pos,
                 pane_id,
                 cursor: &cursor,
@@ -375,6 +426,7 @@ impl crate::TermWindow {
                     line: &&mut Line,
                 ) -> anyhow::Result<()> {
                     let stable_row = stable_top + line_idx as StableRowIndex;
+                    let visual_idx = self.visual_top + line_idx;
                     let selrange = self
                         .selrange
                         .map_or(0..0, |sel| sel.cols_for_row(stable_row, self.rectangular));
@@ -435,10 +487,10 @@ impl crate::TermWindow {
                         cursor,
                         shape_hash,
                         top_pixel_y: NotNan::new(self.top_pixel_y).unwrap()
-                            + (line_idx + self.pos.top) as f32
+                            + (visual_idx + self.pos.top) as f32
                                 * self.term_window.render_metrics.cell_size.height as f32,
                         left_pixel_x: NotNan::new(self.left_pixel_x).unwrap(),
-                        phys_line_idx: line_idx,
+                        phys_line_idx: visual_idx,
                         reverse_video: self.dims.reverse_video,
                     };
 
@@ -565,12 +617,23 @@ impl crate::TermWindow {
                 }
             }
 
-            pos.pane.with_lines_mut(stable_range.clone(), &mut render);
-            if let Some(error) = render.error.take() {
-                return Err(error).context("error while calling with_lines_mut");
+            for (visual_top, stable_range) in runs {
+                render.visual_top = visual_top;
+                pos.pane.with_lines_mut(stable_range, &mut render);
+                if let Some(error) = render.error.take() {
+                    return Err(error).context("error while calling with_lines_mut");
+                }
             }
         }
 
+        if config.show_command_blocks {
+            self.paint_command_blocks(pos, &visible_rows, layers, top_pixel_y, left_pixel_x)
+                .context("paint_command_blocks")?;
+        }
+
+        self.paint_guests(pos, &visible_rows, layers, top_pixel_y, left_pixel_x)
+            .context("paint_guests")?;
+
         /*
         if let Some(zone) = zone {
             // TODO: render a thingy to jump to prior prompt
@@ -582,6 +645,233 @@ impl crate::TermWindow {
         Ok(())
     }
 
+    /// Shows the owner of a shared pane what its guests are looking at:
+    /// each guest has a bar along the right edge of the rows that are in
+    /// its viewport, and a tinted cell where its mouse pointer is.
+    /// `rows` are the stable rows that are displayed in the viewport.
+    fn paint_guests(
+        &mut self,
+        pos: &PositionedPane,
+        rows: &[StableRowIndex],
+        layers: &mut TripleLayerQuadAllocator,
+        top_pixel_y: f32,
+        left_pixel_x: f32,
+    ) -> anyhow::Result<()> {
+        let guests = pos.pane.get_guests();
+        if guests.is_empty() || rows.is_empty() {
+            return Ok(());
+        }
+
+        const GUEST_COLORS: [(u8, u8, u8); 6] = [
+            (0xe0, 0x6c, 0x75),
+            (0x61, 0xaf, 0xef),
+            (0x98, 0xc3, 0x79),
+            (0xe5, 0xc0, 0x7b),
+            (0xc6, 0x78, 0xdd),
+            (0x56, 0xb6, 0xc2),
+        ];
+
+        let dims = pos.pane.get_dimensions();
+        let cell_width = self.render_metrics.cell_size.width as f32;
+        let cell_height = self.render_metrics.cell_size.height as f32;
+        let row_top = |idx: usize| top_pixel_y + (idx + pos.top) as f32 * cell_height;
+        let right_pixel_x = left_pixel_x + pos.width as f32 * cell_width;
+        let bar_width = (cell_width / 4.).max(2.);
+
+        for (guest_idx, presence) in guests.iter().enumerate() {
+            let (r, g, b) = GUEST_COLORS[guest_idx % GUEST_COLORS.len()];
+            let viewport = &presence.viewport;
+
+            let top = viewport.top.unwrap_or(dims.physical_top);
+            let bottom = top + dims.viewport_rows as StableRowIndex;
+            let start_idx = rows.partition_point(|&row| row < top);
+            let end_idx = rows.partition_point(|&row| row < bottom);
+            if start_idx < end_idx {
+                // Stack the bars of the guests from the right edge inwards
+                self.filled_rectangle(
+                    layers,
+                    2,
+                    euclid::rect(
+                        right_pixel_x - (guest_idx + 1) as f32 * bar_width,
+                        row_top(start_idx),
+                        bar_width,
+                        (end_idx - start_idx) as f32 * cell_height,
+                    ),
+                    LinearRgba::with_srgba(r, g, b, 0xc0),
+                )
+                .context("filled_rectangle")?;
+            }
+
+            if let Some((column, row)) = viewport.pointer {
+                if let Ok(idx) = rows.binary_search(&row) {
+                    if column < pos.width {
+                        self.filled_rectangle(
+                            layers,
+                            2,
+                            euclid::rect(
+                                left_pixel_x + column as f32 * cell_width,
+                                row_top(idx),
+                                cell_width,
+                                cell_height,
+                            ),
+                            LinearRgba::with_srgba(r, g, b, 0x60),
+                        )
+                        .context("filled_rectangle")?;
+                    }
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Draws a marker in the gutter alongside each command block,
+    /// colored by its exit status, and labels the prompt with the
+    /// status and duration of the command.
+    /// `rows` are the stable rows that are displayed in the viewport.
+    fn paint_command_blocks(
+        &mut self,
+        pos: &PositionedPane,
+        rows: &[StableRowIndex],
+        layers: &mut TripleLayerQuadAllocator,
+        top_pixel_y: f32,
+        left_pixel_x: f32,
+    ) -> anyhow::Result<()> {
+        let (Some(&first_row), Some(&last_row)) = (rows.first(), rows.last()) else {
+            return Ok(());
+        };
+        let blocks = self.get_command_blocks(&pos.pane);
+        if blocks.is_empty() {
+            return Ok(());
+        }
+
+        let dims = pos.pane.get_dimensions();
+        let bottom = dims.physical_top + dims.viewport_rows as StableRowIndex;
+        let (active, collapsed) = {
+            let state = self.pane_state(pos.pane.pane_id());
+            (
+                state.active_command_block,
+                state.collapsed_command_blocks.clone(),
+            )
+        };
+
+        let cell_width = self.render_metrics.cell_size.width as f32;
+        let cell_height = self.render_metrics.cell_size.height as f32;
+        let row_top = |idx: usize| top_pixel_y + (idx + pos.top) as f32 * cell_height;
+        let right_pixel_x = left_pixel_x + pos.width as f32 * cell_width;
+
+        let mut labels = vec![];
+        for block in blocks.iter().filter(|block| is_entered_command(block)) {
+            let end = block.end_y.unwrap_or(bottom).max(block.prompt_y + 1);
+            if end <= first_row || block.prompt_y > last_row {
+                continue;
+            }
+            let start_idx = rows.partition_point(|&row| row < block.prompt_y);
+            let end_idx = rows.partition_point(|&row| row < end);
+            if start_idx >= end_idx {
+                continue;
+            }
+
+            let color = match block.exit_status {
+                Some(0) => self.config.command_block_success_color,
+                Some(_) => self.config.command_block_failure_color,
+                None => self.config.command_block_running_color,
+            }
+            .to_linear();
+
+            // The marker sits in the padding to the left of the pane,
+            // and is wider for the active block
+            let width = if active == Some(block.id) {
+                (cell_width / 2.).max(4.)
+            } else {
+                (cell_width / 4.).max(2.)
+            };
+            self.filled_rectangle(
+                layers,
+                2,
+                euclid::rect(
+                    (left_pixel_x - width).max(0.),
+                    row_top(start_idx),
+                    width,
+                    (end_idx - start_idx) as f32 * cell_height,
+                ),
+                color,
+            )
+            .context("filled_rectangle")?;
+
+            if rows[start_idx] == block.prompt_y {
+                let mut label = vec![];
+                match block.exit_status {
+                    Some(0) | None => {}
+                    Some(status) => label.push(format!("exit {status}")),
+                }
+                if let Some(duration) = block.duration() {
+                    label.push(format_command_duration(duration));
+                }
+                if !label.is_empty() {
+                    labels.push((label.join(" · "), color, row_top(start_idx)));
+                }
+            }
+
+            if collapsed.contains(&block.id) {
+                if let Some(output) = block.output_rows(bottom) {
+                    let hidden = output.end - output.start - 1;
+                    let idx = rows.partition_point(|&row| row < output.start);
+                    if hidden > 0 && rows.get(idx) == Some(&output.start) {
+                        labels.push((format!("⋯ {hidden} more line(s)"), color, row_top(idx)));
+                    }
+                }
+            }
+        }
+
+        if labels.is_empty() {
+            return Ok(());
+        }
+
+        let font = self.fonts.default_font()?;
+        let metrics = self.render_metrics;
+        let background = pos.pane.palette().background.to_linear();
+        for (text, color, y) in labels {
+            let element = Element::new(&font, ElementContent::Text(text)).colors(ElementColors {
+                border: BorderColor::default(),
+                bg: background.into(),
+                text: color.into(),
+            });
+            let mut computed = self.compute_element(
+                &LayoutContext {
+                    height: DimensionContext {
+                        dpi: self.dimensions.dpi as f32,
+                        pixel_max: self.dimensions.pixel_height as f32,
+                        pixel_cell: cell_height,
+                    },
+                    width: DimensionContext {
+                        dpi: self.dimensions.dpi as f32,
+                        pixel_max: self.dimensions.pixel_width as f32,
+                        pixel_cell: cell_width,
+                    },
+                    bounds: euclid::rect(
+                        left_pixel_x,
+                        y,
+                        right_pixel_x - left_pixel_x,
+                        cell_height,
+                    ),
+                    metrics: &metrics,
+                    gl_state: self.render_state.as_ref().unwrap(),
+                    zindex: 1,
+                },
+                &element,
+            )?;
+            // Right-align the label within the pane
+            computed.translate(euclid::vec2(
+                (right_pixel_x - computed.bounds.max_x()).max(0.),
+                0.,
+            ));
+            self.render_element(&computed, self.render_state.as_ref().unwrap(), None)?;
+        }
+
+        Ok(())
+    }
+
     pub fn build_pane(&mut self, pos: &PositionedPane) -> anyhow::Result<ComputedElement> {
         // First compute the bounds for the pane background
 
@@ -10,7 +10,7 @@ use anyhow::Context;
 use config::{HsbTransform, TextStyle};
 use shelldone_bidi::Direction;
 use shelldone_term::color::ColorAttribute;
-use shelldone_term::CellAttributes;
+use shelldone_term::{CellAttributes, TextSizeHorizontalAlign, TextSizeVerticalAlign};
 use std::ops::Range;
 use std::rc::Rc;
 use std::time::Instant;
@@ -444,6 +444,38 @@ impl crate::TermWindow {
                 }
             };
 
+            // Text that was sized using OSC 66 is shaped at the regular
+            // size and then scaled to fill its block of cells, which
+            // extends into the rows below this one
+            let (text_scale, text_x_adjust, text_y_adjust) = match cluster
+                .attrs
+                .text_size()
+                .filter(|text_size| text_size.is_origin())
+            {
+                Some(text_size) => {
+                    let size = text_size.size;
+                    let text_scale = size.font_scale();
+                    let block_width = cluster.width as f32 * cell_width;
+                    let block_height = size.scale as f32 * cell_height;
+                    let spare_width =
+                        (block_width - item.pixel_width * width_scale * text_scale).max(0.);
+                    let spare_height = (block_height - cell_height * text_scale).max(0.);
+                    let x_adjust = match size.horizontal_align {
+                        TextSizeHorizontalAlign::Left => 0.,
+                        TextSizeHorizontalAlign::Right => spare_width,
+                        TextSizeHorizontalAlign::Centered => spare_width / 2.,
+                    };
+                    let y_adjust = match size.vertical_align {
+                        TextSizeVerticalAlign::Top => 0.,
+                        TextSizeVerticalAlign::Bottom => spare_height,
+                        TextSizeVerticalAlign::Centered => spare_height / 2.,
+                    };
+                    (text_scale, x_adjust, y_adjust)
+                }
+                None => (1., 0., 0.),
+            };
+            let glyph_width_scale = width_scale * text_scale;
+
             // TODO: remember logical/visual mapping for selection
             #[allow(unused_variables)]
             let mut phys_cell_idx = cluster.first_cell_idx;
@@ -491,10 +523,12 @@ impl crate::TermWindow {
                     // First, resolve this glyph to a texture
                     let mut texture = glyph.texture.as_ref().cloned();
 
-                    let mut top = cell_height
-                        + (params.render_metrics.descender.get() as f32 + valign_adjust
-                            - (glyph.y_offset + glyph.bearing_y).get() as f32)
-                            * height_scale;
+                    let mut top = text_y_adjust
+                        + (cell_height
+                            + (params.render_metrics.descender.get() as f32 + valign_adjust
+                                - (glyph.y_offset + glyph.bearing_y).get() as f32)
+                                * height_scale)
+                            * text_scale;
 
                     if self.config.custom_block_glyphs {
                         if let Some(block) = &info.block_key {
@@ -508,7 +542,7 @@ impl crate::TermWindow {
                             // Custom glyphs don't have the same offsets as computed
                             // by the shaper, and are rendered relative to the cell
                             // top left, rather than the baseline.
-                            top = 0.;
+                            top = text_y_adjust;
                         }
                     }
 
@@ -516,8 +550,9 @@ impl crate::TermWindow {
                         // TODO: clipping, but we can do that based on pixels
 
                         let pos_x = cluster_x_pos
+                            + text_x_adjust
                             + if params.use_pixel_positioning {
-                                (glyph.x_offset + glyph.bearing_x).get() as f32
+                                (glyph.x_offset + glyph.bearing_x).get() as f32 * text_scale
                             } else {
                                 0.
                             };
@@ -584,9 +619,11 @@ impl crate::TermWindow {
                             (left, i, right)
                         }
 
-                        let adjust = (glyph.x_offset + glyph.bearing_x).get() as f32;
+                        let adjust = (glyph.x_offset + glyph.bearing_x).get() as f32 * text_scale;
                         let texture_range = pos_x + adjust
-                            ..pos_x + adjust + (texture.coords.size.width as f32 * width_scale);
+                            ..pos_x
+                                + adjust
+                                + (texture.coords.size.width as f32 * glyph_width_scale);
 
                         // First bucket the ranges according to cursor position
                         let (left, mid, right) = range3(&texture_range, &cursor_range_pixels);
@@ -633,9 +670,10 @@ impl crate::TermWindow {
                             }
 
                             let pixel_rect = euclid::rect(
-                                texture.coords.origin.x + (range.start - (pos_x + adjust)) as isize,
+                                texture.coords.origin.x
+                                    + ((range.start - (pos_x + adjust)) / text_scale) as isize,
                                 texture.coords.origin.y,
-                                ((range.end - range.start) / width_scale) as isize,
+                                ((range.end - range.start) / glyph_width_scale) as isize,
                                 texture.coords.size.height,
                             );
 
@@ -646,7 +684,9 @@ impl crate::TermWindow {
                                 gl_x + range.start,
                                 pos_y + top,
                                 gl_x + range.end,
-                                pos_y + top + texture.coords.size.height as f32 * height_scale,
+                                pos_y
+                                    + top
+                                    + texture.coords.size.height as f32 * height_scale * text_scale,
                             );
                             quad.set_fg_color(glyph_color);
                             quad.set_alt_color_and_mix_value(fg_color_alt, fg_color_mix);
@@ -679,7 +719,7 @@ impl crate::TermWindow {
                 phys_cell_idx += info.pos.num_cells as usize;
                 visual_cell_idx += info.pos.num_cells as usize;
                 cluster_x_pos += if params.use_pixel_positioning {
-                    glyph.x_advance.get() as f32 * width_scale
+                    glyph.x_advance.get() as f32 * glyph_width_scale
                 } else {
                     info.pos.num_cells as f32 * cell_width
                 };
@@ -3,6 +3,7 @@ use ::window::WindowOps;
 use mux::pane::{Pane, PaneId};
 use shelldone_term::StableRowIndex;
 use std::cell::RefMut;
+use std::ops::Range;
 use std::sync::Arc;
 use termwiz::surface::Line;
 
@@ -109,6 +110,21 @@ impl super::TermWindow {
         s
     }
 
+    /// Returns the text of the logical lines that start within `rows`,
+    /// omitting any trailing blank lines
+    pub fn text_for_rows(&self, pane: &Arc<dyn Pane>, rows: Range<StableRowIndex>) -> String {
+        let mut lines: Vec<String> = pane
+            .get_logical_lines(rows.clone())
+            .into_iter()
+            .filter(|line| rows.contains(&line.first_row))
+            .map(|line| line.logical.as_str().trim_end().to_string())
+            .collect();
+        while lines.last().map(|line| line.is_empty()).unwrap_or(false) {
+            lines.pop();
+        }
+        lines.join("\n")
+    }
+
     pub fn clear_selection(&mut self, pane: &Arc<dyn Pane>) {
         let mut selection = self.selection(pane.pane_id());
         selection.clear();
@@ -1,4 +1,5 @@
 use crate::sessionhandler::{PduSender, SessionHandler};
+use crate::share::Guest;
 use anyhow::Context;
 use async_ossl::AsyncSslStream;
 use codec::{DecodedPdu, Pdu};
@@ -21,6 +22,8 @@ enum Item {
     Notif(MuxNotification),
     WritePdu(DecodedPdu),
     Readable,
+    /// Checks that the share of a guest is still valid
+    Revalidate,
 }
 
 pub async fn process<T>(stream: T) -> anyhow::Result<()>
@@ -33,10 +36,24 @@ where
     T: async_io::IoSafe,
 {
     let stream = smol::Async::new(stream)?;
-    process_async(stream).await
+    process_async(stream, None).await
 }
 
-pub async fn process_async<T>(mut stream: Async<T>) -> anyhow::Result<()>
+/// Serves a guest that connected with the credential of a share
+pub async fn process_guest<T>(stream: T, guest: Guest) -> anyhow::Result<()>
+where
+    T: 'static,
+    T: std::io::Read,
+    T: std::io::Write,
+    T: AsRawDesc,
+    T: std::fmt::Debug,
+    T: async_io::IoSafe,
+{
+    let stream = smol::Async::new(stream)?;
+    process_async(stream, Some(guest)).await
+}
+
+pub async fn process_async<T>(mut stream: Async<T>, guest: Option<Guest>) -> anyhow::Result<()>
 where
     T: 'static,
     T: std::io::Read,
@@ -56,7 +73,20 @@ where
                 .map_err(|e| anyhow::anyhow!("{:?}", e))
         }
     });
-    let mut handler = SessionHandler::new(pdu_sender);
+    let mut handler = match guest {
+        Some(guest) => {
+            // Disconnect the guest as soon as the share expires
+            let tx = item_tx.clone();
+            let expires = guest.expires;
+            promise::spawn::spawn(async move {
+                smol::Timer::at(expires).await;
+                tx.try_send(Item::Revalidate).ok();
+            })
+            .detach();
+            SessionHandler::new_guest(pdu_sender, guest)
+        }
+        None => SessionHandler::new(pdu_sender),
+    };
 
     {
         let mux = Mux::get();
@@ -68,7 +98,24 @@ where
         let rx_msg = item_rx.recv();
         let wait_for_read = stream.readable().map(|_| Ok(Item::Readable));
 
-        match smol::future::or(rx_msg, wait_for_read).await {
+        let item = smol::future::or(rx_msg, wait_for_read).await;
+
+        if let Some(guest) = handler.guest() {
+            if !guest.is_valid() {
+                log::info!(
+                    "closing the session of {}: share {} was revoked or has expired",
+                    guest.peer,
+                    guest.share_id
+                );
+                return Ok(());
+            }
+        }
+
+        // Guests only hear about the panes and tab that they may see
+        let is_guest = handler.guest().is_some();
+
+        match item {
+            Ok(Item::Revalidate) => {}
             Ok(Item::Readable) => {
                 let decoded = match Pdu::decode_async(&mut stream, None).await {
                     Ok(data) => data,
@@ -119,6 +166,8 @@ where
                 stream.flush().await.context("flushing PDU to client")?;
             }
             Ok(Item::Notif(MuxNotification::SigmaGuard(_))) => {}
+            Ok(Item::Notif(MuxNotification::Alert { pane_id, .. }))
+                if !handler.may_observe_pane(pane_id) => {}
             Ok(Item::Notif(MuxNotification::Alert { pane_id, alert })) => {
                 {
                     let per_pane = handler.per_pane(pane_id);
@@ -128,6 +177,11 @@ where
                 handler.schedule_pane_push(pane_id);
             }
             Ok(Item::Notif(MuxNotification::SaveToDownloads { .. })) => {}
+            // The clipboard of the owner is not shared with guests
+            Ok(Item::Notif(
+                MuxNotification::AssignClipboard { .. }
+                | MuxNotification::AssignClipboardData { .. },
+            )) if is_guest => {}
             Ok(Item::Notif(MuxNotification::AssignClipboard {
                 pane_id,
                 selection,
@@ -142,6 +196,37 @@ where
                 .await?;
                 stream.flush().await.context("flushing PDU to client")?;
             }
+            Ok(Item::Notif(MuxNotification::AssignClipboardData {
+                pane_id,
+                selection,
+                data,
+            })) => {
+                // The protocol can only carry text to the client
+                if let Some(text) = data.iter().find(|d| shelldone_term::is_text_mime(&d.mime)) {
+                    Pdu::SetClipboard(codec::SetClipboard {
+                        pane_id,
+                        clipboard: Some(String::from_utf8_lossy(&text.data).into_owned()),
+                        selection,
+                    })
+                    .encode_async(&mut stream, 0)
+                    .await?;
+                    stream.flush().await.context("flushing PDU to client")?;
+                }
+            }
+            // The client can't yet be asked to permit a clipboard read
+            // or a file transfer; the process hosting the mux answers
+            // them instead, either by prompting in its own gui or, for
+            // the headless server, by refusing them
+            Ok(Item::Notif(
+                MuxNotification::RequestClipboard { .. }
+                | MuxNotification::RequestFileTransfer { .. },
+            )) => {}
+            Ok(Item::Notif(
+                MuxNotification::TabAddedToWindow { .. }
+                | MuxNotification::WindowWorkspaceChanged(_)
+                | MuxNotification::WindowTitleChanged { .. }
+                | MuxNotification::WorkspaceRenamed { .. },
+            )) if is_guest => {}
             Ok(Item::Notif(MuxNotification::TabAddedToWindow { tab_id, window_id })) => {
                 Pdu::TabAddedToWindow(codec::TabAddedToWindow { tab_id, window_id })
                     .encode_async(&mut stream, 0)
@@ -167,12 +252,18 @@ where
                     stream.flush().await.context("flushing PDU to client")?;
                 }
             }
+            Ok(Item::Notif(MuxNotification::PaneFocused(pane_id)))
+                if !handler.may_observe_pane(pane_id) => {}
             Ok(Item::Notif(MuxNotification::PaneFocused(pane_id))) => {
                 Pdu::PaneFocused(codec::PaneFocused { pane_id })
                     .encode_async(&mut stream, 0)
                     .await?;
                 stream.flush().await.context("flushing PDU to client")?;
             }
+            Ok(Item::Notif(
+                MuxNotification::TabResized(tab_id)
+                | MuxNotification::TabTitleChanged { tab_id, .. },
+            )) if !handler.may_observe_tab(tab_id) => {}
             Ok(Item::Notif(MuxNotification::TabResized(tab_id))) => {
                 Pdu::TabResized(codec::TabResized { tab_id })
                     .encode_async(&mut stream, 0)
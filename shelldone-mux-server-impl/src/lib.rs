@@ -1,5 +1,6 @@
 use config::{ConfigHandle, SshMultiplexing};
 use mux::domain::{Domain, LocalDomain};
+use mux::playback::PlaybackDomain;
 use mux::ssh::RemoteSshDomain;
 use mux::Mux;
 use shelldone_client::domain::{ClientDomain, ClientDomainConfig};
@@ -9,6 +10,7 @@ pub mod dispatch;
 pub mod local;
 pub mod pki;
 pub mod sessionhandler;
+pub mod share;
 
 fn client_domains(config: &config::ConfigHandle) -> Vec<ClientDomainConfig> {
     let mut domains = vec![];
@@ -79,6 +81,11 @@ fn update_mux_domains_impl(config: &ConfigHandle, is_standalone_mux: bool) -> an
         mux.add_domain(&domain);
     }
 
+    if mux.get_domain_by_name(PlaybackDomain::NAME).is_none() {
+        let domain: Arc<dyn Domain> = Arc::new(PlaybackDomain::new());
+        mux.add_domain(&domain);
+    }
+
     for serial in &config.serial_ports {
         if mux.get_domain_by_name(&serial.name).is_some() {
             continue;
@@ -1,7 +1,11 @@
 use anyhow::{anyhow, Context as _};
+use chrono::{DateTime, Datelike, Utc};
 #[cfg(unix)]
 use libc::{AF_UNSPEC, AI_CANONNAME, SOCK_DGRAM};
-use rcgen::{BasicConstraints, Certificate, CertificateParams, DistinguishedName, DnType, IsCa};
+use rcgen::{
+    date_time_ymd, BasicConstraints, Certificate, CertificateParams, DistinguishedName, DnType,
+    IsCa,
+};
 use std::path::PathBuf;
 #[cfg(windows)]
 use winapi::shared::ws2def::{AF_UNSPEC, AI_CANONNAME, SOCK_DGRAM};
@@ -98,6 +102,40 @@ impl Pki {
         Ok(signed_cert)
     }
 
+    /// Generates the client certificate of the guest of a share.
+    /// The CN of the certificate identifies the share, which is what
+    /// grants access to the guest.  The certificate is valid until the
+    /// end of the day after `expires`, while the share itself enforces
+    /// the precise expiry.
+    pub fn generate_guest_cert(
+        &self,
+        share_id: &str,
+        expires: DateTime<Utc>,
+    ) -> anyhow::Result<String> {
+        let cn = format!("{}{}", crate::share::GUEST_CN_PREFIX, share_id);
+
+        let mut params = CertificateParams::new(vec![cn.clone()]);
+        let mut dn = DistinguishedName::new();
+        dn.push(DnType::CommonName, cn);
+        params.distinguished_name = dn;
+        let not_after = expires
+            .date_naive()
+            .succ_opt()
+            .unwrap_or(expires.date_naive());
+        params.not_after = date_time_ymd(
+            not_after.year(),
+            not_after.month() as u8,
+            not_after.day() as u8,
+        );
+
+        let guest_cert = Certificate::from_params(params)?;
+        let mut signed_cert = guest_cert.serialize_pem_with_signer(&self.ca_cert)?;
+        let key_bits = guest_cert.get_key_pair().serialize_pem();
+        signed_cert.push_str(&key_bits);
+
+        Ok(signed_cert)
+    }
+
     pub fn ca_pem_string(&self) -> anyhow::Result<String> {
         self.ca_cert
             .serialize_pem()
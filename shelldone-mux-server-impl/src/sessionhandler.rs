@@ -1,16 +1,20 @@
+use crate::share::{self, Guest, GuestVerdict};
 use crate::PKI;
 use anyhow::{anyhow, Context};
 use codec::*;
 use config::TermConfig;
-use mux::client::ClientId;
+use mux::client::{ClientId, GuestPresence};
 use mux::domain::SplitSource;
 use mux::pane::{CachePolicy, Pane, PaneId};
 use mux::renderable::{RenderableDimensions, StableCursorPosition};
+use mux::ssh::RemoteSshDomain;
 use mux::tab::TabId;
 use mux::{Mux, MuxNotification, SpawnRequest};
 use promise::spawn::spawn_into_main_thread;
+use shelldone_dynamic::Value;
+use shelldone_ssh::{ForwardKind, ForwardSpec};
 use shelldone_term::terminal::Alert;
-use shelldone_term::StableRowIndex;
+use shelldone_term::{CommandBlock, StableRowIndex};
 use std::collections::HashMap;
 use std::sync::{Arc, Mutex};
 use std::time::Instant;
@@ -45,6 +49,13 @@ pub(crate) struct PerPane {
     sent_initial_palette: bool,
     seqno: SequenceNo,
     config_generation: usize,
+    guests: Vec<GuestPresence>,
+    alt_screen_active: bool,
+    password_input: bool,
+    command_blocks: Vec<CommandBlock>,
+    /// Set for the sessions of guests, which aren't told about
+    /// the other guests
+    hide_guests: bool,
     pub(crate) notifications: Vec<Alert>,
 }
 
@@ -80,6 +91,31 @@ impl PerPane {
             changed = true;
         }
 
+        let guests = if self.hide_guests {
+            vec![]
+        } else {
+            pane.get_guests()
+        };
+        if guests != self.guests {
+            changed = true;
+        }
+
+        let alt_screen_active = pane.is_alt_screen_active();
+        if alt_screen_active != self.alt_screen_active {
+            changed = true;
+        }
+
+        let password_input = is_password_input(pane);
+        if password_input != self.password_input {
+            changed = true;
+        }
+
+        let command_blocks = pane.get_command_blocks();
+        let command_blocks_changed = command_blocks != self.command_blocks;
+        if command_blocks_changed {
+            changed = true;
+        }
+
         let old_seqno = self.seqno;
         self.seqno = pane.get_current_seqno();
         let mut all_dirty_lines = pane.get_changed_since(
@@ -126,6 +162,15 @@ impl PerPane {
         self.working_dir = working_dir.clone();
         self.dimensions = dims;
         self.mouse_grabbed = mouse_grabbed;
+        self.guests = guests.clone();
+        self.alt_screen_active = alt_screen_active;
+        self.password_input = password_input;
+        let command_blocks = if command_blocks_changed {
+            self.command_blocks = command_blocks.clone();
+            Some(command_blocks)
+        } else {
+            None
+        };
 
         let bonus_lines = Box::new(bonus_lines.into());
         Some(GetPaneRenderChangesResponse {
@@ -139,10 +184,26 @@ impl PerPane {
             working_dir: working_dir.map(Into::into),
             input_serial: force_with_input_serial,
             seqno: self.seqno,
+            guests,
+            alt_screen_active,
+            password_input,
+            command_blocks,
         })
     }
 }
 
+/// Consults the `password_input` metadata that local panes compute
+/// from the termios of their pty
+fn is_password_input(pane: &Arc<dyn Pane>) -> bool {
+    match pane.get_metadata() {
+        Value::Object(obj) => matches!(
+            obj.get(&Value::String("password_input".to_string())),
+            Some(Value::Bool(true))
+        ),
+        _ => false,
+    }
+}
+
 fn maybe_push_pane_changes(
     pane: &Arc<dyn Pane>,
     sender: PduSender,
@@ -201,6 +262,8 @@ pub struct SessionHandler {
     per_pane: HashMap<TabId, Arc<Mutex<PerPane>>>,
     client_id: Option<Arc<ClientId>>,
     proxy_client_id: Option<ClientId>,
+    /// Set when the peer connected with the credential of a share
+    guest: Option<Guest>,
 }
 
 impl Drop for SessionHandler {
@@ -219,18 +282,58 @@ impl SessionHandler {
             per_pane: HashMap::new(),
             client_id: None,
             proxy_client_id: None,
+            guest: None,
+        }
+    }
+
+    /// Creates the session of a guest, which is limited to what
+    /// its share permits
+    pub fn new_guest(to_write_tx: PduSender, guest: Guest) -> Self {
+        Self {
+            to_write_tx,
+            per_pane: HashMap::new(),
+            client_id: None,
+            proxy_client_id: None,
+            guest: Some(guest),
         }
     }
 
+    pub fn guest(&self) -> Option<&Guest> {
+        self.guest.as_ref()
+    }
+
+    /// Returns false if the session is that of a guest
+    /// that may not see `pane_id`
+    pub fn may_observe_pane(&self, pane_id: PaneId) -> bool {
+        self.guest
+            .as_ref()
+            .map(|guest| guest.may_observe_pane(pane_id))
+            .unwrap_or(true)
+    }
+
+    /// Returns false if the session is that of a guest
+    /// that may not see `tab_id`
+    pub fn may_observe_tab(&self, tab_id: TabId) -> bool {
+        self.guest
+            .as_ref()
+            .map(|guest| guest.may_observe_tab(tab_id))
+            .unwrap_or(true)
+    }
+
     pub(crate) fn per_pane(&mut self, pane_id: PaneId) -> Arc<Mutex<PerPane>> {
-        Arc::clone(
-            self.per_pane
-                .entry(pane_id)
-                .or_insert_with(|| Arc::new(Mutex::new(PerPane::default()))),
-        )
+        let hide_guests = self.guest.is_some();
+        Arc::clone(self.per_pane.entry(pane_id).or_insert_with(|| {
+            Arc::new(Mutex::new(PerPane {
+                hide_guests,
+                ..PerPane::default()
+            }))
+        }))
     }
 
     pub fn schedule_pane_push(&mut self, pane_id: PaneId) {
+        if !self.may_observe_pane(pane_id) {
+            return;
+        }
         let sender = self.to_write_tx.clone();
         let per_pane = self.per_pane(pane_id);
         spawn_into_main_thread(async move {
@@ -274,6 +377,20 @@ impl SessionHandler {
             send_response(f());
         }
 
+        if let Some(guest) = self.guest.as_mut() {
+            match guest.authorize(&decoded.pdu) {
+                GuestVerdict::Allow => {}
+                GuestVerdict::Ignore => {
+                    send_response(Ok(Pdu::UnitResponse(UnitResponse {})));
+                    return;
+                }
+                GuestVerdict::Deny(reason) => {
+                    send_response(Err(anyhow!("{}", reason)));
+                    return;
+                }
+            }
+        }
+
         match decoded.pdu {
             Pdu::Ping(Ping {}) => send_response(Ok(Pdu::Pong(Pong {}))),
             Pdu::SetWindowWorkspace(SetWindowWorkspace {
@@ -299,6 +416,11 @@ impl SessionHandler {
                 mut client_id,
                 is_proxy,
             }) => {
+                if self.guest.is_some() {
+                    // The agent of the owner must not be reachable
+                    // through the session of a guest
+                    client_id.ssh_auth_sock = None;
+                }
                 if is_proxy {
                     if self.proxy_client_id.is_none() {
                         // Copy proxy identity, but don't assign it to the mux;
@@ -320,9 +442,13 @@ impl SessionHandler {
 
                     let client_id = Arc::new(client_id);
                     self.client_id.replace(client_id.clone());
+                    let guest_info = self.guest.as_ref().and_then(|guest| guest.guest_info());
                     spawn_into_main_thread(async move {
                         let mux = Mux::get();
-                        mux.register_client(client_id);
+                        mux.register_client(client_id.clone());
+                        if let Some(guest_info) = guest_info {
+                            mux.set_client_guest(&client_id, guest_info);
+                        }
                     })
                     .detach();
                 }
@@ -386,6 +512,7 @@ impl SessionHandler {
                 .detach();
             }
             Pdu::ListPanes(ListPanes {}) => {
+                let guest_scope = self.guest.as_ref().map(|guest| guest.scope());
                 spawn_into_main_thread(async move {
                     catch(
                         move || {
@@ -402,6 +529,11 @@ impl SessionHandler {
                                 }
                             }
                             log::trace!("ListPanes {tabs:#?} {tab_titles:?}");
+                            if let Some(scope) = guest_scope {
+                                return Ok(Pdu::ListPanesResponse(share::filter_tabs(
+                                    scope, tabs, tab_titles,
+                                )));
+                            }
                             Ok(Pdu::ListPanesResponse(ListPanesResponse {
                                 tabs,
                                 tab_titles,
@@ -489,7 +621,11 @@ impl SessionHandler {
                 })
                 .detach();
             }
-            Pdu::SendPaste(SendPaste { pane_id, data }) => {
+            Pdu::SendPaste(SendPaste {
+                pane_id,
+                data,
+                input_serial,
+            }) => {
                 let sender = self.to_write_tx.clone();
                 let per_pane = self.per_pane(pane_id);
                 spawn_into_main_thread(async move {
@@ -500,7 +636,17 @@ impl SessionHandler {
                                 .get_pane(pane_id)
                                 .ok_or_else(|| anyhow!("no such pane {}", pane_id))?;
                             pane.send_paste(&data)?;
-                            maybe_push_pane_changes(&pane, sender, per_pane)?;
+
+                            // Always respond, so that the client learns that
+                            // the paste was processed and resumes predicting
+                            let mut per_pane = per_pane.lock().unwrap();
+                            if let Some(resp) = per_pane.compute_changes(&pane, Some(input_serial))
+                            {
+                                sender.send(DecodedPdu {
+                                    pdu: Pdu::GetPaneRenderChangesResponse(Box::new(resp)),
+                                    serial: 0,
+                                })?;
+                            }
                             Ok(Pdu::UnitResponse(UnitResponse {}))
                         },
                         send_response,
@@ -584,6 +730,112 @@ impl SessionHandler {
                 .detach();
             }
 
+            Pdu::SetPaneMonitors(SetPaneMonitors { pane_id, monitors }) => {
+                spawn_into_main_thread(async move {
+                    catch(
+                        move || {
+                            let mux = Mux::get();
+                            let pane = mux
+                                .get_pane(pane_id)
+                                .ok_or_else(|| anyhow!("no such pane {}", pane_id))?;
+                            pane.set_monitors(monitors);
+                            Ok(Pdu::UnitResponse(UnitResponse {}))
+                        },
+                        send_response,
+                    )
+                })
+                .detach();
+            }
+
+            Pdu::SetPaneRecording(SetPaneRecording { pane_id, options }) => {
+                let recording_sender = self.to_write_tx.clone();
+                spawn_into_main_thread(async move {
+                    catch(
+                        move || {
+                            let mux = Mux::get();
+                            let pane = mux
+                                .get_pane(pane_id)
+                                .ok_or_else(|| anyhow!("no such pane {}", pane_id))?;
+                            match options {
+                                Some(options) => pane.start_recording(
+                                    options,
+                                    Box::new(move |event| {
+                                        recording_sender.send(DecodedPdu {
+                                            pdu: Pdu::PaneRecordingOutput(PaneRecordingOutput {
+                                                pane_id,
+                                                events: vec![event],
+                                            }),
+                                            serial: 0,
+                                        })
+                                    }),
+                                ),
+                                None => {
+                                    pane.stop_recording();
+                                }
+                            }
+                            Ok(Pdu::UnitResponse(UnitResponse {}))
+                        },
+                        send_response,
+                    )
+                })
+                .detach();
+            }
+
+            Pdu::CreateShare(request) => {
+                spawn_into_main_thread(async move {
+                    catch(
+                        move || Ok(Pdu::CreateShareResponse(share::create_share(request)?)),
+                        send_response,
+                    )
+                })
+                .detach();
+            }
+
+            Pdu::ListShares(ListShares {}) => {
+                spawn_into_main_thread(async move {
+                    catch(
+                        move || {
+                            Ok(Pdu::ListSharesResponse(ListSharesResponse {
+                                shares: share::list_shares(),
+                            }))
+                        },
+                        send_response,
+                    )
+                })
+                .detach();
+            }
+
+            Pdu::UpdateShare(request) => {
+                spawn_into_main_thread(async move {
+                    catch(
+                        move || {
+                            Ok(Pdu::ListSharesResponse(ListSharesResponse {
+                                shares: share::update_share(request)?,
+                            }))
+                        },
+                        send_response,
+                    )
+                })
+                .detach();
+            }
+
+            Pdu::SetClientViewport(SetClientViewport { viewport }) => {
+                let client_id = self.client_id.clone();
+                spawn_into_main_thread(async move {
+                    catch(
+                        move || {
+                            let client_id = client_id
+                                .ok_or_else(|| anyhow!("SetClientId must be sent first"))?;
+                            let mux = Mux::get();
+                            mux.record_viewport_for_client(&client_id, viewport);
+                            Ok(Pdu::UnitResponse(UnitResponse {}))
+                        },
+                        send_response,
+                    )
+                })
+                .detach();
+            }
+
             Pdu::GetPaneDirection(GetPaneDirection { pane_id, direction }) => {
                 spawn_into_main_thread(async move {
                     catch(
@@ -733,6 +985,32 @@ impl SessionHandler {
                 .detach();
             }
 
+            Pdu::SwapPanes(request) => {
+                let client_id = self.client_id.clone();
+                spawn_into_main_thread(async move {
+                    schedule_swap_panes(request, send_response, client_id);
+                })
+                .detach();
+            }
+
+            Pdu::MoveTabToWindow(request) => {
+                let client_id = self.client_id.clone();
+                spawn_into_main_thread(async move {
+                    schedule_move_tab(request, send_response, client_id);
+                })
+                .detach();
+            }
+
+            Pdu::SshPortForwards(request) => {
+                spawn_into_main_thread(async move {
+                    promise::spawn::spawn(async move {
+                        send_response(ssh_port_forwards(request).await)
+                    })
+                    .detach();
+                })
+                .detach();
+            }
+
             Pdu::GetPaneRenderableDimensions(GetPaneRenderableDimensions { pane_id }) => {
                 spawn_into_main_thread(async move {
                     catch(
@@ -993,6 +1271,7 @@ impl SessionHandler {
             | Pdu::ListPanesResponse { .. }
             | Pdu::SetClipboard { .. }
             | Pdu::NotifyAlert { .. }
+            | Pdu::PaneRecordingOutput { .. }
             | Pdu::SpawnResponse { .. }
             | Pdu::GetPaneRenderChangesResponse(_)
             | Pdu::UnitResponse { .. }
@@ -1009,8 +1288,12 @@ impl SessionHandler {
             | Pdu::TabResized { .. }
             | Pdu::GetImageCellResponse { .. }
             | Pdu::MovePaneToNewTabResponse { .. }
+            | Pdu::MoveTabToWindowResponse { .. }
+            | Pdu::SshPortForwardsResponse { .. }
             | Pdu::TabAddedToWindow { .. }
             | Pdu::GetPaneRenderableDimensionsResponse { .. }
+            | Pdu::CreateShareResponse { .. }
+            | Pdu::ListSharesResponse { .. }
             | Pdu::ErrorResponse { .. } => {
                 send_response(Err(anyhow!("expected a request, got {:?}", decoded.pdu)))
             }
@@ -1126,3 +1409,93 @@ async fn move_pane(
         window_id,
     }))
 }
+
+fn schedule_swap_panes<SND>(
+    request: SwapPanes,
+    send_response: SND,
+    client_id: Option<Arc<ClientId>>,
+) where
+    SND: Fn(anyhow::Result<Pdu>) + 'static,
+{
+    promise::spawn::spawn(async move { send_response(swap_panes(request, client_id).await) })
+        .detach();
+}
+
+async fn swap_panes(request: SwapPanes, client_id: Option<Arc<ClientId>>) -> anyhow::Result<Pdu> {
+    let mux = Mux::get();
+    let _identity = mux.with_identity(client_id);
+
+    mux.swap_panes(request.pane_id, request.other_pane_id)
+        .await?;
+
+    Ok::<Pdu, anyhow::Error>(Pdu::UnitResponse(UnitResponse {}))
+}
+
+fn schedule_move_tab<SND>(
+    request: MoveTabToWindow,
+    send_response: SND,
+    client_id: Option<Arc<ClientId>>,
+) where
+    SND: Fn(anyhow::Result<Pdu>) + 'static,
+{
+    promise::spawn::spawn(async move { send_response(move_tab(request, client_id).await) })
+        .detach();
+}
+
+async fn move_tab(
+    request: MoveTabToWindow,
+    client_id: Option<Arc<ClientId>>,
+) -> anyhow::Result<Pdu> {
+    let mux = Mux::get();
+    let _identity = mux.with_identity(client_id);
+
+    let window_id = mux
+        .move_tab_to_window(
+            request.tab_id,
+            request.window_id,
+            request.workspace_for_new_window,
+        )
+        .await?;
+
+    Ok::<Pdu, anyhow::Error>(Pdu::MoveTabToWindowResponse(MoveTabToWindowResponse {
+        window_id,
+    }))
+}
+
+async fn ssh_port_forwards(request: SshPortForwards) -> anyhow::Result<Pdu> {
+    let mux = Mux::get();
+    let domain = mux
+        .get_domain_by_name(&request.domain)
+        .ok_or_else(|| anyhow!("no such domain {}", request.domain))?;
+    let ssh = domain
+        .downcast_ref::<RemoteSshDomain>()
+        .ok_or_else(|| anyhow!("domain {} is not an ssh domain", request.domain))?;
+
+    match request.action {
+        SshPortForwardAction::List => {}
+        SshPortForwardAction::Add { kind, spec } => {
+            let kind: ForwardKind = kind.parse()?;
+            ssh.add_port_forward(ForwardSpec::from_ssh_arg(kind, &spec)?)
+                .await?;
+        }
+        SshPortForwardAction::Remove { id } => {
+            ssh.remove_port_forward(id).await?;
+        }
+    }
+
+    let forwards = ssh
+        .port_forwards()
+        .await?
+        .into_iter()
+        .map(|info| SshPortForwardEntry {
+            id: info.id,
+            description: info.spec.to_string(),
+            bound_port: info.bound_port,
+            connections: info.connections,
+        })
+        .collect();
+
+    Ok::<Pdu, anyhow::Error>(Pdu::SshPortForwardsResponse(SshPortForwardsResponse {
+        forwards,
+    }))
+}
@@ -0,0 +1,837 @@
+//! Shares let the owner of a mux server issue a credential with which a
+//! guest can connect to a `tls_servers` listener and watch, and optionally
+//! type into, a single pane or tab.
+//!
+//! The credential is a client certificate whose CN names the share; the
+//! share itself lives only in the memory of the server, so revoking it or
+//! restarting the server invalidates the credential.  Everything that a
+//! guest does is recorded in the audit log.
+use crate::PKI;
+use anyhow::{anyhow, Context};
+use chrono::{DateTime, Utc};
+use codec::*;
+use mux::client::GuestInfo;
+use mux::pane::PaneId;
+use mux::tab::{PaneNode, TabId};
+use mux::{Mux, MuxNotification};
+use serde::Serialize;
+use std::collections::{HashMap, HashSet};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+/// The prefix of the CN of the certificate of a guest;
+/// it is followed by the id of the share
+pub const GUEST_CN_PREFIX: &str = "guest:";
+
+/// Set when a `tls_servers` listener is running in this process
+static TLS_LISTENING: AtomicBool = AtomicBool::new(false);
+
+lazy_static::lazy_static! {
+    static ref SHARES: Mutex<HashMap<String, Share>> = Mutex::new(HashMap::new());
+}
+
+struct Share {
+    scope: ShareScope,
+    access: ShareAccess,
+    label: Option<String>,
+    created_at: DateTime<Utc>,
+    expires_at: DateTime<Utc>,
+    guests: usize,
+}
+
+impl Share {
+    fn info(&self, share_id: &str) -> ShareInfo {
+        ShareInfo {
+            share_id: share_id.to_string(),
+            scope: self.scope,
+            access: self.access,
+            label: self.label.clone(),
+            created_at: self.created_at.timestamp() as u64,
+            expires_at: self.expires_at.timestamp() as u64,
+            guests: self.guests,
+        }
+    }
+
+    fn is_expired(&self) -> bool {
+        Utc::now() >= self.expires_at
+    }
+
+    fn guest_info(&self, share_id: &str) -> GuestInfo {
+        GuestInfo {
+            share_id: share_id.to_string(),
+            label: self.label.clone(),
+            interactive: self.access == ShareAccess::Interactive,
+        }
+    }
+}
+
+pub fn set_tls_listening() {
+    TLS_LISTENING.store(true, Ordering::SeqCst);
+}
+
+pub fn audit_log_path() -> PathBuf {
+    if cfg!(test) {
+        // Keep the tests out of the audit log of the user
+        return std::env::temp_dir().join(format!(
+            "shelldone-share-audit-test-{}.log",
+            std::process::id()
+        ));
+    }
+    config::DATA_DIR.join("share-audit.log")
+}
+
+#[derive(Serialize)]
+struct AuditRecord<'a> {
+    time: String,
+    event: &'a str,
+    share_id: &'a str,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    peer: Option<&'a str>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    detail: Option<&'a str>,
+}
+
+/// Appends an event to the audit log, which holds one JSON object
+/// per line, and mirrors it to the log
+pub fn audit(event: &str, share_id: &str, peer: Option<&str>, detail: Option<&str>) {
+    log::info!(
+        "share {share_id}: {event} peer={} {}",
+        peer.unwrap_or("-"),
+        detail.unwrap_or("")
+    );
+
+    let record = AuditRecord {
+        time: Utc::now().to_rfc3339(),
+        event,
+        share_id,
+        peer,
+        detail,
+    };
+
+    if let Err(err) = append_audit_record(&audit_log_path(), &record) {
+        log::error!("failed to write to the share audit log: {err:#}");
+    }
+}
+
+/// Appends a record to the audit log at `path`, which is readable
+/// only by the user as it names the peers of the guests
+fn append_audit_record(path: &Path, record: &AuditRecord) -> anyhow::Result<()> {
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent)?;
+    }
+    let mut options = std::fs::OpenOptions::new();
+    options.create(true).append(true);
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::OpenOptionsExt;
+        options.mode(0o600);
+    }
+    let mut file = options
+        .open(path)
+        .with_context(|| format!("opening {}", path.display()))?;
+    #[cfg(unix)]
+    {
+        // The log may have been created by an earlier version
+        use std::os::unix::fs::PermissionsExt;
+        if file.metadata()?.permissions().mode() & 0o777 != 0o600 {
+            file.set_permissions(std::fs::Permissions::from_mode(0o600))
+                .with_context(|| format!("restricting permissions of {}", path.display()))?;
+        }
+    }
+    let mut line = serde_json::to_string(record)?;
+    line.push('\n');
+    file.write_all(line.as_bytes())?;
+    Ok(())
+}
+
+/// Returns the panes that are visible through `scope`
+fn panes_in_scope(scope: ShareScope) -> Vec<PaneId> {
+    match scope {
+        ShareScope::Pane(pane_id) => vec![pane_id],
+        ShareScope::Tab(tab_id) => Mux::get()
+            .get_tab(tab_id)
+            .map(|tab| {
+                tab.iter_panes_ignoring_zoom()
+                    .into_iter()
+                    .map(|p| p.pane.pane_id())
+                    .collect()
+            })
+            .unwrap_or_default(),
+    }
+}
+
+/// Wakes up the sessions of the guests of a share, so that they notice
+/// that it changed or went away
+fn wake_guests(scope: ShareScope) {
+    let mux = Mux::get();
+    for pane_id in panes_in_scope(scope) {
+        mux.notify(MuxNotification::PaneOutput(pane_id));
+    }
+}
+
+fn prune_expired(shares: &mut HashMap<String, Share>) {
+    let mut expired = vec![];
+    shares.retain(|share_id, share| {
+        if share.is_expired() {
+            expired.push((share_id.clone(), share.scope));
+            false
+        } else {
+            true
+        }
+    });
+    for (share_id, scope) in expired {
+        audit("expired", &share_id, None, None);
+        wake_guests(scope);
+    }
+}
+
+fn list_shares_locked(shares: &mut HashMap<String, Share>) -> Vec<ShareInfo> {
+    prune_expired(shares);
+    let mut infos: Vec<ShareInfo> = shares
+        .iter()
+        .map(|(share_id, share)| share.info(share_id))
+        .collect();
+    infos.sort_by(|a, b| {
+        a.created_at
+            .cmp(&b.created_at)
+            .then_with(|| a.share_id.cmp(&b.share_id))
+    });
+    infos
+}
+
+pub fn create_share(request: CreateShare) -> anyhow::Result<CreateShareResponse> {
+    if !TLS_LISTENING.load(Ordering::SeqCst) {
+        anyhow::bail!(
+            "this process has no tls_servers listener for guests to connect to; \
+             configure one and share from the mux server, \
+             eg: `shelldone cli --prefer-mux share`"
+        );
+    }
+    anyhow::ensure!(request.ttl_secs > 0, "the ttl of a share must be positive");
+
+    let mux = Mux::get();
+    match request.scope {
+        ShareScope::Pane(pane_id) => {
+            mux.get_pane(pane_id)
+                .ok_or_else(|| anyhow!("no such pane {pane_id}"))?;
+        }
+        ShareScope::Tab(tab_id) => {
+            mux.get_tab(tab_id)
+                .ok_or_else(|| anyhow!("no such tab {tab_id}"))?;
+        }
+    }
+
+    let created_at = Utc::now();
+    let ttl = chrono::Duration::from_std(Duration::from_secs(request.ttl_secs))
+        .context("ttl is out of range")?;
+    let expires_at = created_at
+        .checked_add_signed(ttl)
+        .ok_or_else(|| anyhow!("ttl is out of range"))?;
+
+    let share_id = uuid::Uuid::new_v4().simple().to_string()[..12].to_string();
+    let client_cert_pem = PKI.generate_guest_cert(&share_id, expires_at)?;
+    let ca_cert_pem = PKI.ca_pem_string()?;
+
+    let share = Share {
+        scope: request.scope,
+        access: request.access,
+        label: request.label,
+        created_at,
+        expires_at,
+        guests: 0,
+    };
+    let info = share.info(&share_id);
+    SHARES.lock().unwrap().insert(share_id.clone(), share);
+
+    audit(
+        "issued",
+        &share_id,
+        None,
+        Some(&format!(
+            "scope={:?} access={:?} expires={}",
+            info.scope,
+            info.access,
+            expires_at.to_rfc3339()
+        )),
+    );
+
+    Ok(CreateShareResponse {
+        share: info,
+        ca_cert_pem,
+        client_cert_pem,
+    })
+}
+
+pub fn list_shares() -> Vec<ShareInfo> {
+    list_shares_locked(&mut SHARES.lock().unwrap())
+}
+
+pub fn update_share(request: UpdateShare) -> anyhow::Result<Vec<ShareInfo>> {
+    let mut shares = SHARES.lock().unwrap();
+    prune_expired(&mut shares);
+    let share = shares
+        .get_mut(&request.share_id)
+        .ok_or_else(|| anyhow!("no such share {}", request.share_id))?;
+    let scope = share.scope;
+
+    match request.action {
+        UpdateShareAction::SetAccess(access) => {
+            share.access = access;
+            audit(
+                "access changed",
+                &request.share_id,
+                None,
+                Some(&format!("access={access:?}")),
+            );
+        }
+        UpdateShareAction::Revoke => {
+            shares.remove(&request.share_id);
+            audit("revoked", &request.share_id, None, None);
+        }
+    }
+
+    let infos = list_shares_locked(&mut shares);
+    let guest_info = shares
+        .get(&request.share_id)
+        .map(|share| share.guest_info(&request.share_id));
+    drop(shares);
+
+    let mux = Mux::get();
+    if let Some(guest_info) = guest_info {
+        for client in mux.iter_clients() {
+            if client.guest.as_ref().map(|g| g.share_id.as_str()) == Some(&request.share_id) {
+                mux.set_client_guest(&client.client_id, guest_info.clone());
+            }
+        }
+    }
+    wake_guests(scope);
+    Ok(infos)
+}
+
+/// Returns the share id named by the CN of a client certificate,
+/// if it is the certificate of a guest
+pub fn share_id_from_cn(cn: &str) -> Option<&str> {
+    cn.strip_prefix(GUEST_CN_PREFIX)
+}
+
+/// Called when a guest has presented its certificate, to check that its
+/// share is still valid.  Returns the connection state of the guest.
+pub fn authorize_connection(share_id: &str, peer: &str) -> anyhow::Result<Guest> {
+    let mut shares = SHARES.lock().unwrap();
+    prune_expired(&mut shares);
+    match shares.get_mut(share_id) {
+        Some(share) => {
+            share.guests += 1;
+            audit("connected", share_id, Some(peer), None);
+            Ok(Guest {
+                share_id: share_id.to_string(),
+                peer: peer.to_string(),
+                expires: Instant::now()
+                    + (share.expires_at - Utc::now()).to_std().unwrap_or_default(),
+                denied: HashSet::new(),
+            })
+        }
+        None => {
+            audit(
+                "rejected",
+                share_id,
+                Some(peer),
+                Some("share is unknown, revoked or expired"),
+            );
+            anyhow::bail!("share {share_id} is unknown, revoked or expired");
+        }
+    }
+}
+
+/// How a PDU that is received from a guest is handled
+#[derive(Debug, PartialEq, Eq)]
+pub enum GuestVerdict {
+    Allow,
+    /// Respond as though it succeeded, without doing anything.
+    /// The client sends these as a matter of course, but they
+    /// would affect how the owner sees the panes.
+    Ignore,
+    Deny(String),
+}
+
+/// The pane that a PDU reads from or writes to
+#[derive(Debug, PartialEq, Eq)]
+enum PduAccess {
+    /// Needed to establish the session
+    Session,
+    Read(PaneId),
+    Input(PaneId),
+    Ignored,
+    Forbidden,
+}
+
+fn classify(pdu: &Pdu) -> PduAccess {
+    match pdu {
+        Pdu::Ping(_)
+        | Pdu::GetCodecVersion(_)
+        | Pdu::SetClientId(_)
+        | Pdu::ListPanes(_)
+        | Pdu::SetClientViewport(SetClientViewport { viewport: None }) => PduAccess::Session,
+        Pdu::SetClientViewport(SetClientViewport {
+            viewport: Some(viewport),
+        }) => PduAccess::Read(viewport.pane_id),
+        Pdu::GetPaneRenderChanges(GetPaneRenderChanges { pane_id, .. })
+        | Pdu::GetLines(GetLines { pane_id, .. })
+        | Pdu::GetImageCell(GetImageCell { pane_id, .. })
+        | Pdu::GetPaneRenderableDimensions(GetPaneRenderableDimensions { pane_id })
+        | Pdu::SearchScrollbackRequest(SearchScrollbackRequest { pane_id, .. }) => {
+            PduAccess::Read(*pane_id)
+        }
+        Pdu::WriteToPane(WriteToPane { pane_id, .. })
+        | Pdu::SendPaste(SendPaste { pane_id, .. })
+        | Pdu::SendKeyDown(SendKeyDown { pane_id, .. })
+        | Pdu::SendMouseEvent(SendMouseEvent { pane_id, .. }) => PduAccess::Input(*pane_id),
+        Pdu::SetFocusedPane(_) | Pdu::Resize(_) | Pdu::SetPalette(_) | Pdu::SetPaneZoomed(_) => {
+            PduAccess::Ignored
+        }
+        _ => PduAccess::Forbidden,
+    }
+}
+
+/// The state of the connection of a guest
+pub struct Guest {
+    pub share_id: String,
+    pub peer: String,
+    /// When the share expires
+    pub expires: Instant,
+    /// The kinds of PDU that were denied, so that each is
+    /// only audited once per connection
+    denied: HashSet<&'static str>,
+}
+
+impl Drop for Guest {
+    fn drop(&mut self) {
+        if let Some(share) = SHARES.lock().unwrap().get_mut(&self.share_id) {
+            share.guests = share.guests.saturating_sub(1);
+        }
+        audit("disconnected", &self.share_id, Some(&self.peer), None);
+    }
+}
+
+impl Guest {
+    fn with_share<R>(&self, f: impl FnOnce(&Share) -> R) -> Option<R> {
+        let shares = SHARES.lock().unwrap();
+        shares
+            .get(&self.share_id)
+            .filter(|share| !share.is_expired())
+            .map(f)
+    }
+
+    /// Returns false once the share has been revoked or has expired
+    pub fn is_valid(&self) -> bool {
+        self.with_share(|_| ()).is_some()
+    }
+
+    pub fn guest_info(&self) -> Option<GuestInfo> {
+        self.with_share(|share| share.guest_info(&self.share_id))
+    }
+
+    pub fn scope(&self) -> Option<ShareScope> {
+        self.with_share(|share| share.scope)
+    }
+
+    /// Returns true if the guest may see `pane_id`
+    pub fn may_observe_pane(&self, pane_id: PaneId) -> bool {
+        match self.scope() {
+            Some(ShareScope::Pane(shared)) => shared == pane_id,
+            Some(ShareScope::Tab(tab_id)) => Mux::get()
+                .resolve_pane_id(pane_id)
+                .map(|(_, _, pane_tab_id)| pane_tab_id == tab_id)
+                .unwrap_or(false),
+            None => false,
+        }
+    }
+
+    /// Returns true if the guest may see `tab_id`
+    pub fn may_observe_tab(&self, tab_id: TabId) -> bool {
+        self.scope() == Some(ShareScope::Tab(tab_id))
+    }
+
+    /// Decides how to handle a PDU that was received from the guest,
+    /// auditing each kind of PDU that is denied
+    pub fn authorize(&mut self, pdu: &Pdu) -> GuestVerdict {
+        let access = match self.with_share(|share| share.access) {
+            Some(access) => access,
+            None => {
+                return GuestVerdict::Deny(format!(
+                    "share {} was revoked or has expired",
+                    self.share_id
+                ))
+            }
+        };
+
+        let denied = match classify(pdu) {
+            PduAccess::Session => return GuestVerdict::Allow,
+            PduAccess::Ignored => return GuestVerdict::Ignore,
+            PduAccess::Read(pane_id) if self.may_observe_pane(pane_id) => {
+                return GuestVerdict::Allow
+            }
+            PduAccess::Input(pane_id) if self.may_observe_pane(pane_id) => {
+                if access == ShareAccess::Interactive {
+                    return GuestVerdict::Allow;
+                }
+                "the share is read-only"
+            }
+            PduAccess::Read(_) | PduAccess::Input(_) => "the pane is not shared",
+            PduAccess::Forbidden => "guests may not do that",
+        };
+
+        let name = pdu.pdu_name();
+        if self.denied.insert(name) {
+            audit("denied", &self.share_id, Some(&self.peer), Some(name));
+        }
+        GuestVerdict::Deny(format!("{name}: {denied}"))
+    }
+}
+
+/// Reduces the tabs of a ListPanesResponse to those that are visible
+/// through `scope`.  When a single pane is shared, it is presented as the
+/// only pane of its tab.
+pub fn filter_tabs(
+    scope: Option<ShareScope>,
+    tabs: Vec<PaneNode>,
+    tab_titles: Vec<String>,
+) -> ListPanesResponse {
+    let mut response = ListPanesResponse {
+        tabs: vec![],
+        tab_titles: vec![],
+        window_titles: HashMap::new(),
+    };
+
+    fn find_leaf(node: PaneNode, pane_id: PaneId) -> Option<PaneNode> {
+        match node {
+            PaneNode::Empty => None,
+            PaneNode::Split { left, right, .. } => {
+                find_leaf(*left, pane_id).or_else(|| find_leaf(*right, pane_id))
+            }
+            PaneNode::Leaf(mut entry) if entry.pane_id == pane_id => {
+                entry.left_col = 0;
+                entry.top_row = 0;
+                entry.is_active_pane = true;
+                entry.is_zoomed_pane = false;
+                Some(PaneNode::Leaf(entry))
+            }
+            PaneNode::Leaf(_) => None,
+        }
+    }
+
+    for (tab, title) in tabs.into_iter().zip(tab_titles) {
+        let tab = match (scope, tab.window_and_tab_ids()) {
+            (Some(ShareScope::Pane(pane_id)), Some(_)) => find_leaf(tab, pane_id),
+            (Some(ShareScope::Tab(shared)), Some((_, tab_id))) if shared == tab_id => Some(tab),
+            _ => None,
+        };
+        if let Some(tab) = tab {
+            response.tabs.push(tab);
+            response.tab_titles.push(title);
+        }
+    }
+
+    response
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use mux::client::ClientViewport;
+    use mux::renderable::StableCursorPosition;
+    use mux::tab::{PaneEntry, SplitDirection, SplitDirectionAndSize};
+    use shelldone_term::TerminalSize;
+    use std::sync::{Arc, Once};
+    use termwiz::input::{KeyCode, KeyEvent, Modifiers};
+
+    /// Changes to shares notify the mux, so the tests need one
+    fn init_mux() {
+        static INIT: Once = Once::new();
+        INIT.call_once(|| {
+            let mut config = config::Config::default_config();
+            config.mux_enable_ssh_agent = false;
+            config::use_this_configuration(config);
+            Mux::set_mux(&Arc::new(Mux::new(None)));
+        });
+    }
+
+    /// Adds a share, bypassing create_share, which needs a
+    /// tls listener and the PKI
+    fn add_share(share_id: &str, scope: ShareScope, access: ShareAccess, ttl_secs: i64) {
+        let created_at = Utc::now();
+        SHARES.lock().unwrap().insert(
+            share_id.to_string(),
+            Share {
+                scope,
+                access,
+                label: None,
+                created_at,
+                expires_at: created_at + chrono::Duration::seconds(ttl_secs),
+                guests: 0,
+            },
+        );
+    }
+
+    fn write_to_pane(pane_id: PaneId) -> Pdu {
+        Pdu::WriteToPane(WriteToPane {
+            pane_id,
+            data: b"ls\r".to_vec(),
+        })
+    }
+
+    fn key_down(pane_id: PaneId) -> Pdu {
+        Pdu::SendKeyDown(SendKeyDown {
+            pane_id,
+            event: KeyEvent {
+                key: KeyCode::Char('l'),
+                modifiers: Modifiers::NONE,
+            },
+            input_serial: InputSerial::empty(),
+        })
+    }
+
+    fn get_lines(pane_id: PaneId) -> Pdu {
+        Pdu::GetLines(GetLines {
+            pane_id,
+            lines: vec![],
+        })
+    }
+
+    fn leaf(tab_id: TabId, pane_id: PaneId) -> PaneNode {
+        PaneNode::Leaf(PaneEntry {
+            window_id: 1,
+            tab_id,
+            pane_id,
+            title: format!("pane {pane_id}"),
+            size: TerminalSize::default(),
+            working_dir: None,
+            is_active_pane: false,
+            is_zoomed_pane: false,
+            is_active_tab: true,
+            workspace: "default".to_string(),
+            cursor_pos: StableCursorPosition::default(),
+            physical_top: 0,
+            top_row: 0,
+            left_col: 40,
+            tty_name: None,
+            monitors: Default::default(),
+            monitor_alert: None,
+            domain_name: None,
+        })
+    }
+
+    fn split(tab_id: TabId, left: PaneId, right: PaneId) -> PaneNode {
+        PaneNode::Split {
+            left: Box::new(leaf(tab_id, left)),
+            right: Box::new(leaf(tab_id, right)),
+            node: SplitDirectionAndSize {
+                direction: SplitDirection::Horizontal,
+                first: TerminalSize::default(),
+                second: TerminalSize::default(),
+            },
+        }
+    }
+
+    fn leaf_pane_ids(node: &PaneNode) -> Vec<PaneId> {
+        match node {
+            PaneNode::Empty => vec![],
+            PaneNode::Split { left, right, .. } => {
+                let mut ids = leaf_pane_ids(left);
+                ids.extend(leaf_pane_ids(right));
+                ids
+            }
+            PaneNode::Leaf(entry) => vec![entry.pane_id],
+        }
+    }
+
+    #[test]
+    fn classify_pdus() {
+        assert_eq!(classify(&Pdu::Ping(Ping {})), PduAccess::Session);
+        assert_eq!(classify(&Pdu::ListPanes(ListPanes {})), PduAccess::Session);
+        assert_eq!(
+            classify(&Pdu::SetClientViewport(SetClientViewport {
+                viewport: Some(ClientViewport {
+                    pane_id: 3,
+                    top: None,
+                    pointer: None,
+                }),
+            })),
+            PduAccess::Read(3)
+        );
+        assert_eq!(
+            classify(&Pdu::GetLines(GetLines {
+                pane_id: 1,
+                lines: vec![],
+            })),
+            PduAccess::Read(1)
+        );
+        assert_eq!(
+            classify(&Pdu::WriteToPane(WriteToPane {
+                pane_id: 2,
+                data: b"ls\r".to_vec(),
+            })),
+            PduAccess::Input(2)
+        );
+        assert_eq!(
+            classify(&Pdu::SetFocusedPane(SetFocusedPane { pane_id: 2 })),
+            PduAccess::Ignored
+        );
+        assert_eq!(
+            classify(&Pdu::GetTlsCreds(GetTlsCreds {})),
+            PduAccess::Forbidden
+        );
+        assert_eq!(
+            classify(&Pdu::KillPane(KillPane { pane_id: 2 })),
+            PduAccess::Forbidden
+        );
+        assert_eq!(
+            classify(&Pdu::CreateShare(CreateShare {
+                scope: ShareScope::Pane(2),
+                access: ShareAccess::Interactive,
+                ttl_secs: 60,
+                label: None,
+            })),
+            PduAccess::Forbidden
+        );
+    }
+
+    #[test]
+    fn read_only_share_denies_input_until_made_interactive() {
+        init_mux();
+        add_share("readonly", ShareScope::Pane(10), ShareAccess::ReadOnly, 60);
+        let mut guest = authorize_connection("readonly", "peer").unwrap();
+
+        assert_eq!(guest.authorize(&get_lines(10)), GuestVerdict::Allow);
+        assert!(matches!(
+            guest.authorize(&write_to_pane(10)),
+            GuestVerdict::Deny(_)
+        ));
+        assert!(matches!(
+            guest.authorize(&key_down(10)),
+            GuestVerdict::Deny(_)
+        ));
+
+        update_share(UpdateShare {
+            share_id: "readonly".to_string(),
+            action: UpdateShareAction::SetAccess(ShareAccess::Interactive),
+        })
+        .unwrap();
+        assert_eq!(guest.authorize(&write_to_pane(10)), GuestVerdict::Allow);
+        assert_eq!(guest.authorize(&key_down(10)), GuestVerdict::Allow);
+    }
+
+    #[test]
+    fn panes_outside_the_scope_are_denied() {
+        init_mux();
+        add_share("scoped", ShareScope::Pane(20), ShareAccess::Interactive, 60);
+        let mut guest = authorize_connection("scoped", "peer").unwrap();
+
+        assert_eq!(guest.authorize(&get_lines(20)), GuestVerdict::Allow);
+        assert!(matches!(
+            guest.authorize(&get_lines(21)),
+            GuestVerdict::Deny(_)
+        ));
+        assert!(matches!(
+            guest.authorize(&write_to_pane(21)),
+            GuestVerdict::Deny(_)
+        ));
+        assert!(guest.may_observe_pane(20));
+        assert!(!guest.may_observe_pane(21));
+    }
+
+    #[test]
+    fn revoked_and_expired_shares_are_invalid() {
+        init_mux();
+        add_share(
+            "revoked",
+            ShareScope::Pane(30),
+            ShareAccess::Interactive,
+            60,
+        );
+        let mut guest = authorize_connection("revoked", "peer").unwrap();
+        assert!(guest.is_valid());
+
+        update_share(UpdateShare {
+            share_id: "revoked".to_string(),
+            action: UpdateShareAction::Revoke,
+        })
+        .unwrap();
+        assert!(!guest.is_valid());
+        assert!(matches!(
+            guest.authorize(&get_lines(30)),
+            GuestVerdict::Deny(_)
+        ));
+        assert!(authorize_connection("revoked", "peer").is_err());
+
+        add_share(
+            "expired",
+            ShareScope::Pane(31),
+            ShareAccess::Interactive,
+            -1,
+        );
+        assert!(authorize_connection("expired", "peer").is_err());
+        assert!(!list_shares().iter().any(|info| info.share_id == "expired"));
+    }
+
+    #[test]
+    fn filter_tabs_by_scope() {
+        let tabs = || vec![split(1, 100, 101), leaf(2, 200)];
+        let titles = || vec!["one".to_string(), "two".to_string()];
+
+        let response = filter_tabs(Some(ShareScope::Pane(101)), tabs(), titles());
+        assert_eq!(response.tabs.len(), 1);
+        assert_eq!(response.tab_titles, vec!["one".to_string()]);
+        match &response.tabs[0] {
+            PaneNode::Leaf(entry) => {
+                assert_eq!(entry.pane_id, 101);
+                assert_eq!(entry.left_col, 0);
+                assert!(entry.is_active_pane);
+            }
+            _ => panic!("a shared pane is presented as the only pane of its tab"),
+        }
+
+        let response = filter_tabs(Some(ShareScope::Tab(1)), tabs(), titles());
+        assert_eq!(response.tab_titles, vec!["one".to_string()]);
+        assert_eq!(leaf_pane_ids(&response.tabs[0]), vec![100, 101]);
+
+        let response = filter_tabs(Some(ShareScope::Tab(3)), tabs(), titles());
+        assert!(response.tabs.is_empty());
+        assert!(filter_tabs(None, tabs(), titles()).tabs.is_empty());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn audit_log_is_private() {
+        use std::os::unix::fs::PermissionsExt;
+        let path = std::env::temp_dir().join(format!(
+            "shelldone-share-audit-mode-{}.log",
+            std::process::id()
+        ));
+        let record = AuditRecord {
+            time: Utc::now().to_rfc3339(),
+            event: "issued",
+            share_id: "private",
+            peer: None,
+            detail: None,
+        };
+        let mode = |path: &Path| std::fs::metadata(path).unwrap().permissions().mode() & 0o777;
+
+        append_audit_record(&path, &record).unwrap();
+        assert_eq!(mode(&path), 0o600);
+
+        std::fs::set_permissions(&path, std::fs::Permissions::from_mode(0o644)).unwrap();
+        append_audit_record(&path, &record).unwrap();
+        assert_eq!(mode(&path), 0o600);
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn guest_cn() {
+        assert_eq!(share_id_from_cn("guest:abc123"), Some("abc123"));
+        assert_eq!(share_id_from_cn("wez"), None);
+    }
+}
@@ -2,15 +2,18 @@ use clap::*;
 use config::configuration;
 use mux::activity::Activity;
 use mux::domain::{Domain, LocalDomain};
-use mux::Mux;
+use mux::session::MuxSnapshot;
+use mux::{Mux, MuxNotification};
 use portable_pty::cmdbuilder::CommandBuilder;
 use shelldone_gui_subcommands::*;
 use shelldone_mux_server_impl::update_mux_domains_for_server;
 use std::ffi::OsString;
+use std::path::PathBuf;
 use std::process::Command;
 use std::rc::Rc;
 use std::sync::Arc;
 use std::thread;
+use std::time::Duration;
 
 mod daemonize;
 mod sigma;
@@ -228,6 +231,7 @@ fn run() -> anyhow::Result<()> {
     let domain: Arc<dyn Domain> = Arc::new(LocalDomain::new("local")?);
     let mux = Arc::new(mux::Mux::new(Some(domain.clone())));
     Mux::set_mux(&mux);
+    deny_requests_without_frontend(&mux);
 
     sigma::install_sigma_reporter();
 
@@ -253,6 +257,34 @@ fn run() -> anyhow::Result<()> {
     }
 }
 
+/// The server has no frontend that could ask the user for permission,
+/// so requests that need one are refused straight away rather than
+/// leaving the application waiting for an answer that never comes.
+fn deny_requests_without_frontend(mux: &Mux) {
+    mux.subscribe(|n| {
+        match n {
+            MuxNotification::RequestFileTransfer { pane_id, request } => {
+                log::warn!(
+                    "refusing file transfer {} in pane {}: no frontend to permit it",
+                    request.id,
+                    pane_id
+                );
+                mux::file_transfer::respond_to_file_transfer(pane_id, request.id, false, None);
+            }
+            MuxNotification::RequestClipboard { pane_id, request } => {
+                log::warn!("refusing clipboard read in pane {pane_id}: no frontend to permit it");
+                mux::clipboard::deny_clipboard_read(
+                    pane_id,
+                    &request,
+                    mux::clipboard::KittyClipboardStatus::PermissionDenied,
+                );
+            }
+            _ => {}
+        }
+        true
+    });
+}
+
 async fn trigger_mux_startup(lua: Option<Rc<mlua::Lua>>) -> anyhow::Result<()> {
     if let Some(lua) = lua {
         let args = lua.pack_multi(())?;
@@ -289,7 +321,15 @@ async fn async_run(cmd: Option<CommandBuilder>) -> anyhow::Result<()> {
         .iter()
         .any(|p| p.domain_id() == domain.domain_id());
 
-    if !have_panes_in_domain {
+    // An explicit program or cwd on the command line takes precedence
+    // over the saved session.
+    let restored = if !have_panes_in_domain && cmd.is_none() && config.mux_session_persistence {
+        restore_mux_session(&mux).await
+    } else {
+        0
+    };
+
+    if !have_panes_in_domain && restored == 0 {
         let workspace = None;
         let position = None;
         let window_id = mux.new_empty_window(workspace, position);
@@ -300,9 +340,94 @@ async fn async_run(cmd: Option<CommandBuilder>) -> anyhow::Result<()> {
             .spawn(config.initial_size(0, None), cmd, None, *window_id)
             .await?;
     }
+
+    if config.mux_session_persistence {
+        #[cfg(unix)]
+        if let Err(err) = save_mux_session_on_exit(config.mux_session_scrollback_lines) {
+            log::error!("unable to save the mux session on exit: {:#}", err);
+        }
+        promise::spawn::spawn(mux::session::save_periodically(
+            mux_session_path(),
+            Duration::from_secs(config.mux_session_save_interval_secs.max(1)),
+            config.mux_session_scrollback_lines,
+        ))
+        .detach();
+    }
+    Ok(())
+}
+
+/// The session file of this server, named after the unix domain that it
+/// serves so that servers for different domains keep separate sessions
+fn mux_session_path() -> PathBuf {
+    let config = configuration();
+    let name = config
+        .unix_domains
+        .first()
+        .map(|unix_dom| unix_dom.name.as_str())
+        .unwrap_or("unix");
+    mux::session::snapshot_path(name)
+}
+
+/// Save the session when the server is asked to terminate, so that the
+/// changes made since the last periodic save are not lost
+#[cfg(unix)]
+fn save_mux_session_on_exit(scrollback_lines: usize) -> anyhow::Result<()> {
+    use signal_hook::consts::{SIGHUP, SIGINT, SIGTERM};
+    let mut signals = signal_hook::iterator::Signals::new([SIGHUP, SIGINT, SIGTERM])?;
+    thread::spawn(move || {
+        let Some(signal) = signals.forever().next() else {
+            return;
+        };
+        let (tx, rx) = std::sync::mpsc::channel();
+        promise::spawn::spawn_into_main_thread(async move {
+            if let Some(mux) = Mux::try_get() {
+                let path = mux_session_path();
+                if let Err(err) = MuxSnapshot::capture(&mux, scrollback_lines).save(&path) {
+                    log::error!("saving mux session: {:#}", err);
+                }
+            }
+            tx.send(()).ok();
+        })
+        .detach();
+        // Don't let a wedged main thread keep the server from exiting
+        rx.recv_timeout(Duration::from_secs(5)).ok();
+        // Terminate the way the signal would have, so that whoever sent it
+        // sees the same outcome as without a handler
+        if let Err(err) = signal_hook::low_level::emulate_default_handler(signal) {
+            log::error!("re-raising signal {}: {:#}", signal, err);
+        }
+        std::process::exit(128 + signal);
+    });
     Ok(())
 }
 
+async fn restore_mux_session(mux: &Arc<Mux>) -> usize {
+    let path = mux_session_path();
+    let snapshot = match MuxSnapshot::load(&path) {
+        Ok(Some(snapshot)) => snapshot,
+        Ok(None) => return 0,
+        Err(err) => {
+            log::error!("loading mux session: {:#}", err);
+            return 0;
+        }
+    };
+    match snapshot.restore(mux).await {
+        Ok(count) => {
+            log::info!(
+                "restored {} of {} panes from {}",
+                count,
+                snapshot.pane_count(),
+                path.display()
+            );
+            count
+        }
+        Err(err) => {
+            log::error!("restoring mux session: {:#}", err);
+            0
+        }
+    }
+}
+
 fn terminate_with_error(err: anyhow::Error) -> ! {
     log::error!("{:#}; terminating", err);
     std::process::exit(1);
@@ -4,11 +4,19 @@ use config::TlsDomainServer;
 use openssl::ssl::{SslAcceptor, SslFiletype, SslMethod, SslStream, SslVerifyMode};
 use openssl::x509::X509;
 use promise::spawn::spawn_into_main_thread;
-use shelldone_mux_server_impl::PKI;
+use shelldone_mux_server_impl::{dispatch, share, PKI};
 use std::net::TcpListener;
 use std::path::Path;
 use std::sync::Arc;
 
+/// Who the peer authenticated as
+enum PeerIdentity {
+    /// The user running this mux server instance
+    Owner,
+    /// The guest of the share with this id
+    Guest(String),
+}
+
 struct OpenSSLNetListener {
     acceptor: Arc<SslAcceptor>,
     listener: TcpListener,
@@ -31,7 +39,9 @@ impl OpenSSLNetListener {
     ///   user running this mux server instance, or must match
     ///   a special encoded prefix set up by a proprietary PKI
     ///   infrastructure in an environment used by the author.
-    fn verify_peer_cert<T>(stream: &SslStream<T>) -> anyhow::Result<()> {
+    /// * Alternatively, the CN may identify the share of a guest,
+    ///   which is then checked against the registry of shares.
+    fn verify_peer_cert<T>(stream: &SslStream<T>) -> anyhow::Result<PeerIdentity> {
         let cert = stream
             .ssl()
             .peer_certificate()
@@ -43,6 +53,11 @@ impl OpenSSLNetListener {
             .ok_or_else(|| anyhow!("cert has no CN"))?;
         let cn_str = cn.data().as_utf8()?.to_string();
 
+        if let Some(share_id) = share::share_id_from_cn(&cn_str) {
+            log::trace!("Peer certificate CN `{}` is a guest", cn_str);
+            return Ok(PeerIdentity::Guest(share_id.to_string()));
+        }
+
         let wanted_unix_name = std::env::var("USER")?;
 
         if wanted_unix_name == cn_str {
@@ -51,7 +66,7 @@ impl OpenSSLNetListener {
                 cn_str,
                 wanted_unix_name
             );
-            Ok(())
+            Ok(PeerIdentity::Owner)
         } else {
             // Some environments that are used by the author of this
             // program encode the CN in the form `user:unixname/DATA`
@@ -62,7 +77,7 @@ impl OpenSSLNetListener {
                     cn_str,
                     wanted_unix_name
                 );
-                Ok(())
+                Ok(PeerIdentity::Owner)
             } else {
                 anyhow::bail!("CN `{}` did not match $USER `{}`", cn_str, wanted_unix_name);
             }
@@ -74,21 +89,38 @@ impl OpenSSLNetListener {
             match stream {
                 Ok(stream) => {
                     stream.set_nodelay(true).ok();
+                    let peer = stream
+                        .peer_addr()
+                        .map(|addr| addr.to_string())
+                        .unwrap_or_else(|_| "unknown".to_string());
                     let acceptor = self.acceptor.clone();
 
                     match acceptor.accept(stream) {
                         Ok(stream) => {
-                            if let Err(err) = Self::verify_peer_cert(&stream) {
-                                log::error!("problem with peer cert: {}", err);
-                                break;
-                            }
+                            let identity = match Self::verify_peer_cert(&stream) {
+                                Ok(identity) => identity,
+                                Err(err) => {
+                                    // Keep listening: a guest whose share has
+                                    // gone away shouldn't shut out everyone else
+                                    log::error!("problem with peer cert from {}: {}", peer, err);
+                                    continue;
+                                }
+                            };
                             spawn_into_main_thread(async move {
                                 log::error!("Making new AsyncSslStream");
-                                shelldone_mux_server_impl::dispatch::process(AsyncSslStream::new(
-                                    stream,
-                                ))
-                                .await
-                                .map_err(|e| {
+                                let stream = AsyncSslStream::new(stream);
+                                let result = match identity {
+                                    PeerIdentity::Owner => dispatch::process(stream).await,
+                                    PeerIdentity::Guest(share_id) => {
+                                        match share::authorize_connection(&share_id, &peer) {
+                                            Ok(guest) => {
+                                                dispatch::process_guest(stream, guest).await
+                                            }
+                                            Err(err) => Err(err),
+                                        }
+                                    }
+                                };
+                                result.map_err(|e| {
                                     log::error!("process: {:?}", e);
                                     e
                                 })
@@ -184,5 +216,6 @@ pub fn spawn_tls_listener(tls_server: &TlsDomainServer) -> Result<(), Error> {
     std::thread::spawn(move || {
         net_listener.run();
     });
+    share::set_tls_listening();
     Ok(())
 }
@@ -1,11 +1,14 @@
 use chrono::Utc;
 use config::CACHE_DIR;
 use crossbeam::channel::{bounded, Receiver, Sender, TrySendError};
+use mux::file_transfer::FileTransferDirection;
 use mux::sigma_proxy::{
-    set_sigma_policy_reporter, SigmaDirection, SigmaPolicyReporter, SigmaViolation,
+    set_sigma_policy_reporter, SigmaClipboardAction, SigmaClipboardEvent, SigmaCommandEvent,
+    SigmaDirection, SigmaFileTransferEvent, SigmaPolicyReporter, SigmaTriggerEvent, SigmaViolation,
 };
 use reqwest::blocking::Client;
 use serde::{Deserialize, Serialize};
+use shelldone_term::ClipboardSelection;
 use std::io::Write;
 use std::path::{Path, PathBuf};
 use std::sync::atomic::{AtomicUsize, Ordering};
@@ -47,7 +50,7 @@ pub fn install_sigma_reporter() {
         }))
     };
 
-    let (tx, rx) = bounded::<SigmaViolation>(QUEUE_BOUND);
+    let (tx, rx) = bounded::<JournalRequest>(QUEUE_BOUND);
     let reporter = Arc::new(AgentdSigmaReporter::new(tx, spool_config.clone()));
     set_sigma_policy_reporter(reporter);
 
@@ -64,46 +67,42 @@ struct SpoolConfig {
 }
 
 struct AgentdSigmaReporter {
-    tx: Sender<SigmaViolation>,
+    tx: Sender<JournalRequest>,
     dropped: AtomicUsize,
     spool: Option<Arc<SpoolConfig>>,
 }
 
 impl AgentdSigmaReporter {
-    fn new(tx: Sender<SigmaViolation>, spool: Option<Arc<SpoolConfig>>) -> Self {
+    fn new(tx: Sender<JournalRequest>, spool: Option<Arc<SpoolConfig>>) -> Self {
         Self {
             tx,
             dropped: AtomicUsize::new(0),
             spool,
         }
     }
-}
 
-impl SigmaPolicyReporter for AgentdSigmaReporter {
-    fn report(&self, violation: SigmaViolation) {
-        match self.tx.try_send(violation) {
+    fn enqueue(&self, request: JournalRequest) {
+        match self.tx.try_send(request) {
             Ok(_) => {
                 self.dropped.store(0, Ordering::Relaxed);
             }
-            Err(TrySendError::Full(violation)) => {
+            Err(TrySendError::Full(request)) => {
                 if self.dropped.fetch_add(1, Ordering::Relaxed) == 0 {
-                    log::warn!("Sigma policy reporter queue is full; dropping violations");
+                    log::warn!("Sigma policy reporter queue is full; dropping events");
                 }
                 if let Some(spool) = &self.spool {
-                    let request = JournalRequest::from_violation(&violation);
                     if let Err(err) = persist_request(spool, &request) {
-                        log::error!("Failed to persist sigma guard event: {err:#}");
+                        log::error!("Failed to persist sigma event: {err:#}");
                     }
                 }
             }
-            Err(TrySendError::Disconnected(violation)) => {
+            Err(TrySendError::Disconnected(request)) => {
                 if self.dropped.fetch_add(1, Ordering::Relaxed) == 0 {
                     log::error!("Sigma policy reporter channel disconnected");
                 }
                 if let Some(spool) = &self.spool {
-                    let request = JournalRequest::from_violation(&violation);
                     if let Err(err) = persist_request(spool, &request) {
-                        log::error!("Failed to persist sigma guard event: {err:#}");
+                        log::error!("Failed to persist sigma event: {err:#}");
                     }
                 }
             }
@@ -111,7 +110,29 @@ impl SigmaPolicyReporter for AgentdSigmaReporter {
     }
 }
 
-fn worker_loop(endpoint: String, rx: Receiver<SigmaViolation>, spool: Option<Arc<SpoolConfig>>) {
+impl SigmaPolicyReporter for AgentdSigmaReporter {
+    fn report(&self, violation: SigmaViolation) {
+        self.enqueue(JournalRequest::from_violation(&violation));
+    }
+
+    fn report_clipboard(&self, event: SigmaClipboardEvent) {
+        self.enqueue(JournalRequest::from_clipboard_event(&event));
+    }
+
+    fn report_file_transfer(&self, event: SigmaFileTransferEvent) {
+        self.enqueue(JournalRequest::from_file_transfer_event(&event));
+    }
+
+    fn report_trigger(&self, event: SigmaTriggerEvent) {
+        self.enqueue(JournalRequest::from_trigger_event(&event));
+    }
+
+    fn report_command(&self, event: SigmaCommandEvent) {
+        self.enqueue(JournalRequest::from_command_event(&event));
+    }
+}
+
+fn worker_loop(endpoint: String, rx: Receiver<JournalRequest>, spool: Option<Arc<SpoolConfig>>) {
     let client = match Client::builder()
         .timeout(Duration::from_millis(200))
         .build()
@@ -131,12 +152,11 @@ fn worker_loop(endpoint: String, rx: Receiver<SigmaViolation>, spool: Option<Arc
         }
     }
 
-    for violation in rx.iter() {
-        let request = JournalRequest::from_violation(&violation);
+    for request in rx.iter() {
         if !send_request(&client, &endpoint, &request, &mut last_error) {
             if let Some(spool) = &spool {
                 if let Err(err) = persist_request(spool, &request) {
-                    log::error!("Failed to persist sigma guard event: {err:#}");
+                    log::error!("Failed to persist sigma event: {err:#}");
                 }
             }
         }
@@ -159,13 +179,13 @@ fn throttle_warn(last_error: &mut Option<Instant>, message: String) {
 struct JournalRequest {
     kind: String,
     persona: Option<String>,
-    payload: JournalPayload,
+    payload: serde_json::Value,
     spectral_tag: Option<String>,
     bytes: Option<usize>,
 }
 
 #[derive(Clone, Serialize, Deserialize)]
-struct JournalPayload {
+struct GuardPayload {
     reason: String,
     direction: String,
     sequence_preview: String,
@@ -173,12 +193,48 @@ struct JournalPayload {
     occurred_at: String,
 }
 
+#[derive(Clone, Serialize, Deserialize)]
+struct ClipboardPayload {
+    pane_id: usize,
+    selection: String,
+    mime_types: Vec<String>,
+    allowed: bool,
+    occurred_at: String,
+}
+
+#[derive(Clone, Serialize, Deserialize)]
+struct FileTransferPayload {
+    pane_id: usize,
+    path: Option<String>,
+    allowed: bool,
+    reason: Option<String>,
+    occurred_at: String,
+}
+
+#[derive(Clone, Serialize, Deserialize)]
+struct TriggerPayload {
+    pane_id: usize,
+    line: String,
+    occurred_at: String,
+}
+
+#[derive(Clone, Serialize, Deserialize)]
+struct CommandPayload {
+    pane_id: usize,
+    command: String,
+    cwd: Option<String>,
+    hostname: String,
+    exit_code: Option<i32>,
+    started_at: String,
+    occurred_at: String,
+}
+
 impl JournalRequest {
     fn from_violation(violation: &SigmaViolation) -> Self {
         Self {
             kind: "sigma.guard".to_string(),
             persona: None,
-            payload: JournalPayload {
+            payload: to_payload(GuardPayload {
                 reason: violation.reason.to_string(),
                 direction: match violation.direction {
                     SigmaDirection::Input => "input".to_string(),
@@ -187,11 +243,91 @@ impl JournalRequest {
                 sequence_preview: violation.sequence_preview.clone(),
                 sequence_len: violation.sequence_len,
                 occurred_at: chrono::DateTime::<Utc>::from(violation.occurred_at).to_rfc3339(),
-            },
+            }),
             spectral_tag: Some("sigma::guard".to_string()),
             bytes: Some(violation.sequence_len),
         }
     }
+
+    fn from_clipboard_event(event: &SigmaClipboardEvent) -> Self {
+        Self {
+            kind: match event.action {
+                SigmaClipboardAction::Read => "sigma.clipboard.read".to_string(),
+                SigmaClipboardAction::Write => "sigma.clipboard.write".to_string(),
+            },
+            persona: None,
+            payload: to_payload(ClipboardPayload {
+                pane_id: event.pane_id,
+                selection: match event.selection {
+                    ClipboardSelection::Clipboard => "clipboard".to_string(),
+                    ClipboardSelection::PrimarySelection => "primary".to_string(),
+                },
+                mime_types: event.mime_types.clone(),
+                allowed: event.allowed,
+                occurred_at: chrono::DateTime::<Utc>::from(event.occurred_at).to_rfc3339(),
+            }),
+            spectral_tag: Some("sigma::clipboard".to_string()),
+            bytes: Some(event.bytes),
+        }
+    }
+
+    fn from_file_transfer_event(event: &SigmaFileTransferEvent) -> Self {
+        Self {
+            kind: match event.direction {
+                FileTransferDirection::Send => "sigma.file_transfer.send".to_string(),
+                FileTransferDirection::Receive => "sigma.file_transfer.receive".to_string(),
+            },
+            persona: None,
+            payload: to_payload(FileTransferPayload {
+                pane_id: event.pane_id,
+                path: event
+                    .path
+                    .as_ref()
+                    .map(|path| path.to_string_lossy().into_owned()),
+                allowed: event.allowed,
+                reason: event.reason.clone(),
+                occurred_at: chrono::DateTime::<Utc>::from(event.occurred_at).to_rfc3339(),
+            }),
+            spectral_tag: Some("sigma::file_transfer".to_string()),
+            bytes: Some(event.bytes as usize),
+        }
+    }
+
+    fn from_trigger_event(event: &SigmaTriggerEvent) -> Self {
+        Self {
+            kind: event.kind.clone(),
+            persona: None,
+            payload: to_payload(TriggerPayload {
+                pane_id: event.pane_id,
+                line: event.line.clone(),
+                occurred_at: chrono::DateTime::<Utc>::from(event.occurred_at).to_rfc3339(),
+            }),
+            spectral_tag: Some("sigma::trigger".to_string()),
+            bytes: Some(event.line.len()),
+        }
+    }
+
+    fn from_command_event(event: &SigmaCommandEvent) -> Self {
+        Self {
+            kind: "shell.command".to_string(),
+            persona: None,
+            payload: to_payload(CommandPayload {
+                pane_id: event.pane_id,
+                command: event.command.clone(),
+                cwd: event.cwd.clone(),
+                hostname: event.hostname.clone(),
+                exit_code: event.exit_code,
+                started_at: chrono::DateTime::<Utc>::from(event.started_at).to_rfc3339(),
+                occurred_at: chrono::DateTime::<Utc>::from(event.occurred_at).to_rfc3339(),
+            }),
+            spectral_tag: Some("sigma::command_history".to_string()),
+            bytes: Some(event.command.len()),
+        }
+    }
+}
+
+fn to_payload<T: Serialize>(payload: T) -> serde_json::Value {
+    serde_json::to_value(payload).unwrap_or(serde_json::Value::Null)
 }
 
 fn send_request(
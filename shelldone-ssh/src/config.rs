@@ -345,13 +345,14 @@ impl ParsedConfigFile {
 
                 fn add_option(options: &mut ConfigMap, k: String, v: &str) {
                     // first option wins in ssh_config, except for identityfile
-                    // which explicitly allows multiple entries to combine together
-                    let is_identity_file = k == "identityfile";
+                    // and the forwarding options, which explicitly allow multiple
+                    // entries to combine together
+                    let separator = option_separator(&k);
                     options
                         .entry(k)
                         .and_modify(|e| {
-                            if is_identity_file {
-                                e.push(' ');
+                            if let Some(sep) = separator {
+                                e.push(sep);
                                 e.push_str(v);
                             }
                         })
@@ -389,7 +390,18 @@ impl ParsedConfigFile {
             }
             if group.is_match(hostname, user, local_user, context) {
                 for (k, v) in &group.options {
-                    target.entry(k.to_string()).or_insert_with(|| v.to_string());
+                    if is_forward_option(k) {
+                        // Forwards from every matching block are applied
+                        target
+                            .entry(k.to_string())
+                            .and_modify(|e| {
+                                e.push('\n');
+                                e.push_str(v);
+                            })
+                            .or_insert_with(|| v.to_string());
+                    } else {
+                        target.entry(k.to_string()).or_insert_with(|| v.to_string());
+                    }
                 }
             }
         }
@@ -398,6 +410,24 @@ impl ParsedConfigFile {
     }
 }
 
+fn is_forward_option(key: &str) -> bool {
+    matches!(key, "localforward" | "remoteforward" | "dynamicforward")
+}
+
+/// Returns the separator used to combine repeated entries for options
+/// that accumulate rather than having the first entry win.
+/// Each forward is a whitespace separated pair, so forwards are kept
+/// one per line.
+fn option_separator(key: &str) -> Option<char> {
+    if key == "identityfile" {
+        Some(' ')
+    } else if is_forward_option(key) {
+        Some('\n')
+    } else {
+        None
+    }
+}
+
 /// A context for resolving configuration values.
 /// Holds a combination of environment and token expansion state,
 /// as well as the set of configs that should be consulted.
@@ -564,6 +594,11 @@ impl Config {
                 .map(|p| p.to_string())
                 .unwrap_or_else(|| "22".to_string()),
         );
+        if let Some(jump) = result.get("proxyjump") {
+            if jump != "none" {
+                token_map.insert("%j".to_string(), jump.to_string());
+            }
+        }
 
         for (k, v) in &mut result {
             if let Some(tokens) = self.should_expand_tokens(k) {
@@ -714,13 +749,8 @@ impl Config {
                     *value = items.join(" ");
                 }
             } else if t == "%j" {
-                // %j: The contents of the ProxyJump option, or the empty string if this option is unset
-                // We don't directly support ProxyJump, and this %j token referencing
-                // may technically put this into two-phase evaluation territory which
-                // we don't support.
-                // Let's silently gloss over this and treat this token as the empty
-                // string.
-                // Someone in the future will probably curse this.
+                // %j: The contents of the ProxyJump option, or the empty string if this option is unset.
+                // When it is set, it is present in the token_map and was handled above.
                 *value = value.replace(t, "");
             } else if t == "%T" {
                 // %T: The local tun(4) or tap(4) network interface assigned if tunnel
@@ -845,6 +875,42 @@ mod test {
         );
     }
 
+    #[test]
+    fn forwards_accumulate() {
+        let mut config = Config::new();
+        config.add_config_string(
+            r#"
+        Host foo
+            LocalForward 8080 localhost:80
+            LocalForward 9090 localhost:90
+
+        Host *
+            LocalForward 7070 localhost:70
+            DynamicForward 1080
+            "#,
+        );
+        let mut fake_env = ConfigMap::new();
+        fake_env.insert("HOME".to_string(), "/home/me".to_string());
+        fake_env.insert("USER".to_string(), "me".to_string());
+        config.assign_environment(fake_env);
+
+        let opts = config.for_host("foo");
+        snapshot!(
+            opts,
+            r#"
+{
+    "dynamicforward": "1080",
+    "hostname": "foo",
+    "identityfile": "/home/me/.ssh/id_dsa /home/me/.ssh/id_ecdsa /home/me/.ssh/id_ed25519 /home/me/.ssh/id_rsa",
+    "localforward": "8080 localhost:80\n9090 localhost:90\n7070 localhost:70",
+    "port": "22",
+    "user": "me",
+    "userknownhostsfile": "/home/me/.ssh/known_hosts /home/me/.ssh/known_hosts2",
+}
+"#
+        );
+    }
+
     #[test]
     fn parse_proxy_command_tokens() {
         let mut config = Config::new();
@@ -0,0 +1,886 @@
+use crate::channelwrap::ChannelWrap;
+use crate::config::ConfigMap;
+use crate::sessioninner::{ChannelId, ChannelInfo, DescriptorState, SessionInner};
+use crate::sessionwrap::SessionWrap;
+use anyhow::{anyhow, Context};
+use filedescriptor::FileDescriptor;
+use smol::channel::{Sender, TryRecvError};
+use std::collections::VecDeque;
+use std::io::{Read, Write};
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr, TcpListener, TcpStream, ToSocketAddrs};
+use std::time::Duration;
+
+pub type ForwardId = usize;
+
+/// How long we allow a SOCKS client to take to tell us where it
+/// wants to go, and how long we wait to connect to the target of
+/// a remote forward.
+const FORWARD_TIMEOUT: Duration = Duration::from_secs(10);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ForwardKind {
+    /// `LocalForward`, `ssh -L`
+    Local,
+    /// `RemoteForward`, `ssh -R`
+    Remote,
+    /// `DynamicForward`, `ssh -D`
+    Dynamic,
+}
+
+impl std::str::FromStr for ForwardKind {
+    type Err = anyhow::Error;
+    fn from_str(s: &str) -> anyhow::Result<Self> {
+        match s.to_ascii_lowercase().as_str() {
+            "l" | "local" | "localforward" => Ok(Self::Local),
+            "r" | "remote" | "remoteforward" => Ok(Self::Remote),
+            "d" | "dynamic" | "dynamicforward" => Ok(Self::Dynamic),
+            _ => anyhow::bail!(
+                "invalid forward kind {s:?}, expected one of Local, Remote or Dynamic"
+            ),
+        }
+    }
+}
+
+/// A host and port; the host is optional for listening addresses,
+/// in which case the default is loopback-only, matching the behavior
+/// of `ssh` with `GatewayPorts no`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ForwardAddr {
+    pub host: Option<String>,
+    pub port: u16,
+}
+
+impl std::fmt::Display for ForwardAddr {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self.host.as_deref() {
+            Some(host) if host.contains(':') => write!(f, "[{}]:{}", host, self.port),
+            Some(host) => write!(f, "{}:{}", host, self.port),
+            None => write!(f, "{}", self.port),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ForwardSpec {
+    /// Listen on `listen` locally; connections are made to `connect`
+    /// from the remote host
+    Local {
+        listen: ForwardAddr,
+        connect: ForwardAddr,
+    },
+    /// Ask the remote host to listen on `listen`; connections are made
+    /// to `connect` from the local host
+    Remote {
+        listen: ForwardAddr,
+        connect: ForwardAddr,
+    },
+    /// Listen on `listen` locally and act as a SOCKS4/SOCKS5 proxy;
+    /// connections are made from the remote host
+    Dynamic { listen: ForwardAddr },
+}
+
+impl std::fmt::Display for ForwardSpec {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            Self::Local { listen, connect } => write!(f, "L {listen} -> {connect}"),
+            Self::Remote { listen, connect } => write!(f, "R {listen} -> {connect}"),
+            Self::Dynamic { listen } => write!(f, "D {listen} (socks)"),
+        }
+    }
+}
+
+impl ForwardSpec {
+    pub fn kind(&self) -> ForwardKind {
+        match self {
+            Self::Local { .. } => ForwardKind::Local,
+            Self::Remote { .. } => ForwardKind::Remote,
+            Self::Dynamic { .. } => ForwardKind::Dynamic,
+        }
+    }
+
+    pub fn listen(&self) -> &ForwardAddr {
+        match self {
+            Self::Local { listen, .. } | Self::Remote { listen, .. } | Self::Dynamic { listen } => {
+                listen
+            }
+        }
+    }
+
+    /// Parses the argument syntax used by `ssh -L`, `ssh -R` and `ssh -D`,
+    /// eg: `[bind_address:]port:host:hostport` or `[bind_address:]port`.
+    pub fn from_ssh_arg(kind: ForwardKind, arg: &str) -> anyhow::Result<Self> {
+        let fields = split_fields(arg)?;
+        let spec = match (kind, fields.as_slice()) {
+            (ForwardKind::Dynamic, [port]) => Self::Dynamic {
+                listen: listen_addr(None, port)?,
+            },
+            (ForwardKind::Dynamic, [bind, port]) => Self::Dynamic {
+                listen: listen_addr(Some(bind), port)?,
+            },
+            (ForwardKind::Local | ForwardKind::Remote, [port, host, hostport]) => Self::with_addrs(
+                kind,
+                listen_addr(None, port)?,
+                connect_addr(host, hostport)?,
+            ),
+            (ForwardKind::Local | ForwardKind::Remote, [bind, port, host, hostport]) => {
+                Self::with_addrs(
+                    kind,
+                    listen_addr(Some(bind), port)?,
+                    connect_addr(host, hostport)?,
+                )
+            }
+            _ => anyhow::bail!("invalid {kind:?} forward specification {arg:?}"),
+        };
+        Ok(spec)
+    }
+
+    /// Parses the value of a `LocalForward`, `RemoteForward` or
+    /// `DynamicForward` ssh_config option, eg: `[bind_address:]port host:hostport`
+    pub fn from_config_value(kind: ForwardKind, value: &str) -> anyhow::Result<Self> {
+        let words: Vec<&str> = value.split_whitespace().collect();
+        match (kind, words.as_slice()) {
+            (ForwardKind::Dynamic, [listen]) => Self::from_ssh_arg(kind, listen),
+            (ForwardKind::Local | ForwardKind::Remote, [listen, connect]) => {
+                let listen = split_fields(listen)?;
+                let listen = match listen.as_slice() {
+                    [port] => listen_addr(None, port)?,
+                    [bind, port] => listen_addr(Some(bind), port)?,
+                    _ => anyhow::bail!("invalid listen address in {value:?}"),
+                };
+                let connect = split_fields(connect)?;
+                let connect = match connect.as_slice() {
+                    [host, port] => connect_addr(host, port)?,
+                    _ => anyhow::bail!("invalid connect address in {value:?}"),
+                };
+                Ok(Self::with_addrs(kind, listen, connect))
+            }
+            _ => anyhow::bail!("invalid {kind:?} forward specification {value:?}"),
+        }
+    }
+
+    fn with_addrs(kind: ForwardKind, listen: ForwardAddr, connect: ForwardAddr) -> Self {
+        match kind {
+            ForwardKind::Local => Self::Local { listen, connect },
+            ForwardKind::Remote => Self::Remote { listen, connect },
+            ForwardKind::Dynamic => Self::Dynamic { listen },
+        }
+    }
+}
+
+/// Splits a forwarding argument on `:` (or `/`, which ssh accepts as an
+/// alternative), keeping bracketed IPv6 addresses intact.
+fn split_fields(arg: &str) -> anyhow::Result<Vec<String>> {
+    let sep = if arg.contains('/') && !arg.contains(':') {
+        '/'
+    } else {
+        ':'
+    };
+    let mut fields = vec![];
+    let mut current = String::new();
+    let mut chars = arg.chars();
+    while let Some(c) = chars.next() {
+        if c == '[' && current.is_empty() {
+            loop {
+                match chars.next() {
+                    Some(']') => break,
+                    Some(c) => current.push(c),
+                    None => anyhow::bail!("unterminated '[' in {arg:?}"),
+                }
+            }
+        } else if c == sep {
+            fields.push(std::mem::take(&mut current));
+        } else {
+            current.push(c);
+        }
+    }
+    fields.push(current);
+    Ok(fields)
+}
+
+fn parse_port(port: &str) -> anyhow::Result<u16> {
+    port.parse()
+        .with_context(|| format!("invalid port number {port:?}"))
+}
+
+fn listen_addr(bind: Option<&String>, port: &str) -> anyhow::Result<ForwardAddr> {
+    Ok(ForwardAddr {
+        host: bind.filter(|b| !b.is_empty()).cloned(),
+        port: parse_port(port)?,
+    })
+}
+
+fn connect_addr(host: &str, port: &str) -> anyhow::Result<ForwardAddr> {
+    anyhow::ensure!(!host.is_empty(), "missing host to connect to");
+    Ok(ForwardAddr {
+        host: Some(host.to_string()),
+        port: parse_port(port)?,
+    })
+}
+
+/// Returns the forwards configured via `LocalForward`, `RemoteForward`
+/// and `DynamicForward`.  Invalid entries are logged and skipped.
+pub fn forwards_from_config(config: &ConfigMap) -> Vec<ForwardSpec> {
+    let mut forwards = vec![];
+    for (key, kind) in [
+        ("localforward", ForwardKind::Local),
+        ("remoteforward", ForwardKind::Remote),
+        ("dynamicforward", ForwardKind::Dynamic),
+    ] {
+        if let Some(values) = config.get(key) {
+            for value in values.lines().filter(|v| !v.trim().is_empty()) {
+                match ForwardSpec::from_config_value(kind, value) {
+                    Ok(spec) => forwards.push(spec),
+                    Err(err) => log::error!("ssh: ignoring {key} {value}: {err:#}"),
+                }
+            }
+        }
+    }
+    forwards
+}
+
+/// Describes an active forward
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ForwardInfo {
+    pub id: ForwardId,
+    pub spec: ForwardSpec,
+    /// The port that is actually being listened on, which differs
+    /// from the port in the spec when that was 0
+    pub bound_port: u16,
+    /// The number of connections currently being relayed
+    pub connections: usize,
+}
+
+impl std::fmt::Display for ForwardInfo {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(f, "#{} {}", self.id, self.spec)?;
+        if self.spec.listen().port != self.bound_port {
+            write!(f, " (port {})", self.bound_port)?;
+        }
+        if self.connections > 0 {
+            write!(f, " [{} active]", self.connections)?;
+        }
+        Ok(())
+    }
+}
+
+pub(crate) struct ForwardState {
+    pub info: ForwardInfo,
+    /// The local listening socket for Local and Dynamic forwards
+    pub listener: Option<TcpListener>,
+}
+
+/// The outcome of work that a helper thread did for a forward, so
+/// that the session thread doesn't block on it
+pub(crate) enum ForwardConnect {
+    /// A SOCKS client has told us where it wants to connect
+    Socks(SocksConnect),
+    /// We connected, or failed to connect, to the target of a
+    /// remote forward on behalf of the pending channel `channel_id`
+    Remote {
+        channel_id: ChannelId,
+        stream: anyhow::Result<TcpStream>,
+    },
+}
+
+/// A SOCKS client that has told us where it wants to connect
+pub(crate) struct SocksConnect {
+    pub forward: ForwardId,
+    pub stream: TcpStream,
+    pub peer: SocketAddr,
+    pub host: String,
+    pub port: u16,
+    pub version: u8,
+}
+
+#[derive(Debug)]
+pub(crate) enum ForwardRequest {
+    Add(ForwardSpec, Sender<anyhow::Result<ForwardInfo>>),
+    Remove(ForwardId, Sender<anyhow::Result<()>>),
+    List(Sender<Vec<ForwardInfo>>),
+}
+
+impl SessionInner {
+    pub(crate) fn dispatch_forward_request(
+        &mut self,
+        sess: &mut SessionWrap,
+        req: ForwardRequest,
+    ) -> anyhow::Result<bool> {
+        let res = match req {
+            ForwardRequest::Add(spec, reply) => reply.try_send(self.add_forward(sess, spec)),
+            ForwardRequest::Remove(id, reply) => reply.try_send(self.remove_forward(sess, id)),
+            ForwardRequest::List(reply) => reply.try_send(self.list_forwards()),
+        };
+        if let Err(err) = res {
+            log::error!("forward request: {:#}", err);
+        }
+        Ok(true)
+    }
+
+    /// Establishes the forwards from the ssh config
+    pub(crate) fn setup_configured_forwards(&mut self, sess: &mut SessionWrap) {
+        for spec in forwards_from_config(&self.config) {
+            sess.set_blocking(true);
+            let res = self.add_forward(sess, spec.clone());
+            sess.set_blocking(false);
+            if let Err(err) = res {
+                log::error!("ssh: failed to set up forward {spec}: {err:#}");
+            }
+        }
+    }
+
+    pub(crate) fn add_forward(
+        &mut self,
+        sess: &mut SessionWrap,
+        spec: ForwardSpec,
+    ) -> anyhow::Result<ForwardInfo> {
+        let id = self.next_forward_id;
+
+        let (listener, bound_port) = match &spec {
+            ForwardSpec::Local { listen, .. } | ForwardSpec::Dynamic { listen } => {
+                let addr = local_listen_addr(listen)?;
+                let listener =
+                    TcpListener::bind(addr).with_context(|| format!("listening on {addr}"))?;
+                listener.set_nonblocking(true)?;
+                let bound_port = listener.local_addr()?.port();
+                (Some(listener), bound_port)
+            }
+            ForwardSpec::Remote { listen, .. } => {
+                let bound_port = sess
+                    .listen_forward(id, listen.host.as_deref(), listen.port)
+                    .with_context(|| format!("requesting remote listener on {listen}"))?;
+                (None, bound_port)
+            }
+        };
+
+        self.next_forward_id += 1;
+        let info = ForwardInfo {
+            id,
+            spec,
+            bound_port,
+            connections: 0,
+        };
+        log::info!("ssh: forwarding {info}");
+        self.forwards.insert(
+            id,
+            ForwardState {
+                info: info.clone(),
+                listener,
+            },
+        );
+        Ok(info)
+    }
+
+    pub(crate) fn remove_forward(
+        &mut self,
+        sess: &mut SessionWrap,
+        id: ForwardId,
+    ) -> anyhow::Result<()> {
+        let state = self
+            .forwards
+            .remove(&id)
+            .ok_or_else(|| anyhow!("invalid forward id {id}"))?;
+        if let ForwardSpec::Remote { .. } = state.info.spec {
+            sess.cancel_forward(id);
+        }
+        self.pending_remote.retain(|_, (forward, channel)| {
+            if *forward == id {
+                channel.close();
+                false
+            } else {
+                true
+            }
+        });
+
+        let channels: Vec<_> = self
+            .channels
+            .iter()
+            .filter(|(_, info)| info.forward == Some(id))
+            .map(|(channel_id, _)| *channel_id)
+            .collect();
+        for channel_id in channels {
+            if let Some(mut info) = self.channels.remove(&channel_id) {
+                info.channel.close();
+            }
+        }
+        log::info!("ssh: stopped forwarding {}", state.info);
+        Ok(())
+    }
+
+    /// Stops all forwards, closing their connections
+    pub(crate) fn remove_all_forwards(&mut self, sess: &mut SessionWrap) {
+        let ids: Vec<ForwardId> = self.forwards.keys().copied().collect();
+        for id in ids {
+            if let Err(err) = self.remove_forward(sess, id) {
+                log::error!("ssh: removing forward {id}: {err:#}");
+            }
+        }
+    }
+
+    pub(crate) fn list_forwards(&self) -> Vec<ForwardInfo> {
+        let mut forwards: Vec<ForwardInfo> = self
+            .forwards
+            .values()
+            .map(|state| {
+                let mut info = state.info.clone();
+                info.connections = self
+                    .channels
+                    .values()
+                    .filter(|chan| chan.forward == Some(info.id))
+                    .count();
+                info
+            })
+            .collect();
+        forwards.sort_by_key(|info| info.id);
+        forwards
+    }
+
+    /// Returns the listening sockets, so that the request loop
+    /// can wake up when a connection arrives
+    pub(crate) fn forward_listeners(&self) -> impl Iterator<Item = &TcpListener> {
+        self.forwards
+            .values()
+            .filter_map(|state| state.listener.as_ref())
+    }
+
+    /// Accepts any pending connections to our forwards and wires
+    /// them up to ssh channels
+    pub(crate) fn connect_pending_forward_channels(&mut self, sess: &mut SessionWrap) {
+        if self.forwards.is_empty() {
+            return;
+        }
+
+        let mut accepted = vec![];
+        for (id, state) in &self.forwards {
+            if let Some(listener) = &state.listener {
+                loop {
+                    match listener.accept() {
+                        Ok((stream, peer)) => accepted.push((*id, stream, peer)),
+                        Err(err) if err.kind() == std::io::ErrorKind::WouldBlock => break,
+                        Err(err) => {
+                            log::error!("ssh: accept on forward {id}: {err:#}");
+                            break;
+                        }
+                    }
+                }
+            }
+        }
+
+        for (id, stream, peer) in accepted {
+            let spec = self.forwards[&id].info.spec.clone();
+            match spec {
+                ForwardSpec::Local { connect, .. } => {
+                    let host = connect.host.as_deref().unwrap_or("localhost");
+                    sess.set_blocking(true);
+                    let res = self.open_direct_channel(sess, id, stream, peer, host, connect.port);
+                    sess.set_blocking(false);
+                    if let Err(err) = res {
+                        log::error!("ssh: forward {id} to {connect}: {err:#}");
+                    }
+                }
+                ForwardSpec::Dynamic { .. } => {
+                    // The SOCKS negotiation can block for a while, so we
+                    // let another thread take care of it and pick up the
+                    // result in connect_pending_helper_channels
+                    let tx = self.connect_tx.clone();
+                    let wakeup = self.wakeup.clone();
+                    std::thread::spawn(move || match socks_handshake(stream, peer, id) {
+                        Ok(connect) => {
+                            if tx.try_send(ForwardConnect::Socks(connect)).is_ok() {
+                                let _ = wakeup.lock().unwrap().write(b"x");
+                            }
+                        }
+                        Err(err) => log::error!("ssh: socks handshake with {peer}: {err:#}"),
+                    });
+                }
+                ForwardSpec::Remote { .. } => unreachable!(),
+            }
+        }
+
+        self.accept_pending_remote_channels(sess);
+        self.connect_pending_helper_channels(sess);
+    }
+
+    /// Picks up the results of the helper threads
+    fn connect_pending_helper_channels(&mut self, sess: &mut SessionWrap) {
+        loop {
+            match self.connect_rx.try_recv() {
+                Ok(ForwardConnect::Socks(connect)) => self.connect_socks_channel(sess, connect),
+                Ok(ForwardConnect::Remote { channel_id, stream }) => {
+                    self.connect_remote_channel(channel_id, stream)
+                }
+                Err(TryRecvError::Empty) | Err(TryRecvError::Closed) => break,
+            }
+        }
+    }
+
+    fn connect_socks_channel(&mut self, sess: &mut SessionWrap, mut connect: SocksConnect) {
+        if !self.forwards.contains_key(&connect.forward) {
+            // The forward was removed while the client was negotiating
+            return;
+        }
+        let reply = socks_reply(connect.version, true);
+        sess.set_blocking(true);
+        let res = sess
+            .open_direct_tcpip(
+                &connect.host,
+                connect.port,
+                &connect.peer.ip().to_string(),
+                connect.peer.port(),
+            )
+            .and_then(|channel| {
+                let stream = connect.stream.try_clone()?;
+                self.insert_forward_channel(connect.forward, channel, stream, reply)
+            });
+        sess.set_blocking(false);
+        if let Err(err) = res {
+            log::error!(
+                "ssh: socks connect to {}:{}: {:#}",
+                connect.host,
+                connect.port,
+                err
+            );
+            connect
+                .stream
+                .write_all(&socks_reply(connect.version, false))
+                .ok();
+        }
+    }
+
+    fn accept_pending_remote_channels(&mut self, sess: &mut SessionWrap) {
+        while let Some((id, mut channel)) = sess.accept_forward() {
+            let connect = match self.forwards.get(&id).map(|state| &state.info.spec) {
+                Some(ForwardSpec::Remote { connect, .. }) => connect.clone(),
+                _ => {
+                    channel.close();
+                    continue;
+                }
+            };
+            // Connecting can take up to FORWARD_TIMEOUT, so we let
+            // another thread do it and pick up the stream in
+            // connect_pending_helper_channels, holding on to the
+            // channel until then
+            let channel_id = self.next_channel_id;
+            self.next_channel_id += 1;
+            self.pending_remote.insert(channel_id, (id, channel));
+            let tx = self.connect_tx.clone();
+            let wakeup = self.wakeup.clone();
+            std::thread::spawn(move || {
+                let stream = connect_with_timeout(&connect);
+                if tx
+                    .try_send(ForwardConnect::Remote { channel_id, stream })
+                    .is_ok()
+                {
+                    let _ = wakeup.lock().unwrap().write(b"x");
+                }
+            });
+        }
+    }
+
+    fn connect_remote_channel(&mut self, channel_id: ChannelId, stream: anyhow::Result<TcpStream>) {
+        let Some((id, mut channel)) = self.pending_remote.remove(&channel_id) else {
+            // The forward was removed while we were connecting
+            return;
+        };
+        let res = match stream {
+            Ok(stream) => self.insert_forward_channel(id, channel, stream, vec![]),
+            Err(err) => {
+                channel.close();
+                Err(err)
+            }
+        };
+        if let Err(err) = res {
+            log::error!("ssh: remote forward {id}: {err:#}");
+        }
+    }
+
+    fn open_direct_channel(
+        &mut self,
+        sess: &mut SessionWrap,
+        forward: ForwardId,
+        stream: TcpStream,
+        peer: SocketAddr,
+        host: &str,
+        port: u16,
+    ) -> anyhow::Result<()> {
+        let channel = sess.open_direct_tcpip(host, port, &peer.ip().to_string(), peer.port())?;
+        self.insert_forward_channel(forward, channel, stream, vec![])
+    }
+
+    /// Relays data between `stream` and `channel`.  `greeting` is
+    /// sent to the stream before anything that arrives on the channel.
+    fn insert_forward_channel(
+        &mut self,
+        forward: ForwardId,
+        channel: ChannelWrap,
+        stream: TcpStream,
+        greeting: Vec<u8>,
+    ) -> anyhow::Result<()> {
+        stream.set_nonblocking(true)?;
+        self.insert_relay_channel(Some(forward), channel, tcp_stream_to_fd(stream), greeting)
+    }
+
+    /// Relays data between the non-blocking `peer` and `channel`
+    pub(crate) fn insert_relay_channel(
+        &mut self,
+        forward: Option<ForwardId>,
+        channel: ChannelWrap,
+        peer: FileDescriptor,
+        greeting: Vec<u8>,
+    ) -> anyhow::Result<()> {
+        let read_from_peer = peer;
+        let write_to_peer = read_from_peer.try_clone()?;
+
+        let mut to_peer = VecDeque::with_capacity(8192.max(greeting.len()));
+        to_peer.extend(greeting);
+
+        let channel_id = self.next_channel_id;
+        self.next_channel_id += 1;
+        let info = ChannelInfo {
+            channel_id,
+            channel,
+            exit: None,
+            exited: false,
+            forward,
+            descriptors: [
+                DescriptorState {
+                    fd: Some(read_from_peer),
+                    buf: VecDeque::with_capacity(8192),
+                },
+                DescriptorState {
+                    fd: Some(write_to_peer),
+                    buf: to_peer,
+                },
+                DescriptorState {
+                    fd: None,
+                    buf: VecDeque::with_capacity(8192),
+                },
+            ],
+        };
+        self.channels.insert(channel_id, info);
+        Ok(())
+    }
+}
+
+fn local_listen_addr(listen: &ForwardAddr) -> anyhow::Result<SocketAddr> {
+    let host = match listen.host.as_deref() {
+        None | Some("localhost") => return Ok((Ipv4Addr::LOCALHOST, listen.port).into()),
+        Some("*") => return Ok((Ipv4Addr::UNSPECIFIED, listen.port).into()),
+        Some(host) => host,
+    };
+    (host, listen.port)
+        .to_socket_addrs()?
+        .next()
+        .ok_or_else(|| anyhow!("unable to resolve {listen}"))
+}
+
+fn connect_with_timeout(connect: &ForwardAddr) -> anyhow::Result<TcpStream> {
+    let host = connect.host.as_deref().unwrap_or("localhost");
+    let mut last_err = None;
+    for addr in (host, connect.port).to_socket_addrs()? {
+        match TcpStream::connect_timeout(&addr, FORWARD_TIMEOUT) {
+            Ok(stream) => return Ok(stream),
+            Err(err) => last_err = Some(err),
+        }
+    }
+    match last_err {
+        Some(err) => Err(err).with_context(|| format!("connecting to {connect}")),
+        None => anyhow::bail!("unable to resolve {connect}"),
+    }
+}
+
+fn tcp_stream_to_fd(stream: TcpStream) -> FileDescriptor {
+    #[cfg(unix)]
+    {
+        FileDescriptor::new(stream)
+    }
+    #[cfg(windows)]
+    unsafe {
+        use std::os::windows::io::{FromRawSocket, IntoRawSocket};
+        FileDescriptor::from_raw_socket(stream.into_raw_socket())
+    }
+}
+
+/// Negotiates with a SOCKS4, SOCKS4a or SOCKS5 client to learn
+/// where it wants to connect.  Only the CONNECT command and the
+/// "no authentication" method are supported, just like `ssh -D`.
+fn socks_handshake(
+    mut stream: TcpStream,
+    peer: SocketAddr,
+    forward: ForwardId,
+) -> anyhow::Result<SocksConnect> {
+    stream.set_nonblocking(false)?;
+    stream.set_read_timeout(Some(FORWARD_TIMEOUT))?;
+
+    let mut byte = [0u8; 1];
+    stream.read_exact(&mut byte)?;
+    let version = byte[0];
+    let (host, port) = match version {
+        4 => {
+            let mut header = [0u8; 7];
+            stream.read_exact(&mut header)?;
+            let command = header[0];
+            let port = u16::from_be_bytes([header[1], header[2]]);
+            let ip = Ipv4Addr::new(header[3], header[4], header[5], header[6]);
+            // Skip the user id
+            read_nul_terminated(&mut stream)?;
+            if command != 1 {
+                stream.write_all(&socks_reply(4, false))?;
+                anyhow::bail!("unsupported SOCKS4 command {command}");
+            }
+            let octets = ip.octets();
+            let host = if octets[..3] == [0, 0, 0] && octets[3] != 0 {
+                // SOCKS4a: the hostname follows the user id
+                read_nul_terminated(&mut stream)?
+            } else {
+                ip.to_string()
+            };
+            (host, port)
+        }
+        5 => {
+            stream.read_exact(&mut byte)?;
+            let mut methods = vec![0u8; byte[0] as usize];
+            stream.read_exact(&mut methods)?;
+            if !methods.contains(&0) {
+                stream.write_all(&[5, 0xff])?;
+                anyhow::bail!("SOCKS5 client doesn't support unauthenticated access");
+            }
+            stream.write_all(&[5, 0])?;
+
+            let mut header = [0u8; 4];
+            stream.read_exact(&mut header)?;
+            let command = header[1];
+            let host = match header[3] {
+                1 => {
+                    let mut ip = [0u8; 4];
+                    stream.read_exact(&mut ip)?;
+                    IpAddr::from(ip).to_string()
+                }
+                3 => {
+                    stream.read_exact(&mut byte)?;
+                    let mut name = vec![0u8; byte[0] as usize];
+                    stream.read_exact(&mut name)?;
+                    String::from_utf8(name).context("SOCKS5 hostname is not UTF-8")?
+                }
+                4 => {
+                    let mut ip = [0u8; 16];
+                    stream.read_exact(&mut ip)?;
+                    Ipv6Addr::from(ip).to_string()
+                }
+                atyp => anyhow::bail!("unsupported SOCKS5 address type {atyp}"),
+            };
+            let mut port = [0u8; 2];
+            stream.read_exact(&mut port)?;
+            if command != 1 {
+                stream.write_all(&[5, 7, 0, 1, 0, 0, 0, 0, 0, 0])?;
+                anyhow::bail!("unsupported SOCKS5 command {command}");
+            }
+            (host, u16::from_be_bytes(port))
+        }
+        _ => anyhow::bail!("unsupported SOCKS version {version}"),
+    };
+
+    stream.set_read_timeout(None)?;
+    Ok(SocksConnect {
+        forward,
+        stream,
+        peer,
+        host,
+        port,
+        version,
+    })
+}
+
+fn read_nul_terminated(stream: &mut TcpStream) -> anyhow::Result<String> {
+    let mut result = vec![];
+    let mut byte = [0u8; 1];
+    loop {
+        stream.read_exact(&mut byte)?;
+        if byte[0] == 0 {
+            break;
+        }
+        anyhow::ensure!(result.len() < 256, "SOCKS4 string is too long");
+        result.push(byte[0]);
+    }
+    Ok(String::from_utf8(result)?)
+}
+
+fn socks_reply(version: u8, success: bool) -> Vec<u8> {
+    if version == 4 {
+        vec![0, if success { 0x5a } else { 0x5b }, 0, 0, 0, 0, 0, 0]
+    } else {
+        vec![5, if success { 0 } else { 1 }, 0, 1, 0, 0, 0, 0, 0, 0]
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use k9::snapshot;
+
+    #[test]
+    fn parse_ssh_args() {
+        assert_eq!(
+            ForwardSpec::from_ssh_arg(ForwardKind::Local, "8080:localhost:80").unwrap(),
+            ForwardSpec::Local {
+                listen: ForwardAddr {
+                    host: None,
+                    port: 8080
+                },
+                connect: ForwardAddr {
+                    host: Some("localhost".to_string()),
+                    port: 80
+                },
+            }
+        );
+        assert_eq!(
+            ForwardSpec::from_ssh_arg(ForwardKind::Remote, "*:2222:[::1]:22").unwrap(),
+            ForwardSpec::Remote {
+                listen: ForwardAddr {
+                    host: Some("*".to_string()),
+                    port: 2222
+                },
+                connect: ForwardAddr {
+                    host: Some("::1".to_string()),
+                    port: 22
+                },
+            }
+        );
+        assert_eq!(
+            ForwardSpec::from_ssh_arg(ForwardKind::Dynamic, "1080").unwrap(),
+            ForwardSpec::Dynamic {
+                listen: ForwardAddr {
+                    host: None,
+                    port: 1080
+                },
+            }
+        );
+        assert!(ForwardSpec::from_ssh_arg(ForwardKind::Local, "8080").is_err());
+        assert!(ForwardSpec::from_ssh_arg(ForwardKind::Dynamic, "x").is_err());
+    }
+
+    #[test]
+    fn parse_config_values() {
+        let mut config = ConfigMap::new();
+        config.insert(
+            "localforward".to_string(),
+            "127.0.0.1:5432 db.internal:5432\n8000 localhost/8000".to_string(),
+        );
+        config.insert("dynamicforward".to_string(), "1080".to_string());
+        config.insert("remoteforward".to_string(), "bogus".to_string());
+
+        let forwards: Vec<String> = forwards_from_config(&config)
+            .iter()
+            .map(|spec| spec.to_string())
+            .collect();
+        snapshot!(
+            forwards,
+            r#"
+[
+    "L 127.0.0.1:5432 -> db.internal:5432",
+    "L 8000 -> localhost:8000",
+    "D 1080 (socks)",
+]
+"#
+        );
+    }
+}
@@ -0,0 +1,263 @@
+use crate::config::{Config, ConfigMap};
+use crate::session::{Session, SessionEvent};
+use crate::sessioninner::SessionInner;
+use crate::sessionwrap::SessionWrap;
+use anyhow::Context;
+use filedescriptor::{socketpair, FileDescriptor};
+use smol::channel::Sender;
+
+/// One of the hosts listed in a ProxyJump option,
+/// in `[user@]host[:port]` or `ssh://[user@]host[:port]` form
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct JumpHost {
+    pub user: Option<String>,
+    pub host: String,
+    pub port: Option<u16>,
+}
+
+impl std::fmt::Display for JumpHost {
+    fn fmt(&self, fmt: &mut std::fmt::Formatter) -> std::fmt::Result {
+        if let Some(user) = &self.user {
+            write!(fmt, "{}@", user)?;
+        }
+        if self.host.contains(':') {
+            write!(fmt, "[{}]", self.host)?;
+        } else {
+            write!(fmt, "{}", self.host)?;
+        }
+        if let Some(port) = self.port {
+            write!(fmt, ":{}", port)?;
+        }
+        Ok(())
+    }
+}
+
+impl std::str::FromStr for JumpHost {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> anyhow::Result<Self> {
+        let s = s.trim();
+        let s = s.strip_prefix("ssh://").unwrap_or(s);
+        let (user, host_port) = match s.rsplit_once('@') {
+            Some((user, rest)) => (Some(user.to_string()), rest),
+            None => (None, s),
+        };
+
+        let (host, port) = if let Some(bracketed) = host_port.strip_prefix('[') {
+            let (host, rest) = bracketed
+                .split_once(']')
+                .ok_or_else(|| anyhow::anyhow!("unterminated `[` in jump host {}", s))?;
+            match rest.strip_prefix(':') {
+                Some(port) => (host, Some(port)),
+                None if rest.is_empty() => (host, None),
+                None => anyhow::bail!("unexpected `{}` after `]` in jump host {}", rest, s),
+            }
+        } else {
+            match host_port.split_once(':') {
+                Some((host, port)) => (host, Some(port)),
+                None => (host_port, None),
+            }
+        };
+
+        if host.is_empty() {
+            anyhow::bail!("jump host {} has an empty hostname", s);
+        }
+        let port = port
+            .map(|p| {
+                p.parse::<u16>()
+                    .with_context(|| format!("invalid port `{}` in jump host {}", p, s))
+            })
+            .transpose()?;
+
+        Ok(Self {
+            user,
+            host: host.to_string(),
+            port,
+        })
+    }
+}
+
+/// Parses the comma separated list of hosts from a ProxyJump option
+pub(crate) fn parse_proxy_jump(value: &str) -> anyhow::Result<Vec<JumpHost>> {
+    value.split(',').map(|hop| hop.parse()).collect()
+}
+
+impl JumpHost {
+    /// Resolves the ssh config for this hop.  `earlier_hops` are the
+    /// hops that must be traversed in order to reach this one.
+    /// The options that select the ssh implementation and its verbosity
+    /// are inherited from `target`, the config of the host being reached
+    /// through this hop.
+    fn resolve_config(&self, earlier_hops: &[JumpHost], target: &ConfigMap) -> ConfigMap {
+        let mut config = Config::new();
+        config.add_default_config_files();
+        let mut config = config.for_host(&self.host);
+
+        for (k, v) in target {
+            if k.starts_with("shelldone_") {
+                config.insert(k.to_string(), v.to_string());
+            }
+        }
+        if let Some(user) = &self.user {
+            config.insert("user".to_string(), user.to_string());
+        }
+        if let Some(port) = self.port {
+            config.insert("port".to_string(), port.to_string());
+        }
+        if !earlier_hops.is_empty() {
+            config.remove("proxycommand");
+            config.insert(
+                "proxyjump".to_string(),
+                earlier_hops
+                    .iter()
+                    .map(|hop| hop.to_string())
+                    .collect::<Vec<_>>()
+                    .join(","),
+            );
+        }
+        config
+    }
+}
+
+impl SessionInner {
+    /// Connects to `hostname:port` via the hosts listed in `proxy_jump`.
+    /// A session is established with the last of those hosts, which
+    /// itself connects via any hosts that precede it, and a direct-tcpip
+    /// channel through that session is used to carry our own connection.
+    /// Each hop performs its own host verification and authentication;
+    /// the associated prompts are relayed to our own event channel.
+    /// The returned session must be kept alive for as long as the
+    /// returned descriptor is in use.
+    pub(crate) fn connect_via_jump_hosts(
+        &self,
+        proxy_jump: &str,
+        hostname: &str,
+        port: u16,
+    ) -> anyhow::Result<(FileDescriptor, Session)> {
+        let mut hops = parse_proxy_jump(proxy_jump)
+            .with_context(|| format!("parsing ProxyJump {}", proxy_jump))?;
+        let hop = hops
+            .pop()
+            .ok_or_else(|| anyhow::anyhow!("ProxyJump {} lists no hosts", proxy_jump))?;
+
+        let config = hop.resolve_config(&hops, &self.config);
+        self.tx_event
+            .try_send(SessionEvent::Banner(Some(format!(
+                "Connecting to {}:{} via jump host {}",
+                hostname, port, hop
+            ))))
+            .context("notifying user of banner")?;
+
+        let (session, events) = Session::connect(config)?;
+        smol::block_on(async {
+            while let Ok(event) = events.recv().await {
+                match event {
+                    SessionEvent::Authenticated => return Ok(()),
+                    SessionEvent::Error(err) => {
+                        anyhow::bail!("jump host {}: {}", hop, err);
+                    }
+                    SessionEvent::HostVerificationFailed(failed) => {
+                        relay_event(&self.tx_event, SessionEvent::HostVerificationFailed(failed))
+                            .await?;
+                        anyhow::bail!("host key verification failed for jump host {}", hop);
+                    }
+                    event => relay_event(&self.tx_event, event).await?,
+                }
+            }
+            anyhow::bail!("jump host {} closed the session during authentication", hop)
+        })?;
+
+        let fd = smol::block_on(session.open_tunnel(hostname, port)).with_context(|| {
+            format!("connecting to {}:{} via jump host {}", hostname, port, hop)
+        })?;
+        Ok((fd, session))
+    }
+
+    /// Opens a direct-tcpip channel to `host:port` and returns a
+    /// socket through which data can be exchanged with it
+    pub(crate) fn open_tunnel(
+        &mut self,
+        sess: &mut SessionWrap,
+        host: &str,
+        port: u16,
+    ) -> anyhow::Result<FileDescriptor> {
+        sess.set_blocking(true);
+        let channel = sess.open_direct_tcpip(host, port, "127.0.0.1", 0);
+        sess.set_blocking(false);
+        let channel = channel.with_context(|| format!("opening channel to {}:{}", host, port))?;
+
+        let (mut ours, theirs) = socketpair()?;
+        ours.set_non_blocking(true)?;
+        self.insert_relay_channel(None, channel, ours, vec![])?;
+        Ok(theirs)
+    }
+}
+
+async fn relay_event(tx_event: &Sender<SessionEvent>, event: SessionEvent) -> anyhow::Result<()> {
+    tx_event
+        .send(event)
+        .await
+        .context("relaying jump host event to user")
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use k9::snapshot;
+
+    #[test]
+    fn parse_jump_hosts() {
+        snapshot!(
+            parse_proxy_jump("bastion").unwrap(),
+            r#"
+[
+    JumpHost {
+        user: None,
+        host: "bastion",
+        port: None,
+    },
+]
+"#
+        );
+
+        let hops = parse_proxy_jump("admin@bastion:2222,ssh://inner,[fe80::1]:22").unwrap();
+        snapshot!(
+            hops,
+            r#"
+[
+    JumpHost {
+        user: Some(
+            "admin",
+        ),
+        host: "bastion",
+        port: Some(
+            2222,
+        ),
+    },
+    JumpHost {
+        user: None,
+        host: "inner",
+        port: None,
+    },
+    JumpHost {
+        user: None,
+        host: "fe80::1",
+        port: Some(
+            22,
+        ),
+    },
+]
+"#
+        );
+        snapshot!(
+            hops.iter()
+                .map(|h| h.to_string())
+                .collect::<Vec<_>>()
+                .join(","),
+            r#""admin@bastion:2222,inner,[fe80::1]:22""#
+        );
+
+        assert!(parse_proxy_jump("bastion:ssh").is_err());
+        assert!(parse_proxy_jump("admin@").is_err());
+    }
+}
@@ -1,12 +1,13 @@
 use crate::auth::*;
 use crate::config::ConfigMap;
+use crate::forward::{ForwardId, ForwardInfo, ForwardRequest, ForwardSpec};
 use crate::host::*;
 use crate::pty::*;
 use crate::sessioninner::*;
 use crate::sftp::{Sftp, SftpRequest};
 use filedescriptor::{socketpair, FileDescriptor};
 use portable_pty::PtySize;
-use smol::channel::{bounded, Receiver, Sender};
+use smol::channel::{bounded, unbounded, Receiver, Sender};
 use std::collections::HashMap;
 use std::io::Write;
 use std::sync::{Arc, Mutex};
@@ -58,6 +59,8 @@ pub(crate) enum SessionRequest {
     Exec(Exec, Sender<anyhow::Result<ExecResult>>),
     Sftp(SftpRequest),
     SignalChannel(SignalChannel),
+    Forward(ForwardRequest),
+    OpenTunnel(OpenTunnel, Sender<anyhow::Result<FileDescriptor>>),
     SessionDropped,
 }
 
@@ -67,6 +70,12 @@ pub(crate) struct SignalChannel {
     pub signame: &'static str,
 }
 
+#[derive(Debug)]
+pub(crate) struct OpenTunnel {
+    pub host: String,
+    pub port: u16,
+}
+
 #[derive(Debug)]
 pub(crate) struct Exec {
     pub command_line: String,
@@ -108,6 +117,7 @@ impl Session {
         });
 
         let now = Instant::now();
+        let (connect_tx, connect_rx) = unbounded();
 
         let mut inner = SessionInner {
             config,
@@ -123,6 +133,12 @@ impl Session {
             shown_accept_env_error: false,
             last_keep_alive: now,
             keep_alive,
+            forwards: HashMap::new(),
+            next_forward_id: 1,
+            connect_tx,
+            connect_rx,
+            pending_remote: HashMap::new(),
+            wakeup: Arc::clone(&session_sender.pipe),
         };
         std::thread::spawn(move || inner.run());
         Ok((Self { tx: session_sender }, rx_event))
@@ -175,6 +191,61 @@ impl Session {
         Ok(exec)
     }
 
+    /// Starts forwarding ports according to `spec`
+    pub async fn add_forward(&self, spec: ForwardSpec) -> anyhow::Result<ForwardInfo> {
+        let (reply, rx) = bounded(1);
+        self.tx
+            .send(SessionRequest::Forward(ForwardRequest::Add(spec, reply)))
+            .await
+            .map_err(|_| DeadSession)?;
+        rx.recv().await?
+    }
+
+    /// Stops the forward with the specified id, closing any
+    /// connections that are being relayed through it
+    pub async fn remove_forward(&self, id: ForwardId) -> anyhow::Result<()> {
+        let (reply, rx) = bounded(1);
+        self.tx
+            .send(SessionRequest::Forward(ForwardRequest::Remove(id, reply)))
+            .await
+            .map_err(|_| DeadSession)?;
+        rx.recv().await?
+    }
+
+    /// Returns the active forwards, including those that were
+    /// established from LocalForward, RemoteForward and DynamicForward
+    /// in the ssh config
+    pub async fn forwards(&self) -> anyhow::Result<Vec<ForwardInfo>> {
+        let (reply, rx) = bounded(1);
+        self.tx
+            .send(SessionRequest::Forward(ForwardRequest::List(reply)))
+            .await
+            .map_err(|_| DeadSession)?;
+        Ok(rx.recv().await?)
+    }
+
+    /// Opens a direct-tcpip channel to `host:port`, returning a socket
+    /// that relays data to and from it.  This is used to carry the
+    /// connection to the next host when following a ProxyJump.
+    pub(crate) async fn open_tunnel(
+        &self,
+        host: &str,
+        port: u16,
+    ) -> anyhow::Result<FileDescriptor> {
+        let (reply, rx) = bounded(1);
+        self.tx
+            .send(SessionRequest::OpenTunnel(
+                OpenTunnel {
+                    host: host.to_string(),
+                    port,
+                },
+                reply,
+            ))
+            .await
+            .map_err(|_| DeadSession)?;
+        rx.recv().await?
+    }
+
     /// Creates a new reference to the sftp channel for filesystem operations
     ///
     /// ### Note
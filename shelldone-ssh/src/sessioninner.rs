@@ -2,8 +2,9 @@ use crate::channelwrap::ChannelWrap;
 use crate::config::ConfigMap;
 use crate::dirwrap::DirWrap;
 use crate::filewrap::FileWrap;
+use crate::forward::{ForwardConnect, ForwardId, ForwardState};
 use crate::pty::*;
-use crate::session::{Exec, ExecResult, SessionEvent, SessionRequest, SignalChannel};
+use crate::session::{Exec, ExecResult, Session, SessionEvent, SessionRequest, SignalChannel};
 use crate::sessionwrap::SessionWrap;
 use crate::sftp::dir::{Dir, DirId, DirRequest};
 use crate::sftp::file::{File, FileId, FileRequest};
@@ -20,6 +21,7 @@ use socket2::{Domain, Socket, Type};
 use std::collections::{HashMap, VecDeque};
 use std::io::{Read, Write};
 use std::net::ToSocketAddrs;
+use std::sync::{Arc, Mutex};
 use std::time::{Duration, Instant};
 
 #[derive(Debug)]
@@ -33,6 +35,8 @@ pub(crate) struct ChannelInfo {
     pub channel: ChannelWrap,
     pub exit: Option<Sender<ExitStatus>>,
     pub exited: bool,
+    /// Set for channels that relay a connection for a port forward
+    pub forward: Option<ForwardId>,
     pub descriptors: [DescriptorState; 3],
 }
 
@@ -52,6 +56,14 @@ pub(crate) struct SessionInner {
     pub shown_accept_env_error: bool,
     pub last_keep_alive: Instant,
     pub keep_alive: Option<Duration>,
+    pub forwards: HashMap<ForwardId, ForwardState>,
+    pub next_forward_id: ForwardId,
+    pub connect_tx: Sender<ForwardConnect>,
+    pub connect_rx: Receiver<ForwardConnect>,
+    /// Remote forward channels whose local connection is still
+    /// being made by a helper thread
+    pub pending_remote: HashMap<ChannelId, (ForwardId, ChannelWrap)>,
+    pub wakeup: Arc<Mutex<FileDescriptor>>,
 }
 
 impl Drop for SessionInner {
@@ -206,7 +218,7 @@ impl SessionInner {
             sess.set_option(libssh_rs::SshOption::HostKeys(host_key.to_string()))?;
         }
 
-        let (sock, _child) = self.connect_to_host(&hostname, port, verbose)?;
+        let (sock, _proxy) = self.connect_to_host(&hostname, port, verbose)?;
         let raw = {
             #[cfg(unix)]
             {
@@ -288,7 +300,7 @@ impl SessionInner {
             ))))
             .context("notifying user of banner")?;
 
-        let (sock, _child) = self.connect_to_host(&hostname, port, verbose)?;
+        let (sock, _proxy) = self.connect_to_host(&hostname, port, verbose)?;
 
         let mut sess = ssh2::Session::new()?;
         if verbose {
@@ -325,12 +337,15 @@ impl SessionInner {
     /// If proxy_command is set, then we execute that process for ourselves
     /// too, as proxy commands are not supported by libssh2 and are not supported
     /// on Windows in libssh.
+    /// Likewise, ProxyJump is implemented here by tunneling through a
+    /// session with the jump host.  ProxyCommand takes precedence if
+    /// both are set.
     fn connect_to_host(
         &self,
         hostname: &str,
         port: u16,
         verbose: bool,
-    ) -> anyhow::Result<(Socket, Option<KillOnDropChild>)> {
+    ) -> anyhow::Result<(Socket, Option<Proxy>)> {
         match self.config.get("proxycommand").map(|s| s.as_str()) {
             Some("none") | None => {}
             Some(proxy_command) => {
@@ -364,14 +379,45 @@ impl SessionInner {
                         _ => raw,
                     };
 
-                    return Ok((Socket::from_raw_fd(dest), Some(KillOnDropChild(child))));
+                    return Ok((
+                        Socket::from_raw_fd(dest),
+                        Some(Proxy::Command {
+                            _child: KillOnDropChild(child),
+                        }),
+                    ));
                 }
                 #[cfg(windows)]
                 unsafe {
                     use std::os::windows::io::{FromRawSocket, IntoRawSocket};
                     return Ok((
                         Socket::from_raw_socket(a.into_raw_socket()),
-                        Some(KillOnDropChild(child)),
+                        Some(Proxy::Command {
+                            _child: KillOnDropChild(child),
+                        }),
+                    ));
+                }
+            }
+        }
+
+        match self.config.get("proxyjump").map(|s| s.as_str()) {
+            Some("none") | None => {}
+            Some(proxy_jump) => {
+                let (fd, session) = self.connect_via_jump_hosts(proxy_jump, hostname, port)?;
+
+                #[cfg(unix)]
+                unsafe {
+                    use std::os::unix::io::{FromRawFd, IntoRawFd};
+                    return Ok((
+                        Socket::from_raw_fd(fd.into_raw_fd()),
+                        Some(Proxy::Jump { _session: session }),
+                    ));
+                }
+                #[cfg(windows)]
+                unsafe {
+                    use std::os::windows::io::{FromRawSocket, IntoRawSocket};
+                    return Ok((
+                        Socket::from_raw_socket(fd.into_raw_socket()),
+                        Some(Proxy::Jump { _session: session }),
                     ));
                 }
             }
@@ -446,12 +492,15 @@ impl SessionInner {
     fn request_loop(&mut self, sess: &mut SessionWrap) -> anyhow::Result<()> {
         let mut sleep_delay = Duration::from_millis(100);
 
+        self.setup_configured_forwards(sess);
+
         loop {
             self.do_keepalive(sess)?;
             self.tick_io()?;
             self.drain_request_pipe();
             self.dispatch_pending_requests(sess)?;
             self.connect_pending_agent_forward_channels(sess);
+            self.connect_pending_forward_channels(sess);
 
             if self.channels.is_empty() && self.session_was_dropped {
                 log::trace!(
@@ -492,6 +541,15 @@ impl SessionInner {
                     }
                 }
             }
+            let num_channel_fds = mapping.len();
+
+            for listener in self.forward_listeners() {
+                poll_array.push(pollfd {
+                    fd: listener.as_socket_descriptor(),
+                    events: POLLIN,
+                    revents: 0,
+                });
+            }
 
             poll(&mut poll_array, Some(sleep_delay)).context("poll")?;
             sleep_delay += sleep_delay;
@@ -500,7 +558,7 @@ impl SessionInner {
                 if poll.revents != 0 {
                     sleep_delay = Duration::from_millis(100);
                 }
-                if idx == 0 || idx == 1 {
+                if idx == 0 || idx == 1 || idx >= num_channel_fds + 2 {
                     // Dealt with at the top of the loop
                 } else if poll.revents != 0 {
                     let (channel_id, fd_num) = mapping[idx - 2];
@@ -646,7 +704,10 @@ impl SessionInner {
                 sess.set_blocking(true);
                 let res = match req {
                     SessionRequest::SessionDropped => {
+                        // Nobody is left to manage the forwards, and
+                        // their listeners would otherwise keep us alive
                         self.session_was_dropped = true;
+                        self.remove_all_forwards(sess);
                         Ok(true)
                     }
                     SessionRequest::NewPty(newpty, reply) => {
@@ -664,6 +725,12 @@ impl SessionInner {
                     SessionRequest::Exec(exec, reply) => {
                         dispatch(reply, || self.exec(sess, exec), "exec")
                     }
+                    SessionRequest::Forward(req) => self.dispatch_forward_request(sess, req),
+                    SessionRequest::OpenTunnel(tunnel, reply) => dispatch(
+                        reply,
+                        || self.open_tunnel(sess, &tunnel.host, tunnel.port),
+                        "open_tunnel",
+                    ),
                     SessionRequest::SignalChannel(info) => {
                         if let Err(err) = self.signal_channel(&info) {
                             log::error!("{:?} -> error: {:#}", info, err);
@@ -888,6 +955,7 @@ impl SessionInner {
                 channel,
                 exit: None,
                 exited: false,
+                forward: None,
                 descriptors: [
                     DescriptorState {
                         fd: Some(read_from_agent),
@@ -985,6 +1053,7 @@ impl SessionInner {
             channel,
             exit: Some(exit_tx),
             exited: false,
+            forward: None,
             descriptors: [
                 DescriptorState {
                     fd: Some(read_from_stdin),
@@ -1124,6 +1193,13 @@ where
     Ok(true)
 }
 
+/// Keeps whatever is carrying the connection to the remote host
+/// alive for the lifetime of the session
+enum Proxy {
+    Command { _child: KillOnDropChild },
+    Jump { _session: Session },
+}
+
 /// A little helper to ensure the Child process is killed on Drop.
 struct KillOnDropChild(std::process::Child);
 
@@ -1,17 +1,23 @@
 use crate::channelwrap::ChannelWrap;
+use crate::forward::ForwardId;
 use crate::sftpwrap::SftpWrap;
 use filedescriptor::{AsRawSocketDescriptor, SocketDescriptor, POLLIN, POLLOUT};
+use std::collections::HashMap;
 
 #[cfg(feature = "ssh2")]
 pub(crate) struct Ssh2Session {
     pub sess: ssh2::Session,
     pub sftp: Option<SftpWrap>,
+    /// Remote forward listeners; dropping one cancels the forward
+    pub listeners: HashMap<ForwardId, ssh2::Listener>,
 }
 
 #[cfg(feature = "libssh-rs")]
 pub(crate) struct LibSshSession {
     pub sess: libssh_rs::Session,
     pub sftp: Option<SftpWrap>,
+    /// Maps the remote port of a remote forward to its id
+    pub remote_forwards: HashMap<u16, ForwardId>,
 }
 
 pub(crate) enum SessionWrap {
@@ -25,12 +31,20 @@ pub(crate) enum SessionWrap {
 impl SessionWrap {
     #[cfg(feature = "ssh2")]
     pub fn with_ssh2(sess: ssh2::Session) -> Self {
-        Self::Ssh2(Ssh2Session { sess, sftp: None })
+        Self::Ssh2(Ssh2Session {
+            sess,
+            sftp: None,
+            listeners: HashMap::new(),
+        })
     }
 
     #[cfg(feature = "libssh-rs")]
     pub fn with_libssh(sess: libssh_rs::Session) -> Self {
-        Self::LibSsh(LibSshSession { sess, sftp: None })
+        Self::LibSsh(LibSshSession {
+            sess,
+            sftp: None,
+            remote_forwards: HashMap::new(),
+        })
     }
 
     pub fn set_blocking(&mut self, blocking: bool) {
@@ -104,4 +118,106 @@ impl SessionWrap {
             Self::LibSsh(sess) => sess.sess.accept_agent_forward().map(ChannelWrap::LibSsh),
         }
     }
+
+    pub fn open_direct_tcpip(
+        &self,
+        host: &str,
+        port: u16,
+        orig_host: &str,
+        orig_port: u16,
+    ) -> anyhow::Result<ChannelWrap> {
+        match self {
+            #[cfg(feature = "ssh2")]
+            Self::Ssh2(sess) => {
+                let channel =
+                    sess.sess
+                        .channel_direct_tcpip(host, port, Some((orig_host, orig_port)))?;
+                Ok(ChannelWrap::Ssh2(channel))
+            }
+
+            #[cfg(feature = "libssh-rs")]
+            Self::LibSsh(sess) => {
+                let channel = sess.sess.new_channel()?;
+                channel.open_forward(host, port, orig_host, orig_port)?;
+                Ok(ChannelWrap::LibSsh(channel))
+            }
+        }
+    }
+
+    /// Asks the server to listen on the specified address and port,
+    /// returning the port that it is listening on
+    pub fn listen_forward(
+        &mut self,
+        id: ForwardId,
+        host: Option<&str>,
+        port: u16,
+    ) -> anyhow::Result<u16> {
+        match self {
+            #[cfg(feature = "ssh2")]
+            Self::Ssh2(sess) => {
+                let (listener, bound_port) = sess.sess.channel_forward_listen(port, host, None)?;
+                sess.listeners.insert(id, listener);
+                Ok(bound_port)
+            }
+
+            #[cfg(feature = "libssh-rs")]
+            Self::LibSsh(sess) => {
+                let bound_port = match sess.sess.listen_forward(host, port)? {
+                    0 => port,
+                    bound_port => bound_port,
+                };
+                sess.remote_forwards.insert(bound_port, id);
+                Ok(bound_port)
+            }
+        }
+    }
+
+    /// Returns the next connection that the server accepted on
+    /// behalf of one of our remote forwards
+    pub fn accept_forward(&mut self) -> Option<(ForwardId, ChannelWrap)> {
+        match self {
+            #[cfg(feature = "ssh2")]
+            Self::Ssh2(sess) => {
+                for (id, listener) in sess.listeners.iter_mut() {
+                    if let Ok(channel) = listener.accept() {
+                        return Some((*id, ChannelWrap::Ssh2(channel)));
+                    }
+                }
+                None
+            }
+
+            #[cfg(feature = "libssh-rs")]
+            Self::LibSsh(sess) => {
+                if sess.remote_forwards.is_empty() {
+                    return None;
+                }
+                let (port, channel) = sess.sess.accept_forward(std::time::Duration::ZERO).ok()?;
+                match sess.remote_forwards.get(&port) {
+                    Some(id) => Some((*id, ChannelWrap::LibSsh(channel))),
+                    None => {
+                        log::warn!("ssh: rejecting forwarded connection for unknown port {port}");
+                        let _ = channel.close();
+                        None
+                    }
+                }
+            }
+        }
+    }
+
+    pub fn cancel_forward(&mut self, id: ForwardId) {
+        match self {
+            #[cfg(feature = "ssh2")]
+            Self::Ssh2(sess) => {
+                sess.listeners.remove(&id);
+            }
+
+            /* libssh-rs doesn't expose ssh_channel_cancel_forward, so
+             * the server keeps listening, but we refuse any further
+             * connections that it hands to us for this port */
+            #[cfg(feature = "libssh-rs")]
+            Self::LibSsh(sess) => {
+                sess.remote_forwards.retain(|_, fwd| *fwd != id);
+            }
+        }
+    }
 }
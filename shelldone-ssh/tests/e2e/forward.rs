@@ -0,0 +1,162 @@
+use crate::sshd::*;
+use rstest::*;
+use shelldone_ssh::{ForwardKind, ForwardSpec};
+use std::io::{Read, Write};
+use std::net::{TcpListener, TcpStream};
+use std::time::Duration;
+
+/// Starts a server on a free loopback port that echoes back
+/// whatever is sent to it, returning that port
+fn spawn_echo_server() -> u16 {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let port = listener.local_addr().unwrap().port();
+    std::thread::spawn(move || {
+        for stream in listener.incoming() {
+            let mut stream = match stream {
+                Ok(stream) => stream,
+                Err(_) => break,
+            };
+            std::thread::spawn(move || {
+                let mut buf = [0u8; 1024];
+                while let Ok(n) = stream.read(&mut buf) {
+                    if n == 0 || stream.write_all(&buf[..n]).is_err() {
+                        break;
+                    }
+                }
+            });
+        }
+    });
+    port
+}
+
+fn connect(port: u16) -> TcpStream {
+    let stream = TcpStream::connect(("127.0.0.1", port)).unwrap();
+    stream
+        .set_read_timeout(Some(Duration::from_secs(10)))
+        .unwrap();
+    stream
+}
+
+fn assert_echoes(stream: &mut TcpStream, message: &[u8]) {
+    stream.write_all(message).unwrap();
+    let mut buf = vec![0u8; message.len()];
+    stream.read_exact(&mut buf).unwrap();
+    assert_eq!(buf, message);
+}
+
+#[rstest]
+#[cfg_attr(not(any(target_os = "macos", target_os = "linux")), ignore)]
+fn local_forward_should_relay_connections_via_the_remote_host(#[future] session: SessionWithSshd) {
+    if !sshd_available() {
+        return;
+    }
+    smol::block_on(async {
+        let session: SessionWithSshd = session.await;
+        let echo_port = spawn_echo_server();
+
+        let spec =
+            ForwardSpec::from_ssh_arg(ForwardKind::Local, &format!("0:127.0.0.1:{echo_port}"))
+                .unwrap();
+        let info = session.add_forward(spec).await.unwrap();
+        assert_ne!(info.bound_port, 0);
+
+        let mut stream = connect(info.bound_port);
+        assert_echoes(&mut stream, b"hello through -L");
+
+        let forwards = session.forwards().await.unwrap();
+        assert_eq!(forwards.len(), 1);
+        assert_eq!(forwards[0].id, info.id);
+        assert_eq!(forwards[0].connections, 1);
+
+        session.remove_forward(info.id).await.unwrap();
+        assert!(session.forwards().await.unwrap().is_empty());
+        assert!(TcpStream::connect(("127.0.0.1", info.bound_port)).is_err());
+    })
+}
+
+#[rstest]
+#[cfg_attr(not(any(target_os = "macos", target_os = "linux")), ignore)]
+fn remote_forward_should_relay_connections_to_the_local_host(#[future] session: SessionWithSshd) {
+    if !sshd_available() {
+        return;
+    }
+    smol::block_on(async {
+        let session: SessionWithSshd = session.await;
+        let echo_port = spawn_echo_server();
+
+        let spec = ForwardSpec::from_ssh_arg(
+            ForwardKind::Remote,
+            &format!("127.0.0.1:0:127.0.0.1:{echo_port}"),
+        )
+        .unwrap();
+        let info = session.add_forward(spec).await.unwrap();
+        assert_ne!(info.bound_port, 0);
+
+        // The "remote" host is the sshd on loopback, so we can
+        // connect to the port that it is listening on directly
+        let mut stream = connect(info.bound_port);
+        assert_echoes(&mut stream, b"hello through -R");
+    })
+}
+
+#[rstest]
+#[cfg_attr(not(any(target_os = "macos", target_os = "linux")), ignore)]
+fn dynamic_forward_should_act_as_a_socks5_proxy(#[future] session: SessionWithSshd) {
+    if !sshd_available() {
+        return;
+    }
+    smol::block_on(async {
+        let session: SessionWithSshd = session.await;
+        let echo_port = spawn_echo_server();
+
+        let spec = ForwardSpec::from_ssh_arg(ForwardKind::Dynamic, "0").unwrap();
+        let info = session.add_forward(spec).await.unwrap();
+
+        let mut stream = connect(info.bound_port);
+
+        // Greeting: version 5, one method, "no authentication"
+        stream.write_all(&[5, 1, 0]).unwrap();
+        let mut method = [0u8; 2];
+        stream.read_exact(&mut method).unwrap();
+        assert_eq!(method, [5, 0]);
+
+        // CONNECT to 127.0.0.1:echo_port
+        let mut request = vec![5, 1, 0, 1, 127, 0, 0, 1];
+        request.extend_from_slice(&echo_port.to_be_bytes());
+        stream.write_all(&request).unwrap();
+        let mut reply = [0u8; 10];
+        stream.read_exact(&mut reply).unwrap();
+        assert_eq!(reply[..2], [5, 0]);
+
+        assert_echoes(&mut stream, b"hello through -D");
+    })
+}
+
+#[rstest]
+#[cfg_attr(not(any(target_os = "macos", target_os = "linux")), ignore)]
+fn dynamic_forward_should_support_socks4a(#[future] session: SessionWithSshd) {
+    if !sshd_available() {
+        return;
+    }
+    smol::block_on(async {
+        let session: SessionWithSshd = session.await;
+        let echo_port = spawn_echo_server();
+
+        let spec = ForwardSpec::from_ssh_arg(ForwardKind::Dynamic, "127.0.0.1:0").unwrap();
+        let info = session.add_forward(spec).await.unwrap();
+
+        let mut stream = connect(info.bound_port);
+
+        // CONNECT, port, 0.0.0.1 to signal 4a, empty user id, then the hostname
+        let mut request = vec![4, 1];
+        request.extend_from_slice(&echo_port.to_be_bytes());
+        request.extend_from_slice(&[0, 0, 0, 1, 0]);
+        request.extend_from_slice(b"127.0.0.1\0");
+        stream.write_all(&request).unwrap();
+        let mut reply = [0u8; 8];
+        stream.read_exact(&mut reply).unwrap();
+        assert_eq!(reply[1], 0x5a);
+
+        assert_echoes(&mut stream, b"hello through socks4a");
+    })
+}
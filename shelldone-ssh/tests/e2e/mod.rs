@@ -1,2 +1,3 @@
 mod agent_forward;
+mod forward;
 mod sftp;
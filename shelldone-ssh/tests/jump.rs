@@ -0,0 +1,88 @@
+//! Connects to a host via one and then two jump hosts.
+//!
+//! Like `ssh`, the config of each jump host is resolved from the default
+//! ssh config files, so the test points HOME at a generated
+//! `~/.ssh/config`.  That changes the environment of the whole process,
+//! which is why this test lives in a binary of its own.
+#[allow(dead_code)]
+mod sshd;
+
+use assert_fs::prelude::*;
+use assert_fs::TempDir;
+use shelldone_ssh::{Config, Session};
+use sshd::*;
+use std::io::Read;
+
+/// Formats the options for connecting to `sshd` as a `Host` block
+fn host_block(alias: &str, sshd: &Sshd) -> String {
+    let mut block = format!("Host {alias}\n");
+    for (key, value) in sshd.client_config(Config::new()) {
+        block.push_str(&format!("  {key} {value}\n"));
+    }
+    block
+}
+
+/// Returns the number of sessions that `sshd` has authenticated
+fn accepted_logins(sshd: &Sshd) -> usize {
+    std::fs::read_to_string(sshd.tmp.child("sshd.log").path())
+        .unwrap_or_default()
+        .matches("Accepted publickey")
+        .count()
+}
+
+/// Connects to `target` via `proxy_jump` and returns the
+/// `SSH_CONNECTION` that it reports
+async fn ssh_connection_via(target: &Sshd, proxy_jump: &str) -> String {
+    let mut config = Config::new();
+    config.set_option("proxyjump", proxy_jump);
+    let (session, events) =
+        Session::connect(target.client_config(config)).expect("Failed to connect to sshd");
+    authenticate(events).await;
+
+    let mut exec = session
+        .exec("echo $SSH_CONNECTION", None)
+        .await
+        .expect("Failed to run echo");
+    let mut output = String::new();
+    exec.stdout.read_to_string(&mut output).unwrap();
+    output.trim_end().to_string()
+}
+
+#[test]
+#[cfg_attr(not(any(target_os = "macos", target_os = "linux")), ignore)]
+fn connect_via_jump_hosts() {
+    if !sshd_available() {
+        return;
+    }
+    let target = Sshd::spawn(Default::default()).unwrap();
+    let bastion = Sshd::spawn(Default::default()).unwrap();
+    let inner = Sshd::spawn(Default::default()).unwrap();
+
+    let home = TempDir::new().unwrap();
+    home.child(".ssh").create_dir_all().unwrap();
+    home.child(".ssh/config")
+        .write_str(&format!(
+            "{}{}",
+            host_block("bastion", &bastion),
+            host_block("inner", &inner)
+        ))
+        .unwrap();
+    std::env::set_var("HOME", home.path());
+
+    smol::block_on(async {
+        // The target sees the connection arrive at its own port,
+        // having been relayed through the jump hosts
+        let target_port = format!(" {}", target.port);
+
+        let connection = ssh_connection_via(&target, "bastion").await;
+        assert!(connection.ends_with(&target_port), "{connection}");
+        assert_eq!(accepted_logins(&bastion), 1);
+        assert_eq!(accepted_logins(&inner), 0);
+
+        // inner is itself reached via bastion
+        let connection = ssh_connection_via(&target, "bastion,inner").await;
+        assert!(connection.ends_with(&target_port), "{connection}");
+        assert_eq!(accepted_logins(&bastion), 2);
+        assert_eq!(accepted_logins(&inner), 1);
+    })
+}
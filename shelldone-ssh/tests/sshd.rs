@@ -1,7 +1,8 @@
 use assert_fs::prelude::*;
 use assert_fs::TempDir;
 use rstest::*;
-use shelldone_ssh::{Config, Session, SessionEvent};
+use shelldone_ssh::{Config, ConfigMap, Session, SessionEvent};
+use smol::channel::Receiver;
 use std::collections::HashMap;
 use std::io::Result as IoResult;
 use std::path::{Path, PathBuf};
@@ -423,66 +424,67 @@ impl std::ops::DerefMut for SessionWithSshd {
     }
 }
 
-#[fixture]
-/// Stand up an sshd instance and then connect to it and perform authentication
-pub async fn session(#[default(Config::new())] config: Config, sshd: Sshd) -> SessionWithSshd {
-    let port = sshd.port;
-
-    // Do not add the default config files; they take the config of the
-    // user that is running the tests which can vary wildly and have
-    // inappropriate configuration that disrupts the tests.
-    // NO: config.add_default_config_files();
-
-    // Load our config to point to ourselves, using current sshd instance's port,
-    // generated identity file, and host file
-    let mut config = config.for_host("localhost");
-    config.insert("port".to_string(), port.to_string());
-    config.insert("shelldone_ssh_verbose".to_string(), "true".to_string());
-
-    // If libssh-rs is not loaded (but ssh2 is), then we use ssh2 as the backend
-    #[cfg(not(feature = "libssh-rs"))]
-    config.insert("shelldone_ssh_backend".to_string(), "ssh2".to_string());
-
-    config.insert(
-        "identityagent".to_string(),
-        format!("{}", sshd.agent_sock.display()),
-    );
-
-    let username = USERNAME.clone();
-    config.insert("user".to_string(), username);
-    config.insert("identitiesonly".to_string(), "yes".to_string());
-    config.insert(
-        "pubkeyacceptedtypes".to_string(),
-        // Ensure that we have ssh-rsa in the list, as debian9
-        // seems unhappy without it
-        "ssh-rsa,ssh-ed25519,\
-                  rsa-sha2-512,rsa-sha2-256,ecdsa-sha2-nistp521,\
-                  ecdsa-sha2-nistp384,ecdsa-sha2-nistp256"
-            .to_string(),
-    );
-    config.insert(
-        "identityfile".to_string(),
-        sshd.tmp
-            .child("id_rsa")
-            .path()
-            .to_str()
-            .expect("Failed to get string path for id_rsa")
-            .to_string(),
-    );
-    config.insert(
-        "userknownhostsfile".to_string(),
-        sshd.tmp
-            .child("known_hosts")
-            .path()
-            .to_str()
-            .expect("Failed to get string path for known_hosts")
-            .to_string(),
-    );
+impl Sshd {
+    /// Resolves `config` into the options with which to connect to this
+    /// sshd as the current user, with the identity that it authorizes
+    pub fn client_config(&self, config: Config) -> ConfigMap {
+        // Do not add the default config files; they take the config of the
+        // user that is running the tests which can vary wildly and have
+        // inappropriate configuration that disrupts the tests.
+        // NO: config.add_default_config_files();
+
+        // Load our config to point to ourselves, using current sshd instance's port,
+        // generated identity file, and host file
+        let mut config = config.for_host("localhost");
+        config.insert("port".to_string(), self.port.to_string());
+        config.insert("shelldone_ssh_verbose".to_string(), "true".to_string());
+
+        // If libssh-rs is not loaded (but ssh2 is), then we use ssh2 as the backend
+        #[cfg(not(feature = "libssh-rs"))]
+        config.insert("shelldone_ssh_backend".to_string(), "ssh2".to_string());
+
+        config.insert(
+            "identityagent".to_string(),
+            format!("{}", self.agent_sock.display()),
+        );
 
-    // Perform our actual connection
-    let (session, events) = Session::connect(config.clone()).expect("Failed to connect to sshd");
+        let username = USERNAME.clone();
+        config.insert("user".to_string(), username);
+        config.insert("identitiesonly".to_string(), "yes".to_string());
+        config.insert(
+            "pubkeyacceptedtypes".to_string(),
+            // Ensure that we have ssh-rsa in the list, as debian9
+            // seems unhappy without it
+            "ssh-rsa,ssh-ed25519,\
+                      rsa-sha2-512,rsa-sha2-256,ecdsa-sha2-nistp521,\
+                      ecdsa-sha2-nistp384,ecdsa-sha2-nistp256"
+                .to_string(),
+        );
+        config.insert(
+            "identityfile".to_string(),
+            self.tmp
+                .child("id_rsa")
+                .path()
+                .to_str()
+                .expect("Failed to get string path for id_rsa")
+                .to_string(),
+        );
+        config.insert(
+            "userknownhostsfile".to_string(),
+            self.tmp
+                .child("known_hosts")
+                .path()
+                .to_str()
+                .expect("Failed to get string path for known_hosts")
+                .to_string(),
+        );
+        config
+    }
+}
 
-    // Perform automated authentication, assuming that we have a publickey with empty password
+/// Perform automated authentication, assuming that we have a publickey
+/// with empty password.  Any host is verified.
+pub async fn authenticate(events: Receiver<SessionEvent>) {
     while let Ok(event) = events.recv().await {
         match event {
             SessionEvent::Banner(banner) => {
@@ -522,6 +524,16 @@ pub async fn session(#[default(Config::new())] config: Config, sshd: Sshd) -> Se
             SessionEvent::Authenticated => break,
         }
     }
+}
+
+#[fixture]
+/// Stand up an sshd instance and then connect to it and perform authentication
+pub async fn session(#[default(Config::new())] config: Config, sshd: Sshd) -> SessionWithSshd {
+    let config = sshd.client_config(config);
+
+    // Perform our actual connection
+    let (session, events) = Session::connect(config).expect("Failed to connect to sshd");
+    authenticate(events).await;
 
     SessionWithSshd {
         session,
@@ -1,10 +1,11 @@
 #![cfg(all(not(target_os = "macos"), not(windows)))]
 //! See <https://developer.gnome.org/notification-spec/>
 
-use crate::ToastNotification;
+use crate::{ToastNotification, Urgency};
 use futures_util::stream::{abortable, StreamExt};
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
+use std::sync::Mutex;
 use zbus::proxy::SignalStream;
 use zbus::Proxy;
 use zvariant::{Type, Value};
@@ -90,6 +91,10 @@ impl<'a> NotificationsProxy<'a> {
         self.inner.call("Notify", &body).await
     }
 
+    async fn close_notification(&self, nid: u32) -> zbus::Result<()> {
+        self.inner.call("CloseNotification", &(nid,)).await
+    }
+
     async fn receive_action_invoked(&self) -> zbus::Result<SignalStream<'_>> {
         self.inner.receive_signal("ActionInvoked").await
     }
@@ -126,6 +131,18 @@ impl Reason {
     }
 }
 
+/// Maps the tag of a notification to the id that the server assigned
+/// to it, so that it can be replaced or closed
+static TAGGED: Mutex<Option<HashMap<String, u32>>> = Mutex::new(None);
+
+fn tagged_nid(tag: &str) -> Option<u32> {
+    TAGGED
+        .lock()
+        .unwrap()
+        .as_ref()
+        .and_then(|map| map.get(tag).copied())
+}
+
 async fn show_notif_impl(notif: ToastNotification) -> Result<(), Box<dyn std::error::Error>> {
     let connection = zbus::ConnectionBuilder::session()?.build().await?;
 
@@ -149,47 +166,85 @@ async fn show_notif_impl(notif: ToastNotification) -> Result<(), Box<dyn std::er
     }
 
     let mut hints: HashMap<&str, Value<'_>> = HashMap::new();
-    hints.insert("urgency", Value::U8(2 /* Critical */));
-    let actions: &[&str] = if notif.url.is_some() {
-        &["show", "Show"]
-    } else {
-        &[]
-    };
+    hints.insert(
+        "urgency",
+        Value::U8(match notif.urgency {
+            Some(Urgency::Low) => 0,
+            Some(Urgency::Normal) => 1,
+            Some(Urgency::Critical) | None => 2,
+        }),
+    );
+    let mut actions: Vec<&str> = vec![];
+    if notif.url.is_some() {
+        actions.extend_from_slice(&["show", "Show"]);
+    }
+    if notif.on_activate.is_some() {
+        // The "default" action is invoked when the notification
+        // itself is clicked
+        actions.extend_from_slice(&["default", "Activate"]);
+    }
+    let replaces_id = notif.tag.as_deref().and_then(tagged_nid).unwrap_or(0);
     let notification = proxy
         .notify(&NotificationPayload {
             app_name: "shelldone",
-            replaces_id: 0,
+            replaces_id,
             app_icon: "net.shelldone.terminal",
             summary: &notif.title,
             body: &notif.message,
-            actions,
+            actions: &actions,
             hints: &hints,
             expire_timeout: notif.timeout.map(|d| d.as_millis() as _).unwrap_or(0),
         })
         .await?;
 
+    if let Some(tag) = &notif.tag {
+        TAGGED
+            .lock()
+            .unwrap()
+            .get_or_insert_with(HashMap::new)
+            .insert(tag.clone(), notification);
+    }
+
     let (mut invoked_stream, abort_invoked) = abortable(proxy.receive_action_invoked().await?);
     let (mut closed_stream, abort_closed) = abortable(proxy.receive_notification_closed().await?);
 
     futures_util::try_join!(
         async {
             while let Some(message) = invoked_stream.next().await {
-                let (nid, _action_key): (u32, String) = message.body().deserialize()?;
-                if nid == notification {
-                    if let Some(url) = notif.url.as_ref() {
-                        shelldone_open_url::open_url(url);
-                        abort_closed.abort();
-                        break;
+                let (nid, action_key): (u32, String) = message.body().deserialize()?;
+                if nid != notification {
+                    continue;
+                }
+                if action_key == "default" {
+                    if let Some(on_activate) = &notif.on_activate {
+                        on_activate.call();
                     }
+                } else if let Some(url) = notif.url.as_ref() {
+                    shelldone_open_url::open_url(url);
                 }
+                if notif.on_close.is_none() {
+                    abort_closed.abort();
+                }
+                break;
             }
             Ok::<(), zbus::Error>(())
         },
         async {
             while let Some(message) = closed_stream.next().await {
                 let (nid, reason): (u32, u32) = message.body().deserialize()?;
-                let _reason = Reason::new(reason);
+                let reason = Reason::new(reason);
                 if nid == notification {
+                    log::trace!("notification {} closed: {:?}", nid, reason);
+                    if let Some(tag) = &notif.tag {
+                        if let Some(map) = TAGGED.lock().unwrap().as_mut() {
+                            if map.get(tag) == Some(&notification) {
+                                map.remove(tag);
+                            }
+                        }
+                    }
+                    if let Some(on_close) = &notif.on_close {
+                        on_close.call();
+                    }
                     abort_invoked.abort();
                     break;
                 }
@@ -212,3 +267,21 @@ pub fn show_notif(notif: ToastNotification) -> Result<(), Box<dyn std::error::Er
     });
     Ok(())
 }
+
+pub fn close_notif(tag: &str) -> Result<(), Box<dyn std::error::Error>> {
+    let nid = match tagged_nid(tag) {
+        Some(nid) => nid,
+        None => return Ok(()),
+    };
+    std::thread::spawn(move || {
+        let res = async_io::block_on(async move {
+            let connection = zbus::ConnectionBuilder::session()?.build().await?;
+            let proxy = NotificationsProxy::new(&connection).await?;
+            proxy.close_notification(nid).await
+        });
+        if let Err(err) = res {
+            log::error!("while closing notification: {:#}", err);
+        }
+    });
+    Ok(())
+}
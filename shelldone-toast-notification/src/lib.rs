@@ -1,13 +1,55 @@
 mod dbus;
 mod macos;
+mod recorder;
 mod windows;
 
-#[derive(Debug, Clone)]
+pub use recorder::{record_notifications, NotificationRecorder};
+
+/// How prominently a notification should be presented.
+/// Not all platforms are able to express this.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Urgency {
+    Low,
+    Normal,
+    Critical,
+}
+
+/// A function that is called when the user interacts with a notification
+#[derive(Clone)]
+pub struct NotificationCallback(std::sync::Arc<dyn Fn() + Send + Sync>);
+
+impl NotificationCallback {
+    pub fn new<F: Fn() + Send + Sync + 'static>(func: F) -> Self {
+        Self(std::sync::Arc::new(func))
+    }
+
+    pub fn call(&self) {
+        (self.0)()
+    }
+}
+
+impl std::fmt::Debug for NotificationCallback {
+    fn fmt(&self, fmt: &mut std::fmt::Formatter) -> std::fmt::Result {
+        fmt.write_str("NotificationCallback")
+    }
+}
+
+#[derive(Debug, Clone, Default)]
 pub struct ToastNotification {
     pub title: String,
     pub message: String,
     pub url: Option<String>,
     pub timeout: Option<std::time::Duration>,
+    /// None uses the default urgency for the platform
+    pub urgency: Option<Urgency>,
+    /// A notification replaces any notification previously shown
+    /// with the same tag.  The tag can be passed to `close` to
+    /// close the notification.
+    pub tag: Option<String>,
+    /// Called when the notification is clicked
+    pub on_activate: Option<NotificationCallback>,
+    /// Called when the notification is dismissed or closed
+    pub on_close: Option<NotificationCallback>,
 }
 
 impl ToastNotification {
@@ -30,20 +72,39 @@ mod nop {
     pub fn show_notif(_: ToastNotification) -> Result<(), Box<dyn std::error::Error>> {
         Ok(())
     }
+
+    #[allow(dead_code)]
+    pub fn close_notif(_: &str) -> Result<(), Box<dyn std::error::Error>> {
+        Ok(())
+    }
 }
 
 pub fn show(notif: ToastNotification) {
+    let notif = match recorder::record(notif) {
+        Some(notif) => notif,
+        None => return,
+    };
     if let Err(err) = backend::show_notif(notif) {
         log::error!("Failed to show notification: {}", err);
     }
 }
 
+/// Closes the notification that was shown with `tag`
+pub fn close(tag: &str) {
+    if recorder::record_close(tag) {
+        return;
+    }
+    if let Err(err) = backend::close_notif(tag) {
+        log::error!("Failed to close notification: {}", err);
+    }
+}
+
 pub fn persistent_toast_notification_with_click_to_open_url(title: &str, message: &str, url: &str) {
     show(ToastNotification {
         title: title.to_string(),
         message: message.to_string(),
         url: Some(url.to_string()),
-        timeout: None,
+        ..Default::default()
     });
 }
 
@@ -51,8 +112,7 @@ pub fn persistent_toast_notification(title: &str, message: &str) {
     show(ToastNotification {
         title: title.to_string(),
         message: message.to_string(),
-        url: None,
-        timeout: None,
+        ..Default::default()
     });
 }
 
@@ -1,5 +1,5 @@
 #![cfg(target_os = "macos")]
-use crate::ToastNotification;
+use crate::{NotificationCallback, ToastNotification};
 use block2::{Block, RcBlock};
 use objc2::rc::Retained;
 use objc2::runtime::{Bool, NSObject, NSObjectProtocol, ProtocolObject};
@@ -11,7 +11,18 @@ use objc2_user_notifications::{
     UNNotificationPresentationOptions, UNNotificationRequest, UNNotificationResponse,
     UNUserNotificationCenter, UNUserNotificationCenterDelegate,
 };
-use std::sync::{LazyLock, Once};
+use std::collections::HashMap;
+use std::sync::{LazyLock, Mutex, Once};
+
+/// The callbacks of the notifications that are currently shown,
+/// keyed by the notification request identifier
+#[derive(Default)]
+struct Callbacks {
+    on_activate: Option<NotificationCallback>,
+    on_close: Option<NotificationCallback>,
+}
+
+static CALLBACKS: LazyLock<Mutex<HashMap<String, Callbacks>>> = LazyLock::new(Mutex::default);
 
 const NEEDS_SIGN: &str = "Note that the application must be code-signed \
                           for UNUserNotificationCenter to work";
@@ -59,13 +70,29 @@ define_class!(
             response: &UNNotificationResponse,
             completion_handler: &Block<dyn Fn()>,
         ) {
-            let action = response.actionIdentifier();
-            let user_info = response.notification().request().content().userInfo();
+            let action = response.actionIdentifier().to_string();
+            let request = response.notification().request();
+            let identifier = request.identifier().to_string();
+            let user_info = request.content().userInfo();
             let url = user_info.valueForKey(ns_string!("url"));
 
             log::debug!("did_receive_notification -> action={action:?} url={url:?}");
 
-            if let Some(url) = url {
+            if action == "com.apple.UNNotificationDismissActionIdentifier" {
+                let callbacks = CALLBACKS.lock().unwrap().remove(&identifier);
+                if let Some(on_close) = callbacks.and_then(|cb| cb.on_close) {
+                    on_close.call();
+                }
+            } else if action == "com.apple.UNNotificationDefaultActionIdentifier" && url.is_none() {
+                let on_activate = CALLBACKS
+                    .lock()
+                    .unwrap()
+                    .get(&identifier)
+                    .and_then(|cb| cb.on_activate.clone());
+                if let Some(on_activate) = on_activate {
+                    on_activate.call();
+                }
+            } else if let Some(url) = url {
                 if let Ok(url_str) = url.downcast::<NSString>() {
                     shelldone_open_url::open_url(&url_str.to_string());
                 }
@@ -123,7 +150,16 @@ pub fn initialize() {
                 &NSArray::from_slice(&[]),
                 UNNotificationCategoryOptions::CustomDismissAction,
             );
-        CENTER.setNotificationCategories(&NSSet::from_retained_slice(&[show_url_cat]));
+        // Has no actions of its own; used so that we are told
+        // when a notification with an on_close callback is dismissed
+        let tracked_cat =
+            UNNotificationCategory::categoryWithIdentifier_actions_intentIdentifiers_options(
+                ns_string!("TRACKED"),
+                &NSArray::from_slice(&[]),
+                &NSArray::from_slice(&[]),
+                UNNotificationCategoryOptions::CustomDismissAction,
+            );
+        CENTER.setNotificationCategories(&NSSet::from_retained_slice(&[show_url_cat, tracked_cat]));
 
         let delegate = NotifDelegate::new();
         let delegate_proto = ProtocolObject::from_retained(delegate.clone());
@@ -162,9 +198,22 @@ pub fn show_notif(toast: ToastNotification) -> Result<(), Box<dyn std::error::Er
                     .expect("is NSDictionary"),
             );
             notif.setCategoryIdentifier(ns_string!("SHOW_URL_ACTION"));
+        } else if toast.on_activate.is_some() || toast.on_close.is_some() {
+            notif.setCategoryIdentifier(ns_string!("TRACKED"));
         }
 
-        let identifier = uuid::Uuid::new_v4().to_string();
+        // Requests with the same identifier replace each other
+        let identifier = toast
+            .tag
+            .clone()
+            .unwrap_or_else(|| uuid::Uuid::new_v4().to_string());
+        CALLBACKS.lock().unwrap().insert(
+            identifier.clone(),
+            Callbacks {
+                on_activate: toast.on_activate.clone(),
+                on_close: toast.on_close.clone(),
+            },
+        );
         let request = UNNotificationRequest::requestWithIdentifier_content_trigger(
             &NSString::from_str(&identifier),
             &*notif,
@@ -199,3 +248,17 @@ pub fn show_notif(toast: ToastNotification) -> Result<(), Box<dyn std::error::Er
 
     Ok(())
 }
+
+pub fn close_notif(tag: &str) -> Result<(), Box<dyn std::error::Error>> {
+    let callbacks = CALLBACKS.lock().unwrap().remove(tag);
+    unsafe {
+        let ident_array = NSArray::from_retained_slice(&[NSString::from_str(tag)]);
+        CENTER.removeDeliveredNotificationsWithIdentifiers(&ident_array);
+    }
+    // Removing a delivered notification doesn't count as a dismissal,
+    // so we won't hear about it from the delegate
+    if let Some(on_close) = callbacks.and_then(|cb| cb.on_close) {
+        on_close.call();
+    }
+    Ok(())
+}
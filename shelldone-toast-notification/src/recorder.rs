@@ -0,0 +1,147 @@
+//! A backend that records notifications rather than showing them,
+//! so that the routing of notifications can be tested
+use crate::ToastNotification;
+use std::sync::{Mutex, MutexGuard};
+
+#[derive(Default)]
+struct Recording {
+    shown: Vec<ToastNotification>,
+    closed: Vec<String>,
+}
+
+static RECORDING: Mutex<Option<Recording>> = Mutex::new(None);
+/// Serializes the tests that record notifications, as the
+/// recording is global to the process
+static SERIAL: Mutex<()> = Mutex::new(());
+
+/// Captures the notifications that are shown or closed while the
+/// returned recorder is alive, instead of passing them to the system
+pub fn record_notifications() -> NotificationRecorder {
+    let serial = SERIAL.lock().unwrap_or_else(|err| err.into_inner());
+    RECORDING.lock().unwrap().replace(Recording::default());
+    NotificationRecorder { _serial: serial }
+}
+
+pub struct NotificationRecorder {
+    _serial: MutexGuard<'static, ()>,
+}
+
+impl NotificationRecorder {
+    /// Returns the notifications that were shown, oldest first
+    pub fn shown(&self) -> Vec<ToastNotification> {
+        RECORDING
+            .lock()
+            .unwrap()
+            .as_ref()
+            .map(|r| r.shown.clone())
+            .unwrap_or_default()
+    }
+
+    /// Returns the tags of the notifications that were closed
+    pub fn closed(&self) -> Vec<String> {
+        RECORDING
+            .lock()
+            .unwrap()
+            .as_ref()
+            .map(|r| r.closed.clone())
+            .unwrap_or_default()
+    }
+
+    /// Simulates the user clicking on the most recent notification
+    /// with `tag`.  Returns false if there is no such notification.
+    pub fn activate(&self, tag: &str) -> bool {
+        match self.find(tag).and_then(|n| n.on_activate) {
+            Some(cb) => {
+                cb.call();
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Simulates the user dismissing the most recent notification
+    /// with `tag`.  Returns false if there is no such notification.
+    pub fn dismiss(&self, tag: &str) -> bool {
+        match self.find(tag).and_then(|n| n.on_close) {
+            Some(cb) => {
+                cb.call();
+                true
+            }
+            None => false,
+        }
+    }
+
+    fn find(&self, tag: &str) -> Option<ToastNotification> {
+        RECORDING.lock().unwrap().as_ref().and_then(|r| {
+            r.shown
+                .iter()
+                .rev()
+                .find(|n| n.tag.as_deref() == Some(tag))
+                .cloned()
+        })
+    }
+}
+
+impl Drop for NotificationRecorder {
+    fn drop(&mut self) {
+        RECORDING.lock().unwrap().take();
+    }
+}
+
+/// Records `notif` if recording is enabled, otherwise returns it
+/// so that it can be shown
+pub(crate) fn record(notif: ToastNotification) -> Option<ToastNotification> {
+    match RECORDING.lock().unwrap().as_mut() {
+        Some(recording) => {
+            recording.shown.push(notif);
+            None
+        }
+        None => Some(notif),
+    }
+}
+
+/// Returns true if recording is enabled and the close was recorded
+pub(crate) fn record_close(tag: &str) -> bool {
+    match RECORDING.lock().unwrap().as_mut() {
+        Some(recording) => {
+            recording.closed.push(tag.to_string());
+            true
+        }
+        None => false,
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{close, show, NotificationCallback};
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::Arc;
+
+    #[test]
+    fn records_and_activates() {
+        let recorder = record_notifications();
+        let activations = Arc::new(AtomicUsize::new(0));
+        let counter = Arc::clone(&activations);
+
+        show(ToastNotification {
+            title: "hello".to_string(),
+            tag: Some("t".to_string()),
+            on_activate: Some(NotificationCallback::new(move || {
+                counter.fetch_add(1, Ordering::SeqCst);
+            })),
+            ..Default::default()
+        });
+        close("t");
+
+        let shown = recorder.shown();
+        assert_eq!(shown.len(), 1);
+        assert_eq!(shown[0].title, "hello");
+        assert_eq!(recorder.closed(), vec!["t".to_string()]);
+
+        assert!(recorder.activate("t"));
+        assert!(!recorder.dismiss("t"));
+        assert!(!recorder.activate("other"));
+        assert_eq!(activations.load(Ordering::SeqCst), 1);
+    }
+}
@@ -1,6 +1,6 @@
 #![cfg(windows)]
 
-use crate::ToastNotification as TN;
+use crate::{ToastNotification as TN, Urgency};
 use xml::escape::escape_str_pcdata;
 
 use windows::core::{Error as WinError, IInspectable, Interface, HSTRING};
@@ -8,9 +8,12 @@ use windows::Data::Xml::Dom::XmlDocument;
 use windows::Foundation::TypedEventHandler;
 use windows::Win32::Foundation::E_POINTER;
 use windows::UI::Notifications::{
-    ToastActivatedEventArgs, ToastNotification, ToastNotificationManager,
+    ToastActivatedEventArgs, ToastDismissedEventArgs, ToastNotification, ToastNotificationManager,
 };
 
+const APP_ID: &str = "net.shelldone.terminal";
+const GROUP: &str = "shelldone";
+
 fn unwrap_arg<T>(a: &Option<T>) -> Result<&T, WinError> {
     match a {
         Some(t) => Ok(t),
@@ -31,8 +34,20 @@ fn show_notif_impl(toast: TN) -> Result<(), Box<dyn std::error::Error>> {
         ""
     };
 
+    // Clicking on the body of the toast activates it with
+    // the launch arguments
+    let launch = if toast.on_activate.is_some() {
+        r#" launch="activate""#
+    } else {
+        ""
+    };
+    let scenario = match toast.urgency {
+        Some(Urgency::Critical) => r#" scenario="urgent""#,
+        _ => "",
+    };
+
     xml.LoadXml(HSTRING::from(format!(
-        r#"<toast duration="long">
+        r#"<toast duration="long"{}{}>
         <visual>
             <binding template="ToastGeneric">
                 <text>{}</text>
@@ -41,13 +56,21 @@ fn show_notif_impl(toast: TN) -> Result<(), Box<dyn std::error::Error>> {
         </visual>
         {}
     </toast>"#,
+        launch,
+        scenario,
         escape_str_pcdata(&toast.title),
         escape_str_pcdata(&toast.message),
         url_actions
     )))?;
 
     let notif = ToastNotification::CreateToastNotification(xml)?;
+    if let Some(tag) = &toast.tag {
+        // Toasts with the same tag and group replace each other
+        notif.SetTag(&HSTRING::from(tag.as_str()))?;
+        notif.SetGroup(&HSTRING::from(GROUP))?;
+    }
 
+    let on_close = toast.on_close.clone();
     notif.Activated(TypedEventHandler::new(
         move |_: &Option<ToastNotification>, result: &Option<IInspectable>| {
             // let myself = unwrap_arg(myself)?;
@@ -59,27 +82,34 @@ fn show_notif_impl(toast: TN) -> Result<(), Box<dyn std::error::Error>> {
                 if let Some(url) = toast.url.as_ref() {
                     shelldone_open_url::open_url(url);
                 }
+            } else if args == "activate" {
+                if let Some(on_activate) = toast.on_activate.as_ref() {
+                    on_activate.call();
+                }
             }
 
             Ok(())
         },
     ))?;
 
-    /*
-    notif.dismissed(TypedEventHandler::new(|sender, result| {
-        log::info!("dismissed {:?}", result);
-        Ok(())
-    }))?;
+    if let Some(on_close) = on_close {
+        notif.Dismissed(TypedEventHandler::new(
+            move |_: &Option<ToastNotification>, result: &Option<ToastDismissedEventArgs>| {
+                log::debug!("dismissed {:?}", unwrap_arg(result)?.Reason()?);
+                on_close.call();
+                Ok(())
+            },
+        ))?;
+    }
 
+    /*
     notif.failed(TypedEventHandler::new(|sender, result| {
         log::warn!("toasts are disabled {:?}", result);
         Ok(())
     }))?;
     */
 
-    let notifier = ToastNotificationManager::CreateToastNotifierWithId(HSTRING::from(
-        "net.shelldone.terminal",
-    ))?;
+    let notifier = ToastNotificationManager::CreateToastNotifierWithId(HSTRING::from(APP_ID))?;
 
     notifier.Show(&notif)?;
 
@@ -98,3 +128,14 @@ pub fn show_notif(notif: TN) -> Result<(), Box<dyn std::error::Error>> {
 
     Ok(())
 }
+
+pub fn close_notif(tag: &str) -> Result<(), Box<dyn std::error::Error>> {
+    // Removing the toast from the history fires its Dismissed
+    // event with the ApplicationHidden reason
+    ToastNotificationManager::History()?.RemoveGroupedTagWithId(
+        &HSTRING::from(tag),
+        &HSTRING::from(GROUP),
+        &HSTRING::from(APP_ID),
+    )?;
+    Ok(())
+}
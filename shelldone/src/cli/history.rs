@@ -0,0 +1,163 @@
+use crate::cli::CliOutputFormatKind;
+use chrono::{DateTime, Local, Utc};
+use clap::Parser;
+use mux::command_history::{
+    default_history_path, CommandHistory, CommandHistoryMatch, CommandHistoryQuery,
+};
+use mux::pane::PaneId;
+use serde::Serializer as _;
+use tabout::{tabulate_output, Alignment, Column};
+
+#[derive(Debug, Parser, Clone)]
+pub struct History {
+    /// A fuzzy pattern that the commands must match.
+    /// Matches are ranked by how well they match and by how
+    /// frequently and recently the command was run.
+    /// When omitted, the most recent commands are listed first.
+    pattern: Option<String>,
+
+    /// Only list commands that ran in this directory
+    #[arg(long)]
+    cwd: Option<String>,
+
+    /// Only list commands that ran on this host
+    #[arg(long)]
+    host: Option<String>,
+
+    /// Only list commands that ran in this pane
+    #[arg(long)]
+    pane_id: Option<PaneId>,
+
+    /// Only list commands that exited with this status
+    #[arg(long, allow_hyphen_values = true)]
+    exit_code: Option<i32>,
+
+    /// List each command line once, along with the number of times
+    /// that it was run
+    #[arg(long)]
+    unique: bool,
+
+    /// The maximum number of commands to list
+    #[arg(long, default_value = "50")]
+    limit: usize,
+
+    /// Controls the output format.
+    /// "table" and "json" are possible formats.
+    #[arg(long = "format", default_value = "table")]
+    format: CliOutputFormatKind,
+}
+
+impl History {
+    pub fn run(self) -> anyhow::Result<()> {
+        let history = CommandHistory::load(&default_history_path())?;
+        let results = history.search(&CommandHistoryQuery {
+            pattern: self.pattern.unwrap_or_default(),
+            cwd: self.cwd,
+            hostname: self.host,
+            pane_id: self.pane_id,
+            exit_code: self.exit_code,
+            unique: self.unique,
+            limit: Some(self.limit),
+        });
+
+        let out = std::io::stdout();
+        match self.format {
+            CliOutputFormatKind::Json => {
+                let items = results.into_iter().map(CliHistoryResultItem::from);
+                let mut writer = serde_json::Serializer::pretty(out.lock());
+                writer.collect_seq(items)?;
+            }
+            CliOutputFormatKind::Table => {
+                let cols = vec![
+                    Column {
+                        name: "FINISHED".to_string(),
+                        alignment: Alignment::Left,
+                    },
+                    Column {
+                        name: "EXIT".to_string(),
+                        alignment: Alignment::Right,
+                    },
+                    Column {
+                        name: "DURATION".to_string(),
+                        alignment: Alignment::Right,
+                    },
+                    Column {
+                        name: "RUNS".to_string(),
+                        alignment: Alignment::Right,
+                    },
+                    Column {
+                        name: "PANE".to_string(),
+                        alignment: Alignment::Right,
+                    },
+                    Column {
+                        name: "HOST".to_string(),
+                        alignment: Alignment::Left,
+                    },
+                    Column {
+                        name: "CWD".to_string(),
+                        alignment: Alignment::Left,
+                    },
+                    Column {
+                        name: "COMMAND".to_string(),
+                        alignment: Alignment::Left,
+                    },
+                ];
+                let data: Vec<Vec<String>> = results
+                    .into_iter()
+                    .map(|result| {
+                        let entry = result.entry;
+                        let finished: DateTime<Local> = entry.end_time().into();
+                        let duration = std::time::Duration::from_secs(entry.duration().as_secs());
+                        vec![
+                            finished.format("%Y-%m-%d %H:%M:%S").to_string(),
+                            entry
+                                .exit_code
+                                .map(|code| code.to_string())
+                                .unwrap_or_default(),
+                            humantime::format_duration(duration).to_string(),
+                            result.runs.to_string(),
+                            entry.pane_id.to_string(),
+                            entry.hostname,
+                            entry.cwd.unwrap_or_default(),
+                            entry.command.replace('\n', " "),
+                        ]
+                    })
+                    .collect();
+                tabulate_output(&cols, &data, &mut out.lock())?;
+            }
+        }
+        Ok(())
+    }
+}
+
+// This will be serialized to JSON via the 'History' command.
+// As such it is intended to be a stable output format,
+// Thus we need to be careful about the stability of the fields and types
+// herein as they are directly reflected in the output.
+#[derive(serde::Serialize)]
+struct CliHistoryResultItem {
+    command: String,
+    cwd: Option<String>,
+    exit_code: Option<i32>,
+    start_time: DateTime<Utc>,
+    end_time: DateTime<Utc>,
+    hostname: String,
+    pane_id: PaneId,
+    runs: usize,
+}
+
+impl From<CommandHistoryMatch> for CliHistoryResultItem {
+    fn from(result: CommandHistoryMatch) -> CliHistoryResultItem {
+        let entry = result.entry;
+        CliHistoryResultItem {
+            start_time: entry.start_time(),
+            end_time: entry.end_time(),
+            command: entry.command,
+            cwd: entry.cwd,
+            exit_code: entry.exit_code,
+            hostname: entry.hostname,
+            pane_id: entry.pane_id,
+            runs: result.runs,
+        }
+    }
+}
@@ -0,0 +1,117 @@
+use clap::Parser;
+use mux::pane::PaneId;
+use mux::tab::{SplitDirection, SplitRequest, SplitSize, TabId};
+use shelldone_client::client::Client;
+
+#[derive(Debug, Parser, Clone)]
+pub struct JoinPane {
+    /// Specify the pane that should be moved.
+    /// The default is to use the current pane based on the
+    /// environment variable SHELLDONE_PANE.
+    #[arg(long)]
+    pane_id: Option<PaneId>,
+
+    /// Specify the tab into which the pane will be moved.
+    /// The active pane of that tab is split to make room for it.
+    #[arg(long)]
+    tab_id: TabId,
+
+    /// Equivalent to `--right`. If neither this nor any other direction
+    /// is specified, the default is equivalent to `--bottom`.
+    #[arg(long, conflicts_with_all=&["left", "right", "top", "bottom"])]
+    horizontal: bool,
+
+    /// Place the pane on the left of the active pane of the tab
+    #[arg(long, conflicts_with_all=&["right", "top", "bottom"])]
+    left: bool,
+
+    /// Place the pane on the right of the active pane of the tab
+    #[arg(long, conflicts_with_all=&["left", "top", "bottom"])]
+    right: bool,
+
+    /// Place the pane above the active pane of the tab
+    #[arg(long, conflicts_with_all=&["left", "right", "bottom"])]
+    top: bool,
+
+    /// Place the pane below the active pane of the tab
+    #[arg(long, conflicts_with_all=&["left", "right", "top"])]
+    bottom: bool,
+
+    /// Rather than splitting the active pane, split the entire
+    /// tab.
+    #[arg(long)]
+    top_level: bool,
+
+    /// The number of cells that the moved pane should have.
+    /// If omitted, 50% of the available space is used.
+    #[arg(long)]
+    cells: Option<usize>,
+
+    /// Specify the number of cells that the moved pane should
+    /// have, expressed as a percentage of the available space.
+    #[arg(long, conflicts_with = "cells")]
+    percent: Option<u8>,
+}
+
+impl JoinPane {
+    pub async fn run(&self, client: Client) -> anyhow::Result<()> {
+        let move_pane_id = client.resolve_pane_id(self.pane_id).await?;
+
+        let panes = client.list_panes().await?;
+        let mut target = None;
+        'outer: for tabroot in panes.tabs {
+            let mut cursor = tabroot.into_tree().cursor();
+
+            loop {
+                if let Some(entry) = cursor.leaf_mut() {
+                    if entry.tab_id == self.tab_id {
+                        if entry.pane_id == move_pane_id {
+                            anyhow::bail!("pane {move_pane_id} is already in tab {}", self.tab_id);
+                        }
+                        if entry.is_active_pane || target.is_none() {
+                            target.replace(entry.pane_id);
+                        }
+                    } else if target.is_some() {
+                        break 'outer;
+                    }
+                }
+                match cursor.preorder_next() {
+                    Ok(c) => cursor = c,
+                    Err(_) => break,
+                }
+            }
+        }
+        let pane_id = target.ok_or_else(|| anyhow::anyhow!("tab {} not found", self.tab_id))?;
+
+        let direction = if self.left || self.right || self.horizontal {
+            SplitDirection::Horizontal
+        } else {
+            SplitDirection::Vertical
+        };
+        let target_is_second = !(self.left || self.top);
+        let size = match (self.cells, self.percent) {
+            (Some(c), _) => SplitSize::Cells(c),
+            (_, Some(p)) => SplitSize::Percent(p),
+            (None, None) => SplitSize::Percent(50),
+        };
+
+        let joined = client
+            .split_pane(codec::SplitPane {
+                pane_id,
+                split_request: SplitRequest {
+                    direction,
+                    target_is_second,
+                    size,
+                    top_level: self.top_level,
+                },
+                domain: config::keyassignment::SpawnTabDomain::CurrentPaneDomain,
+                command: None,
+                command_dir: None,
+                move_pane_id: Some(move_pane_id),
+            })
+            .await?;
+
+        log::debug!("{:?}", joined);
+        Ok(())
+    }
+}
@@ -69,6 +69,10 @@ impl ListCommand {
                         name: "SIZE".to_string(),
                         alignment: Alignment::Left,
                     },
+                    Column {
+                        name: "MONITOR".to_string(),
+                        alignment: Alignment::Left,
+                    },
                     Column {
                         name: "TITLE".to_string(),
                         alignment: Alignment::Left,
@@ -87,6 +91,7 @@ impl ListCommand {
                             output_item.pane_id.to_string(),
                             output_item.workspace.to_string(),
                             format!("{}x{}", output_item.size.cols, output_item.size.rows),
+                            output_item.monitor_summary(),
                             output_item.title.to_string(),
                             output_item.cwd.to_string(),
                         ]
@@ -139,6 +144,11 @@ struct CliListResultItem {
     is_active: bool,
     is_zoomed: bool,
     tty_name: Option<String>,
+    monitor_activity: bool,
+    monitor_silence_secs: Option<u64>,
+    /// The monitor that alerted while the pane was not focused,
+    /// if it has not been focused since
+    monitor_alert: Option<String>,
 }
 
 impl CliListResultItem {
@@ -157,6 +167,8 @@ impl CliListResultItem {
             is_active_pane,
             is_zoomed_pane,
             tty_name,
+            monitors,
+            monitor_alert,
             size:
                 TerminalSize {
                     rows,
@@ -197,6 +209,29 @@ impl CliListResultItem {
             is_active: is_active_pane,
             is_zoomed: is_zoomed_pane,
             tty_name,
+            monitor_activity: monitors.activity,
+            monitor_silence_secs: monitors.silence.map(|interval| interval.as_secs()),
+            monitor_alert: monitor_alert.map(|alert| alert.as_str().to_string()),
+        }
+    }
+
+    /// Describes the monitors of the pane for the table format,
+    /// marking the monitor that has a pending alert with a `!`
+    fn monitor_summary(&self) -> String {
+        let mark = |name: &str| {
+            if self.monitor_alert.as_deref() == Some(name) {
+                format!("{name}!")
+            } else {
+                name.to_string()
+            }
+        };
+        let mut monitors = vec![];
+        if self.monitor_activity {
+            monitors.push(mark("activity"));
+        }
+        if let Some(secs) = self.monitor_silence_secs {
+            monitors.push(format!("{}={secs}s", mark("silence")));
         }
+        monitors.join(",")
     }
 }
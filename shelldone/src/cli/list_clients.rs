@@ -57,6 +57,10 @@ impl ListClientsCommand {
                         name: "FOCUS".to_string(),
                         alignment: Alignment::Right,
                     },
+                    Column {
+                        name: "GUEST".to_string(),
+                        alignment: Alignment::Left,
+                    },
                     Column {
                         name: "SSH_AUTH_SOCK".to_string(),
                         alignment: Alignment::Left,
@@ -99,6 +103,16 @@ impl ListClientsCommand {
                         info.focused_pane_id
                             .map(|id| id.to_string())
                             .unwrap_or_else(String::new),
+                        info.guest
+                            .as_ref()
+                            .map(|guest| {
+                                if guest.interactive {
+                                    format!("{} (interactive)", guest.share_id)
+                                } else {
+                                    guest.share_id.clone()
+                                }
+                            })
+                            .unwrap_or_default(),
                         info.client_id
                             .ssh_auth_sock
                             .as_deref()
@@ -128,6 +142,8 @@ struct CliListClientsResultItem {
     workspace: String,
     focused_pane_id: Option<mux::pane::PaneId>,
     ssh_auth_sock: Option<String>,
+    /// The id of the share, when the client is a guest
+    guest_share_id: Option<String>,
 }
 
 impl From<mux::client::ClientInfo> for CliListClientsResultItem {
@@ -140,6 +156,7 @@ impl From<mux::client::ClientInfo> for CliListClientsResultItem {
             active_workspace,
             focused_pane_id,
             client_id,
+            guest,
             ..
         } = client_info;
 
@@ -165,6 +182,7 @@ impl From<mux::client::ClientInfo> for CliListClientsResultItem {
             workspace: active_workspace.as_deref().unwrap_or("").to_string(),
             focused_pane_id,
             ssh_auth_sock: ssh_auth_sock.as_ref().map(|s| s.to_string()),
+            guest_share_id: guest.map(|guest| guest.share_id),
         }
     }
 }
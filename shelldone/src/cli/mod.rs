@@ -1,5 +1,5 @@
 use anyhow::anyhow;
-use clap::Parser;
+use clap::{Args, Parser};
 use shelldone_client::client::Client;
 use std::env;
 use std::ffi::OsString;
@@ -11,17 +11,24 @@ mod adjust_pane_size;
 pub mod agent;
 mod get_pane_direction;
 mod get_text;
+mod history;
+mod join_pane;
 mod kill_pane;
 mod list;
 mod list_clients;
+mod monitor_pane;
 mod move_pane_to_new_tab;
+mod move_tab_to_window;
 mod proxy;
 mod rename_workspace;
 mod send_text;
 mod set_tab_title;
 mod set_window_title;
+mod share;
 mod spawn_command;
 mod split_pane;
+mod ssh_forward;
+mod swap_panes;
 mod tls_creds;
 mod zoom_pane;
 
@@ -52,8 +59,9 @@ struct CliOutputFormat {
     format: CliOutputFormatKind,
 }
 
-#[derive(Debug, Parser, Clone)]
-pub struct CliCommand {
+/// How to find the mux server or gui instance to control
+#[derive(Debug, Args, Clone)]
+pub struct ConnectOptions {
     /// Don't automatically start the server
     #[arg(long = "no-auto-start")]
     no_auto_start: bool,
@@ -70,6 +78,29 @@ pub struct CliCommand {
     /// the correct gui instance.
     #[arg(long = "class")]
     class: Option<String>,
+}
+
+impl ConnectOptions {
+    pub fn connect(&self) -> anyhow::Result<Client> {
+        let mut ui = mux::connui::ConnectionUI::new_headless();
+        let initial = true;
+
+        Client::new_default_unix_domain(
+            initial,
+            &mut ui,
+            self.no_auto_start,
+            self.prefer_mux,
+            self.class
+                .as_deref()
+                .unwrap_or(shelldone_gui_subcommands::DEFAULT_WINDOW_CLASS),
+        )
+    }
+}
+
+#[derive(Debug, Parser, Clone)]
+pub struct CliCommand {
+    #[command(flatten)]
+    connect: ConnectOptions,
 
     #[command(subcommand)]
     sub: CliSubCommand,
@@ -96,6 +127,25 @@ enum CliSubCommand {
     )]
     MovePaneToNewTab(move_pane_to_new_tab::MovePaneToNewTab),
 
+    /// Move a pane out of its tab and into a split in another tab
+    #[command(name = "join-pane", rename_all = "kebab")]
+    JoinPane(join_pane::JoinPane),
+
+    /// Swap the positions of two panes, which may be in
+    /// different tabs or windows
+    #[command(name = "swap-panes", rename_all = "kebab")]
+    SwapPanes(swap_panes::SwapPanes),
+
+    /// Move a tab into another window, or into a new window.
+    /// Outputs the window-id of the destination window on success
+    #[command(name = "move-tab-to-window", rename_all = "kebab")]
+    MoveTabToWindow(move_tab_to_window::MoveTabToWindow),
+
+    /// List, add or remove the port forwards of an ssh domain.
+    /// The active forwards are listed after any change is made
+    #[command(name = "ssh-forward", rename_all = "kebab")]
+    SshForward(ssh_forward::SshForward),
+
     #[command(
         name = "split-pane",
         rename_all = "kebab",
@@ -166,22 +216,38 @@ Outputs the pane-id for the newly created pane on success"
     #[command(name = "zoom-pane", rename_all = "kebab")]
     ZoomPane(zoom_pane::ZoomPane),
 
+    /// Monitor a pane for activity or silence, alerting when a pane
+    /// that is not focused produces output, or when a pane has
+    /// produced no output for a number of seconds
+    #[command(name = "monitor-pane", rename_all = "kebab")]
+    MonitorPane(monitor_pane::MonitorPane),
+
     /// Interact with the agent control plane.
     #[command(name = "agent", rename_all = "kebab")]
     Agent(agent::AgentCommand),
+
+    /// Search the history of the commands that were run at the shell
+    /// prompt of local panes, as recorded via shell integration
+    #[command(name = "history", rename_all = "kebab")]
+    History(history::History),
+
+    /// Share a pane or tab with guests who connect to the `tls_servers`
+    /// listener of the mux server, and manage the shares
+    #[command(name = "share", rename_all = "kebab")]
+    Share(share::Share),
 }
 
 async fn run_cli_async(opts: &crate::Opt, cli: CliCommand) -> anyhow::Result<()> {
-    let CliCommand {
-        no_auto_start,
-        prefer_mux,
-        class,
-        sub,
-    } = cli;
+    let CliCommand { connect, sub } = cli;
 
     if let CliSubCommand::Agent(agent_cmd) = sub {
         return agent::run(agent_cmd).await;
     }
+    // The history is read from the data directory rather than
+    // via the mux, so that it includes every shelldone process
+    if let CliSubCommand::History(cmd) = sub {
+        return cmd.run();
+    }
 
     let endpoint_env = env::var("SHELLDONE_AGENT_ENDPOINT").ok();
     let persona_env = env::var("SHELLDONE_AGENT_PERSONA").ok();
@@ -191,23 +257,16 @@ async fn run_cli_async(opts: &crate::Opt, cli: CliCommand) -> anyhow::Result<()>
         log::warn!("agent handshake failed: {err:?}");
     }
 
-    let mut ui = mux::connui::ConnectionUI::new_headless();
-    let initial = true;
-
-    let client = Client::new_default_unix_domain(
-        initial,
-        &mut ui,
-        no_auto_start,
-        prefer_mux,
-        class
-            .as_deref()
-            .unwrap_or(shelldone_gui_subcommands::DEFAULT_WINDOW_CLASS),
-    )?;
+    let client = connect.connect()?;
 
     match sub {
         CliSubCommand::ListClients(cmd) => cmd.run(client).await,
         CliSubCommand::List(cmd) => cmd.run(client).await,
         CliSubCommand::MovePaneToNewTab(cmd) => cmd.run(client).await,
+        CliSubCommand::JoinPane(cmd) => cmd.run(client).await,
+        CliSubCommand::SwapPanes(cmd) => cmd.run(client).await,
+        CliSubCommand::MoveTabToWindow(cmd) => cmd.run(client).await,
+        CliSubCommand::SshForward(cmd) => cmd.run(client).await,
         CliSubCommand::SplitPane(cmd) => cmd.run(client).await,
         CliSubCommand::SendText(cmd) => cmd.run(client).await,
         CliSubCommand::GetText(cmd) => cmd.run(client).await,
@@ -224,7 +283,9 @@ async fn run_cli_async(opts: &crate::Opt, cli: CliCommand) -> anyhow::Result<()>
         CliSubCommand::SetWindowTitle(cmd) => cmd.run(client).await,
         CliSubCommand::RenameWorkspace(cmd) => cmd.run(client).await,
         CliSubCommand::ZoomPane(cmd) => cmd.run(client).await,
-        CliSubCommand::Agent(_) => unreachable!(),
+        CliSubCommand::MonitorPane(cmd) => cmd.run(client).await,
+        CliSubCommand::Share(cmd) => cmd.run(client, &crate::init_config(opts)?).await,
+        CliSubCommand::Agent(_) | CliSubCommand::History(_) => unreachable!(),
     }
 }
 
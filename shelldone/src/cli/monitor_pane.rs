@@ -0,0 +1,74 @@
+use anyhow::{anyhow, Result};
+use clap::Parser;
+use codec::SetPaneMonitors;
+use mux::pane::PaneId;
+use shelldone_client::client::Client;
+use std::time::Duration;
+
+#[derive(Debug, Parser, Clone)]
+pub struct MonitorPane {
+    /// Specify the target pane.
+    /// The default is to use the current pane based on the
+    /// environment variable SHELLDONE_PANE.
+    #[arg(long)]
+    pane_id: Option<PaneId>,
+
+    /// Alert when the pane produces output while it is not focused
+    #[arg(long, conflicts_with = "no_activity")]
+    activity: bool,
+
+    /// Stop monitoring the pane for activity
+    #[arg(long)]
+    no_activity: bool,
+
+    /// Alert when the pane has produced no output for this many seconds
+    #[arg(long, value_name = "SECONDS", conflicts_with = "no_silence")]
+    silence: Option<u64>,
+
+    /// Stop monitoring the pane for silence
+    #[arg(long)]
+    no_silence: bool,
+}
+
+impl MonitorPane {
+    pub async fn run(&self, client: Client) -> Result<()> {
+        let pane_id = client.resolve_pane_id(self.pane_id).await?;
+        let panes = client.list_panes().await?;
+
+        let mut monitors = None;
+        for tabroot in panes.tabs {
+            let mut cursor = tabroot.into_tree().cursor();
+
+            loop {
+                if let Some(entry) = cursor.leaf_mut() {
+                    if entry.pane_id == pane_id {
+                        monitors.replace(entry.monitors);
+                    }
+                }
+                match cursor.preorder_next() {
+                    Ok(c) => cursor = c,
+                    Err(_) => break,
+                }
+            }
+        }
+        let mut monitors = monitors.ok_or_else(|| anyhow!("unable to resolve pane {pane_id}"))?;
+
+        if self.activity {
+            monitors.activity = true;
+        }
+        if self.no_activity {
+            monitors.activity = false;
+        }
+        if let Some(secs) = self.silence {
+            monitors.silence = Some(Duration::from_secs(secs.max(1)));
+        }
+        if self.no_silence {
+            monitors.silence = None;
+        }
+
+        client
+            .set_pane_monitors(SetPaneMonitors { pane_id, monitors })
+            .await?;
+        Ok(())
+    }
+}
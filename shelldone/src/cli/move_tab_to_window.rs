@@ -0,0 +1,80 @@
+use clap::Parser;
+use mux::pane::PaneId;
+use mux::tab::TabId;
+use mux::window::WindowId;
+use shelldone_client::client::Client;
+
+#[derive(Debug, Parser, Clone)]
+pub struct MoveTabToWindow {
+    /// Specify the tab that should be moved.
+    /// If omitted, the tab containing the pane specified
+    /// by --pane-id is used.
+    #[arg(long)]
+    tab_id: Option<TabId>,
+
+    /// Specify the current pane.
+    /// The default is to use the current pane based on the
+    /// environment variable SHELLDONE_PANE.
+    #[arg(long, conflicts_with = "tab_id")]
+    pane_id: Option<PaneId>,
+
+    /// Specify the window into which the tab will be moved.
+    #[arg(long, required_unless_present = "new_window")]
+    window_id: Option<WindowId>,
+
+    /// Move the tab into a new window.
+    #[arg(long, conflicts_with = "window_id")]
+    new_window: bool,
+
+    /// If creating a new window, override the default workspace name
+    /// with the provided name.  The default is to use the workspace
+    /// of the window that currently contains the tab.
+    #[arg(long)]
+    workspace: Option<String>,
+}
+
+impl MoveTabToWindow {
+    pub async fn run(&self, client: Client) -> anyhow::Result<()> {
+        let tab_id = match self.tab_id {
+            Some(tab_id) => tab_id,
+            None => {
+                let pane_id = client.resolve_pane_id(self.pane_id).await?;
+                let panes = client.list_panes().await?;
+                let mut tab_id = None;
+                'outer_move: for tabroot in panes.tabs {
+                    let mut cursor = tabroot.into_tree().cursor();
+
+                    loop {
+                        if let Some(entry) = cursor.leaf_mut() {
+                            if entry.pane_id == pane_id {
+                                tab_id.replace(entry.tab_id);
+                                break 'outer_move;
+                            }
+                        }
+                        match cursor.preorder_next() {
+                            Ok(c) => cursor = c,
+                            Err(_) => break,
+                        }
+                    }
+                }
+                tab_id.ok_or_else(|| anyhow::anyhow!("unable to resolve tab for pane {pane_id}"))?
+            }
+        };
+
+        let moved = client
+            .move_tab_to_window(codec::MoveTabToWindow {
+                tab_id,
+                window_id: if self.new_window {
+                    None
+                } else {
+                    self.window_id
+                },
+                workspace_for_new_window: self.workspace.clone(),
+            })
+            .await?;
+
+        log::debug!("{:?}", moved);
+        println!("{}", moved.window_id);
+        Ok(())
+    }
+}
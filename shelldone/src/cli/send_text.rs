@@ -44,7 +44,11 @@ impl SendText {
                 .await?;
         } else {
             client
-                .send_paste(codec::SendPaste { pane_id, data })
+                .send_paste(codec::SendPaste {
+                    pane_id,
+                    data,
+                    input_serial: codec::InputSerial::now(),
+                })
                 .await?;
         }
         Ok(())
@@ -0,0 +1,297 @@
+use crate::cli::CliOutputFormatKind;
+use anyhow::{anyhow, Context};
+use chrono::{Local, TimeZone};
+use clap::{Parser, Subcommand};
+use codec::{CreateShare, ShareAccess, ShareInfo, ShareScope, UpdateShare, UpdateShareAction};
+use config::ConfigHandle;
+use mux::pane::PaneId;
+use serde::Serializer as _;
+use shelldone_client::client::Client;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+use tabout::{tabulate_output, Alignment, Column};
+
+#[derive(Debug, Parser, Clone)]
+pub struct Share {
+    #[command(subcommand)]
+    sub: ShareSubCommand,
+}
+
+#[derive(Debug, Subcommand, Clone)]
+enum ShareSubCommand {
+    /// Issue a credential with which a guest can connect to the
+    /// `tls_servers` listener of the mux server and see a pane or tab.
+    /// The credential is written to a directory, and the configuration
+    /// that the guest needs is printed.
+    #[command(name = "create", rename_all = "kebab")]
+    Create(CreateCommand),
+
+    /// List the shares that are valid, and how many guests are
+    /// connected to each of them
+    #[command(name = "list", rename_all = "kebab")]
+    List(ListCommand),
+
+    /// Allow the guests of a share to send input to its panes
+    #[command(name = "grant", rename_all = "kebab")]
+    Grant(ShareIdArg),
+
+    /// Stop the guests of a share from sending input to its panes
+    #[command(name = "read-only", rename_all = "kebab")]
+    ReadOnly(ShareIdArg),
+
+    /// Invalidate the credential of a share and disconnect its guests
+    #[command(name = "revoke", rename_all = "kebab")]
+    Revoke(ShareIdArg),
+}
+
+#[derive(Debug, Parser, Clone)]
+struct CreateCommand {
+    /// Specify the pane to share.
+    /// The default is to use the current pane based on the
+    /// environment variable SHELLDONE_PANE.
+    #[arg(long)]
+    pane_id: Option<PaneId>,
+
+    /// Share the whole tab that contains the pane, rather
+    /// than just the pane
+    #[arg(long)]
+    tab: bool,
+
+    /// Allow the guest to send input to the shared panes.
+    /// The default is to only allow the guest to watch.
+    #[arg(long)]
+    interactive: bool,
+
+    /// How long the credential remains valid, eg: `30m` or `2h`
+    #[arg(long, default_value = "1h", value_parser = humantime::parse_duration)]
+    ttl: Duration,
+
+    /// A label that identifies the guest to you, eg: their name
+    #[arg(long)]
+    label: Option<String>,
+
+    /// The directory into which the credential is written.
+    /// The default is `shelldone-share-ID` in the current directory.
+    #[arg(long, value_hint=clap::ValueHint::DirPath)]
+    output: Option<PathBuf>,
+}
+
+#[derive(Debug, Parser, Clone, Copy)]
+struct ListCommand {
+    /// Controls the output format.
+    /// "table" and "json" are possible formats.
+    #[arg(long = "format", default_value = "table")]
+    format: CliOutputFormatKind,
+}
+
+#[derive(Debug, Parser, Clone)]
+struct ShareIdArg {
+    /// The id of the share, as shown by `shelldone cli share list`
+    share_id: String,
+}
+
+fn format_time(secs: u64) -> String {
+    match Local.timestamp_opt(secs as i64, 0).single() {
+        Some(time) => time.format("%Y-%m-%d %H:%M:%S").to_string(),
+        None => secs.to_string(),
+    }
+}
+
+fn format_scope(scope: ShareScope) -> String {
+    match scope {
+        ShareScope::Pane(pane_id) => format!("pane {pane_id}"),
+        ShareScope::Tab(tab_id) => format!("tab {tab_id}"),
+    }
+}
+
+fn format_access(access: ShareAccess) -> &'static str {
+    match access {
+        ShareAccess::ReadOnly => "read-only",
+        ShareAccess::Interactive => "interactive",
+    }
+}
+
+fn print_shares(shares: Vec<ShareInfo>, format: CliOutputFormatKind) -> anyhow::Result<()> {
+    let out = std::io::stdout();
+    match format {
+        CliOutputFormatKind::Json => {
+            let mut writer = serde_json::Serializer::pretty(out.lock());
+            writer.collect_seq(shares)?;
+        }
+        CliOutputFormatKind::Table => {
+            let cols = vec![
+                Column {
+                    name: "ID".to_string(),
+                    alignment: Alignment::Left,
+                },
+                Column {
+                    name: "SCOPE".to_string(),
+                    alignment: Alignment::Left,
+                },
+                Column {
+                    name: "ACCESS".to_string(),
+                    alignment: Alignment::Left,
+                },
+                Column {
+                    name: "EXPIRES".to_string(),
+                    alignment: Alignment::Left,
+                },
+                Column {
+                    name: "GUESTS".to_string(),
+                    alignment: Alignment::Right,
+                },
+                Column {
+                    name: "LABEL".to_string(),
+                    alignment: Alignment::Left,
+                },
+            ];
+            let data: Vec<Vec<String>> = shares
+                .into_iter()
+                .map(|share| {
+                    vec![
+                        share.share_id,
+                        format_scope(share.scope),
+                        format_access(share.access).to_string(),
+                        format_time(share.expires_at),
+                        share.guests.to_string(),
+                        share.label.unwrap_or_default(),
+                    ]
+                })
+                .collect();
+            tabulate_output(&cols, &data, &mut out.lock())?;
+        }
+    }
+    Ok(())
+}
+
+/// Writes a file that only the current user can read,
+/// as it holds a private key
+fn write_private_file(path: &Path, contents: &str) -> anyhow::Result<()> {
+    let mut options = std::fs::OpenOptions::new();
+    options.write(true).create_new(true);
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::OpenOptionsExt;
+        options.mode(0o600);
+    }
+    let mut file = options
+        .open(path)
+        .with_context(|| format!("creating {}", path.display()))?;
+    std::io::Write::write_all(&mut file, contents.as_bytes())
+        .with_context(|| format!("writing {}", path.display()))
+}
+
+impl CreateCommand {
+    async fn run(&self, client: Client, config: &ConfigHandle) -> anyhow::Result<()> {
+        let pane_id = client.resolve_pane_id(self.pane_id).await?;
+        let scope = if self.tab {
+            let panes = client.list_panes().await?;
+            let mut tab_id = None;
+            for tabroot in panes.tabs {
+                let mut cursor = tabroot.into_tree().cursor();
+                loop {
+                    if let Some(entry) = cursor.leaf_mut() {
+                        if entry.pane_id == pane_id {
+                            tab_id.replace(entry.tab_id);
+                        }
+                    }
+                    match cursor.preorder_next() {
+                        Ok(c) => cursor = c,
+                        Err(_) => break,
+                    }
+                }
+            }
+            ShareScope::Tab(tab_id.ok_or_else(|| anyhow!("unable to resolve pane {pane_id}"))?)
+        } else {
+            ShareScope::Pane(pane_id)
+        };
+
+        let response = client
+            .create_share(CreateShare {
+                scope,
+                access: if self.interactive {
+                    ShareAccess::Interactive
+                } else {
+                    ShareAccess::ReadOnly
+                },
+                ttl_secs: self.ttl.as_secs().max(1),
+                label: self.label.clone(),
+            })
+            .await?;
+        let share = response.share;
+
+        let dir = self
+            .output
+            .clone()
+            .unwrap_or_else(|| PathBuf::from(format!("shelldone-share-{}", share.share_id)));
+        std::fs::create_dir_all(&dir).with_context(|| format!("creating {}", dir.display()))?;
+        write_private_file(&dir.join("guest.pem"), &response.client_cert_pem)?;
+        write_private_file(&dir.join("ca.pem"), &response.ca_cert_pem)?;
+
+        let remote_address = config
+            .tls_servers
+            .first()
+            .map(|server| server.bind_address.clone())
+            .unwrap_or_else(|| "HOST:PORT".to_string());
+
+        println!(
+            "Shared {} {} until {} as share {}.",
+            format_scope(share.scope),
+            format_access(share.access),
+            format_time(share.expires_at),
+            share.share_id
+        );
+        println!(
+            "Give the guest the files in {}, and have them add this to their configuration,\n\
+             replacing the address with one at which they can reach this host, and the\n\
+             file names with the paths at which they saved the files:\n",
+            dir.display()
+        );
+        println!(
+            "config.tls_clients = {{\n  \
+               {{\n    \
+                 name = 'share-{id}',\n    \
+                 remote_address = '{remote_address}',\n    \
+                 pem_cert = 'guest.pem',\n    \
+                 pem_private_key = 'guest.pem',\n    \
+                 pem_root_certs = {{ 'ca.pem' }},\n  \
+               }},\n\
+             }}\n",
+            id = share.share_id,
+        );
+        println!(
+            "They can then connect with `shelldone connect share-{}`.",
+            share.share_id
+        );
+        Ok(())
+    }
+}
+
+impl Share {
+    pub async fn run(&self, client: Client, config: &ConfigHandle) -> anyhow::Result<()> {
+        let (share_id, action) = match &self.sub {
+            ShareSubCommand::Create(cmd) => return cmd.run(client, config).await,
+            ShareSubCommand::List(cmd) => {
+                let response = client.list_shares().await?;
+                return print_shares(response.shares, cmd.format);
+            }
+            ShareSubCommand::Grant(arg) => (
+                &arg.share_id,
+                UpdateShareAction::SetAccess(ShareAccess::Interactive),
+            ),
+            ShareSubCommand::ReadOnly(arg) => (
+                &arg.share_id,
+                UpdateShareAction::SetAccess(ShareAccess::ReadOnly),
+            ),
+            ShareSubCommand::Revoke(arg) => (&arg.share_id, UpdateShareAction::Revoke),
+        };
+
+        let response = client
+            .update_share(UpdateShare {
+                share_id: share_id.clone(),
+                action,
+            })
+            .await?;
+        print_shares(response.shares, CliOutputFormatKind::Table)
+    }
+}
@@ -0,0 +1,100 @@
+use clap::Parser;
+use codec::SshPortForwardAction;
+use shelldone_client::client::Client;
+use tabout::{tabulate_output, Alignment, Column};
+
+#[derive(Debug, Parser, Clone)]
+pub struct SshForward {
+    /// The name of the ssh domain whose port forwards are to
+    /// be listed or changed.
+    #[arg(long)]
+    domain: String,
+
+    /// Forward connections made to a local port to a host and
+    /// port reachable from the remote host.
+    /// Uses the same syntax as `ssh -L`: [bind_address:]port:host:hostport
+    #[arg(short = 'L', long, value_name = "SPEC", group = "action")]
+    local: Option<String>,
+
+    /// Forward connections made to a port on the remote host to
+    /// a host and port reachable from this machine.
+    /// Uses the same syntax as `ssh -R`: [bind_address:]port:host:hostport
+    #[arg(short = 'R', long, value_name = "SPEC", group = "action")]
+    remote: Option<String>,
+
+    /// Run a SOCKS4/SOCKS5 proxy on a local port that makes its
+    /// connections from the remote host.
+    /// Uses the same syntax as `ssh -D`: [bind_address:]port
+    #[arg(short = 'D', long, value_name = "SPEC", group = "action")]
+    dynamic: Option<String>,
+
+    /// Stop the forward with the specified id.
+    #[arg(long, value_name = "ID", group = "action")]
+    remove: Option<usize>,
+}
+
+impl SshForward {
+    pub async fn run(&self, client: Client) -> anyhow::Result<()> {
+        let action = if let Some(spec) = &self.local {
+            SshPortForwardAction::Add {
+                kind: "Local".to_string(),
+                spec: spec.clone(),
+            }
+        } else if let Some(spec) = &self.remote {
+            SshPortForwardAction::Add {
+                kind: "Remote".to_string(),
+                spec: spec.clone(),
+            }
+        } else if let Some(spec) = &self.dynamic {
+            SshPortForwardAction::Add {
+                kind: "Dynamic".to_string(),
+                spec: spec.clone(),
+            }
+        } else if let Some(id) = self.remove {
+            SshPortForwardAction::Remove { id }
+        } else {
+            SshPortForwardAction::List
+        };
+
+        let response = client
+            .ssh_port_forwards(codec::SshPortForwards {
+                domain: self.domain.clone(),
+                action,
+            })
+            .await?;
+
+        let cols = vec![
+            Column {
+                name: "ID".to_string(),
+                alignment: Alignment::Right,
+            },
+            Column {
+                name: "FORWARD".to_string(),
+                alignment: Alignment::Left,
+            },
+            Column {
+                name: "PORT".to_string(),
+                alignment: Alignment::Right,
+            },
+            Column {
+                name: "CONNECTIONS".to_string(),
+                alignment: Alignment::Right,
+            },
+        ];
+        let data: Vec<Vec<String>> = response
+            .forwards
+            .into_iter()
+            .map(|entry| {
+                vec![
+                    entry.id.to_string(),
+                    entry.description,
+                    entry.bound_port.to_string(),
+                    entry.connections.to_string(),
+                ]
+            })
+            .collect();
+
+        tabulate_output(&cols, &data, &mut std::io::stdout().lock())?;
+        Ok(())
+    }
+}
@@ -0,0 +1,31 @@
+use clap::Parser;
+use mux::pane::PaneId;
+use shelldone_client::client::Client;
+
+#[derive(Debug, Parser, Clone)]
+pub struct SwapPanes {
+    /// Specify the pane that should be swapped.
+    /// The default is to use the current pane based on the
+    /// environment variable SHELLDONE_PANE.
+    #[arg(long)]
+    pane_id: Option<PaneId>,
+
+    /// The pane that will trade places with the pane
+    /// specified by --pane-id.
+    /// The two panes may be in different tabs or windows.
+    #[arg(long)]
+    other_pane_id: PaneId,
+}
+
+impl SwapPanes {
+    pub async fn run(&self, client: Client) -> anyhow::Result<()> {
+        let pane_id = client.resolve_pane_id(self.pane_id).await?;
+        client
+            .swap_panes(codec::SwapPanes {
+                pane_id,
+                other_pane_id: self.other_pane_id,
+            })
+            .await?;
+        Ok(())
+    }
+}
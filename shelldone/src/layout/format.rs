@@ -0,0 +1,865 @@
+//! The declarative workspace layout format.
+//!
+//! A layout file describes the windows of a workspace, the tabs within
+//! them, and the tree of splits within each tab.  The same structure is
+//! accepted as YAML or TOML; the format is chosen by the file extension.
+//!
+//! A split lists its panes along with their relative sizes, so a tab with
+//! an editor taking two thirds of the width and a pair of stacked shells
+//! next to it reads:
+//!
+//! ```yaml
+//! windows:
+//!   - tabs:
+//!       - title: dev
+//!         split: horizontal
+//!         panes:
+//!           - command: nvim
+//!             ratio: 2
+//!           - split: vertical
+//!             panes:
+//!               - {}
+//!               - command: cargo watch -x test
+//! ```
+//!
+//! Before spawning, a layout is resolved into a [`LayoutPlan`], which has
+//! the inherited settings applied to each pane and the splits expressed
+//! as the sequence of binary splits that the mux performs.
+use anyhow::{anyhow, bail, Context};
+use codec::ListPanesResponse;
+use mux::pane::PaneId;
+use mux::tab::{PaneEntry, PaneNode, SplitDirection};
+use mux::window::WindowId;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LayoutFormat {
+    Yaml,
+    Toml,
+}
+
+impl LayoutFormat {
+    /// Chooses the format based on the extension of `path`
+    pub fn for_path(path: &Path) -> anyhow::Result<Self> {
+        match path.extension().and_then(|ext| ext.to_str()) {
+            Some("yaml") | Some("yml") => Ok(Self::Yaml),
+            Some("toml") => Ok(Self::Toml),
+            _ => bail!(
+                "{}: layout files must have a .yaml, .yml or .toml extension",
+                path.display()
+            ),
+        }
+    }
+}
+
+impl std::str::FromStr for LayoutFormat {
+    type Err = anyhow::Error;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "yaml" => Ok(Self::Yaml),
+            "toml" => Ok(Self::Toml),
+            _ => Err(anyhow!("unknown layout format {s}; use yaml or toml")),
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Default)]
+#[serde(deny_unknown_fields)]
+pub struct WorkspaceLayout {
+    /// The workspace into which the windows are spawned
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub workspace: Option<String>,
+    /// The directory in which panes start.  A relative path is
+    /// relative to the directory that contains the layout file.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub cwd: Option<String>,
+    /// The domain in which panes are spawned
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub domain: Option<String>,
+    /// Environment variables that are set for every pane
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub env: BTreeMap<String, String>,
+    pub windows: Vec<WindowLayout>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Default)]
+#[serde(deny_unknown_fields)]
+pub struct WindowLayout {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub title: Option<String>,
+    pub tabs: Vec<LayoutNode>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum SplitAxis {
+    /// The panes are placed side by side
+    Horizontal,
+    /// The panes are stacked on top of each other
+    Vertical,
+}
+
+impl From<SplitAxis> for SplitDirection {
+    fn from(axis: SplitAxis) -> SplitDirection {
+        match axis {
+            SplitAxis::Horizontal => SplitDirection::Horizontal,
+            SplitAxis::Vertical => SplitDirection::Vertical,
+        }
+    }
+}
+
+impl From<SplitDirection> for SplitAxis {
+    fn from(direction: SplitDirection) -> SplitAxis {
+        match direction {
+            SplitDirection::Horizontal => SplitAxis::Horizontal,
+            SplitDirection::Vertical => SplitAxis::Vertical,
+        }
+    }
+}
+
+/// A command line, either as a single string that is split
+/// using shell quoting rules, or as a list of arguments
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(untagged)]
+pub enum CommandLine {
+    Line(String),
+    Argv(Vec<String>),
+}
+
+impl CommandLine {
+    fn argv(&self) -> anyhow::Result<Vec<String>> {
+        let argv = match self {
+            Self::Line(line) => {
+                shell_words::split(line).with_context(|| format!("parsing command `{line}`"))?
+            }
+            Self::Argv(argv) => argv.clone(),
+        };
+        if argv.is_empty() {
+            bail!("command is empty");
+        }
+        Ok(argv)
+    }
+}
+
+/// Either a pane, or a split of its area into `panes`.
+/// A tab is the root node of its split tree, which is the
+/// only place that `title` may be used.
+/// `cwd`, `domain` and `env` set on a split apply to all of the
+/// panes within it, unless they override them.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Default)]
+#[serde(deny_unknown_fields)]
+pub struct LayoutNode {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub title: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub split: Option<SplitAxis>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub panes: Vec<LayoutNode>,
+    /// The size of this node relative to its siblings in the
+    /// enclosing split.  The default is 1.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub ratio: Option<f64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub cwd: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub domain: Option<String>,
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub env: BTreeMap<String, String>,
+    /// The program to run instead of the default program of the domain
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub command: Option<CommandLine>,
+    /// Focus this pane once the layout has been applied
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub focus: bool,
+}
+
+/// A pane with its inherited settings applied
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct PaneSpec {
+    pub domain: Option<String>,
+    pub cwd: Option<String>,
+    pub argv: Option<Vec<String>>,
+    pub env: BTreeMap<String, String>,
+    pub focus: bool,
+}
+
+/// The binary splits that produce a tab.  The pane that is split
+/// keeps the first part, and a new pane is spawned into the second.
+#[derive(Debug, Clone, PartialEq)]
+pub enum SpawnTree {
+    Pane(PaneSpec),
+    Split {
+        direction: SplitDirection,
+        /// The size of the second part, as a percentage of the
+        /// area that is being split
+        second_percent: u8,
+        first: Box<SpawnTree>,
+        second: Box<SpawnTree>,
+    },
+}
+
+impl SpawnTree {
+    /// The pane that occupies the top left of the tree, which is
+    /// the one that is spawned before the tree is split
+    pub fn first_pane(&self) -> &PaneSpec {
+        match self {
+            Self::Pane(pane) => pane,
+            Self::Split { first, .. } => first.first_pane(),
+        }
+    }
+
+    pub fn pane_count(&self) -> usize {
+        match self {
+            Self::Pane(_) => 1,
+            Self::Split { first, second, .. } => first.pane_count() + second.pane_count(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct TabPlan {
+    pub title: Option<String>,
+    pub tree: SpawnTree,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct WindowPlan {
+    pub title: Option<String>,
+    pub tabs: Vec<TabPlan>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct LayoutPlan {
+    pub workspace: Option<String>,
+    pub windows: Vec<WindowPlan>,
+}
+
+/// The settings that a node passes down to its children
+#[derive(Clone)]
+struct Inherited {
+    cwd: Option<PathBuf>,
+    domain: Option<String>,
+    env: BTreeMap<String, String>,
+}
+
+impl Inherited {
+    fn apply(
+        &self,
+        cwd: &Option<String>,
+        domain: &Option<String>,
+        env: &BTreeMap<String, String>,
+        base_dir: &Path,
+    ) -> Self {
+        let mut result = self.clone();
+        if let Some(cwd) = cwd {
+            let parent = self.cwd.as_deref().unwrap_or(base_dir);
+            result.cwd.replace(resolve_dir(parent, cwd));
+        }
+        if let Some(domain) = domain {
+            result.domain.replace(domain.clone());
+        }
+        for (k, v) in env {
+            result.env.insert(k.clone(), v.clone());
+        }
+        result
+    }
+}
+
+/// Resolves `dir` relative to `parent`, expanding a leading `~`
+fn resolve_dir(parent: &Path, dir: &str) -> PathBuf {
+    if dir == "~" {
+        return config::HOME_DIR.clone();
+    }
+    if let Some(rest) = dir.strip_prefix("~/") {
+        return config::HOME_DIR.join(rest);
+    }
+    parent.join(dir)
+}
+
+/// Abbreviates paths under the home directory with `~`, so that
+/// exported layouts can be shared with other users
+fn abbreviate_home(path: &str) -> String {
+    match Path::new(path).strip_prefix(&*config::HOME_DIR) {
+        Ok(rest) if rest.as_os_str().is_empty() => "~".to_string(),
+        Ok(rest) => format!("~/{}", rest.display()),
+        Err(_) => path.to_string(),
+    }
+}
+
+impl LayoutNode {
+    fn is_split(&self) -> bool {
+        self.split.is_some() || !self.panes.is_empty()
+    }
+
+    fn validate(
+        &self,
+        location: &str,
+        is_tab: bool,
+        focus_count: &mut usize,
+    ) -> anyhow::Result<()> {
+        if self.title.is_some() && !is_tab {
+            bail!("{location}: only tabs may have a title");
+        }
+        if let Some(ratio) = self.ratio {
+            if !ratio.is_finite() || ratio <= 0.0 {
+                bail!("{location}: ratio must be a positive number, not {ratio}");
+            }
+        }
+        if self.is_split() {
+            if self.split.is_none() {
+                bail!("{location}: `panes` requires `split` to be horizontal or vertical");
+            }
+            if self.panes.is_empty() {
+                bail!("{location}: a split must have at least one pane");
+            }
+            if self.command.is_some() {
+                bail!("{location}: a split cannot have a command; set it on one of its panes");
+            }
+            if self.focus {
+                bail!("{location}: a split cannot be focused; set focus on one of its panes");
+            }
+            for (idx, pane) in self.panes.iter().enumerate() {
+                pane.validate(&format!("{location}.panes[{idx}]"), false, focus_count)?;
+            }
+        } else {
+            if let Some(command) = &self.command {
+                command
+                    .argv()
+                    .with_context(|| format!("{location}: invalid command"))?;
+            }
+            if self.focus {
+                *focus_count += 1;
+            }
+        }
+        Ok(())
+    }
+
+    fn ratio(&self) -> f64 {
+        self.ratio.unwrap_or(1.0)
+    }
+
+    fn plan(&self, inherited: &Inherited, base_dir: &Path) -> anyhow::Result<SpawnTree> {
+        let inherited = inherited.apply(&self.cwd, &self.domain, &self.env, base_dir);
+        match self.split {
+            None => Ok(SpawnTree::Pane(PaneSpec {
+                domain: inherited.domain,
+                cwd: inherited.cwd.map(|cwd| cwd.to_string_lossy().into_owned()),
+                argv: self.command.as_ref().map(|c| c.argv()).transpose()?,
+                env: inherited.env,
+                focus: self.focus,
+            })),
+            Some(axis) => Self::plan_panes(&self.panes, axis.into(), &inherited, base_dir),
+        }
+    }
+
+    /// The mux splits a pane in two, so a split with more than two
+    /// panes is produced by splitting off the remainder repeatedly
+    fn plan_panes(
+        panes: &[LayoutNode],
+        direction: SplitDirection,
+        inherited: &Inherited,
+        base_dir: &Path,
+    ) -> anyhow::Result<SpawnTree> {
+        let first = panes[0].plan(inherited, base_dir)?;
+        if panes.len() == 1 {
+            return Ok(first);
+        }
+        let rest = &panes[1..];
+        let rest_ratio: f64 = rest.iter().map(|pane| pane.ratio()).sum();
+        let total_ratio = panes[0].ratio() + rest_ratio;
+        let second_percent = (100. * rest_ratio / total_ratio).round().clamp(1., 99.) as u8;
+        Ok(SpawnTree::Split {
+            direction,
+            second_percent,
+            first: Box::new(first),
+            second: Box::new(Self::plan_panes(rest, direction, inherited, base_dir)?),
+        })
+    }
+}
+
+impl WorkspaceLayout {
+    pub fn parse(text: &str, format: LayoutFormat) -> anyhow::Result<Self> {
+        match format {
+            LayoutFormat::Yaml => Ok(serde_yaml::from_str(text)?),
+            LayoutFormat::Toml => Ok(toml::from_str(text)?),
+        }
+    }
+
+    pub fn load(path: &Path) -> anyhow::Result<Self> {
+        let format = LayoutFormat::for_path(path)?;
+        let text =
+            std::fs::read_to_string(path).with_context(|| format!("reading {}", path.display()))?;
+        Self::parse(&text, format).with_context(|| format!("parsing {}", path.display()))
+    }
+
+    pub fn serialize(&self, format: LayoutFormat) -> anyhow::Result<String> {
+        match format {
+            LayoutFormat::Yaml => Ok(serde_yaml::to_string(self)?),
+            LayoutFormat::Toml => Ok(toml::to_string_pretty(self)?),
+        }
+    }
+
+    /// Checks the layout for mistakes that the file format itself
+    /// can't express
+    pub fn validate(&self) -> anyhow::Result<()> {
+        if self.windows.is_empty() {
+            bail!("the layout has no windows");
+        }
+        let mut focus_count = 0;
+        for (window_idx, window) in self.windows.iter().enumerate() {
+            if window.tabs.is_empty() {
+                bail!("windows[{window_idx}] has no tabs");
+            }
+            for (tab_idx, tab) in window.tabs.iter().enumerate() {
+                tab.validate(
+                    &format!("windows[{window_idx}].tabs[{tab_idx}]"),
+                    true,
+                    &mut focus_count,
+                )?;
+            }
+        }
+        if focus_count > 1 {
+            bail!("only one pane may have focus, but {focus_count} do");
+        }
+        Ok(())
+    }
+
+    /// Validates the layout and resolves it into the panes and splits that
+    /// need to be spawned.  Relative directories are resolved against
+    /// `base_dir`, which is the directory that contains the layout file.
+    pub fn plan(&self, base_dir: &Path) -> anyhow::Result<LayoutPlan> {
+        self.validate()?;
+        let inherited = Inherited {
+            cwd: None,
+            domain: None,
+            env: BTreeMap::new(),
+        }
+        .apply(&self.cwd, &self.domain, &self.env, base_dir);
+
+        let mut windows = vec![];
+        for window in &self.windows {
+            let mut tabs = vec![];
+            for tab in &window.tabs {
+                tabs.push(TabPlan {
+                    title: tab.title.clone(),
+                    tree: tab.plan(&inherited, base_dir)?,
+                });
+            }
+            windows.push(WindowPlan {
+                title: window.title.clone(),
+                tabs,
+            });
+        }
+        Ok(LayoutPlan {
+            workspace: self.workspace.clone(),
+            windows,
+        })
+    }
+
+    /// Captures the windows of `workspace`, as reported by the mux.
+    /// Commands and environment variables aren't reported by the mux,
+    /// so the panes of the exported layout run the default program.
+    /// The `focused` pane is marked with `focus` when it is part of the
+    /// workspace; otherwise the active pane of the active tab of its
+    /// first window is.
+    pub fn from_panes(panes: ListPanesResponse, workspace: &str, focused: Option<PaneId>) -> Self {
+        let focused = {
+            let entries: Vec<&PaneEntry> = pane_entries(&panes)
+                .into_iter()
+                .filter(|entry| entry.workspace == workspace)
+                .collect();
+            entries
+                .iter()
+                .find(|entry| Some(entry.pane_id) == focused)
+                .or_else(|| {
+                    entries
+                        .iter()
+                        .find(|entry| entry.is_active_tab && entry.is_active_pane)
+                })
+                .map(|entry| entry.pane_id)
+        };
+
+        let ListPanesResponse {
+            tabs,
+            tab_titles,
+            window_titles,
+        } = panes;
+
+        let mut windows: Vec<(WindowId, WindowLayout)> = vec![];
+        for (tab, tab_title) in tabs.into_iter().zip(tab_titles.into_iter()) {
+            let window_id = match first_entry(&tab) {
+                Some(entry) if entry.workspace == workspace => entry.window_id,
+                _ => continue,
+            };
+            let mut node = match export_node(tab, focused) {
+                Some(node) => node,
+                None => continue,
+            };
+            if !tab_title.is_empty() {
+                node.title.replace(tab_title);
+            }
+            match windows.iter_mut().find(|(id, _)| *id == window_id) {
+                Some((_, window)) => window.tabs.push(node),
+                None => windows.push((
+                    window_id,
+                    WindowLayout {
+                        title: window_titles
+                            .get(&window_id)
+                            .filter(|title| !title.is_empty())
+                            .cloned(),
+                        tabs: vec![node],
+                    },
+                )),
+            }
+        }
+
+        Self {
+            workspace: Some(workspace.to_string()),
+            windows: windows.into_iter().map(|(_, window)| window).collect(),
+            ..Default::default()
+        }
+    }
+}
+
+/// Returns the panes of every tab reported by the mux
+pub fn pane_entries(panes: &ListPanesResponse) -> Vec<&PaneEntry> {
+    fn collect<'a>(node: &'a PaneNode, entries: &mut Vec<&'a PaneEntry>) {
+        match node {
+            PaneNode::Empty => {}
+            PaneNode::Leaf(entry) => entries.push(entry),
+            PaneNode::Split { left, right, .. } => {
+                collect(left, entries);
+                collect(right, entries);
+            }
+        }
+    }
+    let mut entries = vec![];
+    for tab in &panes.tabs {
+        collect(tab, &mut entries);
+    }
+    entries
+}
+
+fn first_entry(node: &PaneNode) -> Option<&PaneEntry> {
+    match node {
+        PaneNode::Empty => None,
+        PaneNode::Leaf(entry) => Some(entry),
+        PaneNode::Split { left, right, .. } => first_entry(left).or_else(|| first_entry(right)),
+    }
+}
+
+/// Converts a tab's tree of binary splits into a layout node.  Chains
+/// of splits in the same direction become a single split with several
+/// panes, whose ratios are their sizes in cells.
+fn export_node(node: PaneNode, focused: Option<PaneId>) -> Option<LayoutNode> {
+    match node {
+        PaneNode::Empty => None,
+        PaneNode::Leaf(entry) => Some(LayoutNode {
+            cwd: entry
+                .working_dir
+                .and_then(|url| mux::session::url_to_path(&url.url))
+                .map(|path| abbreviate_home(&path)),
+            domain: entry.domain_name,
+            focus: Some(entry.pane_id) == focused,
+            ..Default::default()
+        }),
+        PaneNode::Split { left, right, node } => {
+            let direction = node.direction;
+            let extent = |size: &shelldone_term::TerminalSize| match direction {
+                SplitDirection::Horizontal => size.cols,
+                SplitDirection::Vertical => size.rows,
+            };
+            let first_ratio = extent(&node.first) as f64;
+            let second_ratio = extent(&node.second) as f64;
+
+            let mut first = match export_node(*left, focused) {
+                Some(first) => first,
+                None => return export_node(*right, focused),
+            };
+            let mut second = match export_node(*right, focused) {
+                Some(second) => second,
+                None => return Some(first),
+            };
+            first.ratio.replace(first_ratio);
+
+            let mut panes = vec![first];
+            if second.split == Some(direction.into()) && second.title.is_none() {
+                panes.append(&mut second.panes);
+            } else {
+                second.ratio.replace(second_ratio);
+                panes.push(second);
+            }
+            Some(LayoutNode {
+                split: Some(direction.into()),
+                panes,
+                ..Default::default()
+            })
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    const DEV_YAML: &str = r#"
+workspace: project
+cwd: src
+env:
+  RUST_LOG: debug
+windows:
+  - title: editor
+    tabs:
+      - title: dev
+        split: horizontal
+        panes:
+          - command: nvim
+            ratio: 2
+            focus: true
+          - split: vertical
+            cwd: tests
+            panes:
+              - {}
+              - command: ["cargo", "watch", "-x", "test"]
+                env:
+                  RUST_LOG: info
+      - title: logs
+        domain: remote
+        command: tail -f '/var/log/my app.log'
+"#;
+
+    fn pane(cwd: &str, argv: Option<&[&str]>, log: &str, focus: bool) -> SpawnTree {
+        SpawnTree::Pane(PaneSpec {
+            domain: None,
+            cwd: Some(cwd.to_string()),
+            argv: argv.map(|argv| argv.iter().map(|s| s.to_string()).collect()),
+            env: [("RUST_LOG".to_string(), log.to_string())].into(),
+            focus,
+        })
+    }
+
+    #[test]
+    fn plan_yaml() {
+        let layout = WorkspaceLayout::parse(DEV_YAML, LayoutFormat::Yaml).unwrap();
+        let plan = layout.plan(Path::new("/project")).unwrap();
+
+        assert_eq!(plan.workspace.as_deref(), Some("project"));
+        assert_eq!(plan.windows.len(), 1);
+        let window = &plan.windows[0];
+        assert_eq!(window.title.as_deref(), Some("editor"));
+        assert_eq!(window.tabs.len(), 2);
+
+        let dev = &window.tabs[0];
+        assert_eq!(dev.title.as_deref(), Some("dev"));
+        assert_eq!(
+            dev.tree,
+            SpawnTree::Split {
+                direction: SplitDirection::Horizontal,
+                second_percent: 33,
+                first: Box::new(pane("/project/src", Some(&["nvim"]), "debug", true)),
+                second: Box::new(SpawnTree::Split {
+                    direction: SplitDirection::Vertical,
+                    second_percent: 50,
+                    first: Box::new(pane("/project/src/tests", None, "debug", false)),
+                    second: Box::new(pane(
+                        "/project/src/tests",
+                        Some(&["cargo", "watch", "-x", "test"]),
+                        "info",
+                        false
+                    )),
+                }),
+            }
+        );
+        assert_eq!(dev.tree.pane_count(), 3);
+        assert_eq!(dev.tree.first_pane().argv, Some(vec!["nvim".to_string()]));
+
+        let logs = &window.tabs[1];
+        assert_eq!(
+            logs.tree,
+            SpawnTree::Pane(PaneSpec {
+                domain: Some("remote".to_string()),
+                cwd: Some("/project/src".to_string()),
+                argv: Some(vec![
+                    "tail".to_string(),
+                    "-f".to_string(),
+                    "/var/log/my app.log".to_string()
+                ]),
+                env: [("RUST_LOG".to_string(), "debug".to_string())].into(),
+                focus: false,
+            })
+        );
+    }
+
+    #[test]
+    fn toml_matches_yaml() {
+        let toml = r#"
+workspace = "project"
+cwd = "src"
+env = { RUST_LOG = "debug" }
+
+[[windows]]
+title = "editor"
+
+[[windows.tabs]]
+title = "dev"
+split = "horizontal"
+
+[[windows.tabs.panes]]
+command = "nvim"
+ratio = 2
+focus = true
+
+[[windows.tabs.panes]]
+split = "vertical"
+cwd = "tests"
+panes = [
+  {},
+  { command = ["cargo", "watch", "-x", "test"], env = { RUST_LOG = "info" } },
+]
+
+[[windows.tabs]]
+title = "logs"
+domain = "remote"
+command = "tail -f '/var/log/my app.log'"
+"#;
+        assert_eq!(
+            WorkspaceLayout::parse(toml, LayoutFormat::Toml).unwrap(),
+            WorkspaceLayout::parse(DEV_YAML, LayoutFormat::Yaml).unwrap()
+        );
+    }
+
+    #[test]
+    fn round_trip() {
+        let layout = WorkspaceLayout::parse(DEV_YAML, LayoutFormat::Yaml).unwrap();
+        for format in [LayoutFormat::Yaml, LayoutFormat::Toml] {
+            let text = layout.serialize(format).unwrap();
+            assert_eq!(WorkspaceLayout::parse(&text, format).unwrap(), layout);
+        }
+    }
+
+    #[test]
+    fn split_percentages() {
+        let layout = WorkspaceLayout::parse(
+            r#"
+windows:
+  - tabs:
+      - split: vertical
+        panes: [{}, {}, {}, {ratio: 3}]
+"#,
+            LayoutFormat::Yaml,
+        )
+        .unwrap();
+        let plan = layout.plan(Path::new("/")).unwrap();
+        let mut percents = vec![];
+        let mut tree = &plan.windows[0].tabs[0].tree;
+        while let SpawnTree::Split {
+            second_percent,
+            second,
+            ..
+        } = tree
+        {
+            percents.push(*second_percent);
+            tree = second;
+        }
+        // 5/6 of the tab, then 4/5 of the rest, then 3/4 of that
+        assert_eq!(percents, vec![83, 80, 75]);
+    }
+
+    #[test]
+    fn validation() {
+        let check = |yaml: &str| {
+            WorkspaceLayout::parse(yaml, LayoutFormat::Yaml)
+                .and_then(|layout| layout.validate())
+                .map_err(|err| format!("{:#}", err))
+        };
+
+        assert_eq!(
+            check("windows: []"),
+            Err("the layout has no windows".to_string())
+        );
+        assert_eq!(
+            check("windows: [{tabs: [{panes: [{}]}]}]"),
+            Err(
+                "windows[0].tabs[0]: `panes` requires `split` to be horizontal or vertical"
+                    .to_string()
+            )
+        );
+        assert_eq!(
+            check("windows: [{tabs: [{split: vertical, panes: [{title: nope}]}]}]"),
+            Err("windows[0].tabs[0].panes[0]: only tabs may have a title".to_string())
+        );
+        assert_eq!(
+            check("windows: [{tabs: [{split: vertical, panes: [{ratio: 0}]}]}]"),
+            Err("windows[0].tabs[0].panes[0]: ratio must be a positive number, not 0".to_string())
+        );
+        assert_eq!(
+            check("windows: [{tabs: [{focus: true}, {focus: true}]}]"),
+            Err("only one pane may have focus, but 2 do".to_string())
+        );
+        assert_eq!(
+            check("windows: [{tabs: [{command: \"\"}]}]"),
+            Err("windows[0].tabs[0]: invalid command: command is empty".to_string())
+        );
+        assert!(check("windows: [{tabs: [{comand: ls}]}]")
+            .unwrap_err()
+            .contains("unknown field `comand`"));
+        assert_eq!(check("windows: [{tabs: [{}]}]"), Ok(()));
+    }
+
+    fn leaf(
+        window_id: WindowId,
+        pane_id: PaneId,
+        workspace: &str,
+        is_active_tab: bool,
+        is_active_pane: bool,
+    ) -> PaneNode {
+        PaneNode::Leaf(PaneEntry {
+            window_id,
+            tab_id: pane_id,
+            pane_id,
+            title: String::new(),
+            size: Default::default(),
+            working_dir: None,
+            is_active_pane,
+            is_zoomed_pane: false,
+            is_active_tab,
+            workspace: workspace.to_string(),
+            cursor_pos: Default::default(),
+            physical_top: 0,
+            top_row: 0,
+            left_col: 0,
+            tty_name: None,
+            monitors: Default::default(),
+            monitor_alert: None,
+            domain_name: None,
+        })
+    }
+
+    #[test]
+    fn export_focus() {
+        let panes = || ListPanesResponse {
+            tabs: vec![
+                leaf(0, 1, "default", false, true),
+                leaf(0, 2, "default", true, true),
+                leaf(1, 3, "other", true, true),
+            ],
+            tab_titles: vec![String::new(); 3],
+            window_titles: Default::default(),
+        };
+        let focus = |focused: Option<PaneId>| -> Vec<bool> {
+            WorkspaceLayout::from_panes(panes(), "default", focused).windows[0]
+                .tabs
+                .iter()
+                .map(|tab| tab.focus)
+                .collect()
+        };
+
+        // The pane that the command runs in
+        assert_eq!(focus(Some(1)), vec![true, false]);
+        // Otherwise the active pane of the active tab
+        assert_eq!(focus(Some(3)), vec![false, true]);
+        assert_eq!(focus(None), vec![false, true]);
+    }
+}
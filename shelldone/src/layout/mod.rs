@@ -0,0 +1,289 @@
+use crate::cli::ConnectOptions;
+use anyhow::{anyhow, bail, Context};
+use clap::{Parser, Subcommand, ValueHint};
+use codec::{SpawnResponse, SpawnV2, SplitPane};
+use config::keyassignment::SpawnTabDomain;
+use config::ConfigHandle;
+use format::{pane_entries, LayoutFormat, PaneSpec, SpawnTree, WorkspaceLayout};
+use mux::pane::PaneId;
+use mux::tab::{SplitRequest, SplitSize};
+use portable_pty::cmdbuilder::CommandBuilder;
+use shelldone_client::client::Client;
+use std::path::PathBuf;
+
+mod format;
+
+#[derive(Debug, Parser, Clone)]
+pub struct LayoutCommand {
+    #[command(flatten)]
+    connect: ConnectOptions,
+
+    #[command(subcommand)]
+    sub: LayoutSubCommand,
+}
+
+#[derive(Debug, Subcommand, Clone)]
+enum LayoutSubCommand {
+    /// Spawn the windows, tabs and panes described by a layout file
+    #[command(name = "apply", rename_all = "kebab")]
+    Apply(ApplyCommand),
+
+    /// Print a layout file that describes the windows, tabs
+    /// and panes of a workspace
+    #[command(name = "export", rename_all = "kebab")]
+    Export(ExportCommand),
+}
+
+#[derive(Debug, Parser, Clone)]
+struct ApplyCommand {
+    /// Spawn into this workspace, rather than the one named by the
+    /// layout file.  If neither names one, the `default_workspace`
+    /// from your configuration is used.
+    #[arg(long)]
+    workspace: Option<String>,
+
+    /// Add the windows to the workspace even if it already has panes.
+    /// The default is to refuse, so that applying a layout twice
+    /// doesn't leave you with two copies of it.
+    #[arg(long)]
+    append: bool,
+
+    /// The layout file to apply; its extension must be
+    /// `.yaml`, `.yml` or `.toml`
+    #[arg(value_hint=ValueHint::FilePath)]
+    file: PathBuf,
+}
+
+#[derive(Debug, Parser, Clone)]
+struct ExportCommand {
+    /// The workspace to export.
+    /// The default is the workspace that contains the current pane.
+    #[arg(long)]
+    workspace: Option<String>,
+
+    /// Controls the output format.
+    /// "yaml" and "toml" are possible formats.
+    #[arg(long = "format", default_value = "yaml")]
+    format: LayoutFormat,
+}
+
+fn default_workspace(config: &ConfigHandle) -> String {
+    config
+        .default_workspace
+        .as_deref()
+        .unwrap_or(mux::DEFAULT_WORKSPACE)
+        .to_string()
+}
+
+/// Only build a command when the pane needs something other than
+/// the default program, so that the domain can choose it
+fn command_for(pane: &PaneSpec) -> Option<CommandBuilder> {
+    let mut builder = match &pane.argv {
+        Some(argv) => CommandBuilder::from_argv(argv.iter().map(Into::into).collect()),
+        None if pane.env.is_empty() => return None,
+        None => CommandBuilder::new_default_prog(),
+    };
+    for (k, v) in &pane.env {
+        builder.env(k, v);
+    }
+    Some(builder)
+}
+
+impl ApplyCommand {
+    async fn run(self, client: Client, config: &ConfigHandle) -> anyhow::Result<()> {
+        let path = std::env::current_dir()?.join(&self.file);
+        let base_dir = path
+            .parent()
+            .ok_or_else(|| anyhow!("{} has no parent directory", path.display()))?;
+        let plan = WorkspaceLayout::load(&path)?
+            .plan(base_dir)
+            .with_context(|| format!("in {}", path.display()))?;
+
+        let workspace = self
+            .workspace
+            .or(plan.workspace)
+            .unwrap_or_else(|| default_workspace(config));
+
+        if !self.append {
+            let panes = client.list_panes().await?;
+            if pane_entries(&panes)
+                .iter()
+                .any(|entry| entry.workspace == workspace)
+            {
+                bail!(
+                    "workspace {workspace} already has panes; \
+                     pass --append to add the layout to it anyway"
+                );
+            }
+        }
+
+        let size = config.initial_size(0, None);
+        let mut focus = None;
+        let mut tab_count = 0;
+        let mut pane_count = 0;
+
+        for window in &plan.windows {
+            let mut window_id = None;
+            let mut first_pane_id = None;
+
+            for tab in &window.tabs {
+                let pane = tab.tree.first_pane();
+                let spawned = client
+                    .spawn_v2(SpawnV2 {
+                        domain: pane
+                            .domain
+                            .clone()
+                            .map_or(SpawnTabDomain::DefaultDomain, SpawnTabDomain::DomainName),
+                        window_id,
+                        command: command_for(pane),
+                        command_dir: pane.cwd.clone(),
+                        size,
+                        workspace: workspace.clone(),
+                    })
+                    .await?;
+                log::debug!("{:?}", spawned);
+                window_id.replace(spawned.window_id);
+                first_pane_id = first_pane_id.or(Some(spawned.pane_id));
+
+                // Each split divides an existing pane, keeping the first
+                // part and spawning the first pane of the second part
+                // into the remainder
+                let mut stack = vec![(spawned.pane_id, &tab.tree)];
+                while let Some((pane_id, node)) = stack.pop() {
+                    match node {
+                        SpawnTree::Pane(pane) => {
+                            if pane.focus {
+                                focus.replace(pane_id);
+                            }
+                        }
+                        SpawnTree::Split {
+                            direction,
+                            second_percent,
+                            first,
+                            second,
+                        } => {
+                            let pane = second.first_pane();
+                            let SpawnResponse {
+                                pane_id: second_pane_id,
+                                ..
+                            } = client
+                                .split_pane(SplitPane {
+                                    pane_id,
+                                    split_request: SplitRequest {
+                                        direction: *direction,
+                                        target_is_second: true,
+                                        top_level: false,
+                                        size: SplitSize::Percent(*second_percent),
+                                    },
+                                    command: command_for(pane),
+                                    command_dir: pane.cwd.clone(),
+                                    domain: pane.domain.clone().map_or(
+                                        SpawnTabDomain::CurrentPaneDomain,
+                                        SpawnTabDomain::DomainName,
+                                    ),
+                                    move_pane_id: None,
+                                })
+                                .await?;
+                            stack.push((second_pane_id, &**second));
+                            stack.push((pane_id, &**first));
+                        }
+                    }
+                }
+
+                if let Some(title) = &tab.title {
+                    client
+                        .set_tab_title(codec::TabTitleChanged {
+                            tab_id: spawned.tab_id,
+                            title: title.clone(),
+                        })
+                        .await?;
+                }
+                // Splitting focuses the new pane; start out in the top left
+                client
+                    .set_focused_pane_id(codec::SetFocusedPane {
+                        pane_id: spawned.pane_id,
+                    })
+                    .await?;
+                tab_count += 1;
+                pane_count += tab.tree.pane_count();
+            }
+
+            if let (Some(window_id), Some(title)) = (window_id, &window.title) {
+                client
+                    .set_window_title(codec::WindowTitleChanged {
+                        window_id,
+                        title: title.clone(),
+                    })
+                    .await?;
+            }
+            if let Some(pane_id) = first_pane_id {
+                client
+                    .set_focused_pane_id(codec::SetFocusedPane { pane_id })
+                    .await?;
+            }
+        }
+
+        if let Some(pane_id) = focus {
+            client
+                .set_focused_pane_id(codec::SetFocusedPane { pane_id })
+                .await?;
+        }
+
+        println!(
+            "Spawned {} windows with {tab_count} tabs and {pane_count} panes \
+             in workspace {workspace}",
+            plan.windows.len()
+        );
+        Ok(())
+    }
+}
+
+impl ExportCommand {
+    async fn run(self, client: Client, config: &ConfigHandle) -> anyhow::Result<()> {
+        let panes = client.list_panes().await?;
+
+        // The current pane is only known when running inside of shelldone
+        let current = match client.resolve_pane_id(None).await {
+            Ok(pane_id) => pane_entries(&panes)
+                .into_iter()
+                .find(|entry| entry.pane_id == pane_id)
+                .map(|entry| (entry.pane_id, entry.workspace.clone())),
+            Err(_) => None,
+        };
+
+        let workspace = match (self.workspace, &current) {
+            (Some(workspace), _) => workspace,
+            (None, Some((_, workspace))) => workspace.clone(),
+            (None, None) => default_workspace(config),
+        };
+        if !pane_entries(&panes)
+            .iter()
+            .any(|entry| entry.workspace == workspace)
+        {
+            bail!("workspace {workspace} has no panes");
+        }
+
+        let focused: Option<PaneId> = current.map(|(pane_id, _)| pane_id);
+        let layout = WorkspaceLayout::from_panes(panes, &workspace, focused);
+        print!("{}", layout.serialize(self.format)?);
+        Ok(())
+    }
+}
+
+impl LayoutCommand {
+    async fn run_async(self, config: ConfigHandle) -> anyhow::Result<()> {
+        let client = self.connect.connect()?;
+        match self.sub {
+            LayoutSubCommand::Apply(cmd) => cmd.run(client, &config).await,
+            LayoutSubCommand::Export(cmd) => cmd.run(client, &config).await,
+        }
+    }
+
+    pub fn run(self, config: ConfigHandle) -> anyhow::Result<()> {
+        let executor = promise::spawn::ScopedExecutor::new();
+        match promise::spawn::block_on(executor.run(async move { self.run_async(config).await })) {
+            Ok(_) => Ok(()),
+            Err(err) => crate::terminate_with_error(err),
+        }
+    }
+}
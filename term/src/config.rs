@@ -147,6 +147,19 @@ pub trait TerminalConfiguration: Downcast + std::fmt::Debug + Send + Sync {
         3500
     }
 
+    /// Returns how many lines that have fallen off the in-memory
+    /// scrollback are kept compressed on disk.  0 disables the disk tier.
+    /// Requires the `disk_scrollback` feature.
+    fn scrollback_spill_size(&self) -> usize {
+        0
+    }
+
+    /// Returns the directory that holds the per-screen scrollback
+    /// spill files.
+    fn scrollback_spill_dir(&self) -> std::path::PathBuf {
+        std::env::temp_dir()
+    }
+
     /// Return true if the embedding application wants to use CSI-u encoding
     /// for keys that would otherwise be ambiguous.
     /// <http://www.leonerd.org.uk/hacks/fixterms/>
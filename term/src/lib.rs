@@ -35,6 +35,8 @@ pub use shelldone_surface::line::*;
 pub mod screen;
 pub use crate::screen::*;
 
+mod spill;
+
 pub mod terminal;
 pub use crate::terminal::*;
 
@@ -122,6 +124,58 @@ pub struct SemanticZone {
     pub semantic_type: SemanticType,
 }
 
+/// A command that was run from a shell prompt, as delimited by the
+/// OSC 133 semantic prompt escapes.
+/// The rows are the first rows of the logical lines that hold the
+/// prompt and the output of the command; `end_y` is the row after
+/// the last row of output.
+#[cfg_attr(feature = "use_serde", derive(Deserialize, Serialize))]
+#[derive(Debug, Clone, Eq, PartialEq, FromDynamic, ToDynamic)]
+pub struct CommandBlock {
+    /// Uniquely identifies the block within its terminal
+    pub id: u64,
+    pub prompt_y: StableRowIndex,
+    /// None until the command starts to run
+    pub output_y: Option<StableRowIndex>,
+    /// None until the command has finished
+    pub end_y: Option<StableRowIndex>,
+    /// The text of the command line that was entered
+    pub command: String,
+    pub exit_status: Option<i32>,
+    /// When the command started, in milliseconds since the unix epoch
+    pub start_time_ms: Option<u64>,
+    /// When the command finished, in milliseconds since the unix epoch
+    pub end_time_ms: Option<u64>,
+}
+
+impl CommandBlock {
+    /// Returns true if the command has started but has not
+    /// yet reported its exit status
+    pub fn is_running(&self) -> bool {
+        self.output_y.is_some() && self.end_y.is_none()
+    }
+
+    /// Returns how long the command took to run, or None if it
+    /// has not finished
+    pub fn duration(&self) -> Option<std::time::Duration> {
+        match (self.start_time_ms, self.end_time_ms) {
+            (Some(start), Some(end)) => {
+                Some(std::time::Duration::from_millis(end.saturating_sub(start)))
+            }
+            _ => None,
+        }
+    }
+
+    /// Returns the rows that hold the output of the command.
+    /// `bottom` is used in place of the end of a command that is
+    /// still running.
+    pub fn output_rows(&self, bottom: StableRowIndex) -> Option<Range<StableRowIndex>> {
+        let start = self.output_y?;
+        let end = self.end_y.unwrap_or(bottom).max(start);
+        Some(start..end)
+    }
+}
+
 pub mod color;
 
 #[cfg(test)]
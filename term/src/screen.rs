@@ -1,10 +1,11 @@
 #![allow(clippy::range_plus_one)]
 use super::*;
 use crate::config::BidiMode;
+use crate::spill::ScrollbackSpill;
 use log::debug;
 use shelldone_surface::SequenceNo;
 use std::collections::VecDeque;
-use std::sync::Arc;
+use std::sync::{Arc, Mutex};
 use termwiz::input::KeyboardEncoding;
 
 /// Holds the model of a screen.  This can either be the primary screen
@@ -35,6 +36,15 @@ pub struct Screen {
     /// that we're the primary rather than the alternate screen.
     allow_scrollback: bool,
 
+    /// Lines that have scrolled off the top of `lines` but are still
+    /// reachable, compressed on disk.  Their StableRowIndex values
+    /// immediately precede `stable_row_index_offset`.
+    /// Created on first use when `scrollback_spill_size` is non-zero.
+    spill: Option<Arc<Mutex<ScrollbackSpill>>>,
+    /// Set if the spill file could not be created, so that we don't
+    /// retry for every line.
+    spill_failed: bool,
+
     pub(crate) keyboard_stack: Vec<KeyboardEncoding>,
 
     /// Physical, visible height of the screen (not including scrollback)
@@ -80,6 +90,8 @@ impl Screen {
             lines,
             config: Arc::clone(config),
             allow_scrollback,
+            spill: None,
+            spill_failed: false,
             physical_rows,
             physical_cols,
             stable_row_index_offset: 0,
@@ -97,6 +109,114 @@ impl Screen {
         scrollback_size(&self.config, self.allow_scrollback)
     }
 
+    fn spill_enabled(&self) -> bool {
+        self.allow_scrollback && !self.spill_failed && self.config.scrollback_spill_size() > 0
+    }
+
+    /// Move lines that are leaving the in-memory scrollback to the disk tier.
+    fn spill_lines(&mut self, first: StableRowIndex, lines: Vec<Line>) {
+        if lines.is_empty() {
+            return;
+        }
+        let max_lines = self.config.scrollback_spill_size();
+        if self.spill.is_none() {
+            match ScrollbackSpill::new(&self.config.scrollback_spill_dir(), max_lines) {
+                Ok(spill) => self.spill = Some(Arc::new(Mutex::new(spill))),
+                Err(err) => {
+                    log::warn!("disk scrollback disabled: {:#}", err);
+                    self.spill_failed = true;
+                    return;
+                }
+            }
+        }
+        if let Some(spill) = &self.spill {
+            let mut spill = spill.lock().unwrap();
+            spill.set_max_lines(max_lines);
+            spill.extend(first, lines);
+        }
+    }
+
+    /// Write the spilled lines that were modified by a `_mut` accessor
+    /// back to the disk tier.  `original` holds the lines as they were
+    /// before the modification.
+    fn store_spilled_lines(&self, first: StableRowIndex, original: Vec<Line>, lines: Vec<Line>) {
+        let changed: Vec<(StableRowIndex, Line)> = lines
+            .into_iter()
+            .zip(original)
+            .enumerate()
+            .filter(|(_, (line, original))| line != original)
+            .map(|(idx, (line, _))| (first + idx as StableRowIndex, line))
+            .collect();
+        if changed.is_empty() {
+            return;
+        }
+        if let Some(spill) = &self.spill {
+            spill.lock().unwrap().update_lines(changed);
+        }
+    }
+
+    /// Returns copies of the spilled lines that fall within `range`,
+    /// or None if `range` is entirely held in memory.
+    fn spilled_lines(&self, range: &Range<StableRowIndex>) -> Option<(StableRowIndex, Vec<Line>)> {
+        let spill = self.spill.as_ref()?;
+        let mut spill = spill.lock().unwrap();
+        // The spill is only reachable while it abuts the in-memory lines;
+        // a gap means spilling was turned off in the meantime.
+        if spill.is_empty()
+            || spill.end() != self.phys_to_stable_row_index(0)
+            || range.start >= spill.end()
+            || range.end <= spill.first()
+        {
+            return None;
+        }
+        Some(spill.lines_in_range(range.clone()))
+    }
+
+    /// Like `spilled_lines`, but if the last line in `range` wraps onto
+    /// further spilled lines, those are returned too so that the logical
+    /// line is complete.
+    fn spilled_lines_to_line_end(
+        &self,
+        range: &Range<StableRowIndex>,
+    ) -> Option<(StableRowIndex, Vec<Line>)> {
+        let (first, mut lines) = self.spilled_lines(range)?;
+        let memory_top = self.phys_to_stable_row_index(0);
+        let mut extra_len = 0;
+        while extra_len < MAX_LOGICAL_LINE_LEN
+            && lines.last().map_or(false, Line::last_cell_was_wrapped)
+        {
+            let end = first + lines.len() as StableRowIndex;
+            if end >= memory_top {
+                break;
+            }
+            match self.spilled_lines(&(end..end + 1)) {
+                Some((_, more)) if !more.is_empty() => {
+                    extra_len += more.iter().map(Line::len).sum::<usize>();
+                    lines.extend(more);
+                }
+                _ => break,
+            }
+        }
+        Some((first, lines))
+    }
+
+    /// The StableRowIndex of the oldest line that can still be retrieved,
+    /// including lines that were spilled to disk.
+    pub fn scrollback_top(&self) -> StableRowIndex {
+        let memory_top = self.phys_to_stable_row_index(0);
+        match &self.spill {
+            Some(spill) => {
+                let spill = spill.lock().unwrap();
+                if spill.is_empty() || spill.end() != memory_top {
+                    memory_top
+                } else {
+                    spill.first()
+                }
+            }
+            None => memory_top,
+        }
+    }
+
     fn rewrap_lines(
         &mut self,
         physical_cols: usize,
@@ -331,9 +451,11 @@ impl Screen {
         &mut self.lines[idx]
     }
 
-    /// Returns the number of occupied rows of scrollback
+    /// Returns the number of occupied rows of scrollback, including
+    /// rows that were spilled to disk
     pub fn scrollback_rows(&self) -> usize {
-        self.lines.len()
+        let memory_top = self.phys_to_stable_row_index(0);
+        self.lines.len() + (memory_top - self.scrollback_top()) as usize
     }
 
     /// Sets a line dirty.  The line is relative to the visible origin.
@@ -698,6 +820,13 @@ impl Screen {
             phys_scroll.start
         };
 
+        // Lines leaving the top of the scrollback go to the disk tier,
+        // if one is configured.  They are moved rather than copied, and
+        // handed over in one batch once all of them have been removed.
+        let spilling = remove_idx == 0 && scrollback_ok && self.spill_enabled();
+        let spill_first = self.phys_to_stable_row_index(0);
+        let mut spilled = vec![];
+
         let default_blank = CellAttributes::blank();
         // To avoid thrashing the heap, prefer to move lines that were
         // scrolled off the top and re-use them at the bottom.
@@ -706,7 +835,17 @@ impl Screen {
             for _ in 0..to_move {
                 let mut line = self.lines.remove(remove_idx).unwrap();
                 let line = if default_blank == blank_attr {
+                    if spilling {
+                        spilled.push(line);
+                    }
                     Line::new(seqno)
+                } else if spilling {
+                    spilled.push(line);
+                    Line::with_width_and_cell(
+                        self.physical_cols,
+                        Cell::blank_with_attrs(blank_attr.clone()),
+                        seqno,
+                    )
                 } else {
                     // Make the line like a new one of the appropriate width
                     line.resize_and_clear(self.physical_cols, seqno, blank_attr.clone());
@@ -726,8 +865,12 @@ impl Screen {
 
         // Perform the removal
         for _ in 0..to_remove {
-            self.lines.remove(remove_idx);
+            let line = self.lines.remove(remove_idx);
+            if spilling {
+                spilled.extend(line);
+            }
         }
+        self.spill_lines(spill_first, spilled);
 
         if remove_idx == 0 && scrollback_ok {
             self.stable_row_index_offset += lines_removed;
@@ -760,6 +903,9 @@ impl Screen {
     }
 
     pub fn erase_scrollback(&mut self) {
+        if let Some(spill) = &self.spill {
+            spill.lock().unwrap().reset();
+        }
         let len = self.lines.len();
         let to_clear = len - self.physical_rows;
         for _ in 0..to_clear {
@@ -906,6 +1052,100 @@ impl Screen {
             .collect()
     }
 
+    /// Returns copies of the lines in the stable range, including lines
+    /// that were spilled to disk, along with the StableRowIndex of the
+    /// first returned line.
+    pub fn lines_in_stable_range(
+        &self,
+        range: &Range<StableRowIndex>,
+    ) -> (StableRowIndex, Vec<Line>) {
+        let Some((first, mut lines)) = self.spilled_lines(range) else {
+            let phys_range = self.stable_range(range);
+            let first = self.phys_to_stable_row_index(phys_range.start);
+            return (first, self.lines_in_phys_range(phys_range));
+        };
+        let memory_range = self.memory_part(range);
+        if !memory_range.is_empty() {
+            lines.extend(self.lines_in_phys_range(self.stable_range(&memory_range)));
+        }
+        (first, lines)
+    }
+
+    /// Call `func` with the lines in the stable range, including lines
+    /// that were spilled to disk.
+    pub fn with_stable_lines<F>(&self, range: &Range<StableRowIndex>, mut func: F)
+    where
+        F: FnMut(StableRowIndex, &[&Line]),
+    {
+        let Some((first, spilled)) = self.spilled_lines(range) else {
+            let phys_range = self.stable_range(range);
+            let first = self.phys_to_stable_row_index(phys_range.start);
+            return self.with_phys_lines(phys_range, |lines| func(first, lines));
+        };
+        let memory_range = self.memory_part(range);
+        let phys_range = if memory_range.is_empty() {
+            0..0
+        } else {
+            self.stable_range(&memory_range)
+        };
+        self.with_phys_lines(phys_range, |lines| {
+            let mut all: Vec<&Line> = spilled.iter().collect();
+            all.extend_from_slice(lines);
+            func(first, &all)
+        });
+    }
+
+    /// Call `func` with mutable lines in the stable range.  Spilled lines
+    /// are passed as temporary copies; those that `func` changes (for
+    /// example by applying hyperlink rules) are written back to disk.
+    pub fn with_stable_lines_mut<F>(&mut self, range: &Range<StableRowIndex>, mut func: F)
+    where
+        F: FnMut(StableRowIndex, &mut [&mut Line]),
+    {
+        let Some((first, mut spilled)) = self.spilled_lines(range) else {
+            let phys_range = self.stable_range(range);
+            let first = self.phys_to_stable_row_index(phys_range.start);
+            return self.with_phys_lines_mut(phys_range, |lines| func(first, lines));
+        };
+        let original = spilled.clone();
+        let memory_range = self.memory_part(range);
+        let phys_range = if memory_range.is_empty() {
+            0..0
+        } else {
+            self.stable_range(&memory_range)
+        };
+        self.with_phys_lines_mut(phys_range, |lines| {
+            let mut all: Vec<&mut Line> = spilled.iter_mut().collect();
+            for line in lines.iter_mut() {
+                all.push(&mut **line);
+            }
+            func(first, &mut all)
+        });
+        self.store_spilled_lines(first, original, spilled);
+    }
+
+    /// The portion of `range` that is held in memory
+    fn memory_part(&self, range: &Range<StableRowIndex>) -> Range<StableRowIndex> {
+        range.start.max(self.phys_to_stable_row_index(0))..range.end
+    }
+
+    /// Groups the spilled `lines`, the first of which is at `first`, into
+    /// logical lines.  If the last one continues into the in-memory lines,
+    /// it is returned separately so that it can be joined with them.
+    fn spilled_logical_lines(
+        &self,
+        first: StableRowIndex,
+        lines: &[Line],
+    ) -> (Vec<Range<usize>>, Option<Range<usize>>) {
+        let mut ranges = logical_line_ranges(lines);
+        let continues = first + lines.len() as StableRowIndex == self.phys_to_stable_row_index(0)
+            && lines
+                .last()
+                .map_or(false, |line| line.last_cell_was_wrapped());
+        let head = if continues { ranges.pop() } else { None };
+        (ranges, head)
+    }
+
     pub fn get_changed_stable_rows(
         &self,
         stable_lines: Range<StableRowIndex>,
@@ -995,13 +1235,61 @@ impl Screen {
     ) where
         F: FnMut(Range<StableRowIndex>, &mut [&mut Line]) -> bool,
     {
-        let mut phys_range = self.stable_range(&stable_range);
+        let Some((first, mut spilled)) = self.spilled_lines_to_line_end(&stable_range) else {
+            return self.for_each_logical_line_in_memory_mut(stable_range, &mut [], &mut f);
+        };
+
+        // Spilled lines come first; they are temporary copies, so keep the
+        // originals to tell which ones need to be written back
+        let original = spilled.clone();
+        let (ranges, head) = self.spilled_logical_lines(first, &spilled);
+        let mut continue_iteration = true;
+        for range in ranges {
+            let logical_stable_range =
+                first + range.start as StableRowIndex..first + range.end as StableRowIndex;
+            let mut lines: Vec<&mut Line> = spilled[range].iter_mut().collect();
+            if !f(logical_stable_range, &mut lines) {
+                continue_iteration = false;
+                break;
+            }
+        }
+
+        if continue_iteration {
+            let memory_range = self.memory_part(&stable_range);
+            match head {
+                Some(head) => {
+                    // The logical line continues in memory, even if the
+                    // range stops short of it
+                    let memory_range =
+                        memory_range.start..memory_range.end.max(memory_range.start + 1);
+                    self.for_each_logical_line_in_memory_mut(
+                        memory_range,
+                        &mut spilled[head],
+                        &mut f,
+                    );
+                }
+                None if !memory_range.is_empty() => {
+                    self.for_each_logical_line_in_memory_mut(memory_range, &mut [], &mut f);
+                }
+                None => {}
+            }
+        }
 
-        // Avoid pathological cases where we have eg: a really long logical line
-        // (such as 1.5MB of json) that we previously wrapped.  We don't want to
-        // un-wrap, scan, and re-wrap that thing.
-        // This is an imperfect length constraint to partially manage the cost.
-        const MAX_LOGICAL_LINE_LEN: usize = 1024;
+        self.store_spilled_lines(first, original, spilled);
+    }
+
+    /// Iterates the logical lines of the in-memory part of the screen.
+    /// `spilled_head` holds spilled lines that are continued by the first
+    /// in-memory line; they are joined with it.
+    fn for_each_logical_line_in_memory_mut<F>(
+        &mut self,
+        stable_range: Range<StableRowIndex>,
+        mut spilled_head: &mut [Line],
+        f: &mut F,
+    ) where
+        F: FnMut(Range<StableRowIndex>, &mut [&mut Line]) -> bool,
+    {
+        let mut phys_range = self.stable_range(&stable_range);
 
         // Look backwards to find the start of the first logical line
         let mut back_len = 0;
@@ -1044,7 +1332,13 @@ impl Screen {
 
             let phys_range = phys_row..end_inclusive + 1;
 
+            let head: &mut [Line] = if phys_row == 0 {
+                std::mem::take(&mut spilled_head)
+            } else {
+                &mut []
+            };
             let logical_stable_range = self.phys_to_stable_row_index(phys_row)
+                - head.len() as StableRowIndex
                 ..self.phys_to_stable_row_index(end_inclusive + 1);
 
             phys_row = end_inclusive + 1;
@@ -1058,7 +1352,15 @@ impl Screen {
 
             let mut continue_iteration = false;
             self.with_phys_lines_mut(phys_range, |lines| {
-                continue_iteration = f(logical_stable_range.clone(), lines);
+                if head.is_empty() {
+                    continue_iteration = f(logical_stable_range.clone(), lines);
+                } else {
+                    let mut all: Vec<&mut Line> = head.iter_mut().collect();
+                    for line in lines.iter_mut() {
+                        all.push(&mut **line);
+                    }
+                    continue_iteration = f(logical_stable_range.clone(), &mut all);
+                }
             });
 
             if !continue_iteration {
@@ -1074,13 +1376,47 @@ impl Screen {
     ) where
         F: FnMut(Range<StableRowIndex>, &[&Line]) -> bool,
     {
-        let mut phys_range = self.stable_range(&stable_range);
+        let Some((first, spilled)) = self.spilled_lines_to_line_end(&stable_range) else {
+            return self.for_each_logical_line_in_memory(stable_range, &[], &mut f);
+        };
+
+        let (ranges, head) = self.spilled_logical_lines(first, &spilled);
+        for range in ranges {
+            let logical_stable_range =
+                first + range.start as StableRowIndex..first + range.end as StableRowIndex;
+            let lines: Vec<&Line> = spilled[range].iter().collect();
+            if !f(logical_stable_range, &lines) {
+                return;
+            }
+        }
 
-        // Avoid pathological cases where we have eg: a really long logical line
-        // (such as 1.5MB of json) that we previously wrapped.  We don't want to
-        // un-wrap, scan, and re-wrap that thing.
-        // This is an imperfect length constraint to partially manage the cost.
-        const MAX_LOGICAL_LINE_LEN: usize = 1024;
+        let memory_range = self.memory_part(&stable_range);
+        match head {
+            Some(head) => {
+                // The logical line continues in memory, even if the
+                // range stops short of it
+                let memory_range = memory_range.start..memory_range.end.max(memory_range.start + 1);
+                self.for_each_logical_line_in_memory(memory_range, &spilled[head], &mut f);
+            }
+            None if !memory_range.is_empty() => {
+                self.for_each_logical_line_in_memory(memory_range, &[], &mut f);
+            }
+            None => {}
+        }
+    }
+
+    /// Iterates the logical lines of the in-memory part of the screen.
+    /// `spilled_head` holds spilled lines that are continued by the first
+    /// in-memory line; they are joined with it.
+    fn for_each_logical_line_in_memory<F>(
+        &self,
+        stable_range: Range<StableRowIndex>,
+        spilled_head: &[Line],
+        f: &mut F,
+    ) where
+        F: FnMut(Range<StableRowIndex>, &[&Line]) -> bool,
+    {
+        let mut phys_range = self.stable_range(&stable_range);
 
         // Look backwards to find the start of the first logical line
         let mut back_len = 0;
@@ -1103,6 +1439,12 @@ impl Screen {
             let mut total_len = 0;
             let mut end_inclusive = phys_row;
             line_vec.clear();
+            let head_len = if phys_row == 0 {
+                line_vec.extend(spilled_head.iter());
+                spilled_head.len()
+            } else {
+                0
+            };
 
             for idx in phys_row.. {
                 if let Some(line) = self.lines.get(idx) {
@@ -1124,6 +1466,7 @@ impl Screen {
             }
 
             let logical_stable_range = self.phys_to_stable_row_index(phys_row)
+                - head_len as StableRowIndex
                 ..self.phys_to_stable_row_index(end_inclusive + 1);
 
             phys_row = end_inclusive + 1;
@@ -1144,6 +1487,36 @@ impl Screen {
     }
 }
 
+/// Avoid pathological cases where we have eg: a really long logical line
+/// (such as 1.5MB of json) that we previously wrapped.  We don't want to
+/// un-wrap, scan, and re-wrap that thing.
+/// This is an imperfect length constraint to partially manage the cost.
+const MAX_LOGICAL_LINE_LEN: usize = 1024;
+
+/// Group `lines` into logical lines, returning index ranges into `lines`.
+fn logical_line_ranges(lines: &[Line]) -> Vec<Range<usize>> {
+    let mut ranges = vec![];
+    let mut start = 0;
+    let mut total_len = 0;
+    for (idx, line) in lines.iter().enumerate() {
+        if idx > start && total_len + line.len() > MAX_LOGICAL_LINE_LEN {
+            ranges.push(start..idx);
+            start = idx;
+            total_len = 0;
+        }
+        total_len += line.len();
+        if !line.last_cell_was_wrapped() {
+            ranges.push(start..idx + 1);
+            start = idx + 1;
+            total_len = 0;
+        }
+    }
+    if start < lines.len() {
+        ranges.push(start..lines.len());
+    }
+    ranges
+}
+
 fn phys_intersection(r1: &Range<PhysRowIndex>, r2: &Range<PhysRowIndex>) -> Range<PhysRowIndex> {
     let start = r1.start.max(r2.start);
     let end = r1.end.min(r2.end);
@@ -0,0 +1,472 @@
+//! Disk tier for the primary screen's scrollback.
+//!
+//! Lines that fall off the in-memory scrollback are batched into chunks,
+//! compressed with zstd and appended to an anonymous, per-screen spill file.
+//! Each chunk remembers the `StableRowIndex` of its first line so that
+//! lookups, search and copy mode can reach spilled lines transparently.
+//! Spilled lines are immutable: they keep the width they had when they
+//! left memory and are not rewrapped on resize.
+use crate::{Line, StableRowIndex};
+use anyhow::Context;
+use lru::LruCache;
+use std::collections::{BTreeMap, VecDeque};
+use std::fs::File;
+use std::io::{Read, Seek, SeekFrom, Write};
+use std::num::NonZeroUsize;
+use std::ops::Range;
+use std::path::{Path, PathBuf};
+
+/// Number of lines compressed together.  Larger chunks compress better but
+/// make a random lookup decode more.
+const CHUNK_LINES: usize = 256;
+/// Number of decoded chunks kept around for scrolling and search.
+const CACHED_CHUNKS: usize = 8;
+/// Don't bother compacting the spill file below this size.
+const COMPACT_MIN_BYTES: u64 = 4 * 1024 * 1024;
+
+#[derive(Debug, Clone, Copy)]
+struct SpillChunk {
+    first: StableRowIndex,
+    count: usize,
+    offset: u64,
+    len: u64,
+}
+
+pub(crate) struct ScrollbackSpill {
+    dir: PathBuf,
+    file: File,
+    file_len: u64,
+    /// Compressed chunks, oldest first.
+    chunks: VecDeque<SpillChunk>,
+    /// The most recently spilled lines that don't fill a chunk yet.
+    pending: Vec<Line>,
+    /// StableRowIndex of the oldest retained line.
+    first: StableRowIndex,
+    len: usize,
+    max_lines: usize,
+    cache: LruCache<StableRowIndex, Vec<Line>>,
+}
+
+impl std::fmt::Debug for ScrollbackSpill {
+    fn fmt(&self, fmt: &mut std::fmt::Formatter) -> std::fmt::Result {
+        fmt.debug_struct("ScrollbackSpill")
+            .field("dir", &self.dir)
+            .field("first", &self.first)
+            .field("len", &self.len)
+            .field("max_lines", &self.max_lines)
+            .field("chunks", &self.chunks.len())
+            .field("file_len", &self.file_len)
+            .finish()
+    }
+}
+
+impl ScrollbackSpill {
+    pub fn new(dir: &Path, max_lines: usize) -> anyhow::Result<Self> {
+        if !codec_available() {
+            anyhow::bail!("shelldone-term was built without the disk_scrollback feature");
+        }
+        let file = open_spill_file(dir)?;
+        Ok(Self {
+            dir: dir.to_path_buf(),
+            file,
+            file_len: 0,
+            chunks: VecDeque::new(),
+            pending: vec![],
+            first: 0,
+            len: 0,
+            max_lines,
+            cache: LruCache::new(NonZeroUsize::new(CACHED_CHUNKS).unwrap()),
+        })
+    }
+
+    /// StableRowIndex of the oldest spilled line.
+    pub fn first(&self) -> StableRowIndex {
+        self.first
+    }
+
+    /// StableRowIndex one past the newest spilled line.
+    pub fn end(&self) -> StableRowIndex {
+        self.first + self.len as StableRowIndex
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    pub fn set_max_lines(&mut self, max_lines: usize) {
+        self.max_lines = max_lines;
+        self.trim();
+    }
+
+    /// Append `lines`, the first of which previously lived at `first`.
+    /// Spilled lines must be contiguous; a gap (for example after the
+    /// in-memory scrollback was erased) discards what was spilled before it.
+    pub fn extend(&mut self, first: StableRowIndex, lines: Vec<Line>) {
+        if self.len > 0 && first != self.end() {
+            self.reset();
+        }
+        if self.len == 0 {
+            self.first = first;
+        }
+        for line in lines {
+            self.pending.push(line);
+            self.len += 1;
+            if self.pending.len() >= CHUNK_LINES {
+                if let Err(err) = self.flush_pending() {
+                    log::warn!("scrollback spill: {:#}; discarding spilled lines", err);
+                    self.reset();
+                    return;
+                }
+            }
+        }
+        self.trim();
+    }
+
+    /// Replace spilled lines that were modified after they left memory,
+    /// for example by applying hyperlink rules.  Rewritten chunks are
+    /// appended to the spill file, leaving their old copy to compaction.
+    pub fn update_lines(&mut self, lines: Vec<(StableRowIndex, Line)>) {
+        let pending_first = self.end() - self.pending.len() as StableRowIndex;
+        let mut by_chunk: BTreeMap<usize, Vec<(usize, Line)>> = BTreeMap::new();
+        for (stable, line) in lines {
+            if stable < self.first || stable >= self.end() {
+                continue;
+            }
+            if stable >= pending_first {
+                self.pending[(stable - pending_first) as usize] = line;
+                continue;
+            }
+            let idx = self
+                .chunks
+                .partition_point(|chunk| chunk.first + chunk.count as StableRowIndex <= stable);
+            let offset = (stable - self.chunks[idx].first) as usize;
+            by_chunk.entry(idx).or_default().push((offset, line));
+        }
+
+        let mut rewrote = false;
+        for (idx, lines) in by_chunk {
+            if let Err(err) = self.rewrite_chunk(idx, lines) {
+                log::warn!("scrollback spill: {:#}", err);
+            }
+            rewrote = true;
+        }
+        if rewrote {
+            self.maybe_compact();
+        }
+    }
+
+    fn rewrite_chunk(&mut self, idx: usize, lines: Vec<(usize, Line)>) -> anyhow::Result<()> {
+        let chunk = self.chunks[idx];
+        let mut decoded = self.load_chunk(chunk)?.clone();
+        for (offset, line) in lines {
+            if let Some(slot) = decoded.get_mut(offset) {
+                *slot = line;
+            }
+        }
+        let encoded = encode_lines(&decoded).context("encoding spill chunk")?;
+        self.file
+            .seek(SeekFrom::Start(self.file_len))
+            .and_then(|_| self.file.write_all(&encoded))
+            .context("writing spill chunk")?;
+        self.chunks[idx] = SpillChunk {
+            offset: self.file_len,
+            len: encoded.len() as u64,
+            ..chunk
+        };
+        self.file_len += encoded.len() as u64;
+        self.cache.put(chunk.first, decoded);
+        Ok(())
+    }
+
+    /// Drop every spilled line.
+    pub fn reset(&mut self) {
+        self.chunks.clear();
+        self.pending.clear();
+        self.cache.clear();
+        self.len = 0;
+        self.first = 0;
+        if let Err(err) = self.file.set_len(0) {
+            log::warn!("scrollback spill: truncating spill file: {:#}", err);
+        }
+        self.file_len = 0;
+    }
+
+    /// Copies of the spilled lines in `range`, clamped to what is retained.
+    /// Returns the StableRowIndex of the first returned line.
+    pub fn lines_in_range(&mut self, range: Range<StableRowIndex>) -> (StableRowIndex, Vec<Line>) {
+        let start = range.start.max(self.first);
+        let end = range.end.min(self.end());
+        let mut lines = vec![];
+        if start >= end {
+            return (start, lines);
+        }
+
+        for idx in 0..self.chunks.len() {
+            let chunk = self.chunks[idx];
+            let chunk_end = chunk.first + chunk.count as StableRowIndex;
+            if chunk_end <= start || chunk.first >= end {
+                continue;
+            }
+            match self.load_chunk(chunk) {
+                Ok(decoded) => {
+                    let from = (start.max(chunk.first) - chunk.first) as usize;
+                    let to = (end.min(chunk_end) - chunk.first) as usize;
+                    lines.extend(decoded[from..to.min(decoded.len())].iter().cloned());
+                }
+                Err(err) => {
+                    // Keep the row numbering intact even if a chunk is lost
+                    log::warn!("scrollback spill: {:#}", err);
+                    let count = (end.min(chunk_end) - start.max(chunk.first)) as usize;
+                    lines.extend((0..count).map(|_| Line::new(1)));
+                }
+            }
+        }
+
+        let pending_first = self.end() - self.pending.len() as StableRowIndex;
+        if end > pending_first {
+            let from = (start.max(pending_first) - pending_first) as usize;
+            let to = (end - pending_first) as usize;
+            lines.extend(self.pending[from..to].iter().cloned());
+        }
+
+        (start, lines)
+    }
+
+    fn load_chunk(&mut self, chunk: SpillChunk) -> anyhow::Result<&Vec<Line>> {
+        if !self.cache.contains(&chunk.first) {
+            let mut compressed = vec![0u8; chunk.len as usize];
+            self.file
+                .seek(SeekFrom::Start(chunk.offset))
+                .and_then(|_| self.file.read_exact(&mut compressed))
+                .context("reading spill chunk")?;
+            let lines = decode_lines(&compressed).context("decoding spill chunk")?;
+            self.cache.put(chunk.first, lines);
+        }
+        Ok(self.cache.get(&chunk.first).expect("just inserted"))
+    }
+
+    fn flush_pending(&mut self) -> anyhow::Result<()> {
+        let lines = std::mem::take(&mut self.pending);
+        let count = lines.len();
+        let encoded = encode_lines(&lines).context("encoding spill chunk")?;
+        self.file
+            .seek(SeekFrom::Start(self.file_len))
+            .and_then(|_| self.file.write_all(&encoded))
+            .context("writing spill chunk")?;
+        let first = self.end() - count as StableRowIndex;
+        self.chunks.push_back(SpillChunk {
+            first,
+            count,
+            offset: self.file_len,
+            len: encoded.len() as u64,
+        });
+        self.file_len += encoded.len() as u64;
+        Ok(())
+    }
+
+    fn trim(&mut self) {
+        let mut dropped = false;
+        while self.len > self.max_lines {
+            let excess = self.len - self.max_lines;
+            match self.chunks.front().copied() {
+                Some(chunk) if chunk.count <= excess || self.max_lines < CHUNK_LINES => {
+                    self.chunks.pop_front();
+                    self.cache.pop(&chunk.first);
+                    self.first += chunk.count as StableRowIndex;
+                    self.len -= chunk.count;
+                    dropped = true;
+                }
+                Some(_) => break,
+                None => {
+                    let count = excess.min(self.pending.len());
+                    self.pending.drain(..count);
+                    self.first += count as StableRowIndex;
+                    self.len -= count;
+                    if count == 0 {
+                        break;
+                    }
+                }
+            }
+        }
+        if self.chunks.is_empty() && self.file_len > 0 {
+            if let Err(err) = self.file.set_len(0) {
+                log::warn!("scrollback spill: truncating spill file: {:#}", err);
+            }
+            self.file_len = 0;
+        } else if dropped {
+            self.maybe_compact();
+        }
+    }
+
+    /// Chunks are dropped from the front, so the file only ever grows;
+    /// rewrite it once most of it is dead space.
+    fn maybe_compact(&mut self) {
+        let live: u64 = self.chunks.iter().map(|chunk| chunk.len).sum();
+        if self.file_len < COMPACT_MIN_BYTES || self.file_len < live * 2 {
+            return;
+        }
+        if let Err(err) = self.compact() {
+            log::warn!("scrollback spill: compaction failed: {:#}", err);
+        }
+    }
+
+    fn compact(&mut self) -> anyhow::Result<()> {
+        let mut file = open_spill_file(&self.dir)?;
+        let mut offset = 0;
+        let mut chunks = VecDeque::with_capacity(self.chunks.len());
+        for chunk in &self.chunks {
+            let mut data = vec![0u8; chunk.len as usize];
+            self.file.seek(SeekFrom::Start(chunk.offset))?;
+            self.file.read_exact(&mut data)?;
+            file.write_all(&data)?;
+            chunks.push_back(SpillChunk { offset, ..*chunk });
+            offset += chunk.len;
+        }
+        self.file = file;
+        self.file_len = offset;
+        self.chunks = chunks;
+        Ok(())
+    }
+}
+
+#[cfg(feature = "disk_scrollback")]
+fn open_spill_file(dir: &Path) -> anyhow::Result<File> {
+    std::fs::create_dir_all(dir)
+        .with_context(|| format!("creating scrollback spill dir {}", dir.display()))?;
+    tempfile::tempfile_in(dir)
+        .with_context(|| format!("creating scrollback spill file in {}", dir.display()))
+}
+
+#[cfg(feature = "disk_scrollback")]
+fn codec_available() -> bool {
+    true
+}
+
+#[cfg(feature = "disk_scrollback")]
+fn encode_lines(lines: &[Line]) -> anyhow::Result<Vec<u8>> {
+    use serde::Serialize;
+    let mut compressed = vec![];
+    let mut compress = zstd::Encoder::new(&mut compressed, zstd::DEFAULT_COMPRESSION_LEVEL)?;
+    let mut encode = varbincode::Serializer::new(&mut compress);
+    lines.serialize(&mut encode)?;
+    compress.finish()?;
+    Ok(compressed)
+}
+
+#[cfg(feature = "disk_scrollback")]
+fn decode_lines(data: &[u8]) -> anyhow::Result<Vec<Line>> {
+    let mut decompress = zstd::Decoder::new(data)?;
+    let mut decode = varbincode::Deserializer::new(&mut decompress);
+    Ok(serde::Deserialize::deserialize(&mut decode)?)
+}
+
+#[cfg(not(feature = "disk_scrollback"))]
+fn open_spill_file(_dir: &Path) -> anyhow::Result<File> {
+    anyhow::bail!("disk_scrollback feature is disabled")
+}
+
+#[cfg(not(feature = "disk_scrollback"))]
+fn codec_available() -> bool {
+    false
+}
+
+#[cfg(not(feature = "disk_scrollback"))]
+fn encode_lines(_lines: &[Line]) -> anyhow::Result<Vec<u8>> {
+    anyhow::bail!("disk_scrollback feature is disabled")
+}
+
+#[cfg(not(feature = "disk_scrollback"))]
+fn decode_lines(_data: &[u8]) -> anyhow::Result<Vec<Line>> {
+    anyhow::bail!("disk_scrollback feature is disabled")
+}
+
+#[cfg(all(test, feature = "disk_scrollback"))]
+mod test {
+    use super::*;
+
+    fn line(text: &str) -> Line {
+        Line::from_text(text, &Default::default(), 1, None)
+    }
+
+    fn texts(lines: &[Line]) -> Vec<String> {
+        lines
+            .iter()
+            .map(|line| line.as_str().into_owned())
+            .collect()
+    }
+
+    #[test]
+    fn spilled_lines_round_trip_by_stable_index() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut spill = ScrollbackSpill::new(dir.path(), 10_000).unwrap();
+        spill.extend(
+            100,
+            (0..1000).map(|idx| line(&format!("line {idx}"))).collect(),
+        );
+        assert_eq!(spill.first(), 100);
+        assert_eq!(spill.end(), 1100);
+        assert_eq!(spill.chunks.len(), 1000 / CHUNK_LINES);
+
+        // Spans two compressed chunks and the uncompressed tail
+        let (first, lines) = spill.lines_in_range(350..1050);
+        assert_eq!(first, 350);
+        assert_eq!(lines.len(), 700);
+        assert_eq!(lines[0].as_str(), "line 250");
+        assert_eq!(lines[699].as_str(), "line 949");
+
+        // Out of range requests are clamped
+        let (first, lines) = spill.lines_in_range(0..102);
+        assert_eq!(first, 100);
+        assert_eq!(texts(&lines), vec!["line 0", "line 1"]);
+    }
+
+    #[test]
+    fn oldest_chunks_are_dropped_past_the_limit() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut spill = ScrollbackSpill::new(dir.path(), CHUNK_LINES * 2).unwrap();
+        for idx in 0..(CHUNK_LINES * 4) as StableRowIndex {
+            spill.extend(idx, vec![line(&format!("line {idx}"))]);
+        }
+        assert!(spill.len <= CHUNK_LINES * 2);
+        let (first, lines) = spill.lines_in_range(0..spill.end());
+        assert_eq!(first, spill.first());
+        assert_eq!(lines.len(), spill.len);
+        assert_eq!(
+            lines.last().unwrap().as_str(),
+            format!("line {}", CHUNK_LINES * 4 - 1)
+        );
+    }
+
+    #[test]
+    fn gaps_reset_the_spill() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut spill = ScrollbackSpill::new(dir.path(), 100).unwrap();
+        spill.extend(5, vec![line("a"), line("b")]);
+        spill.extend(20, vec![line("c")]);
+        assert_eq!(spill.first(), 20);
+        assert_eq!(spill.len, 1);
+        let (_, lines) = spill.lines_in_range(0..100);
+        assert_eq!(texts(&lines), vec!["c"]);
+    }
+
+    #[test]
+    fn updated_lines_are_rewritten() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut spill = ScrollbackSpill::new(dir.path(), 10_000).unwrap();
+        spill.extend(
+            0,
+            (0..300).map(|idx| line(&format!("line {idx}"))).collect(),
+        );
+        let file_len = spill.file_len;
+
+        // One line in a compressed chunk and one that is still pending
+        spill.update_lines(vec![(10, line("ten")), (290, line("two ninety"))]);
+        assert!(spill.file_len > file_len);
+        spill.cache.clear();
+
+        let (_, lines) = spill.lines_in_range(9..12);
+        assert_eq!(texts(&lines), vec!["line 9", "ten", "line 11"]);
+        let (_, lines) = spill.lines_in_range(290..291);
+        assert_eq!(texts(&lines), vec!["two ninety"]);
+    }
+}
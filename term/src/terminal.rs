@@ -1,5 +1,6 @@
 use super::*;
 use crate::terminalstate::performer::Performer;
+use shelldone_escape_parser::kitty_file_transfer::FileTransfer;
 use shelldone_escape_parser::parser::Parser;
 use std::sync::Arc;
 
@@ -10,12 +11,67 @@ pub enum ClipboardSelection {
     PrimarySelection,
 }
 
+/// The clipboard contents for a single MIME type
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ClipboardData {
+    pub mime: String,
+    pub data: Vec<u8>,
+}
+
+/// Returns true if `mime` names plain text, using either its
+/// MIME type or one of the X11 names for it
+pub fn is_text_mime(mime: &str) -> bool {
+    let mime = mime.to_ascii_lowercase();
+    mime == "text/plain"
+        || mime.starts_with("text/plain;")
+        || mime == "utf8_string"
+        || mime == "string"
+        || mime == "text"
+}
+
+/// A request from the application to read the clipboard
+/// using the kitty clipboard protocol
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ClipboardReadRequest {
+    pub selection: ClipboardSelection,
+    /// The MIME types that the application would like to read.
+    /// `.` asks for the list of available MIME types.
+    pub mime_types: Vec<String>,
+    /// Identifies the request in the response
+    pub id: Option<String>,
+}
+
 pub trait Clipboard: Send + Sync {
     fn set_contents(
         &self,
         selection: ClipboardSelection,
         data: Option<String>,
     ) -> anyhow::Result<()>;
+
+    /// Places data for a number of MIME types in the clipboard.
+    /// The default implementation keeps only the plain text.
+    fn set_data(
+        &self,
+        selection: ClipboardSelection,
+        data: Vec<ClipboardData>,
+    ) -> anyhow::Result<()> {
+        match data.into_iter().find(|d| is_text_mime(&d.mime)) {
+            Some(text) => self.set_contents(
+                selection,
+                Some(String::from_utf8_lossy(&text.data).into_owned()),
+            ),
+            None => anyhow::bail!("only text can be placed in this clipboard"),
+        }
+    }
+
+    /// Arranges for the clipboard to be read on behalf of the
+    /// application.  The response is sent to the application
+    /// asynchronously, once the user has had the opportunity to
+    /// permit or deny the read.
+    /// Returns an error if the clipboard cannot be read.
+    fn request_read(&self, _request: ClipboardReadRequest) -> anyhow::Result<()> {
+        anyhow::bail!("reading the clipboard is not supported")
+    }
 }
 
 impl Clipboard for Box<dyn Clipboard> {
@@ -26,6 +82,18 @@ impl Clipboard for Box<dyn Clipboard> {
     ) -> anyhow::Result<()> {
         self.as_ref().set_contents(selection, data)
     }
+
+    fn set_data(
+        &self,
+        selection: ClipboardSelection,
+        data: Vec<ClipboardData>,
+    ) -> anyhow::Result<()> {
+        self.as_ref().set_data(selection, data)
+    }
+
+    fn request_read(&self, request: ClipboardReadRequest) -> anyhow::Result<()> {
+        self.as_ref().request_read(request)
+    }
 }
 
 pub trait DeviceControlHandler: Send + Sync {
@@ -42,6 +110,29 @@ pub enum Progress {
     Indeterminate,
 }
 
+/// How prominently a notification should be presented
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "use_serde", derive(Serialize, Deserialize))]
+pub enum NotificationUrgency {
+    Low,
+    #[default]
+    Normal,
+    Critical,
+}
+
+/// The circumstances under which a notification should be shown
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "use_serde", derive(Serialize, Deserialize))]
+pub enum NotificationOccasion {
+    /// Subject to the notification_handling configuration
+    #[default]
+    Always,
+    /// Only when the pane that generated it is not focused
+    Unfocused,
+    /// Only when the pane that generated it is not visible
+    Invisible,
+}
+
 #[derive(Debug, Clone, PartialEq, Eq)]
 #[cfg_attr(feature = "use_serde", derive(Serialize, Deserialize))]
 pub enum Alert {
@@ -54,6 +145,24 @@ pub enum Alert {
         /// Whether clicking on the notification should focus the
         /// window/tab/pane that generated it
         focus: bool,
+        /// Identifies the notification so that a subsequent notification
+        /// with the same id replaces it, and so that it can be closed
+        id: Option<String>,
+        urgency: NotificationUrgency,
+        occasion: NotificationOccasion,
+        /// Whether to send an OSC 99 activation report to the pane
+        /// when the notification is clicked
+        report_activation: bool,
+        /// Whether to send an OSC 99 close report to the pane
+        /// when the notification is closed
+        report_close: bool,
+        /// How long to show the notification, or None for the
+        /// system default
+        timeout: Option<std::time::Duration>,
+    },
+    /// Close the notification that was previously shown with `id`
+    CloseToastNotification {
+        id: String,
     },
     CurrentWorkingDirectoryChanged,
     IconTitleChanged(Option<String>),
@@ -71,6 +180,24 @@ pub enum Alert {
     OutputSinceFocusLost,
     /// A change to the progress bar state
     Progress(Progress),
+    /// An output trigger matched a line and asked for the
+    /// named event to be emitted
+    TriggerEvent {
+        name: String,
+        /// The text of the logical line that matched
+        line: String,
+        /// The captures of the regex; the first is the whole match
+        captures: Vec<String>,
+    },
+    /// A command that was run at the shell prompt has finished,
+    /// as delimited by the OSC 133 semantic prompt escapes
+    CommandFinished(CommandBlock),
+    /// A pane that is monitored for activity produced output
+    /// while it was not focused
+    Activity,
+    /// A pane that is monitored for silence has produced no output
+    /// for the monitored interval
+    Silence(std::time::Duration),
 }
 
 pub trait AlertHandler: Send + Sync {
@@ -81,6 +208,14 @@ pub trait DownloadHandler: Send + Sync {
     fn save_to_downloads(&self, name: Option<String>, data: Vec<u8>);
 }
 
+/// Carries out the OSC 5113 file transfers that are requested by
+/// the application.  The handler is responsible for asking the user
+/// to permit each transfer and for sending the responses, which it
+/// may do asynchronously.
+pub trait FileTransferHandler: Send + Sync {
+    fn handle_file_transfer(&self, transfer: FileTransfer);
+}
+
 /// Represents an instance of a terminal emulator.
 pub struct Terminal {
     /// The terminal model/state
@@ -0,0 +1,200 @@
+use crate::terminal::{ClipboardData, ClipboardReadRequest, ClipboardSelection};
+use crate::TerminalState;
+use shelldone_escape_parser::kitty_clipboard::{
+    KittyClipboard, KittyClipboardStatus, KittyClipboardType,
+};
+use shelldone_escape_parser::OperatingSystemCommand;
+use std::io::Write;
+
+/// Guard against an application that never completes a write
+/// from consuming unbounded memory
+const MAX_WRITE_SIZE: usize = 64 * 1024 * 1024;
+
+#[derive(Debug)]
+struct PendingWrite {
+    /// The `type=write` request that started the write
+    request: KittyClipboard,
+    data: Vec<ClipboardData>,
+    /// (alias, mime) pairs
+    aliases: Vec<(String, String)>,
+    size: usize,
+    /// Set when the write cannot succeed; the remaining chunks
+    /// are discarded and this is reported when the write completes
+    failed: Option<KittyClipboardStatus>,
+}
+
+/// Accumulates OSC 5522 writes that are sent in chunks
+#[derive(Debug, Default)]
+pub struct KittyClipboardState {
+    write: Option<PendingWrite>,
+}
+
+impl TerminalState {
+    pub(crate) fn kitty_clipboard(&mut self, clip: KittyClipboard) {
+        if clip.status.is_some() {
+            log::debug!("ignoring OSC 5522 response sent by the application: {clip:?}");
+            return;
+        }
+        let selection = if clip.primary {
+            ClipboardSelection::PrimarySelection
+        } else {
+            ClipboardSelection::Clipboard
+        };
+
+        match clip.kind {
+            KittyClipboardType::Read => {
+                let mime_types = clip.mime_list();
+                if mime_types.is_empty() {
+                    self.reply_to_kitty_clipboard(KittyClipboard::response(
+                        &clip,
+                        KittyClipboardType::Read,
+                        KittyClipboardStatus::Invalid,
+                    ));
+                    return;
+                }
+                let request = ClipboardReadRequest {
+                    selection,
+                    mime_types,
+                    id: clip.id.clone(),
+                };
+                let result = match self.clipboard.as_ref() {
+                    Some(clipboard) => clipboard.request_read(request),
+                    None => Err(anyhow::anyhow!("there is no clipboard")),
+                };
+                if let Err(err) = result {
+                    log::debug!("OSC 5522 read: {err:#}");
+                    self.reply_to_kitty_clipboard(KittyClipboard::response(
+                        &clip,
+                        KittyClipboardType::Read,
+                        KittyClipboardStatus::NotSupported,
+                    ));
+                }
+            }
+            KittyClipboardType::Write => {
+                if let Some(abandoned) = self.kitty_clipboard.write.take() {
+                    log::debug!(
+                        "OSC 5522 write {:?} was abandoned in favor of a new write",
+                        abandoned.request.id
+                    );
+                }
+                self.kitty_clipboard.write.replace(PendingWrite {
+                    request: clip,
+                    data: vec![],
+                    aliases: vec![],
+                    size: 0,
+                    failed: None,
+                });
+            }
+            KittyClipboardType::WriteData => {
+                let pending = match self.kitty_clipboard.write.as_mut() {
+                    Some(pending) => pending,
+                    None => {
+                        self.reply_to_kitty_clipboard(KittyClipboard::response(
+                            &clip,
+                            KittyClipboardType::Write,
+                            KittyClipboardStatus::Invalid,
+                        ));
+                        return;
+                    }
+                };
+                match clip.mime {
+                    Some(mime) => {
+                        if pending.failed.is_some() {
+                            return;
+                        }
+                        pending.size += clip.payload.len();
+                        if pending.size > MAX_WRITE_SIZE {
+                            log::warn!("OSC 5522 write is too large; discarding it");
+                            pending.data.clear();
+                            pending.failed.replace(KittyClipboardStatus::IoError);
+                            return;
+                        }
+                        match pending.data.iter_mut().find(|d| d.mime == mime) {
+                            Some(entry) => entry.data.extend(clip.payload),
+                            None => pending.data.push(ClipboardData {
+                                mime,
+                                data: clip.payload,
+                            }),
+                        }
+                    }
+                    None => {
+                        if let Some(pending) = self.kitty_clipboard.write.take() {
+                            self.complete_kitty_clipboard_write(pending);
+                        }
+                    }
+                }
+            }
+            KittyClipboardType::WriteAlias => {
+                let aliases = clip.mime_list();
+                match (self.kitty_clipboard.write.as_mut(), clip.mime) {
+                    (Some(pending), Some(mime)) => {
+                        for alias in aliases {
+                            pending.aliases.push((alias, mime.clone()));
+                        }
+                    }
+                    (_, mime) => {
+                        log::debug!("ignoring OSC 5522 walias for {mime:?} outside of a write");
+                    }
+                }
+            }
+        }
+    }
+
+    fn complete_kitty_clipboard_write(&mut self, pending: PendingWrite) {
+        let PendingWrite {
+            request,
+            mut data,
+            aliases,
+            failed,
+            ..
+        } = pending;
+
+        let status = if let Some(status) = failed {
+            status
+        } else {
+            for (alias, mime) in aliases {
+                if data.iter().any(|d| d.mime == alias) {
+                    continue;
+                }
+                if let Some(target) = data.iter().find(|d| d.mime == mime) {
+                    let data_for_alias = target.data.clone();
+                    data.push(ClipboardData {
+                        mime: alias,
+                        data: data_for_alias,
+                    });
+                }
+            }
+
+            let selection = if request.primary {
+                ClipboardSelection::PrimarySelection
+            } else {
+                ClipboardSelection::Clipboard
+            };
+            let result = match self.clipboard.as_ref() {
+                Some(clipboard) => clipboard.set_data(selection, data),
+                None => Err(anyhow::anyhow!("there is no clipboard")),
+            };
+            match result {
+                Ok(()) => KittyClipboardStatus::Done,
+                Err(err) => {
+                    log::error!("failed to set clipboard in response to OSC 5522: {err:#}");
+                    KittyClipboardStatus::IoError
+                }
+            }
+        };
+
+        self.reply_to_kitty_clipboard(KittyClipboard::response(
+            &request,
+            KittyClipboardType::Write,
+            status,
+        ));
+    }
+
+    fn reply_to_kitty_clipboard(&mut self, clip: KittyClipboard) {
+        let response = OperatingSystemCommand::KittyClipboard(clip);
+        if let Err(err) = write!(self.writer, "{}", response) {
+            log::error!("error while writing OSC 5522 response: {err:#}");
+        }
+        self.flush_writer_or_log();
+    }
+}
@@ -0,0 +1,251 @@
+use crate::{Alert, CommandBlock, StableRowIndex, TerminalState, VisibleRowIndex};
+use shelldone_cell::SemanticType;
+use std::collections::VecDeque;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// Tracks the commands that were run at the shell prompt in the
+/// primary screen, as reported by OSC 133
+#[derive(Debug, Default)]
+pub struct CommandBlockState {
+    blocks: VecDeque<CommandBlock>,
+    next_id: u64,
+}
+
+fn now_ms() -> Option<u64> {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .ok()
+        .map(|d| d.as_millis() as u64)
+}
+
+impl TerminalState {
+    /// Returns the command blocks whose prompts are still present
+    /// in the scrollback, oldest first
+    pub fn get_command_blocks(&self) -> Vec<CommandBlock> {
+        let top = self.screen.screen.scrollback_top();
+        self.command_blocks
+            .blocks
+            .iter()
+            .filter(|block| block.prompt_y >= top)
+            .cloned()
+            .collect()
+    }
+
+    fn cursor_stable_row(&self) -> StableRowIndex {
+        self.screen().visible_row_to_stable_row(self.cursor.y)
+    }
+
+    /// The row that follows the output that has been produced so far
+    fn end_of_output_row(&self) -> StableRowIndex {
+        let row = self.cursor_stable_row();
+        if self.cursor.x == 0 {
+            row
+        } else {
+            row + 1
+        }
+    }
+
+    fn prune_command_blocks(&mut self) {
+        let top = self.screen.screen.scrollback_top();
+        while let Some(block) = self.command_blocks.blocks.front() {
+            if block.prompt_y >= top {
+                break;
+            }
+            self.command_blocks.blocks.pop_front();
+        }
+    }
+
+    /// Called when the shell starts to draw its primary prompt
+    pub(crate) fn command_block_prompt_started(&mut self) {
+        if self.screen.is_alt_screen_active() {
+            return;
+        }
+        self.prune_command_blocks();
+        let prompt_y = self.cursor_stable_row();
+        let end_y = self.end_of_output_row();
+
+        if let Some(last) = self.command_blocks.blocks.back_mut() {
+            if last.output_y.is_none() && last.exit_status.is_none() {
+                // The shell is redrawing the prompt, perhaps in response
+                // to a resize; this is still the same block
+                last.prompt_y = prompt_y;
+                return;
+            }
+            if last.end_y.is_none() {
+                // The shell didn't report the status of the prior command
+                last.end_y.replace(end_y.min(prompt_y).max(last.prompt_y));
+                last.end_time_ms = now_ms();
+                let finished = last.clone();
+                self.command_block_alert(finished);
+            }
+        }
+
+        let id = self.command_blocks.next_id;
+        self.command_blocks.next_id += 1;
+        self.command_blocks.blocks.push_back(CommandBlock {
+            id,
+            prompt_y,
+            output_y: None,
+            end_y: None,
+            command: String::new(),
+            exit_status: None,
+            start_time_ms: None,
+            end_time_ms: None,
+        });
+    }
+
+    /// Called when the command has been entered and its output begins
+    pub(crate) fn command_block_output_started(&mut self) {
+        if self.screen.is_alt_screen_active() {
+            return;
+        }
+        let output_y = self.cursor_stable_row();
+        let Some(prompt_y) = self.command_blocks.blocks.back().map(|b| b.prompt_y) else {
+            return;
+        };
+        let command = self.command_text(prompt_y, output_y);
+        if let Some(block) = self.command_blocks.blocks.back_mut() {
+            if block.output_y.is_some() {
+                return;
+            }
+            block.output_y.replace(output_y);
+            block.command = command;
+            block.start_time_ms = now_ms();
+        }
+    }
+
+    /// Called when the shell reports the exit status of the command
+    pub(crate) fn command_block_finished(&mut self, status: i32) {
+        if self.screen.is_alt_screen_active() {
+            return;
+        }
+        let end_y = self.end_of_output_row();
+        if let Some(block) = self.command_blocks.blocks.back_mut() {
+            if block.exit_status.is_some() {
+                return;
+            }
+            block.exit_status.replace(status);
+            block.end_time_ms = now_ms();
+            block.end_y.replace(end_y.max(block.prompt_y));
+            let finished = block.clone();
+            self.command_block_alert(finished);
+        }
+    }
+
+    fn command_block_alert(&mut self, block: CommandBlock) {
+        if block.output_y.is_none() {
+            // The prompt was abandoned without running a command
+            return;
+        }
+        if let Some(handler) = self.alert_handler.as_mut() {
+            handler.alert(Alert::CommandFinished(block));
+        }
+    }
+
+    /// Returns the text of the cells that were marked as input
+    /// in the rows from `prompt_y` through `last_y`
+    fn command_text(&self, prompt_y: StableRowIndex, last_y: StableRowIndex) -> String {
+        let (_first, lines) = self.screen().lines_in_stable_range(&(prompt_y..last_y + 1));
+        let mut command = String::new();
+        for line in &lines {
+            let mut text = String::new();
+            for cell in line.visible_cells() {
+                if cell.attrs().semantic_type() == SemanticType::Input {
+                    text.push_str(cell.str());
+                }
+            }
+            if line.last_cell_was_wrapped() {
+                command.push_str(&text);
+            } else {
+                command.push_str(text.trim_end());
+                command.push('\n');
+            }
+        }
+        command.trim().to_string()
+    }
+
+    /// Returns the first row of each logical line in the portion of
+    /// the primary screen that is held in memory, up to and including
+    /// the line that holds the cursor.  The blank lines below the
+    /// cursor are excluded because resizing may discard them.
+    fn logical_line_starts(&self, cursor_y: VisibleRowIndex) -> Vec<StableRowIndex> {
+        let screen = &self.screen.screen;
+        let cursor_row = screen.visible_row_to_stable_row(cursor_y);
+        let mut starts = vec![];
+        let mut prior_wrapped = false;
+        screen.for_each_phys_line(|idx, line| {
+            let row = screen.phys_to_stable_row_index(idx);
+            if !prior_wrapped && row <= cursor_row {
+                starts.push(row);
+            }
+            prior_wrapped = line.last_cell_was_wrapped();
+        });
+        starts
+    }
+
+    /// Rewrapping the primary screen changes the stable rows of its
+    /// lines, but not the sequence of logical lines.  The rows of the
+    /// command blocks are moved to the same logical lines in the
+    /// rewrapped screen; `before` holds the logical line starts
+    /// prior to the rewrap and `cursor_y` is the position of the cursor
+    /// of the primary screen after it.
+    pub(crate) fn reanchor_command_blocks(
+        &mut self,
+        before: &[StableRowIndex],
+        cursor_y: VisibleRowIndex,
+    ) {
+        let after = self.logical_line_starts(cursor_y);
+        let (Some(&first), false) = (before.first(), after.is_empty()) else {
+            return;
+        };
+        // Lines that no longer fit are removed from the top of the scrollback
+        let removed = before.len().saturating_sub(after.len());
+
+        let map_row = |row: StableRowIndex| -> Option<StableRowIndex> {
+            if row < first {
+                // Spilled to disk, which is not rewrapped
+                return Some(row);
+            }
+            let idx = before.partition_point(|&start| start <= row) - 1;
+            let new_idx = idx.checked_sub(removed)?;
+            let new_start = *after.get(new_idx)?;
+            let delta = row - before[idx];
+            let limit = after
+                .get(new_idx + 1)
+                .map(|next| next - new_start)
+                .unwrap_or(delta + 1);
+            Some(new_start + delta.min(limit.saturating_sub(1)))
+        };
+
+        let mut blocks = VecDeque::with_capacity(self.command_blocks.blocks.len());
+        for mut block in self.command_blocks.blocks.drain(..) {
+            let Some(prompt_y) = map_row(block.prompt_y) else {
+                continue;
+            };
+            block.prompt_y = prompt_y;
+            block.output_y = block.output_y.and_then(map_row);
+            // The end is the row that follows the output, which may not
+            // exist yet; map it relative to the last row of output
+            block.end_y = block
+                .end_y
+                .and_then(|end| map_row(end - 1).map(|row| row + 1));
+            blocks.push_back(block);
+        }
+        self.command_blocks.blocks = blocks;
+    }
+
+    /// Returns the information that `reanchor_command_blocks` needs
+    /// in order to follow a resize to `cols` columns, or None if the
+    /// resize cannot move the blocks.
+    /// `cursor_y` is the position of the cursor of the primary screen.
+    pub(crate) fn command_block_anchors(
+        &self,
+        cols: usize,
+        cursor_y: VisibleRowIndex,
+    ) -> Option<Vec<StableRowIndex>> {
+        if cols == self.screen.screen.physical_cols || self.command_blocks.blocks.is_empty() {
+            return None;
+        }
+        Some(self.logical_line_starts(cursor_y))
+    }
+}
@@ -0,0 +1,36 @@
+use crate::TerminalState;
+use shelldone_escape_parser::kitty_file_transfer::{FileTransfer, FileTransferAction};
+use shelldone_escape_parser::OperatingSystemCommand;
+use std::io::Write;
+
+impl TerminalState {
+    pub(crate) fn kitty_file_transfer(&mut self, transfer: FileTransfer) {
+        if transfer.action == FileTransferAction::Status {
+            log::debug!("ignoring OSC 5113 status sent by the application: {transfer:?}");
+            return;
+        }
+        match self.file_transfer_handler.as_ref() {
+            Some(handler) => handler.handle_file_transfer(transfer),
+            None => {
+                // Only the session-starting requests are answered, so
+                // that the application isn't flooded with errors for
+                // each of its chunks of data
+                if matches!(
+                    transfer.action,
+                    FileTransferAction::Send | FileTransferAction::Receive
+                ) && transfer.quiet < 2
+                {
+                    let response = OperatingSystemCommand::KittyFileTransfer(FileTransfer::error(
+                        &transfer,
+                        "ENOSYS",
+                        "file transfer is not supported",
+                    ));
+                    if let Err(err) = write!(self.writer, "{}", response) {
+                        log::error!("error while writing OSC 5113 response: {err:#}");
+                    }
+                    self.flush_writer_or_log();
+                }
+            }
+        }
+    }
+}
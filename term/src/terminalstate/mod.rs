@@ -25,15 +25,23 @@ use terminfo::{Database, Value};
 use termwiz::input::KeyboardEncoding;
 use url::Url;
 
+mod clipboard;
+mod command_blocks;
+mod file_transfer;
 mod image;
 mod iterm;
 mod keyboard;
 mod kitty;
 mod mouse;
+mod notification;
 pub(crate) mod performer;
 mod sixel;
+mod text_sizing;
+use crate::terminalstate::clipboard::*;
+use crate::terminalstate::command_blocks::*;
 use crate::terminalstate::image::*;
 use crate::terminalstate::kitty::*;
+use crate::terminalstate::notification::*;
 
 lazy_static::lazy_static! {
     static ref DB: Database = {
@@ -337,6 +345,7 @@ pub struct TerminalState {
     device_control_handler: Option<Box<dyn DeviceControlHandler>>,
     alert_handler: Option<Box<dyn AlertHandler>>,
     download_handler: Option<Arc<dyn DownloadHandler>>,
+    file_transfer_handler: Option<Arc<dyn FileTransferHandler>>,
 
     current_dir: Option<Url>,
 
@@ -351,6 +360,9 @@ pub struct TerminalState {
     user_vars: HashMap<String, String>,
 
     kitty_img: KittyImageState,
+    kitty_notifications: KittyNotificationState,
+    kitty_clipboard: KittyClipboardState,
+    command_blocks: CommandBlockState,
     seqno: SequenceNo,
 
     /// The unicode version that is in effect
@@ -552,6 +564,7 @@ impl TerminalState {
             device_control_handler: None,
             alert_handler: None,
             download_handler: None,
+            file_transfer_handler: None,
             current_dir: None,
             term_program: term_program.to_string(),
             term_version: term_version.to_string(),
@@ -559,6 +572,9 @@ impl TerminalState {
             image_cache: lru::LruCache::new(NonZeroUsize::new(16).unwrap()),
             user_vars: HashMap::new(),
             kitty_img: Default::default(),
+            kitty_notifications: Default::default(),
+            kitty_clipboard: Default::default(),
+            command_blocks: Default::default(),
             seqno,
             unicode_version,
             unicode_version_stack: vec![],
@@ -623,6 +639,10 @@ impl TerminalState {
         self.download_handler.replace(handler.clone());
     }
 
+    pub fn set_file_transfer_handler(&mut self, handler: &Arc<dyn FileTransferHandler>) {
+        self.file_transfer_handler.replace(handler.clone());
+    }
+
     /// Returns the title text associated with the terminal session.
     /// The title can be changed by the application using a number
     /// of escape sequences:
@@ -878,6 +898,7 @@ impl TerminalState {
             )
         };
 
+        let command_block_anchors = self.command_block_anchors(size.cols, cursor_main.y);
         let (adjusted_cursor_main, adjusted_cursor_alt) = self.screen.resize(
             size,
             cursor_main,
@@ -891,6 +912,9 @@ impl TerminalState {
         self.pixel_width = size.pixel_width;
         self.dpi = size.dpi;
         self.tabs.resize(size.cols);
+        if let Some(anchors) = command_block_anchors {
+            self.reanchor_command_blocks(&anchors, adjusted_cursor_main.y);
+        }
 
         if self.screen.alt_screen_is_active {
             self.set_cursor_pos(
@@ -0,0 +1,163 @@
+use crate::terminal::{Alert, NotificationOccasion, NotificationUrgency};
+use crate::TerminalState;
+use shelldone_escape_parser::kitty_notification::{
+    KittyNotification, KittyNotificationOccasion, KittyNotificationPayload,
+    KittyNotificationUrgency,
+};
+use shelldone_escape_parser::OperatingSystemCommand;
+use std::collections::HashMap;
+use std::io::Write;
+use std::time::Duration;
+
+/// Guard against an application that never completes a chunked
+/// notification from consuming unbounded memory
+const MAX_PAYLOAD: usize = 64 * 1024;
+/// Likewise for an application that starts many notifications,
+/// each with a different id, without completing them
+const MAX_PENDING: usize = 32;
+
+#[derive(Debug, Default)]
+struct PendingNotification {
+    /// Orders the pending notifications by when they were started
+    started: u64,
+    /// Merged metadata from the chunks received so far
+    meta: KittyNotification,
+    title: Vec<u8>,
+    body: Vec<u8>,
+}
+
+/// Accumulates OSC 99 notifications that are sent in chunks
+#[derive(Debug, Default)]
+pub struct KittyNotificationState {
+    /// Keyed by the notification id, or the empty string
+    /// for notifications that don't have an id
+    pending: HashMap<String, PendingNotification>,
+    next_started: u64,
+}
+
+impl KittyNotificationState {
+    fn pending_entry(&mut self, key: &str) -> &mut PendingNotification {
+        if !self.pending.contains_key(key) {
+            if self.pending.len() >= MAX_PENDING {
+                let oldest = self
+                    .pending
+                    .iter()
+                    .min_by_key(|(_, pending)| pending.started)
+                    .map(|(key, _)| key.clone());
+                if let Some(oldest) = oldest {
+                    log::warn!("too many incomplete OSC 99 notifications; discarding {oldest:?}");
+                    self.pending.remove(&oldest);
+                }
+            }
+            self.next_started += 1;
+            self.pending.insert(
+                key.to_string(),
+                PendingNotification {
+                    started: self.next_started,
+                    ..Default::default()
+                },
+            );
+        }
+        self.pending.get_mut(key).expect("inserted above")
+    }
+}
+
+impl TerminalState {
+    pub(crate) fn kitty_notification(&mut self, mut notif: KittyNotification) {
+        let key = notif.id.clone().unwrap_or_default();
+        match notif.payload_type {
+            KittyNotificationPayload::Query => {
+                self.reply_to_kitty_notification(KittyNotification::capabilities(notif.id));
+            }
+            KittyNotificationPayload::Close => {
+                self.kitty_notifications.pending.remove(&key);
+                if let Some(id) = notif.id {
+                    self.notification_alert(Alert::CloseToastNotification { id });
+                }
+            }
+            KittyNotificationPayload::Alive
+            | KittyNotificationPayload::Icon
+            | KittyNotificationPayload::Buttons => {
+                log::debug!(
+                    "ignoring unsupported OSC 99 payload type {:?}",
+                    notif.payload_type
+                );
+            }
+            KittyNotificationPayload::Title | KittyNotificationPayload::Body => {
+                let payload = std::mem::take(&mut notif.payload);
+                let done = notif.done;
+                let pending = self.kitty_notifications.pending_entry(&key);
+                let target = if notif.payload_type == KittyNotificationPayload::Title {
+                    &mut pending.title
+                } else {
+                    &mut pending.body
+                };
+                if target.len() + payload.len() > MAX_PAYLOAD {
+                    log::warn!("OSC 99 notification {key:?} is too large; discarding it");
+                    self.kitty_notifications.pending.remove(&key);
+                    return;
+                }
+                target.extend(payload);
+                pending.meta.merge(notif);
+
+                if done {
+                    if let Some(pending) = self.kitty_notifications.pending.remove(&key) {
+                        self.show_kitty_notification(pending);
+                    }
+                }
+            }
+        }
+    }
+
+    fn show_kitty_notification(&mut self, pending: PendingNotification) {
+        let title = String::from_utf8_lossy(&pending.title).into_owned();
+        let body = String::from_utf8_lossy(&pending.body).into_owned();
+        let (title, body) = match (title.is_empty(), body.is_empty()) {
+            (true, true) => return,
+            (true, false) => (None, body),
+            (false, _) => (Some(title), body),
+        };
+        let meta = pending.meta;
+
+        self.notification_alert(Alert::ToastNotification {
+            title,
+            body,
+            focus: meta.focus.unwrap_or(true),
+            // We can only report back about notifications that the
+            // application can identify
+            report_activation: meta.id.is_some() && meta.report.unwrap_or(false),
+            report_close: meta.id.is_some() && meta.report_close.unwrap_or(false),
+            id: meta.id,
+            urgency: match meta.urgency {
+                Some(KittyNotificationUrgency::Low) => NotificationUrgency::Low,
+                None | Some(KittyNotificationUrgency::Normal) => NotificationUrgency::Normal,
+                Some(KittyNotificationUrgency::Critical) => NotificationUrgency::Critical,
+            },
+            occasion: match meta.occasion {
+                None | Some(KittyNotificationOccasion::Always) => NotificationOccasion::Always,
+                Some(KittyNotificationOccasion::Unfocused) => NotificationOccasion::Unfocused,
+                Some(KittyNotificationOccasion::Invisible) => NotificationOccasion::Invisible,
+            },
+            timeout: match meta.expire_ms {
+                Some(ms) if ms > 0 => Some(Duration::from_millis(ms as u64)),
+                _ => None,
+            },
+        });
+    }
+
+    fn notification_alert(&mut self, alert: Alert) {
+        if let Some(handler) = self.alert_handler.as_mut() {
+            handler.alert(alert);
+        } else {
+            log::info!("Application sends notification: {:?}", alert);
+        }
+    }
+
+    fn reply_to_kitty_notification(&mut self, notif: KittyNotification) {
+        let response = OperatingSystemCommand::KittyNotification(notif);
+        if let Err(err) = write!(self.writer, "{}", response) {
+            log::error!("error while writing OSC 99 response: {err:#}");
+        }
+        self.flush_writer_or_log();
+    }
+}
@@ -1,4 +1,4 @@
-use crate::terminal::{Alert, Progress};
+use crate::terminal::{Alert, NotificationOccasion, NotificationUrgency, Progress};
 use crate::terminalstate::{
     default_color_map, CharSet, MouseEncoding, TabStop, UnicodeVersionStackEntry,
 };
@@ -15,7 +15,7 @@ use shelldone_escape_parser::csi::{
     CharacterPath, EraseInDisplay, Keyboard, KittyKeyboardFlags, KittyKeyboardMode,
 };
 use shelldone_escape_parser::osc::{
-    ChangeColorPair, ColorOrQuery, FinalTermSemanticPrompt, ITermProprietary,
+    ChangeColorPair, ColorOrQuery, FinalTermPromptKind, FinalTermSemanticPrompt, ITermProprietary,
     ITermUnicodeVersionOp, Selection,
 };
 use shelldone_escape_parser::{
@@ -863,17 +863,26 @@ impl<'a> Performer<'a> {
                 FinalTermSemanticPrompt::FreshLineAndStartPrompt { .. },
             ) => {
                 self.fresh_line();
+                self.command_block_prompt_started();
                 self.pen.set_semantic_type(SemanticType::Prompt);
             }
             OperatingSystemCommand::FinalTermSemanticPrompt(
-                FinalTermSemanticPrompt::StartPrompt(_),
+                FinalTermSemanticPrompt::StartPrompt(kind),
             ) => {
+                // Shells that don't emit OSC 133;A begin the primary
+                // prompt with this instead
+                if kind == FinalTermPromptKind::Initial
+                    && self.pen.semantic_type() != SemanticType::Prompt
+                {
+                    self.command_block_prompt_started();
+                }
                 self.pen.set_semantic_type(SemanticType::Prompt);
             }
             OperatingSystemCommand::FinalTermSemanticPrompt(
                 FinalTermSemanticPrompt::MarkEndOfCommandWithFreshLine { .. },
             ) => {
                 self.fresh_line();
+                self.command_block_prompt_started();
                 self.pen.set_semantic_type(SemanticType::Prompt);
             }
             OperatingSystemCommand::FinalTermSemanticPrompt(
@@ -891,11 +900,14 @@ impl<'a> Performer<'a> {
                 FinalTermSemanticPrompt::MarkEndOfInputAndStartOfOutput { .. },
             ) => {
                 self.pen.set_semantic_type(SemanticType::Output);
+                self.command_block_output_started();
             }
 
             OperatingSystemCommand::FinalTermSemanticPrompt(
-                FinalTermSemanticPrompt::CommandStatus { .. },
-            ) => {}
+                FinalTermSemanticPrompt::CommandStatus { status, .. },
+            ) => {
+                self.command_block_finished(status);
+            }
 
             OperatingSystemCommand::SystemNotification(message) => {
                 if let Some(handler) = self.alert_handler.as_mut() {
@@ -903,6 +915,12 @@ impl<'a> Performer<'a> {
                         title: None,
                         body: message,
                         focus: true,
+                        id: None,
+                        urgency: NotificationUrgency::Normal,
+                        occasion: NotificationOccasion::Always,
+                        report_activation: false,
+                        report_close: false,
+                        timeout: None,
                     });
                 } else {
                     log::info!("Application sends SystemNotification: {}", message);
@@ -925,10 +943,28 @@ impl<'a> Performer<'a> {
                             title,
                             body,
                             focus: true,
+                            id: None,
+                            urgency: NotificationUrgency::Normal,
+                            occasion: NotificationOccasion::Always,
+                            report_activation: false,
+                            report_close: false,
+                            timeout: None,
                         });
                     }
                 }
             }
+            OperatingSystemCommand::KittyNotification(notif) => {
+                self.kitty_notification(notif);
+            }
+            OperatingSystemCommand::KittyClipboard(clip) => {
+                self.kitty_clipboard(clip);
+            }
+            OperatingSystemCommand::KittyFileTransfer(transfer) => {
+                self.kitty_file_transfer(transfer);
+            }
+            OperatingSystemCommand::SizedText(sized) => {
+                self.print_sized_text(sized);
+            }
             OperatingSystemCommand::CurrentWorkingDirectory(url) => {
                 self.current_dir = Url::parse(&url).ok();
                 if let Some(handler) = self.alert_handler.as_mut() {
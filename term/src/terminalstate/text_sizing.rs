@@ -0,0 +1,82 @@
+use crate::TerminalState;
+use finl_unicode::grapheme_clusters::Graphemes;
+use shelldone_cell::{grapheme_column_width, TextSizeCell};
+use shelldone_escape_parser::text_sizing::{SizedText, TextSize};
+
+impl TerminalState {
+    /// Prints text that was sized using OSC 66.
+    /// Each block of scaled text occupies `scale` rows, starting with
+    /// the cursor row; the cell in the cursor row holds the text and
+    /// spans the width of the block, while the cells beneath it are
+    /// blank placeholders that record their row within the block.
+    pub(crate) fn print_sized_text(&mut self, sized: SizedText) {
+        let size = sized.size;
+        let scale = size.scale as usize;
+
+        if size.width == 0 {
+            // Each grapheme occupies its natural width, scaled
+            let mut blocks: Vec<(String, usize)> = vec![];
+            for g in Graphemes::new(&sized.text) {
+                let width = grapheme_column_width(g, Some(&self.unicode_version));
+                match (width, blocks.last_mut()) {
+                    // Keep zero-width graphemes with the preceding text
+                    (0, Some((text, _))) => text.push_str(g),
+                    (0, None) => {}
+                    (width, _) => blocks.push((g.to_string(), width * scale)),
+                }
+            }
+            for (text, cols) in blocks {
+                self.print_text_block(&text, cols, size);
+            }
+        } else if !sized.text.is_empty() {
+            self.print_text_block(&sized.text, size.width as usize * scale, size);
+        }
+    }
+
+    fn print_text_block(&mut self, text: &str, cols: usize, size: TextSize) {
+        let seqno = self.seqno;
+        let scale = size.scale as usize;
+        let margins = self.left_and_right_margins.clone();
+        let rows = (self.top_and_bottom_margins.end - self.top_and_bottom_margins.start) as usize;
+
+        if cols > margins.end - margins.start || scale > rows {
+            log::debug!("OSC 66 text {text:?} needs {cols}x{scale} cells, which cannot fit");
+            return;
+        }
+
+        if self.wrap_next || self.cursor.x + cols > margins.end {
+            let y = self.cursor.y;
+            let screen = self.screen_mut();
+            let y = screen.phys_row(y);
+            screen.line_mut(y).set_last_cell_was_wrapped(true, seqno);
+            self.new_line(true);
+        }
+
+        // Make room for the rows below the cursor, scrolling if needed,
+        // and then return to the top row of the block
+        for _ in 1..scale {
+            self.new_line(false);
+        }
+        self.cursor.y -= scale as i64 - 1;
+
+        let x = self.cursor.x;
+        let top = self.cursor.y;
+        for row in 0..scale {
+            let mut attrs = self.pen.clone();
+            attrs.set_text_size(Some(TextSizeCell {
+                size,
+                row: row as u8,
+            }));
+            let text = if row == 0 { text } else { " " };
+            self.screen_mut()
+                .set_cell_grapheme(x, top + row as i64, text, cols, attrs, seqno);
+        }
+
+        if x + cols >= margins.end {
+            self.wrap_next = self.dec_auto_wrap;
+        } else {
+            self.cursor.x += cols;
+            self.wrap_next = false;
+        }
+    }
+}
@@ -0,0 +1,180 @@
+//! Testing the tracking of command blocks via OSC 133
+
+use super::*;
+use shelldone_escape_parser::osc::FinalTermSemanticPrompt;
+
+fn osc133(term: &mut TestTerm, prompt: FinalTermSemanticPrompt) {
+    term.print(format!(
+        "{}",
+        OperatingSystemCommand::FinalTermSemanticPrompt(prompt)
+    ));
+}
+
+fn start_prompt(term: &mut TestTerm) {
+    osc133(
+        term,
+        FinalTermSemanticPrompt::FreshLineAndStartPrompt {
+            aid: None,
+            cl: None,
+        },
+    );
+    term.print("> ");
+    osc133(
+        term,
+        FinalTermSemanticPrompt::MarkEndOfPromptAndStartOfInputUntilNextMarker,
+    );
+}
+
+fn run_command(term: &mut TestTerm, command: &str, output: &str, status: i32) {
+    term.print(format!("{command}\r\n"));
+    osc133(
+        term,
+        FinalTermSemanticPrompt::MarkEndOfInputAndStartOfOutput { aid: None },
+    );
+    term.print(output);
+    osc133(
+        term,
+        FinalTermSemanticPrompt::CommandStatus { status, aid: None },
+    );
+}
+
+type BlockSummary = (
+    StableRowIndex,
+    Option<StableRowIndex>,
+    Option<StableRowIndex>,
+    String,
+    Option<i32>,
+);
+
+fn summarize(term: &TestTerm) -> Vec<BlockSummary> {
+    term.get_command_blocks()
+        .into_iter()
+        .map(|block| {
+            (
+                block.prompt_y,
+                block.output_y,
+                block.end_y,
+                block.command,
+                block.exit_status,
+            )
+        })
+        .collect()
+}
+
+#[test]
+fn command_blocks() {
+    let mut term = TestTerm::new(10, 10, 0);
+    start_prompt(&mut term);
+    run_command(&mut term, "ls -l", "one\r\ntwo\r\n", 1);
+    start_prompt(&mut term);
+
+    assert_visible_contents(
+        &term,
+        file!(),
+        line!(),
+        &["> ls -l", "one", "two", ">", "", "", "", "", "", ""],
+    );
+    assert_eq!(
+        summarize(&term),
+        vec![
+            (0, Some(1), Some(3), "ls -l".to_string(), Some(1)),
+            (3, None, None, String::new(), None),
+        ]
+    );
+
+    let blocks = term.get_command_blocks();
+    assert!(blocks[0].duration().is_some());
+    assert!(!blocks[0].is_running());
+    assert_eq!(blocks[0].output_rows(5), Some(1..3));
+}
+
+#[test]
+fn command_blocks_redrawn_prompt() {
+    let mut term = TestTerm::new(10, 10, 0);
+    start_prompt(&mut term);
+    // The shell redraws its prompt without a command having run
+    term.print("\r");
+    start_prompt(&mut term);
+    run_command(&mut term, "true", "", 0);
+
+    assert_eq!(
+        summarize(&term),
+        vec![(0, Some(1), Some(1), "true".to_string(), Some(0))]
+    );
+}
+
+#[test]
+fn command_blocks_running() {
+    let mut term = TestTerm::new(10, 10, 0);
+    start_prompt(&mut term);
+    term.print("sleep\r\n");
+    osc133(
+        &mut term,
+        FinalTermSemanticPrompt::MarkEndOfInputAndStartOfOutput { aid: None },
+    );
+    term.print("zzz");
+
+    let blocks = term.get_command_blocks();
+    assert!(blocks[0].is_running());
+    assert_eq!(blocks[0].output_rows(3), Some(1..3));
+
+    // A shell that doesn't report the status still ends the block
+    // when it starts the next prompt
+    start_prompt(&mut term);
+    assert_eq!(
+        summarize(&term),
+        vec![
+            (0, Some(1), Some(2), "sleep".to_string(), None),
+            (2, None, None, String::new(), None),
+        ]
+    );
+}
+
+#[test]
+fn command_blocks_follow_rewrap() {
+    let mut term = TestTerm::new(10, 10, 0);
+    start_prompt(&mut term);
+    run_command(&mut term, "echo", "0123456789abcdef\r\n", 0);
+    start_prompt(&mut term);
+
+    assert_eq!(
+        summarize(&term),
+        vec![
+            (0, Some(1), Some(3), "echo".to_string(), Some(0)),
+            (3, None, None, String::new(), None),
+        ]
+    );
+
+    term.resize(TerminalSize {
+        rows: 10,
+        cols: 20,
+        pixel_width: 160,
+        pixel_height: 160,
+        dpi: 0,
+    });
+
+    assert_visible_contents(
+        &term,
+        file!(),
+        line!(),
+        &[
+            "> echo",
+            "0123456789abcdef",
+            ">",
+            "",
+            "",
+            "",
+            "",
+            "",
+            "",
+            "",
+        ],
+    );
+    assert_eq!(
+        summarize(&term),
+        vec![
+            (0, Some(1), Some(2), "echo".to_string(), Some(0)),
+            (2, None, None, String::new(), None),
+        ]
+    );
+}
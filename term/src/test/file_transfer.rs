@@ -0,0 +1,66 @@
+//! Testing the kitty file transfer protocol, OSC 5113
+
+use super::kitty_clipboard::Output;
+use super::*;
+use shelldone_escape_parser::kitty_file_transfer::{FileTransfer, FileTransferAction};
+
+#[derive(Default)]
+struct RecordingHandler {
+    transfers: Mutex<Vec<FileTransfer>>,
+}
+
+impl FileTransferHandler for RecordingHandler {
+    fn handle_file_transfer(&self, transfer: FileTransfer) {
+        self.transfers.lock().unwrap().push(transfer);
+    }
+}
+
+fn transfer_term(handler: Option<Arc<RecordingHandler>>) -> (Terminal, Output) {
+    let output = Output::default();
+    let mut term = Terminal::new(
+        TerminalSize::default(),
+        Arc::new(TestTermConfig { scrollback: 0 }),
+        "Shelldone",
+        "O_o",
+        Box::new(output.clone()),
+    );
+    if let Some(handler) = handler {
+        let handler: Arc<dyn FileTransferHandler> = handler;
+        term.set_file_transfer_handler(&handler);
+    }
+    (term, output)
+}
+
+#[test]
+fn requests_are_delegated_to_the_handler() {
+    let handler = Arc::new(RecordingHandler::default());
+    let (mut term, _output) = transfer_term(Some(Arc::clone(&handler)));
+
+    term.advance_bytes("\x1b]5113;ac=send;id=s\x1b\\");
+    term.advance_bytes("\x1b]5113;ac=file;id=s;fid=f;n=YS50eHQ=\x1b\\");
+    term.advance_bytes("\x1b]5113;ac=end_data;id=s;fid=f;d=aGk=\x1b\\");
+    // Status is only ever sent by the terminal
+    term.advance_bytes("\x1b]5113;ac=status;id=s;st=T0s=\x1b\\");
+
+    let transfers = handler.transfers.lock().unwrap();
+    assert_eq!(
+        transfers.iter().map(|t| t.action).collect::<Vec<_>>(),
+        vec![
+            FileTransferAction::Send,
+            FileTransferAction::File,
+            FileTransferAction::EndData
+        ]
+    );
+    assert_eq!(transfers[1].name.as_deref(), Some("a.txt"));
+    assert_eq!(transfers[2].data, b"hi".to_vec());
+}
+
+#[test]
+fn transfer_without_handler_is_not_supported() {
+    let (mut term, output) = transfer_term(None);
+    term.advance_bytes("\x1b]5113;ac=send;id=s\x1b\\");
+    assert_eq!(
+        output.wait_for_text(),
+        "\x1b]5113;ac=status;id=s;st=RU5PU1lTOmZpbGUgdHJhbnNmZXIgaXMgbm90IHN1cHBvcnRlZA==\x1b\\"
+    );
+}
@@ -0,0 +1,169 @@
+//! Testing the kitty clipboard protocol, OSC 5522
+
+use super::*;
+use std::time::{Duration, Instant};
+
+#[derive(Default)]
+struct RichClip {
+    data: Mutex<Vec<(ClipboardSelection, Vec<ClipboardData>)>>,
+    reads: Mutex<Vec<ClipboardReadRequest>>,
+}
+
+impl Clipboard for RichClip {
+    fn set_contents(
+        &self,
+        selection: ClipboardSelection,
+        clip: Option<String>,
+    ) -> anyhow::Result<()> {
+        self.set_data(
+            selection,
+            clip.map(|text| ClipboardData {
+                mime: "text/plain".to_string(),
+                data: text.into_bytes(),
+            })
+            .into_iter()
+            .collect(),
+        )
+    }
+
+    fn set_data(
+        &self,
+        selection: ClipboardSelection,
+        data: Vec<ClipboardData>,
+    ) -> anyhow::Result<()> {
+        self.data.lock().unwrap().push((selection, data));
+        Ok(())
+    }
+
+    fn request_read(&self, request: ClipboardReadRequest) -> anyhow::Result<()> {
+        self.reads.lock().unwrap().push(request);
+        Ok(())
+    }
+}
+
+#[derive(Clone, Default)]
+pub(super) struct Output {
+    data: Arc<Mutex<Vec<u8>>>,
+}
+
+impl std::io::Write for Output {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        self.data.lock().unwrap().extend_from_slice(buf);
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        Ok(())
+    }
+}
+
+impl Output {
+    /// Responses are written from another thread, so wait a little
+    /// while for them to show up
+    pub(super) fn wait_for_text(&self) -> String {
+        let deadline = Instant::now() + Duration::from_secs(5);
+        loop {
+            let data = std::mem::take(&mut *self.data.lock().unwrap());
+            if !data.is_empty() || Instant::now() > deadline {
+                return String::from_utf8(data).unwrap();
+            }
+            std::thread::sleep(Duration::from_millis(10));
+        }
+    }
+}
+
+fn clip_term(clip: Option<Arc<RichClip>>) -> (Terminal, Output) {
+    let output = Output::default();
+    let mut term = Terminal::new(
+        TerminalSize::default(),
+        Arc::new(TestTermConfig { scrollback: 0 }),
+        "Shelldone",
+        "O_o",
+        Box::new(output.clone()),
+    );
+    if let Some(clip) = clip {
+        let clip: Arc<dyn Clipboard> = clip;
+        term.set_clipboard(&clip);
+    }
+    (term, output)
+}
+
+#[test]
+fn chunked_write_with_alias() {
+    let clip = Arc::new(RichClip::default());
+    let (mut term, output) = clip_term(Some(Arc::clone(&clip)));
+
+    term.advance_bytes("\x1b]5522;type=write:id=w1\x1b\\");
+    // "text/html" in two chunks: "<b>hi" and "</b>"
+    term.advance_bytes("\x1b]5522;type=wdata:mime=dGV4dC9odG1s;PGI+aGk=\x1b\\");
+    term.advance_bytes("\x1b]5522;type=wdata:mime=dGV4dC9odG1s;PC9iPg==\x1b\\");
+    // "text/plain": "hi"
+    term.advance_bytes("\x1b]5522;type=wdata:mime=dGV4dC9wbGFpbg==;aGk=\x1b\\");
+    // alias "UTF8_STRING" to text/plain
+    term.advance_bytes("\x1b]5522;type=walias:mime=dGV4dC9wbGFpbg==;VVRGOF9TVFJJTkc=\x1b\\");
+    assert!(clip.data.lock().unwrap().is_empty());
+
+    term.advance_bytes("\x1b]5522;type=wdata\x1b\\");
+    assert_eq!(
+        output.wait_for_text(),
+        "\x1b]5522;type=write:status=DONE:id=w1\x1b\\"
+    );
+    assert_eq!(
+        clip.data.lock().unwrap().clone(),
+        vec![(
+            ClipboardSelection::Clipboard,
+            vec![
+                ClipboardData {
+                    mime: "text/html".to_string(),
+                    data: b"<b>hi</b>".to_vec(),
+                },
+                ClipboardData {
+                    mime: "text/plain".to_string(),
+                    data: b"hi".to_vec(),
+                },
+                ClipboardData {
+                    mime: "UTF8_STRING".to_string(),
+                    data: b"hi".to_vec(),
+                },
+            ]
+        )]
+    );
+}
+
+#[test]
+fn data_without_write_is_invalid() {
+    let clip = Arc::new(RichClip::default());
+    let (mut term, output) = clip_term(Some(Arc::clone(&clip)));
+    term.advance_bytes("\x1b]5522;type=wdata:mime=dGV4dC9wbGFpbg==:loc=primary;aGk=\x1b\\");
+    assert_eq!(
+        output.wait_for_text(),
+        "\x1b]5522;type=write:status=EINVAL:loc=primary\x1b\\"
+    );
+    assert!(clip.data.lock().unwrap().is_empty());
+}
+
+#[test]
+fn read_is_delegated_to_the_clipboard() {
+    let clip = Arc::new(RichClip::default());
+    let (mut term, _output) = clip_term(Some(Arc::clone(&clip)));
+    // "image/png text/plain" from the primary selection
+    term.advance_bytes("\x1b]5522;type=read:loc=primary:id=r;aW1hZ2UvcG5nIHRleHQvcGxhaW4=\x1b\\");
+    assert_eq!(
+        clip.reads.lock().unwrap().clone(),
+        vec![ClipboardReadRequest {
+            selection: ClipboardSelection::PrimarySelection,
+            mime_types: vec!["image/png".to_string(), "text/plain".to_string()],
+            id: Some("r".to_string()),
+        }]
+    );
+}
+
+#[test]
+fn read_without_clipboard_is_not_supported() {
+    let (mut term, output) = clip_term(None);
+    term.advance_bytes("\x1b]5522;type=read;dGV4dC9wbGFpbg==\x1b\\");
+    assert_eq!(
+        output.wait_for_text(),
+        "\x1b]5522;type=read:status=ENOSYS\x1b\\"
+    );
+}
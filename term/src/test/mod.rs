@@ -5,7 +5,14 @@ use super::*;
 mod c0;
 use bitflags::bitflags;
 mod c1;
+mod command_blocks;
 mod csi;
+mod file_transfer;
+mod kitty_clipboard;
+mod notification;
+#[cfg(feature = "disk_scrollback")]
+mod spill;
+mod text_sizing;
 // mod selection; FIXME: port to render layer
 use crate::color::ColorPalette;
 use k9::assert_equal as assert_eq;
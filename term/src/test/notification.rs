@@ -0,0 +1,170 @@
+//! Testing the kitty desktop notification protocol, OSC 99
+
+use super::*;
+use std::time::{Duration, Instant};
+
+#[derive(Clone, Default)]
+struct Recorder {
+    alerts: Arc<Mutex<Vec<Alert>>>,
+}
+
+impl AlertHandler for Recorder {
+    fn alert(&mut self, alert: Alert) {
+        self.alerts.lock().unwrap().push(alert);
+    }
+}
+
+#[derive(Clone, Default)]
+struct Output {
+    data: Arc<Mutex<Vec<u8>>>,
+}
+
+impl std::io::Write for Output {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        self.data.lock().unwrap().extend_from_slice(buf);
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        Ok(())
+    }
+}
+
+impl Output {
+    /// Responses are written from another thread, so wait a little
+    /// while for them to show up
+    fn wait_for_text(&self) -> String {
+        let deadline = Instant::now() + Duration::from_secs(5);
+        loop {
+            let data = self.data.lock().unwrap().clone();
+            if !data.is_empty() || Instant::now() > deadline {
+                return String::from_utf8(data).unwrap();
+            }
+            std::thread::sleep(Duration::from_millis(10));
+        }
+    }
+}
+
+fn notify_term() -> (Terminal, Recorder, Output) {
+    let output = Output::default();
+    let mut term = Terminal::new(
+        TerminalSize::default(),
+        Arc::new(TestTermConfig { scrollback: 0 }),
+        "Shelldone",
+        "O_o",
+        Box::new(output.clone()),
+    );
+    let recorder = Recorder::default();
+    term.set_notification_handler(Box::new(recorder.clone()));
+    (term, recorder, output)
+}
+
+fn alerts(recorder: &Recorder) -> Vec<Alert> {
+    std::mem::take(&mut *recorder.alerts.lock().unwrap())
+}
+
+#[test]
+fn chunked_notification() {
+    let (mut term, recorder, _output) = notify_term();
+    term.advance_bytes("\x1b]99;i=build:d=0:u=2:o=unfocused;Build \x1b\\");
+    term.advance_bytes("\x1b]99;i=build:d=0;finished\x1b\\");
+    assert_eq!(alerts(&recorder), vec![]);
+
+    term.advance_bytes("\x1b]99;i=build:p=body:a=report:c=1:w=3000;YWxsIHRlc3RzIHBhc3NlZA==\x1b\\");
+    // The body above was not sent with e=1, so it is taken literally
+    assert_eq!(
+        alerts(&recorder),
+        vec![Alert::ToastNotification {
+            title: Some("Build finished".to_string()),
+            body: "YWxsIHRlc3RzIHBhc3NlZA==".to_string(),
+            focus: true,
+            id: Some("build".to_string()),
+            urgency: NotificationUrgency::Critical,
+            occasion: NotificationOccasion::Unfocused,
+            report_activation: true,
+            report_close: true,
+            timeout: Some(Duration::from_millis(3000)),
+        }]
+    );
+
+    term.advance_bytes("\x1b]99;e=1:a=-focus,report;ZG9uZQ==\x1b\\");
+    assert_eq!(
+        alerts(&recorder),
+        vec![Alert::ToastNotification {
+            title: Some("done".to_string()),
+            body: String::new(),
+            focus: false,
+            id: None,
+            urgency: NotificationUrgency::Normal,
+            occasion: NotificationOccasion::Always,
+            // There is no id with which to report activation
+            report_activation: false,
+            report_close: false,
+            timeout: None,
+        }]
+    );
+}
+
+#[test]
+fn close_notification() {
+    let (mut term, recorder, _output) = notify_term();
+    term.advance_bytes("\x1b]99;i=1:d=0;partial\x1b\\");
+    term.advance_bytes("\x1b]99;i=1:p=close;\x1b\\");
+    assert_eq!(
+        alerts(&recorder),
+        vec![Alert::CloseToastNotification {
+            id: "1".to_string()
+        }]
+    );
+
+    // The partial notification was discarded by the close
+    term.advance_bytes("\x1b]99;i=1:p=body;body only\x1b\\");
+    assert_eq!(
+        alerts(&recorder),
+        vec![Alert::ToastNotification {
+            title: None,
+            body: "body only".to_string(),
+            focus: true,
+            id: Some("1".to_string()),
+            urgency: NotificationUrgency::Normal,
+            occasion: NotificationOccasion::Always,
+            report_activation: false,
+            report_close: false,
+            timeout: None,
+        }]
+    );
+}
+
+#[test]
+fn incomplete_notifications_are_bounded() {
+    let (mut term, recorder, _output) = notify_term();
+    // One more than the number of incomplete notifications that are kept
+    for i in 0..33 {
+        term.advance_bytes(format!("\x1b]99;i={i}:d=0;title {i}\x1b\\"));
+    }
+    assert_eq!(alerts(&recorder), vec![]);
+
+    // The oldest one was discarded to make room for the newest
+    term.advance_bytes("\x1b]99;i=0:p=body;body\x1b\\");
+    term.advance_bytes("\x1b]99;i=1:p=body;body\x1b\\");
+    let titles: Vec<Option<String>> = alerts(&recorder)
+        .into_iter()
+        .map(|alert| match alert {
+            Alert::ToastNotification { title, .. } => title,
+            alert => panic!("unexpected {alert:?}"),
+        })
+        .collect();
+    assert_eq!(titles, vec![None, Some("title 1".to_string())]);
+}
+
+#[test]
+fn query_capabilities() {
+    let (mut term, recorder, output) = notify_term();
+    term.advance_bytes("\x1b]99;i=q:p=?;\x1b\\");
+    assert_eq!(alerts(&recorder), vec![]);
+    assert_eq!(
+        output.wait_for_text(),
+        "\x1b]99;i=q:p=?;a=focus,report:c=1:o=always,unfocused,invisible:\
+         p=title,body,?,close:u=0,1,2:w=1\x1b\\"
+    );
+}
@@ -0,0 +1,152 @@
+//! Testing scrollback that spills to disk
+
+use super::*;
+use std::path::PathBuf;
+
+#[derive(Debug)]
+struct SpillTermConfig {
+    dir: PathBuf,
+}
+
+impl TerminalConfiguration for SpillTermConfig {
+    fn scrollback_size(&self) -> usize {
+        5
+    }
+
+    fn scrollback_spill_size(&self) -> usize {
+        1000
+    }
+
+    fn scrollback_spill_dir(&self) -> PathBuf {
+        self.dir.clone()
+    }
+
+    fn color_palette(&self) -> ColorPalette {
+        ColorPalette::default()
+    }
+}
+
+fn spill_term(dir: &tempfile::TempDir) -> Terminal {
+    Terminal::new(
+        TerminalSize {
+            rows: 3,
+            cols: 10,
+            pixel_width: 80,
+            pixel_height: 48,
+            dpi: 0,
+        },
+        Arc::new(SpillTermConfig {
+            dir: dir.path().to_path_buf(),
+        }),
+        "Shelldone",
+        "O_o",
+        Box::new(Vec::new()),
+    )
+}
+
+fn texts(lines: &[Line]) -> Vec<String> {
+    lines
+        .iter()
+        .map(|line| line.as_str().trim_end().to_string())
+        .collect()
+}
+
+#[test]
+fn spilled_lines_stay_reachable_by_stable_index() {
+    let dir = tempfile::tempdir().unwrap();
+    let mut term = spill_term(&dir);
+    for idx in 0..50 {
+        term.advance_bytes(format!("line {idx}\r\n"));
+    }
+
+    let screen = term.screen();
+    assert_eq!(screen.scrollback_top(), 0);
+    assert_eq!(
+        screen.scrollback_rows() as StableRowIndex,
+        screen.visible_row_to_stable_row(2) + 1
+    );
+
+    let (first, lines) = screen.lines_in_stable_range(&(0..3));
+    assert_eq!(first, 0);
+    assert_eq!(texts(&lines), vec!["line 0", "line 1", "line 2"]);
+
+    // Spans the boundary between the disk and memory tiers
+    let (first, lines) = screen.lines_in_stable_range(&(40..46));
+    assert_eq!(first, 40);
+    assert_eq!(
+        texts(&lines),
+        (40..46)
+            .map(|idx| format!("line {idx}"))
+            .collect::<Vec<_>>()
+    );
+
+    let mut found = vec![];
+    screen.for_each_logical_line_in_stable_range(0..50, |range, lines| {
+        if lines[0].as_str().starts_with("line 1") {
+            found.push(range.start);
+        }
+        true
+    });
+    assert_eq!(found, vec![1, 10, 11, 12, 13, 14, 15, 16, 17, 18, 19]);
+}
+
+#[test]
+fn erasing_scrollback_drops_spilled_lines() {
+    let dir = tempfile::tempdir().unwrap();
+    let mut term = spill_term(&dir);
+    for idx in 0..50 {
+        term.advance_bytes(format!("line {idx}\r\n"));
+    }
+    // ED 3: erase saved lines
+    term.advance_bytes("\x1b[3J");
+
+    let screen = term.screen();
+    assert_eq!(screen.scrollback_top(), screen.visible_row_to_stable_row(0));
+    assert_eq!(screen.scrollback_rows(), 3);
+}
+
+#[test]
+fn logical_lines_are_joined_across_the_disk_boundary() {
+    let dir = tempfile::tempdir().unwrap();
+    let mut term = spill_term(&dir);
+    for idx in 0..20 {
+        term.advance_bytes(format!("line {idx}\r\n"));
+    }
+    // Wraps over rows 20..30, of which only the last two are in memory
+    term.advance_bytes("x".repeat(100));
+    term.advance_bytes("\r\n");
+    for idx in 0..5 {
+        term.advance_bytes(format!("tail {idx}\r\n"));
+    }
+
+    let screen = term.screen();
+    for range in [0..40, 0..25] {
+        let mut found = vec![];
+        screen.for_each_logical_line_in_stable_range(range, |range, lines| {
+            if lines[0].as_str().starts_with('x') {
+                let text: String = lines.iter().map(|line| line.as_str()).collect();
+                found.push((range, text.len()));
+            }
+            true
+        });
+        assert_eq!(found, vec![(20..30, 100)]);
+    }
+}
+
+#[test]
+fn changes_to_spilled_lines_are_kept() {
+    let dir = tempfile::tempdir().unwrap();
+    let mut term = spill_term(&dir);
+    for idx in 0..50 {
+        term.advance_bytes(format!("line {idx}\r\n"));
+    }
+
+    let screen = term.screen_mut();
+    screen.with_stable_lines_mut(&(2..3), |first, lines| {
+        assert_eq!(first, 2);
+        lines[0].set_cell(0, Cell::new('L', CellAttributes::default()), 1);
+    });
+
+    let (_, lines) = screen.lines_in_stable_range(&(2..3));
+    assert_eq!(texts(&lines), vec!["Line 2"]);
+}
@@ -0,0 +1,83 @@
+//! Testing the kitty text sizing protocol, OSC 66
+
+use super::*;
+
+/// Describes the cells of each visible row that are part of
+/// scaled text, as `column:text/width@row`
+fn sized_cells(term: &TestTerm) -> Vec<String> {
+    let screen = term.screen();
+    screen
+        .visible_lines()
+        .iter()
+        .map(|line| {
+            line.visible_cells()
+                .filter_map(|cell| {
+                    let size = cell.attrs().text_size()?;
+                    Some(format!(
+                        "{}:{}/{}@{}",
+                        cell.cell_index(),
+                        cell.str(),
+                        cell.width(),
+                        size.row
+                    ))
+                })
+                .collect::<Vec<_>>()
+                .join(" ")
+        })
+        .collect()
+}
+
+#[test]
+fn double_size_graphemes() {
+    let mut term = TestTerm::new(3, 8, 0);
+    term.print("a\x1b]66;s=2;Hi\x1b\\b");
+
+    assert_eq!(
+        sized_cells(&term),
+        vec!["1:H/2@0 3:i/2@0", "1: /2@1 3: /2@1", ""]
+    );
+    assert_eq!(term.screen().visible_lines()[0].as_str(), "aHib");
+    term.assert_cursor_pos(6, 0, None, None);
+}
+
+#[test]
+fn fixed_width_block_with_fractional_scale() {
+    let mut term = TestTerm::new(3, 8, 0);
+    term.print("\x1b]66;s=2:w=3:n=1:d=2;Big\x1b\\");
+
+    assert_eq!(sized_cells(&term), vec!["0:Big/6@0", "0: /6@1", ""]);
+
+    let screen = term.screen();
+    let lines = screen.visible_lines();
+    let size = lines[0]
+        .get_cell(0)
+        .unwrap()
+        .attrs()
+        .text_size()
+        .unwrap()
+        .size;
+    assert_eq!(size.scale, 2);
+    assert_eq!(size.font_scale(), 1.0);
+    term.assert_cursor_pos(6, 0, None, None);
+}
+
+#[test]
+fn scaled_text_scrolls_to_make_room() {
+    let mut term = TestTerm::new(3, 8, 0);
+    term.print("one\r\ntwo\r\n\x1b]66;s=3;X\x1b\\");
+
+    // The block needs all three rows, so the earlier output
+    // scrolls away and the block starts in the top row
+    assert_eq!(sized_cells(&term), vec!["0:X/3@0", "0: /3@1", "0: /3@2"]);
+    term.assert_cursor_pos(3, 0, None, None);
+}
+
+#[test]
+fn scaled_text_wraps_to_the_next_line() {
+    let mut term = TestTerm::new(4, 8, 0);
+    term.print("abcdefg\x1b]66;s=2;W\x1b\\");
+
+    assert_eq!(sized_cells(&term), vec!["", "0:W/2@0", "0: /2@1", ""]);
+    assert!(term.screen().visible_lines()[0].last_cell_was_wrapped());
+    term.assert_cursor_pos(2, 1, None, None);
+}
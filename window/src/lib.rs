@@ -46,6 +46,36 @@ pub enum Clipboard {
     PrimarySelection,
 }
 
+/// The clipboard contents for a single MIME type
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ClipboardData {
+    pub mime: String,
+    pub data: Vec<u8>,
+}
+
+/// Adapts a textual clipboard transfer to the form returned
+/// by `WindowOps::get_clipboard_data`
+pub(crate) fn text_clipboard_data(text: Future<String>) -> Future<Vec<u8>> {
+    let mut promise = promise::Promise::new();
+    let future = promise.get_future().unwrap();
+    promise::spawn::spawn(async move {
+        promise.result(text.await.map(String::into_bytes));
+    })
+    .detach();
+    future
+}
+
+/// Returns true if `mime` names plain text, using either its
+/// MIME type or one of the X11 names for it
+pub fn is_text_mime(mime: &str) -> bool {
+    let mime = mime.to_ascii_lowercase();
+    mime == "text/plain"
+        || mime.starts_with("text/plain;")
+        || mime == "utf8_string"
+        || mime == "string"
+        || mime == "text"
+}
+
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub struct Dimensions {
     pub pixel_width: usize,
@@ -317,6 +347,34 @@ pub trait WindowOps {
     /// Set some text in the clipboard
     fn set_clipboard(&self, clipboard: Clipboard, text: String);
 
+    /// Place data for a number of MIME types in the clipboard.
+    /// Backends that can only hold text keep just the plain text.
+    fn set_clipboard_data(&self, clipboard: Clipboard, data: Vec<ClipboardData>) {
+        match data.into_iter().find(|d| is_text_mime(&d.mime)) {
+            Some(text) => {
+                self.set_clipboard(clipboard, String::from_utf8_lossy(&text.data).into_owned())
+            }
+            None => log::warn!("set_clipboard_data: only text is supported by this backend"),
+        }
+    }
+
+    /// Initiate transfer of the clipboard contents for `mime`.
+    /// Backends that can only hold text support just the plain text.
+    fn get_clipboard_data(&self, clipboard: Clipboard, mime: String) -> Future<Vec<u8>> {
+        if !is_text_mime(&mime) {
+            return Future::err(anyhow::anyhow!(
+                "{mime} is not supported by this clipboard backend"
+            ));
+        }
+        text_clipboard_data(self.get_clipboard(clipboard))
+    }
+
+    /// Initiate transfer of the list of MIME types that are
+    /// available in the clipboard
+    fn get_clipboard_mime_types(&self, _clipboard: Clipboard) -> Future<Vec<String>> {
+        Future::ok(vec!["text/plain".to_string()])
+    }
+
     /// Set window level. Depending on the environment and user preferences
     fn set_window_level(&self, _level: WindowLevel) {}
 
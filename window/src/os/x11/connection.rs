@@ -969,6 +969,13 @@ impl XConnection {
         name
     }
 
+    /// Returns the atom for `name`, creating it if necessary
+    pub fn intern_atom_by_name(&self, name: &str) -> anyhow::Result<Atom> {
+        let atom = Self::intern_atom(&self.conn, name)?;
+        self.atom_names.borrow_mut().insert(atom, name.to_string());
+        Ok(atom)
+    }
+
     pub fn conn(&self) -> &xcb::Connection {
         &self.conn
     }
@@ -3,10 +3,10 @@ use crate::bitmaps::*;
 use crate::connection::ConnectionOps;
 use crate::os::{xkeysyms, Connection, Window};
 use crate::{
-    Appearance, Clipboard, DeadKeyStatus, Dimensions, MouseButtons, MouseCursor, MouseEvent,
-    MouseEventKind, MousePress, Point, Rect, RequestedWindowGeometry, ResizeIncrement,
-    ResolvedGeometry, ScreenPoint, ScreenRect, WindowDecorations, WindowEvent, WindowEventSender,
-    WindowOps, WindowState,
+    is_text_mime, Appearance, Clipboard, ClipboardData, DeadKeyStatus, Dimensions, MouseButtons,
+    MouseCursor, MouseEvent, MouseEventKind, MousePress, Point, Rect, RequestedWindowGeometry,
+    ResizeIncrement, ResolvedGeometry, ScreenPoint, ScreenRect, WindowDecorations, WindowEvent,
+    WindowEventSender, WindowOps, WindowState,
 };
 use anyhow::{anyhow, Context as _};
 use async_trait::async_trait;
@@ -29,12 +29,46 @@ use url::Url;
 use xcb::x::{Atom, PropMode};
 use xcb::{Event, Xid};
 
+/// A pending request for the clipboard contents in a format
+/// other than text
+enum DataRequest {
+    /// The data for a MIME type
+    Data {
+        clipboard: Clipboard,
+        target: Atom,
+        promise: Promise<Vec<u8>>,
+    },
+    /// The list of MIME types that are available
+    Targets {
+        clipboard: Clipboard,
+        promise: Promise<Vec<String>>,
+    },
+}
+
+impl DataRequest {
+    fn matches(&self, clipboard: Clipboard, target: Atom, atom_targets: Atom) -> bool {
+        match self {
+            Self::Data {
+                clipboard: c,
+                target: t,
+                ..
+            } => *c == clipboard && *t == target,
+            Self::Targets { clipboard: c, .. } => *c == clipboard && target == atom_targets,
+        }
+    }
+}
+
 #[derive(Default)]
 struct CopyAndPaste {
     clipboard_owned: Option<String>,
     primary_selection_owned: Option<String>,
+    /// Non-textual data that we hold for the clipboard,
+    /// along with any text in `clipboard_owned`
+    clipboard_data: Vec<ClipboardData>,
+    primary_selection_data: Vec<ClipboardData>,
     clipboard_request: Option<Promise<String>>,
     selection_request: Option<Promise<String>>,
+    data_requests: Vec<DataRequest>,
     time: u32,
 }
 
@@ -53,6 +87,37 @@ impl CopyAndPaste {
         }
     }
 
+    fn data(&self, clipboard: Clipboard) -> &Vec<ClipboardData> {
+        match clipboard {
+            Clipboard::PrimarySelection => &self.primary_selection_data,
+            Clipboard::Clipboard => &self.clipboard_data,
+        }
+    }
+
+    fn data_mut(&mut self, clipboard: Clipboard) -> &mut Vec<ClipboardData> {
+        match clipboard {
+            Clipboard::PrimarySelection => &mut self.primary_selection_data,
+            Clipboard::Clipboard => &mut self.clipboard_data,
+        }
+    }
+
+    fn owns(&self, clipboard: Clipboard) -> bool {
+        self.clipboard(clipboard).is_some() || !self.data(clipboard).is_empty()
+    }
+
+    fn take_data_request(
+        &mut self,
+        clipboard: Clipboard,
+        target: Atom,
+        atom_targets: Atom,
+    ) -> Option<DataRequest> {
+        let idx = self
+            .data_requests
+            .iter()
+            .position(|req| req.matches(clipboard, target, atom_targets))?;
+        Some(self.data_requests.remove(idx))
+    }
+
     fn request_mut(&mut self, clipboard: Clipboard) -> &mut Option<Promise<String>> {
         match clipboard {
             Clipboard::PrimarySelection => &mut self.selection_request,
@@ -907,7 +972,7 @@ impl XWindowInner {
             .unwrap()
             .owner();
 
-        let we_own_it = self.copy_and_paste.clipboard(clipboard).is_some();
+        let we_own_it = self.copy_and_paste.owns(clipboard);
 
         if !we_own_it && current_owner == window_id {
             log::trace!(
@@ -941,6 +1006,21 @@ impl XWindowInner {
         Ok(())
     }
 
+    /// Ask the owner of the selection to send it to us as `target`
+    fn convert_selection(&self, clipboard: Clipboard, target: Atom) {
+        let conn = self.conn();
+        conn.send_request_no_reply_log(&xcb::x::ConvertSelection {
+            requestor: self.window_id,
+            selection: match clipboard {
+                Clipboard::Clipboard => conn.atom_clipboard,
+                Clipboard::PrimarySelection => xcb::x::ATOM_PRIMARY,
+            },
+            target,
+            property: conn.atom_xsel_data,
+            time: self.copy_and_paste.time,
+        });
+    }
+
     fn selection_atom_to_clipboard(&self, atom: Atom) -> Option<Clipboard> {
         if atom == xcb::x::ATOM_PRIMARY {
             Some(Clipboard::PrimarySelection)
@@ -956,6 +1036,7 @@ impl XWindowInner {
         log::debug!("SEL: window_id={window_id:?} {:?}", request);
         if let Some(clipboard) = self.selection_atom_to_clipboard(request.selection()) {
             self.copy_and_paste.clipboard_mut(clipboard).take();
+            self.copy_and_paste.data_mut(clipboard).clear();
             self.copy_and_paste.request_mut(clipboard).take();
             self.update_selection_owner(clipboard)?;
         }
@@ -979,18 +1060,50 @@ impl XWindowInner {
 
         let selprop = if request.target() == conn.atom_targets {
             // They want to know which targets we support
-            let atoms: [Atom; 1] = [conn.atom_utf8_string];
+            let mut atoms: Vec<Atom> = vec![conn.atom_targets];
+            if let Some(clipboard) = self.selection_atom_to_clipboard(request.selection()) {
+                if self.copy_and_paste.clipboard(clipboard).is_some() {
+                    atoms.push(conn.atom_utf8_string);
+                }
+                for data in self.copy_and_paste.data(clipboard) {
+                    atoms.push(conn.intern_atom_by_name(&data.mime)?);
+                }
+            }
             log::trace!("SEL: window_id={window_id:?} requestor wants supported targets");
             conn.send_request_no_reply(&xcb::x::ChangeProperty {
                 mode: PropMode::Replace,
                 window: request.requestor(),
                 property: request.property(),
                 r#type: xcb::x::ATOM_ATOM,
-                data: &atoms,
+                data: &atoms[..],
             })?;
 
             // let the requestor know that we set their property
             request.property()
+        } else if let Some(data) = self
+            .selection_atom_to_clipboard(request.selection())
+            .and_then(|clipboard| {
+                let target = conn.atom_name(request.target());
+                self.copy_and_paste
+                    .data(clipboard)
+                    .iter()
+                    .find(|data| data.mime == target)
+            })
+        {
+            log::trace!(
+                "SEL: window_id={window_id:?} requestor wants {} data",
+                data.mime
+            );
+            // Large transfers would need the INCR protocol, which
+            // we don't implement; the data is sent in one piece.
+            conn.send_request_no_reply(&xcb::x::ChangeProperty {
+                mode: PropMode::Replace,
+                window: request.requestor(),
+                property: request.property(),
+                r#type: request.target(),
+                data: &data.data[..],
+            })?;
+            request.property()
         } else if request.target() == conn.atom_utf8_string
             || request.target() == xcb::x::ATOM_STRING
         {
@@ -1054,6 +1167,53 @@ impl XWindowInner {
             selection.selection={selection_name} selection.target={target_name}"
         );
 
+        let data_request = self
+            .selection_atom_to_clipboard(selection.selection())
+            .and_then(|clipboard| {
+                self.copy_and_paste.take_data_request(
+                    clipboard,
+                    selection.target(),
+                    conn.atom_targets,
+                )
+            });
+        if let Some(request) = data_request {
+            let value = if selection.property() == xcb::x::ATOM_NONE {
+                Err(anyhow!(
+                    "{target_name} is not available in {selection_name}"
+                ))
+            } else {
+                let value = conn
+                    .send_and_wait_request(&xcb::x::GetProperty {
+                        delete: false,
+                        window: selection.requestor(),
+                        property: selection.property(),
+                        r#type: xcb::x::ATOM_ANY,
+                        long_offset: 0,
+                        long_length: u32::MAX,
+                    })
+                    .map_err(|err| anyhow!("getting {target_name} from {selection_name}: {err:#}"));
+                conn.send_request_no_reply_log(&xcb::x::DeleteProperty {
+                    window: self.window_id,
+                    property: conn.atom_xsel_data,
+                });
+                value
+            };
+            match request {
+                DataRequest::Data { mut promise, .. } => {
+                    promise.result(value.map(|prop| prop.value::<u8>().to_vec()));
+                }
+                DataRequest::Targets { mut promise, .. } => {
+                    promise.result(value.map(|prop| {
+                        prop.value::<Atom>()
+                            .iter()
+                            .map(|&atom| conn.atom_name(atom))
+                            .collect()
+                    }));
+                }
+            }
+            return Ok(());
+        }
+
         if let Some(clipboard) = self.selection_atom_to_clipboard(selection.selection()) {
             if selection.property() == xcb::x::ATOM_NONE {
                 if selection.target() == conn.atom_utf8_string {
@@ -2155,10 +2315,78 @@ impl WindowOps for XWindow {
                 .copy_and_paste
                 .clipboard_mut(clipboard)
                 .replace(text.clone());
+            inner.copy_and_paste.data_mut(clipboard).clear();
             inner.update_selection_owner(clipboard)?;
             Ok(())
         });
     }
+
+    fn set_clipboard_data(&self, clipboard: Clipboard, data: Vec<ClipboardData>) {
+        let window_id = self.0;
+        XConnection::with_window_inner(window_id, move |inner| {
+            let (text, data): (Vec<_>, Vec<_>) =
+                data.into_iter().partition(|d| is_text_mime(&d.mime));
+            log::trace!(
+                "SEL: window_id={window_id:?} now owns selection for {clipboard:?} \
+                 with {} text and {} other formats",
+                text.len(),
+                data.len()
+            );
+            *inner.copy_and_paste.clipboard_mut(clipboard) = text
+                .into_iter()
+                .next()
+                .map(|text| String::from_utf8_lossy(&text.data).into_owned());
+            *inner.copy_and_paste.data_mut(clipboard) = data;
+            inner.update_selection_owner(clipboard)?;
+            Ok(())
+        });
+    }
+
+    fn get_clipboard_data(&self, clipboard: Clipboard, mime: String) -> Future<Vec<u8>> {
+        if is_text_mime(&mime) {
+            return crate::text_clipboard_data(self.get_clipboard(clipboard));
+        }
+        let window_id = self.0;
+        let mut promise = Promise::new();
+        let future = promise.get_future().unwrap();
+        let mut promise = Some(promise);
+
+        XConnection::with_window_inner(window_id, move |inner| {
+            let conn = inner.conn();
+            let target = conn.intern_atom_by_name(&mime)?;
+            inner.copy_and_paste.data_requests.push(DataRequest::Data {
+                clipboard,
+                target,
+                promise: promise.take().unwrap(),
+            });
+            inner.convert_selection(clipboard, target);
+            Ok(())
+        });
+
+        future
+    }
+
+    fn get_clipboard_mime_types(&self, clipboard: Clipboard) -> Future<Vec<String>> {
+        let window_id = self.0;
+        let mut promise = Promise::new();
+        let future = promise.get_future().unwrap();
+        let mut promise = Some(promise);
+
+        XConnection::with_window_inner(window_id, move |inner| {
+            inner
+                .copy_and_paste
+                .data_requests
+                .push(DataRequest::Targets {
+                    clipboard,
+                    promise: promise.take().unwrap(),
+                });
+            let target = inner.conn().atom_targets;
+            inner.convert_selection(clipboard, target);
+            Ok(())
+        });
+
+        future
+    }
 }
 
 fn parse_texturi_list(url_list: &[u8]) -> Vec<PathBuf> {